@@ -1,8 +1,16 @@
 #!/bin/sh
 set -ex
 MODE=${1:---release}
-wasm-pack build --no-typescript --no-pack --out-dir wasm_build $MODE --target web
+FEATURES_ARGS=${FEATURES:+-- --features $FEATURES}
+wasm-pack build --no-typescript --no-pack --out-dir wasm_build $MODE --target web $FEATURES_ARGS
+wasm-pack build --no-typescript --no-pack --out-dir wasm_build_no_modules $MODE --target no-modules $FEATURES_ARGS
 mv ./wasm_build/stremio_core_web_bg.wasm stremio_core_web_bg.wasm
+mv ./wasm_build_no_modules/stremio_core_web_bg.wasm stremio_core_web_no_modules_bg.wasm
+# the no-modules glue only declares a global, export it so it can be bundled as the ESM glue
+echo 'module.exports = wasm_bindgen;' >> wasm_build_no_modules/stremio_core_web.js
 npx babel wasm_build/stremio_core_web.js --config-file ./.babelrc --out-file stremio_core_web.js
+npx babel wasm_build_no_modules/stremio_core_web.js --config-file ./.babelrc --out-file stremio_core_web_no_modules.js
 npx babel src/bridge.js --config-file ./.babelrc --out-file bridge.js
+npx babel src/worker_common.js --config-file ./.babelrc --out-file worker_common.js
 npx babel src/worker.js --config-file ./.babelrc --out-file worker.js
+npx babel src/worker_no_modules.js --config-file ./.babelrc --out-file worker_no_modules.js
@@ -0,0 +1,512 @@
+use std::sync::RwLock;
+
+use chrono::{DateTime, Duration, Utc};
+use futures::{future, FutureExt, TryFutureExt};
+use http::Request;
+use lazy_static::lazy_static;
+use serde::{de::DeserializeOwned, Deserialize, Serialize};
+use url::Url;
+
+use stremio_core::{
+    constants::API_URL,
+    models::common::Loadable,
+    runtime::{Env, EnvError, TryEnvFuture},
+    types::{
+        api::{APIResult, SuccessResponse},
+        profile::{AuthKey, User},
+    },
+};
+
+use crate::env::WebEnv;
+
+pub const PROFILE_DISPLAY_STORAGE_KEY: &str = "profile_display";
+const AVATARS_URL: &str = "https://web.stremio.com/images/avatars/";
+pub const AVATAR_PRESETS: [&str; 8] = [
+    "default", "cat", "dog", "fox", "owl", "panda", "penguin", "robot",
+];
+const MAX_DISPLAY_NAME_LENGTH: usize = 32;
+/// Seconds before an email of the same flow can be sent again
+const EMAIL_RESEND_COOLDOWN: i64 = 60;
+
+lazy_static! {
+    static ref ACCOUNT: RwLock<Account> = Default::default();
+}
+
+#[derive(Clone, Default, PartialEq, Serialize, Deserialize, Debug)]
+#[serde(rename_all = "camelCase")]
+pub struct ProfileDisplay {
+    pub avatar: Option<Url>,
+    pub display_name: Option<String>,
+}
+
+/// The profile display confirmed by the API, kept on the device
+/// since the display name is not part of the user in core.
+#[derive(Clone, Serialize, Deserialize, Debug)]
+#[serde(rename_all = "camelCase")]
+pub struct SavedProfileDisplay {
+    pub user_id: String,
+    #[serde(flatten)]
+    pub display: ProfileDisplay,
+}
+
+/// Account state which is not part of the core profile
+#[derive(Clone, Default, Debug)]
+pub struct Account {
+    pub saved_display: Option<SavedProfileDisplay>,
+    /// Optimistic update of the profile display, shown while it is being saved
+    pub pending_display: Option<ProfileDisplay>,
+    /// Reason the last update was rejected for, the previous display is shown again
+    pub display_error: Option<String>,
+    /// Sessions of the user with the id, `None` until they are loaded
+    pub sessions: Option<(String, Loadable<Vec<Session>, String>)>,
+    /// Ids of the sessions which are being revoked
+    pub revoking_sessions: Vec<String>,
+    pub email_verification: Option<EmailFlow>,
+    pub password_reset: Option<EmailFlow>,
+}
+
+impl Account {
+    pub fn sessions(&self, user: &User) -> Option<&Loadable<Vec<Session>, String>> {
+        self.sessions
+            .as_ref()
+            .filter(|(user_id, _)| *user_id == user.id)
+            .map(|(_, sessions)| sessions)
+    }
+    /// The pending update over the saved display over the avatar of the core user
+    pub fn display(&self, user: &User) -> ProfileDisplay {
+        if let Some(pending_display) = &self.pending_display {
+            return pending_display.to_owned();
+        }
+        let saved_display = self
+            .saved_display
+            .as_ref()
+            .filter(|saved_display| saved_display.user_id == user.id)
+            .map(|saved_display| saved_display.display.to_owned())
+            .unwrap_or_default();
+        ProfileDisplay {
+            avatar: saved_display.avatar.or_else(|| {
+                user.avatar
+                    .as_ref()
+                    .and_then(|avatar| Url::parse(avatar).ok())
+            }),
+            display_name: saved_display.display_name,
+        }
+    }
+}
+
+#[derive(Deserialize, Debug)]
+#[serde(tag = "type", content = "value")]
+pub enum AvatarSelection {
+    /// One of the `AVATAR_PRESETS`
+    Preset(String),
+    /// An uploaded image, served over https
+    Url(Url),
+}
+
+#[derive(Deserialize, Debug)]
+#[serde(tag = "action", content = "args")]
+pub enum ProfileDisplayAction {
+    SetAvatar(AvatarSelection),
+    /// An empty name clears it
+    SetDisplayName(String),
+}
+
+/// A device the user is logged in on
+#[derive(Clone, Serialize, Deserialize, Debug)]
+#[serde(rename_all = "camelCase")]
+pub struct Session {
+    pub id: String,
+    pub device_name: Option<String>,
+    pub platform: Option<String>,
+    pub last_seen: DateTime<Utc>,
+    /// The session of this device, it is ended by logging out instead
+    #[serde(default)]
+    pub current: bool,
+}
+
+#[derive(Deserialize, Debug)]
+#[serde(tag = "action", content = "args")]
+pub enum SessionsAction {
+    Load,
+    /// Logs the device of the session out
+    Revoke(String),
+}
+
+/// Progress of a flow which sends an email to the user, e.g. the password reset
+#[derive(Clone, Serialize, Debug)]
+#[serde(tag = "type", content = "content")]
+pub enum EmailFlow {
+    #[serde(rename_all = "camelCase")]
+    Sending {
+        email: String,
+    },
+    #[serde(rename_all = "camelCase")]
+    Sent {
+        email: String,
+        /// The email can't be sent again before
+        resend_at: DateTime<Utc>,
+        /// Set when sending the email again was rejected as it is too soon
+        error: Option<EmailFlowError>,
+    },
+    Failed(EmailFlowError),
+}
+
+#[derive(Clone, Serialize, Debug)]
+#[serde(rename_all = "camelCase")]
+pub struct EmailFlowError {
+    pub code: EmailFlowErrorCode,
+    pub message: String,
+}
+
+#[derive(Clone, Copy, PartialEq, Eq, Serialize, Debug)]
+pub enum EmailFlowErrorCode {
+    InvalidEmail,
+    /// The email was sent recently
+    TooSoon,
+    /// The email verification is only available for a logged in user
+    NotLoggedIn,
+    Api,
+}
+
+impl EmailFlowError {
+    pub fn new(code: EmailFlowErrorCode, message: &str) -> Self {
+        Self {
+            code,
+            message: message.to_owned(),
+        }
+    }
+}
+
+#[derive(Deserialize, Debug)]
+#[serde(tag = "action", content = "args")]
+pub enum EmailFlowAction {
+    ResendVerification,
+    /// Sends the password reset link to the email
+    ResetPassword(String),
+    /// Clears the progress of both flows, e.g. when the login screen is left
+    Clear,
+}
+
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum EmailFlowKind {
+    EmailVerification,
+    PasswordReset,
+}
+
+#[derive(Serialize)]
+#[serde(rename_all = "camelCase")]
+struct SaveUserRequest {
+    auth_key: AuthKey,
+    avatar: Option<Url>,
+    display_name: Option<String>,
+}
+
+#[derive(Serialize)]
+#[serde(rename_all = "camelCase")]
+struct SessionsRequest {
+    auth_key: AuthKey,
+}
+
+#[derive(Serialize)]
+#[serde(rename_all = "camelCase")]
+struct RevokeSessionRequest {
+    auth_key: AuthKey,
+    session_id: String,
+}
+
+#[derive(Serialize)]
+#[serde(rename_all = "camelCase")]
+struct VerificationEmailRequest {
+    auth_key: AuthKey,
+}
+
+#[derive(Serialize)]
+#[serde(rename_all = "camelCase")]
+struct PasswordResetRequest {
+    email: String,
+}
+
+#[derive(Deserialize)]
+struct SessionsResponse {
+    sessions: Vec<Session>,
+}
+
+pub fn account() -> Account {
+    ACCOUNT.read().expect("account read failed").to_owned()
+}
+
+pub fn set_saved_display(saved_display: Option<SavedProfileDisplay>) {
+    ACCOUNT.write().expect("account write failed").saved_display = saved_display;
+}
+
+pub fn preset_avatar_url(preset: &str) -> Url {
+    Url::parse(&format!("{AVATARS_URL}{preset}.png")).expect("avatar url builder failed")
+}
+
+/// Applies the update optimistically and returns the display to be saved,
+/// `None` when another update is being saved or the update is not valid.
+pub fn start_update(action: ProfileDisplayAction, user: &User) -> Option<ProfileDisplay> {
+    let mut account = ACCOUNT.write().expect("account write failed");
+    if account.pending_display.is_some() {
+        return None;
+    }
+    let display = account.display(user);
+    let display = match action {
+        ProfileDisplayAction::SetAvatar(AvatarSelection::Preset(preset))
+            if AVATAR_PRESETS.contains(&preset.as_str()) =>
+        {
+            ProfileDisplay {
+                avatar: Some(preset_avatar_url(&preset)),
+                ..display
+            }
+        }
+        ProfileDisplayAction::SetAvatar(AvatarSelection::Preset(_)) => {
+            account.display_error = Some("Unknown avatar".to_owned());
+            return None;
+        }
+        ProfileDisplayAction::SetAvatar(AvatarSelection::Url(url)) if url.scheme() == "https" => {
+            ProfileDisplay {
+                avatar: Some(url),
+                ..display
+            }
+        }
+        ProfileDisplayAction::SetAvatar(AvatarSelection::Url(_)) => {
+            account.display_error = Some("Avatar must be served over https".to_owned());
+            return None;
+        }
+        ProfileDisplayAction::SetDisplayName(display_name) => {
+            let display_name = display_name.trim();
+            if display_name.chars().count() > MAX_DISPLAY_NAME_LENGTH {
+                account.display_error = Some(format!(
+                    "Display name must be at most {MAX_DISPLAY_NAME_LENGTH} characters"
+                ));
+                return None;
+            }
+            ProfileDisplay {
+                display_name: (!display_name.is_empty()).then(|| display_name.to_owned()),
+                ..display
+            }
+        }
+    };
+    account.pending_display = Some(display.to_owned());
+    account.display_error = None;
+    Some(display)
+}
+
+/// Keeps the saved display or rolls back to the previous one,
+/// returns the saved display to be persisted.
+pub fn finish_update(
+    user_id: String,
+    display: ProfileDisplay,
+    result: Result<(), String>,
+) -> Option<SavedProfileDisplay> {
+    let mut account = ACCOUNT.write().expect("account write failed");
+    account.pending_display = None;
+    match result {
+        Ok(()) => {
+            account.saved_display = Some(SavedProfileDisplay { user_id, display });
+            account.saved_display.to_owned()
+        }
+        Err(error) => {
+            account.display_error = Some(error);
+            None
+        }
+    }
+}
+
+pub fn start_loading_sessions(user_id: String) {
+    ACCOUNT.write().expect("account write failed").sessions = Some((user_id, Loadable::Loading));
+}
+
+pub fn set_sessions(user_id: String, sessions: Loadable<Vec<Session>, String>) {
+    ACCOUNT.write().expect("account write failed").sessions = Some((user_id, sessions));
+}
+
+/// Marks the session as being revoked, `false` when it can't be revoked
+pub fn start_revoking(session_id: &str) -> bool {
+    let mut account = ACCOUNT.write().expect("account write failed");
+    let is_revocable = match &account.sessions {
+        Some((_, Loadable::Ready(sessions))) => sessions
+            .iter()
+            .any(|session| session.id == session_id && !session.current),
+        _ => false,
+    };
+    if !is_revocable || account.revoking_sessions.iter().any(|id| id == session_id) {
+        return false;
+    }
+    account.revoking_sessions.push(session_id.to_owned());
+    true
+}
+
+/// Drops the session if it was revoked, the error of a failed revoke is not kept
+pub fn finish_revoking(session_id: &str, revoked: bool) {
+    let mut account = ACCOUNT.write().expect("account write failed");
+    account.revoking_sessions.retain(|id| id != session_id);
+    if let Some((_, Loadable::Ready(sessions))) = &mut account.sessions {
+        if revoked {
+            sessions.retain(|session| session.id != session_id);
+        }
+    }
+}
+
+fn email_flow(account: &mut Account, kind: EmailFlowKind) -> &mut Option<EmailFlow> {
+    match kind {
+        EmailFlowKind::EmailVerification => &mut account.email_verification,
+        EmailFlowKind::PasswordReset => &mut account.password_reset,
+    }
+}
+
+/// Marks the email as being sent, `false` when it is not sent because of an error
+/// which is set as the progress of the flow or it is already being sent.
+/// Sending again too soon keeps the flow sent so its `resend_at` is not lost.
+pub fn start_sending(kind: EmailFlowKind, email: &str, now: DateTime<Utc>) -> bool {
+    let mut account = ACCOUNT.write().expect("account write failed");
+    let flow = email_flow(&mut account, kind);
+    let error = match flow {
+        Some(EmailFlow::Sending { .. }) => return false,
+        Some(EmailFlow::Sent {
+            email: sent_email,
+            resend_at,
+            error,
+        }) if sent_email == email && *resend_at > now => {
+            *error = Some(EmailFlowError::new(
+                EmailFlowErrorCode::TooSoon,
+                "The email was sent recently, check your inbox",
+            ));
+            return false;
+        }
+        _ if !is_valid_email(email) => Some(EmailFlowError::new(
+            EmailFlowErrorCode::InvalidEmail,
+            "Invalid email",
+        )),
+        _ => None,
+    };
+    *flow = Some(match &error {
+        Some(error) => EmailFlow::Failed(error.to_owned()),
+        None => EmailFlow::Sending {
+            email: email.to_owned(),
+        },
+    });
+    error.is_none()
+}
+
+pub fn finish_sending(
+    kind: EmailFlowKind,
+    email: String,
+    result: Result<(), String>,
+    now: DateTime<Utc>,
+) {
+    let mut account = ACCOUNT.write().expect("account write failed");
+    *email_flow(&mut account, kind) = Some(match result {
+        Ok(()) => EmailFlow::Sent {
+            email,
+            resend_at: now + Duration::seconds(EMAIL_RESEND_COOLDOWN),
+            error: None,
+        },
+        Err(error) => EmailFlow::Failed(EmailFlowError::new(EmailFlowErrorCode::Api, &error)),
+    });
+}
+
+pub fn set_email_flow_error(kind: EmailFlowKind, error: EmailFlowError) {
+    let mut account = ACCOUNT.write().expect("account write failed");
+    *email_flow(&mut account, kind) = Some(EmailFlow::Failed(error));
+}
+
+pub fn clear_email_flows() {
+    let mut account = ACCOUNT.write().expect("account write failed");
+    account.email_verification = None;
+    account.password_reset = None;
+}
+
+fn is_valid_email(email: &str) -> bool {
+    match email.split_once('@') {
+        Some((local, domain)) => {
+            !local.is_empty() && domain.contains('.') && !domain.starts_with('.')
+        }
+        None => false,
+    }
+}
+
+pub fn save_display(auth_key: &AuthKey, display: &ProfileDisplay) -> TryEnvFuture<()> {
+    fetch_api::<_, SuccessResponse>(
+        "saveUser",
+        SaveUserRequest {
+            auth_key: auth_key.to_owned(),
+            avatar: display.avatar.to_owned(),
+            display_name: display.display_name.to_owned(),
+        },
+    )
+    .map_ok(|_| ())
+    .boxed_local()
+}
+
+pub fn fetch_sessions(auth_key: &AuthKey) -> TryEnvFuture<Vec<Session>> {
+    fetch_api::<_, SessionsResponse>(
+        "getSessions",
+        SessionsRequest {
+            auth_key: auth_key.to_owned(),
+        },
+    )
+    .map_ok(|response| response.sessions)
+    .boxed_local()
+}
+
+pub fn revoke_session(auth_key: &AuthKey, session_id: &str) -> TryEnvFuture<()> {
+    fetch_api::<_, SuccessResponse>(
+        "revokeSession",
+        RevokeSessionRequest {
+            auth_key: auth_key.to_owned(),
+            session_id: session_id.to_owned(),
+        },
+    )
+    .map_ok(|_| ())
+    .boxed_local()
+}
+
+pub fn send_verification_email(auth_key: &AuthKey) -> TryEnvFuture<()> {
+    fetch_api::<_, SuccessResponse>(
+        "sendVerificationEmail",
+        VerificationEmailRequest {
+            auth_key: auth_key.to_owned(),
+        },
+    )
+    .map_ok(|_| ())
+    .boxed_local()
+}
+
+pub fn send_password_reset(email: &str) -> TryEnvFuture<()> {
+    fetch_api::<_, SuccessResponse>(
+        "resetPassword",
+        PasswordResetRequest {
+            email: email.to_owned(),
+        },
+    )
+    .map_ok(|_| ())
+    .boxed_local()
+}
+
+/// Calls the API methods which are not known to core
+pub fn fetch_api<REQ, RESP>(method: &str, body: REQ) -> TryEnvFuture<RESP>
+where
+    REQ: Serialize + 'static,
+    RESP: DeserializeOwned + 'static,
+{
+    let url = API_URL
+        .join("api/")
+        .and_then(|url| url.join(method))
+        .expect("url builder failed");
+    let request = Request::post(url.as_str())
+        .body(body)
+        .expect("request builder failed");
+    WebEnv::fetch::<_, APIResult<RESP>>(request)
+        .and_then(|result| match result {
+            APIResult::Ok { result } => future::ok(result),
+            APIResult::Err { error } => future::err(EnvError::Fetch(error.message)),
+        })
+        .boxed_local()
+}
+
+/// Drops the state of the account, the saved display is loaded again from the storage
+pub fn clear() {
+    *ACCOUNT.write().expect("account write failed") = Default::default();
+}
@@ -0,0 +1,116 @@
+use serde::Serialize;
+use serde_json::Value;
+
+use stremio_core::runtime::msg::Action;
+
+use crate::model::{WebModel, WebModelField};
+
+#[derive(Clone, Copy, PartialEq, Eq, Serialize, Debug)]
+pub enum RejectionReason {
+    /// A load was dispatched without a field
+    MissingField,
+    /// The action is meant for another model than the one of the field
+    WrongField,
+    /// The model of the field has nothing loaded for the action to act on
+    NotLoaded,
+}
+
+/// An action the runtime would ignore in the current state, e.g. a player action with no selection
+#[derive(Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ActionRejection {
+    /// The name of the action along with the one of its args, e.g. `Player/TimeChanged`
+    pub action: String,
+    pub field: Option<WebModelField>,
+    pub reason: RejectionReason,
+    /// The state the action expects, for the error messages in development
+    pub expected: String,
+}
+
+/// Checks the action against the state of the model before it's dispatched,
+/// the runtime itself drops the invalid actions without a trace.
+/// An action without a field is dispatched to every model, so one of its models has to be loaded.
+pub fn validate(
+    action: &Action,
+    field: Option<&WebModelField>,
+    model: &WebModel,
+) -> Result<(), ActionRejection> {
+    let reject = |reason: RejectionReason, expected: String| {
+        Err(ActionRejection {
+            action: action_name(action),
+            field: field.cloned(),
+            reason,
+            expected,
+        })
+    };
+    let fields = match action {
+        Action::Load(_) | Action::Unload if field.is_none() => {
+            return reject(RejectionReason::MissingField, "a field to load".to_owned())
+        }
+        Action::Player(_) => vec![WebModelField::Player],
+        Action::MetaDetails(_) => vec![WebModelField::MetaDetails],
+        Action::CatalogWithFilters(_) => {
+            vec![WebModelField::Discover, WebModelField::RemoteAddons]
+        }
+        Action::CatalogsWithExtra(_) => vec![WebModelField::Board, WebModelField::Search],
+        Action::LibraryWithFilters(_) => {
+            vec![WebModelField::Library, WebModelField::ContinueWatching]
+        }
+        _ => return Ok(()),
+    };
+    let expected_fields = format!(
+        "one of the fields {}",
+        fields.iter().map(field_name).collect::<Vec<_>>().join(", ")
+    );
+    let fields = match field {
+        Some(field) if fields.contains(field) => vec![field.to_owned()],
+        Some(_) => return reject(RejectionReason::WrongField, expected_fields),
+        None => fields,
+    };
+    if !fields.iter().any(|field| is_loaded(field, model)) {
+        let fields = fields
+            .iter()
+            .map(field_name)
+            .collect::<Vec<_>>()
+            .join(" or ");
+        return reject(RejectionReason::NotLoaded, format!("{fields} to be loaded"));
+    }
+    Ok(())
+}
+
+/// The models whose actions are ignored by the runtime when nothing is selected
+fn is_loaded(field: &WebModelField, model: &WebModel) -> bool {
+    match field {
+        WebModelField::Player => model.player.selected.is_some(),
+        WebModelField::MetaDetails => model.meta_details.selected.is_some(),
+        WebModelField::Discover => model.discover.selected.is_some(),
+        WebModelField::RemoteAddons => model.remote_addons.selected.is_some(),
+        WebModelField::Board => model.board.selected.is_some(),
+        WebModelField::Search => model.search.selected.is_some(),
+        WebModelField::Library => model.library.selected.is_some(),
+        WebModelField::ContinueWatching => model.continue_watching.selected.is_some(),
+        _ => true,
+    }
+}
+
+fn field_name(field: &WebModelField) -> String {
+    serde_json::to_value(field)
+        .ok()
+        .and_then(|value| value.as_str().map(|name| name.to_owned()))
+        .unwrap_or_default()
+}
+
+fn action_name(action: &Action) -> String {
+    let value = serde_json::to_value(action).unwrap_or_default();
+    let name = |value: &Value| {
+        value
+            .get("action")
+            .and_then(Value::as_str)
+            .map(|name| name.to_owned())
+    };
+    match (name(&value), value.get("args").and_then(name)) {
+        (Some(action), Some(args)) => format!("{action}/{args}"),
+        (Some(action), None) => action,
+        (None, _) => "Unknown".to_owned(),
+    }
+}
@@ -0,0 +1,104 @@
+use futures::future::LocalBoxFuture;
+use futures::FutureExt;
+use http::Request;
+use serde::Serialize;
+use serde_json::Value;
+use url::Url;
+
+use stremio_core::{
+    constants::ADDON_MANIFEST_PATH,
+    runtime::Env,
+    types::addon::{Descriptor, ResourceRequest},
+};
+
+use crate::{
+    env::WebEnv,
+    schema_validation::{self, SchemaWarning},
+};
+
+/// Outcome of a request sent from the addon console
+#[derive(Serialize)]
+#[serde(tag = "type", content = "content")]
+pub enum ReplayResult {
+    #[serde(rename_all = "camelCase")]
+    Ok {
+        url: Url,
+        /// The response as plain JSON, before core parses it
+        response: Value,
+        warnings: Vec<SchemaWarning>,
+        /// Milliseconds the addon took to respond
+        duration: i64,
+    },
+    /// The request was not sent
+    InvalidRequest(String),
+    #[serde(rename_all = "camelCase")]
+    Err { url: Url, error: String },
+}
+
+/// The url the request is sent to.
+/// The requests of an installed addon must be for a resource declared in its manifest.
+pub fn validate(request: &ResourceRequest, addons: &[Descriptor]) -> Result<Url, String> {
+    if !matches!(request.base.scheme(), "http" | "https")
+        || !request.base.path().ends_with(ADDON_MANIFEST_PATH)
+    {
+        return Err(format!(
+            "Only addons served over http with a {ADDON_MANIFEST_PATH} are supported"
+        ));
+    }
+    let path = &request.path;
+    if path.resource.is_empty() || path.r#type.is_empty() || path.id.is_empty() {
+        return Err("The resource, the type and the id are required".to_owned());
+    }
+    if path.extra.iter().any(|extra| extra.name.is_empty()) {
+        return Err("The extra properties must have a name".to_owned());
+    }
+    let installed = addons
+        .iter()
+        .find(|addon| addon.transport_url == request.base);
+    if let Some(addon) = installed {
+        if !addon.manifest.is_resource_supported(path) {
+            return Err(format!(
+                "{} does not provide {} for {} {}",
+                addon.manifest.name, path.resource, path.r#type, path.id
+            ));
+        }
+    }
+    Url::parse(
+        &request
+            .base
+            .as_str()
+            .replace(ADDON_MANIFEST_PATH, &path.to_url_path()),
+    )
+    .map_err(|error| error.to_string())
+}
+
+/// Sends the request as it is, bypassing the cache and the transports of the runtime
+pub fn replay(
+    request: ResourceRequest,
+    addons: &[Descriptor],
+) -> LocalBoxFuture<'static, ReplayResult> {
+    let url = match validate(&request, addons) {
+        Ok(url) => url,
+        Err(error) => {
+            return futures::future::ready(ReplayResult::InvalidRequest(error)).boxed_local()
+        }
+    };
+    let started = WebEnv::now();
+    let fetch_request = Request::get(url.as_str())
+        .body(())
+        .expect("request builder failed");
+    WebEnv::fetch::<_, Value>(fetch_request)
+        .map(move |result| match result {
+            Ok(response) => ReplayResult::Ok {
+                warnings: schema_validation::validate(&request.path.resource, &response),
+                duration: (WebEnv::now() - started).num_milliseconds(),
+                url,
+                response,
+            },
+            Err(error) => ReplayResult::Err {
+                url,
+                error: error.message(),
+            },
+        })
+        .boxed_local()
+}
@@ -0,0 +1,264 @@
+use std::sync::RwLock;
+
+use chrono::{DateTime, Utc};
+use http::Request;
+use lazy_static::lazy_static;
+use serde::Serialize;
+use serde_json::Value;
+use url::Url;
+
+use stremio_core::{
+    models::common::Loadable,
+    runtime::Env,
+    types::addon::{Manifest, ResourcePath, ResourceRequest},
+};
+
+use crate::{addon_console, env::WebEnv};
+
+/// Streams are requested for these when no catalog of the addon provides an item,
+/// only if the id prefixes of the addon allow them
+const SAMPLE_STREAM_IDS: [(&str, &str); 2] = [("movie", "tt1254207"), ("series", "tt0944947:1:1")];
+
+lazy_static! {
+    static ref REPORTS: RwLock<Vec<(Url, Loadable<DiagnosticsReport, String>)>> =
+        Default::default();
+}
+
+#[derive(Clone, Serialize, Debug)]
+#[serde(rename_all = "camelCase")]
+pub struct DiagnosticsReport {
+    pub started_at: DateTime<Utc>,
+    /// Milliseconds all of the checks took
+    pub duration: i64,
+    pub manifest: DiagnosticsCheck,
+    pub catalog: DiagnosticsCheck,
+    pub stream: DiagnosticsCheck,
+}
+
+#[derive(Clone, Serialize, Debug)]
+#[serde(tag = "type", content = "content")]
+pub enum DiagnosticsCheck {
+    #[serde(rename_all = "camelCase")]
+    Ok {
+        url: Url,
+        /// Milliseconds the addon took to respond
+        duration: i64,
+        /// Items of the response, the catalogs of the manifest, the metas or the streams
+        items: usize,
+    },
+    #[serde(rename_all = "camelCase")]
+    Err {
+        url: Url,
+        duration: i64,
+        error: String,
+    },
+    /// The check was not made, e.g. the addon provides no catalogs
+    Skipped(String),
+}
+
+impl DiagnosticsCheck {
+    fn with_items(self, items: usize) -> Self {
+        match self {
+            DiagnosticsCheck::Ok { url, duration, .. } => DiagnosticsCheck::Ok {
+                url,
+                duration,
+                items,
+            },
+            check => check,
+        }
+    }
+    fn with_error(self, error: String) -> Self {
+        match self {
+            DiagnosticsCheck::Ok { url, duration, .. }
+            | DiagnosticsCheck::Err { url, duration, .. } => DiagnosticsCheck::Err {
+                url,
+                duration,
+                error,
+            },
+            check => check,
+        }
+    }
+}
+
+pub fn report(transport_url: &Url) -> Option<Loadable<DiagnosticsReport, String>> {
+    REPORTS
+        .read()
+        .expect("addon diagnostics read failed")
+        .iter()
+        .find(|(report_url, _)| report_url == transport_url)
+        .map(|(_, report)| report.to_owned())
+}
+
+/// Marks the diagnostics as running, `false` when they are running already.
+/// Unlike the other checks, a finished report is replaced so the addon can be tested again.
+pub fn start(transport_url: &Url) -> bool {
+    let mut reports = REPORTS.write().expect("addon diagnostics write failed");
+    if reports.iter().any(|(report_url, report)| {
+        report_url == transport_url && matches!(report, Loadable::Loading)
+    }) {
+        return false;
+    }
+    reports.retain(|(report_url, _)| report_url != transport_url);
+    reports.push((transport_url.to_owned(), Loadable::Loading));
+    true
+}
+
+pub fn set_report(transport_url: &Url, result: Result<DiagnosticsReport, String>) {
+    if let Some((_, report)) = REPORTS
+        .write()
+        .expect("addon diagnostics write failed")
+        .iter_mut()
+        .find(|(report_url, _)| report_url == transport_url)
+    {
+        *report = match result {
+            Ok(diagnostics) => Loadable::Ready(diagnostics),
+            Err(error) => Loadable::Err(error),
+        };
+    }
+}
+
+/// Fetches the manifest, the first catalog which needs no user input and the streams
+/// of its first item, bypassing the cache and the transports of the runtime.
+/// A failed check doesn't stop the next ones which don't depend on it.
+pub async fn run(transport_url: Url) -> Result<DiagnosticsReport, String> {
+    if !matches!(transport_url.scheme(), "http" | "https") {
+        return Err("Only addons served over http can be tested".to_owned());
+    }
+    let started_at = WebEnv::now();
+    let (manifest_check, manifest) = match fetch(transport_url.to_owned()).await {
+        (check, Some(manifest)) => match serde_json::from_value::<Manifest>(manifest) {
+            Ok(manifest) => (check.with_items(manifest.catalogs.len()), Some(manifest)),
+            Err(error) => (check.with_error(format!("Invalid manifest: {error}")), None),
+        },
+        (check, None) => (check, None),
+    };
+    let manifest = match manifest {
+        Some(manifest) => manifest,
+        None => {
+            let skipped = || DiagnosticsCheck::Skipped("The manifest is not available".to_owned());
+            return Ok(DiagnosticsReport {
+                started_at,
+                duration: (WebEnv::now() - started_at).num_milliseconds(),
+                manifest: manifest_check,
+                catalog: skipped(),
+                stream: skipped(),
+            });
+        }
+    };
+    let catalog_path = manifest
+        .catalogs
+        .iter()
+        .find(|catalog| catalog.is_extra_supported(&[]))
+        .map(|catalog| ResourcePath::without_extra("catalog", &catalog.r#type, &catalog.id));
+    let (catalog_check, sample_item) = match catalog_path {
+        Some(path) => {
+            let request = ResourceRequest::new(transport_url.to_owned(), path);
+            match resource_url(&request) {
+                Ok(url) => match fetch(url).await {
+                    (check, Some(response)) => {
+                        let metas = response
+                            .get("metas")
+                            .and_then(Value::as_array)
+                            .cloned()
+                            .unwrap_or_default();
+                        let sample_item = metas.first().and_then(|meta| {
+                            Some((
+                                meta.get("type")?.as_str()?.to_owned(),
+                                meta.get("id")?.as_str()?.to_owned(),
+                            ))
+                        });
+                        (check.with_items(metas.len()), sample_item)
+                    }
+                    (check, None) => (check, None),
+                },
+                Err(error) => (DiagnosticsCheck::Skipped(error), None),
+            }
+        }
+        None => (
+            DiagnosticsCheck::Skipped(
+                "The addon provides no catalogs without user input".to_owned(),
+            ),
+            None,
+        ),
+    };
+    let stream_path = sample_item
+        .map(|(r#type, id)| match r#type.as_str() {
+            // the streams of a series are of its episodes
+            "series" => ResourcePath::without_extra("stream", &r#type, &format!("{id}:1:1")),
+            _ => ResourcePath::without_extra("stream", &r#type, &id),
+        })
+        .filter(|path| manifest.is_resource_supported(path))
+        .or_else(|| {
+            SAMPLE_STREAM_IDS
+                .iter()
+                .map(|(r#type, id)| ResourcePath::without_extra("stream", r#type, id))
+                .find(|path| manifest.is_resource_supported(path))
+        });
+    let stream_check = match stream_path {
+        Some(path) => {
+            let request = ResourceRequest::new(transport_url.to_owned(), path);
+            match resource_url(&request) {
+                Ok(url) => match fetch(url).await {
+                    (check, Some(response)) => {
+                        let streams = response
+                            .get("streams")
+                            .and_then(Value::as_array)
+                            .map_or(0, Vec::len);
+                        check.with_items(streams)
+                    }
+                    (check, None) => check,
+                },
+                Err(error) => DiagnosticsCheck::Skipped(error),
+            }
+        }
+        None => {
+            DiagnosticsCheck::Skipped("The addon provides no streams for a sample item".to_owned())
+        }
+    };
+    Ok(DiagnosticsReport {
+        started_at,
+        duration: (WebEnv::now() - started_at).num_milliseconds(),
+        manifest: manifest_check,
+        catalog: catalog_check,
+        stream: stream_check,
+    })
+}
+
+fn resource_url(request: &ResourceRequest) -> Result<Url, String> {
+    addon_console::validate(request, &[])
+}
+
+/// The response is returned only when the check succeeded
+async fn fetch(url: Url) -> (DiagnosticsCheck, Option<Value>) {
+    let started = WebEnv::now();
+    let request = Request::get(url.as_str())
+        .body(())
+        .expect("request builder failed");
+    let result = WebEnv::fetch::<_, Value>(request).await;
+    let duration = (WebEnv::now() - started).num_milliseconds();
+    match result {
+        Ok(response) => (
+            DiagnosticsCheck::Ok {
+                url,
+                duration,
+                items: 0,
+            },
+            Some(response),
+        ),
+        Err(error) => (
+            DiagnosticsCheck::Err {
+                url,
+                duration,
+                error: error.message(),
+            },
+            None,
+        ),
+    }
+}
+
+pub fn clear() {
+    REPORTS
+        .write()
+        .expect("addon diagnostics write failed")
+        .clear();
+}
@@ -0,0 +1,97 @@
+use std::sync::RwLock;
+
+use lazy_static::lazy_static;
+
+use stremio_core::{
+    models::common::Loadable,
+    types::{
+        addon::{Descriptor, ResourcePath, ResourceRequest},
+        resource::MetaItemPreview,
+    },
+};
+
+/// Items of a catalog shown in its preview row
+pub const PREVIEW_ITEMS: usize = 10;
+/// Catalog pages kept in memory, the oldest are dropped
+const MAX_PREVIEWS: usize = 50;
+
+lazy_static! {
+    static ref PREVIEWS: RwLock<Vec<(ResourceRequest, Loadable<Vec<MetaItemPreview>, String>)>> =
+        Default::default();
+}
+
+/// Requests of the first page of every catalog of the addon.
+/// The catalogs which can't be loaded without user input, e.g. search, are skipped.
+pub fn preview_requests(addon: &Descriptor) -> Vec<ResourceRequest> {
+    addon
+        .manifest
+        .catalogs
+        .iter()
+        .filter(|catalog| catalog.is_extra_supported(&[]))
+        .map(|catalog| {
+            ResourceRequest::new(
+                addon.transport_url.to_owned(),
+                ResourcePath::without_extra("catalog", &catalog.r#type, &catalog.id),
+            )
+        })
+        .collect()
+}
+
+pub fn preview(request: &ResourceRequest) -> Option<Loadable<Vec<MetaItemPreview>, String>> {
+    PREVIEWS
+        .read()
+        .expect("addon previews read failed")
+        .iter()
+        .find(|(preview_request, _)| preview_request == request)
+        .map(|(_, preview)| preview.to_owned())
+}
+
+/// Marks the previews which are not loaded yet as loading and returns their requests
+pub fn start_loading(requests: Vec<ResourceRequest>) -> Vec<ResourceRequest> {
+    let mut previews = PREVIEWS.write().expect("addon previews write failed");
+    let requests = requests
+        .into_iter()
+        .filter(|request| {
+            !previews
+                .iter()
+                .any(|(preview_request, preview)| preview_request == request && !preview.is_err())
+        })
+        .collect::<Vec<_>>();
+    previews.retain(|(preview_request, _)| !requests.contains(preview_request));
+    previews.extend(
+        requests
+            .iter()
+            .map(|request| (request.to_owned(), Loadable::Loading)),
+    );
+    let overflow = previews.len().saturating_sub(MAX_PREVIEWS);
+    previews.drain(..overflow);
+    requests
+}
+
+pub fn set_preview(request: &ResourceRequest, preview: Loadable<Vec<MetaItemPreview>, String>) {
+    let mut previews = PREVIEWS.write().expect("addon previews write failed");
+    if let Some((_, loading_preview)) = previews
+        .iter_mut()
+        .find(|(preview_request, _)| preview_request == request)
+    {
+        *loading_preview = match preview {
+            Loadable::Ready(mut metas) => {
+                metas.truncate(PREVIEW_ITEMS);
+                Loadable::Ready(metas)
+            }
+            preview => preview,
+        };
+    }
+}
+
+pub fn len() -> usize {
+    PREVIEWS.read().expect("addon previews read failed").len()
+}
+
+/// Drops the loaded previews, they are loaded again when the addon details are opened
+pub fn clear() {
+    PREVIEWS
+        .write()
+        .expect("addon previews write failed")
+        .clear();
+}
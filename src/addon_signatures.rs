@@ -0,0 +1,143 @@
+use std::sync::RwLock;
+
+#[cfg(feature = "addon-signatures")]
+use ed25519_dalek::{Signature, Verifier, VerifyingKey};
+use lazy_static::lazy_static;
+use serde::Serialize;
+use serde_json::Value;
+use url::Url;
+
+use stremio_core::models::common::Loadable;
+
+/// Keys the manifests of the official addons are signed with, by key id
+#[cfg(feature = "addon-signatures")]
+const OFFICIAL_PUBLIC_KEYS: [(&str, &str); 1] = [(
+    "stremio-official-1",
+    "185bc6c7bde72263de240d22efed5af0fe35b5f92150841726caabe6c52101a9",
+)];
+/// Manifest ids of the remote official addons, they are only shown as official with a valid
+/// signature. The local addon is served by the streaming server of the device, it's not signed.
+pub const OFFICIAL_ADDON_IDS: [&str; 4] = [
+    "com.linvo.cinemeta",
+    "com.linvo.stremiochannels",
+    "org.stremio.opensubtitlesv3",
+    "org.stremio.watchhub",
+];
+/// Field of the manifest holding the hex encoded ed25519 signature of the rest of the manifest
+const SIGNATURE_FIELD: &str = "signature";
+
+lazy_static! {
+    static ref VERIFICATIONS: RwLock<Vec<(Url, Loadable<SignatureVerification, String>)>> =
+        Default::default();
+}
+
+#[derive(Clone, PartialEq, Eq, Serialize, Debug)]
+#[serde(tag = "type", content = "content")]
+pub enum SignatureVerification {
+    #[serde(rename_all = "camelCase")]
+    Verified {
+        key_id: &'static str,
+    },
+    Unsigned,
+    /// The signature is malformed or it was not made with any of the official keys
+    Invalid,
+}
+
+impl SignatureVerification {
+    pub fn is_verified(&self) -> bool {
+        matches!(self, SignatureVerification::Verified { .. })
+    }
+}
+
+pub fn verification(transport_url: &Url) -> Option<Loadable<SignatureVerification, String>> {
+    VERIFICATIONS
+        .read()
+        .expect("signature verifications read failed")
+        .iter()
+        .find(|(verified_url, _)| verified_url == transport_url)
+        .map(|(_, verification)| verification.to_owned())
+}
+
+/// Marks the manifest as being verified, `false` when it was verified already
+/// or when the verification is not built in
+pub fn start_verifying(transport_url: &Url) -> bool {
+    if !cfg!(feature = "addon-signatures") {
+        return false;
+    }
+    let mut verifications = VERIFICATIONS
+        .write()
+        .expect("signature verifications write failed");
+    if verifications
+        .iter()
+        .any(|(verified_url, verification)| verified_url == transport_url && !verification.is_err())
+    {
+        return false;
+    }
+    verifications.retain(|(verified_url, _)| verified_url != transport_url);
+    verifications.push((transport_url.to_owned(), Loadable::Loading));
+    true
+}
+
+pub fn set_verification(transport_url: &Url, result: Result<Value, String>) {
+    let mut verifications = VERIFICATIONS
+        .write()
+        .expect("signature verifications write failed");
+    if let Some((_, verification)) = verifications
+        .iter_mut()
+        .find(|(verified_url, _)| verified_url == transport_url)
+    {
+        *verification = match result {
+            Ok(manifest) => Loadable::Ready(verify(&manifest)),
+            Err(error) => Loadable::Err(error),
+        };
+    }
+}
+
+/// Verifies the signature of the manifest as plain JSON, the fields unknown to core are signed too.
+/// The keys of the objects of `serde_json` are sorted, the signed bytes don't depend on their order.
+#[cfg(feature = "addon-signatures")]
+pub fn verify(manifest: &Value) -> SignatureVerification {
+    let mut manifest = match manifest.as_object() {
+        Some(manifest) => manifest.to_owned(),
+        None => return SignatureVerification::Invalid,
+    };
+    let signature = match manifest.remove(SIGNATURE_FIELD) {
+        Some(Value::String(signature)) => signature,
+        Some(_) => return SignatureVerification::Invalid,
+        None => return SignatureVerification::Unsigned,
+    };
+    let signature = match hex::decode(signature)
+        .ok()
+        .and_then(|signature| <[u8; 64]>::try_from(signature).ok())
+    {
+        Some(signature) => Signature::from_bytes(&signature),
+        None => return SignatureVerification::Invalid,
+    };
+    let message = serde_json::to_vec(&manifest).expect("manifest serialization failed");
+    OFFICIAL_PUBLIC_KEYS
+        .iter()
+        .find(|(_, public_key)| {
+            hex::decode(public_key)
+                .ok()
+                .and_then(|public_key| <[u8; 32]>::try_from(public_key).ok())
+                .and_then(|public_key| VerifyingKey::from_bytes(&public_key).ok())
+                .map_or(false, |public_key| {
+                    public_key.verify(&message, &signature).is_ok()
+                })
+        })
+        .map_or(SignatureVerification::Invalid, |(key_id, _)| {
+            SignatureVerification::Verified { key_id }
+        })
+}
+
+#[cfg(not(feature = "addon-signatures"))]
+pub fn verify(_manifest: &Value) -> SignatureVerification {
+    SignatureVerification::Unsigned
+}
+
+pub fn clear() {
+    VERIFICATIONS
+        .write()
+        .expect("signature verifications write failed")
+        .clear();
+}
@@ -0,0 +1,237 @@
+use std::sync::RwLock;
+
+use chrono::{DateTime, Utc};
+use lazy_static::lazy_static;
+use semver::Version;
+use serde::{Deserialize, Serialize};
+use url::Url;
+
+use stremio_core::types::addon::{Manifest, ManifestResource};
+
+pub const ADDON_UPDATES_STORAGE_KEY: &str = "addon_updates";
+
+lazy_static! {
+    static ref UPDATES: RwLock<Vec<AddonUpdate>> = Default::default();
+    static ref AVAILABLE: RwLock<Vec<AvailableUpdate>> = Default::default();
+}
+
+/// The last version change of an installed addon
+#[derive(Clone, Serialize, Deserialize, Debug)]
+#[serde(rename_all = "camelCase")]
+pub struct AddonUpdate {
+    pub transport_url: Url,
+    pub previous_version: Version,
+    pub version: Version,
+    pub updated_at: DateTime<Utc>,
+    pub changelog: Vec<ChangelogEntry>,
+}
+
+/// A newer version of an installed addon, installed only once the user confirms it
+#[derive(Clone, Debug)]
+pub struct AvailableUpdate {
+    pub update: AddonUpdate,
+    pub manifest: Manifest,
+    /// The user dismissed the update, it is not offered again until a newer version is found
+    dismissed: bool,
+}
+
+#[derive(Deserialize, Debug)]
+#[serde(tag = "action", content = "args")]
+pub enum AddonUpdatesAction {
+    /// Installs the available update of the addon
+    Install(Url),
+    /// Drops the available update of the addon until a newer version is found
+    Dismiss(Url),
+}
+
+/// A difference between the manifests of two versions of an addon
+#[derive(Clone, PartialEq, Serialize, Deserialize, Debug)]
+#[serde(tag = "type", content = "content")]
+pub enum ChangelogEntry {
+    #[serde(rename_all = "camelCase")]
+    CatalogAdded {
+        r#type: String,
+        id: String,
+        name: Option<String>,
+    },
+    #[serde(rename_all = "camelCase")]
+    CatalogRemoved {
+        r#type: String,
+        id: String,
+        name: Option<String>,
+    },
+    ResourceAdded(String),
+    ResourceRemoved(String),
+    TypeAdded(String),
+    TypeRemoved(String),
+}
+
+pub fn updates() -> Vec<AddonUpdate> {
+    UPDATES
+        .read()
+        .expect("addon updates read failed")
+        .to_owned()
+}
+
+pub fn set_updates(updates: Vec<AddonUpdate>) {
+    *UPDATES.write().expect("addon updates write failed") = updates;
+}
+
+pub fn available() -> Vec<AddonUpdate> {
+    AVAILABLE
+        .read()
+        .expect("addon updates read failed")
+        .iter()
+        .filter(|available| !available.dismissed)
+        .map(|available| available.update.to_owned())
+        .collect()
+}
+
+/// Sets the update as available in place of the previous one of the addon,
+/// `false` when the same version was already found or dismissed.
+pub fn set_available(update: AddonUpdate, manifest: Manifest) -> bool {
+    let mut available = AVAILABLE.write().expect("addon updates write failed");
+    match available
+        .iter_mut()
+        .find(|available| available.update.transport_url == update.transport_url)
+    {
+        Some(available) if available.update.version >= update.version => false,
+        Some(available) => {
+            *available = AvailableUpdate {
+                update,
+                manifest,
+                dismissed: false,
+            };
+            true
+        }
+        None => {
+            available.push(AvailableUpdate {
+                update,
+                manifest,
+                dismissed: false,
+            });
+            true
+        }
+    }
+}
+
+/// Takes the available update of the addon to be installed, `None` if it was dismissed
+pub fn take_available(transport_url: &Url) -> Option<AvailableUpdate> {
+    let mut available = AVAILABLE.write().expect("addon updates write failed");
+    let position = available.iter().position(|available| {
+        available.update.transport_url == *transport_url && !available.dismissed
+    })?;
+    Some(available.remove(position))
+}
+
+pub fn dismiss_available(transport_url: &Url) {
+    let mut available = AVAILABLE.write().expect("addon updates write failed");
+    if let Some(available) = available
+        .iter_mut()
+        .find(|available| available.update.transport_url == *transport_url)
+    {
+        available.dismissed = true;
+    }
+}
+
+pub fn clear() {
+    AVAILABLE
+        .write()
+        .expect("addon updates write failed")
+        .clear();
+}
+
+/// Records the update in place of the previous one of the addon,
+/// the updates of the addons which are no longer installed are dropped.
+/// Returns the updates to be persisted.
+pub fn record(update: AddonUpdate, installed: &[Url]) -> Vec<AddonUpdate> {
+    let mut updates = UPDATES.write().expect("addon updates write failed");
+    updates.retain(|recorded| {
+        recorded.transport_url != update.transport_url
+            && installed.contains(&recorded.transport_url)
+    });
+    updates.push(update);
+    updates.to_owned()
+}
+
+/// `None` unless the fetched manifest has a newer version, a downgrade is not an update
+pub fn detect_update(
+    transport_url: &Url,
+    installed: &Manifest,
+    fetched: &Manifest,
+    now: DateTime<Utc>,
+) -> Option<AddonUpdate> {
+    (fetched.version > installed.version).then(|| AddonUpdate {
+        transport_url: transport_url.to_owned(),
+        previous_version: installed.version.to_owned(),
+        version: fetched.version.to_owned(),
+        updated_at: now,
+        changelog: changelog(installed, fetched),
+    })
+}
+
+fn changelog(previous: &Manifest, manifest: &Manifest) -> Vec<ChangelogEntry> {
+    let mut changelog = vec![];
+    for catalog in &manifest.catalogs {
+        if !previous.catalogs.iter().any(|previous_catalog| {
+            previous_catalog.r#type == catalog.r#type && previous_catalog.id == catalog.id
+        }) {
+            changelog.push(ChangelogEntry::CatalogAdded {
+                r#type: catalog.r#type.to_owned(),
+                id: catalog.id.to_owned(),
+                name: catalog.name.to_owned(),
+            });
+        }
+    }
+    for previous_catalog in &previous.catalogs {
+        if !manifest.catalogs.iter().any(|catalog| {
+            catalog.r#type == previous_catalog.r#type && catalog.id == previous_catalog.id
+        }) {
+            changelog.push(ChangelogEntry::CatalogRemoved {
+                r#type: previous_catalog.r#type.to_owned(),
+                id: previous_catalog.id.to_owned(),
+                name: previous_catalog.name.to_owned(),
+            });
+        }
+    }
+    let resources = resource_names(manifest);
+    let previous_resources = resource_names(previous);
+    changelog.extend(
+        resources
+            .iter()
+            .filter(|resource| !previous_resources.contains(resource))
+            .map(|resource| ChangelogEntry::ResourceAdded(resource.to_owned())),
+    );
+    changelog.extend(
+        previous_resources
+            .iter()
+            .filter(|resource| !resources.contains(resource))
+            .map(|resource| ChangelogEntry::ResourceRemoved(resource.to_owned())),
+    );
+    changelog.extend(
+        manifest
+            .types
+            .iter()
+            .filter(|r#type| !previous.types.contains(r#type))
+            .map(|r#type| ChangelogEntry::TypeAdded(r#type.to_owned())),
+    );
+    changelog.extend(
+        previous
+            .types
+            .iter()
+            .filter(|r#type| !manifest.types.contains(r#type))
+            .map(|r#type| ChangelogEntry::TypeRemoved(r#type.to_owned())),
+    );
+    changelog
+}
+
+fn resource_names(manifest: &Manifest) -> Vec<String> {
+    manifest
+        .resources
+        .iter()
+        .map(|resource| match resource {
+            ManifestResource::Short(name) => name.to_owned(),
+            ManifestResource::Full { name, .. } => name.to_owned(),
+        })
+        .collect()
+}
@@ -0,0 +1,510 @@
+use std::sync::RwLock;
+
+use chrono::{DateTime, Duration, NaiveTime, TimeZone, Utc};
+use lazy_static::lazy_static;
+use serde::Serialize;
+use wasm_bindgen::JsValue;
+
+use stremio_core::runtime::{
+    msg::{Action, ActionStreamingServer},
+    Env,
+};
+
+use crate::env::WebEnv;
+use crate::fetch_limiter;
+use crate::web_settings::{self, CatalogsSettings, NotificationsSettings};
+
+/// How often the schedule is checked for due tasks
+pub const TICK_INTERVAL: i32 = 60 * 1000;
+/// The tasks which run on focus are skipped when they ran within this many minutes
+const FOCUS_DEBOUNCE: i64 = 5;
+
+lazy_static! {
+    static ref SCHEDULE: RwLock<Schedule> = Default::default();
+}
+
+/// Periodic work of the runtime which is not requested by the UI
+#[derive(Clone, Copy, PartialEq, Eq, Serialize, Debug)]
+pub enum BackgroundTask {
+    PullNotifications,
+    RefreshBoard,
+    CheckReminders,
+    CheckAddonUpdates,
+    /// Drops the data kept on the device for longer than it's useful
+    ApplyRetention,
+    /// Merges the profile and the library with the other devices of the network, when enabled
+    SyncLan,
+    /// Loads the meta details of the top Continue Watching items ahead of time
+    PrefetchMetaDetails,
+}
+
+/// When a task is due, relative to its last run
+#[derive(Clone, Copy, PartialEq, Eq, Serialize, Debug)]
+#[serde(tag = "type", content = "content")]
+pub enum Timing {
+    /// Milliseconds between the runs
+    Every(i64),
+    /// At the local time of the day, as `HH:MM:SS`
+    DailyAt(NaiveTime),
+}
+
+/// What made a task run
+#[derive(Clone, Copy, PartialEq, Eq, Serialize, Debug)]
+pub enum Trigger {
+    Start,
+    Focus,
+    Schedule,
+    /// It became due while deferred and ran once it was no longer deferred
+    Deferred,
+}
+
+impl BackgroundTask {
+    pub const ALL: [BackgroundTask; 7] = [
+        BackgroundTask::PullNotifications,
+        BackgroundTask::RefreshBoard,
+        BackgroundTask::CheckReminders,
+        BackgroundTask::CheckAddonUpdates,
+        BackgroundTask::ApplyRetention,
+        BackgroundTask::SyncLan,
+        BackgroundTask::PrefetchMetaDetails,
+    ];
+    pub fn timing(self) -> Timing {
+        match self {
+            BackgroundTask::PullNotifications => {
+                let pull_interval = web_settings::web_settings().notifications.pull_interval;
+                Timing::Every(minutes(
+                    pull_interval.max(NotificationsSettings::MIN_PULL_INTERVAL),
+                ))
+            }
+            BackgroundTask::RefreshBoard => {
+                let refresh_interval = web_settings::web_settings().catalogs.board_refresh_interval;
+                Timing::Every(minutes(
+                    refresh_interval.max(CatalogsSettings::MIN_BOARD_REFRESH_INTERVAL),
+                ))
+            }
+            BackgroundTask::CheckReminders => Timing::Every(minutes(1)),
+            BackgroundTask::CheckAddonUpdates => Timing::Every(minutes(6 * 60)),
+            BackgroundTask::ApplyRetention => {
+                Timing::DailyAt(NaiveTime::from_hms_opt(4, 0, 0).expect("invalid time"))
+            }
+            BackgroundTask::SyncLan => Timing::Every(minutes(5)),
+            BackgroundTask::PrefetchMetaDetails => Timing::Every(minutes(10)),
+        }
+    }
+    /// The initial load pulls the notifications and loads the Board already
+    pub fn runs_on_start(self) -> bool {
+        matches!(
+            self,
+            BackgroundTask::CheckReminders | BackgroundTask::ApplyRetention
+        )
+    }
+    /// Tasks which catch up once the page is focused again, even when they are not due yet
+    pub fn runs_on_focus(self) -> bool {
+        matches!(
+            self,
+            BackgroundTask::PullNotifications
+                | BackgroundTask::CheckReminders
+                | BackgroundTask::SyncLan
+        )
+    }
+    /// Reminders are due at a given time, so they are surfaced even while the page is hidden
+    pub fn is_deferrable(self) -> bool {
+        self != BackgroundTask::CheckReminders
+    }
+    /// Tasks which are deferred during the quiet hours
+    pub fn is_quiet(self) -> bool {
+        self == BackgroundTask::PullNotifications
+    }
+    /// Tasks which are deferred while the addon requests of the UI are in flight
+    pub fn is_low_priority(self) -> bool {
+        self == BackgroundTask::PrefetchMetaDetails
+    }
+}
+
+#[derive(Clone, Copy)]
+struct TaskRun {
+    task: BackgroundTask,
+    at: DateTime<Utc>,
+    trigger: Trigger,
+    /// Runs since the schedule started
+    count: u32,
+}
+
+struct Schedule {
+    visible: bool,
+    hidden_since: Option<DateTime<Utc>>,
+    last_runs: Vec<TaskRun>,
+    /// Tasks which became due while the page was hidden, run once it is visible again
+    deferred: Vec<BackgroundTask>,
+    /// The offset of the local time zone at a time, see `local_offset`
+    local_offset: fn(DateTime<Utc>) -> Duration,
+}
+
+impl Default for Schedule {
+    fn default() -> Self {
+        Self {
+            visible: true,
+            hidden_since: None,
+            last_runs: vec![],
+            deferred: vec![],
+            local_offset,
+        }
+    }
+}
+
+impl Schedule {
+    fn last_run(&self, task: BackgroundTask) -> Option<&TaskRun> {
+        self.last_runs.iter().find(|last_run| last_run.task == task)
+    }
+    fn next_run(&self, task: BackgroundTask) -> Option<DateTime<Utc>> {
+        self.last_run(task)
+            .map(|last_run| next_run_at(task.timing(), last_run.at, self.local_offset))
+    }
+    fn set_last_run(&mut self, task: BackgroundTask, now: DateTime<Utc>, trigger: Trigger) {
+        let count = self.last_run(task).map_or(0, |last_run| last_run.count);
+        self.last_runs.retain(|last_run| last_run.task != task);
+        self.last_runs.push(TaskRun {
+            task,
+            at: now,
+            trigger,
+            count: count + 1,
+        });
+    }
+    fn is_deferred(&self, task: BackgroundTask, quiet: bool) -> bool {
+        (!self.visible && task.is_deferrable())
+            || (quiet && task.is_quiet())
+            || (task.is_low_priority() && !fetch_limiter::is_idle())
+    }
+    fn set_visible(
+        &mut self,
+        visible: bool,
+        now: DateTime<Utc>,
+        quiet: bool,
+    ) -> Vec<BackgroundTask> {
+        if self.visible == visible {
+            return vec![];
+        }
+        self.visible = visible;
+        if !visible {
+            self.hidden_since = Some(now);
+            return vec![];
+        }
+        self.hidden_since = None;
+        let (deferred, mut due_tasks) = std::mem::take(&mut self.deferred)
+            .into_iter()
+            .partition::<Vec<_>, _>(|task| self.is_deferred(*task, quiet));
+        self.deferred = deferred;
+        for task in &due_tasks {
+            self.set_last_run(*task, now, Trigger::Deferred);
+        }
+        let focus_tasks = BackgroundTask::ALL
+            .into_iter()
+            .filter(|task| task.runs_on_focus() && !due_tasks.contains(task))
+            .filter(|task| !self.is_deferred(*task, quiet))
+            .filter(|task| {
+                self.last_run(*task).map_or(true, |last_run| {
+                    now - last_run.at >= Duration::minutes(FOCUS_DEBOUNCE)
+                })
+            })
+            .collect::<Vec<_>>();
+        for task in &focus_tasks {
+            self.set_last_run(*task, now, Trigger::Focus);
+        }
+        due_tasks.extend(focus_tasks);
+        due_tasks
+    }
+    fn take_due_tasks(&mut self, now: DateTime<Utc>, quiet: bool) -> Vec<BackgroundTask> {
+        let (deferred, due_tasks) = BackgroundTask::ALL
+            .into_iter()
+            .filter(|task| {
+                self.deferred.contains(task)
+                    || self
+                        .next_run(*task)
+                        .map_or(false, |next_run| next_run <= now)
+            })
+            .partition::<Vec<_>, _>(|task| self.is_deferred(*task, quiet));
+        self.deferred = deferred;
+        for task in &due_tasks {
+            self.set_last_run(*task, now, Trigger::Schedule);
+        }
+        due_tasks
+    }
+}
+
+mod model {
+    use super::*;
+    #[derive(Serialize)]
+    #[serde(rename_all = "camelCase")]
+    pub struct ScheduledTask {
+        pub task: BackgroundTask,
+        pub timing: Timing,
+        pub runs_on_start: bool,
+        pub runs_on_focus: bool,
+        pub last_run: Option<DateTime<Utc>>,
+        pub last_trigger: Option<Trigger>,
+        pub runs: u32,
+        pub next_run: Option<DateTime<Utc>>,
+        pub deferred: bool,
+    }
+    #[derive(Serialize)]
+    #[serde(rename_all = "camelCase")]
+    pub struct BackgroundSchedule {
+        pub visible: bool,
+        pub hidden_since: Option<DateTime<Utc>>,
+        /// Within the quiet hours of the notifications settings
+        pub quiet: bool,
+        pub tasks: Vec<ScheduledTask>,
+    }
+}
+
+/// Starts the schedule of every task from now, returns the tasks which run on start.
+/// The initial load does the work of the others already.
+pub fn start(now: DateTime<Utc>) -> Vec<BackgroundTask> {
+    let mut schedule = SCHEDULE.write().expect("background schedule write failed");
+    schedule.deferred.clear();
+    schedule.last_runs = BackgroundTask::ALL
+        .iter()
+        .map(|task| TaskRun {
+            task: *task,
+            at: now,
+            trigger: Trigger::Start,
+            count: u32::from(task.runs_on_start()),
+        })
+        .collect();
+    BackgroundTask::ALL
+        .into_iter()
+        .filter(|task| task.runs_on_start())
+        .collect()
+}
+
+pub fn is_visible() -> bool {
+    SCHEDULE
+        .read()
+        .expect("background schedule read failed")
+        .visible
+}
+
+/// Updates the visibility of the page, returns the tasks to catch up on when it becomes visible.
+pub fn set_visible(visible: bool, now: DateTime<Utc>) -> Vec<BackgroundTask> {
+    SCHEDULE
+        .write()
+        .expect("background schedule write failed")
+        .set_visible(visible, now, is_quiet(now))
+}
+
+/// Returns the tasks which are due and marks them as run, the deferrable tasks are deferred
+/// while the page is hidden, the quiet tasks during the quiet hours
+/// and the low priority tasks until no addon request is in flight.
+pub fn take_due_tasks(now: DateTime<Utc>) -> Vec<BackgroundTask> {
+    SCHEDULE
+        .write()
+        .expect("background schedule write failed")
+        .take_due_tasks(now, is_quiet(now))
+}
+
+/// The time the task is due at after it ran at the given time
+pub fn next_run(timing: Timing, last_run: DateTime<Utc>) -> DateTime<Utc> {
+    next_run_at(timing, last_run, local_offset)
+}
+
+/// The offset of the daily time is the one of the day it runs on, not the one of the last run,
+/// so that it stays at the same local time when the daylight saving time starts or ends
+fn next_run_at(
+    timing: Timing,
+    last_run: DateTime<Utc>,
+    local_offset: fn(DateTime<Utc>) -> Duration,
+) -> DateTime<Utc> {
+    match timing {
+        Timing::Every(interval) => last_run + Duration::milliseconds(interval),
+        Timing::DailyAt(time) => {
+            let local_last_run = last_run - local_offset(last_run);
+            let local_next_run = Utc.from_utc_datetime(&local_last_run.date_naive().and_time(time));
+            let local_next_run = if local_next_run <= local_last_run {
+                local_next_run + Duration::days(1)
+            } else {
+                local_next_run
+            };
+            // the offset at the next run is found from the one at the last run
+            let next_run = local_next_run + local_offset(last_run);
+            local_next_run + local_offset(next_run)
+        }
+    }
+}
+
+/// Whether the local time is within the quiet hours of the notifications settings
+pub fn is_quiet(now: DateTime<Utc>) -> bool {
+    web_settings::web_settings()
+        .notifications
+        .quiet_hours
+        .map_or(false, |quiet_hours| {
+            quiet_hours.contains(local_time(now, local_offset))
+        })
+}
+
+fn local_time(now: DateTime<Utc>, local_offset: fn(DateTime<Utc>) -> Duration) -> NaiveTime {
+    (now - local_offset(now)).time()
+}
+
+/// The offset is positive for the time zones behind UTC
+fn local_offset(now: DateTime<Utc>) -> Duration {
+    let date = js_sys::Date::new(&JsValue::from_f64(now.timestamp_millis() as f64));
+    Duration::minutes(date.get_timezone_offset() as i64)
+}
+
+fn minutes(minutes: u32) -> i64 {
+    Duration::minutes(minutes as i64).num_milliseconds()
+}
+
+/// Actions polled by the UI which are dropped while the page is hidden,
+/// the UI polls them again once it is visible.
+pub fn is_deferred_action(action: &Action) -> bool {
+    !is_visible()
+        && matches!(
+            action,
+            Action::StreamingServer(ActionStreamingServer::GetStatistics(_))
+        )
+}
+
+pub fn serialize_schedule() -> JsValue {
+    let schedule = SCHEDULE.read().expect("background schedule read failed");
+    JsValue::from_serde(&model::BackgroundSchedule {
+        visible: schedule.visible,
+        hidden_since: schedule.hidden_since,
+        quiet: is_quiet(WebEnv::now()),
+        tasks: BackgroundTask::ALL
+            .iter()
+            .map(|task| {
+                let last_run = schedule.last_run(*task);
+                model::ScheduledTask {
+                    task: *task,
+                    timing: task.timing(),
+                    runs_on_start: task.runs_on_start(),
+                    runs_on_focus: task.runs_on_focus(),
+                    last_run: last_run.map(|last_run| last_run.at),
+                    last_trigger: last_run.map(|last_run| last_run.trigger),
+                    runs: last_run.map_or(0, |last_run| last_run.count),
+                    next_run: schedule.next_run(*task),
+                    deferred: schedule.deferred.contains(task),
+                }
+            })
+            .collect(),
+    })
+    .unwrap()
+}
+
+#[cfg(test)]
+mod tests {
+    use chrono::{DateTime, Duration, NaiveTime, TimeZone, Utc};
+
+    use crate::web_settings::QuietHours;
+
+    use super::*;
+
+    fn utc(month: u32, day: u32, hour: u32, minute: u32) -> DateTime<Utc> {
+        Utc.with_ymd_and_hms(2026, month, day, hour, minute, 0)
+            .unwrap()
+    }
+
+    /// Central European Time, the daylight saving time is from 2026-03-29 to 2026-10-25
+    fn cet_offset(at: DateTime<Utc>) -> Duration {
+        if (utc(3, 29, 1, 0)..utc(10, 25, 1, 0)).contains(&at) {
+            Duration::minutes(-120)
+        } else {
+            Duration::minutes(-60)
+        }
+    }
+
+    fn schedule(last_run: DateTime<Utc>) -> Schedule {
+        Schedule {
+            last_runs: BackgroundTask::ALL
+                .iter()
+                .map(|task| TaskRun {
+                    task: *task,
+                    at: last_run,
+                    trigger: Trigger::Start,
+                    count: 0,
+                })
+                .collect(),
+            local_offset: cet_offset,
+            ..Default::default()
+        }
+    }
+
+    fn daily_at(hour: u32) -> Timing {
+        Timing::DailyAt(NaiveTime::from_hms_opt(hour, 0, 0).unwrap())
+    }
+
+    #[test]
+    fn every_runs_after_the_interval() {
+        assert_eq!(
+            next_run_at(Timing::Every(minutes(5)), utc(1, 10, 0, 0), cet_offset),
+            utc(1, 10, 0, 5)
+        );
+    }
+
+    #[test]
+    fn daily_at_runs_later_the_same_day() {
+        assert_eq!(
+            next_run_at(daily_at(4), utc(1, 10, 0, 0), cet_offset),
+            utc(1, 10, 3, 0)
+        );
+        assert_eq!(
+            next_run_at(daily_at(4), utc(1, 10, 3, 0), cet_offset),
+            utc(1, 11, 3, 0)
+        );
+    }
+
+    #[test]
+    fn daily_at_keeps_the_local_time_when_the_daylight_saving_time_starts() {
+        assert_eq!(
+            next_run_at(daily_at(4), utc(3, 28, 3, 0), cet_offset),
+            utc(3, 29, 2, 0)
+        );
+    }
+
+    #[test]
+    fn daily_at_keeps_the_local_time_when_the_daylight_saving_time_ends() {
+        assert_eq!(
+            next_run_at(daily_at(4), utc(10, 24, 2, 0), cet_offset),
+            utc(10, 25, 3, 0)
+        );
+    }
+
+    #[test]
+    fn hidden_page_defers_the_deferrable_tasks() {
+        let mut schedule = schedule(utc(1, 10, 0, 0));
+        schedule.set_visible(false, utc(1, 10, 0, 0), false);
+        let due_tasks = schedule.take_due_tasks(utc(1, 11, 0, 0), false);
+        assert_eq!(due_tasks, vec![BackgroundTask::CheckReminders]);
+        assert_eq!(schedule.deferred.len(), BackgroundTask::ALL.len() - 1);
+        let due_tasks = schedule.set_visible(true, utc(1, 11, 0, 1), false);
+        assert_eq!(due_tasks.len(), BackgroundTask::ALL.len() - 1);
+        assert!(!due_tasks.contains(&BackgroundTask::CheckReminders));
+        assert!(schedule.deferred.is_empty());
+        assert!(due_tasks.iter().all(|task| {
+            schedule.last_run(*task).map(|last_run| last_run.trigger) == Some(Trigger::Deferred)
+        }));
+    }
+
+    #[test]
+    fn quiet_tasks_are_deferred_during_the_quiet_hours() {
+        let mut schedule = schedule(utc(1, 10, 0, 0));
+        let due_tasks = schedule.take_due_tasks(utc(1, 11, 0, 0), true);
+        assert!(!due_tasks.contains(&BackgroundTask::PullNotifications));
+        assert_eq!(schedule.deferred, vec![BackgroundTask::PullNotifications]);
+        let due_tasks = schedule.take_due_tasks(utc(1, 11, 0, 1), false);
+        assert!(due_tasks.contains(&BackgroundTask::PullNotifications));
+        assert!(schedule.deferred.is_empty());
+    }
+
+    #[test]
+    fn quiet_hours_span_midnight_in_the_local_time() {
+        let quiet_hours = QuietHours {
+            start: NaiveTime::from_hms_opt(22, 0, 0).unwrap(),
+            end: NaiveTime::from_hms_opt(7, 0, 0).unwrap(),
+        };
+        assert!(quiet_hours.contains(local_time(utc(1, 10, 5, 30), cet_offset)));
+        assert!(!quiet_hours.contains(local_time(utc(1, 10, 6, 30), cet_offset)));
+        assert!(quiet_hours.contains(local_time(utc(1, 10, 21, 30), cet_offset)));
+    }
+}
@@ -0,0 +1,75 @@
+use std::{collections::HashSet, sync::RwLock};
+
+use lazy_static::lazy_static;
+use serde::{Deserialize, Serialize};
+use url::Url;
+
+pub const BLOCKED_ITEMS_STORAGE_KEY: &str = "blocked_items";
+
+lazy_static! {
+    /// Items the user is not interested in, hidden from all of the catalogs
+    static ref BLOCKED_ITEMS: RwLock<Vec<BlockedItem>> = Default::default();
+}
+
+/// Kept with its name and poster for listing it in the settings, where it can be unblocked
+#[derive(Clone, PartialEq, Serialize, Deserialize, Debug)]
+#[serde(rename_all = "camelCase")]
+pub struct BlockedItem {
+    pub id: String,
+    pub r#type: String,
+    pub name: String,
+    #[serde(default)]
+    pub poster: Option<Url>,
+}
+
+#[derive(Deserialize, Debug)]
+#[serde(tag = "action", content = "args")]
+pub enum BlockedItemsAction {
+    Block(BlockedItem),
+    Unblock(String),
+}
+
+pub fn set_blocked_items(blocked_items: Vec<BlockedItem>) {
+    *BLOCKED_ITEMS.write().expect("blocked items write failed") = blocked_items;
+}
+
+/// Applies the action and returns the updated blocked items to be persisted
+pub fn update_blocked_items(action: BlockedItemsAction) -> Vec<BlockedItem> {
+    let mut blocked_items = BLOCKED_ITEMS.write().expect("blocked items write failed");
+    match action {
+        BlockedItemsAction::Block(blocked_item) => {
+            blocked_items.retain(|blocked| blocked.id != blocked_item.id);
+            blocked_items.push(blocked_item);
+        }
+        BlockedItemsAction::Unblock(id) => blocked_items.retain(|blocked| blocked.id != id),
+    };
+    blocked_items.to_owned()
+}
+
+/// The most recently blocked first
+pub fn blocked_items() -> Vec<BlockedItem> {
+    BLOCKED_ITEMS
+        .read()
+        .expect("blocked items read failed")
+        .iter()
+        .rev()
+        .cloned()
+        .collect()
+}
+
+/// Ids of the blocked items, looked up once per serialization
+pub fn blocked_ids() -> HashSet<String> {
+    BLOCKED_ITEMS
+        .read()
+        .expect("blocked items read failed")
+        .iter()
+        .map(|blocked_item| blocked_item.id.to_owned())
+        .collect()
+}
+
+pub fn clear() {
+    BLOCKED_ITEMS
+        .write()
+        .expect("blocked items write failed")
+        .clear();
+}
@@ -0,0 +1,142 @@
+use std::{
+    collections::hash_map::DefaultHasher,
+    hash::{Hash, Hasher},
+    sync::RwLock,
+};
+
+use boolinator::Boolinator;
+use chrono::{DateTime, Utc};
+use futures::FutureExt;
+use lazy_static::lazy_static;
+use tracing::error;
+
+use stremio_core::{
+    runtime::{Env, RuntimeEvent},
+    types::{
+        addon::{ResourceRequest, ResourceResponse},
+        resource::MetaItemPreview,
+    },
+};
+
+use crate::{catalog_cache, env::WebEnv, model::WebModelField, stremio_core_web::emit_event};
+
+lazy_static! {
+    static ref REFRESHED_CATALOGS: RwLock<Vec<RefreshedCatalog>> = Default::default();
+}
+
+struct RefreshedCatalog {
+    request: ResourceRequest,
+    /// Hash of the latest items, either the loaded or the refreshed ones
+    hash: u64,
+    /// Items which differ from the loaded ones, `None` while they are the same
+    meta_items: Option<Vec<MetaItemPreview>>,
+    refreshed_at: DateTime<Utc>,
+}
+
+/// Refetches the loaded catalogs of the Board in the background, without them going through
+/// the loading state. The Board is updated only when the items of a catalog have changed,
+/// the changed items are passed to `on_changed` as well.
+pub fn refresh<F: Fn(&[MetaItemPreview]) + Clone + 'static>(
+    catalogs: Vec<(ResourceRequest, u64)>,
+    on_changed: F,
+) {
+    for (request, loaded_hash) in catalogs {
+        catalog_cache::invalidate(&request);
+        let on_changed = on_changed.to_owned();
+        WebEnv::exec_concurrent(
+            WebEnv::addon_transport(&request.base)
+                .resource(&request.path)
+                .map(move |result| {
+                    let meta_items = match result {
+                        Ok(ResourceResponse::Metas { metas }) => metas,
+                        Ok(_) => return,
+                        Err(error) => {
+                            error!("Failed to refresh board catalog: {error:?}");
+                            return;
+                        }
+                    };
+                    if set_refreshed(request, loaded_hash, &meta_items, WebEnv::now()) {
+                        emit_event(&RuntimeEvent::NewState(vec![WebModelField::Board]));
+                        on_changed(&meta_items);
+                    }
+                }),
+        );
+    }
+}
+
+/// Catalogs whose refreshed items differ from the loaded ones
+pub fn refreshed_catalogs() -> Vec<(ResourceRequest, Vec<MetaItemPreview>)> {
+    REFRESHED_CATALOGS
+        .read()
+        .expect("refreshed catalogs read failed")
+        .iter()
+        .filter_map(|refreshed| {
+            refreshed
+                .meta_items
+                .to_owned()
+                .map(|meta_items| (refreshed.request.to_owned(), meta_items))
+        })
+        .collect()
+}
+
+pub fn last_refreshed_at(request: &ResourceRequest) -> Option<DateTime<Utc>> {
+    REFRESHED_CATALOGS
+        .read()
+        .expect("refreshed catalogs read failed")
+        .iter()
+        .find(|refreshed| refreshed.request == *request)
+        .map(|refreshed| refreshed.refreshed_at)
+}
+
+/// Drops the refreshed items once the Board is loaded again
+pub fn clear() {
+    REFRESHED_CATALOGS
+        .write()
+        .expect("refreshed catalogs write failed")
+        .clear();
+}
+
+/// Returns whether the items have changed since the last refresh or since they were loaded
+fn set_refreshed(
+    request: ResourceRequest,
+    loaded_hash: u64,
+    meta_items: &[MetaItemPreview],
+    now: DateTime<Utc>,
+) -> bool {
+    let mut refreshed_catalogs = REFRESHED_CATALOGS
+        .write()
+        .expect("refreshed catalogs write failed");
+    let refreshed_hash = hash(meta_items);
+    match refreshed_catalogs
+        .iter_mut()
+        .find(|refreshed| refreshed.request == request)
+    {
+        Some(refreshed) => {
+            refreshed.refreshed_at = now;
+            if refreshed.hash == refreshed_hash {
+                return false;
+            }
+            refreshed.hash = refreshed_hash;
+            refreshed.meta_items = Some(meta_items.to_vec());
+            true
+        }
+        None => {
+            let changed = loaded_hash != refreshed_hash;
+            refreshed_catalogs.push(RefreshedCatalog {
+                request,
+                hash: refreshed_hash,
+                meta_items: changed.as_some_from(|| meta_items.to_vec()),
+                refreshed_at: now,
+            });
+            changed
+        }
+    }
+}
+
+pub fn hash(meta_items: &[MetaItemPreview]) -> u64 {
+    let mut hasher = DefaultHasher::new();
+    serde_json::to_string(meta_items)
+        .unwrap_or_default()
+        .hash(&mut hasher);
+    hasher.finish()
+}
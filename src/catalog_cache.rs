@@ -0,0 +1,135 @@
+use std::sync::RwLock;
+
+use chrono::{DateTime, Duration, Utc};
+use futures::{future, FutureExt, TryFutureExt};
+use lazy_static::lazy_static;
+use url::Url;
+
+use stremio_core::{
+    addon_transport::AddonTransport,
+    runtime::{Env, TryEnvFuture},
+    types::addon::{Manifest, ResourcePath, ResourceRequest, ResourceResponse},
+};
+
+use crate::{catalog_hints, env::WebEnv};
+
+/// Maximum number of catalog responses kept in memory
+const MAX_CACHED: usize = 30;
+
+lazy_static! {
+    static ref CACHED: RwLock<Vec<CachedCatalog>> = Default::default();
+}
+
+struct CachedCatalog {
+    request: ResourceRequest,
+    response: ResourceResponse,
+    cached_at: DateTime<Utc>,
+    expires_at: DateTime<Utc>,
+    /// The last load of the request was served by the cache instead of the addon
+    served: bool,
+}
+
+/// How the cached catalog of a request was loaded the last time
+pub struct CacheStatus {
+    pub served_from_cache: bool,
+    /// Seconds since the response was fetched from the addon
+    pub age: i64,
+}
+
+/// Serves the catalogs which are fetched again with the same extra values before
+/// the `cacheMaxAge` declared by the addon manifest has passed.
+pub struct CatalogCacheTransport {
+    transport_url: Url,
+    transport: Box<dyn AddonTransport>,
+}
+
+impl CatalogCacheTransport {
+    pub fn new(transport_url: Url, transport: Box<dyn AddonTransport>) -> Self {
+        Self {
+            transport_url,
+            transport,
+        }
+    }
+}
+
+impl AddonTransport for CatalogCacheTransport {
+    fn resource(&self, path: &ResourcePath) -> TryEnvFuture<ResourceResponse> {
+        if path.resource != "catalog" {
+            return self.transport.resource(path);
+        }
+        let request = ResourceRequest::new(self.transport_url.to_owned(), path.to_owned());
+        let cache_max_age = match catalog_hints::catalog_hints(&request)
+            .and_then(|hints| hints.cache_max_age)
+            .filter(|cache_max_age| *cache_max_age > 0)
+        {
+            Some(cache_max_age) => cache_max_age,
+            None => return self.transport.resource(path),
+        };
+        if let Some(response) = serve(&request, WebEnv::now()) {
+            return future::ok(response).boxed_local();
+        }
+        self.transport
+            .resource(path)
+            .inspect_ok(move |response| {
+                let now = WebEnv::now();
+                set_cached(CachedCatalog {
+                    request,
+                    response: response.to_owned(),
+                    cached_at: now,
+                    expires_at: now + Duration::seconds(cache_max_age.into()),
+                    served: false,
+                })
+            })
+            .boxed_local()
+    }
+    fn manifest(&self) -> TryEnvFuture<Manifest> {
+        self.transport.manifest()
+    }
+}
+
+fn serve(request: &ResourceRequest, now: DateTime<Utc>) -> Option<ResourceResponse> {
+    let mut cached = CACHED.write().expect("catalog cache write failed");
+    cached.retain(|cached| cached.expires_at > now);
+    let cached = cached
+        .iter_mut()
+        .find(|cached| cached.request == *request)?;
+    cached.served = true;
+    Some(cached.response.to_owned())
+}
+
+fn set_cached(catalog: CachedCatalog) {
+    let mut cached = CACHED.write().expect("catalog cache write failed");
+    cached.retain(|cached| cached.request != catalog.request);
+    if cached.len() >= MAX_CACHED {
+        cached.remove(0);
+    }
+    cached.push(catalog);
+}
+
+pub fn cache_status(request: &ResourceRequest, now: DateTime<Utc>) -> Option<CacheStatus> {
+    CACHED
+        .read()
+        .expect("catalog cache read failed")
+        .iter()
+        .find(|cached| cached.request == *request)
+        .map(|cached| CacheStatus {
+            served_from_cache: cached.served,
+            age: (now - cached.cached_at).num_seconds(),
+        })
+}
+
+/// Drops the cached response, so the next load of the request reaches the addon
+pub fn invalidate(request: &ResourceRequest) {
+    CACHED
+        .write()
+        .expect("catalog cache write failed")
+        .retain(|cached| cached.request != *request);
+}
+
+pub fn len() -> usize {
+    CACHED.read().expect("catalog cache read failed").len()
+}
+
+pub fn clear() {
+    CACHED.write().expect("catalog cache write failed").clear();
+}
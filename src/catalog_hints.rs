@@ -0,0 +1,191 @@
+use std::sync::RwLock;
+
+use chrono::{DateTime, Utc};
+use futures::FutureExt;
+use http::Request;
+use lazy_static::lazy_static;
+use serde::Deserialize;
+use serde_json::Value;
+use url::Url;
+
+use stremio_core::{
+    constants::ADDON_MANIFEST_PATH,
+    runtime::{Env, TryEnvFuture},
+    types::{addon::ResourceRequest, resource::PosterShape},
+};
+
+use crate::{env::WebEnv, loads::Loads};
+
+/// Codes of the same language, ISO 639-1 first and then ISO 639-2
+const LANGUAGE_CODES: [&[&str]; 20] = [
+    &["en", "eng"],
+    &["de", "ger", "deu"],
+    &["fr", "fre", "fra"],
+    &["es", "spa"],
+    &["it", "ita"],
+    &["pt", "por"],
+    &["ru", "rus"],
+    &["pl", "pol"],
+    &["nl", "dut", "nld"],
+    &["tr", "tur"],
+    &["ar", "ara"],
+    &["hi", "hin"],
+    &["ja", "jpn"],
+    &["ko", "kor"],
+    &["zh", "chi", "zho"],
+    &["cs", "cze", "ces"],
+    &["el", "gre", "ell"],
+    &["ro", "rum", "ron"],
+    &["bg", "bul"],
+    &["he", "heb"],
+];
+
+lazy_static! {
+    static ref HINTS: RwLock<Loads<Url, Vec<CatalogHints>>> = Default::default();
+    /// Languages declared by the addon manifests, e.g. of the localized meta addons
+    static ref ADDON_LANGUAGES: RwLock<Vec<(Url, String)>> = Default::default();
+}
+
+/// Display hints of a catalog, declared next to it in the addon manifest
+/// but not part of the manifest as parsed by core.
+#[derive(Clone, Deserialize, Debug)]
+#[serde(rename_all = "camelCase")]
+pub struct CatalogHints {
+    pub r#type: String,
+    pub id: String,
+    #[serde(default)]
+    pub poster_shape: Option<PosterShape>,
+    /// Preferred number of items per row of the grid
+    #[serde(default)]
+    pub items_per_row: Option<usize>,
+    /// Language of the titles of the catalog, e.g. `de` or `ger`,
+    /// the one of the manifest unless the catalog declares its own
+    #[serde(default)]
+    pub language: Option<String>,
+    /// Seconds the responses of the catalog can be served from the cache,
+    /// the `behaviorHints.cacheMaxAge` of the manifest unless the catalog declares its own
+    #[serde(default)]
+    pub cache_max_age: Option<u32>,
+}
+
+/// Hints of the catalog of the request, `None` while the manifest is not loaded or it declares none
+pub fn catalog_hints(request: &ResourceRequest) -> Option<CatalogHints> {
+    HINTS
+        .read()
+        .expect("catalog hints read failed")
+        .get(&request.base)
+        .and_then(|hints| hints.ready())
+        .and_then(|hints| {
+            hints
+                .iter()
+                .find(|hints| hints.r#type == request.path.r#type && hints.id == request.path.id)
+        })
+        .cloned()
+}
+
+/// Language declared by the manifest of the addon, `None` while it's not loaded or it declares none
+pub fn addon_language(transport_url: &Url) -> Option<String> {
+    ADDON_LANGUAGES
+        .read()
+        .expect("addon languages read failed")
+        .iter()
+        .find(|(loaded_url, _)| loaded_url == transport_url)
+        .map(|(_, language)| language.to_owned())
+}
+
+/// Marks the manifests which are not loaded yet, or which are due for a retry, as loading
+/// and returns their urls, the hints of the addons which are no longer installed are dropped.
+/// Legacy addons have no hints.
+pub fn start_loading(transport_urls: Vec<Url>, now: DateTime<Utc>) -> Vec<Url> {
+    let mut hints = HINTS.write().expect("catalog hints write failed");
+    hints.retain(|transport_url| transport_urls.contains(transport_url));
+    ADDON_LANGUAGES
+        .write()
+        .expect("addon languages write failed")
+        .retain(|(transport_url, _)| transport_urls.contains(transport_url));
+    let transport_urls = transport_urls
+        .into_iter()
+        .filter(|transport_url| transport_url.path().ends_with(ADDON_MANIFEST_PATH))
+        .collect();
+    hints.start_loading(transport_urls, now)
+}
+
+/// Fetches the manifest as plain JSON, so the fields unknown to core are kept
+pub fn fetch_manifest(transport_url: &Url) -> TryEnvFuture<Value> {
+    let request = Request::get(transport_url.as_str())
+        .body(())
+        .expect("request builder failed");
+    WebEnv::fetch::<_, Value>(request).boxed_local()
+}
+
+pub fn set_hints(transport_url: &Url, result: Result<Value, String>, now: DateTime<Utc>) {
+    let mut hints = HINTS.write().expect("catalog hints write failed");
+    if hints.get(transport_url).is_none() {
+        return;
+    }
+    if let Some(language) = result
+        .as_ref()
+        .ok()
+        .and_then(|manifest| manifest.get("language"))
+        .and_then(Value::as_str)
+    {
+        let mut addon_languages = ADDON_LANGUAGES
+            .write()
+            .expect("addon languages write failed");
+        addon_languages.retain(|(loaded_url, _)| loaded_url != transport_url);
+        addon_languages.push((transport_url.to_owned(), language.to_owned()));
+    }
+    hints.set(
+        transport_url,
+        result.map(|manifest| parse_hints(&manifest)),
+        now,
+    );
+}
+
+/// Catalogs with malformed hints are skipped instead of failing the whole manifest
+fn parse_hints(manifest: &Value) -> Vec<CatalogHints> {
+    let manifest_language = manifest
+        .get("language")
+        .and_then(Value::as_str)
+        .map(|language| language.to_owned());
+    let manifest_cache_max_age = manifest
+        .pointer("/behaviorHints/cacheMaxAge")
+        .and_then(Value::as_u64)
+        .and_then(|cache_max_age| u32::try_from(cache_max_age).ok());
+    manifest
+        .get("catalogs")
+        .and_then(Value::as_array)
+        .map(|catalogs| {
+            catalogs
+                .iter()
+                .filter_map(|catalog| {
+                    serde_json::from_value::<CatalogHints>(catalog.to_owned()).ok()
+                })
+                .map(|hints| CatalogHints {
+                    language: hints.language.or_else(|| manifest_language.to_owned()),
+                    cache_max_age: hints.cache_max_age.or(manifest_cache_max_age),
+                    ..hints
+                })
+                .collect()
+        })
+        .unwrap_or_default()
+}
+
+/// Whether the language declared by an addon is the configured one,
+/// the codes of both ISO 639-1 and ISO 639-2 are recognized, e.g. `de`, `ger` and `deu`
+pub fn is_same_language(declared: &str, configured: &str) -> bool {
+    let declared = declared.to_lowercase();
+    let configured = configured.to_lowercase();
+    declared == configured
+        || LANGUAGE_CODES
+            .iter()
+            .any(|codes| codes.contains(&declared.as_str()) && codes.contains(&configured.as_str()))
+}
+
+pub fn clear() {
+    HINTS.write().expect("catalog hints write failed").clear();
+    ADDON_LANGUAGES
+        .write()
+        .expect("addon languages write failed")
+        .clear();
+}
@@ -0,0 +1,876 @@
+use std::borrow::Cow;
+use std::collections::HashMap;
+use std::sync::RwLock;
+
+use chrono::{DateTime, TimeZone, Utc};
+use futures::future::LocalBoxFuture;
+use futures::FutureExt;
+use http::Method;
+use itertools::Itertools;
+use lazy_static::lazy_static;
+use serde::de::DeserializeOwned;
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use tracing::error;
+use url::Url;
+
+use stremio_core::models::common::Loadable;
+use stremio_core::runtime::Env;
+use stremio_core::types::resource::{Stream, StreamSource};
+
+use crate::{
+    env::WebEnv,
+    ipfs,
+    web_settings::{self, DebridService, DebridSettings},
+};
+
+const REAL_DEBRID_API_URL: &str = "https://api.real-debrid.com/rest/1.0/";
+const ALL_DEBRID_API_URL: &str = "https://api.alldebrid.com/v4/";
+const PREMIUMIZE_API_URL: &str = "https://www.premiumize.me/api/";
+/// Identifies the app to AllDebrid, which requires it on every request
+const ALL_DEBRID_AGENT: &str = "stremio";
+/// Torrents checked against the cache of the service in one request
+const MAX_CACHE_CHECKS: usize = 40;
+
+/// Info hash and file index of a torrent stream
+type TorrentKey = (String, Option<u16>);
+type DebridFuture<T> = LocalBoxFuture<'static, Result<T, DebridError>>;
+
+lazy_static! {
+    static ref RESOLUTIONS: RwLock<HashMap<TorrentKey, Loadable<Url, DebridError>>> =
+        Default::default();
+    /// Whether the torrents are in the cache of the service, by their info hash
+    static ref CACHE: RwLock<HashMap<String, Loadable<bool, DebridError>>> = Default::default();
+    /// Bumped on every reset, so the results of the requests started before it are dropped
+    static ref GENERATION: RwLock<u64> = Default::default();
+    static ref ACCOUNT: RwLock<Option<Loadable<DebridAccount, DebridError>>> = Default::default();
+    static ref LAST_ERROR: RwLock<Option<DebridError>> = Default::default();
+}
+
+#[derive(Clone, PartialEq, Serialize, Debug)]
+#[serde(rename_all = "camelCase")]
+pub struct DebridAccount {
+    pub username: String,
+    pub premium: bool,
+    pub premium_until: Option<DateTime<Utc>>,
+    /// Share of the traffic quota which is used up, only reported by some services
+    pub quota_used: Option<f64>,
+    pub points: Option<u64>,
+}
+
+#[derive(Clone, Copy, PartialEq, Eq, Serialize, Debug)]
+#[serde(rename_all = "camelCase")]
+pub enum DebridErrorCode {
+    InvalidToken,
+    NotPremium,
+    /// The torrent is not in the cache of the service, so it can't be streamed right away
+    NotCached,
+    QuotaExceeded,
+    Api,
+}
+
+#[derive(Clone, PartialEq, Eq, Serialize, Debug)]
+#[serde(rename_all = "camelCase")]
+pub struct DebridError {
+    pub code: DebridErrorCode,
+    pub message: String,
+}
+
+impl DebridError {
+    fn new(code: DebridErrorCode, message: impl Into<String>) -> Self {
+        Self {
+            code,
+            message: message.into(),
+        }
+    }
+}
+
+/// The state of the debrid service, serialized alongside the ctx
+#[derive(Clone, PartialEq, Serialize, Debug)]
+#[serde(rename_all = "camelCase")]
+pub struct DebridStatus {
+    pub service: Option<DebridService>,
+    pub account: Option<Loadable<DebridAccount, DebridError>>,
+    /// Error of the last stream which failed to resolve
+    pub last_error: Option<DebridError>,
+}
+
+/// A torrent stream to be resolved to a direct link
+#[derive(Clone, Debug)]
+pub struct DebridTorrent {
+    pub info_hash: String,
+    pub file_idx: Option<u16>,
+    /// Name of the file to pick, for the services which don't keep the order of the files
+    pub filename: Option<String>,
+}
+
+impl DebridTorrent {
+    pub fn from_stream(stream: &Stream) -> Option<Self> {
+        match &stream.source {
+            StreamSource::Torrent {
+                info_hash,
+                file_idx,
+                ..
+            } => Some(DebridTorrent {
+                info_hash: hex::encode(info_hash),
+                file_idx: file_idx.to_owned(),
+                filename: stream.behavior_hints.filename.to_owned(),
+            }),
+            _ => None,
+        }
+    }
+    fn key(&self) -> TorrentKey {
+        (self.info_hash.to_owned(), self.file_idx)
+    }
+    fn magnet(&self) -> String {
+        format!("magnet:?xt=urn:btih:{}", self.info_hash)
+    }
+}
+
+pub fn status() -> DebridStatus {
+    let settings = web_settings::web_settings().debrid;
+    DebridStatus {
+        service: settings.service.filter(|_| settings.is_enabled()),
+        account: ACCOUNT
+            .read()
+            .expect("debrid account read failed")
+            .to_owned(),
+        last_error: LAST_ERROR
+            .read()
+            .expect("debrid last error read failed")
+            .to_owned(),
+    }
+}
+
+/// Forgets everything resolved with the previous settings
+pub fn reset() {
+    RESOLUTIONS
+        .write()
+        .expect("debrid resolutions write failed")
+        .clear();
+    CACHE.write().expect("debrid cache write failed").clear();
+    *ACCOUNT.write().expect("debrid account write failed") = None;
+    *LAST_ERROR.write().expect("debrid last error write failed") = None;
+    *GENERATION.write().expect("debrid generation write failed") += 1;
+}
+
+/// Taken when a request is started and passed along with its result,
+/// which is dropped when the settings changed meanwhile
+pub fn generation() -> u64 {
+    *GENERATION.read().expect("debrid generation read failed")
+}
+
+fn is_current(generation: u64) -> bool {
+    generation == self::generation()
+}
+
+/// `None` while the cache of the service was not checked for the torrent of the stream,
+/// or when the service has no cache check
+pub fn is_cached(stream: &Stream) -> Option<bool> {
+    let torrent = DebridTorrent::from_stream(stream)?;
+    CACHE
+        .read()
+        .expect("debrid cache read failed")
+        .get(&torrent.info_hash)
+        .and_then(|cached| cached.ready().copied())
+}
+
+/// The info hashes of the torrent streams to be checked against the cache of the service,
+/// `None` when all of them were checked already or the debrid service is not enabled
+/// or has no cache check
+pub fn start_checking_cache<'a>(streams: impl Iterator<Item = &'a Stream>) -> Option<Vec<String>> {
+    let settings = web_settings::web_settings().debrid;
+    if !settings.is_enabled() || !has_cache_check(&settings) {
+        return None;
+    }
+    let mut cache = CACHE.write().expect("debrid cache write failed");
+    let info_hashes = streams
+        .filter_map(DebridTorrent::from_stream)
+        .map(|torrent| torrent.info_hash)
+        .unique()
+        .filter(|info_hash| !cache.contains_key(info_hash))
+        .take(MAX_CACHE_CHECKS)
+        .collect::<Vec<_>>();
+    for info_hash in info_hashes.iter() {
+        cache.insert(info_hash.to_owned(), Loadable::Loading);
+    }
+    (!info_hashes.is_empty()).then_some(info_hashes)
+}
+
+pub fn set_cache(
+    info_hashes: &[String],
+    result: Result<HashMap<String, bool>, DebridError>,
+    generation: u64,
+) {
+    if !is_current(generation) {
+        return;
+    }
+    let mut cache = CACHE.write().expect("debrid cache write failed");
+    for info_hash in info_hashes {
+        let cached = match &result {
+            Ok(cached) => Loadable::Ready(cached.get(info_hash).copied().unwrap_or_default()),
+            Err(error) => Loadable::Err(error.to_owned()),
+        };
+        cache.insert(info_hash.to_owned(), cached);
+    }
+}
+
+/// Rewrites the torrent streams resolved through the debrid service to their direct links,
+/// every other stream is resolved the same way as `ipfs::resolve_stream`.
+pub fn resolve_stream(stream: &Stream) -> Cow<'_, Stream> {
+    let resolved_url = DebridTorrent::from_stream(stream).and_then(|torrent| {
+        RESOLUTIONS
+            .read()
+            .expect("debrid resolutions read failed")
+            .get(&torrent.key())
+            .and_then(|resolution| resolution.ready().cloned())
+    });
+    match resolved_url {
+        Some(url) => {
+            let mut stream = stream.to_owned();
+            stream.source = StreamSource::Url { url };
+            Cow::Owned(stream)
+        }
+        None => ipfs::resolve_stream(stream),
+    }
+}
+
+/// The torrent of the stream to be resolved, `None` when it was resolved already,
+/// it's not a torrent or the debrid service is not enabled.
+/// Failed resolutions are not retried until the settings change.
+pub fn start_resolving(stream: &Stream) -> Option<DebridTorrent> {
+    if !web_settings::web_settings().debrid.is_enabled() {
+        return None;
+    }
+    let torrent = DebridTorrent::from_stream(stream)?;
+    let mut resolutions = RESOLUTIONS
+        .write()
+        .expect("debrid resolutions write failed");
+    if resolutions.contains_key(&torrent.key()) {
+        return None;
+    }
+    resolutions.insert(torrent.key(), Loadable::Loading);
+    Some(torrent)
+}
+
+pub fn set_resolution(torrent: &DebridTorrent, result: Result<Url, DebridError>, generation: u64) {
+    if !is_current(generation) {
+        return;
+    }
+    let resolution = match result {
+        Ok(url) => Loadable::Ready(url),
+        Err(error) => {
+            *LAST_ERROR.write().expect("debrid last error write failed") = Some(error.to_owned());
+            Loadable::Err(error)
+        }
+    };
+    RESOLUTIONS
+        .write()
+        .expect("debrid resolutions write failed")
+        .insert(torrent.key(), resolution);
+}
+
+/// `false` when the account is loaded already, it's loading or the debrid service is not enabled
+pub fn start_loading_account() -> bool {
+    if !web_settings::web_settings().debrid.is_enabled() {
+        return false;
+    }
+    let mut account = ACCOUNT.write().expect("debrid account write failed");
+    if account.is_some() {
+        return false;
+    }
+    *account = Some(Loadable::Loading);
+    true
+}
+
+pub fn set_account(result: Result<DebridAccount, DebridError>, generation: u64) {
+    if !is_current(generation) {
+        return;
+    }
+    *ACCOUNT.write().expect("debrid account write failed") = Some(match result {
+        Ok(account) => Loadable::Ready(account),
+        Err(error) => Loadable::Err(error),
+    });
+}
+
+pub fn resolve(settings: &DebridSettings, torrent: DebridTorrent) -> DebridFuture<Url> {
+    let token = settings.api_token.to_owned();
+    match settings.service {
+        Some(DebridService::RealDebrid) => real_debrid::resolve(token, torrent),
+        Some(DebridService::AllDebrid) => all_debrid::resolve(token, torrent),
+        Some(DebridService::Premiumize) => premiumize::resolve(token, torrent),
+        None => futures::future::err(not_enabled()).boxed_local(),
+    }
+}
+
+/// Which of the torrents are in the cache of the service, by their info hash
+pub fn check_cache(
+    settings: &DebridSettings,
+    info_hashes: Vec<String>,
+) -> DebridFuture<HashMap<String, bool>> {
+    let token = settings.api_token.to_owned();
+    match settings.service {
+        Some(DebridService::Premiumize) => premiumize::check_cache(token, info_hashes),
+        Some(DebridService::RealDebrid) | Some(DebridService::AllDebrid) => futures::future::err(
+            DebridError::new(DebridErrorCode::Api, "Debrid service has no cache check"),
+        )
+        .boxed_local(),
+        None => futures::future::err(not_enabled()).boxed_local(),
+    }
+}
+
+/// Real-Debrid and AllDebrid disabled their instant availability endpoints, whether a torrent
+/// is cached by them is only known once it's added, see `resolve`
+fn has_cache_check(settings: &DebridSettings) -> bool {
+    settings.service == Some(DebridService::Premiumize)
+}
+
+pub fn fetch_account(settings: &DebridSettings) -> DebridFuture<DebridAccount> {
+    let token = settings.api_token.to_owned();
+    match settings.service {
+        Some(DebridService::RealDebrid) => real_debrid::fetch_account(token),
+        Some(DebridService::AllDebrid) => all_debrid::fetch_account(token),
+        Some(DebridService::Premiumize) => premiumize::fetch_account(token),
+        None => futures::future::err(not_enabled()).boxed_local(),
+    }
+}
+
+fn not_enabled() -> DebridError {
+    DebridError::new(DebridErrorCode::Api, "Debrid service is not enabled")
+}
+
+async fn request(
+    method: Method,
+    url: Url,
+    headers: &[(&str, &str)],
+    form: &[(&str, &str)],
+) -> Result<(u16, Value), DebridError> {
+    WebEnv::fetch_form(method, &url, headers, form)
+        .await
+        .map_err(|error| DebridError::new(DebridErrorCode::Api, error.message()))
+}
+
+fn parse<T: DeserializeOwned>(value: Value) -> Result<T, DebridError> {
+    serde_json::from_value(value)
+        .map_err(|error| DebridError::new(DebridErrorCode::Api, error.to_string()))
+}
+
+fn not_cached() -> DebridError {
+    DebridError::new(DebridErrorCode::NotCached, "Torrent is not cached")
+}
+
+fn api_url(base: &str, path: &str) -> Url {
+    Url::parse(base)
+        .and_then(|url| url.join(path))
+        .expect("url builder failed")
+}
+
+/// The file named like the stream, otherwise the largest one
+fn pick_file<T>(
+    files: Vec<T>,
+    torrent: &DebridTorrent,
+    name: impl Fn(&T) -> &str,
+    size: impl Fn(&T) -> u64,
+) -> Option<T> {
+    let named = torrent.filename.as_ref().and_then(|filename| {
+        files
+            .iter()
+            .position(|file| name(file).ends_with(filename.as_str()))
+    });
+    let index = named.or_else(|| {
+        files
+            .iter()
+            .enumerate()
+            .max_by_key(|(_, file)| size(file))
+            .map(|(index, _)| index)
+    })?;
+    files.into_iter().nth(index)
+}
+
+mod real_debrid {
+    use super::*;
+
+    #[derive(Deserialize)]
+    struct ErrorResponse {
+        error: String,
+        error_code: Option<i32>,
+    }
+
+    #[derive(Deserialize)]
+    struct AddMagnetResponse {
+        id: String,
+    }
+
+    #[derive(Deserialize)]
+    struct TorrentFile {
+        id: u32,
+        bytes: u64,
+    }
+
+    #[derive(Deserialize)]
+    struct TorrentInfo {
+        status: String,
+        files: Vec<TorrentFile>,
+        links: Vec<String>,
+    }
+
+    #[derive(Deserialize)]
+    struct UnrestrictResponse {
+        download: Url,
+    }
+
+    #[derive(Deserialize)]
+    struct User {
+        username: String,
+        r#type: String,
+        expiration: Option<DateTime<Utc>>,
+        points: Option<u64>,
+    }
+
+    async fn call(
+        token: &str,
+        method: Method,
+        path: &str,
+        form: &[(&str, &str)],
+    ) -> Result<Value, DebridError> {
+        let authorization = format!("Bearer {token}");
+        let (status, body) = request(
+            method,
+            api_url(REAL_DEBRID_API_URL, path),
+            &[("authorization", authorization.as_str())],
+            form,
+        )
+        .await?;
+        if (200..300).contains(&status) {
+            return Ok(body);
+        }
+        let error = parse::<ErrorResponse>(body).ok();
+        let code = match error.as_ref().and_then(|error| error.error_code) {
+            _ if status == 401 => DebridErrorCode::InvalidToken,
+            Some(8) => DebridErrorCode::InvalidToken,
+            Some(9) | Some(20) => DebridErrorCode::NotPremium,
+            Some(21) | Some(23) | Some(34) => DebridErrorCode::QuotaExceeded,
+            _ => DebridErrorCode::Api,
+        };
+        Err(DebridError::new(
+            code,
+            error
+                .map(|error| error.error)
+                .unwrap_or_else(|| format!("Unexpected HTTP status code {status}")),
+        ))
+    }
+
+    /// The torrent is added and its files are selected, it's cached when it's downloaded
+    /// right away. Otherwise it's deleted, so it's not downloaded on the account.
+    pub fn resolve(token: String, torrent: DebridTorrent) -> DebridFuture<Url> {
+        async move {
+            let magnet = torrent.magnet();
+            let added = parse::<AddMagnetResponse>(
+                call(
+                    &token,
+                    Method::POST,
+                    "torrents/addMagnet",
+                    &[("magnet", magnet.as_str())],
+                )
+                .await?,
+            )?;
+            let result = download_link(&token, &added.id, &torrent).await;
+            if result.is_err() {
+                // the torrent which can't be streamed is not left on the account
+                if let Err(error) = call(
+                    &token,
+                    Method::DELETE,
+                    &format!("torrents/delete/{}", added.id),
+                    &[],
+                )
+                .await
+                {
+                    error!("Failed to delete debrid torrent: {}", error.message);
+                }
+            }
+            result
+        }
+        .boxed_local()
+    }
+
+    async fn download_link(
+        token: &str,
+        id: &str,
+        torrent: &DebridTorrent,
+    ) -> Result<Url, DebridError> {
+        call(
+            token,
+            Method::POST,
+            &format!("torrents/selectFiles/{id}"),
+            &[("files", "all")],
+        )
+        .await?;
+        let info = parse::<TorrentInfo>(
+            call(token, Method::GET, &format!("torrents/info/{id}"), &[]).await?,
+        )?;
+        if info.status != "downloaded" {
+            return Err(DebridError::new(
+                DebridErrorCode::NotCached,
+                format!("Torrent is {}", info.status),
+            ));
+        }
+        // every file is selected, so the links are in the order of the files
+        let index = match torrent.file_idx {
+            Some(file_idx) => info
+                .files
+                .iter()
+                .position(|file| file.id == u32::from(file_idx) + 1),
+            None => info
+                .files
+                .iter()
+                .enumerate()
+                .max_by_key(|(_, file)| file.bytes)
+                .map(|(index, _)| index),
+        };
+        let link = index
+            .and_then(|index| info.links.get(index))
+            .or_else(|| info.links.first())
+            .ok_or_else(|| DebridError::new(DebridErrorCode::Api, "Torrent has no links"))?;
+        let unrestricted = parse::<UnrestrictResponse>(
+            call(
+                token,
+                Method::POST,
+                "unrestrict/link",
+                &[("link", link.as_str())],
+            )
+            .await?,
+        )?;
+        Ok(unrestricted.download)
+    }
+
+    pub fn fetch_account(token: String) -> DebridFuture<DebridAccount> {
+        async move {
+            let user = parse::<User>(call(&token, Method::GET, "user", &[]).await?)?;
+            Ok(DebridAccount {
+                username: user.username,
+                premium: user.r#type == "premium",
+                premium_until: user.expiration,
+                quota_used: None,
+                points: user.points,
+            })
+        }
+        .boxed_local()
+    }
+}
+
+mod all_debrid {
+    use super::*;
+
+    /// Status code of the magnets which are downloaded
+    const READY_STATUS_CODE: u32 = 4;
+
+    #[derive(Deserialize)]
+    struct Error {
+        code: String,
+        message: String,
+    }
+
+    #[derive(Deserialize)]
+    #[serde(tag = "status", rename_all = "lowercase")]
+    enum Response<T> {
+        Success { data: T },
+        Error { error: Error },
+    }
+
+    #[derive(Deserialize)]
+    struct UploadedMagnet {
+        id: Option<u64>,
+        error: Option<Error>,
+    }
+
+    #[derive(Deserialize)]
+    struct UploadResponse {
+        magnets: Vec<UploadedMagnet>,
+    }
+
+    #[derive(Deserialize)]
+    struct MagnetLink {
+        link: String,
+        filename: String,
+        size: u64,
+    }
+
+    #[derive(Deserialize)]
+    #[serde(rename_all = "camelCase")]
+    struct MagnetStatus {
+        /// `READY_STATUS_CODE` once the files can be unlocked
+        status_code: u32,
+        #[serde(default)]
+        links: Vec<MagnetLink>,
+    }
+
+    #[derive(Deserialize)]
+    struct StatusResponse {
+        magnets: MagnetStatus,
+    }
+
+    #[derive(Deserialize)]
+    struct UnlockResponse {
+        link: Url,
+    }
+
+    #[derive(Deserialize)]
+    #[serde(rename_all = "camelCase")]
+    struct User {
+        username: String,
+        is_premium: bool,
+        premium_until: Option<i64>,
+        fidelity_points: Option<u64>,
+    }
+
+    #[derive(Deserialize)]
+    struct UserResponse {
+        user: User,
+    }
+
+    fn to_debrid_error(error: Error) -> DebridError {
+        let code = match error.code.as_str() {
+            "AUTH_MISSING_APIKEY" | "AUTH_BAD_APIKEY" | "AUTH_BLOCKED" | "AUTH_USER_BANNED" => {
+                DebridErrorCode::InvalidToken
+            }
+            "MUST_BE_PREMIUM" | "FREE_TRIAL_LIMIT_REACHED" => DebridErrorCode::NotPremium,
+            "MAGNET_TOO_MANY_ACTIVE" | "MAGNET_TOO_MANY" => DebridErrorCode::QuotaExceeded,
+            _ => DebridErrorCode::Api,
+        };
+        DebridError::new(code, error.message)
+    }
+
+    async fn call<T: DeserializeOwned>(
+        token: &str,
+        path: &str,
+        query: &[(&str, &str)],
+    ) -> Result<T, DebridError> {
+        let mut url = api_url(ALL_DEBRID_API_URL, path);
+        url.query_pairs_mut()
+            .append_pair("agent", ALL_DEBRID_AGENT)
+            .append_pair("apikey", token)
+            .extend_pairs(query);
+        let (_, body) = request(Method::GET, url, &[], &[]).await?;
+        match parse::<Response<T>>(body)? {
+            Response::Success { data } => Ok(data),
+            Response::Error { error } => Err(to_debrid_error(error)),
+        }
+    }
+
+    /// The magnet is uploaded, it's cached when it's ready right away.
+    /// Otherwise it's deleted, so it's not downloaded on the account.
+    pub fn resolve(token: String, torrent: DebridTorrent) -> DebridFuture<Url> {
+        async move {
+            let uploaded = call::<UploadResponse>(
+                &token,
+                "magnet/upload",
+                &[("magnets[]", torrent.info_hash.as_str())],
+            )
+            .await?
+            .magnets
+            .into_iter()
+            .next()
+            .ok_or_else(|| DebridError::new(DebridErrorCode::Api, "Magnet was not uploaded"))?;
+            if let Some(error) = uploaded.error {
+                return Err(to_debrid_error(error));
+            }
+            let id = match uploaded.id {
+                Some(id) => id.to_string(),
+                None => return Err(not_cached()),
+            };
+            let result = download_link(&token, &id, &torrent).await;
+            if result.is_err() {
+                // the magnet which can't be streamed is not left on the account
+                if let Err(error) =
+                    call::<Value>(&token, "magnet/delete", &[("id", id.as_str())]).await
+                {
+                    error!("Failed to delete debrid magnet: {}", error.message);
+                }
+            }
+            result
+        }
+        .boxed_local()
+    }
+
+    async fn download_link(
+        token: &str,
+        id: &str,
+        torrent: &DebridTorrent,
+    ) -> Result<Url, DebridError> {
+        let status = call::<StatusResponse>(token, "magnet/status", &[("id", id)])
+            .await?
+            .magnets;
+        if status.status_code != READY_STATUS_CODE {
+            return Err(not_cached());
+        }
+        let link = pick_file(
+            status.links,
+            torrent,
+            |link| link.filename.as_str(),
+            |link| link.size,
+        )
+        .ok_or_else(|| DebridError::new(DebridErrorCode::Api, "Torrent has no links"))?;
+        let unlocked =
+            call::<UnlockResponse>(token, "link/unlock", &[("link", link.link.as_str())]).await?;
+        Ok(unlocked.link)
+    }
+
+    pub fn fetch_account(token: String) -> DebridFuture<DebridAccount> {
+        async move {
+            let user = call::<UserResponse>(&token, "user", &[]).await?.user;
+            Ok(DebridAccount {
+                username: user.username,
+                premium: user.is_premium,
+                premium_until: user
+                    .premium_until
+                    .filter(|premium_until| *premium_until > 0)
+                    .and_then(|premium_until| Utc.timestamp_opt(premium_until, 0).single()),
+                quota_used: None,
+                points: user.fidelity_points,
+            })
+        }
+        .boxed_local()
+    }
+}
+
+mod premiumize {
+    use super::*;
+
+    #[derive(Deserialize)]
+    struct Response {
+        status: String,
+        message: Option<String>,
+        #[serde(flatten)]
+        content: Value,
+    }
+
+    #[derive(Deserialize)]
+    struct CacheCheckResponse {
+        response: Vec<bool>,
+    }
+
+    #[derive(Deserialize)]
+    struct DirectDownloadFile {
+        path: String,
+        size: u64,
+        link: Url,
+        stream_link: Option<Url>,
+    }
+
+    #[derive(Deserialize)]
+    struct DirectDownloadResponse {
+        content: Vec<DirectDownloadFile>,
+    }
+
+    #[derive(Deserialize)]
+    struct AccountInfo {
+        customer_id: Value,
+        premium_until: Value,
+        limit_used: Option<f64>,
+    }
+
+    async fn call<T: DeserializeOwned>(
+        token: &str,
+        method: Method,
+        path: &str,
+        fields: &[(&str, &str)],
+    ) -> Result<T, DebridError> {
+        let mut url = api_url(PREMIUMIZE_API_URL, path);
+        url.query_pairs_mut().append_pair("apikey", token);
+        let (status, body) = match method {
+            Method::GET => {
+                url.query_pairs_mut().extend_pairs(fields);
+                request(method, url, &[], &[]).await?
+            }
+            _ => request(method, url, &[], fields).await?,
+        };
+        if status == 401 {
+            return Err(DebridError::new(
+                DebridErrorCode::InvalidToken,
+                "Invalid API key",
+            ));
+        }
+        let response = parse::<Response>(body)?;
+        if response.status != "success" {
+            let message = response.message.unwrap_or_default();
+            let code = if message.contains("premium") {
+                DebridErrorCode::NotPremium
+            } else if message.contains("limit") {
+                DebridErrorCode::QuotaExceeded
+            } else if message.contains("auth") || message.contains("apikey") {
+                DebridErrorCode::InvalidToken
+            } else {
+                DebridErrorCode::Api
+            };
+            return Err(DebridError::new(code, message));
+        }
+        parse(response.content)
+    }
+
+    pub fn check_cache(
+        token: String,
+        info_hashes: Vec<String>,
+    ) -> DebridFuture<HashMap<String, bool>> {
+        async move {
+            let items = info_hashes
+                .iter()
+                .map(|info_hash| ("items[]", info_hash.as_str()))
+                .collect::<Vec<_>>();
+            // the cache status of the items is in the order they were passed
+            let cached = call::<CacheCheckResponse>(&token, Method::GET, "cache/check", &items)
+                .await?
+                .response;
+            Ok(info_hashes.into_iter().zip(cached).collect())
+        }
+        .boxed_local()
+    }
+
+    /// Streamed right away without adding a transfer, so nothing is left on the account
+    pub fn resolve(token: String, torrent: DebridTorrent) -> DebridFuture<Url> {
+        async move {
+            let cached = check_cache(token.to_owned(), vec![torrent.info_hash.to_owned()]).await?;
+            if !cached.get(&torrent.info_hash).copied().unwrap_or_default() {
+                return Err(not_cached());
+            }
+            let magnet = torrent.magnet();
+            let files = call::<DirectDownloadResponse>(
+                &token,
+                Method::POST,
+                "transfer/directdl",
+                &[("src", magnet.as_str())],
+            )
+            .await?
+            .content;
+            let file = pick_file(files, &torrent, |file| file.path.as_str(), |file| file.size)
+                .ok_or_else(|| DebridError::new(DebridErrorCode::Api, "Torrent has no files"))?;
+            Ok(file.stream_link.unwrap_or(file.link))
+        }
+        .boxed_local()
+    }
+
+    pub fn fetch_account(token: String) -> DebridFuture<DebridAccount> {
+        async move {
+            let info = call::<AccountInfo>(&token, Method::GET, "account/info", &[]).await?;
+            // `premium_until` is `false` for the free accounts
+            let premium_until = info
+                .premium_until
+                .as_i64()
+                .and_then(|premium_until| Utc.timestamp_opt(premium_until, 0).single());
+            Ok(DebridAccount {
+                username: match info.customer_id {
+                    Value::String(customer_id) => customer_id,
+                    customer_id => customer_id.to_string(),
+                },
+                premium: premium_until.map_or(false, |premium_until| premium_until > WebEnv::now()),
+                premium_until,
+                quota_used: info.limit_used,
+                points: None,
+            })
+        }
+        .boxed_local()
+    }
+}
+
+pub fn clear() {
+    reset();
+}
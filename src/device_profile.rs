@@ -0,0 +1,50 @@
+use std::sync::RwLock;
+
+use lazy_static::lazy_static;
+use serde::{Deserialize, Serialize};
+use url::Url;
+
+pub const DEVICE_PROFILE_STORAGE_KEY: &str = "device_profile";
+/// Header carrying the device profile in the stream creation requests to the streaming server
+pub const DEVICE_PROFILE_HEADER: &str = "x-stremio-device-profile";
+
+lazy_static! {
+    static ref DEVICE_PROFILE: RwLock<Option<DeviceProfile>> = Default::default();
+}
+
+/// Playback capabilities of the device, used by the streaming server to decide whether to transcode.
+#[derive(Clone, Default, PartialEq, Eq, Serialize, Deserialize, Debug)]
+#[serde(rename_all = "camelCase", default)]
+pub struct DeviceProfile {
+    /// Height of the highest resolution the device can play, `None` when not limited
+    pub max_resolution: Option<u32>,
+    pub hdr: bool,
+    /// Codecs the device can decode, empty when unknown
+    pub codecs: Vec<String>,
+}
+
+pub fn device_profile() -> Option<DeviceProfile> {
+    DEVICE_PROFILE
+        .read()
+        .expect("device profile read failed")
+        .to_owned()
+}
+
+pub fn set_device_profile(device_profile: Option<DeviceProfile>) {
+    *DEVICE_PROFILE.write().expect("device profile write failed") = device_profile;
+}
+
+/// The value of the device profile header, if the request creates a stream on the streaming server
+pub fn stream_creation_header(url: &str, streaming_server_url: Option<&Url>) -> Option<String> {
+    let is_stream_creation = streaming_server_url.map_or(false, |streaming_server_url| {
+        url.starts_with(streaming_server_url.as_str())
+    }) && url
+        .split('?')
+        .next()
+        .unwrap_or_default()
+        .ends_with("/create");
+    if !is_stream_creation {
+        return None;
+    }
+    device_profile().map(|device_profile| serde_json::to_string(&device_profile).unwrap())
+}
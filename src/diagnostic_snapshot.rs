@@ -0,0 +1,208 @@
+use std::collections::HashMap;
+
+use chrono::{DateTime, Utc};
+use lazy_static::lazy_static;
+use regex::{Captures, Regex};
+use serde::Serialize;
+use serde_json::{Map, Value};
+use url::Url;
+use wasm_bindgen::JsValue;
+
+use stremio_core::constants::OFFICIAL_ADDONS;
+
+use crate::{
+    background,
+    env::{StorageBackend, WebEnv},
+    features,
+    meta_prefetch::{self, PrefetchStatus},
+    model::{
+        schema_version::{self, SchemaVersion},
+        WebModelField,
+    },
+    recent_logs::{self, LogEntry},
+    request_tracing, tab_sync,
+    web_settings::{self, WebSettings},
+};
+
+/// The states in the snapshot, the auth link and the data export are left out as they are
+/// all about the credentials
+pub const SNAPSHOT_FIELDS: [WebModelField; 14] = [
+    WebModelField::Ctx,
+    WebModelField::ContinueWatchingPreview,
+    WebModelField::Board,
+    WebModelField::Discover,
+    WebModelField::Library,
+    WebModelField::ContinueWatching,
+    WebModelField::Search,
+    WebModelField::LocalSearch,
+    WebModelField::MetaDetails,
+    WebModelField::RemoteAddons,
+    WebModelField::InstalledAddons,
+    WebModelField::AddonDetails,
+    WebModelField::StreamingServer,
+    WebModelField::Player,
+];
+/// Replaces the values of these keys wherever they are, compared case-insensitively
+const SCRUBBED_KEYS: [&str; 9] = [
+    "auth",
+    "authKey",
+    "email",
+    "password",
+    "token",
+    "apiToken",
+    "installationID",
+    "profileDisplay",
+    "sessions",
+];
+const SCRUBBED: &str = "[scrubbed]";
+
+lazy_static! {
+    static ref EMAIL_REGEX: Regex =
+        Regex::new(r"[A-Za-z0-9._%+-]+@[A-Za-z0-9.-]+\.[A-Za-z]{2,}").unwrap();
+    /// Credentials passed in the query of the urls, e.g. `?token=...` or `&api_key=...`
+    static ref QUERY_CREDENTIALS_REGEX: Regex =
+        Regex::new(r"(?i)([?&](?:token|auth|authkey|key|apikey|api_key|password)=)[^&\s]+")
+            .unwrap();
+    /// The transport urls of the addons, their paths carry the configuration of the addons,
+    /// e.g. the keys of the debrid services
+    static ref TRANSPORT_URL_REGEX: Regex =
+        Regex::new(r#"https?://[^\s"'<>]+/manifest\.json"#).unwrap();
+}
+
+#[derive(Serialize)]
+#[serde(rename_all = "camelCase")]
+struct EnvInfo {
+    app_version: String,
+    shell_version: Option<String>,
+    core_web_version: &'static str,
+    user_agent: Option<String>,
+    storage_backend: StorageBackend,
+    streaming_server_url: Option<Url>,
+    is_leader_tab: bool,
+    visible: bool,
+    schema_version: SchemaVersion,
+    features: HashMap<String, bool>,
+    web_settings: WebSettings,
+}
+
+#[derive(Serialize)]
+#[serde(rename_all = "camelCase")]
+struct DiagnosticSnapshot {
+    created_at: DateTime<Utc>,
+    env: EnvInfo,
+    /// The serialized states, by the name of the field
+    states: Map<String, Value>,
+    /// The oldest first
+    logs: Vec<LogEntry>,
+    /// The meta details loaded ahead of time for Continue Watching
+    meta_prefetch: PrefetchStatus,
+}
+
+/// A single JSON document of the states, the recent logs and the environment, to be attached
+/// to the bug reports. The credentials and the emails are scrubbed from all of it.
+pub fn create(states: Vec<(WebModelField, JsValue)>, now: DateTime<Utc>) -> String {
+    let snapshot = DiagnosticSnapshot {
+        created_at: now,
+        env: EnvInfo {
+            app_version: WebEnv::app_version(),
+            shell_version: WebEnv::shell_version(),
+            core_web_version: env!("CARGO_PKG_VERSION"),
+            user_agent: WebEnv::user_agent(),
+            storage_backend: WebEnv::storage_backend(),
+            streaming_server_url: WebEnv::streaming_server_url(),
+            is_leader_tab: tab_sync::is_leader(),
+            visible: background::is_visible(),
+            schema_version: schema_version::schema_version(),
+            features: features::features(),
+            web_settings: web_settings::web_settings(),
+        },
+        states: states
+            .into_iter()
+            .filter_map(|(field, state)| {
+                let name = serde_json::to_value(field).ok()?.as_str()?.to_owned();
+                Some((name, state.into_serde::<Value>().unwrap_or(Value::Null)))
+            })
+            .collect(),
+        logs: recent_logs::recent_logs(),
+        meta_prefetch: meta_prefetch::status(),
+    };
+    let mut value = serde_json::to_value(&snapshot).expect("diagnostic snapshot failed");
+    scrub(&mut value);
+    serde_json::to_string_pretty(&value).expect("diagnostic snapshot failed")
+}
+
+fn scrub(value: &mut Value) {
+    match value {
+        Value::Object(object) => {
+            for (key, value) in object.iter_mut() {
+                if SCRUBBED_KEYS
+                    .iter()
+                    .any(|scrubbed_key| scrubbed_key.eq_ignore_ascii_case(key))
+                {
+                    if !value.is_null() {
+                        *value = Value::String(SCRUBBED.to_owned());
+                    }
+                } else {
+                    scrub(value);
+                }
+            }
+        }
+        Value::Array(values) => values.iter_mut().for_each(scrub),
+        Value::String(string) => {
+            let scrubbed = TRANSPORT_URL_REGEX.replace_all(string, |captures: &Captures| {
+                redact_transport_url(&captures[0])
+            });
+            let scrubbed = EMAIL_REGEX.replace_all(&scrubbed, SCRUBBED);
+            let scrubbed =
+                QUERY_CREDENTIALS_REGEX.replace_all(&scrubbed, format!("${{1}}{SCRUBBED}"));
+            if scrubbed != string.as_str() {
+                *string = scrubbed.into_owned();
+            }
+        }
+        _ => {}
+    }
+}
+
+/// Only the urls of the official addons are kept, they are the same for everyone
+fn redact_transport_url(url: &str) -> String {
+    if OFFICIAL_ADDONS
+        .iter()
+        .any(|addon| addon.transport_url.as_str() == url)
+    {
+        url.to_owned()
+    } else {
+        request_tracing::redact_url(url)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use serde_json::json;
+
+    use super::*;
+
+    #[test]
+    fn scrub_transport_urls() {
+        let official = OFFICIAL_ADDONS[0].transport_url.to_string();
+        let mut value = json!({
+            "addons": [
+                { "transportUrl": official },
+                { "transportUrl": "https://addon.example.com/apikey=abc/manifest.json" },
+            ],
+            "message": "Request failed, url=\"https://addon.example.com/apikey=abc/manifest.json\"",
+            "email": "user@example.com",
+        });
+        scrub(&mut value);
+        assert_eq!(
+            value,
+            json!({
+                "addons": [
+                    { "transportUrl": official },
+                    { "transportUrl": "https://addon.example.com/[redacted]" },
+                ],
+                "message": "Request failed, url=\"https://addon.example.com/[redacted]\"",
+                "email": SCRUBBED,
+            })
+        );
+    }
+}
@@ -1,17 +1,23 @@
-use std::{collections::HashMap, sync::RwLock};
+use std::{
+    cell::RefCell,
+    collections::{HashMap, HashSet},
+    sync::RwLock,
+};
 
 use chrono::{offset::TimeZone, DateTime, Utc};
 use futures::{
-    future::{self, Either},
+    channel::oneshot,
+    future::{self, Shared},
     Future, FutureExt, TryFutureExt,
 };
-use http::{Method, Request};
+use http::{HeaderMap, Method, Request};
 use lazy_static::lazy_static;
 use regex::Regex;
 use serde::{Deserialize, Serialize};
 use serde_json::json;
 
 use stremio_core::{
+    addon_transport::{AddonHTTPTransport, AddonTransport},
     analytics::Analytics,
     models::{ctx::Ctx, streaming_server::StreamingServer},
     runtime::{
@@ -31,12 +37,54 @@ use wasm_bindgen_futures::{spawn_local, JsFuture};
 use web_sys::WorkerGlobalScope;
 
 use crate::{
+    account::{self, SavedProfileDisplay, PROFILE_DISPLAY_STORAGE_KEY},
+    addon_updates::{self, AddonUpdate, ADDON_UPDATES_STORAGE_KEY},
+    background,
+    blocklist::{self, BlockedItem, BLOCKED_ITEMS_STORAGE_KEY},
+    catalog_cache::CatalogCacheTransport,
+    device_profile::{self, DeviceProfile, DEVICE_PROFILE_HEADER, DEVICE_PROFILE_STORAGE_KEY},
     event::{UIEvent, WebEvent},
-    model::WebModel,
+    event_sequence, fetch_limiter,
+    lan_sync::{self, LanSync, LAN_SYNC_STORAGE_KEY},
+    library_tags::{self, LIBRARY_TAGS_STORAGE_KEY},
+    load_cancellation,
+    meta_overrides::{self, MetaOverride, META_OVERRIDES_STORAGE_KEY},
+    mirrors::{self, AddonMirrors, MirrorTransport, ADDON_MIRRORS_STORAGE_KEY},
+    model::{
+        library_sort::{self, SortKeys, LIBRARY_SORT_KEYS_STORAGE_KEY},
+        WebModel,
+    },
+    new_episodes::{self, NewEpisodesState, NEW_EPISODES_STORAGE_KEY},
+    onboarding::{self, ONBOARDING_COMPLETED_STORAGE_KEY},
+    p2p_transport::{self, AddonP2PTransport},
+    palettes,
+    prefetch::PrefetchTransport,
+    push_transport::AddonPushTransport,
+    reminders::{self, Reminder, REMINDERS_STORAGE_KEY},
+    remote_config::{self, RemoteConfig, DISMISSED_ANNOUNCEMENTS_STORAGE_KEY},
+    request_tracing, response_limits,
+    retry::RetryTransport,
+    rewatch::{self, Rewatch, REWATCHES_STORAGE_KEY},
+    schema_validation::ValidatingTransport,
+    shortcuts::{self, PinnedCatalog, PINNED_CATALOGS_STORAGE_KEY},
+    snooze::{self, Snooze, SNOOZED_ITEMS_STORAGE_KEY},
+    stream_history::{self, PlayedStream, STREAM_HISTORY_STORAGE_KEY},
+    stream_timeouts::StreamTimeoutTransport,
+    streaming_catalogs::StreamingCatalogTransport,
+    subtitles_sync::{self, SubtitlesOffset, SUBTITLES_OFFSETS_STORAGE_KEY},
+    tab_sync,
+    uploaded_subtitles::{self, UploadedSubtitles, UPLOADED_SUBTITLES_STORAGE_KEY},
+    user_ratings::{self, UserRating, USER_RATINGS_STORAGE_KEY},
+    web_settings::{self, WebSettings, WEB_SETTINGS_STORAGE_KEY},
 };
 
+#[cfg(feature = "mock-addon")]
+use crate::mock_addon::MockAddonTransport;
+
 const UNKNOWN_ERROR: &str = "Unknown Error";
 const INSTALLATION_ID_STORAGE_KEY: &str = "installation_id";
+const SELF_CHECK_STORAGE_KEY: &str = "self_check";
+const REMOTE_CONFIG_URL: &str = "https://www.strem.io/remote-config.json";
 
 #[wasm_bindgen]
 extern "C" {
@@ -45,6 +93,8 @@ extern "C" {
     #[wasm_bindgen(catch, js_namespace = ["self"])]
     static shell_version: Option<String>;
     #[wasm_bindgen(catch, js_namespace = ["self"])]
+    static remote_config_url: Option<String>;
+    #[wasm_bindgen(catch, js_namespace = ["self"])]
     async fn get_location_hash() -> Result<JsValue, JsValue>;
     #[wasm_bindgen(catch, js_namespace = ["self"])]
     async fn local_storage_get_item(key: String) -> Result<JsValue, JsValue>;
@@ -56,6 +106,12 @@ extern "C" {
 
 lazy_static! {
     static ref INSTALLATION_ID: RwLock<Option<String>> = Default::default();
+    /// Kept outside of the model, as addon transports are created while the model is being updated
+    static ref STREAMING_SERVER_URL: RwLock<Option<Url>> = Default::default();
+    static ref STORAGE_BACKEND: RwLock<StorageBackend> = Default::default();
+    static ref MEMORY_STORAGE: RwLock<HashMap<String, String>> = Default::default();
+    /// Intervals started in `init`
+    static ref INTERVALS: RwLock<Vec<i32>> = Default::default();
     static ref VISIT_ID: String = hex::encode(WebEnv::random_buffer(10));
     static ref ANALYTICS: Analytics<WebEnv> = Default::default();
     static ref PLAYER_REGEX: Regex =
@@ -79,6 +135,64 @@ struct AnalyticsContext {
     path: String,
 }
 
+thread_local! {
+    /// Resolved when the runtime is destroyed, every future executed by the env is raced against it
+    static CANCELLATION: RefCell<Cancellation> = RefCell::new(Cancellation::new());
+}
+
+struct Cancellation {
+    sender: oneshot::Sender<()>,
+    cancelled: Shared<oneshot::Receiver<()>>,
+}
+
+impl Cancellation {
+    fn new() -> Self {
+        let (sender, receiver) = oneshot::channel();
+        Self {
+            sender,
+            cancelled: receiver.shared(),
+        }
+    }
+}
+
+/// Where the storage of the env is kept, chosen by the shell on initialization
+#[derive(Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize, Debug)]
+#[serde(rename_all = "camelCase")]
+pub enum StorageBackend {
+    /// The local storage of the main thread, accessed through the bridge
+    #[default]
+    LocalStorage,
+    /// Kept in memory only, for environments without a persistent storage
+    Memory,
+}
+
+#[derive(Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct CheckResult {
+    pub ok: bool,
+    pub error: Option<String>,
+}
+
+impl CheckResult {
+    fn from_result(result: Result<(), String>) -> Self {
+        Self {
+            ok: result.is_ok(),
+            error: result.err(),
+        }
+    }
+}
+
+/// Sanity checks of the environment, reported back to the shell once the runtime is initialized
+#[derive(Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct SelfCheck {
+    pub storage_backend: StorageBackend,
+    pub storage: CheckResult,
+    pub fetch: CheckResult,
+    pub web_socket: CheckResult,
+    pub random: CheckResult,
+}
+
 pub enum WebEnv {}
 
 impl WebEnv {
@@ -100,14 +214,102 @@ impl WebEnv {
                     Some(&*INSTALLATION_ID.read().expect("installation id read failed")),
                 )
             })
+            .and_then(|_| {
+                WebEnv::get_storage::<HashSet<String>>(DISMISSED_ANNOUNCEMENTS_STORAGE_KEY)
+            })
+            .map_ok(|dismissed_announcements| {
+                remote_config::set_dismissed_announcements(
+                    dismissed_announcements.unwrap_or_default(),
+                )
+            })
+            .and_then(|_| WebEnv::get_storage::<WebSettings>(WEB_SETTINGS_STORAGE_KEY))
+            .map_ok(|settings| web_settings::set_web_settings(settings.unwrap_or_default()))
+            .and_then(|_| WebEnv::get_storage::<bool>(ONBOARDING_COMPLETED_STORAGE_KEY))
+            .map_ok(|completed| onboarding::set_completed(completed.unwrap_or_default()))
+            .and_then(|_| WebEnv::get_storage::<DeviceProfile>(DEVICE_PROFILE_STORAGE_KEY))
+            .map_ok(device_profile::set_device_profile)
+            .and_then(|_| WebEnv::get_storage::<Vec<Reminder>>(REMINDERS_STORAGE_KEY))
+            .map_ok(|reminders| reminders::set_reminders(reminders.unwrap_or_default()))
+            .and_then(|_| WebEnv::get_storage::<Vec<PinnedCatalog>>(PINNED_CATALOGS_STORAGE_KEY))
+            .map_ok(|pinned_catalogs| {
+                shortcuts::set_pinned_catalogs(pinned_catalogs.unwrap_or_default())
+            })
+            .and_then(|_| {
+                WebEnv::get_storage::<HashMap<String, MetaOverride>>(META_OVERRIDES_STORAGE_KEY)
+            })
+            .map_ok(|meta_overrides| {
+                meta_overrides::set_meta_overrides(meta_overrides.unwrap_or_default())
+            })
+            .and_then(|_| {
+                WebEnv::get_storage::<HashMap<String, Vec<String>>>(LIBRARY_TAGS_STORAGE_KEY)
+            })
+            .map_ok(|library_tags| library_tags::set_library_tags(library_tags.unwrap_or_default()))
+            .and_then(|_| {
+                WebEnv::get_storage::<HashMap<String, SortKeys>>(LIBRARY_SORT_KEYS_STORAGE_KEY)
+            })
+            .map_ok(|sort_keys| library_sort::set_sort_keys(sort_keys.unwrap_or_default()))
+            .and_then(|_| WebEnv::get_storage::<Vec<BlockedItem>>(BLOCKED_ITEMS_STORAGE_KEY))
+            .map_ok(|blocked_items| blocklist::set_blocked_items(blocked_items.unwrap_or_default()))
+            .and_then(|_| WebEnv::get_storage::<Vec<Snooze>>(SNOOZED_ITEMS_STORAGE_KEY))
+            .map_ok(|snoozes| snooze::set_snoozes(snoozes.unwrap_or_default()))
+            .and_then(|_| WebEnv::get_storage::<Vec<Rewatch>>(REWATCHES_STORAGE_KEY))
+            .map_ok(|rewatches| rewatch::set_rewatches(rewatches.unwrap_or_default()))
+            .and_then(|_| WebEnv::get_storage::<NewEpisodesState>(NEW_EPISODES_STORAGE_KEY))
+            .map_ok(|state| new_episodes::set_state(state.unwrap_or_default()))
+            .and_then(|_| {
+                WebEnv::get_storage::<Vec<UploadedSubtitles>>(UPLOADED_SUBTITLES_STORAGE_KEY)
+            })
+            .map_ok(|uploaded| uploaded_subtitles::set_uploaded(uploaded.unwrap_or_default()))
+            .and_then(|_| {
+                WebEnv::get_storage::<HashMap<String, UserRating>>(USER_RATINGS_STORAGE_KEY)
+            })
+            .map_ok(|user_ratings| user_ratings::set_user_ratings(user_ratings.unwrap_or_default()))
+            .and_then(|_| {
+                WebEnv::get_storage::<Vec<SubtitlesOffset>>(SUBTITLES_OFFSETS_STORAGE_KEY)
+            })
+            .map_ok(|offsets| subtitles_sync::set_offsets(offsets.unwrap_or_default()))
+            .and_then(|_| {
+                WebEnv::get_storage::<HashMap<Url, AddonMirrors>>(ADDON_MIRRORS_STORAGE_KEY)
+            })
+            .map_ok(|mirrors| mirrors::set_mirrors(mirrors.unwrap_or_default()))
+            .and_then(|_| WebEnv::get_storage::<LanSync>(LAN_SYNC_STORAGE_KEY))
+            .map_ok(|lan_sync| lan_sync::set_lan_sync(lan_sync.unwrap_or_default()))
+            .and_then(|_| WebEnv::get_storage::<SavedProfileDisplay>(PROFILE_DISPLAY_STORAGE_KEY))
+            .map_ok(account::set_saved_display)
+            .and_then(|_| WebEnv::get_storage::<Vec<AddonUpdate>>(ADDON_UPDATES_STORAGE_KEY))
+            .map_ok(|updates| addon_updates::set_updates(updates.unwrap_or_default()))
+            .and_then(|_| WebEnv::get_storage::<Vec<PlayedStream>>(STREAM_HISTORY_STORAGE_KEY))
+            .map_ok(|played_streams| {
+                stream_history::set_played_streams(played_streams.unwrap_or_default())
+            })
             .inspect_ok(|_| {
-                WebEnv::set_interval(
+                let analytics_interval_id = WebEnv::set_interval(
                     || WebEnv::exec_concurrent(WebEnv::send_next_analytics_batch()),
                     30 * 1000,
                 );
+                let background_interval_id = WebEnv::set_interval(
+                    || {
+                        crate::stremio_core_web::run_background_tasks(background::take_due_tasks(
+                            WebEnv::now(),
+                        ))
+                    },
+                    background::TICK_INTERVAL,
+                );
+                *INTERVALS.write().expect("intervals write failed") =
+                    vec![analytics_interval_id, background_interval_id];
             })
             .boxed_local()
     }
+    /// Cancels the futures which are still running and stops the intervals started in `init`
+    pub fn teardown() {
+        CANCELLATION.with(|cancellation| {
+            let cancellation = cancellation.replace(Cancellation::new());
+            let _ = cancellation.sender.send(());
+        });
+        for interval_id in INTERVALS.write().expect("intervals write failed").drain(..) {
+            WebEnv::clear_interval(interval_id);
+        }
+    }
     pub fn get_location_hash() -> EnvFuture<'static, String> {
         get_location_hash()
             .map(|location_hash| {
@@ -118,6 +320,149 @@ impl WebEnv {
             })
             .boxed_env()
     }
+    pub fn app_version() -> String {
+        app_version.to_owned()
+    }
+    pub fn shell_version() -> Option<String> {
+        shell_version.to_owned()
+    }
+    pub fn user_agent() -> Option<String> {
+        global().navigator().user_agent().ok()
+    }
+    pub fn installation_id() -> Option<String> {
+        INSTALLATION_ID
+            .read()
+            .expect("installation id read failed")
+            .to_owned()
+    }
+    pub fn streaming_server_url() -> Option<Url> {
+        STREAMING_SERVER_URL
+            .read()
+            .expect("streaming server url read failed")
+            .to_owned()
+    }
+    pub fn storage_backend() -> StorageBackend {
+        *STORAGE_BACKEND.read().expect("storage backend read failed")
+    }
+    pub fn set_storage_backend(storage_backend: StorageBackend) {
+        *STORAGE_BACKEND
+            .write()
+            .expect("storage backend write failed") = storage_backend;
+    }
+    /// Writes to and reads back from the storage and checks the globals the env depends on
+    pub fn self_check() -> EnvFuture<'static, SelfCheck> {
+        let value = WebEnv::now().timestamp_millis();
+        // written directly, as the writes of the tabs which are not the leader are not persisted
+        WebEnv::write_storage(SELF_CHECK_STORAGE_KEY.to_owned(), Some(value.to_string()))
+            .and_then(|_| WebEnv::get_storage::<i64>(SELF_CHECK_STORAGE_KEY))
+            .and_then(move |stored_value| {
+                WebEnv::write_storage(SELF_CHECK_STORAGE_KEY.to_owned(), None)
+                    .map_ok(move |_| stored_value == Some(value))
+            })
+            .map(|result| {
+                let storage = match result {
+                    Ok(true) => Ok(()),
+                    Ok(false) => Err("Stored value does not match".to_owned()),
+                    Err(error) => Err(error.message()),
+                };
+                let has_global = |name: &str| {
+                    js_sys::Reflect::has(&global(), &JsValue::from_str(name))
+                        .ok()
+                        .filter(|has| *has)
+                        .map(|_| ())
+                        .ok_or_else(|| format!("{name} is not available"))
+                };
+                SelfCheck {
+                    storage_backend: WebEnv::storage_backend(),
+                    storage: CheckResult::from_result(storage),
+                    fetch: CheckResult::from_result(has_global("fetch")),
+                    web_socket: CheckResult::from_result(has_global("WebSocket")),
+                    random: CheckResult::from_result(
+                        getrandom::getrandom(&mut [0u8; 1]).map_err(|error| error.to_string()),
+                    ),
+                }
+            })
+            .boxed_env()
+    }
+    /// Writes the serialized value to the storage backend, `None` removes the key
+    pub fn write_storage(key: String, value: Option<String>) -> TryEnvFuture<()> {
+        if WebEnv::storage_backend() == StorageBackend::Memory {
+            let mut storage = MEMORY_STORAGE.write().expect("memory storage write failed");
+            match value {
+                Some(value) => storage.insert(key, value),
+                None => storage.remove(&key),
+            };
+            return future::ok(()).boxed_local();
+        }
+        let to_storage_error = |error: JsValue| {
+            EnvError::StorageWriteError(
+                error
+                    .dyn_into::<js_sys::Error>()
+                    .map(|error| String::from(error.message()))
+                    .unwrap_or_else(|_| UNKNOWN_ERROR.to_owned()),
+            )
+        };
+        match value {
+            Some(value) => local_storage_set_item(key, value)
+                .map_err(to_storage_error)
+                .boxed_local(),
+            None => local_storage_remove_item(key)
+                .map_err(to_storage_error)
+                .boxed_local(),
+        }
+    }
+    pub fn set_streaming_server_url(url: Option<Url>) {
+        *STREAMING_SERVER_URL
+            .write()
+            .expect("streaming server url write failed") = url;
+    }
+    /// Fetches the remote config, falling back to the default endpoint
+    /// when the shell did not provide one.
+    pub fn fetch_remote_config() -> TryEnvFuture<RemoteConfig> {
+        let url = remote_config_url
+            .to_owned()
+            .unwrap_or_else(|| REMOTE_CONFIG_URL.to_owned());
+        let request = Request::get(url).body(()).expect("request builder failed");
+        WebEnv::fetch::<_, RemoteConfig>(request)
+    }
+    /// Unlike `fetch`, the fields are sent url encoded and the response of any status is parsed,
+    /// for the APIs which describe their errors in the body of the non 2xx responses.
+    /// Empty responses, e.g. `204 No Content`, are parsed as `null`.
+    pub fn fetch_form(
+        method: Method,
+        url: &Url,
+        headers: &[(&str, &str)],
+        form: &[(&str, &str)],
+    ) -> TryEnvFuture<(u16, serde_json::Value)> {
+        let mut headers = headers.iter().copied().collect::<HashMap<_, _>>();
+        let body = (!form.is_empty()).then(|| {
+            headers.insert("content-type", "application/x-www-form-urlencoded");
+            url::form_urlencoded::Serializer::new(String::new())
+                .extend_pairs(form)
+                .finish()
+        });
+        fetch_any_status(method, url, headers, body)
+            .map_ok(|(status, _, body)| (status, body))
+            .boxed_local()
+    }
+    /// Like `fetch_form`, for the JSON APIs with conditional requests.
+    /// The `ETag` of the response is returned along with its status and its body.
+    pub fn fetch_versioned<T: Serialize>(
+        method: Method,
+        url: &Url,
+        headers: &[(&str, &str)],
+        body: Option<&T>,
+    ) -> TryEnvFuture<(u16, Option<String>, serde_json::Value)> {
+        let mut headers = headers.iter().copied().collect::<HashMap<_, _>>();
+        let body = match body.map(serde_json::to_string).transpose() {
+            Ok(body) => body,
+            Err(error) => return future::err(EnvError::from(error)).boxed_local(),
+        };
+        if body.is_some() {
+            headers.insert("content-type", "application/json");
+        }
+        fetch_any_status(method, url, headers, body)
+    }
     pub fn emit_to_analytics(event: &WebEvent, model: &WebModel, path: &str) {
         let (name, data) = match event {
             WebEvent::UIEvent(UIEvent::LocationPathChanged { prev_path }) => (
@@ -134,6 +479,13 @@ impl WebEnv {
             WebEvent::UIEvent(UIEvent::Share { url }) => {
                 ("share".to_owned(), json!({ "url": url }))
             }
+            WebEvent::ExperimentExposure {
+                experiment_id,
+                variant,
+            } => (
+                "experimentExposure".to_owned(),
+                json!({ "experimentID": experiment_id, "variant": variant }),
+            ),
             WebEvent::UIEvent(UIEvent::StreamClicked { stream }) => (
                 "streamClicked".to_owned(),
                 json!({
@@ -249,7 +601,12 @@ impl WebEnv {
         func.forget();
         interval_id
     }
-    #[allow(dead_code)]
+    pub fn set_timeout<F: FnOnce() + 'static>(func: F, timeout: i32) -> i32 {
+        let func = Closure::once_into_js(func);
+        global()
+            .set_timeout_with_callback_and_timeout_and_arguments_0(func.unchecked_ref(), timeout)
+            .expect("set timeout failed")
+    }
     pub fn clear_interval(id: i32) {
         global().clear_interval_with_handle(id);
     }
@@ -258,6 +615,120 @@ impl WebEnv {
         getrandom::getrandom(buffer.as_mut_slice()).expect("generate random buffer failed");
         buffer
     }
+    /// Fetches the body of a GET request chunk by chunk as it arrives,
+    /// instead of waiting for the whole response.
+    /// Sent with the same headers, limiter, cancellation and size limit as the requests of `fetch`,
+    /// resolves with whether the whole body was read or it was cut off at the size limit.
+    pub fn fetch_chunks<F: FnMut(&[u8]) + 'static>(url: &Url, on_chunk: F) -> TryEnvFuture<bool> {
+        let url = url.to_string();
+        let max_size = response_limits::max_size(&url);
+        let method = Method::GET.as_str();
+        let request_id = request_tracing::start(method, &url);
+        let headers = request_headers(&url, &HeaderMap::new());
+        let (request, signal, in_flight_id) = cancellable_request(method, &url, &headers, None);
+        let permit = fetch_limiter::acquire(&url);
+        async move {
+            // the slot is held until the whole body is read
+            let _permit = permit.await;
+            let result = async {
+                let resp = JsFuture::from(global().fetch_with_request(&request))
+                    .await
+                    .map_err(fetch_error)?
+                    .dyn_into::<web_sys::Response>()
+                    .unwrap();
+                if resp.status() != 200 {
+                    return Err(EnvError::Fetch(format!(
+                        "Unexpected HTTP status code {}",
+                        resp.status(),
+                    )));
+                }
+                read_body(&resp, max_size, on_chunk).await
+            }
+            .await;
+            load_cancellation::finish(in_flight_id);
+            result
+        }
+        .map_err(move |error| {
+            if signal.aborted() {
+                EnvError::Fetch(load_cancellation::CANCELLED_ERROR.to_owned())
+            } else {
+                request_tracing::fail(&request_id, &url, error)
+            }
+        })
+        .boxed_local()
+    }
+    /// Fetches an image and returns its RGBA pixels, scaled down to a square of the given size.
+    /// The image is decoded and drawn on an offscreen canvas of the worker.
+    /// The copy cached by the browser when the UI showed the image is preferred over the network.
+    pub fn fetch_image_pixels(url: &Url, size: u32) -> TryEnvFuture<Vec<u8>> {
+        let url = url.to_string();
+        let request_id = request_tracing::start(Method::GET.as_str(), &url);
+        let mut request_options = web_sys::RequestInit::new();
+        request_options
+            .mode(web_sys::RequestMode::Cors)
+            .cache(web_sys::RequestCache::ForceCache);
+        let request = web_sys::Request::new_with_str_and_init(&url, &request_options)
+            .expect("request builder failed");
+        let permit = fetch_limiter::acquire(&url);
+        async move {
+            let _permit = permit.await;
+            // a fetch rejected before a response is mostly a host without cross-origin headers
+            let resp = JsFuture::from(global().fetch_with_request(&request))
+                .await
+                .map_err(|_| EnvError::Fetch(palettes::UNREADABLE_IMAGE_ERROR.to_owned()))?
+                .dyn_into::<web_sys::Response>()
+                .unwrap();
+            if resp.status() != 200 {
+                return Err(EnvError::Fetch(format!(
+                    "Unexpected HTTP status code {}",
+                    resp.status(),
+                )));
+            }
+            let blob = JsFuture::from(resp.blob().map_err(fetch_error)?)
+                .await
+                .map_err(fetch_error)?;
+            let options = js_sys::Object::new();
+            js_sys::Reflect::set(&options, &"resizeWidth".into(), &size.into())
+                .map_err(fetch_error)?;
+            js_sys::Reflect::set(&options, &"resizeHeight".into(), &size.into())
+                .map_err(fetch_error)?;
+            let bitmap = call_method(
+                &global(),
+                "createImageBitmap",
+                &js_sys::Array::of2(&blob, &options),
+            )?;
+            let bitmap = JsFuture::from(bitmap.dyn_into::<js_sys::Promise>().map_err(fetch_error)?)
+                .await
+                .map_err(fetch_error)?;
+            let offscreen_canvas =
+                js_sys::Reflect::get(&global(), &JsValue::from_str("OffscreenCanvas"))
+                    .map_err(fetch_error)?
+                    .dyn_into::<js_sys::Function>()
+                    .map_err(fetch_error)?;
+            let canvas = js_sys::Reflect::construct(
+                &offscreen_canvas,
+                &js_sys::Array::of2(&size.into(), &size.into()),
+            )
+            .map_err(fetch_error)?;
+            let context = call_method(&canvas, "getContext", &js_sys::Array::of1(&"2d".into()))?;
+            call_method(
+                &context,
+                "drawImage",
+                &js_sys::Array::of3(&bitmap, &0.into(), &0.into()),
+            )?;
+            call_method(&bitmap, "close", &js_sys::Array::new())?;
+            let image_data = call_method(
+                &context,
+                "getImageData",
+                &js_sys::Array::of4(&0.into(), &0.into(), &size.into(), &size.into()),
+            )?;
+            let data = js_sys::Reflect::get(&image_data, &JsValue::from_str("data"))
+                .map_err(fetch_error)?;
+            Ok(js_sys::Uint8Array::new(&data).to_vec())
+        }
+        .map_err(move |error| request_tracing::fail(&request_id, &url, error))
+        .boxed_local()
+    }
 }
 
 impl Env for WebEnv {
@@ -269,30 +740,21 @@ impl Env for WebEnv {
         let (parts, body) = request.into_parts();
         let url = parts.uri.to_string();
         let method = parts.method.as_str();
-        let headers = {
-            let mut headers = HashMap::new();
-            for (key, value) in parts.headers.iter() {
-                let key = key.as_str().to_owned();
-                let value = String::from_utf8_lossy(value.as_bytes()).into_owned();
-                headers.entry(key).or_insert_with(Vec::new).push(value);
-            }
-            JsValue::from_serde(&headers).unwrap()
-        };
+        let request_id = request_tracing::start(method, &url);
+        let headers = request_headers(&url, &parts.headers);
         let body = match serde_json::to_string(&body) {
             Ok(ref body) if body != "null" && parts.method != Method::GET => {
                 Some(JsValue::from_str(body))
             }
             _ => None,
         };
-        let mut request_options = web_sys::RequestInit::new();
-        request_options
-            .method(method)
-            .headers(&headers)
-            .body(body.as_ref());
-        let request = web_sys::Request::new_with_str_and_init(&url, &request_options)
-            .expect("request builder failed");
-        let promise = global().fetch_with_request(&request);
-        JsFuture::from(promise)
+        let (request, signal, in_flight_id) =
+            cancellable_request(method, &url, &headers, body.as_ref());
+        let max_size = response_limits::max_size(&url);
+        let response_url = url.to_owned();
+        // the request is sent once the limiter lets it through
+        let response = future::lazy(move |_| global().fetch_with_request(&request))
+            .then(JsFuture::from)
             .map_err(|error| {
                 EnvError::Fetch(
                     error
@@ -301,22 +763,51 @@ impl Env for WebEnv {
                         .unwrap_or_else(|_| UNKNOWN_ERROR.to_owned()),
                 )
             })
-            .and_then(|resp| {
+            .and_then(move |resp| {
                 let resp = resp.dyn_into::<web_sys::Response>().unwrap();
+                let to_fetch_error = |error: JsValue| {
+                    EnvError::Fetch(
+                        error
+                            .dyn_into::<js_sys::Error>()
+                            .map(|error| String::from(error.message()))
+                            .unwrap_or_else(|_| UNKNOWN_ERROR.to_owned()),
+                    )
+                };
                 if resp.status() != 200 {
-                    Either::Right(future::err(EnvError::Fetch(format!(
+                    future::err(EnvError::Fetch(format!(
                         "Unexpected HTTP status code {}",
                         resp.status(),
-                    ))))
+                    )))
+                    .boxed_local()
+                } else if let Some(max_size) = max_size {
+                    // the oversized responses are read up to a point to be truncated,
+                    // the larger ones fail without being downloaded
+                    let max_read_size = response_limits::max_read_size(max_size);
+                    match content_length(&resp) {
+                        Some(size) if size > max_read_size => {
+                            future::err(response_limits::too_large(max_size)).boxed_local()
+                        }
+                        _ => async move {
+                            let mut body = vec![];
+                            let is_complete = read_body(&resp, Some(max_read_size), |chunk| {
+                                body.extend_from_slice(chunk)
+                            })
+                            .await?;
+                            if !is_complete {
+                                return Err(response_limits::too_large(max_size));
+                            }
+                            response_limits::parse(
+                                &response_url,
+                                String::from_utf8_lossy(&body).into_owned(),
+                                max_size,
+                            )
+                        }
+                        .boxed_local(),
+                    }
                 } else {
-                    Either::Left(JsFuture::from(resp.json().unwrap()).map_err(|error| {
-                        EnvError::Fetch(
-                            error
-                                .dyn_into::<js_sys::Error>()
-                                .map(|error| String::from(error.message()))
-                                .unwrap_or_else(|_| UNKNOWN_ERROR.to_owned()),
-                        )
-                    }))
+                    JsFuture::from(resp.json().unwrap())
+                        .map_err(to_fetch_error)
+                        .boxed_local()
                 }
             })
             .and_then(|resp| {
@@ -344,6 +835,21 @@ impl Env for WebEnv {
                         future::ready(resp.into_serde().map_err(EnvError::from))
                     }
                 }
+            });
+        fetch_limiter::acquire(&url)
+            .then(move |permit| {
+                response.map(move |result| {
+                    drop(permit);
+                    load_cancellation::finish(in_flight_id);
+                    result
+                })
+            })
+            .map_err(move |error| {
+                if signal.aborted() {
+                    EnvError::Fetch(load_cancellation::CANCELLED_ERROR.to_owned())
+                } else {
+                    request_tracing::fail(&request_id, &url, error)
+                }
             })
             .boxed_local()
     }
@@ -351,6 +857,20 @@ impl Env for WebEnv {
     where
         for<'de> T: Deserialize<'de> + 'static,
     {
+        if WebEnv::storage_backend() == StorageBackend::Memory {
+            let value = MEMORY_STORAGE
+                .read()
+                .expect("memory storage read failed")
+                .get(key)
+                .cloned();
+            return future::ready(
+                value
+                    .map(|value| serde_json::from_str(&value))
+                    .transpose()
+                    .map_err(EnvError::from),
+            )
+            .boxed_local();
+        }
         local_storage_get_item(key.to_owned())
             .map_err(|error| {
                 EnvError::StorageReadError(
@@ -370,44 +890,52 @@ impl Env for WebEnv {
             .boxed_local()
     }
     fn set_storage<T: Serialize>(key: &str, value: Option<&T>) -> TryEnvFuture<()> {
-        let key = key.to_owned();
-        match value {
-            Some(value) => future::ready(serde_json::to_string(value))
-                .map_err(EnvError::from)
-                .and_then(|value| {
-                    local_storage_set_item(key, value).map_err(|error| {
-                        EnvError::StorageWriteError(
-                            error
-                                .dyn_into::<js_sys::Error>()
-                                .map(|error| String::from(error.message()))
-                                .unwrap_or_else(|_| UNKNOWN_ERROR.to_owned()),
-                        )
-                    })
-                })
-                .boxed_local(),
-            None => local_storage_remove_item(key)
-                .map_err(|error| {
-                    EnvError::StorageWriteError(
-                        error
-                            .dyn_into::<js_sys::Error>()
-                            .map(|error| String::from(error.message()))
-                            .unwrap_or_else(|_| UNKNOWN_ERROR.to_owned()),
-                    )
-                })
-                .boxed_local(),
+        let value = match value.map(serde_json::to_string).transpose() {
+            Ok(value) => value,
+            Err(error) => return future::err(EnvError::from(error)).boxed_local(),
+        };
+        if !tab_sync::is_leader() {
+            // buffered until the leader is elected, dropped when another tab is the leader
+            tab_sync::buffer_write(key, value);
+            return future::ok(()).boxed_local();
         }
+        WebEnv::write_storage(key.to_owned(), value)
+    }
+    fn addon_transport(transport_url: &Url) -> Box<dyn AddonTransport> {
+        let transport: Box<dyn AddonTransport> = match transport_url.scheme() {
+            "ws" | "wss" => Box::new(AddonPushTransport::new(transport_url.to_owned())),
+            #[cfg(feature = "mock-addon")]
+            "mock" => Box::new(MockAddonTransport::new(transport_url.to_owned())),
+            _ if p2p_transport::is_p2p_transport_url(transport_url) => {
+                Box::new(AddonP2PTransport::new(transport_url.to_owned()))
+            }
+            _ => Box::new(RetryTransport::new(
+                transport_url.to_owned(),
+                Box::new(MirrorTransport::new(
+                    transport_url.to_owned(),
+                    http_transport,
+                )),
+            )),
+        };
+        Box::new(StreamTimeoutTransport::new(
+            transport_url.to_owned(),
+            Box::new(CatalogCacheTransport::new(
+                transport_url.to_owned(),
+                Box::new(PrefetchTransport::new(transport_url.to_owned(), transport)),
+            )),
+        ))
     }
     fn exec_concurrent<F>(future: F)
     where
         F: Future<Output = ()> + 'static,
     {
-        spawn_local(future)
+        spawn_local(cancellable(effect(future)))
     }
     fn exec_sequential<F>(future: F)
     where
         F: Future<Output = ()> + 'static,
     {
-        spawn_local(future)
+        spawn_local(cancellable(effect(future)))
     }
     fn now() -> DateTime<Utc> {
         let msecs = js_sys::Date::now() as i64;
@@ -455,6 +983,25 @@ impl Env for WebEnv {
     }
 }
 
+/// The transport of an http addon, built for its transport url and for every one of its mirrors
+fn http_transport(transport_url: &Url) -> Box<dyn AddonTransport> {
+    let transport = Box::new(AddonHTTPTransport::<WebEnv>::new(transport_url.to_owned()));
+    if web_settings::web_settings()
+        .developer
+        .validate_addon_responses
+    {
+        Box::new(ValidatingTransport::new(
+            transport_url.to_owned(),
+            transport,
+        ))
+    } else {
+        Box::new(StreamingCatalogTransport::new(
+            transport_url.to_owned(),
+            transport,
+        ))
+    }
+}
+
 fn sanitize_location_path(path: &str) -> String {
     match Url::parse(&format!("stremio://{}", path)) {
         Ok(url) => {
@@ -488,8 +1035,167 @@ fn sanitize_location_path(path: &str) -> String {
     }
 }
 
+fn cancellable<F>(future: F) -> impl Future<Output = ()>
+where
+    F: Future<Output = ()> + 'static,
+{
+    let cancelled = CANCELLATION.with(|cancellation| cancellation.borrow().cancelled.clone());
+    future::select(future.boxed_local(), cancelled).map(|_| ())
+}
+
+/// The future of an effect, attributed to the action and the field of the dispatch it was
+/// spawned by
+fn effect<F>(future: F) -> impl Future<Output = ()>
+where
+    F: Future<Output = ()> + 'static,
+{
+    event_sequence::with_action_id(load_cancellation::with_spawning_field(future))
+}
+
+/// The headers of a request, along with the device profile of the streams created
+/// on the streaming server
+fn request_headers(url: &str, headers: &HeaderMap) -> JsValue {
+    let mut request_headers = HashMap::new();
+    if let Some(device_profile) =
+        device_profile::stream_creation_header(url, WebEnv::streaming_server_url().as_ref())
+    {
+        request_headers.insert(DEVICE_PROFILE_HEADER.to_owned(), vec![device_profile]);
+    }
+    for (key, value) in headers.iter() {
+        let key = key.as_str().to_owned();
+        let value = String::from_utf8_lossy(value.as_bytes()).into_owned();
+        request_headers
+            .entry(key)
+            .or_insert_with(Vec::new)
+            .push(value);
+    }
+    JsValue::from_serde(&request_headers).unwrap()
+}
+
+/// A request which is aborted when the selection of the model which loads it changes,
+/// along with its signal and its id in the requests in flight
+fn cancellable_request(
+    method: &str,
+    url: &str,
+    headers: &JsValue,
+    body: Option<&JsValue>,
+) -> (web_sys::Request, web_sys::AbortSignal, u64) {
+    let controller = web_sys::AbortController::new().expect("abort controller failed");
+    let signal = controller.signal();
+    let mut request_options = web_sys::RequestInit::new();
+    request_options
+        .method(method)
+        .headers(headers)
+        .body(body)
+        .signal(Some(&signal));
+    let request = web_sys::Request::new_with_str_and_init(url, &request_options)
+        .expect("request builder failed");
+    let in_flight_id = load_cancellation::start(url, move || controller.abort());
+    (request, signal, in_flight_id)
+}
+
+/// Calls a method of a JS object which has no binding in `web_sys`
+fn call_method(target: &JsValue, name: &str, args: &js_sys::Array) -> Result<JsValue, EnvError> {
+    js_sys::Reflect::get(target, &JsValue::from_str(name))
+        .and_then(|method| method.dyn_into::<js_sys::Function>().map_err(JsValue::from))
+        .and_then(|method| method.apply(target, args))
+        .map_err(fetch_error)
+}
+
 fn global() -> WorkerGlobalScope {
     js_sys::global()
         .dyn_into::<WorkerGlobalScope>()
         .expect("worker global scope is not available")
 }
+
+/// The response of any status parsed as JSON along with its `ETag`, empty ones as `null`
+fn fetch_any_status(
+    method: Method,
+    url: &Url,
+    headers: HashMap<&str, &str>,
+    body: Option<String>,
+) -> TryEnvFuture<(u16, Option<String>, serde_json::Value)> {
+    let body = body.map(|body| JsValue::from_str(&body));
+    let mut request_options = web_sys::RequestInit::new();
+    request_options
+        .method(method.as_str())
+        .headers(&JsValue::from_serde(&headers).unwrap())
+        .body(body.as_ref());
+    let request = web_sys::Request::new_with_str_and_init(url.as_str(), &request_options)
+        .expect("request builder failed");
+    JsFuture::from(global().fetch_with_request(&request))
+        .map_err(fetch_error)
+        .and_then(move |resp| {
+            let resp = resp.dyn_into::<web_sys::Response>().unwrap();
+            let status = resp.status();
+            let etag = resp.headers().get("etag").ok().flatten();
+            JsFuture::from(resp.text().unwrap())
+                .map_err(fetch_error)
+                .map_ok(move |text| (status, etag, text.as_string().unwrap_or_default()))
+        })
+        .and_then(|(status, etag, text)| {
+            let body = if text.trim().is_empty() {
+                Ok(serde_json::Value::Null)
+            } else {
+                serde_json::from_str(&text).map_err(|error| EnvError::Fetch(error.to_string()))
+            };
+            future::ready(body.map(|body| (status, etag, body)))
+        })
+        .boxed_local()
+}
+
+/// Reads the body chunk by chunk, the bytes over the size are not downloaded.
+/// Resolves with whether the whole body was read.
+async fn read_body(
+    resp: &web_sys::Response,
+    max_size: Option<usize>,
+    mut on_chunk: impl FnMut(&[u8]),
+) -> Result<bool, EnvError> {
+    let reader = match resp.body() {
+        Some(body) => body
+            .get_reader()
+            .unchecked_into::<web_sys::ReadableStreamDefaultReader>(),
+        None => return Ok(true),
+    };
+    let mut remaining = max_size.unwrap_or(usize::MAX);
+    loop {
+        let result = JsFuture::from(reader.read()).await.map_err(fetch_error)?;
+        // the result of a read has no getters in web_sys
+        let done = js_sys::Reflect::get(&result, &JsValue::from_str("done"))
+            .ok()
+            .and_then(|done| done.as_bool())
+            .unwrap_or(true);
+        if done {
+            return Ok(true);
+        }
+        let value =
+            js_sys::Reflect::get(&result, &JsValue::from_str("value")).map_err(fetch_error)?;
+        let chunk = js_sys::Uint8Array::new(&value).to_vec();
+        if chunk.len() > remaining {
+            on_chunk(&chunk[..remaining]);
+            let _ = reader.cancel();
+            return Ok(false);
+        }
+        remaining -= chunk.len();
+        on_chunk(&chunk);
+    }
+}
+
+/// The size of the body announced by the response, if any
+fn content_length(resp: &web_sys::Response) -> Option<usize> {
+    resp.headers()
+        .get("content-length")
+        .ok()
+        .flatten()?
+        .parse()
+        .ok()
+}
+
+fn fetch_error(error: JsValue) -> EnvError {
+    EnvError::Fetch(
+        error
+            .dyn_into::<js_sys::Error>()
+            .map(|error| String::from(error.message()))
+            .unwrap_or_else(|_| UNKNOWN_ERROR.to_owned()),
+    )
+}
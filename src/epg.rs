@@ -0,0 +1,153 @@
+use std::sync::RwLock;
+
+use chrono::{DateTime, Duration, Utc};
+use lazy_static::lazy_static;
+
+use stremio_core::{
+    models::common::Loadable,
+    types::{
+        addon::{ResourcePath, ResourceRequest},
+        resource::{MetaItem, MetaItemPreview, Video},
+    },
+};
+
+use crate::loads::Loads;
+
+/// Meta types of the channel catalogs, their videos are the programs of the channel
+pub const CHANNEL_TYPES: [&str; 2] = ["tv", "channel"];
+/// Maximum number of channels of a catalog for which the guide is loaded
+pub const MAX_CHANNELS: usize = 50;
+/// Maximum number of channels for which the guide is kept
+const MAX_GUIDES: usize = 2 * MAX_CHANNELS;
+/// Seconds between the updates of the progress of the programs on air
+pub const CLOCK_INTERVAL: i32 = 60;
+
+lazy_static! {
+    static ref GUIDES: RwLock<Loads<ResourceRequest, MetaItem>> = Default::default();
+    static ref CLOCK: RwLock<GuideClock> = Default::default();
+}
+
+/// The time the programs on air are serialized for, it's advanced when a program ends
+/// so the serialized guides only change along with the state of Discover
+#[derive(Default)]
+struct GuideClock {
+    now: Option<DateTime<Utc>>,
+    /// The next advance which is scheduled
+    next: Option<DateTime<Utc>>,
+}
+
+/// A program of the guide, it lasts until the next one starts
+pub struct Program<'a> {
+    pub video: &'a Video,
+    pub start: DateTime<Utc>,
+    pub stop: Option<DateTime<Utc>>,
+}
+
+pub fn is_channel_catalog(request: &ResourceRequest) -> bool {
+    CHANNEL_TYPES.contains(&request.path.r#type.as_str())
+}
+
+/// The request of the meta item holding the guide of the channel
+pub fn guide_request(
+    catalog_request: &ResourceRequest,
+    channel: &MetaItemPreview,
+) -> ResourceRequest {
+    ResourceRequest::new(
+        catalog_request.base.to_owned(),
+        ResourcePath::without_extra("meta", &channel.r#type, &channel.id),
+    )
+}
+
+pub fn guide(request: &ResourceRequest) -> Option<Loadable<MetaItem, String>> {
+    GUIDES
+        .read()
+        .expect("guides read failed")
+        .get(request)
+        .cloned()
+}
+
+/// Marks the guides which are not loaded yet, or which are due for a retry, as loading
+/// and returns their requests
+pub fn start_loading(requests: Vec<ResourceRequest>, now: DateTime<Utc>) -> Vec<ResourceRequest> {
+    let mut guides = GUIDES.write().expect("guides write failed");
+    let requests = guides.start_loading(requests, now);
+    guides.truncate(MAX_GUIDES);
+    requests
+}
+
+pub fn set_guide(request: &ResourceRequest, result: Result<MetaItem, String>, now: DateTime<Utc>) {
+    GUIDES
+        .write()
+        .expect("guides write failed")
+        .set(request, result, now);
+}
+
+/// The time the programs on air are serialized for
+pub fn clock() -> Option<DateTime<Utc>> {
+    CLOCK.read().expect("guide clock read failed").now
+}
+
+/// Moves the clock of the guides to now, returns when it has to be advanced next
+/// unless an earlier advance is scheduled already
+pub fn advance_clock(now: DateTime<Utc>) -> Option<DateTime<Utc>> {
+    let next_program = GUIDES
+        .read()
+        .expect("guides read failed")
+        .ready()
+        .flat_map(|(_, meta_item)| meta_item.videos.iter())
+        .filter_map(|video| video.released)
+        .filter(|start| *start > now)
+        .min();
+    let tick = now + Duration::seconds(CLOCK_INTERVAL.into());
+    let next = next_program.map_or(tick, |next_program| next_program.min(tick));
+    let mut clock = CLOCK.write().expect("guide clock write failed");
+    clock.now = Some(now);
+    match clock.next {
+        Some(scheduled) if scheduled > now && scheduled <= next => None,
+        _ => {
+            clock.next = Some(next);
+            Some(next)
+        }
+    }
+}
+
+pub fn len() -> usize {
+    GUIDES.read().expect("guides read failed").len()
+}
+
+/// Drops the loaded guides, they are loaded again when their channels are shown
+pub fn clear() {
+    GUIDES.write().expect("guides write failed").clear();
+    *CLOCK.write().expect("guide clock write failed") = Default::default();
+}
+
+/// The program on air and the one after it, programs without a start time are ignored
+pub fn now_next(
+    meta_item: &MetaItem,
+    now: DateTime<Utc>,
+) -> (Option<Program<'_>>, Option<Program<'_>>) {
+    let mut programs = meta_item
+        .videos
+        .iter()
+        .filter_map(|video| video.released.map(|start| (video, start)))
+        .collect::<Vec<_>>();
+    programs.sort_by_key(|(_, start)| *start);
+    let programs = programs
+        .iter()
+        .enumerate()
+        .map(|(index, (video, start))| Program {
+            video: *video,
+            start: *start,
+            stop: programs.get(index + 1).map(|(_, stop)| *stop),
+        })
+        .collect::<Vec<_>>();
+    let next_index = programs
+        .iter()
+        .position(|program| program.start > now)
+        .unwrap_or(programs.len());
+    let mut programs = programs.into_iter().skip(next_index.saturating_sub(1));
+    match next_index {
+        0 => (None, programs.next()),
+        _ => (programs.next(), programs.next()),
+    }
+}
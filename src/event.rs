@@ -1,6 +1,12 @@
-use serde::Deserialize;
+use semver::Version;
+use serde::{Deserialize, Serialize};
 use stremio_core::runtime::msg::{Action, Event};
 use stremio_core::types::resource::Stream;
+use url::Url;
+
+use crate::action_validation::ActionRejection;
+use crate::addon_updates::ChangelogEntry;
+use crate::shortcuts::Shortcut;
 
 #[derive(Deserialize)]
 #[serde(tag = "event", content = "args")]
@@ -26,4 +32,35 @@ pub enum WebEvent {
     CoreAction(Box<Action>),
     CoreEvent(Box<Event>),
     UIEvent(UIEvent),
+    ExperimentExposure {
+        experiment_id: String,
+        variant: String,
+    },
+}
+
+/// Events of the state kept outside of the core's model
+#[derive(Serialize)]
+#[serde(tag = "event", content = "args")]
+pub enum WebStateEvent {
+    /// A newer version of an installed addon is available, it is installed
+    /// with the `Install` action of `addonUpdates` once the user confirms it
+    #[serde(rename_all = "camelCase")]
+    AddonUpdateAvailable {
+        transport_url: Url,
+        name: String,
+        version: Version,
+        changelog: Vec<ChangelogEntry>,
+    },
+    /// The pinned catalogs or the top items of Continue Watching changed,
+    /// the `shortcuts` of the web app manifest are to be regenerated
+    ShortcutsChanged(Vec<Shortcut>),
+    /// A dispatched action was dropped as it's invalid for the current state
+    ActionRejected(ActionRejection),
+}
+
+/// Emitted to the UI in the same shape as the runtime events of the core
+#[derive(Serialize)]
+#[serde(tag = "name", content = "args")]
+pub enum WebRuntimeEvent<'a> {
+    WebStateEvent(&'a WebStateEvent),
 }
@@ -0,0 +1,90 @@
+use std::{
+    cell::{Cell, RefCell},
+    future::Future,
+};
+
+use futures::{future, FutureExt};
+use serde::Serialize;
+
+thread_local! {
+    /// Never reset, not even when the runtime is rebuilt
+    static SEQUENCE: Cell<u64> = Cell::new(0);
+    /// The action which is dispatched, or whose effect is polled, at the moment
+    static ACTION_ID: RefCell<Option<String>> = RefCell::new(None);
+}
+
+/// An event emitted to the UI, along with its place in the emissions.
+/// A gap in `seq` means an emission was dropped, a lower one that it arrived out of order.
+#[derive(Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct SequencedEvent<'a, T> {
+    #[serde(flatten)]
+    pub event: &'a T,
+    pub seq: u64,
+    /// The action which caused the change, the changes made by its effects later on carry it
+    /// as well. `None` for the changes which no action caused, e.g. the timers.
+    pub action_id: Option<String>,
+}
+
+/// Takes the next sequence number for the event
+pub fn sequenced<T: Serialize>(event: &T) -> SequencedEvent<'_, T> {
+    let seq = SEQUENCE.with(|sequence| {
+        sequence.set(sequence.get() + 1);
+        sequence.get()
+    });
+    SequencedEvent {
+        event,
+        seq,
+        action_id: action_id(),
+    }
+}
+
+/// The id of the action which is dispatched, or whose effect is polled, at the moment
+pub fn action_id() -> Option<String> {
+    ACTION_ID.with(|action_id| action_id.borrow().to_owned())
+}
+
+/// Dispatches an action, the events emitted meanwhile carry its id
+pub fn dispatched<R>(action_id: String, dispatch: impl FnOnce() -> R) -> R {
+    let previous = ACTION_ID.with(|current| current.replace(Some(action_id)));
+    let result = dispatch();
+    ACTION_ID.with(|current| *current.borrow_mut() = previous);
+    result
+}
+
+/// The future of an effect, the events emitted while it's polled carry the id of the action
+/// it was spawned by, the ones of other actions may have been dispatched in between
+pub fn with_action_id<F: Future + 'static>(future: F) -> impl Future<Output = F::Output> {
+    let action_id = ACTION_ID.with(|current| current.borrow().to_owned());
+    let mut future = future.boxed_local();
+    future::poll_fn(move |cx| {
+        let previous = ACTION_ID.with(|current| current.replace(action_id.to_owned()));
+        let poll = future.poll_unpin(cx);
+        ACTION_ID.with(|current| *current.borrow_mut() = previous);
+        poll
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use futures::executor::block_on;
+
+    use super::*;
+
+    fn sequenced_action_id() -> Option<String> {
+        sequenced(&()).action_id
+    }
+
+    #[test]
+    fn effects_carry_the_action_id() {
+        let effect = dispatched("first".to_owned(), || {
+            assert_eq!(sequenced_action_id().as_deref(), Some("first"));
+            assert_eq!(action_id().as_deref(), Some("first"));
+            with_action_id(async { sequenced_action_id() })
+        });
+        assert_eq!(sequenced_action_id(), None);
+        let second = dispatched("second".to_owned(), || block_on(effect));
+        assert_eq!(second.as_deref(), Some("first"));
+        assert_eq!(sequenced_action_id(), None);
+    }
+}
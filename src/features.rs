@@ -0,0 +1,152 @@
+use std::collections::{HashMap, HashSet};
+use std::sync::RwLock;
+
+use lazy_static::lazy_static;
+use serde::Deserialize;
+
+use stremio_core::types::profile::Profile;
+
+use crate::env::WebEnv;
+
+pub const ANNOUNCEMENTS_FEATURE: &str = "announcements";
+
+/// Local defaults, used until (or when) the remote config doesn't override them.
+const DEFAULT_FEATURES: &[(&str, bool)] = &[(ANNOUNCEMENTS_FEATURE, true)];
+
+lazy_static! {
+    static ref FEATURES: RwLock<HashMap<String, bool>> = RwLock::new(
+        DEFAULT_FEATURES
+            .iter()
+            .map(|(name, enabled)| (name.to_string(), *enabled))
+            .collect()
+    );
+    static ref EXPERIMENTS: RwLock<Vec<Experiment>> = Default::default();
+    static ref EXPOSED: RwLock<HashSet<(String, String)>> = Default::default();
+    static ref PENDING_EXPOSURES: RwLock<Vec<(String, String)>> = Default::default();
+}
+
+#[derive(Clone, Deserialize, Debug)]
+#[serde(rename_all = "camelCase")]
+pub struct Experiment {
+    pub id: String,
+    pub variants: Vec<ExperimentVariant>,
+}
+
+#[derive(Clone, Deserialize, Debug)]
+#[serde(rename_all = "camelCase")]
+pub struct ExperimentVariant {
+    pub name: String,
+    #[serde(default = "default_weight")]
+    pub weight: u32,
+}
+
+fn default_weight() -> u32 {
+    1
+}
+
+/// Applies the remote overrides on top of the local defaults.
+pub fn set_overrides(overrides: &HashMap<String, bool>) {
+    let mut features = FEATURES.write().expect("features write failed");
+    *features = DEFAULT_FEATURES
+        .iter()
+        .map(|(name, enabled)| (name.to_string(), *enabled))
+        .chain(
+            overrides
+                .iter()
+                .map(|(name, enabled)| (name.to_owned(), *enabled)),
+        )
+        .collect();
+}
+
+/// Unknown features are considered disabled
+pub fn is_enabled(name: &str) -> bool {
+    FEATURES
+        .read()
+        .expect("features read failed")
+        .get(name)
+        .copied()
+        .unwrap_or_default()
+}
+
+pub fn features() -> HashMap<String, bool> {
+    FEATURES.read().expect("features read failed").to_owned()
+}
+
+pub fn set_experiments(experiments: Vec<Experiment>) {
+    *EXPERIMENTS.write().expect("experiments write failed") = experiments;
+}
+
+/// Assigns a variant for every experiment, bucketing on the user id
+/// (or the installation id for anonymous users) so the assignment is stable
+/// across sessions and devices of the same user.
+///
+/// Every newly seen assignment is queued as an exposure,
+/// see [`take_pending_exposures`].
+pub fn assignments(profile: &Profile) -> HashMap<String, String> {
+    let bucketing_id = profile
+        .auth
+        .as_ref()
+        .map(|auth| auth.user.id.to_owned())
+        .or_else(WebEnv::installation_id)
+        .unwrap_or_default();
+    let assignments = EXPERIMENTS
+        .read()
+        .expect("experiments read failed")
+        .iter()
+        .filter_map(|experiment| {
+            assign_variant(experiment, &bucketing_id)
+                .map(|variant| (experiment.id.to_owned(), variant.name.to_owned()))
+        })
+        .collect::<HashMap<_, _>>();
+    let mut exposed = EXPOSED.write().expect("exposed write failed");
+    let mut pending_exposures = PENDING_EXPOSURES
+        .write()
+        .expect("pending exposures write failed");
+    for (experiment_id, variant) in assignments.iter() {
+        let assignment = (experiment_id.to_owned(), variant.to_owned());
+        if exposed.insert(assignment.to_owned()) {
+            pending_exposures.push(assignment);
+        }
+    }
+    assignments
+}
+
+/// Returns the `(experiment id, variant)` pairs which were exposed
+/// but not yet reported to analytics.
+pub fn take_pending_exposures() -> Vec<(String, String)> {
+    PENDING_EXPOSURES
+        .write()
+        .expect("pending exposures write failed")
+        .drain(..)
+        .collect()
+}
+
+fn assign_variant<'a>(
+    experiment: &'a Experiment,
+    bucketing_id: &str,
+) -> Option<&'a ExperimentVariant> {
+    let total_weight = experiment
+        .variants
+        .iter()
+        .map(|variant| variant.weight as u64)
+        .sum::<u64>();
+    if total_weight == 0 {
+        return None;
+    }
+    let mut bucket = fnv1a(format!("{}:{}", experiment.id, bucketing_id).as_bytes()) % total_weight;
+    experiment.variants.iter().find(|variant| {
+        if bucket < variant.weight as u64 {
+            true
+        } else {
+            bucket -= variant.weight as u64;
+            false
+        }
+    })
+}
+
+/// FNV-1a, used instead of the std hasher as its output must not change between builds.
+pub fn fnv1a(bytes: &[u8]) -> u64 {
+    bytes.iter().fold(0xcbf29ce484222325, |hash, byte| {
+        (hash ^ *byte as u64).wrapping_mul(0x100000001b3)
+    })
+}
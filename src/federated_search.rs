@@ -0,0 +1,225 @@
+use std::sync::RwLock;
+
+use chrono::{DateTime, Utc};
+use lazy_static::lazy_static;
+use url::Url;
+
+use stremio_core::{
+    models::{catalogs_with_extra::CatalogsWithExtra, common::Loadable},
+    types::{
+        addon::{
+            Descriptor, DescriptorPreview, ExtraValue, Manifest, ResourcePath, ResourceRequest,
+        },
+        resource::MetaItemPreview,
+    },
+};
+
+use crate::loads::Loads;
+
+/// Official addons which are searched at most, in the order of the collection
+const MAX_ADDONS: usize = 5;
+/// Manifests of the official addons which are loaded at most, only the ones with a search
+/// catalog are searched
+const MAX_MANIFESTS: usize = 20;
+/// Addon catalog of the official addons, as declared by the official addons which are installed
+const OFFICIAL_COLLECTION_ID: &str = "official";
+
+lazy_static! {
+    /// The remote addons collection of the official addons
+    static ref COLLECTION: RwLock<Loads<ResourceRequest, Vec<DescriptorPreview>>> =
+        Default::default();
+    /// Manifests of the official addons which are not installed, their catalogs are searched
+    static ref MANIFESTS: RwLock<Loads<Url, Manifest>> = Default::default();
+    /// Results of the search catalogs of the official addons which are not installed,
+    /// for the last query only
+    static ref RESULTS: RwLock<Vec<(ResourceRequest, Loadable<Vec<MetaItemPreview>, String>)>> =
+        Default::default();
+}
+
+/// The request of the official addons collection, `None` when no installed official addon
+/// declares it
+pub fn collection_request(installed: &[Descriptor]) -> Option<ResourceRequest> {
+    installed
+        .iter()
+        .filter(|addon| addon.flags.official)
+        .find_map(|addon| {
+            addon
+                .manifest
+                .addon_catalogs
+                .iter()
+                .find(|catalog| catalog.id == OFFICIAL_COLLECTION_ID)
+                .map(|catalog| {
+                    ResourceRequest::new(
+                        addon.transport_url.to_owned(),
+                        ResourcePath::without_extra("addon_catalog", &catalog.r#type, &catalog.id),
+                    )
+                })
+        })
+}
+
+/// Marks the collection as loading unless it's loaded or it failed recently,
+/// returns the request to load
+pub fn start_loading_collection(
+    installed: &[Descriptor],
+    now: DateTime<Utc>,
+) -> Vec<ResourceRequest> {
+    let requests = collection_request(installed)
+        .into_iter()
+        .collect::<Vec<_>>();
+    let mut collection = COLLECTION.write().expect("federated search write failed");
+    collection.retain(|request| requests.contains(request));
+    collection.start_loading(requests, now)
+}
+
+pub fn set_collection(
+    request: &ResourceRequest,
+    result: Result<Vec<DescriptorPreview>, String>,
+    now: DateTime<Utc>,
+) {
+    COLLECTION
+        .write()
+        .expect("federated search write failed")
+        .set(request, result, now);
+}
+
+/// Marks the manifests of the official addons of the collection which are not installed
+/// as loading, unless they're loaded or they failed recently. Returns their urls to load.
+pub fn start_loading_manifests(installed: &[Descriptor], now: DateTime<Utc>) -> Vec<Url> {
+    let transport_urls = COLLECTION
+        .read()
+        .expect("federated search read failed")
+        .ready()
+        .flat_map(|(_, addons)| addons)
+        .filter(|addon| {
+            !installed
+                .iter()
+                .any(|installed_addon| installed_addon.manifest.id == addon.manifest.id)
+        })
+        .take(MAX_MANIFESTS)
+        .map(|addon| addon.transport_url.to_owned())
+        .collect::<Vec<_>>();
+    let mut manifests = MANIFESTS.write().expect("federated search write failed");
+    manifests.retain(|transport_url| transport_urls.contains(transport_url));
+    manifests.start_loading(transport_urls, now)
+}
+
+pub fn set_manifest(transport_url: &Url, result: Result<Manifest, String>, now: DateTime<Utc>) {
+    MANIFESTS
+        .write()
+        .expect("federated search write failed")
+        .set(transport_url, result, now);
+}
+
+/// The official addons of the collection whose manifests are loaded, in the order of the collection
+pub fn addons() -> Vec<Descriptor> {
+    let collection = COLLECTION.read().expect("federated search read failed");
+    let manifests = MANIFESTS.read().expect("federated search read failed");
+    collection
+        .ready()
+        .flat_map(|(_, addons)| addons)
+        .filter_map(|addon| {
+            manifests
+                .get(&addon.transport_url)
+                .and_then(|manifest| manifest.ready())
+                .map(|manifest| Descriptor {
+                    manifest: manifest.to_owned(),
+                    transport_url: addon.transport_url.to_owned(),
+                    flags: addon.flags.to_owned(),
+                })
+        })
+        .collect()
+}
+
+/// Requests of the search catalogs of the official addons which are not installed,
+/// none while nothing is searched
+pub fn requests(search: &CatalogsWithExtra, installed: &[Descriptor]) -> Vec<ResourceRequest> {
+    let query = match search.selected.as_ref().and_then(|selected| {
+        selected
+            .extra
+            .iter()
+            .find(|extra_value| extra_value.name == "search")
+    }) {
+        Some(query) => query,
+        None => return vec![],
+    };
+    let extra = [ExtraValue {
+        name: query.name.to_owned(),
+        value: query.value.to_owned(),
+    }];
+    addons()
+        .iter()
+        .filter(|addon| {
+            !installed
+                .iter()
+                .any(|installed_addon| installed_addon.manifest.id == addon.manifest.id)
+        })
+        .map(|addon| {
+            addon
+                .manifest
+                .catalogs
+                .iter()
+                .filter(|catalog| catalog.is_extra_supported(&extra))
+                .map(|catalog| {
+                    ResourceRequest::new(
+                        addon.transport_url.to_owned(),
+                        ResourcePath::with_extra("catalog", &catalog.r#type, &catalog.id, &extra),
+                    )
+                })
+                .collect::<Vec<_>>()
+        })
+        .filter(|requests| !requests.is_empty())
+        .take(MAX_ADDONS)
+        .flatten()
+        .collect()
+}
+
+/// Drops the results of the other requests, e.g. of the previous query, and marks the requests
+/// which are not loaded yet as loading. Returns the requests to load, the failed ones are
+/// loaded again by searching again.
+pub fn start_loading(requests: Vec<ResourceRequest>) -> Vec<ResourceRequest> {
+    let mut results = RESULTS.write().expect("federated search write failed");
+    results.retain(|(request, _)| requests.contains(request));
+    let requests = requests
+        .into_iter()
+        .filter(|request| !results.iter().any(|(loaded, _)| loaded == request))
+        .collect::<Vec<_>>();
+    results.extend(
+        requests
+            .iter()
+            .map(|request| (request.to_owned(), Loadable::Loading)),
+    );
+    requests
+}
+
+/// The result is dropped when the query changed meanwhile
+pub fn set_result(request: &ResourceRequest, result: Loadable<Vec<MetaItemPreview>, String>) {
+    let mut results = RESULTS.write().expect("federated search write failed");
+    if let Some((_, loading_result)) = results
+        .iter_mut()
+        .find(|(loading_request, _)| loading_request == request)
+    {
+        *loading_result = result;
+    }
+}
+
+pub fn results() -> Vec<(ResourceRequest, Loadable<Vec<MetaItemPreview>, String>)> {
+    RESULTS
+        .read()
+        .expect("federated search read failed")
+        .to_owned()
+}
+
+pub fn clear() {
+    COLLECTION
+        .write()
+        .expect("federated search write failed")
+        .clear();
+    MANIFESTS
+        .write()
+        .expect("federated search write failed")
+        .clear();
+    RESULTS
+        .write()
+        .expect("federated search write failed")
+        .clear();
+}
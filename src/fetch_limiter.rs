@@ -0,0 +1,195 @@
+use std::{collections::VecDeque, sync::RwLock};
+
+use futures::{channel::oneshot, future, future::LocalBoxFuture, FutureExt};
+use lazy_static::lazy_static;
+use serde::Serialize;
+use url::Url;
+
+use stremio_core::constants::{API_URL, LINK_API_URL};
+
+use crate::{env::WebEnv, web_settings};
+
+lazy_static! {
+    static ref LIMITER: RwLock<Limiter> = Default::default();
+}
+
+#[derive(Default)]
+struct Limiter {
+    /// Hosts of the requests in flight, once per request
+    active: Vec<String>,
+    /// Requests waiting for a slot, in the order they were made
+    queue: VecDeque<(String, oneshot::Sender<()>)>,
+}
+
+/// Maximum number of the requests in flight, read from the settings once per check of the queue
+struct Limits {
+    total: usize,
+    per_host: usize,
+}
+
+impl Limits {
+    fn from_settings() -> Self {
+        let settings = web_settings::web_settings().network;
+        Limits {
+            total: settings.max_concurrent_requests.max(1) as usize,
+            per_host: settings.max_concurrent_requests_per_host.max(1) as usize,
+        }
+    }
+}
+
+impl Limiter {
+    fn has_slot(&self, host: &str, limits: &Limits) -> bool {
+        self.active.len() < limits.total
+            && self.active.iter().filter(|active| *active == host).count() < limits.per_host
+    }
+    /// Starts the queued requests which fit in the freed slots,
+    /// a request for a busy host does not hold back the ones for other hosts.
+    fn start_queued(&mut self) {
+        let limits = Limits::from_settings();
+        let mut index = 0;
+        while index < self.queue.len() {
+            // the requests which are no longer awaited are dropped
+            if self.queue[index].1.is_canceled() {
+                self.queue.remove(index);
+                continue;
+            }
+            if !self.has_slot(&self.queue[index].0, &limits) {
+                index += 1;
+                continue;
+            }
+            if let Some((host, sender)) = self.queue.remove(index) {
+                if sender.send(()).is_ok() {
+                    self.active.push(host);
+                }
+            }
+        }
+    }
+}
+
+/// Holds a slot of the limiter until it's dropped
+pub struct Permit {
+    host: Option<String>,
+}
+
+impl Drop for Permit {
+    fn drop(&mut self) {
+        if let Some(host) = self.host.take() {
+            let mut limiter = LIMITER.write().expect("fetch limiter write failed");
+            if let Some(position) = limiter.active.iter().position(|active| *active == host) {
+                limiter.active.remove(position);
+            }
+            limiter.start_queued();
+        }
+    }
+}
+
+#[derive(Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct HostRequests {
+    pub host: String,
+    pub active: usize,
+    pub queued: usize,
+}
+
+#[derive(Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct RequestQueue {
+    pub active: usize,
+    pub queued: usize,
+    pub hosts: Vec<HostRequests>,
+}
+
+/// Waits for a slot for a request to the url. The requests of the API and of the streaming server
+/// are not limited, the limits are meant to spread out the requests of the addons.
+pub fn acquire(url: &str) -> LocalBoxFuture<'static, Permit> {
+    let host = match limited_host(url) {
+        Some(host) => host,
+        None => return future::ready(Permit { host: None }).boxed_local(),
+    };
+    let mut limiter = LIMITER.write().expect("fetch limiter write failed");
+    // the requests to the same host start in the order they were made
+    let is_host_queued = limiter.queue.iter().any(|(queued, _)| *queued == host);
+    if !is_host_queued && limiter.has_slot(&host, &Limits::from_settings()) {
+        limiter.active.push(host.to_owned());
+        return future::ready(Permit { host: Some(host) }).boxed_local();
+    }
+    let (sender, receiver) = oneshot::channel();
+    limiter.queue.push_back((host.to_owned(), sender));
+    receiver
+        .map(move |result| Permit {
+            // the queue is only dropped along with the runtime
+            host: result.ok().map(|_| host),
+        })
+        .boxed_local()
+}
+
+pub fn request_queue() -> RequestQueue {
+    let limiter = LIMITER.read().expect("fetch limiter read failed");
+    let mut hosts = Vec::<HostRequests>::new();
+    let requests = limiter
+        .active
+        .iter()
+        .map(|host| (host, true))
+        .chain(limiter.queue.iter().map(|(host, _)| (host, false)));
+    for (host, active) in requests {
+        let index = match hosts.iter().position(|requests| requests.host == *host) {
+            Some(index) => index,
+            None => {
+                hosts.push(HostRequests {
+                    host: host.to_owned(),
+                    active: 0,
+                    queued: 0,
+                });
+                hosts.len() - 1
+            }
+        };
+        if active {
+            hosts[index].active += 1;
+        } else {
+            hosts[index].queued += 1;
+        }
+    }
+    RequestQueue {
+        active: limiter.active.len(),
+        queued: limiter.queue.len(),
+        hosts,
+    }
+}
+
+/// Whether no addon request is in flight or waiting for a slot
+pub fn is_idle() -> bool {
+    let limiter = LIMITER.read().expect("fetch limiter read failed");
+    limiter.active.is_empty() && limiter.queue.is_empty()
+}
+
+/// Drops the queued requests, their futures are cancelled along with the runtime
+pub fn clear() {
+    let mut limiter = LIMITER.write().expect("fetch limiter write failed");
+    limiter.active.clear();
+    limiter.queue.clear();
+}
+
+/// Whether the request is to an addon, the API and the streaming server are not limited
+pub fn is_limited(url: &str) -> bool {
+    limited_host(url).is_some()
+}
+
+fn limited_host(url: &str) -> Option<String> {
+    let url = Url::parse(url).ok()?;
+    let streaming_server_url = WebEnv::streaming_server_url();
+    let is_unlimited = [
+        Some(&*API_URL),
+        Some(&*LINK_API_URL),
+        streaming_server_url.as_ref(),
+    ]
+    .into_iter()
+    .flatten()
+    .any(|unlimited| unlimited.host_str() == url.host_str() && unlimited.port() == url.port());
+    if is_unlimited {
+        return None;
+    }
+    url.host_str().map(|host| match url.port() {
+        Some(port) => format!("{host}:{port}"),
+        None => host.to_owned(),
+    })
+}
@@ -0,0 +1,74 @@
+use std::borrow::Cow;
+
+use url::Url;
+
+use stremio_core::types::resource::{Stream, StreamSource};
+
+use crate::{env::WebEnv, web_settings};
+
+/// Gateway urls for the given `{cid}/{path}`, in order of preference
+pub fn gateway_urls(ipfs_path: &str) -> Vec<Url> {
+    let ipfs_settings = web_settings::web_settings().ipfs;
+    let streaming_server_gateway = ipfs_settings
+        .use_streaming_server
+        .then(WebEnv::streaming_server_url)
+        .flatten()
+        .and_then(|streaming_server_url| streaming_server_url.join("ipfs/").ok());
+    streaming_server_gateway
+        .into_iter()
+        .chain(ipfs_settings.gateways)
+        .filter_map(|gateway| gateway.join(ipfs_path).ok())
+        .collect()
+}
+
+/// Extracts `{cid}/{path}` from either an `ipfs://` url or an url of one of the gateways
+pub fn ipfs_path(url: &Url) -> Option<String> {
+    if url.scheme() == "ipfs" {
+        let cid = url.host_str()?;
+        return Some(format!("{}{}", cid, url.path()));
+    }
+    url.path()
+        .split_once("/ipfs/")
+        .map(|(_, ipfs_path)| ipfs_path.to_owned())
+        .filter(|_| {
+            web_settings::web_settings()
+                .ipfs
+                .gateways
+                .iter()
+                .chain(WebEnv::streaming_server_url().iter())
+                .any(|gateway| gateway.origin() == url.origin())
+        })
+}
+
+/// Rewrites `ipfs://` stream sources to the preferred gateway,
+/// every other stream is returned as it is.
+pub fn resolve_stream(stream: &Stream) -> Cow<'_, Stream> {
+    match &stream.source {
+        StreamSource::Url { url } if url.scheme() == "ipfs" => {
+            match ipfs_path(url).and_then(|ipfs_path| gateway_urls(&ipfs_path).into_iter().next()) {
+                Some(gateway_url) => {
+                    let mut stream = stream.to_owned();
+                    stream.source = StreamSource::Url { url: gateway_url };
+                    Cow::Owned(stream)
+                }
+                None => Cow::Borrowed(stream),
+            }
+        }
+        _ => Cow::Borrowed(stream),
+    }
+}
+
+/// The remaining gateway urls to try when the stream served from IPFS fails to play
+pub fn fallback_urls(stream: &Stream) -> Vec<Url> {
+    match &stream.source {
+        StreamSource::Url { url } => ipfs_path(url)
+            .map(|ipfs_path| {
+                gateway_urls(&ipfs_path)
+                    .into_iter()
+                    .filter(|gateway_url| gateway_url != url)
+                    .collect()
+            })
+            .unwrap_or_default(),
+        _ => vec![],
+    }
+}
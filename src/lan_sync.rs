@@ -0,0 +1,812 @@
+use std::{collections::HashMap, sync::RwLock};
+
+use chrono::{DateTime, Utc};
+use futures::{future, FutureExt, TryFutureExt};
+use http::Method;
+use lazy_static::lazy_static;
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use url::{Host, Url};
+
+use stremio_core::{
+    constants::OFFICIAL_ADDONS,
+    models::common::Loadable,
+    runtime::{Env, EnvError, TryEnvFuture},
+    types::{addon::Descriptor, library::LibraryItem, profile::Settings},
+};
+
+use crate::{env::WebEnv, features, model::loadable_states};
+
+pub const LAN_SYNC_STORAGE_KEY: &str = "lan_sync";
+/// The snapshot shared by the devices of the network on the streaming server.
+/// `GET` responds with the snapshot and its `ETag`, `204` until a device pushes one.
+/// `PUT` replaces it only while the `If-Match` version is the current one, `412` otherwise.
+/// Servers without the LAN sync respond with `404`.
+const SNAPSHOT_PATH: &str = "lan-sync/snapshot";
+/// Pushes of a sync, the snapshot is merged again when another device pushed in between
+const MAX_PUSH_ATTEMPTS: usize = 3;
+/// Settings of the device itself, they are neither compared nor pulled
+const DEVICE_SETTINGS: [&str; 3] = ["streamingServerUrl", "playerType", "hardwareDecoding"];
+
+lazy_static! {
+    static ref LAN_SYNC: RwLock<LanSync> = Default::default();
+    static ref LAST_SYNC: RwLock<Option<Loadable<SyncOutcome, String>>> = Default::default();
+    /// The side of a conflict of the profile the user chose to keep, applied by the next sync
+    static ref RESOLUTION: RwLock<Option<ProfileSide>> = Default::default();
+}
+
+/// Sync of the profile and the library through the streaming server of a device of the local
+/// network, for the devices which are not logged in
+#[derive(Clone, Default, PartialEq, Serialize, Deserialize, Debug)]
+#[serde(rename_all = "camelCase", default)]
+pub struct LanSync {
+    pub enabled: bool,
+    /// Tells the devices apart in the snapshots, generated once the sync is enabled
+    pub device_id: String,
+    /// The streaming server the snapshot is kept on, the same one for all of the devices
+    pub server_url: Option<Url>,
+    pub last_synced_at: Option<DateTime<Utc>>,
+    /// Hash of the addons and the settings as of the last sync,
+    /// tells which device changed them since
+    pub synced_profile_hash: Option<String>,
+}
+
+#[derive(Deserialize, Debug)]
+#[serde(tag = "action")]
+pub enum LanSyncAction {
+    /// The url of the server on the network, also on the device running it
+    #[serde(rename_all = "camelCase")]
+    Enable {
+        server_url: Url,
+    },
+    Disable,
+    Sync,
+    /// Keeps the addons and the settings of one of the sides of a conflict and syncs
+    ResolveConflict {
+        keep: ProfileSide,
+    },
+}
+
+#[derive(Clone, Copy, PartialEq, Eq, Deserialize, Debug)]
+pub enum ProfileSide {
+    ThisDevice,
+    OtherDevice,
+}
+
+/// The state shared by the devices, the merge of the last sync of every one of them
+#[derive(Clone, Serialize, Deserialize, Debug)]
+#[serde(rename_all = "camelCase")]
+pub struct Snapshot {
+    /// The device which pushed the snapshot
+    pub device_id: String,
+    pub updated_at: DateTime<Utc>,
+    pub addons: Vec<Descriptor>,
+    pub settings: Settings,
+    pub library: Vec<LibraryItem>,
+}
+
+#[derive(Clone, Copy, PartialEq, Eq, Serialize, Debug)]
+pub enum ProfileMerge {
+    Unchanged,
+    /// The addons and the settings were changed on another device only
+    Pulled,
+    /// They were changed on this device only, or no other device synced yet
+    Pushed,
+    /// They were changed on both, neither is overwritten until the user resolves it
+    Conflict,
+}
+
+#[derive(Clone, PartialEq, Serialize, Debug)]
+#[serde(rename_all = "camelCase")]
+pub struct SyncOutcome {
+    pub synced_at: DateTime<Utc>,
+    /// The device which pushed the snapshot that was merged, `None` when there was none
+    pub from_device: Option<String>,
+    pub profile: ProfileMerge,
+    /// Library items which were added, removed or watched on the other devices
+    pub pulled_items: usize,
+    /// Library items which were newer or only on this device
+    pub pushed_items: usize,
+    /// Library items changed on both since the last sync, the most recently changed is kept
+    pub conflicts: usize,
+    /// Addons of the other devices which are not installed, as anyone on the network can push
+    /// a snapshot. Only the official ones are installed, the rest are offered to the user.
+    pub skipped_addons: Vec<Url>,
+}
+
+/// What a sync changes on this device and what it pushes to the others
+pub struct Merge {
+    pub install_addons: Vec<Descriptor>,
+    pub uninstall_addons: Vec<Descriptor>,
+    /// The settings of the other devices, when they replace the ones of this device
+    pub settings: Option<Settings>,
+    /// Library items of the snapshot which were added, removed or watched on the other devices
+    pub library: Vec<LibraryItem>,
+    pub snapshot: Snapshot,
+    pub outcome: SyncOutcome,
+    /// Hash of the addons and the settings the devices agree on, `None` on a conflict
+    pub profile_hash: Option<String>,
+}
+
+/// The changes of the addons and the settings of the other devices applied to this one
+#[derive(Default)]
+struct PulledProfile {
+    install_addons: Vec<Descriptor>,
+    uninstall_addons: Vec<Descriptor>,
+    settings: Option<Settings>,
+    skipped_addons: Vec<Url>,
+}
+
+#[derive(Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct LanSyncStatus {
+    pub enabled: bool,
+    pub device_id: String,
+    pub server_url: Option<Url>,
+    pub last_synced_at: Option<DateTime<Utc>>,
+    #[serde(serialize_with = "loadable_states::not_asked")]
+    pub last_sync: Option<Loadable<SyncOutcome, String>>,
+}
+
+pub fn set_lan_sync(lan_sync: LanSync) {
+    *LAN_SYNC.write().expect("lan sync write failed") = lan_sync;
+}
+
+pub fn lan_sync() -> LanSync {
+    LAN_SYNC.read().expect("lan sync read failed").to_owned()
+}
+
+pub fn is_enabled() -> bool {
+    LAN_SYNC.read().expect("lan sync read failed").enabled
+}
+
+/// Returns the state to be persisted. The server has to be reachable by the other devices,
+/// the streaming server of this device at `127.0.0.1` would be synced with itself.
+pub fn set_enabled(enabled: bool, server_url: Option<Url>) -> Result<LanSync, String> {
+    if let Some(server_url) = &server_url {
+        if is_loopback(server_url) {
+            return Err("The server is not reachable by the other devices".to_owned());
+        }
+    }
+    let mut lan_sync = LAN_SYNC.write().expect("lan sync write failed");
+    lan_sync.enabled = enabled;
+    if enabled && lan_sync.device_id.is_empty() {
+        lan_sync.device_id = hex::encode(WebEnv::random_buffer(10));
+    }
+    if server_url.is_some() && server_url != lan_sync.server_url {
+        // the snapshot of another server has nothing in common with the last sync
+        lan_sync.server_url = server_url;
+        lan_sync.last_synced_at = None;
+        lan_sync.synced_profile_hash = None;
+    }
+    if !enabled {
+        *LAST_SYNC.write().expect("lan sync write failed") = None;
+    }
+    Ok(lan_sync.to_owned())
+}
+
+pub fn set_resolution(side: ProfileSide) {
+    *RESOLUTION.write().expect("lan sync write failed") = Some(side);
+}
+
+pub fn resolution() -> Option<ProfileSide> {
+    *RESOLUTION.read().expect("lan sync read failed")
+}
+
+pub fn status() -> LanSyncStatus {
+    let lan_sync = LAN_SYNC.read().expect("lan sync read failed");
+    LanSyncStatus {
+        enabled: lan_sync.enabled,
+        device_id: lan_sync.device_id.to_owned(),
+        server_url: lan_sync.server_url.to_owned(),
+        last_synced_at: lan_sync.last_synced_at,
+        last_sync: LAST_SYNC.read().expect("lan sync read failed").to_owned(),
+    }
+}
+
+/// `false` when a sync is running already
+pub fn start_sync() -> bool {
+    let mut last_sync = LAST_SYNC.write().expect("lan sync write failed");
+    if matches!(*last_sync, Some(Loadable::Loading)) {
+        return false;
+    }
+    *last_sync = Some(Loadable::Loading);
+    true
+}
+
+/// Records the outcome of the sync along with the hash of the profile the devices agree on,
+/// returns the state to be persisted once it succeeded
+pub fn set_sync(result: Result<(SyncOutcome, Option<String>), String>) -> Option<LanSync> {
+    let mut last_sync = LAST_SYNC.write().expect("lan sync write failed");
+    match result {
+        Ok((outcome, profile_hash)) => {
+            let mut lan_sync = LAN_SYNC.write().expect("lan sync write failed");
+            lan_sync.last_synced_at = Some(outcome.synced_at);
+            if profile_hash.is_some() {
+                lan_sync.synced_profile_hash = profile_hash;
+                *RESOLUTION.write().expect("lan sync write failed") = None;
+            }
+            *last_sync = Some(Loadable::Ready(outcome));
+            Some(lan_sync.to_owned())
+        }
+        Err(error) => {
+            *last_sync = Some(Loadable::Err(error));
+            None
+        }
+    }
+}
+
+/// The snapshot of this device, with the id it's pushed under
+pub fn local_snapshot(
+    addons: &[Descriptor],
+    settings: &Settings,
+    library: Vec<LibraryItem>,
+    now: DateTime<Utc>,
+) -> Snapshot {
+    Snapshot {
+        device_id: LAN_SYNC
+            .read()
+            .expect("lan sync read failed")
+            .device_id
+            .to_owned(),
+        updated_at: now,
+        addons: addons.to_owned(),
+        settings: settings.to_owned(),
+        library,
+    }
+}
+
+/// The library items are merged one by one, the most recently changed wins.
+/// The addons and the settings are merged as a whole, as they depend on each other.
+pub fn merge(
+    lan_sync: &LanSync,
+    local: Snapshot,
+    remote: Option<Snapshot>,
+    resolution: Option<ProfileSide>,
+) -> Merge {
+    let remote = match remote {
+        Some(remote) => remote,
+        None => {
+            return Merge {
+                install_addons: vec![],
+                uninstall_addons: vec![],
+                settings: None,
+                library: vec![],
+                outcome: SyncOutcome {
+                    synced_at: local.updated_at,
+                    from_device: None,
+                    profile: ProfileMerge::Pushed,
+                    pulled_items: 0,
+                    pushed_items: local.library.len(),
+                    conflicts: 0,
+                    skipped_addons: vec![],
+                },
+                profile_hash: Some(profile_hash(&local.addons, &local.settings)),
+                snapshot: local,
+            }
+        }
+    };
+    let local_hash = profile_hash(&local.addons, &local.settings);
+    let remote_hash = profile_hash(&remote.addons, &remote.settings);
+    let synced_hash = lan_sync.synced_profile_hash.as_ref();
+    let profile = if local_hash == remote_hash {
+        ProfileMerge::Unchanged
+    } else if synced_hash == Some(&local_hash) {
+        ProfileMerge::Pulled
+    } else if synced_hash == Some(&remote_hash) {
+        ProfileMerge::Pushed
+    } else {
+        match resolution {
+            Some(ProfileSide::ThisDevice) => ProfileMerge::Pushed,
+            Some(ProfileSide::OtherDevice) => ProfileMerge::Pulled,
+            None => ProfileMerge::Conflict,
+        }
+    };
+    let pulled_profile = if profile == ProfileMerge::Pulled {
+        pulled_profile(&local, &remote)
+    } else {
+        PulledProfile::default()
+    };
+    let profile_hash = match profile {
+        ProfileMerge::Unchanged | ProfileMerge::Pushed => Some(local_hash),
+        // the profile of this device once the changes are applied, the skipped addons
+        // are pulled again until the user installs them
+        ProfileMerge::Pulled => {
+            let addons = local
+                .addons
+                .iter()
+                .filter(|addon| {
+                    !pulled_profile
+                        .uninstall_addons
+                        .iter()
+                        .any(|uninstalled| uninstalled.transport_url == addon.transport_url)
+                })
+                .chain(pulled_profile.install_addons.iter())
+                .cloned()
+                .collect::<Vec<_>>();
+            Some(profile_hash(
+                &addons,
+                pulled_profile.settings.as_ref().unwrap_or(&local.settings),
+            ))
+        }
+        ProfileMerge::Conflict => None,
+    };
+    let changed_since_sync = |library_item: &LibraryItem| {
+        lan_sync
+            .last_synced_at
+            .map_or(true, |last_synced_at| library_item.mtime > last_synced_at)
+    };
+    let remote_library = remote
+        .library
+        .into_iter()
+        .map(|library_item| (library_item.id.to_owned(), library_item))
+        .collect::<HashMap<_, _>>();
+    let pushed_items = local
+        .library
+        .iter()
+        .filter(|local_item| {
+            remote_library
+                .get(&local_item.id)
+                .map_or(true, |remote_item| local_item.mtime > remote_item.mtime)
+        })
+        .count();
+    let mut library = local
+        .library
+        .into_iter()
+        .map(|library_item| (library_item.id.to_owned(), library_item))
+        .collect::<HashMap<_, _>>();
+    let mut pulled = vec![];
+    let mut conflicts = 0;
+    for (id, remote_item) in remote_library {
+        let local_item = library.get(&id);
+        if local_item.map_or(false, |local_item| local_item.mtime >= remote_item.mtime) {
+            continue;
+        }
+        if local_item.map_or(false, |local_item| {
+            changed_since_sync(local_item) && changed_since_sync(&remote_item)
+        }) {
+            conflicts += 1;
+        }
+        // the playback progress of the other devices is kept in the snapshot only
+        let is_changed = local_item.map_or(is_in_library(&remote_item), |local_item| {
+            is_in_library(local_item) != is_in_library(&remote_item)
+                || is_watched(local_item) != is_watched(&remote_item)
+        });
+        if is_changed {
+            pulled.push(remote_item.to_owned());
+        }
+        library.insert(id, remote_item);
+    }
+    let (addons, settings) = match profile {
+        ProfileMerge::Pushed => (local.addons, local.settings),
+        _ => (remote.addons, remote.settings),
+    };
+    Merge {
+        install_addons: pulled_profile.install_addons,
+        uninstall_addons: pulled_profile.uninstall_addons,
+        settings: pulled_profile.settings,
+        outcome: SyncOutcome {
+            synced_at: local.updated_at,
+            from_device: Some(remote.device_id),
+            profile,
+            pulled_items: pulled.len(),
+            pushed_items,
+            conflicts,
+            skipped_addons: pulled_profile.skipped_addons,
+        },
+        library: pulled,
+        profile_hash,
+        snapshot: Snapshot {
+            device_id: local.device_id,
+            updated_at: local.updated_at,
+            addons,
+            settings,
+            library: library.into_values().collect(),
+        },
+    }
+}
+
+/// Hash of the addons and the settings, to tell whether they changed since the last sync.
+/// The order of the addons and the settings of the devices themselves are left out.
+pub fn profile_hash(addons: &[Descriptor], settings: &Settings) -> String {
+    let mut transport_urls = addons
+        .iter()
+        .map(|addon| addon.transport_url.as_str())
+        .collect::<Vec<_>>();
+    transport_urls.sort_unstable();
+    let mut settings = serde_json::to_value(settings).unwrap_or_default();
+    if let Value::Object(settings) = &mut settings {
+        for key in DEVICE_SETTINGS {
+            settings.remove(key);
+        }
+    }
+    let hash = features::fnv1a(
+        serde_json::to_string(&(transport_urls, settings))
+            .unwrap_or_default()
+            .as_bytes(),
+    );
+    format!("{hash:016x}")
+}
+
+/// Merges the snapshot of the server into the one of this device and pushes the merge,
+/// it's merged again when another device pushed in between
+pub fn sync(local: Snapshot) -> TryEnvFuture<Merge> {
+    let lan_sync = lan_sync();
+    let resolution = resolution();
+    let server_url = match lan_sync.server_url.to_owned() {
+        Some(server_url) => server_url,
+        None => {
+            return future::err(EnvError::Fetch(
+                "The server of the LAN sync is not set".to_owned(),
+            ))
+            .boxed_local()
+        }
+    };
+    async move {
+        for _ in 0..MAX_PUSH_ATTEMPTS {
+            let (remote, etag) = fetch_snapshot(&server_url).await?;
+            let merge = merge(&lan_sync, local.to_owned(), remote, resolution);
+            if push_snapshot(&server_url, &merge.snapshot, etag.as_deref()).await? {
+                return Ok(merge);
+            }
+        }
+        Err(EnvError::Fetch(
+            "The snapshot kept changing on the other devices".to_owned(),
+        ))
+    }
+    .boxed_local()
+}
+
+/// The snapshot along with its version, `None` until a device pushes the first one
+fn fetch_snapshot(server_url: &Url) -> TryEnvFuture<(Option<Snapshot>, Option<String>)> {
+    let url = match snapshot_url(server_url) {
+        Ok(url) => url,
+        Err(error) => return future::err(error).boxed_local(),
+    };
+    WebEnv::fetch_versioned::<()>(Method::GET, &url, &[], None)
+        .and_then(|(status, etag, body)| {
+            future::ready(match status {
+                200 => serde_json::from_value(body)
+                    .map(|snapshot| (Some(snapshot), etag))
+                    .map_err(EnvError::from),
+                204 => Ok((None, None)),
+                status => Err(status_error(status)),
+            })
+        })
+        .boxed_local()
+}
+
+/// Replaces the snapshot of the given version, `false` when another device pushed one since
+fn push_snapshot(server_url: &Url, snapshot: &Snapshot, etag: Option<&str>) -> TryEnvFuture<bool> {
+    let url = match snapshot_url(server_url) {
+        Ok(url) => url,
+        Err(error) => return future::err(error).boxed_local(),
+    };
+    let precondition = match etag {
+        Some(etag) => ("if-match", etag),
+        None => ("if-none-match", "*"),
+    };
+    WebEnv::fetch_versioned(Method::PUT, &url, &[precondition], Some(snapshot))
+        .and_then(|(status, _, _)| {
+            future::ready(match status {
+                200 | 201 | 204 => Ok(true),
+                412 => Ok(false),
+                status => Err(status_error(status)),
+            })
+        })
+        .boxed_local()
+}
+
+pub fn is_in_library(library_item: &LibraryItem) -> bool {
+    !library_item.removed && !library_item.temp
+}
+
+pub fn is_watched(library_item: &LibraryItem) -> bool {
+    library_item.state.times_watched > 0 || library_item.state.flagged_watched > 0
+}
+
+pub fn clear() {
+    *LAST_SYNC.write().expect("lan sync write failed") = None;
+    *RESOLUTION.write().expect("lan sync write failed") = None;
+}
+
+/// Only the official addons of the other devices are installed, the settings of this device
+/// itself are kept
+fn pulled_profile(local: &Snapshot, remote: &Snapshot) -> PulledProfile {
+    let is_installed = |addons: &[Descriptor], addon: &Descriptor| {
+        addons
+            .iter()
+            .any(|installed| installed.transport_url == addon.transport_url)
+    };
+    let (install_addons, skipped_addons) = remote
+        .addons
+        .iter()
+        .filter(|addon| !is_installed(&local.addons, addon))
+        .fold((vec![], vec![]), |(mut install, mut skipped), addon| {
+            // the descriptor bundled with the app, the pushed one could be anything
+            match OFFICIAL_ADDONS
+                .iter()
+                .find(|official| official.transport_url == addon.transport_url)
+            {
+                Some(official) => install.push(official.to_owned()),
+                None => skipped.push(addon.transport_url.to_owned()),
+            };
+            (install, skipped)
+        });
+    let uninstall_addons = local
+        .addons
+        .iter()
+        .filter(|addon| !addon.flags.protected && !is_installed(&remote.addons, addon))
+        .cloned()
+        .collect();
+    let settings = device_settings(&remote.settings, &local.settings)
+        .filter(|settings| *settings != local.settings);
+    PulledProfile {
+        install_addons,
+        uninstall_addons,
+        settings,
+        skipped_addons,
+    }
+}
+
+/// The settings with the ones of the device itself taken from its own settings
+fn device_settings(settings: &Settings, device: &Settings) -> Option<Settings> {
+    let mut settings = serde_json::to_value(settings).ok()?;
+    let device = serde_json::to_value(device).ok()?;
+    if let (Value::Object(settings), Value::Object(device)) = (&mut settings, &device) {
+        for key in DEVICE_SETTINGS {
+            match device.get(key) {
+                Some(value) => settings.insert(key.to_owned(), value.to_owned()),
+                None => settings.remove(key),
+            };
+        }
+    }
+    serde_json::from_value(settings).ok()
+}
+
+fn is_loopback(url: &Url) -> bool {
+    match url.host() {
+        Some(Host::Domain(domain)) => domain.eq_ignore_ascii_case("localhost"),
+        Some(Host::Ipv4(ip)) => ip.is_loopback(),
+        Some(Host::Ipv6(ip)) => ip.is_loopback(),
+        None => true,
+    }
+}
+
+fn snapshot_url(server_url: &Url) -> Result<Url, EnvError> {
+    server_url
+        .join(SNAPSHOT_PATH)
+        .map_err(|error| EnvError::Fetch(error.to_string()))
+}
+
+fn status_error(status: u16) -> EnvError {
+    match status {
+        404 => EnvError::Fetch("The streaming server does not support the LAN sync".to_owned()),
+        status => EnvError::Fetch(format!("Unexpected HTTP status code {status}")),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use chrono::TimeZone;
+    use serde_json::json;
+
+    use super::*;
+
+    fn date(day: u32) -> DateTime<Utc> {
+        Utc.with_ymd_and_hms(2024, 1, day, 0, 0, 0).unwrap()
+    }
+
+    fn library_item(
+        id: &str,
+        mtime: DateTime<Utc>,
+        removed: bool,
+        times_watched: u32,
+    ) -> LibraryItem {
+        serde_json::from_value(json!({
+            "_id": id,
+            "name": id,
+            "type": "movie",
+            "poster": null,
+            "posterShape": "poster",
+            "removed": removed,
+            "temp": false,
+            "_ctime": date(1),
+            "_mtime": mtime,
+            "state": {
+                "lastWatched": null,
+                "timeWatched": 0,
+                "timeOffset": 0,
+                "overallTimeWatched": 0,
+                "timesWatched": times_watched,
+                "flaggedWatched": 0,
+                "duration": 0,
+                "video_id": null,
+                "watched": null,
+                "noNotif": false,
+            },
+            "behaviorHints": {
+                "defaultVideoId": null,
+                "featuredVideoId": null,
+                "hasScheduledVideos": false,
+            },
+        }))
+        .unwrap()
+    }
+
+    fn snapshot(device_id: &str, addons: Vec<Descriptor>, library: Vec<LibraryItem>) -> Snapshot {
+        Snapshot {
+            device_id: device_id.to_owned(),
+            updated_at: date(10),
+            addons,
+            settings: Settings::default(),
+            library,
+        }
+    }
+
+    fn synced(addons: &[Descriptor]) -> LanSync {
+        LanSync {
+            enabled: true,
+            device_id: "local".to_owned(),
+            server_url: None,
+            last_synced_at: Some(date(5)),
+            synced_profile_hash: Some(profile_hash(addons, &Settings::default())),
+        }
+    }
+
+    fn transport_urls(addons: &[Descriptor]) -> Vec<&Url> {
+        addons.iter().map(|addon| &addon.transport_url).collect()
+    }
+
+    #[test]
+    fn first_sync() {
+        let local = snapshot("local", vec![], vec![library_item("a", date(2), false, 0)]);
+        let merge = merge(&LanSync::default(), local, None, None);
+        assert_eq!(merge.outcome.profile, ProfileMerge::Pushed);
+        assert_eq!(merge.outcome.pushed_items, 1);
+        assert!(merge.profile_hash.is_some());
+        assert_eq!(merge.snapshot.library.len(), 1);
+    }
+
+    #[test]
+    fn pulled_profile_installs_only_official_addons() {
+        let local_addons = vec![OFFICIAL_ADDONS[0].to_owned()];
+        let mut untrusted = OFFICIAL_ADDONS[0].to_owned();
+        untrusted.transport_url = Url::parse("https://example.com/manifest.json").unwrap();
+        let remote_addons = vec![
+            OFFICIAL_ADDONS[0].to_owned(),
+            OFFICIAL_ADDONS[1].to_owned(),
+            untrusted.to_owned(),
+        ];
+        let merge = merge(
+            &synced(&local_addons),
+            snapshot("local", local_addons, vec![]),
+            Some(snapshot("remote", remote_addons.to_owned(), vec![])),
+            None,
+        );
+        assert_eq!(merge.outcome.profile, ProfileMerge::Pulled);
+        assert_eq!(
+            transport_urls(&merge.install_addons),
+            vec![&OFFICIAL_ADDONS[1].transport_url]
+        );
+        assert!(merge.uninstall_addons.is_empty());
+        assert_eq!(merge.outcome.skipped_addons, vec![untrusted.transport_url]);
+        assert_eq!(
+            transport_urls(&merge.snapshot.addons),
+            transport_urls(&remote_addons)
+        );
+        // the skipped addon is pulled again, until the user installs it
+        assert_eq!(
+            merge.profile_hash,
+            Some(profile_hash(&OFFICIAL_ADDONS[..2], &Settings::default()))
+        );
+    }
+
+    #[test]
+    fn conflict_overwrites_neither() {
+        let local_addons = vec![OFFICIAL_ADDONS[0].to_owned()];
+        let remote_addons = vec![OFFICIAL_ADDONS[1].to_owned()];
+        let lan_sync = LanSync {
+            synced_profile_hash: None,
+            ..synced(&[])
+        };
+        let local = snapshot("local", local_addons.to_owned(), vec![]);
+        let remote = snapshot("remote", remote_addons.to_owned(), vec![]);
+        let conflict = merge(&lan_sync, local.to_owned(), Some(remote.to_owned()), None);
+        assert_eq!(conflict.outcome.profile, ProfileMerge::Conflict);
+        assert!(conflict.install_addons.is_empty() && conflict.uninstall_addons.is_empty());
+        assert_eq!(
+            transport_urls(&conflict.snapshot.addons),
+            transport_urls(&remote_addons)
+        );
+        assert_eq!(conflict.profile_hash, None);
+        let resolved = merge(
+            &lan_sync,
+            local,
+            Some(remote),
+            Some(ProfileSide::ThisDevice),
+        );
+        assert_eq!(resolved.outcome.profile, ProfileMerge::Pushed);
+        assert_eq!(
+            transport_urls(&resolved.snapshot.addons),
+            transport_urls(&local_addons)
+        );
+    }
+
+    #[test]
+    fn device_settings_are_not_synced() {
+        let mut remote = snapshot("remote", vec![], vec![]);
+        remote.settings.streaming_server_url = Url::parse("http://192.168.1.2:11470/").unwrap();
+        let merge = merge(
+            &synced(&[]),
+            snapshot("local", vec![], vec![]),
+            Some(remote),
+            None,
+        );
+        assert_eq!(merge.outcome.profile, ProfileMerge::Unchanged);
+        assert!(merge.settings.is_none());
+    }
+
+    #[test]
+    fn library_items() {
+        let local = snapshot(
+            "local",
+            vec![],
+            vec![
+                library_item("progress", date(6), false, 0),
+                library_item("removed", date(3), false, 0),
+                library_item("watched", date(3), false, 0),
+                library_item("local", date(3), false, 0),
+                library_item("newer", date(8), false, 0),
+            ],
+        );
+        let remote = snapshot(
+            "remote",
+            vec![],
+            vec![
+                library_item("progress", date(7), false, 0),
+                library_item("removed", date(4), true, 0),
+                library_item("watched", date(4), false, 1),
+                library_item("remote", date(4), false, 0),
+                library_item("newer", date(7), true, 0),
+            ],
+        );
+        let merge = merge(&synced(&[]), local, Some(remote), None);
+        let mut pulled = merge
+            .library
+            .iter()
+            .map(|library_item| library_item.id.as_str())
+            .collect::<Vec<_>>();
+        pulled.sort_unstable();
+        assert_eq!(pulled, vec!["remote", "removed", "watched"]);
+        assert_eq!(merge.outcome.pulled_items, 3);
+        assert_eq!(merge.outcome.pushed_items, 2);
+        assert_eq!(merge.outcome.conflicts, 1);
+        let snapshot_item = |id: &str| {
+            merge
+                .snapshot
+                .library
+                .iter()
+                .find(|library_item| library_item.id == id)
+                .unwrap()
+                .to_owned()
+        };
+        assert_eq!(snapshot_item("progress").mtime, date(7));
+        assert!(!snapshot_item("newer").removed);
+        assert_eq!(merge.snapshot.library.len(), 6);
+    }
+
+    #[test]
+    fn loopback() {
+        for url in [
+            "http://127.0.0.1:11470/",
+            "http://localhost:11470/",
+            "http://[::1]:11470/",
+        ] {
+            assert!(is_loopback(&Url::parse(url).unwrap()));
+        }
+        assert!(!is_loopback(
+            &Url::parse("http://192.168.1.2:11470/").unwrap()
+        ));
+    }
+}
@@ -1,6 +1,75 @@
 #[allow(clippy::module_inception)]
 pub mod model;
 
+pub mod account;
+pub mod action_validation;
+pub mod addon_console;
+pub mod addon_diagnostics;
+pub mod addon_preview;
+pub mod addon_signatures;
+pub mod addon_updates;
+pub mod background;
+pub mod blocklist;
+pub mod board_refresh;
+pub mod catalog_cache;
+pub mod catalog_hints;
+pub mod debrid;
+pub mod device_profile;
+pub mod diagnostic_snapshot;
 pub mod env;
+pub mod epg;
 pub mod event;
+pub mod event_sequence;
+pub mod features;
+pub mod federated_search;
+pub mod fetch_limiter;
+pub mod ipfs;
+pub mod lan_sync;
+pub mod library_pending;
+pub mod library_tags;
+pub mod library_transfer;
+pub mod load_cancellation;
+pub mod loads;
+pub mod memory;
+pub mod meta_overrides;
+pub mod meta_prefetch;
+pub mod mirrors;
+#[cfg(feature = "mock-addon")]
+pub mod mock_addon;
+pub mod new_episodes;
+pub mod observed_fields;
+pub mod onboarding;
+pub mod p2p_transport;
+pub mod palettes;
+pub mod player_source;
+pub mod prefetch;
+pub mod push_transport;
+pub mod recent_logs;
+pub mod reminders;
+pub mod remote_config;
+pub mod request_tracing;
+pub mod response_limits;
+pub mod retry;
+pub mod rewatch;
+pub mod schema_validation;
+pub mod season_packs;
+pub mod shortcuts;
+pub mod snooze;
+pub mod state_cache;
+pub mod still_watching;
+pub mod stream_history;
+pub mod stream_timeouts;
+pub mod streaming_catalogs;
+pub mod streaming_server_cache;
+pub mod streaming_server_jobs;
+pub mod subtitles_sync;
+pub mod subtitles_translation;
+pub mod tab_sync;
+pub mod trailers;
+pub mod undo;
+pub mod uploaded_subtitles;
+pub mod user_ratings;
+pub mod watch_party;
+pub mod web_playback;
+pub mod web_settings;
 pub mod stremio_core_web;
@@ -0,0 +1,103 @@
+use std::sync::RwLock;
+
+use lazy_static::lazy_static;
+
+use stremio_core::runtime::msg::{Action, ActionCtx, Event};
+
+lazy_static! {
+    static ref CHANGES: RwLock<Vec<PendingChange>> = Default::default();
+}
+
+/// A library change applied locally which is not yet confirmed by the API
+#[derive(Clone, Debug)]
+struct PendingChange {
+    id: String,
+    removed: bool,
+    /// Set when the API rejected the change, it's shown as reverted
+    error: Option<String>,
+}
+
+/// Records the library change of the action, replacing a previous change of the same item
+pub fn start(action: &Action) {
+    let (id, removed) = match action {
+        Action::Ctx(ActionCtx::AddToLibrary(meta_item)) => (&meta_item.id, false),
+        Action::Ctx(ActionCtx::RemoveFromLibrary(id)) => (id, true),
+        _ => return,
+    };
+    let mut changes = CHANGES
+        .write()
+        .expect("pending library changes write failed");
+    changes.retain(|change| &change.id != id);
+    changes.push(PendingChange {
+        id: id.to_owned(),
+        removed,
+        error: None,
+    });
+}
+
+/// Confirms or fails the changes pushed to the API, returns whether any of them changed
+pub fn reconcile(event: &Event) -> bool {
+    let mut changes = CHANGES
+        .write()
+        .expect("pending library changes write failed");
+    match event {
+        Event::LibraryItemsPushedToAPI { ids } => {
+            let len = changes.len();
+            changes.retain(|change| !ids.contains(&change.id));
+            changes.len() != len
+        }
+        Event::Error { error, source } => match source.as_ref() {
+            Event::LibraryItemsPushedToAPI { ids } => {
+                let mut changed = false;
+                for change in changes.iter_mut().filter(|change| ids.contains(&change.id)) {
+                    change.error = Some(error.message());
+                    changed = true;
+                }
+                changed
+            }
+            _ => false,
+        },
+        Event::UserLoggedOut { .. } => {
+            let changed = !changes.is_empty();
+            changes.clear();
+            changed
+        }
+        _ => false,
+    }
+}
+
+/// Whether the item has a change waiting for the API
+pub fn is_pending(id: &str) -> bool {
+    CHANGES
+        .read()
+        .expect("pending library changes read failed")
+        .iter()
+        .any(|change| change.id == id && change.error.is_none())
+}
+
+/// Whether the item is shown in the library, a change rejected by the API is reverted
+pub fn in_library(id: &str, in_library: bool) -> bool {
+    CHANGES
+        .read()
+        .expect("pending library changes read failed")
+        .iter()
+        .find(|change| change.id == id && change.error.is_some())
+        .map_or(in_library, |change| change.removed)
+}
+
+/// The reason the last change of the item was rejected by the API
+pub fn error(id: &str) -> Option<String> {
+    CHANGES
+        .read()
+        .expect("pending library changes read failed")
+        .iter()
+        .find(|change| change.id == id)
+        .and_then(|change| change.error.to_owned())
+}
+
+pub fn clear() {
+    CHANGES
+        .write()
+        .expect("pending library changes write failed")
+        .clear();
+}
@@ -0,0 +1,151 @@
+use std::{collections::HashMap, sync::RwLock};
+
+use itertools::Itertools;
+use lazy_static::lazy_static;
+use serde::{Deserialize, Serialize};
+use url::{form_urlencoded, Url};
+
+pub const LIBRARY_TAGS_STORAGE_KEY: &str = "library_tags";
+pub const TAG_QUERY_PARAM: &str = "tag";
+
+lazy_static! {
+    /// Tags of the library items set by the user, by item id
+    static ref LIBRARY_TAGS: RwLock<HashMap<String, Vec<String>>> = Default::default();
+    /// Selected tag per library root (`library`, `continuewatching`)
+    static ref SELECTED_TAGS: RwLock<HashMap<String, String>> = Default::default();
+}
+
+#[derive(Deserialize, Debug)]
+#[serde(tag = "action", content = "args")]
+pub enum LibraryTagsAction {
+    AddTag {
+        id: String,
+        tag: String,
+    },
+    RemoveTag {
+        id: String,
+        tag: String,
+    },
+    /// Filters the library of the root by the tag, `None` shows all of the items
+    SelectTag {
+        root: String,
+        tag: Option<String>,
+    },
+}
+
+#[derive(Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct TagCount {
+    pub tag: String,
+    pub count: usize,
+}
+
+pub fn set_library_tags(library_tags: HashMap<String, Vec<String>>) {
+    *LIBRARY_TAGS.write().expect("library tags write failed") = library_tags;
+}
+
+/// Applies the action and returns the updated tags to be persisted, `None` when there's nothing to persist
+pub fn update_library_tags(action: LibraryTagsAction) -> Option<HashMap<String, Vec<String>>> {
+    let mut library_tags = LIBRARY_TAGS.write().expect("library tags write failed");
+    match action {
+        LibraryTagsAction::AddTag { id, tag } => {
+            let tag = tag.trim().to_owned();
+            if tag.is_empty() {
+                return None;
+            }
+            let tags = library_tags.entry(id).or_default();
+            if !tags.contains(&tag) {
+                tags.push(tag);
+            }
+        }
+        LibraryTagsAction::RemoveTag { id, tag } => {
+            if let Some(tags) = library_tags.get_mut(&id) {
+                tags.retain(|item_tag| *item_tag != tag);
+                if tags.is_empty() {
+                    library_tags.remove(&id);
+                }
+            }
+        }
+        LibraryTagsAction::SelectTag { root, tag } => {
+            set_selected_tag(root, tag);
+            return None;
+        }
+    };
+    Some(library_tags.to_owned())
+}
+
+pub fn tags(id: &str) -> Vec<String> {
+    LIBRARY_TAGS
+        .read()
+        .expect("library tags read failed")
+        .get(id)
+        .cloned()
+        .unwrap_or_default()
+}
+
+/// All of the tags along with the number of the items tagged by them, by name
+pub fn tag_counts<'a>(ids: impl Iterator<Item = &'a String>) -> Vec<TagCount> {
+    let library_tags = LIBRARY_TAGS.read().expect("library tags read failed");
+    ids.filter_map(|id| library_tags.get(id))
+        .flatten()
+        .counts()
+        .into_iter()
+        .map(|(tag, count)| TagCount {
+            tag: tag.to_owned(),
+            count,
+        })
+        .sorted_by(|a, b| a.tag.to_lowercase().cmp(&b.tag.to_lowercase()))
+        .collect()
+}
+
+pub fn selected_tag(root: &str) -> Option<String> {
+    SELECTED_TAGS
+        .read()
+        .expect("selected tags read failed")
+        .get(root)
+        .cloned()
+}
+
+/// The tag of the `#/library` or `#/continuewatching` location, `None` shows all of the items
+pub fn parse_tag(location_hash: &str) -> Option<String> {
+    let (_, query) = location_hash.split_once('?')?;
+    form_urlencoded::parse(query.as_bytes())
+        .find(|(key, _)| key == TAG_QUERY_PARAM)
+        .map(|(_, tag)| tag.trim().to_owned())
+        .filter(|tag| !tag.is_empty())
+}
+
+pub fn set_selected_tag(root: String, tag: Option<String>) {
+    let mut selected_tags = SELECTED_TAGS.write().expect("selected tags write failed");
+    match tag {
+        Some(tag) => selected_tags.insert(root, tag),
+        None => selected_tags.remove(&root),
+    };
+}
+
+/// Appends (or replaces) the tag query param of a `stremio://` library deep link
+pub fn with_tag(deep_link: &str, tag: &str) -> String {
+    match Url::parse(deep_link) {
+        Ok(mut url) => {
+            let query_pairs = url
+                .query_pairs()
+                .filter(|(key, _)| key != TAG_QUERY_PARAM)
+                .map(|(key, value)| (key.into_owned(), value.into_owned()))
+                .collect::<Vec<_>>();
+            url.query_pairs_mut()
+                .clear()
+                .extend_pairs(query_pairs)
+                .append_pair(TAG_QUERY_PARAM, tag);
+            url.to_string()
+        }
+        _ => deep_link.to_owned(),
+    }
+}
+
+/// Drops the selected tags, the tags are loaded again from the storage
+pub fn clear() {
+    SELECTED_TAGS
+        .write()
+        .expect("selected tags write failed")
+        .clear();
+}
@@ -0,0 +1,750 @@
+use std::{collections::HashSet, sync::RwLock};
+
+use chrono::{DateTime, Datelike, Utc};
+use lazy_static::lazy_static;
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+
+use stremio_core::{
+    runtime::Env,
+    types::{
+        addon::{Descriptor, ExtraValue, ResourcePath, ResourceResponse},
+        library::LibraryBucket,
+        resource::MetaItemPreview,
+    },
+};
+
+use crate::env::WebEnv;
+
+const SEARCH_EXTRA_NAME: &str = "search";
+const EXPORT_COLUMNS: [&str; 9] = [
+    "id",
+    "type",
+    "name",
+    "added",
+    "lastWatched",
+    "timesWatched",
+    "watched",
+    "progress",
+    "videoId",
+];
+
+lazy_static! {
+    /// The report of the last import, along with its id
+    static ref IMPORT_REPORT: RwLock<(u64, Option<ImportReport>)> = Default::default();
+}
+
+#[derive(Clone, Copy, PartialEq, Eq, Deserialize, Debug)]
+pub enum ExportFormat {
+    Csv,
+    Json,
+}
+
+/// The exports of the other services which can be imported
+#[derive(Clone, Copy, PartialEq, Eq, Serialize, Deserialize, Debug)]
+pub enum ImportSource {
+    /// The JSON of a watchlist, a collection or the watch history
+    Trakt,
+    /// The CSV of a list or of the ratings
+    Imdb,
+    /// The CSV of the watched films or of the watchlist, it has no IMDb ids
+    Letterboxd,
+}
+
+#[derive(Serialize)]
+#[serde(rename_all = "camelCase")]
+struct ExportedItem<'a> {
+    id: &'a String,
+    r#type: &'a String,
+    name: &'a String,
+    added: Option<DateTime<Utc>>,
+    last_watched: Option<DateTime<Utc>>,
+    times_watched: u32,
+    watched: bool,
+    /// Percent of the last watched video
+    progress: f64,
+    video_id: Option<&'a String>,
+}
+
+/// An entry of the imported file, before it's resolved to an item of the meta addons
+#[derive(Clone, PartialEq, Debug)]
+pub struct ImportEntry {
+    pub title: String,
+    pub year: Option<u32>,
+    /// `movie` or `series`
+    pub r#type: String,
+    pub imdb_id: Option<String>,
+    /// The file lists the entry as watched, e.g. the watch history or the ratings
+    pub watched: bool,
+}
+
+#[derive(Clone, Copy, PartialEq, Eq, Serialize, Debug)]
+pub enum SkipReason {
+    AlreadyInLibrary,
+    /// Neither a movie nor a series, e.g. an episode or a video game
+    UnsupportedType,
+    /// The file has the same title more than once
+    Duplicate,
+}
+
+#[derive(Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ImportedItem {
+    pub title: String,
+    pub year: Option<u32>,
+    /// Id of the library item, `None` for the unmatched entries
+    pub id: Option<String>,
+    pub watched: bool,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub reason: Option<SkipReason>,
+}
+
+#[derive(Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ImportReport {
+    pub source: ImportSource,
+    /// Entries which are not resolved yet
+    pub pending: usize,
+    pub matched: Vec<ImportedItem>,
+    /// Entries none of the meta addons know
+    pub unmatched: Vec<ImportedItem>,
+    pub skipped: Vec<ImportedItem>,
+    /// The file could not be parsed
+    pub error: Option<String>,
+}
+
+/// The library items which are not removed, along with their watch state
+pub fn export(library: &LibraryBucket, format: ExportFormat) -> String {
+    let items = library
+        .items
+        .values()
+        .filter(|library_item| !library_item.removed && !library_item.temp)
+        .map(|library_item| ExportedItem {
+            id: &library_item.id,
+            r#type: &library_item.r#type,
+            name: &library_item.name,
+            added: library_item.ctime,
+            last_watched: library_item.state.last_watched,
+            times_watched: library_item.state.times_watched,
+            watched: library_item.state.times_watched > 0 || library_item.state.flagged_watched > 0,
+            progress: library_item.progress(),
+            video_id: library_item.state.video_id.as_ref(),
+        })
+        .collect::<Vec<_>>();
+    match format {
+        ExportFormat::Json => serde_json::to_string_pretty(&items).unwrap_or_default(),
+        ExportFormat::Csv => {
+            let rows = items.iter().map(|item| {
+                [
+                    item.id.to_owned(),
+                    item.r#type.to_owned(),
+                    item.name.to_owned(),
+                    item.added
+                        .map(|added| added.to_rfc3339())
+                        .unwrap_or_default(),
+                    item.last_watched
+                        .map(|last_watched| last_watched.to_rfc3339())
+                        .unwrap_or_default(),
+                    item.times_watched.to_string(),
+                    item.watched.to_string(),
+                    format!("{:.1}", item.progress),
+                    item.video_id.cloned().unwrap_or_default(),
+                ]
+                .iter()
+                .map(|field| csv_field(field))
+                .collect::<Vec<_>>()
+                .join(",")
+            });
+            std::iter::once(EXPORT_COLUMNS.join(","))
+                .chain(rows)
+                .collect::<Vec<_>>()
+                .join("\r\n")
+        }
+    }
+}
+
+/// The movies and the series of the file, the other entries are skipped right away
+pub fn parse(
+    source: ImportSource,
+    content: &str,
+) -> Result<(Vec<ImportEntry>, Vec<ImportedItem>), String> {
+    let entries = match source {
+        ImportSource::Trakt => parse_trakt(content)?,
+        ImportSource::Imdb => parse_imdb(content)?,
+        ImportSource::Letterboxd => parse_letterboxd(content)?,
+    };
+    let mut parsed = Vec::<ImportEntry>::new();
+    let mut skipped = Vec::new();
+    let mut parsed_keys = ParsedKeys::default();
+    for (entry, supported) in entries {
+        let reason = if !supported {
+            Some(SkipReason::UnsupportedType)
+        } else if !parsed_keys.insert(&entry) {
+            Some(SkipReason::Duplicate)
+        } else {
+            None
+        };
+        match reason {
+            Some(reason) => skipped.push(ImportedItem {
+                title: entry.title,
+                year: entry.year,
+                id: entry.imdb_id,
+                watched: entry.watched,
+                reason: Some(reason),
+            }),
+            None => parsed.push(entry),
+        }
+    }
+    Ok((parsed, skipped))
+}
+
+/// Finds the item of the entry in the meta addons.
+/// Entries with an IMDb id are looked up by it, the others are searched for by their title and year.
+pub async fn resolve(entry: &ImportEntry, addons: &[Descriptor]) -> Option<MetaItemPreview> {
+    if let Some(imdb_id) = entry.imdb_id.as_ref() {
+        let path = ResourcePath::without_extra("meta", &entry.r#type, imdb_id);
+        for addon in addons
+            .iter()
+            .filter(|addon| addon.manifest.is_resource_supported(&path))
+        {
+            if let Ok(ResourceResponse::Meta { meta }) =
+                WebEnv::addon_transport(&addon.transport_url)
+                    .resource(&path)
+                    .await
+            {
+                return Some(meta.preview);
+            }
+        }
+    }
+    let extra = [ExtraValue {
+        name: SEARCH_EXTRA_NAME.to_owned(),
+        value: entry.title.to_owned(),
+    }];
+    for (addon, catalog) in addons.iter().flat_map(|addon| {
+        addon
+            .manifest
+            .catalogs
+            .iter()
+            .filter(|catalog| catalog.r#type == entry.r#type && catalog.is_extra_supported(&extra))
+            .map(move |catalog| (addon, catalog))
+    }) {
+        let path = ResourcePath::with_extra("catalog", &catalog.r#type, &catalog.id, &extra);
+        if let Ok(ResourceResponse::Metas { metas }) = WebEnv::addon_transport(&addon.transport_url)
+            .resource(&path)
+            .await
+        {
+            if let Some(meta_item) = metas
+                .into_iter()
+                .find(|meta_item| is_match(entry, meta_item))
+            {
+                return Some(meta_item);
+            }
+        }
+    }
+    None
+}
+
+/// Replaces the report of the import before, which stops once it's no longer the current one.
+/// Returns the id of the import.
+pub fn start_import(source: ImportSource, entries: usize, skipped: Vec<ImportedItem>) -> u64 {
+    set_report(ImportReport {
+        source,
+        pending: entries,
+        matched: vec![],
+        unmatched: vec![],
+        skipped,
+        error: None,
+    })
+}
+
+pub fn fail_import(source: ImportSource, error: String) {
+    set_report(ImportReport {
+        source,
+        pending: 0,
+        matched: vec![],
+        unmatched: vec![],
+        skipped: vec![],
+        error: Some(error),
+    });
+}
+
+/// Records the outcome of an entry, `reason` is set when it was skipped.
+/// Returns whether the import is still the current one.
+pub fn set_resolved(
+    import_id: u64,
+    entry: ImportEntry,
+    id: Option<String>,
+    reason: Option<SkipReason>,
+) -> bool {
+    let mut report = IMPORT_REPORT.write().expect("import report write failed");
+    let (current_id, report) = &mut *report;
+    let report = match report.as_mut() {
+        Some(report) if *current_id == import_id => report,
+        _ => return false,
+    };
+    report.pending = report.pending.saturating_sub(1);
+    let item = ImportedItem {
+        title: entry.title,
+        year: entry.year,
+        id,
+        watched: entry.watched,
+        reason,
+    };
+    match (&item.id, reason) {
+        (_, Some(_)) => report.skipped.push(item),
+        (Some(_), None) => report.matched.push(item),
+        (None, None) => report.unmatched.push(item),
+    };
+    true
+}
+
+pub fn import_report() -> Option<ImportReport> {
+    IMPORT_REPORT
+        .read()
+        .expect("import report read failed")
+        .1
+        .to_owned()
+}
+
+/// Drops the report, the import in progress stops
+pub fn clear() {
+    let mut report = IMPORT_REPORT.write().expect("import report write failed");
+    report.0 += 1;
+    report.1 = None;
+}
+
+fn set_report(import_report: ImportReport) -> u64 {
+    let mut report = IMPORT_REPORT.write().expect("import report write failed");
+    report.0 += 1;
+    report.1 = Some(import_report);
+    report.0
+}
+
+/// The entries of a Trakt export, along with whether they are movies or series.
+/// The episodes and the seasons of the watch history add their series.
+/// The entries of the watch history and of the watched exports are watched.
+fn parse_trakt(content: &str) -> Result<Vec<(ImportEntry, bool)>, String> {
+    let entries = serde_json::from_str::<Vec<Value>>(content)
+        .map_err(|error| format!("Invalid Trakt export: {error}"))?;
+    Ok(entries
+        .iter()
+        .filter_map(|entry| {
+            let (r#type, item) = match (entry.get("movie"), entry.get("show")) {
+                (Some(movie), _) => (Some("movie"), movie),
+                (None, Some(show)) => (Some("series"), show),
+                // e.g. the people of a list
+                _ => (None, entry.get("person")?),
+            };
+            let title = item
+                .get("title")
+                .or_else(|| item.get("name"))?
+                .as_str()?
+                .to_owned();
+            let watched = entry.get("watched_at").is_some()
+                || entry.get("last_watched_at").is_some()
+                || entry
+                    .get("plays")
+                    .and_then(Value::as_u64)
+                    .unwrap_or_default()
+                    > 0;
+            Some((
+                ImportEntry {
+                    title,
+                    year: item
+                        .get("year")
+                        .and_then(Value::as_u64)
+                        .map(|year| year as u32),
+                    r#type: r#type.unwrap_or_default().to_owned(),
+                    imdb_id: item
+                        .pointer("/ids/imdb")
+                        .and_then(Value::as_str)
+                        .map(ToOwned::to_owned),
+                    watched,
+                },
+                r#type.is_some(),
+            ))
+        })
+        .collect())
+}
+
+/// The entries of the ratings are watched, the ones of the lists are not
+fn parse_imdb(content: &str) -> Result<Vec<(ImportEntry, bool)>, String> {
+    let rows = csv_records(
+        content,
+        &["Const", "Title", "Title Type", "Year"],
+        &["Your Rating"],
+    )?;
+    Ok(rows
+        .into_iter()
+        .map(|row| {
+            let title_type = row[2].to_lowercase().replace(' ', "");
+            let r#type = match title_type.as_str() {
+                "movie" | "tvmovie" | "short" | "tvshort" | "tvspecial" => Some("movie"),
+                "tvseries" | "tvminiseries" => Some("series"),
+                _ => None,
+            };
+            (
+                ImportEntry {
+                    imdb_id: Some(row[0].to_owned()).filter(|id| id.starts_with("tt")),
+                    title: row[1].to_owned(),
+                    year: row[3].parse().ok(),
+                    r#type: r#type.unwrap_or("movie").to_owned(),
+                    watched: !row[4].is_empty(),
+                },
+                r#type.is_some(),
+            )
+        })
+        .collect())
+}
+
+/// The entries of the diary and of the ratings are watched, the ones of the watchlist are not.
+/// The watched films export has the same columns as the watchlist, so it can't be told apart.
+fn parse_letterboxd(content: &str) -> Result<Vec<(ImportEntry, bool)>, String> {
+    let rows = csv_records(content, &["Name", "Year"], &["Watched Date", "Rating"])?;
+    Ok(rows
+        .into_iter()
+        .map(|row| {
+            (
+                ImportEntry {
+                    title: row[0].to_owned(),
+                    year: row[1].parse().ok(),
+                    r#type: "movie".to_owned(),
+                    imdb_id: None,
+                    watched: !row[2].is_empty() || !row[3].is_empty(),
+                },
+                true,
+            )
+        })
+        .collect())
+}
+
+/// The given columns of the rows which have a title, in the order of the names.
+/// The optional columns follow the required ones, they are empty when the file has no such column.
+fn csv_records(
+    content: &str,
+    columns: &[&str],
+    optional_columns: &[&str],
+) -> Result<Vec<Vec<String>>, String> {
+    let mut rows = parse_csv(content).into_iter();
+    let header = rows.next().ok_or_else(|| "The file is empty".to_owned())?;
+    let position = |column: &str| {
+        header
+            .iter()
+            .position(|name| name.trim_start_matches('\u{feff}').trim() == column)
+    };
+    let indexes = columns
+        .iter()
+        .map(|column| {
+            position(column)
+                .map(Some)
+                .ok_or_else(|| format!("The file has no {column} column"))
+        })
+        .chain(optional_columns.iter().map(|column| Ok(position(column))))
+        .collect::<Result<Vec<_>, _>>()?;
+    Ok(rows
+        .map(|row| {
+            indexes
+                .iter()
+                .map(|index| {
+                    index
+                        .and_then(|index| row.get(index))
+                        .map(|field| field.trim().to_owned())
+                        .unwrap_or_default()
+                })
+                .collect::<Vec<_>>()
+        })
+        .filter(|row| row.iter().any(|field| !field.is_empty()))
+        .collect())
+}
+
+/// Rows of a CSV file, the quoted fields can contain commas, quotes and line breaks
+fn parse_csv(content: &str) -> Vec<Vec<String>> {
+    let mut rows = vec![];
+    let mut row = vec![];
+    let mut field = String::new();
+    let mut quoted = false;
+    let mut chars = content.chars().peekable();
+    while let Some(char) = chars.next() {
+        match (char, quoted) {
+            ('"', true) if chars.peek() == Some(&'"') => {
+                field.push('"');
+                chars.next();
+            }
+            ('"', _) => quoted = !quoted,
+            (',', false) => row.push(std::mem::take(&mut field)),
+            ('\r', false) => {}
+            ('\n', false) => {
+                row.push(std::mem::take(&mut field));
+                rows.push(std::mem::take(&mut row));
+            }
+            (char, _) => field.push(char),
+        }
+    }
+    if !field.is_empty() || !row.is_empty() {
+        row.push(field);
+        rows.push(row);
+    }
+    rows
+}
+
+/// The fields which a spreadsheet would run as a formula, e.g. a name starting with `=`,
+/// are prefixed with a quote so they are shown as text
+fn csv_field(field: &str) -> String {
+    let field = if field.starts_with(['=', '+', '-', '@', '\t', '\r']) {
+        format!("'{field}")
+    } else {
+        field.to_owned()
+    };
+    if field.contains([',', '"', '\n', '\r']) {
+        format!("\"{}\"", field.replace('"', "\"\""))
+    } else {
+        field
+    }
+}
+
+/// The entries parsed so far, the ones with an IMDb id are the same as another one with that id,
+/// the others as another one with the same type, title and year
+#[derive(Default)]
+struct ParsedKeys {
+    imdb_ids: HashSet<String>,
+    /// The titles of all of the entries
+    titles: HashSet<(String, String, Option<u32>)>,
+    /// The titles of the entries which have no IMDb id
+    titles_without_imdb_id: HashSet<(String, String, Option<u32>)>,
+}
+
+impl ParsedKeys {
+    /// Returns whether the entry is not the same as one of the entries before
+    fn insert(&mut self, entry: &ImportEntry) -> bool {
+        let title = (
+            entry.r#type.to_owned(),
+            entry.title.to_ascii_lowercase(),
+            entry.year,
+        );
+        let is_duplicate = match &entry.imdb_id {
+            Some(imdb_id) => {
+                self.imdb_ids.contains(imdb_id) || self.titles_without_imdb_id.contains(&title)
+            }
+            None => self.titles.contains(&title),
+        };
+        if is_duplicate {
+            return false;
+        }
+        match &entry.imdb_id {
+            Some(imdb_id) => {
+                self.imdb_ids.insert(imdb_id.to_owned());
+            }
+            None => {
+                self.titles_without_imdb_id.insert(title.to_owned());
+            }
+        };
+        self.titles.insert(title);
+        true
+    }
+}
+
+/// The search results match by the title, and by the year when both are known
+fn is_match(entry: &ImportEntry, meta_item: &MetaItemPreview) -> bool {
+    let year = meta_item
+        .release_info
+        .as_ref()
+        .and_then(|release_info| release_info.get(..4))
+        .and_then(|year| year.parse::<u32>().ok())
+        .or_else(|| meta_item.released.map(|released| released.year() as u32));
+    meta_item
+        .name
+        .trim()
+        .eq_ignore_ascii_case(entry.title.trim())
+        && match (entry.year, year) {
+            (Some(entry_year), Some(year)) => entry_year == year,
+            _ => true,
+        }
+}
+
+#[cfg(test)]
+mod tests {
+    use serde_json::json;
+
+    use super::*;
+
+    fn entry(title: &str, imdb_id: Option<&str>) -> ImportEntry {
+        ImportEntry {
+            title: title.to_owned(),
+            year: Some(2010),
+            r#type: "movie".to_owned(),
+            imdb_id: imdb_id.map(ToOwned::to_owned),
+            watched: false,
+        }
+    }
+
+    #[test]
+    fn csv_quotes_and_line_breaks() {
+        let content = "Name,Note\r\n\"Lock, Stock\",\"say \"\"hi\"\"\ntwice\"\r\nInception,";
+        assert_eq!(
+            parse_csv(content),
+            vec![
+                vec!["Name".to_owned(), "Note".to_owned()],
+                vec!["Lock, Stock".to_owned(), "say \"hi\"\ntwice".to_owned()],
+                vec!["Inception".to_owned(), String::new()],
+            ]
+        );
+        assert!(parse_csv("").is_empty());
+    }
+
+    #[test]
+    fn csv_records_columns() {
+        let content = "\u{feff}Year, Name ,Other\n2010,Inception,x\n,,y\n1999,The Matrix,z\n";
+        assert_eq!(
+            csv_records(content, &["Name", "Year"], &["Rating"]),
+            Ok(vec![
+                vec!["Inception".to_owned(), "2010".to_owned(), String::new()],
+                vec!["The Matrix".to_owned(), "1999".to_owned(), String::new()],
+            ])
+        );
+        assert_eq!(
+            csv_records(content, &["Name", "Title"], &[]),
+            Err("The file has no Title column".to_owned())
+        );
+        assert_eq!(
+            csv_records("", &["Name"], &[]),
+            Err("The file is empty".to_owned())
+        );
+    }
+
+    #[test]
+    fn trakt() {
+        let content = json!([
+            {
+                "watched_at": "2020-01-01T00:00:00.000Z",
+                "movie": { "title": "Inception", "year": 2010, "ids": { "imdb": "tt1375666" } },
+            },
+            {
+                "plays": 0,
+                "show": { "title": "Dark", "year": 2017, "ids": {} },
+            },
+            { "person": { "name": "Christopher Nolan" } },
+        ])
+        .to_string();
+        let entries = parse_trakt(&content).unwrap();
+        assert_eq!(
+            entries,
+            vec![
+                (
+                    ImportEntry {
+                        title: "Inception".to_owned(),
+                        year: Some(2010),
+                        r#type: "movie".to_owned(),
+                        imdb_id: Some("tt1375666".to_owned()),
+                        watched: true,
+                    },
+                    true
+                ),
+                (
+                    ImportEntry {
+                        title: "Dark".to_owned(),
+                        year: Some(2017),
+                        r#type: "series".to_owned(),
+                        imdb_id: None,
+                        watched: false,
+                    },
+                    true
+                ),
+                (
+                    ImportEntry {
+                        title: "Christopher Nolan".to_owned(),
+                        year: None,
+                        r#type: String::new(),
+                        imdb_id: None,
+                        watched: false,
+                    },
+                    false
+                ),
+            ]
+        );
+        assert!(parse_trakt("{}").is_err());
+    }
+
+    #[test]
+    fn imdb() {
+        let content = "Const,Your Rating,Title,Title Type,Year\n\
+            tt1375666,9,Inception,Movie,2010\n\
+            tt5753856,,Dark,TV Series,2017\n\
+            tt0000001,,Pilot,TV Episode,2017\n\
+            nm0634240,,Someone,Person,\n";
+        let entries = parse_imdb(content).unwrap();
+        assert_eq!(
+            entries
+                .iter()
+                .map(|(entry, supported)| (
+                    entry.imdb_id.as_deref(),
+                    entry.r#type.as_str(),
+                    entry.watched,
+                    *supported
+                ))
+                .collect::<Vec<_>>(),
+            vec![
+                (Some("tt1375666"), "movie", true, true),
+                (Some("tt5753856"), "series", false, true),
+                (Some("tt0000001"), "movie", false, false),
+                (None, "movie", false, false),
+            ]
+        );
+        assert_eq!(entries[0].0.year, Some(2010));
+    }
+
+    #[test]
+    fn letterboxd() {
+        let content = "Date,Name,Year,Letterboxd URI,Rating,Watched Date\n\
+            2020-01-01,Inception,2010,https://boxd.it/1,4.5,\n\
+            2020-01-02,Dune,,https://boxd.it/2,,\n";
+        assert_eq!(
+            parse_letterboxd(content).unwrap(),
+            vec![
+                (
+                    ImportEntry {
+                        title: "Inception".to_owned(),
+                        year: Some(2010),
+                        r#type: "movie".to_owned(),
+                        imdb_id: None,
+                        watched: true,
+                    },
+                    true
+                ),
+                (
+                    ImportEntry {
+                        title: "Dune".to_owned(),
+                        year: None,
+                        r#type: "movie".to_owned(),
+                        imdb_id: None,
+                        watched: false,
+                    },
+                    true
+                ),
+            ]
+        );
+    }
+
+    #[test]
+    fn parsed_keys_dedup() {
+        let mut parsed_keys = ParsedKeys::default();
+        assert!(parsed_keys.insert(&entry("Inception", Some("tt1375666"))));
+        assert!(!parsed_keys.insert(&entry("Inception (2010)", Some("tt1375666"))));
+        // the title of an entry with an IMDb id is not enough to tell another IMDb id apart
+        assert!(parsed_keys.insert(&entry("inception", Some("tt0000001"))));
+        assert!(!parsed_keys.insert(&entry("INCEPTION", None)));
+        assert!(parsed_keys.insert(&entry("Dune", None)));
+        assert!(!parsed_keys.insert(&entry("dune", None)));
+        assert!(!parsed_keys.insert(&entry("Dune", Some("tt1160419"))));
+    }
+
+    #[test]
+    fn csv_field_escaping() {
+        assert_eq!(csv_field("Inception"), "Inception");
+        assert_eq!(csv_field("=HYPERLINK(\"x\")"), "\"'=HYPERLINK(\"\"x\"\")\"");
+        assert_eq!(csv_field("+1"), "'+1");
+        assert_eq!(csv_field("-1,2"), "\"'-1,2\"");
+        assert_eq!(csv_field("@SUM(A1)"), "'@SUM(A1)");
+        assert_eq!(csv_field("Lock, Stock"), "\"Lock, Stock\"");
+        assert_eq!(csv_field("line\nbreak"), "\"line\nbreak\"");
+    }
+}
@@ -0,0 +1,173 @@
+use std::{
+    cell::{Cell, RefCell},
+    future::Future,
+};
+
+use futures::{future, FutureExt};
+use serde_json::Value;
+use tracing::trace;
+
+use crate::model::{WebModel, WebModelField};
+
+/// The error of the aborted requests, told apart from the other errors in the serialized state
+pub const CANCELLED_ERROR: &str = "Request cancelled";
+
+struct InFlight {
+    id: u64,
+    /// The field whose load started the request, `None` for the requests of the effects
+    field: Option<WebModelField>,
+    url: String,
+    abort: Box<dyn FnOnce()>,
+}
+
+thread_local! {
+    static IN_FLIGHT: RefCell<Vec<InFlight>> = RefCell::new(vec![]);
+    static NEXT_ID: Cell<u64> = Cell::new(1);
+    /// The field of the action being dispatched, or of the one whose effect is polled,
+    /// the requests made meanwhile belong to it
+    static LOADING_FIELD: RefCell<Option<WebModelField>> = RefCell::new(None);
+    /// Fields with cancelled loads, they are loaded from scratch the next time
+    static CANCELLED_FIELDS: RefCell<Vec<WebModelField>> = RefCell::new(vec![]);
+}
+
+/// Tracks a request until `finish` is called, returns its id
+pub fn start(url: &str, abort: impl FnOnce() + 'static) -> u64 {
+    let id = NEXT_ID.with(|next_id| next_id.replace(next_id.get() + 1));
+    IN_FLIGHT.with(|in_flight| {
+        in_flight.borrow_mut().push(InFlight {
+            id,
+            field: LOADING_FIELD.with(|loading_field| loading_field.borrow().to_owned()),
+            url: url.to_owned(),
+            abort: Box::new(abort),
+        })
+    });
+    id
+}
+
+pub fn finish(id: u64) {
+    IN_FLIGHT.with(|in_flight| in_flight.borrow_mut().retain(|request| request.id != id));
+}
+
+/// Runs the dispatch of an action of the field, the requests it makes are attributed to the field
+pub fn with_loading_field<T>(field: Option<WebModelField>, dispatch: impl FnOnce() -> T) -> T {
+    let previous = LOADING_FIELD.with(|loading_field| loading_field.replace(field));
+    let result = dispatch();
+    LOADING_FIELD.with(|loading_field| *loading_field.borrow_mut() = previous);
+    result
+}
+
+/// The future of an effect, the requests it makes are attributed to the field of the dispatch
+/// it was spawned by, even the ones which start once the dispatch is over, e.g. the retries
+pub fn with_spawning_field<F: Future + 'static>(future: F) -> impl Future<Output = F::Output> {
+    let field = LOADING_FIELD.with(|loading_field| loading_field.borrow().to_owned());
+    let mut future = future.boxed_local();
+    future::poll_fn(move |cx| {
+        let previous = LOADING_FIELD.with(|loading_field| loading_field.replace(field.to_owned()));
+        let poll = future.poll_unpin(cx);
+        LOADING_FIELD.with(|loading_field| *loading_field.borrow_mut() = previous);
+        poll
+    })
+}
+
+/// Ids of the requests of the field still in flight
+pub fn in_flight(field: &WebModelField) -> Vec<u64> {
+    IN_FLIGHT.with(|in_flight| {
+        in_flight
+            .borrow()
+            .iter()
+            .filter(|request| request.field.as_ref() == Some(field))
+            .map(|request| request.id)
+            .collect()
+    })
+}
+
+/// Aborts the requests, they fail with `CANCELLED_ERROR` instead of resolving late.
+/// Returns whether any of them was still in flight.
+pub fn cancel(ids: &[u64]) -> bool {
+    let cancelled = IN_FLIGHT.with(|in_flight| {
+        let mut in_flight = in_flight.borrow_mut();
+        let (cancelled, rest) = in_flight
+            .drain(..)
+            .partition::<Vec<_>, _>(|request| ids.contains(&request.id));
+        *in_flight = rest;
+        cancelled
+    });
+    let is_cancelled = !cancelled.is_empty();
+    for request in cancelled {
+        trace!(url = request.url.as_str(), "Request cancelled");
+        (request.abort)();
+    }
+    is_cancelled
+}
+
+/// The field is to be loaded from scratch the next time, even for the same selection
+pub fn set_cancelled(field: &WebModelField) {
+    CANCELLED_FIELDS.with(|cancelled_fields| {
+        let mut cancelled_fields = cancelled_fields.borrow_mut();
+        if !cancelled_fields.contains(field) {
+            cancelled_fields.push(field.to_owned());
+        }
+    });
+}
+
+/// Whether the field has cancelled loads, which are not loaded again by the same selection,
+/// the flag is cleared
+pub fn take_cancelled(field: &WebModelField) -> bool {
+    CANCELLED_FIELDS.with(|cancelled_fields| {
+        let mut cancelled_fields = cancelled_fields.borrow_mut();
+        let is_cancelled = cancelled_fields.contains(field);
+        cancelled_fields.retain(|cancelled_field| cancelled_field != field);
+        is_cancelled
+    })
+}
+
+/// What the field is loaded for, the loads in flight are cancelled once it changes.
+/// `None` for the fields which load nothing from the addons.
+pub fn selected(model: &WebModel, field: &WebModelField) -> Option<Value> {
+    let selected = match field {
+        WebModelField::Board => serde_json::to_value(&model.board.selected),
+        WebModelField::Discover => serde_json::to_value(&model.discover.selected),
+        WebModelField::Search => serde_json::to_value(&model.search.selected),
+        WebModelField::MetaDetails => serde_json::to_value(&model.meta_details.selected),
+        WebModelField::RemoteAddons => serde_json::to_value(&model.remote_addons.selected),
+        WebModelField::AddonDetails => serde_json::to_value(&model.addon_details.selected),
+        WebModelField::Player => serde_json::to_value(&model.player.selected),
+        _ => return None,
+    };
+    selected.ok()
+}
+
+/// Drops the tracked requests, they are cancelled along with the runtime
+pub fn clear() {
+    IN_FLIGHT.with(|in_flight| in_flight.borrow_mut().clear());
+    CANCELLED_FIELDS.with(|cancelled_fields| cancelled_fields.borrow_mut().clear());
+}
+
+#[cfg(test)]
+mod tests {
+    use std::rc::Rc;
+
+    use futures::executor::block_on;
+
+    use super::*;
+
+    #[test]
+    fn leaving_discover_aborts_its_catalog_request() {
+        let aborted = Rc::new(Cell::new(false));
+        let effect = with_loading_field(Some(WebModelField::Discover), || {
+            let aborted = aborted.to_owned();
+            // the transport starts the request once the effect is polled, after the dispatch
+            with_spawning_field(async move {
+                start(
+                    "https://addon.example.com/catalog/movie/top.json",
+                    move || aborted.set(true),
+                )
+            })
+        });
+        let id = block_on(effect);
+        assert_eq!(in_flight(&WebModelField::Discover), vec![id]);
+        assert!(cancel(&in_flight(&WebModelField::Discover)));
+        assert!(aborted.get());
+        assert!(in_flight(&WebModelField::Discover).is_empty());
+    }
+}
@@ -0,0 +1,115 @@
+use chrono::{DateTime, Duration, Utc};
+
+use stremio_core::models::common::Loadable;
+
+/// Seconds until a failed load is retried, the delay doubles with every failure
+const MIN_RETRY_DELAY: i64 = 30;
+/// Seconds, the longest delay of the retries
+const MAX_RETRY_DELAY: i64 = 60 * 60;
+
+/// Side state loaded by the requests of the models, e.g. the manifests of the addons.
+///
+/// The loads are started on the state changes of the models, a failed load
+/// is started again only once its retry delay passed instead of on every state change.
+pub struct Loads<K, T> {
+    entries: Vec<Load<K, T>>,
+}
+
+struct Load<K, T> {
+    key: K,
+    content: Loadable<T, String>,
+    failures: u32,
+    retry_at: Option<DateTime<Utc>>,
+}
+
+impl<K, T> Default for Loads<K, T> {
+    fn default() -> Self {
+        Loads { entries: vec![] }
+    }
+}
+
+impl<K: PartialEq + Clone, T> Loads<K, T> {
+    pub fn get(&self, key: &K) -> Option<&Loadable<T, String>> {
+        self.entries
+            .iter()
+            .find(|load| load.key == *key)
+            .map(|load| &load.content)
+    }
+
+    pub fn ready(&self) -> impl Iterator<Item = (&K, &T)> {
+        self.entries
+            .iter()
+            .filter_map(|load| load.content.ready().map(|content| (&load.key, content)))
+    }
+
+    /// Marks the keys which are not loaded yet, or which failed and are due for a retry,
+    /// as loading and returns them
+    pub fn start_loading(&mut self, keys: Vec<K>, now: DateTime<Utc>) -> Vec<K> {
+        let keys = keys
+            .into_iter()
+            .filter(|key| {
+                self.entries
+                    .iter()
+                    .find(|load| load.key == *key)
+                    .map_or(true, |load| {
+                        load.retry_at.map_or(false, |retry_at| retry_at <= now)
+                    })
+            })
+            .collect::<Vec<_>>();
+        for key in keys.iter() {
+            match self.entries.iter_mut().find(|load| load.key == *key) {
+                Some(load) => {
+                    load.content = Loadable::Loading;
+                    load.retry_at = None;
+                }
+                None => self.entries.push(Load {
+                    key: key.to_owned(),
+                    content: Loadable::Loading,
+                    failures: 0,
+                    retry_at: None,
+                }),
+            }
+        }
+        keys
+    }
+
+    /// Sets the result of a started load, the failed ones are retried with a backoff
+    pub fn set(&mut self, key: &K, result: Result<T, String>, now: DateTime<Utc>) {
+        if let Some(load) = self.entries.iter_mut().find(|load| load.key == *key) {
+            match result {
+                Ok(content) => {
+                    load.content = Loadable::Ready(content);
+                    load.failures = 0;
+                }
+                Err(error) => {
+                    let delay = (MIN_RETRY_DELAY << load.failures.min(16)).min(MAX_RETRY_DELAY);
+                    load.content = Loadable::Err(error);
+                    load.failures += 1;
+                    load.retry_at = Some(now + Duration::seconds(delay));
+                }
+            }
+        }
+    }
+
+    pub fn retain(&mut self, f: impl Fn(&K) -> bool) {
+        self.entries.retain(|load| f(&load.key));
+    }
+
+    /// Drops the oldest loads over the limit
+    pub fn truncate(&mut self, max_len: usize) {
+        let overflow = self.entries.len().saturating_sub(max_len);
+        self.entries.drain(..overflow);
+    }
+
+    pub fn len(&self) -> usize {
+        self.entries.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.entries.is_empty()
+    }
+
+    pub fn clear(&mut self) {
+        self.entries.clear();
+    }
+}
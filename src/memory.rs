@@ -0,0 +1,79 @@
+use serde::{Deserialize, Serialize};
+use wasm_bindgen::JsCast;
+
+use crate::{
+    addon_preview, catalog_cache, epg,
+    model::{loadable_states, WebModelField},
+    palettes, prefetch, schema_validation, state_cache,
+};
+
+/// How much memory the page has to give back, as reported by the webview
+#[derive(Clone, Copy, PartialEq, Eq, Deserialize, Debug)]
+pub enum TrimLevel {
+    /// Drops the caches which are rebuilt on the next state change
+    Moderate,
+    /// Drops every buffer which can be loaded again, the page is about to be killed otherwise
+    Critical,
+}
+
+#[derive(Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct SerializedStateSize {
+    pub field: WebModelField,
+    /// Length of the JSON of the state
+    pub size: u32,
+}
+
+#[derive(Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct MemoryUsage {
+    /// Size of the linear memory of the wasm module, it never shrinks
+    pub wasm_memory: u32,
+    pub serialized_states: Vec<SerializedStateSize>,
+    /// Number of entries of every cache
+    pub loaded_requests: usize,
+    pub prefetched_responses: usize,
+    pub cached_catalogs: usize,
+    pub guides: usize,
+    pub addon_previews: usize,
+    pub schema_warnings: usize,
+    pub palettes: usize,
+}
+
+pub fn usage() -> MemoryUsage {
+    MemoryUsage {
+        wasm_memory: wasm_bindgen::memory()
+            .dyn_into::<js_sys::WebAssembly::Memory>()
+            .map(|memory| {
+                memory
+                    .buffer()
+                    .unchecked_into::<js_sys::ArrayBuffer>()
+                    .byte_length()
+            })
+            .unwrap_or_default(),
+        serialized_states: state_cache::sizes()
+            .into_iter()
+            .map(|(field, size)| SerializedStateSize { field, size })
+            .collect(),
+        loaded_requests: loadable_states::len(),
+        prefetched_responses: prefetch::len(),
+        cached_catalogs: catalog_cache::len(),
+        guides: epg::len(),
+        addon_previews: addon_preview::len(),
+        schema_warnings: schema_validation::len(),
+        palettes: palettes::len(),
+    }
+}
+
+pub fn trim(level: TrimLevel) {
+    state_cache::clear();
+    loadable_states::clear();
+    prefetch::clear();
+    catalog_cache::clear();
+    if level == TrimLevel::Critical {
+        epg::clear();
+        addon_preview::clear();
+        schema_validation::clear();
+        palettes::clear();
+    }
+}
@@ -0,0 +1,147 @@
+use std::{borrow::Cow, collections::HashMap, sync::RwLock};
+
+use lazy_static::lazy_static;
+use serde::{Deserialize, Serialize};
+use url::Url;
+
+use stremio_core::types::resource::{MetaItem, MetaItemPreview};
+
+pub const META_OVERRIDES_STORAGE_KEY: &str = "meta_overrides";
+
+lazy_static! {
+    /// Personalization of the items by the user, by meta id
+    static ref META_OVERRIDES: RwLock<HashMap<String, MetaOverride>> = Default::default();
+}
+
+#[derive(Clone, Default, PartialEq, Serialize, Deserialize, Debug)]
+#[serde(rename_all = "camelCase")]
+pub struct MetaOverride {
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub poster: Option<Url>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub name: Option<String>,
+}
+
+impl MetaOverride {
+    fn is_empty(&self) -> bool {
+        self.poster.is_none() && self.name.is_none()
+    }
+}
+
+#[derive(Deserialize, Debug)]
+#[serde(tag = "action", content = "args")]
+pub enum MetaOverridesAction {
+    SetPoster { id: String, poster: Url },
+    SetName { id: String, name: String },
+    ClearPoster(String),
+    ClearName(String),
+}
+
+pub fn set_meta_overrides(meta_overrides: HashMap<String, MetaOverride>) {
+    *META_OVERRIDES.write().expect("meta overrides write failed") = meta_overrides;
+}
+
+/// Applies the action and returns the updated overrides to be persisted
+pub fn update_meta_overrides(action: MetaOverridesAction) -> HashMap<String, MetaOverride> {
+    let mut meta_overrides = META_OVERRIDES.write().expect("meta overrides write failed");
+    let id = match action {
+        MetaOverridesAction::SetPoster { id, poster } => {
+            meta_overrides.entry(id.to_owned()).or_default().poster = Some(poster);
+            id
+        }
+        MetaOverridesAction::SetName { id, name } => {
+            meta_overrides.entry(id.to_owned()).or_default().name = Some(name);
+            id
+        }
+        MetaOverridesAction::ClearPoster(id) => {
+            if let Some(meta_override) = meta_overrides.get_mut(&id) {
+                meta_override.poster = None;
+            }
+            id
+        }
+        MetaOverridesAction::ClearName(id) => {
+            if let Some(meta_override) = meta_overrides.get_mut(&id) {
+                meta_override.name = None;
+            }
+            id
+        }
+    };
+    if meta_overrides
+        .get(&id)
+        .map_or(false, MetaOverride::is_empty)
+    {
+        meta_overrides.remove(&id);
+    }
+    meta_overrides.to_owned()
+}
+
+/// The overrides of the item, for the details screen to offer clearing them
+pub fn meta_override(id: &str) -> Option<MetaOverride> {
+    META_OVERRIDES
+        .read()
+        .expect("meta overrides read failed")
+        .get(id)
+        .cloned()
+}
+
+/// The custom posters, their palettes are extracted along with the ones of the catalogs
+pub fn posters() -> Vec<Url> {
+    META_OVERRIDES
+        .read()
+        .expect("meta overrides read failed")
+        .values()
+        .filter_map(|meta_override| meta_override.poster.to_owned())
+        .collect()
+}
+
+pub fn name<'a>(id: &str, name: &'a str) -> Cow<'a, str> {
+    match meta_override(id).and_then(|meta_override| meta_override.name) {
+        Some(name) => Cow::Owned(name),
+        None => Cow::Borrowed(name),
+    }
+}
+
+pub fn poster<'a>(id: &str, poster: &'a Option<Url>) -> Cow<'a, Option<Url>> {
+    match meta_override(id).and_then(|meta_override| meta_override.poster) {
+        Some(poster) => Cow::Owned(Some(poster)),
+        None => Cow::Borrowed(poster),
+    }
+}
+
+pub fn meta_item_preview(meta_item: &MetaItemPreview) -> Cow<'_, MetaItemPreview> {
+    match meta_override(&meta_item.id) {
+        Some(meta_override) => {
+            let mut meta_item = meta_item.to_owned();
+            apply(&mut meta_item, meta_override);
+            Cow::Owned(meta_item)
+        }
+        None => Cow::Borrowed(meta_item),
+    }
+}
+
+pub fn meta_item(meta_item: Cow<'_, MetaItem>) -> Cow<'_, MetaItem> {
+    match meta_override(&meta_item.preview.id) {
+        Some(meta_override) => {
+            let mut meta_item = meta_item.into_owned();
+            apply(&mut meta_item.preview, meta_override);
+            Cow::Owned(meta_item)
+        }
+        None => meta_item,
+    }
+}
+
+fn apply(meta_item: &mut MetaItemPreview, meta_override: MetaOverride) {
+    if let Some(poster) = meta_override.poster {
+        meta_item.poster = Some(poster);
+    }
+    if let Some(name) = meta_override.name {
+        meta_item.name = name;
+    }
+}
+
+pub fn clear() {
+    META_OVERRIDES
+        .write()
+        .expect("meta overrides write failed")
+        .clear();
+}
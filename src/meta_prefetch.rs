@@ -0,0 +1,137 @@
+use std::sync::RwLock;
+
+use chrono::{DateTime, Utc};
+use lazy_static::lazy_static;
+use serde::Serialize;
+use url::Url;
+
+use stremio_core::{
+    constants::META_RESOURCE_NAME,
+    models::continue_watching_preview::ContinueWatchingPreview,
+    types::addon::{Descriptor, ResourcePath, ResourceRequest},
+};
+
+use crate::{prefetch, web_settings};
+
+lazy_static! {
+    static ref STATUS: RwLock<PrefetchRun> = Default::default();
+}
+
+#[derive(Default)]
+struct PrefetchRun {
+    last_run: Option<DateTime<Utc>>,
+    skipped_by_data_saver: bool,
+    items: Vec<(String, Vec<ResourceRequest>)>,
+}
+
+#[derive(Clone, Copy, PartialEq, Eq, Serialize, Debug)]
+pub enum RequestStatus {
+    Loading,
+    Ready,
+    /// It failed or it was evicted by the later prefetches, core loads it again
+    Dropped,
+}
+
+#[derive(Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct PrefetchedRequest {
+    pub transport_url: Url,
+    pub status: RequestStatus,
+}
+
+#[derive(Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct PrefetchedItem {
+    pub id: String,
+    pub requests: Vec<PrefetchedRequest>,
+}
+
+#[derive(Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct PrefetchStatus {
+    /// Number of the top Continue Watching items prefetched, `0` when it's disabled
+    pub max_items: u32,
+    pub last_run: Option<DateTime<Utc>>,
+    /// The last run did nothing as the data saver is on
+    pub skipped_by_data_saver: bool,
+    pub items: Vec<PrefetchedItem>,
+}
+
+/// The meta requests of the top Continue Watching items, to every addon which provides their meta
+pub fn requests(
+    continue_watching_preview: &ContinueWatchingPreview,
+    addons: &[Descriptor],
+) -> Vec<(String, Vec<ResourceRequest>)> {
+    let max_items = web_settings::web_settings()
+        .catalogs
+        .prefetch_continue_watching;
+    continue_watching_preview
+        .items
+        .iter()
+        .take(max_items as usize)
+        .map(|item| {
+            let path = ResourcePath::without_extra(
+                META_RESOURCE_NAME,
+                &item.library_item.r#type,
+                &item.library_item.id,
+            );
+            let requests = addons
+                .iter()
+                .filter(|addon| addon.manifest.is_resource_supported(&path))
+                .map(|addon| ResourceRequest::new(addon.transport_url.to_owned(), path.to_owned()))
+                .collect();
+            (item.library_item.id.to_owned(), requests)
+        })
+        .collect()
+}
+
+/// Loads the meta details of the items in the background, so they are shown at once
+/// when they are opened. Nothing is loaded while the data saver is on.
+pub fn prefetch(items: Vec<(String, Vec<ResourceRequest>)>, now: DateTime<Utc>) {
+    let data_saver = web_settings::web_settings().network.data_saver;
+    if !data_saver {
+        for request in items.iter().flat_map(|(_, requests)| requests) {
+            prefetch::prefetch(request, None);
+        }
+    }
+    *STATUS.write().expect("meta prefetch write failed") = PrefetchRun {
+        last_run: Some(now),
+        skipped_by_data_saver: data_saver,
+        items: if data_saver { vec![] } else { items },
+    };
+}
+
+pub fn status() -> PrefetchStatus {
+    let status = STATUS.read().expect("meta prefetch read failed");
+    PrefetchStatus {
+        max_items: web_settings::web_settings()
+            .catalogs
+            .prefetch_continue_watching,
+        last_run: status.last_run,
+        skipped_by_data_saver: status.skipped_by_data_saver,
+        items: status
+            .items
+            .iter()
+            .map(|(id, requests)| PrefetchedItem {
+                id: id.to_owned(),
+                requests: requests
+                    .iter()
+                    .map(|request| PrefetchedRequest {
+                        transport_url: request.base.to_owned(),
+                        status: if prefetch::is_prefetched(request) {
+                            RequestStatus::Ready
+                        } else if prefetch::is_loading(request) {
+                            RequestStatus::Loading
+                        } else {
+                            RequestStatus::Dropped
+                        },
+                    })
+                    .collect(),
+            })
+            .collect(),
+    }
+}
+
+pub fn clear() {
+    *STATUS.write().expect("meta prefetch write failed") = Default::default();
+}
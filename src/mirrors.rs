@@ -0,0 +1,274 @@
+use std::{collections::HashMap, sync::RwLock};
+
+use futures::{future, FutureExt, TryFutureExt};
+use http::Request;
+use lazy_static::lazy_static;
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use url::Url;
+
+use stremio_core::{
+    addon_transport::AddonTransport,
+    constants::ADDON_MANIFEST_PATH,
+    runtime::{Env, EnvError, RuntimeEvent, TryEnvFuture},
+    types::addon::{Manifest, ResourcePath, ResourceResponse},
+};
+
+use crate::{
+    env::WebEnv,
+    model::WebModelField,
+    retry::is_transient,
+    stremio_core_web::{emit_event, persist_addon_mirrors},
+};
+
+pub const ADDON_MIRRORS_STORAGE_KEY: &str = "addon_mirrors";
+
+lazy_static! {
+    /// Mirrors of the installed addons, by transport url
+    static ref MIRRORS: RwLock<HashMap<Url, AddonMirrors>> = Default::default();
+    /// The mirror the last request of the addon succeeded through, when it's not the transport url
+    static ref ACTIVE_MIRRORS: RwLock<HashMap<Url, Url>> = Default::default();
+}
+
+#[derive(Clone, Default, PartialEq, Serialize, Deserialize, Debug)]
+#[serde(rename_all = "camelCase")]
+pub struct AddonMirrors {
+    /// Listed in `behaviorHints.mirrors` of the manifest, refreshed whenever it's fetched
+    #[serde(default)]
+    pub declared: Vec<Url>,
+    /// Added by the user, tried after the declared ones
+    #[serde(default)]
+    pub user: Vec<Url>,
+}
+
+impl AddonMirrors {
+    fn is_empty(&self) -> bool {
+        self.declared.is_empty() && self.user.is_empty()
+    }
+}
+
+#[derive(Deserialize, Debug)]
+#[serde(tag = "action", content = "args")]
+pub enum MirrorsAction {
+    #[serde(rename_all = "camelCase")]
+    Add { transport_url: Url, mirror: Url },
+    #[serde(rename_all = "camelCase")]
+    Remove { transport_url: Url, mirror: Url },
+}
+
+#[derive(Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct MirrorsPreview {
+    pub declared: Vec<Url>,
+    pub user: Vec<Url>,
+    /// `None` while the addon is reached through its transport url
+    pub active: Option<Url>,
+}
+
+pub fn set_mirrors(mirrors: HashMap<Url, AddonMirrors>) {
+    *MIRRORS.write().expect("mirrors write failed") = mirrors;
+}
+
+/// Applies the action and returns the updated mirrors to be persisted
+pub fn update_mirrors(action: MirrorsAction) -> HashMap<Url, AddonMirrors> {
+    let mut mirrors = MIRRORS.write().expect("mirrors write failed");
+    let transport_url = match action {
+        MirrorsAction::Add {
+            transport_url,
+            mirror,
+        } => {
+            let addon_mirrors = mirrors.entry(transport_url.to_owned()).or_default();
+            if mirror != transport_url && !addon_mirrors.user.contains(&mirror) {
+                addon_mirrors.user.push(mirror);
+            }
+            transport_url
+        }
+        MirrorsAction::Remove {
+            transport_url,
+            mirror,
+        } => {
+            if let Some(addon_mirrors) = mirrors.get_mut(&transport_url) {
+                addon_mirrors
+                    .user
+                    .retain(|user_mirror| *user_mirror != mirror);
+            }
+            let mut active_mirrors = ACTIVE_MIRRORS.write().expect("active mirrors write failed");
+            if active_mirrors.get(&transport_url) == Some(&mirror) {
+                active_mirrors.remove(&transport_url);
+            }
+            transport_url
+        }
+    };
+    if mirrors
+        .get(&transport_url)
+        .map_or(false, AddonMirrors::is_empty)
+    {
+        mirrors.remove(&transport_url);
+    }
+    mirrors.to_owned()
+}
+
+/// The mirrors of every addon having any, along with the active one
+pub fn previews() -> HashMap<Url, MirrorsPreview> {
+    let active_mirrors = ACTIVE_MIRRORS.read().expect("active mirrors read failed");
+    MIRRORS
+        .read()
+        .expect("mirrors read failed")
+        .iter()
+        .map(|(transport_url, addon_mirrors)| {
+            (
+                transport_url.to_owned(),
+                MirrorsPreview {
+                    declared: addon_mirrors.declared.to_owned(),
+                    user: addon_mirrors.user.to_owned(),
+                    active: active_mirrors.get(transport_url).cloned(),
+                },
+            )
+        })
+        .collect()
+}
+
+/// The urls to try in order: the last one which worked, the transport url, then the rest of the mirrors
+fn candidates(transport_url: &Url) -> Vec<Url> {
+    let active = ACTIVE_MIRRORS
+        .read()
+        .expect("active mirrors read failed")
+        .get(transport_url)
+        .cloned();
+    let mirrors = MIRRORS.read().expect("mirrors read failed");
+    let mut candidates = active.into_iter().collect::<Vec<_>>();
+    let rest = std::iter::once(transport_url).chain(
+        mirrors
+            .get(transport_url)
+            .into_iter()
+            .flat_map(|addon_mirrors| addon_mirrors.declared.iter().chain(&addon_mirrors.user)),
+    );
+    for url in rest {
+        if !candidates.contains(url) {
+            candidates.push(url.to_owned());
+        }
+    }
+    candidates
+}
+
+fn set_active(transport_url: &Url, url: &Url) {
+    let mut active_mirrors = ACTIVE_MIRRORS.write().expect("active mirrors write failed");
+    let changed = if url == transport_url {
+        active_mirrors.remove(transport_url).is_some()
+    } else {
+        active_mirrors.insert(transport_url.to_owned(), url.to_owned()) != Some(url.to_owned())
+    };
+    drop(active_mirrors);
+    if changed {
+        emit_event(&RuntimeEvent::NewState(vec![
+            WebModelField::InstalledAddons,
+        ]));
+    }
+}
+
+fn set_declared(transport_url: &Url, declared: Vec<Url>) {
+    let mut mirrors = MIRRORS.write().expect("mirrors write failed");
+    let addon_mirrors = mirrors.entry(transport_url.to_owned()).or_default();
+    let changed = addon_mirrors.declared != declared;
+    addon_mirrors.declared = declared;
+    if addon_mirrors.is_empty() {
+        mirrors.remove(transport_url);
+    }
+    if changed {
+        persist_addon_mirrors(&mirrors);
+    }
+}
+
+/// Only the http mirrors of the same kind of transport url are taken, anything else is ignored
+fn declared_mirrors(transport_url: &Url, manifest: &Value) -> Vec<Url> {
+    manifest
+        .pointer("/behaviorHints/mirrors")
+        .and_then(Value::as_array)
+        .into_iter()
+        .flatten()
+        .filter_map(Value::as_str)
+        .filter_map(|mirror| Url::parse(mirror).ok())
+        .filter(|mirror| matches!(mirror.scheme(), "http" | "https"))
+        .filter(|mirror| mirror.path().ends_with(ADDON_MANIFEST_PATH))
+        .filter(|mirror| mirror != transport_url)
+        .collect()
+}
+
+/// Tries the urls one after another for as long as they fail because of the network or of being unavailable
+fn failover<T: 'static>(
+    transport_url: Url,
+    request: impl Fn(&Url) -> TryEnvFuture<T> + 'static,
+) -> TryEnvFuture<T> {
+    async move {
+        let mut candidates = candidates(&transport_url).into_iter().peekable();
+        while let Some(url) = candidates.next() {
+            match request(&url).await {
+                // the next mirror gets the request
+                Err(error) if candidates.peek().is_some() && is_transient(&error) => {}
+                result => {
+                    if result.is_ok() {
+                        set_active(&transport_url, &url);
+                    }
+                    return result;
+                }
+            }
+        }
+        unreachable!("the transport url is always a candidate")
+    }
+    .boxed_local()
+}
+
+/// Sends the requests of an addon through its mirrors when the transport url is unavailable.
+/// The transport of every mirror is built the same way as the one of the transport url.
+pub struct MirrorTransport {
+    transport_url: Url,
+    transport: fn(&Url) -> Box<dyn AddonTransport>,
+}
+
+impl MirrorTransport {
+    pub fn new(transport_url: Url, transport: fn(&Url) -> Box<dyn AddonTransport>) -> Self {
+        Self {
+            transport_url,
+            transport,
+        }
+    }
+}
+
+impl AddonTransport for MirrorTransport {
+    fn resource(&self, path: &ResourcePath) -> TryEnvFuture<ResourceResponse> {
+        let transport = self.transport;
+        let path = path.to_owned();
+        failover(self.transport_url.to_owned(), move |url| {
+            transport(url).resource(&path)
+        })
+    }
+    fn manifest(&self) -> TryEnvFuture<Manifest> {
+        // the mirrors are declared by the addons serving plain JSON only
+        if !self.transport_url.path().ends_with(ADDON_MANIFEST_PATH) {
+            let transport = self.transport;
+            return failover(self.transport_url.to_owned(), move |url| {
+                transport(url).manifest()
+            });
+        }
+        let transport_url = self.transport_url.to_owned();
+        failover(transport_url.to_owned(), |url| {
+            let request = Request::get(url.as_str())
+                .body(())
+                .expect("request builder failed");
+            WebEnv::fetch::<_, Value>(request)
+        })
+        .and_then(move |manifest| {
+            set_declared(&transport_url, declared_mirrors(&transport_url, &manifest));
+            future::ready(serde_json::from_value(manifest).map_err(EnvError::from))
+        })
+        .boxed_local()
+    }
+}
+
+/// Drops the active mirrors, the next requests go through the transport urls first
+pub fn clear() {
+    ACTIVE_MIRRORS
+        .write()
+        .expect("active mirrors write failed")
+        .clear();
+}
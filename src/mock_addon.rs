@@ -0,0 +1,97 @@
+use std::collections::HashMap;
+use std::sync::RwLock;
+
+use futures::{future, FutureExt};
+use lazy_static::lazy_static;
+use serde::Deserialize;
+use url::Url;
+
+use stremio_core::{
+    addon_transport::AddonTransport,
+    runtime::{EnvError, TryEnvFuture},
+    types::addon::{Manifest, ResourcePath, ResourceResponse},
+};
+
+lazy_static! {
+    static ref MOCK_ADDONS: RwLock<HashMap<Url, MockAddon>> = Default::default();
+}
+
+/// Fixtures of an in-memory addon, used to run the runtime without network access.
+///
+/// Resources are keyed by the same paths an http addon would serve them on,
+/// e.g. `/catalog/movie/top.json` or `/catalog/movie/top/skip=100.json`.
+/// Requests with extra values fall back to the resource without them.
+#[derive(Clone, Deserialize, Debug)]
+#[serde(rename_all = "camelCase")]
+pub struct MockAddon {
+    pub manifest: Manifest,
+    #[serde(default)]
+    pub resources: HashMap<String, ResourceResponse>,
+}
+
+/// Transport for addons installed with a `mock://` transport url
+pub struct MockAddonTransport {
+    transport_url: Url,
+}
+
+impl MockAddonTransport {
+    pub fn new(transport_url: Url) -> Self {
+        Self { transport_url }
+    }
+    fn not_found_error(&self) -> EnvError {
+        EnvError::Fetch(format!(
+            "Mock addon {} is not registered",
+            self.transport_url
+        ))
+    }
+}
+
+impl AddonTransport for MockAddonTransport {
+    fn resource(&self, path: &ResourcePath) -> TryEnvFuture<ResourceResponse> {
+        let mock_addons = MOCK_ADDONS.read().expect("mock addons read failed");
+        let mock_addon = match mock_addons.get(&self.transport_url) {
+            Some(mock_addon) => mock_addon,
+            None => return future::err(self.not_found_error()).boxed_local(),
+        };
+        let path_without_extra = ResourcePath {
+            extra: vec![],
+            ..path.to_owned()
+        };
+        let response = mock_addon
+            .resources
+            .get(&path.to_url_path())
+            .or_else(|| mock_addon.resources.get(&path_without_extra.to_url_path()))
+            .cloned();
+        match response {
+            Some(response) => future::ok(response).boxed_local(),
+            None => future::err(EnvError::Fetch(
+                "Unexpected HTTP status code 404".to_owned(),
+            ))
+            .boxed_local(),
+        }
+    }
+    fn manifest(&self) -> TryEnvFuture<Manifest> {
+        match MOCK_ADDONS
+            .read()
+            .expect("mock addons read failed")
+            .get(&self.transport_url)
+        {
+            Some(mock_addon) => future::ok(mock_addon.manifest.to_owned()).boxed_local(),
+            None => future::err(self.not_found_error()).boxed_local(),
+        }
+    }
+}
+
+pub fn register_mock_addon(transport_url: Url, mock_addon: MockAddon) {
+    MOCK_ADDONS
+        .write()
+        .expect("mock addons write failed")
+        .insert(transport_url, mock_addon);
+}
+
+pub fn unregister_mock_addon(transport_url: &Url) {
+    MOCK_ADDONS
+        .write()
+        .expect("mock addons write failed")
+        .remove(transport_url);
+}
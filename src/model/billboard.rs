@@ -0,0 +1,198 @@
+use chrono::{DateTime, Datelike, Utc};
+use serde::{Deserialize, Serialize};
+use url::Url;
+
+use stremio_core::deep_links::{LibraryItemDeepLinks, MetaItemDeepLinks, StreamDeepLinks};
+use stremio_core::models::catalogs_with_extra::CatalogsWithExtra;
+use stremio_core::models::common::Loadable;
+use stremio_core::models::continue_watching_preview::ContinueWatchingPreview;
+use stremio_core::models::ctx::Ctx;
+use stremio_core::types::streams::StreamsItemKey;
+
+use crate::blocklist;
+use crate::model::deep_links_ext::DeepLinksExt;
+use crate::trailers::{self, YouTubeTrailer};
+use crate::web_settings::{ContinueWatchingSettings, TrailersSettings};
+
+const METAHUB_URL: &str = "https://images.metahub.space";
+
+/// Which items are featured in the billboard of the Board, part of the remote config.
+#[derive(Clone, Deserialize, Debug)]
+#[serde(rename_all = "camelCase", default)]
+pub struct BillboardConfig {
+    /// Catalog of the Board to feature the items of, Continue Watching is featured without it
+    pub catalog: Option<BillboardCatalog>,
+    pub count: usize,
+}
+
+impl Default for BillboardConfig {
+    fn default() -> Self {
+        Self {
+            catalog: None,
+            count: 5,
+        }
+    }
+}
+
+#[derive(Clone, Deserialize, Debug)]
+#[serde(rename_all = "camelCase")]
+pub struct BillboardCatalog {
+    pub transport_url: Url,
+    pub r#type: String,
+    pub id: String,
+}
+
+#[derive(Serialize)]
+#[serde(untagged)]
+pub enum BillboardDeepLinks {
+    MetaItem(MetaItemDeepLinks),
+    LibraryItem(LibraryItemDeepLinks),
+}
+
+#[derive(Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct BillboardItem<'a> {
+    pub id: &'a String,
+    pub r#type: &'a String,
+    pub name: &'a String,
+    pub description: Option<&'a String>,
+    pub background: Option<Url>,
+    pub logo: Option<Url>,
+    pub trailer_deep_links: Option<StreamDeepLinks>,
+    /// Embeddable urls of the trailer when it's on YouTube
+    pub trailer: Option<YouTubeTrailer<'a>>,
+    pub deep_links: BillboardDeepLinks,
+}
+
+/// Featured items of the configured catalog or of Continue Watching when it is not loaded,
+/// the selection rotates once per day.
+/// The items hidden from Continue Watching by the settings and the blocked items are not featured either.
+pub fn billboard<'a>(
+    board: &'a CatalogsWithExtra,
+    continue_watching_preview: &'a ContinueWatchingPreview,
+    ctx: &'a Ctx,
+    config: &BillboardConfig,
+    continue_watching_settings: &ContinueWatchingSettings,
+    trailers_settings: &TrailersSettings,
+    now: DateTime<Utc>,
+) -> Vec<BillboardItem<'a>> {
+    let catalog = config.catalog.as_ref().and_then(|billboard_catalog| {
+        board
+            .catalogs
+            .iter()
+            .filter_map(|catalog| catalog.first())
+            .find(|catalog| {
+                catalog.request.base == billboard_catalog.transport_url
+                    && catalog.request.path.r#type == billboard_catalog.r#type
+                    && catalog.request.path.id == billboard_catalog.id
+            })
+            .and_then(|catalog| match &catalog.content {
+                Some(Loadable::Ready(meta_items)) if !meta_items.is_empty() => {
+                    Some((catalog, meta_items))
+                }
+                _ => None,
+            })
+    });
+    let blocked_ids = blocklist::blocked_ids();
+    let items = match catalog {
+        Some((catalog, meta_items)) => meta_items
+            .iter()
+            .filter(|meta_item| !blocked_ids.contains(&meta_item.id))
+            .map(|meta_item| BillboardItem {
+                id: &meta_item.id,
+                r#type: &meta_item.r#type,
+                name: &meta_item.name,
+                description: meta_item.description.as_ref(),
+                background: meta_item
+                    .background
+                    .as_ref()
+                    .map(full_size_background)
+                    .or_else(|| metahub_background(&meta_item.id)),
+                logo: meta_item
+                    .logo
+                    .to_owned()
+                    .or_else(|| metahub_logo(&meta_item.id)),
+                trailer_deep_links: meta_item.trailer_streams.first().map(|stream| {
+                    StreamDeepLinks::from((stream, &ctx.profile.settings)).into_web_deep_links()
+                }),
+                trailer: meta_item.trailer_streams.first().and_then(|stream| {
+                    trailers::youtube_trailer(
+                        stream,
+                        meta_item,
+                        &catalog.request.base,
+                        &ctx.profile.settings,
+                        trailers_settings,
+                    )
+                }),
+                deep_links: BillboardDeepLinks::MetaItem(
+                    MetaItemDeepLinks::from((meta_item, &catalog.request)).into_web_deep_links(),
+                ),
+            })
+            .collect::<Vec<_>>(),
+        None => continue_watching_preview
+            .items
+            .iter()
+            .map(|item| &item.library_item)
+            .filter(|library_item| {
+                let streams_item = library_item.state.video_id.as_ref().and_then(|video_id| {
+                    ctx.streams.items.get(&StreamsItemKey {
+                        meta_id: library_item.id.to_owned(),
+                        video_id: video_id.to_owned(),
+                    })
+                });
+                !continue_watching_settings.excludes(library_item, streams_item)
+            })
+            .map(|library_item| BillboardItem {
+                id: &library_item.id,
+                r#type: &library_item.r#type,
+                name: &library_item.name,
+                description: None,
+                background: metahub_background(&library_item.id),
+                logo: metahub_logo(&library_item.id),
+                trailer_deep_links: None,
+                trailer: None,
+                deep_links: BillboardDeepLinks::LibraryItem(
+                    LibraryItemDeepLinks::from((library_item, None, &ctx.profile.settings))
+                        .into_web_deep_links(),
+                ),
+            })
+            .collect::<Vec<_>>(),
+    };
+    rotate(items, now.num_days_from_ce() as usize)
+        .take(config.count)
+        .collect()
+}
+
+/// Starts the items at the offset of the day, so the selection is the same for the whole day
+fn rotate<T>(mut items: Vec<T>, day: usize) -> impl Iterator<Item = T> {
+    let offset = match items.len() {
+        0 => 0,
+        len => day % len,
+    };
+    items.rotate_left(offset);
+    items.into_iter()
+}
+
+/// Metahub serves the medium size by default, the billboard spans the whole width
+fn full_size_background(url: &Url) -> Url {
+    match url.as_str().strip_prefix(METAHUB_URL) {
+        Some(path) => Url::parse(&format!(
+            "{METAHUB_URL}{}",
+            path.replacen("/background/medium/", "/background/large/", 1)
+        ))
+        .unwrap_or_else(|_| url.to_owned()),
+        None => url.to_owned(),
+    }
+}
+
+fn metahub_background(id: &str) -> Option<Url> {
+    id.starts_with("tt")
+        .then(|| Url::parse(&format!("{METAHUB_URL}/background/large/{id}/img")).ok())
+        .flatten()
+}
+
+fn metahub_logo(id: &str) -> Option<Url> {
+    id.starts_with("tt")
+        .then(|| Url::parse(&format!("{METAHUB_URL}/logo/medium/{id}/img")).ok())
+        .flatten()
+}
@@ -0,0 +1,87 @@
+use std::sync::RwLock;
+
+use lazy_static::lazy_static;
+use serde_json::{Map, Value};
+use wasm_bindgen::JsValue;
+
+/// Routes of the web deep links which were renamed, with the route older web versions use
+const LEGACY_ROUTES: [(&str, &str); 1] = [("#/detail/", "#/metadetails/")];
+/// Suffix of the alternative field holding the legacy route of a deep link
+const LEGACY_FIELD_SUFFIX: &str = "Legacy";
+
+lazy_static! {
+    /// Set by the shells embedding older web versions
+    static ref LEGACY_ROUTES_ENABLED: RwLock<bool> = Default::default();
+}
+
+pub fn set_legacy_routes_enabled(enabled: bool) {
+    *LEGACY_ROUTES_ENABLED
+        .write()
+        .expect("legacy routes write failed") = enabled;
+}
+
+/// Adds a `{field}Legacy` field next to every deep link of the serialized state
+/// which has a legacy route, e.g. `metaDetailsVideosLegacy`. The state is returned as it is
+/// when the legacy routes are not enabled.
+pub fn with_legacy_routes(state: JsValue) -> JsValue {
+    if !*LEGACY_ROUTES_ENABLED
+        .read()
+        .expect("legacy routes read failed")
+    {
+        return state;
+    }
+    match state.into_serde::<Value>() {
+        Ok(mut value) => {
+            add_legacy_routes(&mut value);
+            JsValue::from_serde(&value).unwrap()
+        }
+        Err(_) => state,
+    }
+}
+
+/// The deep link in the route format of the older web versions, `None` when it's the same
+pub fn legacy_route(deep_link: &str) -> Option<String> {
+    LEGACY_ROUTES.iter().find_map(|(route, legacy_route)| {
+        deep_link
+            .strip_prefix(route)
+            .map(|rest| format!("{legacy_route}{rest}"))
+    })
+}
+
+fn add_legacy_routes(value: &mut Value) {
+    match value {
+        Value::Object(object) => {
+            for (key, value) in object.iter_mut() {
+                match value {
+                    Value::Object(deep_links)
+                        if key == "deepLinks" || key.ends_with("DeepLinks") =>
+                    {
+                        add_legacy_fields(deep_links)
+                    }
+                    value => add_legacy_routes(value),
+                }
+            }
+        }
+        Value::Array(values) => values.iter_mut().for_each(add_legacy_routes),
+        _ => {}
+    }
+}
+
+fn add_legacy_fields(deep_links: &mut Map<String, Value>) {
+    // e.g. the links of the external players
+    deep_links
+        .values_mut()
+        .filter_map(|value| value.as_object_mut())
+        .for_each(add_legacy_fields);
+    let legacy_fields = deep_links
+        .iter()
+        .filter_map(|(key, value)| {
+            let legacy_route = legacy_route(value.as_str()?)?;
+            Some((
+                format!("{key}{LEGACY_FIELD_SUFFIX}"),
+                Value::String(legacy_route),
+            ))
+        })
+        .collect::<Vec<_>>();
+    deep_links.extend(legacy_fields);
+}
@@ -8,3 +8,9 @@ mod library_item_deep_links;
 mod meta_item_deep_links;
 mod stream_deep_links;
 mod video_deep_links;
+
+mod protocol_link;
+pub use protocol_link::*;
+
+mod legacy_routes;
+pub use legacy_routes::*;
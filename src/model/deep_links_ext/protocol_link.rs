@@ -0,0 +1,110 @@
+use percent_encoding::percent_decode_str;
+use serde::Serialize;
+use url::Url;
+
+use stremio_core::constants::ADDON_MANIFEST_PATH;
+use stremio_core::types::profile::Profile;
+
+const PROTOCOL: &str = "stremio://";
+/// Hosts of the addons served over http, e.g. the streaming server and the local addons
+const HTTP_HOSTS: &[&str] = &["127.0.0.1", "localhost"];
+
+/// What opening a `stremio://` link does, shown to the user before acting on it
+#[derive(Clone, PartialEq, Serialize, Debug)]
+#[serde(tag = "type", content = "content")]
+pub enum ProtocolLink {
+    /// `stremio://{host}/{path}/manifest.json`, the addon is served over https
+    /// unless it is a local one
+    #[serde(rename_all = "camelCase")]
+    InstallAddon {
+        transport_url: Url,
+        /// Name of the addon when it is already installed
+        installed_name: Option<String>,
+    },
+    /// `stremio:///detail/{type}/{id}/{video_id}`
+    #[serde(rename_all = "camelCase")]
+    OpenMetaItem {
+        r#type: String,
+        id: String,
+        video_id: Option<String>,
+        web_link: String,
+    },
+    /// Any other route of the app, e.g. `stremio:///search?search=...`
+    #[serde(rename_all = "camelCase")]
+    Navigate { web_link: String },
+}
+
+impl ProtocolLink {
+    /// `None` when the link is not a `stremio://` link or it is malformed
+    pub fn parse(link: &str, profile: &Profile) -> Option<Self> {
+        let rest = link.strip_prefix(PROTOCOL)?;
+        if rest.starts_with('/') {
+            let web_link = link.replacen(PROTOCOL, "#", 1);
+            let path = rest.split(['?', '#']).next().unwrap_or_default();
+            let segments = path
+                .split('/')
+                .filter(|segment| !segment.is_empty())
+                .map(decode_segment)
+                .collect::<Vec<_>>();
+            return match segments.as_slice() {
+                [route, r#type, id] if route == "detail" => Some(ProtocolLink::OpenMetaItem {
+                    r#type: r#type.to_owned(),
+                    id: id.to_owned(),
+                    video_id: None,
+                    web_link,
+                }),
+                [route, r#type, id, video_id] if route == "detail" => {
+                    Some(ProtocolLink::OpenMetaItem {
+                        r#type: r#type.to_owned(),
+                        id: id.to_owned(),
+                        video_id: Some(video_id.to_owned()),
+                        web_link,
+                    })
+                }
+                [route, ..] if route == "detail" => None,
+                _ => Some(ProtocolLink::Navigate { web_link }),
+            };
+        }
+        let transport_url = if rest.starts_with("http://") || rest.starts_with("https://") {
+            Url::parse(rest)
+        } else {
+            Url::parse(&format!("https://{rest}")).and_then(|transport_url| {
+                match transport_url.host_str() {
+                    Some(host) if HTTP_HOSTS.contains(&host) => {
+                        Url::parse(&format!("http://{rest}"))
+                    }
+                    _ => Ok(transport_url),
+                }
+            })
+        }
+        .ok()
+        .filter(|transport_url| transport_url.path().ends_with(ADDON_MANIFEST_PATH))?;
+        let installed_name = profile
+            .addons
+            .iter()
+            .find(|addon| addon.transport_url == transport_url)
+            .map(|addon| addon.manifest.name.to_owned());
+        Some(ProtocolLink::InstallAddon {
+            transport_url,
+            installed_name,
+        })
+    }
+}
+
+/// The `stremio://` link installing the addon, `None` for the addons whose scheme
+/// would not be preserved by the link, e.g. a remote addon served over plain http
+pub fn addon_install_link(transport_url: &Url) -> Option<String> {
+    let local = transport_url
+        .host_str()
+        .map_or(false, |host| HTTP_HOSTS.contains(&host));
+    match transport_url.scheme() {
+        "https" if !local => Some(transport_url.as_str().replacen("https://", PROTOCOL, 1)),
+        "http" if local => Some(transport_url.as_str().replacen("http://", PROTOCOL, 1)),
+        _ => None,
+    }
+}
+
+/// The segments are percent encoded, a `+` is kept as it is a valid character of the ids
+fn decode_segment(segment: &str) -> String {
+    percent_decode_str(segment).decode_utf8_lossy().into_owned()
+}
@@ -0,0 +1,210 @@
+use std::sync::RwLock;
+
+use lazy_static::lazy_static;
+use serde::{Deserialize, Serialize};
+
+use stremio_core::{
+    constants::{CATALOG_PAGE_SIZE, SKIP_EXTRA_NAME},
+    types::{
+        addon::{ExtraValue, ResourceRequest},
+        resource::PosterShape,
+    },
+};
+
+/// Height of a poster relative to its width
+const POSTER_RATIO: f64 = 1.464;
+
+lazy_static! {
+    static ref VIEWPORT: RwLock<Option<Viewport>> = Default::default();
+}
+
+/// The area of the grid as described by the UI, in CSS pixels
+#[derive(Clone, Copy, PartialEq, Deserialize, Debug)]
+#[serde(rename_all = "camelCase", default)]
+pub struct Viewport {
+    pub width: f64,
+    pub height: f64,
+    /// Width of a poster item, the items of the other shapes are of the same height
+    pub item_width: f64,
+    /// Space between the items, horizontally and vertically
+    pub gap: f64,
+}
+
+impl Default for Viewport {
+    fn default() -> Self {
+        Self {
+            width: 0.0,
+            height: 0.0,
+            item_width: 150.0,
+            gap: 16.0,
+        }
+    }
+}
+
+/// How the grid is laid out in the viewport
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub struct GridLayout {
+    pub columns: usize,
+    pub rows_per_screen: usize,
+}
+
+#[derive(Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct GridDensity {
+    pub columns: usize,
+    pub rows_per_screen: usize,
+    /// The items of a page of the addon rounded down to full rows
+    pub items_per_page: usize,
+    /// Items of the full rows, the last row is completed by the next page
+    pub full_row_items: usize,
+    /// Skip of the next page, the items loaded so far
+    pub next_page_skip: Option<usize>,
+}
+
+/// Returns whether the viewport changed
+pub fn set_viewport(viewport: Option<Viewport>) -> bool {
+    let mut current = VIEWPORT.write().expect("viewport write failed");
+    if *current == viewport {
+        return false;
+    }
+    *current = viewport;
+    true
+}
+
+/// `None` until the UI describes the viewport
+pub fn grid_layout(poster_shape: &PosterShape, items_per_row: Option<usize>) -> Option<GridLayout> {
+    let viewport = (*VIEWPORT.read().expect("viewport read failed"))?;
+    let item_height = viewport.item_width * POSTER_RATIO;
+    let item_width = match poster_shape {
+        PosterShape::Square => item_height,
+        PosterShape::Landscape => item_height * 16.0 / 9.0,
+        _ => viewport.item_width,
+    };
+    let columns = items_per_row.unwrap_or_else(|| {
+        ((viewport.width + viewport.gap) / (item_width + viewport.gap)).floor() as usize
+    });
+    let rows_per_screen =
+        ((viewport.height + viewport.gap) / (item_height + viewport.gap)).ceil() as usize;
+    Some(GridLayout {
+        columns: columns.max(1),
+        rows_per_screen: rows_per_screen.max(1),
+    })
+}
+
+/// Skeleton entries while a page is loading, a screen of them
+pub fn placeholder_count(layout: Option<&GridLayout>) -> usize {
+    layout.map_or(CATALOG_PAGE_SIZE, |layout| {
+        layout.columns * layout.rows_per_screen
+    })
+}
+
+/// Only full rows are shown while there is a next page, so the pages align with the rows
+pub fn grid_density(
+    layout: &GridLayout,
+    loaded_items: usize,
+    next_page: Option<&ResourceRequest>,
+) -> GridDensity {
+    let next_page_skip = next_page.and_then(|next_page| {
+        next_page
+            .path
+            .extra
+            .iter()
+            .find(|extra_value| extra_value.name == SKIP_EXTRA_NAME)
+            .and_then(|extra_value| extra_value.value.parse().ok())
+    });
+    let full_row_items = match next_page {
+        Some(_) if loaded_items >= layout.columns => loaded_items / layout.columns * layout.columns,
+        _ => loaded_items,
+    };
+    GridDensity {
+        columns: layout.columns,
+        rows_per_screen: layout.rows_per_screen,
+        items_per_page: (CATALOG_PAGE_SIZE / layout.columns).max(1) * layout.columns,
+        full_row_items,
+        next_page_skip,
+    }
+}
+
+/// The request with its skip rounded down to a full row, `None` when it's already aligned.
+/// For the catalogs which are opened mid-way, e.g. by a shared link.
+pub fn aligned_request(request: &ResourceRequest, layout: &GridLayout) -> Option<ResourceRequest> {
+    let skip = request
+        .path
+        .extra
+        .iter()
+        .find(|extra_value| extra_value.name == SKIP_EXTRA_NAME)
+        .and_then(|extra_value| extra_value.value.parse::<usize>().ok())?;
+    let aligned_skip = skip / layout.columns * layout.columns;
+    if aligned_skip == skip {
+        return None;
+    }
+    let mut request = request.to_owned();
+    request
+        .path
+        .extra
+        .retain(|extra_value| extra_value.name != SKIP_EXTRA_NAME);
+    if aligned_skip > 0 {
+        request.path.extra.push(ExtraValue {
+            name: SKIP_EXTRA_NAME.to_owned(),
+            value: aligned_skip.to_string(),
+        });
+    }
+    Some(request)
+}
+
+#[cfg(test)]
+mod tests {
+    use url::Url;
+
+    use stremio_core::types::addon::ResourcePath;
+
+    use super::*;
+
+    fn request(skip: Option<&str>) -> ResourceRequest {
+        let mut extra = vec![ExtraValue {
+            name: "genre".to_owned(),
+            value: "Drama".to_owned(),
+        }];
+        if let Some(skip) = skip {
+            extra.push(ExtraValue {
+                name: SKIP_EXTRA_NAME.to_owned(),
+                value: skip.to_owned(),
+            });
+        }
+        ResourceRequest::new(
+            Url::parse("https://addon.example.com/manifest.json").unwrap(),
+            ResourcePath::with_extra("catalog", "movie", "top", &extra),
+        )
+    }
+
+    #[test]
+    fn aligned_skip() {
+        let layout = GridLayout {
+            columns: 7,
+            rows_per_screen: 4,
+        };
+        assert_eq!(
+            aligned_request(&request(Some("100")), &layout),
+            Some(request(Some("98")))
+        );
+        assert_eq!(
+            aligned_request(&request(Some("5")), &layout),
+            Some(request(None))
+        );
+        assert_eq!(aligned_request(&request(Some("98")), &layout), None);
+        assert_eq!(aligned_request(&request(None), &layout), None);
+    }
+
+    #[test]
+    fn full_rows() {
+        let layout = GridLayout {
+            columns: 7,
+            rows_per_screen: 4,
+        };
+        let density = grid_density(&layout, 100, Some(&request(Some("100"))));
+        assert_eq!(density.items_per_page, 98);
+        assert_eq!(density.full_row_items, 98);
+        assert_eq!(density.next_page_skip, Some(100));
+        assert_eq!(grid_density(&layout, 60, None).full_row_items, 60);
+    }
+}
@@ -0,0 +1,272 @@
+use std::cmp::Ordering;
+use std::collections::HashMap;
+use std::sync::RwLock;
+
+use chrono::{DateTime, Utc};
+use lazy_static::lazy_static;
+use serde::{Deserialize, Serialize};
+use url::{form_urlencoded, Url};
+
+use stremio_core::types::{
+    library::{LibraryBucket, LibraryItem},
+    resource::MetaItemPreview,
+};
+
+pub const WEB_SORT_QUERY_PARAM: &str = "webSort";
+pub const LIBRARY_SORT_KEYS_STORAGE_KEY: &str = "library_sort_keys";
+
+lazy_static! {
+    /// Selected web sort per library root (`library`, `continuewatching`)
+    static ref SELECTED_SORTS: RwLock<HashMap<String, WebSort>> = Default::default();
+    /// Details of the metas which core's library items don't have, by item id.
+    /// They are recorded from the metas loaded by the models.
+    static ref SORT_KEYS: RwLock<HashMap<String, SortKeys>> = Default::default();
+}
+
+#[derive(Clone, Default, PartialEq, Serialize, Deserialize, Debug)]
+#[serde(rename_all = "camelCase")]
+pub struct SortKeys {
+    pub rating: Option<f64>,
+    /// In minutes
+    pub runtime: Option<u32>,
+    pub released: Option<DateTime<Utc>>,
+}
+
+/// Sorts which are not supported by core's `LibraryWithFilters`,
+/// applied on top of the catalog before serialization.
+#[derive(Clone, Copy, PartialEq, Eq, Serialize, Deserialize, Debug)]
+#[serde(rename_all = "lowercase")]
+pub enum WebSort {
+    /// Most recently added first
+    DateAdded,
+    /// A-Z using a collation which ignores case, diacritics and leading articles
+    Alphabetical,
+    /// Highest IMDb rating first
+    Rating,
+    /// Shortest first
+    Runtime,
+    /// Most recently released first
+    Released,
+}
+
+impl WebSort {
+    pub const ALL: [WebSort; 5] = [
+        WebSort::DateAdded,
+        WebSort::Alphabetical,
+        WebSort::Rating,
+        WebSort::Runtime,
+        WebSort::Released,
+    ];
+
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            WebSort::DateAdded => "dateadded",
+            WebSort::Alphabetical => "alphabetical",
+            WebSort::Rating => "rating",
+            WebSort::Runtime => "runtime",
+            WebSort::Released => "released",
+        }
+    }
+
+    fn from_str(sort: &str) -> Option<Self> {
+        WebSort::ALL
+            .into_iter()
+            .find(|web_sort| web_sort.as_str() == sort)
+    }
+
+    /// Compares two items, the items without the sorted detail are last.
+    /// Ties are broken by the id so the order is stable between renders and pages.
+    pub fn compare(&self, a: &LibraryItem, b: &LibraryItem, language: &str) -> Ordering {
+        let ordering = match self {
+            WebSort::DateAdded => b.ctime.cmp(&a.ctime),
+            WebSort::Alphabetical => {
+                collation_key(&a.name, language).cmp(&collation_key(&b.name, language))
+            }
+            WebSort::Rating => {
+                let sort_keys = SORT_KEYS.read().expect("sort keys read failed");
+                let rating = |item: &LibraryItem| {
+                    sort_keys
+                        .get(&item.id)
+                        .and_then(|sort_keys| sort_keys.rating)
+                };
+                missing_last(rating(a), rating(b), |a, b| {
+                    b.partial_cmp(&a).unwrap_or(Ordering::Equal)
+                })
+            }
+            WebSort::Runtime => missing_last(runtime(a), runtime(b), |a, b| a.cmp(&b)),
+            WebSort::Released => {
+                let sort_keys = SORT_KEYS.read().expect("sort keys read failed");
+                let released = |item: &LibraryItem| {
+                    sort_keys
+                        .get(&item.id)
+                        .and_then(|sort_keys| sort_keys.released)
+                };
+                missing_last(released(a), released(b), |a, b| b.cmp(&a))
+            }
+        };
+        ordering.then_with(|| a.id.cmp(&b.id))
+    }
+}
+
+pub fn set_sort_keys(sort_keys: HashMap<String, SortKeys>) {
+    *SORT_KEYS.write().expect("sort keys write failed") = sort_keys;
+}
+
+/// Records the details of the metas which are in the library,
+/// returns the sort keys to be persisted when any of them changed
+pub fn record<'a>(
+    meta_items: impl Iterator<Item = &'a MetaItemPreview>,
+    library: &LibraryBucket,
+) -> Option<HashMap<String, SortKeys>> {
+    let mut sort_keys = SORT_KEYS.write().expect("sort keys write failed");
+    let mut changed = false;
+    for meta_item in meta_items.filter(|meta_item| library.items.contains_key(&meta_item.id)) {
+        let meta_sort_keys = SortKeys {
+            rating: meta_item
+                .links
+                .iter()
+                .find(|link| link.category == "imdb")
+                .and_then(|link| link.name.parse().ok()),
+            runtime: meta_item.runtime.as_deref().and_then(parse_runtime),
+            released: meta_item.released,
+        };
+        if sort_keys.get(&meta_item.id) != Some(&meta_sort_keys) {
+            sort_keys.insert(meta_item.id.to_owned(), meta_sort_keys);
+            changed = true;
+        }
+    }
+    if !changed {
+        return None;
+    }
+    sort_keys.retain(|id, _| library.items.contains_key(id));
+    Some(sort_keys.to_owned())
+}
+
+/// The web sort of the `#/library` or `#/continuewatching` location, `None` for the sorts of core
+pub fn parse_web_sort(location_hash: &str) -> Option<WebSort> {
+    let (_, query) = location_hash.split_once('?')?;
+    form_urlencoded::parse(query.as_bytes())
+        .find(|(key, _)| key == WEB_SORT_QUERY_PARAM)
+        .and_then(|(_, sort)| WebSort::from_str(&sort))
+}
+
+pub fn selected_sort(root: &str) -> Option<WebSort> {
+    SELECTED_SORTS
+        .read()
+        .expect("selected sorts read failed")
+        .get(root)
+        .copied()
+}
+
+pub fn set_selected_sort(root: String, sort: Option<WebSort>) {
+    let mut selected_sorts = SELECTED_SORTS.write().expect("selected sorts write failed");
+    match sort {
+        Some(sort) => selected_sorts.insert(root, sort),
+        None => selected_sorts.remove(&root),
+    };
+}
+
+/// Appends (or replaces) the web sort query param of a `stremio://` library deep link
+pub fn with_web_sort(deep_link: &str, sort: WebSort) -> String {
+    match Url::parse(deep_link) {
+        Ok(mut url) => {
+            let query_pairs = url
+                .query_pairs()
+                .filter(|(key, _)| key != WEB_SORT_QUERY_PARAM)
+                .map(|(key, value)| (key.into_owned(), value.into_owned()))
+                .collect::<Vec<_>>();
+            url.query_pairs_mut()
+                .clear()
+                .extend_pairs(query_pairs)
+                .append_pair(WEB_SORT_QUERY_PARAM, sort.as_str());
+            url.to_string()
+        }
+        _ => deep_link.to_owned(),
+    }
+}
+
+/// The runtime of the meta, otherwise the duration of the last watched video
+fn runtime(library_item: &LibraryItem) -> Option<u32> {
+    SORT_KEYS
+        .read()
+        .expect("sort keys read failed")
+        .get(&library_item.id)
+        .and_then(|sort_keys| sort_keys.runtime)
+        .or_else(|| {
+            Some((library_item.state.duration / 60_000) as u32).filter(|minutes| *minutes > 0)
+        })
+}
+
+fn missing_last<T>(a: Option<T>, b: Option<T>, compare: impl Fn(T, T) -> Ordering) -> Ordering {
+    match (a, b) {
+        (Some(a), Some(b)) => compare(a, b),
+        (Some(_), None) => Ordering::Less,
+        (None, Some(_)) => Ordering::Greater,
+        (None, None) => Ordering::Equal,
+    }
+}
+
+/// Minutes of the runtimes like `142 min`, `2h 22min` or `2h`
+fn parse_runtime(runtime: &str) -> Option<u32> {
+    let runtime = runtime.to_lowercase();
+    let (hours, minutes) = match runtime.split_once('h') {
+        Some((hours, minutes)) => (hours.trim().parse::<u32>().ok()?, minutes),
+        None => (0, runtime.as_str()),
+    };
+    let minutes = minutes
+        .trim()
+        .trim_end_matches("min")
+        .trim_end_matches('m')
+        .trim();
+    let minutes = if minutes.is_empty() {
+        0
+    } else {
+        minutes.parse::<u32>().ok()?
+    };
+    Some(hours * 60 + minutes).filter(|runtime| *runtime > 0)
+}
+
+fn collation_key(name: &str, language: &str) -> String {
+    let name = name.trim().to_lowercase();
+    let articles: &[&str] = match language {
+        "eng" => &["the ", "a ", "an "],
+        "ger" | "deu" => &["der ", "die ", "das "],
+        "fre" | "fra" => &["le ", "la ", "les ", "l'"],
+        "spa" => &["el ", "la ", "los ", "las "],
+        "ita" => &["il ", "lo ", "la ", "gli ", "le ", "l'"],
+        _ => &[],
+    };
+    let name = articles
+        .iter()
+        .find_map(|article| name.strip_prefix(article))
+        .unwrap_or(&name);
+    name.chars().map(fold_diacritic).collect()
+}
+
+fn fold_diacritic(c: char) -> char {
+    match c {
+        'à' | 'á' | 'â' | 'ã' | 'ä' | 'å' | 'ā' | 'ă' | 'ą' => 'a',
+        'ç' | 'ć' | 'č' => 'c',
+        'ď' | 'đ' => 'd',
+        'è' | 'é' | 'ê' | 'ë' | 'ē' | 'ė' | 'ę' | 'ě' => 'e',
+        'ì' | 'í' | 'î' | 'ï' | 'ī' | 'į' => 'i',
+        'ł' | 'ľ' => 'l',
+        'ñ' | 'ń' | 'ň' => 'n',
+        'ò' | 'ó' | 'ô' | 'õ' | 'ö' | 'ø' | 'ō' | 'ő' => 'o',
+        'ř' => 'r',
+        'ś' | 'š' | 'ş' => 's',
+        'ť' | 'ţ' => 't',
+        'ù' | 'ú' | 'û' | 'ü' | 'ū' | 'ů' | 'ű' => 'u',
+        'ý' | 'ÿ' => 'y',
+        'ź' | 'ż' | 'ž' => 'z',
+        _ => c,
+    }
+}
+
+/// Drops the selected sorts, the sort keys are loaded again from the storage
+pub fn clear() {
+    SELECTED_SORTS
+        .write()
+        .expect("selected sorts write failed")
+        .clear();
+}
@@ -0,0 +1,78 @@
+use std::borrow::Cow;
+use std::sync::RwLock;
+
+use lazy_static::lazy_static;
+use serde::{Deserialize, Serialize};
+use url::Url;
+
+use stremio_core::types::resource::MetaItemPreview;
+
+/// Items of the capped arrays serialized with the state, the rest of them are fetched on demand
+const MAX_ITEMS: usize = 50;
+
+lazy_static! {
+    /// Set by the shells of the memory constrained devices, e.g. TV browsers
+    static ref LITE_MODE_ENABLED: RwLock<bool> = Default::default();
+}
+
+/// An array of the state which is serialized with only its first items
+#[derive(Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Truncated {
+    pub total: usize,
+}
+
+/// The capped arrays of the state, the rest of their items are fetched by `get_state_slice`
+#[derive(Deserialize, Debug)]
+#[serde(tag = "slice", content = "args")]
+pub enum StateSlice {
+    /// Videos of the meta item of MetaDetails
+    MetaDetailsVideos,
+    /// Streams of MetaDetails from the addon with the transport url
+    MetaDetailsStreams(Url),
+    /// Videos of the meta item of the Player
+    PlayerVideos,
+}
+
+/// Items of a capped array, along with its length
+#[derive(Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Slice<T> {
+    pub items: Vec<T>,
+    pub total: usize,
+}
+
+pub fn set_lite_mode_enabled(enabled: bool) {
+    *LITE_MODE_ENABLED.write().expect("lite mode write failed") = enabled;
+}
+
+fn is_enabled() -> bool {
+    *LITE_MODE_ENABLED.read().expect("lite mode read failed")
+}
+
+/// How many items of a capped array are serialized with the state
+pub fn max_items() -> usize {
+    if is_enabled() {
+        MAX_ITEMS
+    } else {
+        usize::MAX
+    }
+}
+
+/// Set for a capped array which has more items than the ones serialized with the state
+pub fn truncated(total: usize) -> Option<Truncated> {
+    (total > max_items()).then_some(Truncated { total })
+}
+
+/// The preview of a meta item in the catalogs, without the fields which are only shown
+/// on the details screen when the lite mode is enabled
+pub fn meta_item_preview(meta_item: Cow<'_, MetaItemPreview>) -> Cow<'_, MetaItemPreview> {
+    if !is_enabled() {
+        return meta_item;
+    }
+    let mut meta_item = meta_item.into_owned();
+    meta_item.description = None;
+    meta_item.links = vec![];
+    meta_item.trailer_streams = vec![];
+    Cow::Owned(meta_item)
+}
@@ -0,0 +1,152 @@
+use std::cell::RefCell;
+
+use serde::{Serialize, Serializer};
+
+use stremio_core::models::common::{Loadable, ResourceLoadable};
+use stremio_core::types::addon::ResourceRequest;
+
+use crate::{load_cancellation::CANCELLED_ERROR, model::WebModel, streaming_catalogs};
+
+thread_local! {
+    /// The requests of the resources of the model which were ready when it last changed
+    static READY_REQUESTS: RefCell<Vec<ResourceRequest>> = RefCell::new(vec![]);
+    /// The requests of the resources which are loading again after they were ready
+    static RELOADING_REQUESTS: RefCell<Vec<ResourceRequest>> = RefCell::new(vec![]);
+}
+
+/// The states of a loadable besides the ones serialized by core
+#[derive(Serialize)]
+#[serde(tag = "type", content = "content")]
+enum LoadableState {
+    /// Nothing was requested yet
+    NotAsked,
+}
+
+/// How a resource is loaded besides its loadable state, serialized along with its content
+#[derive(Clone, Copy, PartialEq, Eq, Serialize, Debug)]
+pub enum LoadState {
+    /// Loading again while the content loaded before is still shown by the UI
+    Reloading,
+    /// The request was aborted as the selection changed, e.g. the page was left
+    Cancelled,
+    /// Still loading, the content is the items of the response parsed so far
+    Partial,
+    /// Loading past the timeout of the addon, or loaded since then, the content is shown once
+    /// the user asks for it
+    TimedOut,
+}
+
+/// Serializes a missing loadable as `{ "type": "NotAsked" }` instead of `null`,
+/// only for the fields which were added along with it
+pub fn not_asked<T: Serialize, S: Serializer>(
+    loadable: &Option<T>,
+    serializer: S,
+) -> Result<S::Ok, S::Error> {
+    match loadable {
+        Some(loadable) => loadable.serialize(serializer),
+        None => LoadableState::NotAsked.serialize(serializer),
+    }
+}
+
+/// Tracks the resources of the model which are loading again after they were ready,
+/// called on every change of the model before it is serialized
+pub fn update(model: &WebModel) {
+    let resources = model
+        .board
+        .catalogs
+        .iter()
+        .filter_map(|catalog| catalog.first())
+        .chain(
+            model
+                .search
+                .catalogs
+                .iter()
+                .filter_map(|catalog| catalog.first()),
+        )
+        .chain(model.discover.catalog.iter())
+        .map(|resource| (&resource.request, status(resource)))
+        .chain(
+            model
+                .meta_details
+                .meta_items
+                .iter()
+                .map(|resource| (&resource.request, status(resource))),
+        )
+        .chain(
+            model
+                .meta_details
+                .streams
+                .iter()
+                .map(|resource| (&resource.request, status(resource))),
+        )
+        .collect::<Vec<_>>();
+    READY_REQUESTS.with(|ready_requests| {
+        RELOADING_REQUESTS.with(|reloading_requests| {
+            let mut ready_requests = ready_requests.borrow_mut();
+            let mut reloading_requests = reloading_requests.borrow_mut();
+            *reloading_requests = resources
+                .iter()
+                .filter(|(request, status)| {
+                    *status == Status::Loading
+                        && (ready_requests.contains(*request)
+                            || reloading_requests.contains(*request))
+                })
+                .map(|(request, _)| (*request).to_owned())
+                .collect();
+            *ready_requests = resources
+                .iter()
+                .filter(|(_, status)| *status == Status::Ready)
+                .map(|(request, _)| (*request).to_owned())
+                .collect();
+        })
+    });
+}
+
+/// `Partial` for a catalog shown while its response is still parsed,
+/// `Reloading` for a loading resource which was ready before,
+/// `Cancelled` for a resource which failed as its request was aborted
+pub fn load_state<T, E: ToString>(
+    request: &ResourceRequest,
+    content: Option<&Loadable<T, E>>,
+) -> Option<LoadState> {
+    match content {
+        Some(Loadable::Loading) if streaming_catalogs::is_partial(request) => {
+            Some(LoadState::Partial)
+        }
+        Some(Loadable::Loading) if is_reloading(request) => Some(LoadState::Reloading),
+        Some(Loadable::Err(error)) if error.to_string().contains(CANCELLED_ERROR) => {
+            Some(LoadState::Cancelled)
+        }
+        _ => None,
+    }
+}
+
+pub fn len() -> usize {
+    READY_REQUESTS.with(|ready_requests| ready_requests.borrow().len())
+        + RELOADING_REQUESTS.with(|reloading_requests| reloading_requests.borrow().len())
+}
+
+/// Drops the tracked requests, e.g. when the runtime is destroyed
+pub fn clear() {
+    READY_REQUESTS.with(|ready_requests| ready_requests.borrow_mut().clear());
+    RELOADING_REQUESTS.with(|reloading_requests| reloading_requests.borrow_mut().clear());
+}
+
+#[derive(PartialEq, Eq)]
+enum Status {
+    Loading,
+    Ready,
+    Other,
+}
+
+fn status<T>(resource: &ResourceLoadable<T>) -> Status {
+    match &resource.content {
+        Some(Loadable::Loading) => Status::Loading,
+        Some(Loadable::Ready(_)) => Status::Ready,
+        _ => Status::Other,
+    }
+}
+
+fn is_reloading(request: &ResourceRequest) -> bool {
+    RELOADING_REQUESTS.with(|reloading_requests| reloading_requests.borrow().contains(request))
+}
@@ -0,0 +1,83 @@
+use std::borrow::Cow;
+
+use stremio_core::models::common::ResourceLoadable;
+use stremio_core::types::resource::MetaItem;
+
+use crate::catalog_hints;
+
+/// Language of the titles tried after the interface language, before the original ones
+const FALLBACK_LANGUAGE: &str = "eng";
+
+/// The name and the description of the same item from the addons in the interface language,
+/// then in the fallback language. Only the ones which differ from the shown item are returned.
+fn localized_fields<'a>(
+    meta_item: &'a MetaItem,
+    meta_items: &'a [ResourceLoadable<MetaItem>],
+    interface_language: &str,
+) -> (Option<&'a String>, Option<&'a String>) {
+    let localized_meta_items = |language: &str| {
+        meta_items
+            .iter()
+            .filter(|localized| {
+                catalog_hints::addon_language(&localized.request.base)
+                    .map_or(false, |addon_language| {
+                        catalog_hints::is_same_language(&addon_language, language)
+                    })
+            })
+            .filter_map(|localized| {
+                localized
+                    .content
+                    .as_ref()
+                    .and_then(|content| content.ready())
+            })
+            .filter(|localized| localized.preview.id == meta_item.preview.id)
+            .collect::<Vec<_>>()
+    };
+    let candidates = [interface_language, FALLBACK_LANGUAGE]
+        .into_iter()
+        .flat_map(localized_meta_items)
+        .collect::<Vec<_>>();
+    let name = candidates
+        .iter()
+        .map(|localized| &localized.preview.name)
+        .find(|name| !name.is_empty())
+        .filter(|name| **name != meta_item.preview.name);
+    let description = candidates
+        .iter()
+        .find_map(|localized| localized.preview.description.as_ref())
+        .filter(|description| Some(*description) != meta_item.preview.description.as_ref());
+    (name, description)
+}
+
+/// The item with its name and description in the interface language when an addon provides them,
+/// e.g. a secondary meta addon of the language. It is cloned only when anything is localized.
+pub fn localize<'a>(
+    meta_item: &'a MetaItem,
+    meta_items: &'a [ResourceLoadable<MetaItem>],
+    interface_language: &str,
+) -> Cow<'a, MetaItem> {
+    match localized_fields(meta_item, meta_items, interface_language) {
+        (None, None) => Cow::Borrowed(meta_item),
+        (name, description) => {
+            let mut localized = meta_item.to_owned();
+            if let Some(name) = name {
+                localized.preview.name = name.to_owned();
+            }
+            if let Some(description) = description {
+                localized.preview.description = Some(description.to_owned());
+            }
+            Cow::Owned(localized)
+        }
+    }
+}
+
+/// The name of the item as provided by its addon, `None` unless the name is localized
+pub fn original_name<'a>(
+    meta_item: &'a MetaItem,
+    meta_items: &'a [ResourceLoadable<MetaItem>],
+    interface_language: &str,
+) -> Option<&'a String> {
+    localized_fields(meta_item, meta_items, interface_language)
+        .0
+        .map(|_| &meta_item.preview.name)
+}
@@ -1,6 +1,25 @@
+pub mod billboard;
 pub mod deep_links_ext;
+pub mod grid_density;
+pub mod library_sort;
+pub mod lite_mode;
+pub mod loadable_states;
+pub mod meta_localization;
+pub mod pagination;
+pub mod placeholders;
+pub mod range_extras;
+pub mod schema_version;
+pub mod spatial_navigation;
+pub mod stream_trust;
+
+mod serialize_addon_capabilities;
+pub use serialize_addon_capabilities::*;
+
+mod serialize_addon_details;
+use serialize_addon_details::*;
 
 mod serialize_catalogs_with_extra;
+pub use serialize_catalogs_with_extra::BOARD_ROW_SIZE;
 use serialize_catalogs_with_extra::*;
 
 mod serialize_continue_watching_preview;
@@ -18,6 +37,9 @@ use serialize_installed_addons::*;
 mod serialize_library;
 use serialize_library::*;
 
+mod serialize_library_status;
+pub use serialize_library_status::*;
+
 mod serialize_local_search;
 use serialize_local_search::*;
 
@@ -36,5 +58,14 @@ use serialize_streaming_server::*;
 mod serialize_data_export;
 use serialize_data_export::*;
 
+mod serialize_share_payload;
+pub use serialize_share_payload::*;
+
+mod serialize_global_search;
+pub use serialize_global_search::*;
+
+mod serialize_meta_preview_card;
+pub use serialize_meta_preview_card::*;
+
 mod model;
 pub use model::*;
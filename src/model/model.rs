@@ -19,7 +19,7 @@ use stremio_core::{
         player::Player,
         streaming_server::StreamingServer,
     },
-    runtime::Effects,
+    runtime::{Effects, Env},
     types::{
         addon::DescriptorPreview, api::LinkAuthKey, library::LibraryBucket,
         notifications::NotificationsBucket, profile::Profile, resource::MetaItemPreview,
@@ -29,13 +29,20 @@ use stremio_core::{
 };
 
 use crate::{
+    addon_updates,
     env::WebEnv,
+    features::{self, ANNOUNCEMENTS_FEATURE},
+    federated_search, mirrors,
     model::{
+        billboard::billboard, lite_mode::StateSlice, serialize_addon_details,
         serialize_catalogs_with_extra, serialize_continue_watching_preview, serialize_ctx,
         serialize_data_export, serialize_discover, serialize_installed_addons, serialize_library,
-        serialize_local_search, serialize_meta_details, serialize_player, serialize_remote_addons,
-        serialize_streaming_server,
+        serialize_local_search, serialize_meta_details, serialize_meta_details_streams,
+        serialize_meta_details_videos, serialize_player, serialize_player_videos,
+        serialize_remote_addons, serialize_streaming_server, CtxSideState,
     },
+    new_episodes, remote_config, rewatch, snooze, streaming_server_cache, streaming_server_jobs,
+    web_settings,
 };
 
 #[derive(Model, Clone)]
@@ -113,42 +120,102 @@ impl WebModel {
     }
     pub fn get_state(&self, field: &WebModelField) -> JsValue {
         match field {
-            WebModelField::Ctx => serialize_ctx(&self.ctx),
+            WebModelField::Ctx => serialize_ctx(&self.ctx, &CtxSideState::new(&self.ctx)),
             WebModelField::AuthLink => JsValue::from_serde(&self.auth_link).unwrap(),
             WebModelField::DataExport => serialize_data_export(&self.data_export),
             WebModelField::ContinueWatchingPreview => serialize_continue_watching_preview(
                 &self.continue_watching_preview,
                 &self.ctx.streams,
                 &self.ctx.profile.settings,
+                &web_settings::web_settings().continue_watching,
+                &self.ctx.profile.addons,
+                &snooze::active_snoozes(&self.ctx.notifications, WebEnv::now()),
+                &self.ctx.library,
+                &rewatch::rewatches(),
             ),
-            WebModelField::Board => serialize_catalogs_with_extra(&self.board, &self.ctx),
+            WebModelField::Board => {
+                let web_settings = web_settings::web_settings();
+                serialize_catalogs_with_extra(
+                    &self.board,
+                    &self.ctx,
+                    features::is_enabled(ANNOUNCEMENTS_FEATURE)
+                        .then(|| remote_config::active_announcements(WebEnv::now())),
+                    Some(billboard(
+                        &self.board,
+                        &self.continue_watching_preview,
+                        &self.ctx,
+                        &remote_config::billboard_config(),
+                        &web_settings.continue_watching,
+                        &web_settings.trailers,
+                        WebEnv::now(),
+                    )),
+                    new_episodes::new_episodes_row(&self.ctx.library, WebEnv::now()),
+                    None,
+                )
+            }
             WebModelField::Discover => {
                 serialize_discover(&self.discover, &self.ctx, &self.streaming_server)
             }
-            WebModelField::Library => serialize_library(
-                &self.library,
-                &self.ctx.streams,
-                &self.ctx.profile.settings,
-                "library".to_owned(),
-            ),
+            WebModelField::Library => {
+                serialize_library(&self.library, &self.ctx, "library".to_owned())
+            }
             WebModelField::ContinueWatching => serialize_library(
                 &self.continue_watching,
-                &self.ctx.streams,
-                &self.ctx.profile.settings,
+                &self.ctx,
                 "continuewatching".to_owned(),
             ),
-            WebModelField::Search => serialize_catalogs_with_extra(&self.search, &self.ctx),
+            WebModelField::Search => {
+                let installable_results = web_settings::web_settings()
+                    .catalogs
+                    .search_installable_addons
+                    .then(federated_search::results);
+                serialize_catalogs_with_extra(
+                    &self.search,
+                    &self.ctx,
+                    None,
+                    None,
+                    None,
+                    installable_results.as_deref(),
+                )
+            }
             WebModelField::LocalSearch => serialize_local_search(&self.local_search),
             WebModelField::MetaDetails => {
                 serialize_meta_details(&self.meta_details, &self.ctx, &self.streaming_server)
             }
             WebModelField::RemoteAddons => serialize_remote_addons(&self.remote_addons, &self.ctx),
-            WebModelField::InstalledAddons => serialize_installed_addons(&self.installed_addons),
-            WebModelField::AddonDetails => JsValue::from_serde(&self.addon_details).unwrap(),
-            WebModelField::StreamingServer => serialize_streaming_server(&self.streaming_server),
+            WebModelField::InstalledAddons => serialize_installed_addons(
+                &self.installed_addons,
+                &addon_updates::updates(),
+                &addon_updates::available(),
+                &mirrors::previews(),
+            ),
+            WebModelField::AddonDetails => serialize_addon_details(&self.addon_details),
+            WebModelField::StreamingServer => serialize_streaming_server(
+                &self.streaming_server,
+                &streaming_server_jobs::jobs(),
+                &streaming_server_cache::cache(),
+            ),
             WebModelField::Player => {
                 serialize_player(&self.player, &self.ctx, &self.streaming_server)
             }
         }
     }
+    /// Items of an array which is capped in the lite mode, past the ones serialized with the state
+    pub fn get_state_slice(&self, slice: &StateSlice, offset: usize, limit: usize) -> JsValue {
+        match slice {
+            StateSlice::MetaDetailsVideos => {
+                serialize_meta_details_videos(&self.meta_details, &self.ctx, offset, limit)
+            }
+            StateSlice::MetaDetailsStreams(transport_url) => serialize_meta_details_streams(
+                &self.meta_details,
+                &self.ctx,
+                transport_url,
+                offset,
+                limit,
+            ),
+            StateSlice::PlayerVideos => {
+                serialize_player_videos(&self.player, &self.ctx, offset, limit)
+            }
+        }
+    }
 }
@@ -0,0 +1,264 @@
+use std::collections::HashSet;
+
+use itertools::Itertools;
+use serde::Serialize;
+
+use stremio_core::{
+    constants::SKIP_EXTRA_NAME,
+    models::common::{Loadable, ResourceLoadable},
+    types::{addon::ResourceRequest, resource::MetaItemPreview},
+};
+
+/// Extra of the addons which page their catalogs by the number of the page, from 1
+const PAGE_EXTRA_NAME: &str = "page";
+/// Extras of the addons which page their catalogs by a cursor of their own
+const CURSOR_EXTRA_NAMES: [&str; 3] = ["cursor", "after", "next"];
+
+/// The paging convention of the catalog, `None` for the catalogs in a single page
+#[derive(Clone, Copy, PartialEq, Eq, Serialize, Debug)]
+pub enum PaginationKind {
+    Skip,
+    Page,
+    Cursor,
+}
+
+#[derive(Default, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Pagination {
+    pub kind: Option<PaginationKind>,
+    /// The first loaded page is not the first one of the catalog
+    pub has_prev: bool,
+    /// `None` while the last page is loading
+    pub has_next: Option<bool>,
+    /// Items of the catalog, known once the last page is loaded
+    pub total_hint: Option<usize>,
+    /// Items of the first page as returned by the addon, which may differ from the page size
+    /// the addon is expected to return
+    pub page_size: Option<usize>,
+}
+
+/// The paging of the loaded pages in the same shape for all the conventions.
+/// The addons are not trusted with their page sizes, there is no next page once
+/// a page brings no new items, e.g. when the addon ignores the skip.
+pub fn pagination(
+    pages: &[ResourceLoadable<Vec<MetaItemPreview>>],
+    next_page: Option<&ResourceRequest>,
+) -> Pagination {
+    let (first_page, last_page) = match (pages.first(), pages.last()) {
+        (Some(first_page), Some(last_page)) => (first_page, last_page),
+        _ => return Pagination::default(),
+    };
+    let skip = extra_value(&first_page.request, SKIP_EXTRA_NAME)
+        .and_then(|skip| skip.parse::<usize>().ok());
+    let page_number = extra_value(&first_page.request, PAGE_EXTRA_NAME)
+        .and_then(|page_number| page_number.parse::<usize>().ok());
+    let kind = if next_page.is_some()
+        || pages
+            .iter()
+            .any(|page| extra_value(&page.request, SKIP_EXTRA_NAME).is_some())
+    {
+        Some(PaginationKind::Skip)
+    } else if extra_value(&first_page.request, PAGE_EXTRA_NAME).is_some() {
+        Some(PaginationKind::Page)
+    } else if pages.iter().any(|page| cursor(&page.request).is_some()) {
+        Some(PaginationKind::Cursor)
+    } else {
+        None
+    };
+    let page_size = first_page
+        .content
+        .as_ref()
+        .and_then(|content| content.ready())
+        .map(Vec::len);
+    let previous_ids = pages[..pages.len() - 1]
+        .iter()
+        .filter_map(|page| page.content.as_ref().and_then(|content| content.ready()))
+        .flatten()
+        .map(|meta_item| &meta_item.id)
+        .collect::<HashSet<_>>();
+    let last_page_loading = matches!(last_page.content, None | Some(Loadable::Loading));
+    let brings_new_items = last_page
+        .content
+        .as_ref()
+        .and_then(|content| content.ready())
+        .map_or(false, |meta_items| {
+            meta_items
+                .iter()
+                .any(|meta_item| !previous_ids.contains(&meta_item.id))
+        });
+    let has_next = (!last_page_loading).then(|| match kind {
+        Some(PaginationKind::Skip) => next_page.is_some() && brings_new_items,
+        // the addon tells nothing of the end but an empty page
+        Some(PaginationKind::Page) => brings_new_items,
+        Some(PaginationKind::Cursor) | None => false,
+    });
+    let has_prev = match kind {
+        Some(PaginationKind::Skip) => skip.map_or(false, |skip| skip > 0),
+        Some(PaginationKind::Page) => page_number.map_or(false, |page_number| page_number > 1),
+        // the first page of the catalog is requested without a cursor
+        Some(PaginationKind::Cursor) => cursor(&first_page.request).is_some(),
+        None => false,
+    };
+    let offset = match kind {
+        Some(PaginationKind::Skip) => Some(skip.unwrap_or_default()),
+        Some(PaginationKind::Page) => page_number
+            .zip(page_size)
+            .map(|(page_number, page_size)| page_number.saturating_sub(1) * page_size),
+        Some(PaginationKind::Cursor) => None,
+        None => Some(0),
+    };
+    let loaded_items = pages
+        .iter()
+        .filter_map(|page| page.content.as_ref().and_then(|content| content.ready()))
+        .flatten()
+        .unique_by(|meta_item| &meta_item.id)
+        .count();
+    Pagination {
+        kind,
+        has_prev,
+        has_next,
+        total_hint: offset
+            .filter(|_| has_next == Some(false) && page_size.is_some())
+            .map(|offset| offset + loaded_items),
+        page_size,
+    }
+}
+
+fn cursor(request: &ResourceRequest) -> Option<&String> {
+    CURSOR_EXTRA_NAMES
+        .iter()
+        .find_map(|name| extra_value(request, name))
+        .filter(|cursor| !cursor.is_empty())
+}
+
+fn extra_value<'a>(request: &'a ResourceRequest, name: &str) -> Option<&'a String> {
+    request
+        .path
+        .extra
+        .iter()
+        .find(|extra_value| extra_value.name == name)
+        .map(|extra_value| &extra_value.value)
+}
+
+#[cfg(test)]
+mod tests {
+    use serde_json::json;
+    use url::Url;
+
+    use stremio_core::types::addon::{ExtraValue, ResourcePath};
+
+    use super::*;
+
+    fn request(extra: Option<(&str, &str)>) -> ResourceRequest {
+        let extra = extra
+            .map(|(name, value)| ExtraValue {
+                name: name.to_owned(),
+                value: value.to_owned(),
+            })
+            .into_iter()
+            .collect::<Vec<_>>();
+        ResourceRequest::new(
+            Url::parse("https://addon.example.com/manifest.json").unwrap(),
+            ResourcePath::with_extra("catalog", "movie", "top", &extra),
+        )
+    }
+
+    fn page(
+        extra: Option<(&str, &str)>,
+        ids: Option<&[&str]>,
+    ) -> ResourceLoadable<Vec<MetaItemPreview>> {
+        let content = match ids {
+            Some(ids) => Loadable::Ready(
+                ids.iter()
+                    .map(|id| {
+                        serde_json::from_value(json!({
+                            "id": id,
+                            "type": "movie",
+                            "name": id,
+                        }))
+                        .unwrap()
+                    })
+                    .collect(),
+            ),
+            None => Loadable::Loading,
+        };
+        ResourceLoadable {
+            request: request(extra),
+            content: Some(content),
+        }
+    }
+
+    #[test]
+    fn skip() {
+        let next_page = request(Some((SKIP_EXTRA_NAME, "4")));
+        let pages = [
+            page(None, Some(&["a", "b"])),
+            page(Some((SKIP_EXTRA_NAME, "2")), Some(&["c", "d"])),
+        ];
+        let paging = pagination(&pages, Some(&next_page));
+        assert_eq!(paging.kind, Some(PaginationKind::Skip));
+        assert!(!paging.has_prev);
+        assert_eq!(paging.has_next, Some(true));
+        assert_eq!(paging.total_hint, None);
+        assert_eq!(paging.page_size, Some(2));
+        // the addon ignores the skip and returns the first page again
+        let pages = [
+            page(None, Some(&["a", "b"])),
+            page(Some((SKIP_EXTRA_NAME, "2")), Some(&["a", "b"])),
+        ];
+        let paging = pagination(&pages, Some(&next_page));
+        assert_eq!(paging.has_next, Some(false));
+        assert_eq!(paging.total_hint, Some(2));
+    }
+
+    #[test]
+    fn loading_last_page() {
+        let pages = [
+            page(None, Some(&["a", "b"])),
+            page(Some((SKIP_EXTRA_NAME, "2")), None),
+        ];
+        let paging = pagination(&pages, None);
+        assert_eq!(paging.kind, Some(PaginationKind::Skip));
+        assert_eq!(paging.has_next, None);
+        assert_eq!(paging.total_hint, None);
+    }
+
+    #[test]
+    fn page_number() {
+        let pages = [page(Some((PAGE_EXTRA_NAME, "3")), Some(&["a", "b"]))];
+        let paging = pagination(&pages, None);
+        assert_eq!(paging.kind, Some(PaginationKind::Page));
+        assert!(paging.has_prev);
+        assert_eq!(paging.has_next, Some(true));
+        let pages = [page(Some((PAGE_EXTRA_NAME, "1")), Some(&[]))];
+        let paging = pagination(&pages, None);
+        assert!(!paging.has_prev);
+        assert_eq!(paging.has_next, Some(false));
+        assert_eq!(paging.total_hint, Some(0));
+    }
+
+    #[test]
+    fn cursor_pages() {
+        let pages = [
+            page(None, Some(&["a", "b"])),
+            page(Some(("cursor", "b")), Some(&["c"])),
+        ];
+        let paging = pagination(&pages, None);
+        assert_eq!(paging.kind, Some(PaginationKind::Cursor));
+        assert!(!paging.has_prev);
+        assert_eq!(paging.has_next, Some(false));
+        assert_eq!(paging.total_hint, None);
+        let pages = [page(Some(("after", "b")), Some(&["c"]))];
+        assert!(pagination(&pages, None).has_prev);
+    }
+
+    #[test]
+    fn single_page() {
+        let pages = [page(None, Some(&["a", "b"]))];
+        let paging = pagination(&pages, None);
+        assert_eq!(paging.kind, None);
+        assert!(!paging.has_prev);
+        assert_eq!(paging.has_next, Some(false));
+        assert_eq!(paging.total_hint, Some(2));
+    }
+}
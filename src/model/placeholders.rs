@@ -0,0 +1,55 @@
+use std::sync::RwLock;
+
+use lazy_static::lazy_static;
+use serde::Serialize;
+
+use stremio_core::types::{addon::ResourceRequest, resource::PosterShape};
+
+lazy_static! {
+    /// Poster shape of the last ready response per catalog request
+    static ref POSTER_SHAPES: RwLock<Vec<(ResourceRequest, PosterShape)>> = Default::default();
+}
+
+/// Lightweight skeleton entry rendered while the catalog is loading
+#[derive(Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Placeholder {
+    pub poster_shape: PosterShape,
+}
+
+pub fn remember_poster_shape(request: &ResourceRequest, poster_shape: &PosterShape) {
+    let mut poster_shapes = POSTER_SHAPES.write().expect("poster shapes write failed");
+    match poster_shapes
+        .iter_mut()
+        .find(|(known_request, _)| is_same_catalog(known_request, request))
+    {
+        Some((_, known_poster_shape)) => *known_poster_shape = poster_shape.to_owned(),
+        None => poster_shapes.push((request.to_owned(), poster_shape.to_owned())),
+    }
+}
+
+/// The poster shape this catalog had the last time it was loaded
+pub fn poster_shape(request: &ResourceRequest) -> PosterShape {
+    POSTER_SHAPES
+        .read()
+        .expect("poster shapes read failed")
+        .iter()
+        .find(|(known_request, _)| is_same_catalog(known_request, request))
+        .map(|(_, poster_shape)| poster_shape.to_owned())
+        .unwrap_or_default()
+}
+
+/// Placeholders sized to the page, using the poster shape this catalog had the last time it was loaded
+pub fn placeholders(request: &ResourceRequest, count: usize) -> Vec<Placeholder> {
+    let poster_shape = poster_shape(request);
+    (0..count)
+        .map(|_| Placeholder {
+            poster_shape: poster_shape.to_owned(),
+        })
+        .collect()
+}
+
+/// Pages and extra values of a catalog share the same poster shape
+fn is_same_catalog(a: &ResourceRequest, b: &ResourceRequest) -> bool {
+    a.base == b.base && a.path.r#type == b.path.r#type && a.path.id == b.path.id
+}
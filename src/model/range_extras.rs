@@ -0,0 +1,300 @@
+use serde::Serialize;
+
+use stremio_core::constants::SKIP_EXTRA_NAME;
+use stremio_core::types::addon::{ExtraValue, ResourceRequest};
+
+/// Extras with numeric values which are shown as sliders, e.g. `year` and `rating`
+#[derive(Clone, Copy, PartialEq, Eq, Serialize, Debug)]
+pub enum RangeKind {
+    Year,
+    /// A minimum rating, the range has no upper bound
+    Rating,
+}
+
+/// How the addon expects the value of the extra, as deduced from its options
+#[derive(Clone, Copy, PartialEq, Eq, Serialize, Debug)]
+pub enum RangeConvention {
+    /// One of the options, e.g. `2023` or `7.5`
+    Single,
+    /// Both bounds, e.g. `2010-2019`
+    Range,
+    /// The decade of the lower bound, e.g. `2010s`
+    Decade,
+    /// The lower bound with a plus, e.g. `7+`
+    Minimum,
+}
+
+#[derive(Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct RangeExtra {
+    pub kind: RangeKind,
+    pub convention: RangeConvention,
+    pub min: f64,
+    pub max: f64,
+    pub step: f64,
+    /// The selected range, `None` while nothing is selected
+    pub from: Option<f64>,
+    pub to: Option<f64>,
+}
+
+fn kind(name: &str) -> Option<RangeKind> {
+    let name = name.to_lowercase();
+    if name == "year" || name == "decade" {
+        Some(RangeKind::Year)
+    } else if name.contains("rating") {
+        Some(RangeKind::Rating)
+    } else {
+        None
+    }
+}
+
+/// The bounds of an option in the given convention
+fn parse(option: &str, convention: RangeConvention) -> Option<(f64, f64)> {
+    match convention {
+        RangeConvention::Single => option.parse().ok().map(|value| (value, value)),
+        RangeConvention::Range => {
+            let (from, to) = option.split_once('-')?;
+            Some((from.trim().parse().ok()?, to.trim().parse().ok()?))
+        }
+        RangeConvention::Decade => {
+            let decade = option.strip_suffix('s')?.parse::<f64>().ok()?;
+            Some((decade, decade + 9.0))
+        }
+        RangeConvention::Minimum => option
+            .strip_suffix('+')?
+            .parse()
+            .ok()
+            .map(|value| (value, value)),
+    }
+}
+
+/// The convention all of the options follow, `None` for the extras which are not numeric
+fn convention(options: &[&String]) -> Option<RangeConvention> {
+    if options.is_empty() {
+        return None;
+    }
+    [
+        RangeConvention::Single,
+        RangeConvention::Range,
+        RangeConvention::Decade,
+        RangeConvention::Minimum,
+    ]
+    .into_iter()
+    .find(|convention| {
+        options
+            .iter()
+            .all(|option| parse(option, *convention).is_some())
+    })
+}
+
+/// Describes the slider of a numeric extra of Discover from its options,
+/// `None` when it's not a numeric extra and is shown as a dropdown only.
+pub fn range_extra(
+    name: &str,
+    options: &[&String],
+    selected: Option<&String>,
+) -> Option<RangeExtra> {
+    let kind = kind(name)?;
+    let convention = convention(options)?;
+    let bounds = options
+        .iter()
+        .filter_map(|option| parse(option, convention))
+        .collect::<Vec<_>>();
+    let min = bounds
+        .iter()
+        .map(|(from, _)| *from)
+        .fold(f64::MAX, f64::min);
+    let max = bounds.iter().map(|(_, to)| *to).fold(f64::MIN, f64::max);
+    let step = match (kind, convention) {
+        (_, RangeConvention::Decade) => 10.0,
+        (RangeKind::Year, _) => 1.0,
+        (RangeKind::Rating, _) => {
+            let fractional = bounds.iter().any(|(from, _)| from.fract() != 0.0);
+            if fractional {
+                0.5
+            } else {
+                1.0
+            }
+        }
+    };
+    let selected = selected.and_then(|selected| parse(selected, convention));
+    Some(RangeExtra {
+        kind,
+        convention,
+        min,
+        max,
+        step,
+        from: selected.map(|(from, _)| from),
+        to: selected
+            .filter(|_| kind == RangeKind::Year)
+            .map(|(_, to)| to),
+    })
+}
+
+/// Translates the range selected in the UI into the value of the extra the addon expects.
+/// The closest option within the range is picked when the addon does not take ranges.
+pub fn range_value(
+    name: &str,
+    options: &[&String],
+    from: Option<f64>,
+    to: Option<f64>,
+) -> Option<String> {
+    kind(name)?;
+    let convention = convention(options)?;
+    if from.is_none() && to.is_none() {
+        return None;
+    }
+    let from = from.unwrap_or(f64::MIN);
+    let to = to.unwrap_or(f64::MAX);
+    match convention {
+        RangeConvention::Range if from > f64::MIN && to < f64::MAX => {
+            Some(format!("{}-{}", from.round(), to.round()))
+        }
+        _ => options
+            .iter()
+            .filter_map(|option| parse(option, convention).map(|bounds| (option, bounds)))
+            .filter(|(_, (option_from, option_to))| *option_to >= from && *option_from <= to)
+            .min_by(|(_, (a, _)), (_, (b, _))| (a - from).abs().total_cmp(&(b - from).abs()))
+            .map(|(option, _)| (*option).to_owned()),
+    }
+}
+
+/// The request of Discover with the value of the extra replaced, from its first page
+pub fn with_extra_value(
+    request: &ResourceRequest,
+    name: &str,
+    value: Option<String>,
+) -> ResourceRequest {
+    let mut request = request.to_owned();
+    request
+        .path
+        .extra
+        .retain(|extra_value| extra_value.name != name && extra_value.name != SKIP_EXTRA_NAME);
+    if let Some(value) = value {
+        request.path.extra.push(ExtraValue {
+            name: name.to_owned(),
+            value,
+        });
+    }
+    request
+}
+
+#[cfg(test)]
+mod tests {
+    use url::Url;
+
+    use stremio_core::types::addon::ResourcePath;
+
+    use super::*;
+
+    fn options(options: &[&str]) -> Vec<String> {
+        options.iter().map(|option| (*option).to_owned()).collect()
+    }
+
+    fn extra_value(name: &str, value: &str) -> ExtraValue {
+        ExtraValue {
+            name: name.to_owned(),
+            value: value.to_owned(),
+        }
+    }
+
+    #[test]
+    fn single_years() {
+        let options = options(&["2023", "2022", "2021"]);
+        let options = options.iter().collect::<Vec<_>>();
+        let selected = "2022".to_owned();
+        let range = range_extra("year", &options, Some(&selected)).unwrap();
+        assert_eq!(range.kind, RangeKind::Year);
+        assert_eq!(range.convention, RangeConvention::Single);
+        assert_eq!((range.min, range.max, range.step), (2021.0, 2023.0, 1.0));
+        assert_eq!((range.from, range.to), (Some(2022.0), Some(2022.0)));
+        assert_eq!(
+            range_value("year", &options, Some(2020.0), Some(2021.5)),
+            Some("2021".to_owned())
+        );
+        assert_eq!(range_value("year", &options, None, None), None);
+    }
+
+    #[test]
+    fn decades() {
+        let options = options(&["2010s", "2000s"]);
+        let options = options.iter().collect::<Vec<_>>();
+        let range = range_extra("Decade", &options, None).unwrap();
+        assert_eq!(range.convention, RangeConvention::Decade);
+        assert_eq!((range.min, range.max, range.step), (2000.0, 2019.0, 10.0));
+        assert_eq!((range.from, range.to), (None, None));
+    }
+
+    #[test]
+    fn year_ranges() {
+        let options = options(&["2010-2019", "2000-2009"]);
+        let options = options.iter().collect::<Vec<_>>();
+        assert_eq!(
+            range_extra("year", &options, None).unwrap().convention,
+            RangeConvention::Range
+        );
+        assert_eq!(
+            range_value("year", &options, Some(2005.0), Some(2015.0)),
+            Some("2005-2015".to_owned())
+        );
+        // an open range picks the closest option
+        assert_eq!(
+            range_value("year", &options, Some(2003.0), None),
+            Some("2000-2009".to_owned())
+        );
+    }
+
+    #[test]
+    fn minimum_ratings() {
+        let options = options(&["7+", "7.5+", "8+"]);
+        let options = options.iter().collect::<Vec<_>>();
+        let selected = "7.5+".to_owned();
+        let range = range_extra("imdbRating", &options, Some(&selected)).unwrap();
+        assert_eq!(range.kind, RangeKind::Rating);
+        assert_eq!(range.convention, RangeConvention::Minimum);
+        assert_eq!((range.min, range.max, range.step), (7.0, 8.0, 0.5));
+        assert_eq!((range.from, range.to), (Some(7.5), None));
+        assert_eq!(
+            range_value("imdbRating", &options, Some(7.6), None),
+            Some("8+".to_owned())
+        );
+    }
+
+    #[test]
+    fn not_numeric() {
+        let years = options(&["2023", "new"]);
+        let years = years.iter().collect::<Vec<_>>();
+        assert!(range_extra("year", &years, None).is_none());
+        let genres = options(&["2023"]);
+        let genres = genres.iter().collect::<Vec<_>>();
+        assert!(range_extra("genre", &genres, None).is_none());
+        assert!(range_extra("year", &[], None).is_none());
+    }
+
+    #[test]
+    fn replaced_extra_value() {
+        let request = ResourceRequest::new(
+            Url::parse("https://addon.example.com/manifest.json").unwrap(),
+            ResourcePath::with_extra(
+                "catalog",
+                "movie",
+                "top",
+                &[
+                    extra_value("genre", "Drama"),
+                    extra_value("year", "2020"),
+                    extra_value(SKIP_EXTRA_NAME, "100"),
+                ],
+            ),
+        );
+        assert_eq!(
+            with_extra_value(&request, "year", Some("2021".to_owned()))
+                .path
+                .extra,
+            vec![extra_value("genre", "Drama"), extra_value("year", "2021")]
+        );
+        assert_eq!(
+            with_extra_value(&request, "year", None).path.extra,
+            vec![extra_value("genre", "Drama")]
+        );
+    }
+}
@@ -0,0 +1,89 @@
+use std::sync::RwLock;
+
+use lazy_static::lazy_static;
+use serde::Serialize;
+use wasm_bindgen::JsValue;
+
+use crate::model::deep_links_ext;
+
+/// Version of the shape of the serialized states, bumped whenever a field is renamed or moved.
+///
+/// 1. The deep links of the details screen use the `#/metadetails/` route,
+///    the UI reads them from the legacy fields of the deep links
+/// 2. The deep links of the details screen use the `#/detail/` route
+pub const SCHEMA_VERSION: u32 = 2;
+/// The oldest version the states can still be adapted to
+pub const MIN_SCHEMA_VERSION: u32 = 1;
+const SCHEMA_VERSION_FIELD: &str = "schemaVersion";
+
+lazy_static! {
+    /// The version the UI declared it supports, the states are adapted to it
+    static ref NEGOTIATED_VERSION: RwLock<u32> = RwLock::new(SCHEMA_VERSION);
+}
+
+#[derive(Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct SchemaVersion {
+    pub current: u32,
+    pub min_supported: u32,
+    pub negotiated: u32,
+}
+
+#[derive(Serialize, Debug)]
+#[serde(rename_all = "camelCase")]
+pub struct UnsupportedSchemaVersion {
+    pub requested: u32,
+    pub current: u32,
+    pub min_supported: u32,
+    pub message: String,
+}
+
+/// Adapts the states to the version the UI supports, the current one when it declares none.
+/// Fails for the versions the states can't be adapted to, instead of breaking the UI silently.
+pub fn negotiate(requested: Option<u32>) -> Result<u32, UnsupportedSchemaVersion> {
+    let version = requested.unwrap_or(SCHEMA_VERSION);
+    if !(MIN_SCHEMA_VERSION..=SCHEMA_VERSION).contains(&version) {
+        return Err(UnsupportedSchemaVersion {
+            requested: version,
+            current: SCHEMA_VERSION,
+            min_supported: MIN_SCHEMA_VERSION,
+            message: format!(
+                "Schema version {version} is not supported, the supported versions are {MIN_SCHEMA_VERSION} to {SCHEMA_VERSION}"
+            ),
+        });
+    }
+    *NEGOTIATED_VERSION
+        .write()
+        .expect("schema version write failed") = version;
+    if version < 2 {
+        deep_links_ext::set_legacy_routes_enabled(true);
+    }
+    Ok(version)
+}
+
+pub fn schema_version() -> SchemaVersion {
+    SchemaVersion {
+        current: SCHEMA_VERSION,
+        min_supported: MIN_SCHEMA_VERSION,
+        negotiated: negotiated(),
+    }
+}
+
+fn negotiated() -> u32 {
+    *NEGOTIATED_VERSION
+        .read()
+        .expect("schema version read failed")
+}
+
+/// Adds the negotiated version to the serialized state, the state is not copied
+pub fn with_schema_version(state: JsValue) -> JsValue {
+    if state.is_object() {
+        js_sys::Reflect::set(
+            &state,
+            &JsValue::from_str(SCHEMA_VERSION_FIELD),
+            &JsValue::from(negotiated()),
+        )
+        .expect("schema version set failed");
+    }
+    state
+}
@@ -0,0 +1,156 @@
+use itertools::Itertools;
+use serde::Serialize;
+use stremio_core::types::addon::{Descriptor, ManifestResource};
+use stremio_core::types::profile::Profile;
+use url::Url;
+use wasm_bindgen::JsValue;
+
+/// Resources which are expected to be provided for every type that can be browsed
+const EXPECTED_RESOURCES: [&str; 3] = ["meta", "stream", "subtitles"];
+
+mod model {
+    use super::*;
+    #[derive(Serialize)]
+    #[serde(rename_all = "camelCase")]
+    pub struct Capability<'a> {
+        pub resource: &'a str,
+        pub r#type: &'a str,
+        /// `None` when the addon handles every id of the type
+        pub id_prefixes: Option<&'a Vec<String>>,
+    }
+    #[derive(Serialize)]
+    #[serde(rename_all = "camelCase")]
+    pub struct AddonCapabilities<'a> {
+        pub transport_url: &'a Url,
+        pub name: &'a str,
+        pub capabilities: Vec<Capability<'a>>,
+    }
+    #[derive(Serialize)]
+    #[serde(rename_all = "camelCase")]
+    pub struct MatrixCell<'a> {
+        pub resource: &'a str,
+        pub r#type: &'a str,
+        pub providers: Vec<&'a Url>,
+    }
+    #[derive(Serialize)]
+    #[serde(rename_all = "camelCase")]
+    pub struct Gap<'a> {
+        pub resource: &'a str,
+        pub r#type: &'a str,
+    }
+    #[derive(Serialize)]
+    #[serde(rename_all = "camelCase")]
+    pub struct Capabilities<'a> {
+        pub addons: Vec<AddonCapabilities<'a>>,
+        pub resources: Vec<&'a str>,
+        pub types: Vec<&'a str>,
+        pub matrix: Vec<MatrixCell<'a>>,
+        /// Resources with no provider for a type which is browsable through a catalog
+        pub gaps: Vec<Gap<'a>>,
+    }
+}
+
+pub fn serialize_addon_capabilities(profile: &Profile) -> JsValue {
+    let addons = profile
+        .addons
+        .iter()
+        .map(|addon| model::AddonCapabilities {
+            transport_url: &addon.transport_url,
+            name: &addon.manifest.name,
+            capabilities: capabilities(addon),
+        })
+        .collect::<Vec<_>>();
+    let capabilities = addons
+        .iter()
+        .flat_map(|addon| {
+            addon
+                .capabilities
+                .iter()
+                .map(move |capability| (addon.transport_url, capability))
+        })
+        .collect::<Vec<_>>();
+    let resources = capabilities
+        .iter()
+        .map(|(_, capability)| capability.resource)
+        .chain(EXPECTED_RESOURCES)
+        .unique()
+        .collect::<Vec<_>>();
+    let types = capabilities
+        .iter()
+        .map(|(_, capability)| capability.r#type)
+        .unique()
+        .collect::<Vec<_>>();
+    let matrix = resources
+        .iter()
+        .cartesian_product(types.iter())
+        .map(|(resource, r#type)| model::MatrixCell {
+            resource: *resource,
+            r#type: *r#type,
+            providers: capabilities
+                .iter()
+                .filter(|(_, capability)| {
+                    capability.resource == *resource && capability.r#type == *r#type
+                })
+                .map(|(transport_url, _)| *transport_url)
+                .unique()
+                .collect(),
+        })
+        .collect::<Vec<_>>();
+    let gaps = matrix
+        .iter()
+        .filter(|cell| EXPECTED_RESOURCES.contains(&cell.resource) && cell.providers.is_empty())
+        .filter(|cell| {
+            matrix.iter().any(|catalog_cell| {
+                catalog_cell.resource == "catalog"
+                    && catalog_cell.r#type == cell.r#type
+                    && !catalog_cell.providers.is_empty()
+            })
+        })
+        .map(|cell| model::Gap {
+            resource: cell.resource,
+            r#type: cell.r#type,
+        })
+        .collect();
+    JsValue::from_serde(&model::Capabilities {
+        resources,
+        types,
+        matrix,
+        gaps,
+        addons,
+    })
+    .unwrap()
+}
+
+/// Flattens the resources of the manifest to resource/type pairs,
+/// short resources inherit the types and id prefixes of the manifest
+fn capabilities(addon: &Descriptor) -> Vec<model::Capability<'_>> {
+    let manifest = &addon.manifest;
+    let catalogs = manifest.catalogs.iter().map(|catalog| model::Capability {
+        resource: "catalog",
+        r#type: &catalog.r#type,
+        id_prefixes: None,
+    });
+    let resources = manifest.resources.iter().flat_map(move |resource| {
+        let (name, types, id_prefixes) = match resource {
+            ManifestResource::Short(name) => (name, &manifest.types, manifest.id_prefixes.as_ref()),
+            ManifestResource::Full {
+                name,
+                types,
+                id_prefixes,
+            } => (
+                name,
+                types.as_ref().unwrap_or(&manifest.types),
+                id_prefixes.as_ref().or(manifest.id_prefixes.as_ref()),
+            ),
+        };
+        types
+            .iter()
+            .filter(move |_| name != "catalog")
+            .map(move |r#type| model::Capability {
+                resource: name,
+                r#type,
+                id_prefixes,
+            })
+    });
+    catalogs.chain(resources).collect()
+}
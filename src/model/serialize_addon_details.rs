@@ -0,0 +1,160 @@
+use serde::Serialize;
+use stremio_core::deep_links::MetaItemDeepLinks;
+use stremio_core::models::addon_details::AddonDetails;
+use stremio_core::models::common::Loadable;
+use stremio_core::types::addon::Descriptor;
+use url::Url;
+use wasm_bindgen::JsValue;
+
+use crate::addon_diagnostics::{self, DiagnosticsReport};
+use crate::addon_preview;
+use crate::addon_signatures::{self, SignatureVerification, OFFICIAL_ADDON_IDS};
+use crate::model::deep_links_ext::DeepLinksExt;
+use crate::model::loadable_states;
+use crate::p2p_transport;
+
+mod model {
+    use super::*;
+    #[derive(Serialize)]
+    #[serde(rename_all = "camelCase")]
+    pub struct AddonDetails<'a> {
+        #[serde(flatten)]
+        pub addon_details: &'a stremio_core::models::addon_details::AddonDetails,
+        /// Resolution of the manifest for addons served over a p2p transport
+        pub p2p_resolution: Option<Loadable<Url, String>>,
+        /// Verification of the manifest signature
+        #[serde(serialize_with = "loadable_states::not_asked")]
+        pub signature: Option<Loadable<SignatureVerification, String>>,
+        /// Claims to be an official addon and the claim is backed by a valid signature
+        pub official: bool,
+        /// A row per catalog of an addon which is not installed, with a sample of its items
+        pub preview: Vec<PreviewRow>,
+        /// Report of the network test of the addon
+        #[serde(serialize_with = "loadable_states::not_asked")]
+        pub diagnostics: Option<Loadable<DiagnosticsReport, String>>,
+    }
+    #[derive(Serialize)]
+    #[serde(rename_all = "camelCase")]
+    pub struct PreviewRow {
+        pub id: String,
+        pub r#type: String,
+        pub name: Option<String>,
+        #[serde(serialize_with = "loadable_states::not_asked")]
+        pub content: Option<Loadable<Vec<MetaItemPreview>, String>>,
+    }
+    #[derive(Serialize)]
+    #[serde(rename_all = "camelCase")]
+    pub struct MetaItemPreview {
+        #[serde(flatten)]
+        pub meta_item: stremio_core::types::resource::MetaItemPreview,
+        pub deep_links: MetaItemDeepLinks,
+    }
+}
+
+pub fn serialize_addon_details(addon_details: &AddonDetails) -> JsValue {
+    let signature = addon_details
+        .selected
+        .as_ref()
+        .and_then(|selected| addon_signatures::verification(&selected.transport_url));
+    JsValue::from_serde(&model::AddonDetails {
+        addon_details,
+        p2p_resolution: addon_details
+            .selected
+            .as_ref()
+            .and_then(|selected| p2p_transport::resolution(&selected.transport_url)),
+        official: is_official(addon_details, signature.as_ref()),
+        signature,
+        preview: preview_addon(addon_details)
+            .map(preview_rows)
+            .unwrap_or_default(),
+        diagnostics: addon_details
+            .selected
+            .as_ref()
+            .and_then(|selected| addon_diagnostics::report(&selected.transport_url)),
+    })
+    .unwrap()
+}
+
+/// The remote addon while it's not installed
+fn preview_addon(addon_details: &AddonDetails) -> Option<&Descriptor> {
+    if addon_details.local_addon.is_some() {
+        return None;
+    }
+    match &addon_details.remote_addon.as_ref()?.content {
+        Loadable::Ready(addon) => Some(addon),
+        _ => None,
+    }
+}
+
+fn preview_rows(addon: &Descriptor) -> Vec<model::PreviewRow> {
+    addon_preview::preview_requests(addon)
+        .into_iter()
+        .map(|request| {
+            let catalog = addon.manifest.catalogs.iter().find(|catalog| {
+                catalog.r#type == request.path.r#type && catalog.id == request.path.id
+            });
+            model::PreviewRow {
+                id: request.path.id.to_owned(),
+                r#type: request.path.r#type.to_owned(),
+                name: catalog.and_then(|catalog| catalog.name.to_owned()),
+                content: addon_preview::preview(&request).map(|preview| match preview {
+                    Loadable::Ready(meta_items) => Loadable::Ready(
+                        meta_items
+                            .into_iter()
+                            .map(|meta_item| model::MetaItemPreview {
+                                deep_links: MetaItemDeepLinks::from((&meta_item, &request))
+                                    .into_web_deep_links(),
+                                meta_item,
+                            })
+                            .collect(),
+                    ),
+                    Loadable::Loading => Loadable::Loading,
+                    Loadable::Err(error) => Loadable::Err(error),
+                }),
+            }
+        })
+        .collect()
+}
+
+/// Any addon claiming an official id is official with a valid signature, none with an invalid one.
+/// Otherwise, e.g. when the verification is not built in or the manifest is not signed,
+/// only the addons flagged as official by core are.
+fn is_official(
+    addon_details: &AddonDetails,
+    signature: Option<&Loadable<SignatureVerification, String>>,
+) -> bool {
+    match signature {
+        Some(Loadable::Ready(SignatureVerification::Verified { .. })) => {
+            claims_official(addon_details)
+        }
+        Some(Loadable::Ready(SignatureVerification::Invalid)) => false,
+        _ => flagged_official(addon_details),
+    }
+}
+
+fn flagged_official(addon_details: &AddonDetails) -> bool {
+    addon_details
+        .local_addon
+        .as_ref()
+        .map_or(false, |addon| addon.flags.official)
+}
+
+fn claims_official(addon_details: &AddonDetails) -> bool {
+    let manifest = addon_details
+        .local_addon
+        .as_ref()
+        .map(|addon| &addon.manifest)
+        .or_else(|| {
+            addon_details
+                .remote_addon
+                .as_ref()
+                .and_then(|remote_addon| match &remote_addon.content {
+                    Loadable::Ready(addon) => Some(&addon.manifest),
+                    _ => None,
+                })
+        });
+    flagged_official(addon_details)
+        || manifest.map_or(false, |manifest| {
+            OFFICIAL_ADDON_IDS.contains(&manifest.id.as_str())
+        })
+}
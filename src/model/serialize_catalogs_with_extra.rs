@@ -1,47 +1,145 @@
-use crate::model::deep_links_ext::DeepLinksExt;
+use crate::blocklist;
+use crate::board_refresh;
+use crate::catalog_hints;
+use crate::federated_search;
+use crate::meta_overrides;
+use crate::model::billboard::BillboardItem;
+use crate::model::deep_links_ext::{addon_install_link, DeepLinksExt};
+use crate::model::lite_mode;
+use crate::model::loadable_states::{self, LoadState};
+use crate::model::placeholders::{self, Placeholder};
+use crate::model::spatial_navigation::{self, NavigationHint};
+use crate::new_episodes::NewEpisodesRow;
+use crate::palettes::{self, Palette};
+use crate::push_transport;
+use crate::remote_config::Announcement;
+use crate::request_tracing;
+use crate::response_limits;
+use crate::retry;
+use crate::schema_validation::{self, SchemaWarning};
+use crate::streaming_catalogs;
+use chrono::{DateTime, Utc};
 use inflector::Inflector;
 use itertools::Itertools;
 use serde::Serialize;
+use std::borrow::Cow;
 use stremio_core::deep_links::{DiscoverDeepLinks, MetaItemDeepLinks};
 use stremio_core::models::catalogs_with_extra::{CatalogsWithExtra, Selected};
-use stremio_core::models::common::Loadable;
+use stremio_core::models::common::{Loadable, ResourceLoadable};
 use stremio_core::models::ctx::Ctx;
+use stremio_core::types::addon::{
+    Descriptor, ManifestBehaviorHints, ManifestCatalog, ResourceRequest,
+};
+use stremio_core::types::profile::Settings;
 use stremio_core::types::resource::PosterShape;
 use wasm_bindgen::JsValue;
 
+/// Number of items shown per Board row
+pub const BOARD_ROW_SIZE: usize = 10;
+
 mod model {
 
     use super::*;
     #[derive(Serialize)]
     #[serde(rename_all = "camelCase")]
     pub struct MetaItemPreview<'a> {
+        /// With the poster and the name set by the user
         #[serde(flatten)]
-        pub meta_item: &'a stremio_core::types::resource::MetaItemPreview,
-        pub poster_shape: &'a PosterShape,
+        pub meta_item: Cow<'a, stremio_core::types::resource::MetaItemPreview>,
+        pub poster_shape: PosterShape,
         pub deep_links: MetaItemDeepLinks,
+        #[serde(skip_serializing_if = "Option::is_none")]
+        pub navigation: Option<NavigationHint>,
+        /// Colors of the poster, `None` until they are extracted
+        pub palette: Option<Palette>,
     }
     #[derive(Serialize)]
     #[serde(rename_all = "camelCase")]
     pub struct ResourceLoadable<'a> {
         pub title: String,
         pub content: Option<Loadable<Vec<MetaItemPreview<'a>>, String>>,
+        #[serde(skip_serializing_if = "Option::is_none")]
+        pub load_state: Option<LoadState>,
+        /// Skeleton entries while the content is loading
+        pub placeholder_count: usize,
+        pub placeholders: Vec<Placeholder>,
+        /// Schema violations of the addon response
+        #[serde(skip_serializing_if = "Vec::is_empty")]
+        pub warnings: Vec<SchemaWarning>,
+        /// Declared by the addon manifest, otherwise the one of the first item
+        pub poster_shape: Option<PosterShape>,
+        /// Preferred number of items per row, declared by the addon manifest
+        pub items_per_row: Option<usize>,
+        /// Requests made to load the content, more than one when it was retried
+        pub attempts: u32,
+        /// Id of the failed request in the logs, for the users to report the error
+        #[serde(skip_serializing_if = "Option::is_none")]
+        pub request_id: Option<String>,
+        /// Only the first items of the oversized response of the addon are shown
+        pub truncated_by_limit: bool,
+        /// Language of the titles, declared by the addon manifest
+        #[serde(skip_serializing_if = "Option::is_none")]
+        pub language: Option<String>,
+        /// Declared by the addon manifest, e.g. whether the catalog is of adult content
+        pub addon_behavior_hints: &'a ManifestBehaviorHints,
+        /// Last background refresh of a Board row
+        #[serde(skip_serializing_if = "Option::is_none")]
+        pub last_refreshed_at: Option<DateTime<Utc>>,
         pub deep_links: DiscoverDeepLinks,
     }
     #[derive(Serialize)]
     #[serde(rename_all = "camelCase")]
+    pub struct InstallableCatalog<'a> {
+        pub title: String,
+        pub content: Loadable<Vec<MetaItemPreview<'a>>, &'a String>,
+        /// Installed in one click by dispatching `InstallAddon` with it
+        pub addon: &'a Descriptor,
+        /// The `stremio://` link installing the addon
+        pub install_link: Option<String>,
+    }
+    #[derive(Serialize)]
+    #[serde(rename_all = "camelCase")]
     pub struct CatalogsWithExtra<'a> {
         pub selected: &'a Option<Selected>,
         pub catalogs: Vec<ResourceLoadable<'a>>,
+        /// Dismissible announcements row, only present for the Board
+        #[serde(skip_serializing_if = "Option::is_none")]
+        pub announcements: Option<Vec<Announcement>>,
+        /// Featured items, only present for the Board
+        #[serde(skip_serializing_if = "Option::is_none")]
+        pub billboard: Option<Vec<BillboardItem<'a>>>,
+        /// New episodes of the library series, only present for the Board when there are any
+        #[serde(skip_serializing_if = "Option::is_none")]
+        pub new_episodes: Option<NewEpisodesRow>,
+        /// "From addons you can install", the results of the official addons which are
+        /// not installed. Only present for the Search when it's enabled in the settings.
+        #[serde(skip_serializing_if = "Option::is_none")]
+        pub installable_catalogs: Option<Vec<InstallableCatalog<'a>>>,
     }
 }
 
+type InstallableResult = (
+    ResourceRequest,
+    Loadable<Vec<stremio_core::types::resource::MetaItemPreview>, String>,
+);
+
 pub fn serialize_catalogs_with_extra(
     catalogs_with_extra: &CatalogsWithExtra,
     ctx: &Ctx,
+    announcements: Option<Vec<Announcement>>,
+    billboard: Option<Vec<BillboardItem>>,
+    new_episodes: Option<NewEpisodesRow>,
+    installable_results: Option<&[InstallableResult]>,
 ) -> JsValue {
-    JsValue::from_serde(&model::CatalogsWithExtra {
-        selected: &catalogs_with_extra.selected,
-        catalogs: catalogs_with_extra
+    let pushed_catalogs = push_transport::pushed_catalogs();
+    let partial_catalogs = streaming_catalogs::partial_catalogs();
+    let refreshed_catalogs = board_refresh::refreshed_catalogs();
+    let blocked_ids = blocklist::blocked_ids();
+    let installable_addons = installable_results
+        .map(|_| federated_search::addons())
+        .unwrap_or_default();
+    let catalogs = prefer_languages(
+        catalogs_with_extra
             .catalogs
             .iter()
             .filter_map(|catalog| catalog.first())
@@ -62,8 +160,35 @@ pub fn serialize_catalogs_with_extra(
                             .map(|manifest_catalog| (addon, manifest_catalog, catalog))
                     })
             })
-            .map(
-                |(addon, manifest_catalog, catalog)| model::ResourceLoadable {
+            .collect(),
+        &ctx.profile.settings,
+    );
+    JsValue::from_serde(&model::CatalogsWithExtra {
+        selected: &catalogs_with_extra.selected,
+        catalogs: catalogs
+            .into_iter()
+            .enumerate()
+            .map(|(row, (addon, manifest_catalog, catalog))| {
+                let hints = catalog_hints::catalog_hints(&catalog.request);
+                let hinted_poster_shape =
+                    hints.as_ref().and_then(|hints| hints.poster_shape.as_ref());
+                if let Some(poster_shape) = hinted_poster_shape {
+                    placeholders::remember_poster_shape(&catalog.request, poster_shape);
+                }
+                // the items parsed so far are shown while a large catalog is still loading
+                let ready_meta_items = match &catalog.content {
+                    Some(Loadable::Ready(meta_items)) => Some(
+                        find_meta_items(&pushed_catalogs, &catalog.request)
+                            .or_else(|| find_meta_items(&refreshed_catalogs, &catalog.request))
+                            .unwrap_or(meta_items),
+                    ),
+                    Some(Loadable::Loading) => partial_catalogs
+                        .iter()
+                        .find(|(request, _)| *request == catalog.request)
+                        .map(|(_, meta_items)| meta_items.as_ref()),
+                    _ => None,
+                };
+                model::ResourceLoadable {
                     title: format!(
                         "{} - {}",
                         &manifest_catalog
@@ -73,36 +198,215 @@ pub fn serialize_catalogs_with_extra(
                             .to_title_case(),
                         &manifest_catalog.r#type.to_title_case(),
                     ),
-                    content: match &catalog.content {
-                        Some(Loadable::Ready(meta_items)) => {
-                            let poster_shape =
-                                meta_items.first().map(|meta_item| &meta_item.poster_shape);
+                    content: match (&catalog.content, ready_meta_items) {
+                        (_, Some(meta_items)) => {
+                            let poster_shape = hinted_poster_shape.or_else(|| {
+                                meta_items.first().map(|meta_item| &meta_item.poster_shape)
+                            });
+                            if let Some(poster_shape) = poster_shape {
+                                placeholders::remember_poster_shape(&catalog.request, poster_shape);
+                            }
                             Some(Loadable::Ready(
                                 meta_items
                                     .iter()
+                                    .filter(|meta_item| !blocked_ids.contains(&meta_item.id))
                                     .unique_by(|meta_item| &meta_item.id)
-                                    .take(10)
-                                    .map(|meta_item| model::MetaItemPreview {
-                                        meta_item,
+                                    .take(BOARD_ROW_SIZE)
+                                    .enumerate()
+                                    .map(|(column, meta_item)| model::MetaItemPreview {
+                                        meta_item: lite_mode::meta_item_preview(
+                                            meta_overrides::meta_item_preview(meta_item),
+                                        ),
                                         poster_shape: poster_shape
-                                            .unwrap_or(&meta_item.poster_shape),
+                                            .unwrap_or(&meta_item.poster_shape)
+                                            .to_owned(),
                                         deep_links: MetaItemDeepLinks::from((
                                             meta_item,
                                             &catalog.request,
                                         ))
                                         .into_web_deep_links(),
+                                        navigation: spatial_navigation::row_hint(
+                                            "catalogs",
+                                            row,
+                                            column,
+                                            &meta_item.id,
+                                        ),
+                                        palette: palettes::palette(
+                                            meta_overrides::poster(
+                                                &meta_item.id,
+                                                &meta_item.poster,
+                                            )
+                                            .as_ref()
+                                            .as_ref(),
+                                        ),
                                     })
                                     .collect::<Vec<_>>(),
                             ))
                         }
-                        Some(Loadable::Loading) => Some(Loadable::Loading),
-                        Some(Loadable::Err(error)) => Some(Loadable::Err(error.to_string())),
-                        None => None,
+                        (Some(Loadable::Err(error)), _) => Some(Loadable::Err(error.to_string())),
+                        (Some(_), None) => Some(Loadable::Loading),
+                        (None, _) => None,
+                    },
+                    load_state: loadable_states::load_state(
+                        &catalog.request,
+                        catalog.content.as_ref(),
+                    ),
+                    placeholders: match &catalog.content {
+                        Some(Loadable::Loading) if ready_meta_items.is_none() => {
+                            placeholders::placeholders(&catalog.request, BOARD_ROW_SIZE)
+                        }
+                        _ => vec![],
+                    },
+                    placeholder_count: match &catalog.content {
+                        Some(Loadable::Loading) if ready_meta_items.is_none() => BOARD_ROW_SIZE,
+                        _ => 0,
                     },
+                    warnings: schema_validation::warnings(&catalog.request),
+                    poster_shape: hinted_poster_shape.cloned().or_else(|| {
+                        catalog
+                            .content
+                            .as_ref()
+                            .and_then(|content| content.ready())
+                            .and_then(|meta_items| meta_items.first())
+                            .map(|meta_item| meta_item.poster_shape.to_owned())
+                    }),
+                    items_per_row: hints.as_ref().and_then(|hints| hints.items_per_row),
+                    attempts: retry::attempts(&catalog.request),
+                    request_id: request_tracing::failed_request_id(
+                        &catalog.request,
+                        catalog.content.as_ref(),
+                    ),
+                    truncated_by_limit: response_limits::is_truncated(&catalog.request),
+                    language: hints.as_ref().and_then(|hints| hints.language.to_owned()),
+                    addon_behavior_hints: &addon.manifest.behavior_hints,
+                    last_refreshed_at: board_refresh::last_refreshed_at(&catalog.request),
                     deep_links: DiscoverDeepLinks::from(&catalog.request).into_web_deep_links(),
-                },
-            )
+                }
+            })
             .collect::<Vec<_>>(),
+        announcements,
+        billboard,
+        new_episodes,
+        installable_catalogs: installable_results.map(|installable_results| {
+            installable_results
+                .iter()
+                .filter_map(|(request, result)| {
+                    installable_addons
+                        .iter()
+                        .find(|addon| addon.transport_url == request.base)
+                        .map(|addon| (request, result, addon))
+                })
+                .map(|(request, result, addon)| model::InstallableCatalog {
+                    title: format!(
+                        "{} - {}",
+                        &addon.manifest.name,
+                        &request.path.r#type.to_title_case(),
+                    ),
+                    content: match result {
+                        Loadable::Ready(meta_items) => Loadable::Ready(
+                            meta_items
+                                .iter()
+                                .filter(|meta_item| !blocked_ids.contains(&meta_item.id))
+                                .unique_by(|meta_item| &meta_item.id)
+                                .take(BOARD_ROW_SIZE)
+                                .map(|meta_item| model::MetaItemPreview {
+                                    meta_item: lite_mode::meta_item_preview(
+                                        meta_overrides::meta_item_preview(meta_item),
+                                    ),
+                                    poster_shape: meta_item.poster_shape.to_owned(),
+                                    deep_links: MetaItemDeepLinks::from((meta_item, request))
+                                        .into_web_deep_links(),
+                                    navigation: None,
+                                    palette: palettes::palette(
+                                        meta_overrides::poster(&meta_item.id, &meta_item.poster)
+                                            .as_ref()
+                                            .as_ref(),
+                                    ),
+                                })
+                                .collect::<Vec<_>>(),
+                        ),
+                        Loadable::Loading => Loadable::Loading,
+                        Loadable::Err(error) => Loadable::Err(error),
+                    },
+                    addon,
+                    install_link: addon_install_link(&addon.transport_url),
+                })
+                .collect::<Vec<_>>()
+        }),
     })
     .unwrap()
 }
+
+fn find_meta_items<'a>(
+    catalogs: &'a [(
+        ResourceRequest,
+        Vec<stremio_core::types::resource::MetaItemPreview>,
+    )],
+    request: &ResourceRequest,
+) -> Option<&'a Vec<stremio_core::types::resource::MetaItemPreview>> {
+    catalogs
+        .iter()
+        .find(|(catalog_request, _)| catalog_request == request)
+        .map(|(_, meta_items)| meta_items)
+}
+
+type BoardRow<'a> = (
+    &'a Descriptor,
+    &'a ManifestCatalog,
+    &'a ResourceLoadable<Vec<stremio_core::types::resource::MetaItemPreview>>,
+);
+
+/// Moves the catalogs in the configured languages first among the catalogs of the same type,
+/// when the type is offered by more than one addon. The positions of the types are kept.
+fn prefer_languages<'a>(rows: Vec<BoardRow<'a>>, settings: &Settings) -> Vec<BoardRow<'a>> {
+    let mut ordered = rows.to_owned();
+    let types = rows
+        .iter()
+        .map(|(_, _, catalog)| &catalog.request.path.r#type)
+        .unique();
+    for r#type in types {
+        let positions = (0..rows.len())
+            .filter(|position| rows[*position].2.request.path.r#type == *r#type)
+            .collect::<Vec<_>>();
+        let addons = positions
+            .iter()
+            .map(|position| &rows[*position].2.request.base)
+            .unique()
+            .count();
+        if addons < 2 {
+            continue;
+        }
+        let preferred = positions
+            .iter()
+            .map(|position| rows[*position])
+            .sorted_by_key(|(_, _, catalog)| language_rank(&catalog.request, settings));
+        for (position, row) in positions.iter().zip(preferred) {
+            ordered[*position] = row;
+        }
+    }
+    ordered
+}
+
+/// The interface language first, then the subtitles language, then the undeclared ones
+fn language_rank(request: &ResourceRequest, settings: &Settings) -> u8 {
+    let language = catalog_hints::catalog_hints(request).and_then(|hints| hints.language);
+    match language {
+        Some(language)
+            if catalog_hints::is_same_language(&language, &settings.interface_language) =>
+        {
+            0
+        }
+        Some(language)
+            if settings
+                .subtitles_language
+                .as_ref()
+                .map_or(false, |subtitles_language| {
+                    catalog_hints::is_same_language(&language, subtitles_language)
+                }) =>
+        {
+            1
+        }
+        None => 2,
+        Some(_) => 3,
+    }
+}
@@ -3,42 +3,98 @@ use wasm_bindgen::JsValue;
 
 use stremio_core::{
     models::continue_watching_preview::ContinueWatchingPreview,
-    types::{profile::Settings, streams::StreamsBucket},
+    types::{addon::Descriptor, library::LibraryBucket, profile::Settings, streams::StreamsBucket},
 };
 
+use crate::rewatch::Rewatch;
+use crate::snooze::Snooze;
+use crate::web_settings::ContinueWatchingSettings;
+
 pub fn serialize_continue_watching_preview(
     continue_watching_preview: &ContinueWatchingPreview,
     streams_bucket: &StreamsBucket,
     settings: &Settings,
+    continue_watching_settings: &ContinueWatchingSettings,
+    addons: &[Descriptor],
+    snoozes: &[Snooze],
+    library: &LibraryBucket,
+    rewatches: &[Rewatch],
 ) -> JsValue {
     JsValue::from_serde(&model::ContinueWatchingPreview::from((
         continue_watching_preview,
         streams_bucket,
         settings,
+        continue_watching_settings,
+        addons,
+        snoozes,
+        library,
+        rewatches,
     )))
     .unwrap()
 }
 
 mod model {
+    use itertools::Itertools;
     use serde::Serialize;
+    use std::borrow::Cow;
+    use std::cmp::Reverse;
     use url::Url;
 
     use stremio_core::{
+        constants::CATALOG_PREVIEW_SIZE,
         deep_links::{LibraryDeepLinks, LibraryItemDeepLinks},
         types::{
+            addon::Descriptor,
+            library::LibraryBucket,
             profile::Settings,
             resource::PosterShape,
             streams::{StreamsBucket, StreamsItem, StreamsItemKey},
         },
     };
 
+    use crate::library_pending;
+    use crate::meta_overrides;
     use crate::model::deep_links_ext::DeepLinksExt;
+    use crate::rewatch::Rewatch;
+    use crate::snooze::Snooze;
+    use crate::web_settings::{ContinueWatchingSettings, ExcludedCatalog};
 
     #[derive(Serialize)]
     #[serde(rename_all = "camelCase")]
     pub struct ContinueWatchingPreview<'a> {
         pub items: Vec<Item<'a>>,
+        /// Items hidden by the user for a while, listed so they can be unsnoozed
+        pub snoozed: Vec<SnoozedItem<'a>>,
         pub deep_links: LibraryDeepLinks,
+        /// Exclusions of the settings, so it can be explained why some items are hidden
+        pub exclusions: Exclusions<'a>,
+    }
+
+    #[derive(Serialize)]
+    #[serde(rename_all = "camelCase")]
+    pub struct Exclusions<'a> {
+        pub channels: bool,
+        pub types: &'a Vec<String>,
+        pub addons: Vec<ExcludedAddon<'a>>,
+        pub catalogs: &'a Vec<ExcludedCatalog>,
+        /// a count of the items hidden by the exclusions
+        pub hidden: usize,
+    }
+
+    #[derive(Serialize)]
+    #[serde(rename_all = "camelCase")]
+    pub struct SnoozedItem<'a> {
+        #[serde(flatten)]
+        pub item: Item<'a>,
+        pub snooze: &'a Snooze,
+    }
+
+    #[derive(Serialize)]
+    #[serde(rename_all = "camelCase")]
+    pub struct ExcludedAddon<'a> {
+        pub transport_url: &'a Url,
+        /// `None` when the addon is no longer installed
+        pub name: Option<&'a String>,
     }
 
     impl<'a>
@@ -46,39 +102,137 @@ mod model {
             &'a stremio_core::models::continue_watching_preview::ContinueWatchingPreview,
             &StreamsBucket,
             &Settings,
+            &'a ContinueWatchingSettings,
+            &'a [Descriptor],
+            &'a [Snooze],
+            &'a LibraryBucket,
+            &[Rewatch],
         )> for ContinueWatchingPreview<'a>
     {
         fn from(
-            (continue_watching_preview, streams_bucket, settings): (
+            (
+                continue_watching_preview,
+                streams_bucket,
+                settings,
+                continue_watching_settings,
+                addons,
+                snoozes,
+                library,
+                rewatches,
+            ): (
                 &'a stremio_core::models::continue_watching_preview::ContinueWatchingPreview,
                 &StreamsBucket,
                 &Settings,
+                &'a ContinueWatchingSettings,
+                &'a [Descriptor],
+                &'a [Snooze],
+                &'a LibraryBucket,
+                &[Rewatch],
             ),
         ) -> Self {
+            // the series being rewatched are listed between the episodes too
+            let rewatched_items = rewatches
+                .iter()
+                .filter(|rewatch| {
+                    !continue_watching_preview
+                        .items
+                        .iter()
+                        .any(|core_cw_item| core_cw_item.library_item.id == rewatch.id)
+                })
+                .filter_map(|rewatch| library.items.get(&rewatch.id))
+                .filter(|library_item| !library_item.removed)
+                .map(|library_item| (library_item, 0));
+            // core caps the preview, the items past the cap take the place of the excluded ones
+            let capped_items = library
+                .items
+                .values()
+                .filter(|_| continue_watching_preview.items.len() >= CATALOG_PREVIEW_SIZE)
+                .filter(|library_item| library_item.is_in_continue_watching())
+                .filter(|library_item| {
+                    !continue_watching_preview
+                        .items
+                        .iter()
+                        .any(|core_cw_item| core_cw_item.library_item.id == library_item.id)
+                })
+                .map(|library_item| (library_item, 0));
+            let cw_items = continue_watching_preview
+                .items
+                .iter()
+                .map(|core_cw_item| (&core_cw_item.library_item, core_cw_item.notifications))
+                .chain(capped_items)
+                .chain(rewatched_items)
+                .map(|(library_item, notifications)| {
+                    let rewatch = rewatches
+                        .iter()
+                        .find(|rewatch| rewatch.id == library_item.id);
+                    (library_item, notifications, rewatch)
+                })
+                // the rewatched items are ordered by the last time they were watched
+                .sorted_by_key(|(library_item, _, rewatch)| {
+                    Reverse(rewatch.map_or(library_item.mtime, |rewatch| {
+                        rewatch.last_activity(library_item)
+                    }))
+                })
+                .collect::<Vec<_>>();
+            let (snoozed, items): (Vec<_>, Vec<_>) = cw_items
+                .iter()
+                .map(|(library_item, notifications, rewatch)| {
+                    let library_item_stream =
+                        library_item.state.video_id.clone().and_then(|video_id| {
+                            streams_bucket.items.get(&StreamsItemKey {
+                                meta_id: library_item.id.clone(),
+                                video_id,
+                            })
+                        });
+                    (library_item, notifications, rewatch, library_item_stream)
+                })
+                .filter(|(library_item, _, _, library_item_stream)| {
+                    !continue_watching_settings.excludes(library_item, *library_item_stream)
+                })
+                .map(
+                    |(library_item, notifications, rewatch, library_item_stream)| {
+                        let snooze = snoozes.iter().find(|snooze| snooze.id == library_item.id);
+                        (
+                            Item::from((
+                                *library_item,
+                                *notifications,
+                                rewatch.is_some(),
+                                library_item_stream,
+                                settings,
+                            )),
+                            snooze,
+                        )
+                    },
+                )
+                .partition(|(_, snooze)| snooze.is_some());
+            let hidden = cw_items.len() - items.len() - snoozed.len();
             Self {
-                items: continue_watching_preview
-                    .items
-                    .iter()
-                    .map(|core_cw_item| {
-                        let library_item_stream = core_cw_item
-                            .library_item
-                            .state
-                            .video_id
-                            .clone()
-                            .and_then(|video_id| {
-                                streams_bucket.items.get(&StreamsItemKey {
-                                    meta_id: core_cw_item.library_item.id.clone(),
-                                    video_id,
-                                })
-                            });
-
-                        Item::from((
-                            core_cw_item,
-                            library_item_stream,
-                            settings,
-                        ))
-                    })
-                    .collect::<Vec<_>>(),
+                exclusions: Exclusions {
+                    channels: continue_watching_settings.exclude_channels,
+                    types: &continue_watching_settings.excluded_types,
+                    addons: continue_watching_settings
+                        .excluded_addons
+                        .iter()
+                        .map(|transport_url| ExcludedAddon {
+                            transport_url,
+                            name: addons
+                                .iter()
+                                .find(|addon| &addon.transport_url == transport_url)
+                                .map(|addon| &addon.manifest.name),
+                        })
+                        .collect(),
+                    catalogs: &continue_watching_settings.excluded_catalogs,
+                    hidden,
+                },
+                items: items
+                    .into_iter()
+                    .take(CATALOG_PREVIEW_SIZE)
+                    .map(|(item, _)| item)
+                    .collect(),
+                snoozed: snoozed
+                    .into_iter()
+                    .filter_map(|(item, snooze)| snooze.map(|snooze| SnoozedItem { item, snooze }))
+                    .collect(),
                 deep_links: LibraryDeepLinks::from(&"continuewatching".to_owned())
                     .into_web_deep_links(),
             }
@@ -92,29 +246,32 @@ mod model {
         library_item: LibraryItem<'a>,
         /// a count of the total notifications we have for this item
         notifications: usize,
+        /// Watched again from the first episode
+        rewatching: bool,
     }
 
     impl<'a>
         From<(
-            &'a stremio_core::models::continue_watching_preview::Item,
+            &'a stremio_core::types::library::LibraryItem,
+            usize,
+            bool,
             Option<&StreamsItem>,
             &Settings,
         )> for Item<'a>
     {
         fn from(
-            (item, stream_item, settings): (
-                &'a stremio_core::models::continue_watching_preview::Item,
+            (library_item, notifications, rewatching, stream_item, settings): (
+                &'a stremio_core::types::library::LibraryItem,
+                usize,
+                bool,
                 Option<&StreamsItem>,
                 &Settings,
             ),
         ) -> Self {
             Self {
-                library_item: LibraryItem::from((
-                    &item.library_item,
-                    stream_item,
-                    settings,
-                )),
-                notifications: item.notifications,
+                library_item: LibraryItem::from((library_item, stream_item, settings)),
+                notifications,
+                rewatching,
             }
         }
     }
@@ -124,11 +281,14 @@ mod model {
     pub struct LibraryItem<'a> {
         #[serde(rename = "_id")]
         pub id: &'a String,
-        pub name: &'a String,
+        /// The name and the poster set by the user, otherwise the ones of the item
+        pub name: Cow<'a, str>,
         pub r#type: &'a String,
-        pub poster: &'a Option<Url>,
+        pub poster: Cow<'a, Option<Url>>,
         pub poster_shape: &'a PosterShape,
         pub progress: f64,
+        /// Changed in the library, the API did not confirm it yet
+        pub pending: bool,
         pub deep_links: LibraryItemDeepLinks,
         pub state: LibraryItemState<'a>,
     }
@@ -149,9 +309,9 @@ mod model {
         ) -> Self {
             LibraryItem {
                 id: &library_item.id,
-                name: &library_item.name,
+                name: meta_overrides::name(&library_item.id, &library_item.name),
                 r#type: &library_item.r#type,
-                poster: &library_item.poster,
+                poster: meta_overrides::poster(&library_item.id, &library_item.poster),
                 poster_shape: match library_item.poster_shape {
                     // override poster shape if it's Landscape to over be a Square.
                     PosterShape::Landscape => &PosterShape::Square,
@@ -163,6 +323,7 @@ mod model {
                 } else {
                     0.0
                 },
+                pending: library_pending::is_pending(&library_item.id),
                 deep_links: LibraryItemDeepLinks::from((
                     library_item,
                     streams_item,
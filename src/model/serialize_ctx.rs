@@ -1,9 +1,55 @@
+use std::collections::HashMap;
+
 use wasm_bindgen::JsValue;
 
 use stremio_core::models::ctx::Ctx;
 
-pub fn serialize_ctx(ctx: &Ctx) -> JsValue {
-    JsValue::from_serde(&model::Ctx::from(ctx)).unwrap()
+use crate::account::{self, Account};
+use crate::blocklist::{self, BlockedItem};
+use crate::debrid::{self, DebridStatus};
+use crate::features;
+use crate::lan_sync::{self, LanSyncStatus};
+use crate::onboarding::{self, Onboarding, OnboardingConfig};
+use crate::reminders::{self, Reminder};
+use crate::remote_config;
+use crate::undo::{self, UndoableAction};
+use crate::web_settings::{self, WebSettings};
+
+/// The state of the web modules which is serialized along with the ctx
+pub struct CtxSideState {
+    pub features: HashMap<String, bool>,
+    pub experiments: HashMap<String, String>,
+    pub web_settings: WebSettings,
+    pub onboarding: Onboarding,
+    pub onboarding_config: OnboardingConfig,
+    pub reminders: Vec<Reminder>,
+    pub account: Account,
+    pub debrid: DebridStatus,
+    pub undoable: Vec<UndoableAction>,
+    pub blocked_items: Vec<BlockedItem>,
+    pub lan_sync: LanSyncStatus,
+}
+
+impl CtxSideState {
+    pub fn new(ctx: &Ctx) -> Self {
+        Self {
+            features: features::features(),
+            experiments: features::assignments(&ctx.profile),
+            web_settings: web_settings::web_settings(),
+            onboarding: onboarding::onboarding(),
+            onboarding_config: remote_config::onboarding_config(),
+            reminders: reminders::reminders(),
+            account: account::account(),
+            debrid: debrid::status(),
+            undoable: undo::undoable(),
+            blocked_items: blocklist::blocked_items(),
+            lan_sync: lan_sync::status(),
+        }
+    }
+}
+
+pub fn serialize_ctx(ctx: &Ctx, side_state: &CtxSideState) -> JsValue {
+    JsValue::from_serde(&model::Ctx::from((ctx, side_state))).unwrap()
 }
 
 mod model {
@@ -13,16 +59,148 @@ mod model {
 
     use chrono::{DateTime, Utc};
 
+    use url::Url;
+
+    use stremio_core::deep_links::MetaItemDeepLinks;
+    use stremio_core::models::common::Loadable;
     use stremio_core::types::{
-        notifications::NotificationItem, profile::Profile, resource::MetaItemId,
+        notifications::NotificationItem,
+        profile::Profile,
+        resource::{MetaItemId, MetaItemPreview},
     };
 
+    use crate::account::{self, EmailFlow, ProfileDisplay, Session, AVATAR_PRESETS};
+    use crate::blocklist::BlockedItem;
+    use crate::debrid::DebridStatus;
+    use crate::lan_sync::LanSyncStatus;
+    use crate::model::deep_links_ext::DeepLinksExt;
+    use crate::model::loadable_states;
+    use crate::model::CtxSideState;
+    use crate::onboarding::OnboardingStep;
+    use crate::reminders::PushPayload;
+    use crate::undo::UndoType;
+    use crate::web_settings::WebSettings;
+
     #[derive(Serialize)]
     #[serde(rename_all = "camelCase")]
     pub struct Ctx<'a> {
         /// keep the original Profile model inside.
         pub profile: &'a Profile,
         pub notifications: Notifications<'a>,
+        /// Feature flags, defaults merged with the remote overrides
+        pub features: &'a HashMap<String, bool>,
+        /// Assigned variant per experiment id
+        pub experiments: &'a HashMap<String, String>,
+        /// Settings which are specific to the web app
+        pub web_settings: &'a WebSettings,
+        pub onboarding: Onboarding<'a>,
+        /// Events the user asked to be reminded of
+        pub reminders: Vec<ScheduledReminder<'a>>,
+        /// Avatar and display name of the user, `None` when logged out
+        pub profile_display: Option<ProfileDisplayState<'a>>,
+        /// Devices the user is logged in on, `NotAsked` when logged out or not loaded
+        #[serde(serialize_with = "loadable_states::not_asked")]
+        pub sessions: Option<Loadable<Vec<SessionState<'a>>, &'a String>>,
+        /// Progress of the flows which send an email, driving the login screen
+        pub email_flows: EmailFlows<'a>,
+        /// Account and errors of the debrid service the torrents are resolved through
+        pub debrid: &'a DebridStatus,
+        /// Actions the UI can offer to undo, until they expire
+        pub undo: Vec<PendingUndo<'a>>,
+        /// Items hidden from the catalogs, the most recently blocked first
+        pub blocked_items: &'a [BlockedItem],
+        /// Sync with the other devices of the local network, when not logged in
+        pub lan_sync: &'a LanSyncStatus,
+    }
+
+    #[derive(Serialize)]
+    #[serde(rename_all = "camelCase")]
+    pub struct PendingUndo<'a> {
+        pub id: &'a String,
+        pub r#type: UndoType,
+        pub name: &'a String,
+        pub expires_at: DateTime<Utc>,
+    }
+
+    #[derive(Serialize)]
+    #[serde(rename_all = "camelCase")]
+    pub struct EmailFlows<'a> {
+        pub email_verification: &'a Option<EmailFlow>,
+        pub password_reset: &'a Option<EmailFlow>,
+    }
+
+    #[derive(Serialize)]
+    #[serde(rename_all = "camelCase")]
+    pub struct SessionState<'a> {
+        #[serde(flatten)]
+        pub session: &'a Session,
+        pub revoking: bool,
+    }
+
+    #[derive(Serialize)]
+    #[serde(rename_all = "camelCase")]
+    pub struct ProfileDisplayState<'a> {
+        #[serde(flatten)]
+        pub display: ProfileDisplay,
+        /// The display is an optimistic update which is being saved
+        pub saving: bool,
+        pub error: Option<&'a String>,
+        pub avatar_presets: Vec<AvatarPreset>,
+    }
+
+    #[derive(Serialize)]
+    #[serde(rename_all = "camelCase")]
+    pub struct AvatarPreset {
+        pub id: &'static str,
+        pub url: Url,
+    }
+
+    #[derive(Serialize)]
+    #[serde(rename_all = "camelCase")]
+    pub struct ScheduledReminder<'a> {
+        pub id: &'a String,
+        pub start: &'a DateTime<Utc>,
+        pub remind_at: &'a DateTime<Utc>,
+        pub due: bool,
+    }
+
+    #[derive(Serialize)]
+    #[serde(rename_all = "camelCase")]
+    pub struct DueReminder<'a> {
+        pub meta_item: &'a MetaItemPreview,
+        pub start: &'a DateTime<Utc>,
+        pub deep_links: MetaItemDeepLinks,
+        pub push_payload: PushPayload<'a>,
+    }
+
+    #[derive(Serialize)]
+    #[serde(rename_all = "camelCase")]
+    pub struct Onboarding<'a> {
+        pub completed: bool,
+        pub step: OnboardingStep,
+        pub steps: &'static [OnboardingStep],
+        pub languages: Vec<OnboardingOption<'a>>,
+        pub genres: Vec<OnboardingOption<'a>>,
+        /// Addons recommended for the selected language and genres
+        pub addons: Vec<OnboardingAddon<'a>>,
+        #[serde(serialize_with = "loadable_states::not_asked")]
+        pub finishing: Option<Loadable<(), String>>,
+    }
+
+    #[derive(Serialize)]
+    #[serde(rename_all = "camelCase")]
+    pub struct OnboardingOption<'a> {
+        pub value: &'a str,
+        pub selected: bool,
+    }
+
+    #[derive(Serialize)]
+    #[serde(rename_all = "camelCase")]
+    pub struct OnboardingAddon<'a> {
+        pub transport_url: &'a Url,
+        pub name: &'a str,
+        pub selected: bool,
+        pub installed: bool,
     }
 
     #[derive(Serialize)]
@@ -32,10 +210,25 @@ mod model {
         pub items: HashMap<MetaItemId, Vec<&'a NotificationItem>>,
         pub last_updated: Option<DateTime<Utc>>,
         pub created: DateTime<Utc>,
+        /// Event reminders which are due and were not dismissed yet
+        pub reminders: Vec<DueReminder<'a>>,
     }
 
-    impl<'a> From<&'a stremio_core::models::ctx::Ctx> for Ctx<'a> {
-        fn from(ctx: &'a stremio_core::models::ctx::Ctx) -> Self {
+    impl<'a> From<(&'a stremio_core::models::ctx::Ctx, &'a CtxSideState)> for Ctx<'a> {
+        fn from((ctx, side_state): (&'a stremio_core::models::ctx::Ctx, &'a CtxSideState)) -> Self {
+            let CtxSideState {
+                features,
+                experiments,
+                web_settings,
+                onboarding,
+                onboarding_config,
+                reminders,
+                account,
+                debrid,
+                undoable,
+                blocked_items,
+                lan_sync,
+            } = side_state;
             Self {
                 profile: &ctx.profile,
                 notifications: Notifications {
@@ -49,7 +242,116 @@ mod model {
                         .collect(),
                     last_updated: ctx.notifications.last_updated,
                     created: ctx.notifications.created,
+                    // the reminders are due once the background task notified of them,
+                    // so the serialized state does not change with the time
+                    reminders: reminders
+                        .iter()
+                        .filter(|reminder| reminder.is_notified())
+                        .map(|reminder| DueReminder {
+                            meta_item: &reminder.meta_item,
+                            start: &reminder.start,
+                            deep_links: MetaItemDeepLinks::from((
+                                &reminder.meta_item,
+                                &reminder.meta_request,
+                            ))
+                            .into_web_deep_links(),
+                            push_payload: reminder.push_payload(),
+                        })
+                        .collect(),
+                },
+                features,
+                experiments,
+                web_settings,
+                onboarding: Onboarding {
+                    completed: onboarding.completed,
+                    step: onboarding.step,
+                    steps: &OnboardingStep::ALL,
+                    languages: onboarding_config
+                        .languages
+                        .iter()
+                        .map(|language| OnboardingOption {
+                            value: language,
+                            selected: onboarding.language.as_ref() == Some(language),
+                        })
+                        .collect(),
+                    genres: onboarding_config
+                        .genres
+                        .iter()
+                        .map(|genre| OnboardingOption {
+                            value: genre,
+                            selected: onboarding.genres.contains(genre),
+                        })
+                        .collect(),
+                    addons: onboarding
+                        .recommended_addons(onboarding_config)
+                        .map(|addon| OnboardingAddon {
+                            transport_url: &addon.transport_url,
+                            name: &addon.name,
+                            selected: onboarding.selected_addons.contains(&addon.transport_url),
+                            installed: ctx
+                                .profile
+                                .addons
+                                .iter()
+                                .any(|installed| installed.transport_url == addon.transport_url),
+                        })
+                        .collect(),
+                    finishing: onboarding.finishing.to_owned(),
+                },
+                reminders: reminders
+                    .iter()
+                    .map(|reminder| ScheduledReminder {
+                        id: &reminder.meta_item.id,
+                        start: &reminder.start,
+                        remind_at: &reminder.remind_at,
+                        due: reminder.is_notified(),
+                    })
+                    .collect(),
+                profile_display: ctx.profile.auth.as_ref().map(|auth| ProfileDisplayState {
+                    display: account.display(&auth.user),
+                    saving: account.pending_display.is_some(),
+                    error: account.display_error.as_ref(),
+                    avatar_presets: AVATAR_PRESETS
+                        .iter()
+                        .map(|id| AvatarPreset {
+                            id,
+                            url: account::preset_avatar_url(id),
+                        })
+                        .collect(),
+                }),
+                sessions: ctx
+                    .profile
+                    .auth
+                    .as_ref()
+                    .and_then(|auth| account.sessions(&auth.user))
+                    .map(|sessions| match sessions {
+                        Loadable::Ready(sessions) => Loadable::Ready(
+                            sessions
+                                .iter()
+                                .map(|session| SessionState {
+                                    session,
+                                    revoking: account.revoking_sessions.contains(&session.id),
+                                })
+                                .collect(),
+                        ),
+                        Loadable::Loading => Loadable::Loading,
+                        Loadable::Err(error) => Loadable::Err(error),
+                    }),
+                email_flows: EmailFlows {
+                    email_verification: &account.email_verification,
+                    password_reset: &account.password_reset,
                 },
+                debrid,
+                undo: undoable
+                    .iter()
+                    .map(|undoable_action| PendingUndo {
+                        id: &undoable_action.id,
+                        r#type: undoable_action.r#type(),
+                        name: undoable_action.name(),
+                        expires_at: undoable_action.expires_at,
+                    })
+                    .collect(),
+                blocked_items,
+                lan_sync,
             }
         }
     }
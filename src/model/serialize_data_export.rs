@@ -5,12 +5,16 @@ use stremio_core::models::data_export::DataExport;
 use url::Url;
 use wasm_bindgen::JsValue;
 
+use crate::library_transfer::{self, ImportReport};
+
 mod model {
     use super::*;
     #[derive(Serialize)]
     #[serde(rename_all = "camelCase")]
     pub struct DataExport<'a> {
         pub export_url: Option<&'a Loadable<Url, CtxError>>,
+        /// The progress of the library import, `None` until a file is imported
+        pub library_import: Option<ImportReport>,
     }
 }
 
@@ -20,6 +24,7 @@ pub fn serialize_data_export(data_export: &DataExport) -> JsValue {
             .export_url
             .as_ref()
             .map(|(_auth_key, loadable)| loadable),
+        library_import: library_transfer::import_report(),
     })
     .unwrap()
 }
@@ -1,19 +1,46 @@
 use boolinator::Boolinator;
+use chrono::{DateTime, Utc};
 use itertools::Itertools;
 
 use serde::Serialize;
+use std::borrow::Cow;
 use wasm_bindgen::JsValue;
 
-use stremio_core::deep_links::{DiscoverDeepLinks, MetaItemDeepLinks, StreamDeepLinks};
+use stremio_core::deep_links::{
+    DiscoverDeepLinks, MetaItemDeepLinks, StreamDeepLinks, VideoDeepLinks,
+};
 use stremio_core::models::catalog_with_filters::{
     CatalogWithFilters, Selected as CatalogWithFiltersSelected,
 };
 use stremio_core::models::common::Loadable;
 use stremio_core::models::ctx::Ctx;
 use stremio_core::models::streaming_server::StreamingServer;
-use stremio_core::types::resource::MetaItemPreview;
+use stremio_core::runtime::Env;
+use stremio_core::types::addon::{ManifestBehaviorHints, ResourceRequest};
+use stremio_core::types::resource::{MetaItemPreview, PosterShape};
+use url::Url;
 
+use crate::blocklist;
+use crate::catalog_cache;
+use crate::catalog_hints;
+use crate::env::WebEnv;
+use crate::epg::{self, Program};
+use crate::ipfs;
+use crate::meta_overrides;
 use crate::model::deep_links_ext::DeepLinksExt;
+use crate::model::grid_density::{self, GridDensity};
+use crate::model::loadable_states::{self, LoadState};
+use crate::model::pagination::{self, Pagination};
+use crate::model::placeholders::{self, Placeholder};
+use crate::model::range_extras::{self, RangeExtra};
+use crate::model::spatial_navigation::{self, NavigationHint};
+use crate::palettes::{self, Palette};
+use crate::schema_validation::{self, SchemaWarning};
+use crate::trailers::{self, YouTubeTrailer};
+use crate::{
+    prefetch, push_transport, request_tracing, response_limits, retry, streaming_catalogs,
+    web_settings,
+};
 
 mod model {
     use super::*;
@@ -21,6 +48,7 @@ mod model {
     #[serde(rename_all = "camelCase")]
     pub struct ManifestPreview<'a> {
         pub name: &'a String,
+        pub behavior_hints: &'a ManifestBehaviorHints,
     }
     #[derive(Serialize)]
     #[serde(rename_all = "camelCase")]
@@ -40,6 +68,9 @@ mod model {
         pub name: &'a String,
         pub is_required: &'a bool,
         pub options: Vec<SelectableExtraOption<'a>>,
+        /// Slider of the numeric extras, e.g. the year and the rating
+        #[serde(skip_serializing_if = "Option::is_none")]
+        pub range: Option<RangeExtra>,
     }
     #[derive(Serialize)]
     #[serde(rename_all = "camelCase")]
@@ -64,6 +95,8 @@ mod model {
         pub catalogs: Vec<SelectableCatalog<'a>>,
         pub extra: Vec<SelectableExtra<'a>>,
         pub next_page: bool,
+        /// The next page has been loaded in the background and will be served instantly
+        pub next_page_prefetched: bool,
     }
     #[derive(Serialize)]
     #[serde(rename_all = "camelCase")]
@@ -71,21 +104,84 @@ mod model {
         #[serde(flatten)]
         pub stream: &'a stremio_core::types::resource::Stream,
         pub deep_links: StreamDeepLinks,
+        /// Embeddable urls of a YouTube trailer
+        #[serde(skip_serializing_if = "Option::is_none")]
+        pub youtube: Option<YouTubeTrailer<'a>>,
     }
     #[derive(Serialize)]
     #[serde(rename_all = "camelCase")]
     pub struct MetaItemPreview<'a> {
+        /// With the poster and the name set by the user
         #[serde(flatten)]
-        pub meta_item: &'a stremio_core::types::resource::MetaItemPreview,
+        pub meta_item: Cow<'a, stremio_core::types::resource::MetaItemPreview>,
         pub trailer_streams: Vec<Stream<'a>>,
         pub in_library: bool,
         pub deep_links: MetaItemDeepLinks,
+        #[serde(skip_serializing_if = "Option::is_none")]
+        pub navigation: Option<NavigationHint>,
+        /// Colors of the poster, `None` until they are extracted
+        pub palette: Option<Palette>,
     }
     #[derive(Serialize)]
     #[serde(rename_all = "camelCase")]
     pub struct ResourceLoadable<'a> {
         pub content: Loadable<Vec<MetaItemPreview<'a>>, String>,
+        /// Whether the first page is loading again or it was cancelled
+        #[serde(skip_serializing_if = "Option::is_none")]
+        pub load_state: Option<LoadState>,
+        /// Skeleton entries while the first or the next page is loading
+        pub placeholder_count: usize,
+        pub placeholders: Vec<Placeholder>,
+        /// Schema violations of the addon responses for all of the loaded pages
+        #[serde(skip_serializing_if = "Vec::is_empty")]
+        pub warnings: Vec<SchemaWarning>,
+        /// Declared by the addon manifest, otherwise the one of the first item
+        pub poster_shape: Option<PosterShape>,
+        /// Preferred number of items per row, declared by the addon manifest
+        pub items_per_row: Option<usize>,
+        /// Requests made to load the last page, more than one when it was retried
+        pub attempts: u32,
+        /// Id of the failed request of the last page in the logs, for the users to report the error
+        #[serde(skip_serializing_if = "Option::is_none")]
+        pub request_id: Option<String>,
+        /// Only the first items of the oversized response of the addon are shown, for any page
+        pub truncated_by_limit: bool,
         pub installed: bool,
+        /// The first page was served by the catalog cache within the `cacheMaxAge` of the addon
+        pub served_from_cache: bool,
+        /// Seconds since the cached first page was fetched from the addon
+        pub cache_age: Option<i64>,
+        /// Columns and full rows of the grid, once the UI described its viewport
+        #[serde(skip_serializing_if = "Option::is_none")]
+        pub grid: Option<GridDensity>,
+        /// Paging of the catalog, whichever convention the addon uses
+        pub pagination: Pagination,
+    }
+    #[derive(Serialize)]
+    #[serde(rename_all = "camelCase")]
+    pub struct EpgProgram<'a> {
+        pub id: &'a String,
+        pub title: &'a String,
+        pub thumbnail: &'a Option<String>,
+        pub start: DateTime<Utc>,
+        pub stop: Option<DateTime<Utc>>,
+        /// Elapsed part of the program on air, from 0 to 1
+        pub progress: Option<f64>,
+        pub deep_links: VideoDeepLinks,
+    }
+    #[derive(Serialize)]
+    #[serde(rename_all = "camelCase")]
+    pub struct EpgNowNext<'a> {
+        pub now: Option<EpgProgram<'a>>,
+        pub next: Option<EpgProgram<'a>>,
+    }
+    #[derive(Serialize)]
+    #[serde(rename_all = "camelCase")]
+    pub struct EpgChannel<'a> {
+        pub id: &'a String,
+        pub name: &'a String,
+        pub logo: Option<&'a Url>,
+        pub guide: Loadable<EpgNowNext<'a>, &'a String>,
     }
     #[derive(Serialize)]
     #[serde(rename_all = "camelCase")]
@@ -93,6 +189,9 @@ mod model {
         pub selected: &'a Option<CatalogWithFiltersSelected>,
         pub selectable: Selectable<'a>,
         pub catalog: Option<ResourceLoadable<'a>>,
+        /// Now/next guide of the channels, only for the channel catalogs
+        #[serde(skip_serializing_if = "Option::is_none")]
+        pub epg: Option<Vec<EpgChannel<'a>>>,
     }
 }
 
@@ -101,6 +200,32 @@ pub fn serialize_discover(
     ctx: &Ctx,
     streaming_server: &StreamingServer,
 ) -> JsValue {
+    let pushed_catalogs = push_transport::pushed_catalogs();
+    let partial_catalogs = streaming_catalogs::partial_catalogs();
+    let blocked_ids = blocklist::blocked_ids();
+    let trailers_settings = web_settings::web_settings().trailers;
+    let now = WebEnv::now();
+    // the programs on air change along with the state of Discover, not on every serialization
+    let guides_clock = epg::clock();
+    let guides = discover
+        .catalog
+        .first()
+        .filter(|first_page| epg::is_channel_catalog(&first_page.request))
+        .map(|first_page| {
+            discover
+                .catalog
+                .iter()
+                .filter_map(|page| page.content.as_ref().and_then(|content| content.ready()))
+                .flatten()
+                .unique_by(|channel| &channel.id)
+                .take(epg::MAX_CHANNELS)
+                .map(|channel| {
+                    let guide = epg::guide(&epg::guide_request(&first_page.request, channel))
+                        .unwrap_or(Loadable::Loading);
+                    (channel, guide)
+                })
+                .collect::<Vec<_>>()
+        });
     JsValue::from_serde(&model::CatalogWithFilters {
         selected: &discover.selected,
         selectable: model::Selectable {
@@ -132,6 +257,7 @@ pub fn serialize_discover(
                     addon: model::DescriptorPreview {
                         manifest: model::ManifestPreview {
                             name: &addon.manifest.name,
+                            behavior_hints: &addon.manifest.behavior_hints,
                         },
                     },
                     selected: &selectable_catalog.selected,
@@ -156,23 +282,91 @@ pub fn serialize_discover(
                                 .into_web_deep_links(),
                         })
                         .collect(),
+                    range: range_extras::range_extra(
+                        &selectable_extra.name,
+                        &selectable_extra
+                            .options
+                            .iter()
+                            .filter_map(|option| option.value.as_ref())
+                            .collect::<Vec<_>>(),
+                        selectable_extra
+                            .options
+                            .iter()
+                            .find(|option| option.selected)
+                            .and_then(|option| option.value.as_ref()),
+                    ),
                 })
                 .collect(),
             next_page: discover.selectable.next_page.is_some(),
+            next_page_prefetched: discover
+                .selectable
+                .next_page
+                .as_ref()
+                .map(|next_page| prefetch::is_prefetched(&next_page.request))
+                .unwrap_or_default(),
         },
         catalog: (!discover.catalog.is_empty()).as_option().map(|_| {
             let first_page = discover.catalog.first().unwrap();
-            model::ResourceLoadable {
-                content: match &first_page.content {
-                    Some(Loadable::Ready(_)) => Loadable::Ready(
-                        discover
-                            .catalog
-                            .iter()
-                            .filter_map(|page| page.content.as_ref())
-                            .filter_map(|page_content| page_content.ready())
-                            .flat_map(|meta_items| {
-                                meta_items.iter().map(|meta_item| model::MetaItemPreview {
-                                    meta_item,
+            let last_page = discover.catalog.last().unwrap();
+            let hints = catalog_hints::catalog_hints(&first_page.request);
+            let cache_status = catalog_cache::cache_status(&first_page.request, now)
+                .filter(|cache_status| cache_status.served_from_cache);
+            let poster_shape = hints
+                .as_ref()
+                .and_then(|hints| hints.poster_shape.to_owned())
+                .or_else(|| match &first_page.content {
+                    Some(Loadable::Ready(meta_items)) => meta_items
+                        .first()
+                        .map(|meta_item| meta_item.poster_shape.to_owned()),
+                    _ => None,
+                });
+            if let Some(poster_shape) = &poster_shape {
+                placeholders::remember_poster_shape(&first_page.request, poster_shape);
+            }
+            // the items parsed so far are shown while a large catalog is still loading
+            let first_page_ready = matches!(first_page.content, Some(Loadable::Ready(_)))
+                || partial_catalogs
+                    .iter()
+                    .any(|(request, _)| *request == first_page.request);
+            let layout = grid_density::grid_layout(
+                &poster_shape.to_owned().unwrap_or_default(),
+                hints.as_ref().and_then(|hints| hints.items_per_row),
+            );
+            let placeholders = match &last_page.content {
+                Some(Loadable::Loading) | None => placeholders::placeholders(
+                    &last_page.request,
+                    grid_density::placeholder_count(layout.as_ref()),
+                ),
+                _ => vec![],
+            };
+            let content = match (&first_page.content, first_page_ready) {
+                (_, true) => Loadable::Ready(
+                    discover
+                        .catalog
+                        .iter()
+                        .filter_map(|page| {
+                            pushed_catalogs
+                                .iter()
+                                .find(|(request, _)| *request == page.request)
+                                .map(|(_, meta_items)| meta_items)
+                                .or_else(|| {
+                                    page.content
+                                        .as_ref()
+                                        .and_then(|page_content| page_content.ready())
+                                })
+                                .or_else(|| {
+                                    partial_catalogs
+                                        .iter()
+                                        .find(|(request, _)| *request == page.request)
+                                        .map(|(_, meta_items)| meta_items.as_ref())
+                                })
+                        })
+                        .flat_map(|meta_items| {
+                            meta_items
+                                .iter()
+                                .filter(|meta_item| !blocked_ids.contains(&meta_item.id))
+                                .map(|meta_item| model::MetaItemPreview {
+                                    meta_item: meta_overrides::meta_item_preview(meta_item),
                                     trailer_streams: meta_item
                                         .trailer_streams
                                         .iter()
@@ -180,10 +374,17 @@ pub fn serialize_discover(
                                         .map(|stream| model::Stream {
                                             stream,
                                             deep_links: StreamDeepLinks::from((
-                                                stream,
+                                                ipfs::resolve_stream(stream).as_ref(),
                                                 &ctx.profile.settings,
                                             ))
                                             .into_web_deep_links(),
+                                            youtube: trailers::youtube_trailer(
+                                                stream,
+                                                meta_item,
+                                                &first_page.request.base,
+                                                &ctx.profile.settings,
+                                                &trailers_settings,
+                                            ),
                                         })
                                         .collect::<Vec<_>>(),
                                     in_library: ctx
@@ -197,23 +398,150 @@ pub fn serialize_discover(
                                         &first_page.request,
                                     ))
                                     .into_web_deep_links(),
+                                    navigation: None,
+                                    palette: palettes::palette(
+                                        meta_overrides::poster(&meta_item.id, &meta_item.poster)
+                                            .as_ref()
+                                            .as_ref(),
+                                    ),
                                 })
-                            })
-                            // it is possible that they are duplicates returned in 2 different pages
-                            // so we deduplicate all the results at once
-                            .unique_by(|meta| &meta.meta_item.id)
-                            .collect::<Vec<_>>(),
-                    ),
-                    Some(Loadable::Loading) | None => Loadable::Loading,
-                    Some(Loadable::Err(error)) => Loadable::Err(error.to_string()),
-                },
+                        })
+                        // it is possible that they are duplicates returned in 2 different pages
+                        // so we deduplicate all the results at once
+                        .unique_by(|meta| &meta.meta_item.id)
+                        .enumerate()
+                        .map(|(index, mut meta)| {
+                            meta.navigation = spatial_navigation::grid_hint(
+                                "discover",
+                                index,
+                                // the columns of the viewport, which include the hinted ones
+                                layout.as_ref().map(|layout| layout.columns).or_else(|| {
+                                    hints.as_ref().and_then(|hints| hints.items_per_row)
+                                }),
+                                &meta.meta_item.id,
+                            );
+                            meta
+                        })
+                        .collect::<Vec<_>>(),
+                ),
+                (Some(Loadable::Err(error)), _) => Loadable::Err(error.to_string()),
+                _ => Loadable::Loading,
+            };
+            let grid = layout.as_ref().map(|layout| {
+                grid_density::grid_density(
+                    layout,
+                    content.ready().map_or(0, Vec::len),
+                    discover
+                        .selectable
+                        .next_page
+                        .as_ref()
+                        .map(|next_page| &next_page.request),
+                )
+            });
+            model::ResourceLoadable {
+                content,
+                // the items of the last page parsed so far are shown along with the loaded pages
+                load_state: loadable_states::load_state(
+                    &last_page.request,
+                    last_page.content.as_ref(),
+                )
+                .filter(|load_state| *load_state == LoadState::Partial)
+                .or_else(|| {
+                    loadable_states::load_state(&first_page.request, first_page.content.as_ref())
+                }),
+                placeholder_count: placeholders.len(),
+                placeholders,
+                warnings: discover
+                    .catalog
+                    .iter()
+                    .flat_map(|page| schema_validation::warnings(&page.request))
+                    .collect(),
+                poster_shape,
+                items_per_row: hints.and_then(|hints| hints.items_per_row),
+                attempts: retry::attempts(&last_page.request),
+                request_id: request_tracing::failed_request_id(
+                    &last_page.request,
+                    last_page.content.as_ref(),
+                ),
+                truncated_by_limit: discover
+                    .catalog
+                    .iter()
+                    .any(|page| response_limits::is_truncated(&page.request)),
                 installed: ctx
                     .profile
                     .addons
                     .iter()
                     .any(|addon| addon.transport_url == first_page.request.base),
+                served_from_cache: cache_status.is_some(),
+                cache_age: cache_status.map(|cache_status| cache_status.age),
+                grid,
+                pagination: pagination::pagination(
+                    &discover.catalog,
+                    discover
+                        .selectable
+                        .next_page
+                        .as_ref()
+                        .map(|next_page| &next_page.request),
+                ),
             }
         }),
+        epg: discover
+            .catalog
+            .first()
+            .zip(guides.as_ref())
+            .map(|(first_page, guides)| {
+                guides
+                    .iter()
+                    .map(|(channel, guide)| model::EpgChannel {
+                        id: &channel.id,
+                        name: &channel.name,
+                        logo: channel.logo.as_ref().or(channel.poster.as_ref()),
+                        guide: match guide {
+                            Loadable::Ready(meta_item) => match guides_clock {
+                                Some(now) => {
+                                    let request = epg::guide_request(&first_page.request, channel);
+                                    let (now_program, next_program) = epg::now_next(meta_item, now);
+                                    Loadable::Ready(model::EpgNowNext {
+                                        now: now_program.map(|program| {
+                                            epg_program(program, &request, ctx, now)
+                                        }),
+                                        next: next_program.map(|program| {
+                                            epg_program(program, &request, ctx, now)
+                                        }),
+                                    })
+                                }
+                                None => Loadable::Loading,
+                            },
+                            Loadable::Loading => Loadable::Loading,
+                            Loadable::Err(error) => Loadable::Err(error),
+                        },
+                    })
+                    .collect()
+            }),
     })
     .unwrap()
 }
+
+fn epg_program<'a>(
+    program: Program<'a>,
+    request: &ResourceRequest,
+    ctx: &Ctx,
+    now: DateTime<Utc>,
+) -> model::EpgProgram<'a> {
+    model::EpgProgram {
+        id: &program.video.id,
+        title: &program.video.title,
+        thumbnail: &program.video.thumbnail,
+        start: program.start,
+        stop: program.stop,
+        progress: program
+            .stop
+            .filter(|stop| program.start <= now && now < *stop)
+            .map(|stop| {
+                (now - program.start).num_seconds() as f64
+                    / (stop - program.start).num_seconds().max(1) as f64
+            }),
+        deep_links: VideoDeepLinks::from((program.video, request, &ctx.profile.settings))
+            .into_web_deep_links(),
+    }
+}
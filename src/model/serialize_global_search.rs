@@ -0,0 +1,249 @@
+use std::cmp::Reverse;
+
+use itertools::Itertools;
+use serde::Serialize;
+use url::{form_urlencoded, Url};
+use wasm_bindgen::JsValue;
+
+use stremio_core::deep_links::{DiscoverDeepLinks, LibraryItemDeepLinks};
+use stremio_core::models::ctx::Ctx;
+use stremio_core::types::addon::{ResourcePath, ResourceRequest};
+use stremio_core::types::streams::StreamsItemKey;
+
+use crate::model::deep_links_ext::DeepLinksExt;
+
+/// Results of every group, the best matches first
+const MAX_GROUP_RESULTS: usize = 10;
+
+/// Settings which can be jumped to, as `(id, section, title, keywords)`
+const SETTINGS_ENTRIES: [(&str, &str, &str, &[&str]); 12] = [
+    (
+        "interfaceLanguage",
+        "general",
+        "Interface language",
+        &["locale"],
+    ),
+    (
+        "hideSpoilers",
+        "general",
+        "Hide spoilers",
+        &["blur", "thumbnails"],
+    ),
+    (
+        "subtitlesLanguage",
+        "player",
+        "Subtitles language",
+        &["captions"],
+    ),
+    (
+        "subtitlesSize",
+        "player",
+        "Subtitles size",
+        &["captions", "font"],
+    ),
+    ("audioLanguage", "player", "Audio language", &["dub"]),
+    (
+        "autoplay",
+        "player",
+        "Play next video automatically",
+        &["binge", "next episode"],
+    ),
+    (
+        "playInExternalPlayer",
+        "player",
+        "Play in external player",
+        &["vlc", "mpv"],
+    ),
+    (
+        "streamingServerUrl",
+        "streaming",
+        "Streaming server url",
+        &["server"],
+    ),
+    ("cacheSize", "streaming", "Caching", &["cache", "disk"]),
+    (
+        "debrid",
+        "streaming",
+        "Debrid service",
+        &["real-debrid", "alldebrid", "premiumize"],
+    ),
+    (
+        "notifications",
+        "notifications",
+        "New episode notifications",
+        &["quiet hours"],
+    ),
+    (
+        "shortcuts",
+        "shortcuts",
+        "Keyboard shortcuts",
+        &["keys", "hotkeys"],
+    ),
+];
+
+mod model {
+    use super::*;
+    #[derive(Serialize)]
+    #[serde(rename_all = "camelCase")]
+    pub struct LibraryItem<'a> {
+        #[serde(rename = "_id")]
+        pub id: &'a String,
+        pub name: &'a String,
+        pub r#type: &'a String,
+        pub poster: &'a Option<Url>,
+        pub deep_links: LibraryItemDeepLinks,
+    }
+    #[derive(Serialize)]
+    #[serde(rename_all = "camelCase")]
+    pub struct Addon<'a> {
+        pub transport_url: &'a Url,
+        pub name: &'a String,
+        pub logo: &'a Option<Url>,
+        /// Opens the details of the addon
+        pub deep_link: String,
+    }
+    #[derive(Serialize)]
+    #[serde(rename_all = "camelCase")]
+    pub struct Catalog<'a> {
+        pub addon_name: &'a String,
+        pub r#type: &'a String,
+        pub id: &'a String,
+        pub name: Option<&'a String>,
+        pub deep_links: DiscoverDeepLinks,
+    }
+    #[derive(Serialize)]
+    #[serde(rename_all = "camelCase")]
+    pub struct Setting {
+        pub id: &'static str,
+        pub section: &'static str,
+        pub title: &'static str,
+        pub deep_link: String,
+    }
+    #[derive(Serialize)]
+    #[serde(rename_all = "camelCase")]
+    pub struct GlobalSearch<'a> {
+        pub query: &'a str,
+        pub library: Vec<LibraryItem<'a>>,
+        pub addons: Vec<Addon<'a>>,
+        pub catalogs: Vec<Catalog<'a>>,
+        pub settings: Vec<Setting>,
+    }
+}
+
+/// Searches the library, the installed addons and their catalogs and the settings locally,
+/// for the quick switcher. The groups are empty for a blank query.
+pub fn serialize_global_search(query: &str, ctx: &Ctx) -> JsValue {
+    let query = query.trim();
+    let needle = query.to_lowercase();
+    let matches = |text: &str| rank(text, &needle);
+    JsValue::from_serde(&model::GlobalSearch {
+        query,
+        library: best_matches(
+            ctx.library
+                .items
+                .values()
+                .filter(|library_item| !library_item.removed && !library_item.temp),
+            |library_item| matches(&library_item.name),
+        )
+        .map(|library_item| {
+            let streams_item = library_item.state.video_id.as_ref().and_then(|video_id| {
+                ctx.streams.items.get(&StreamsItemKey {
+                    meta_id: library_item.id.to_owned(),
+                    video_id: video_id.to_owned(),
+                })
+            });
+            model::LibraryItem {
+                id: &library_item.id,
+                name: &library_item.name,
+                r#type: &library_item.r#type,
+                poster: &library_item.poster,
+                deep_links: LibraryItemDeepLinks::from((
+                    library_item,
+                    streams_item,
+                    &ctx.profile.settings,
+                ))
+                .into_web_deep_links(),
+            }
+        })
+        .collect(),
+        addons: best_matches(ctx.profile.addons.iter(), |addon| {
+            matches(&addon.manifest.name)
+        })
+        .map(|addon| model::Addon {
+            transport_url: &addon.transport_url,
+            name: &addon.manifest.name,
+            logo: &addon.manifest.logo,
+            deep_link: format!(
+                "#/addons?{}",
+                form_urlencoded::Serializer::new(String::new())
+                    .append_pair("addon", addon.transport_url.as_str())
+                    .finish()
+            ),
+        })
+        .collect(),
+        catalogs: best_matches(
+            ctx.profile.addons.iter().flat_map(|addon| {
+                addon
+                    .manifest
+                    .catalogs
+                    .iter()
+                    .map(move |catalog| (addon, catalog))
+            }),
+            |(_, catalog)| matches(catalog.name.as_deref().unwrap_or(&catalog.id)),
+        )
+        .map(|(addon, catalog)| model::Catalog {
+            addon_name: &addon.manifest.name,
+            r#type: &catalog.r#type,
+            id: &catalog.id,
+            name: catalog.name.as_ref(),
+            deep_links: DiscoverDeepLinks::from(&ResourceRequest::new(
+                addon.transport_url.to_owned(),
+                ResourcePath::without_extra("catalog", &catalog.r#type, &catalog.id),
+            ))
+            .into_web_deep_links(),
+        })
+        .collect(),
+        settings: best_matches(SETTINGS_ENTRIES.iter(), |(_, _, title, keywords)| {
+            let keywords_rank = || keywords.iter().filter_map(|keyword| matches(keyword)).max();
+            matches(title).or_else(keywords_rank)
+        })
+        .map(|(id, section, title, _)| model::Setting {
+            id: *id,
+            section: *section,
+            title: *title,
+            deep_link: format!("#/settings?section={section}"),
+        })
+        .collect(),
+    })
+    .unwrap()
+}
+
+/// How well the text matches the lowercase query, `None` when it does not
+fn rank(text: &str, needle: &str) -> Option<u8> {
+    if needle.is_empty() {
+        return None;
+    }
+    let text = text.to_lowercase();
+    if text == needle {
+        Some(3)
+    } else if text.starts_with(needle) {
+        Some(2)
+    } else if text.split_whitespace().any(|word| word.starts_with(needle)) {
+        Some(1)
+    } else if text.contains(needle) {
+        Some(0)
+    } else {
+        None
+    }
+}
+
+fn best_matches<T>(
+    items: impl Iterator<Item = T>,
+    rank: impl Fn(&T) -> Option<u8>,
+) -> impl Iterator<Item = T> {
+    items
+        .filter_map(|item| rank(&item).map(|rank| (rank, item)))
+        .sorted_by_key(|(rank, _)| Reverse(*rank))
+        .take(MAX_GROUP_RESULTS)
+        .map(|(_, item)| item)
+}
@@ -1,9 +1,14 @@
+use crate::addon_updates::{AddonUpdate, ChangelogEntry};
+use crate::mirrors::MirrorsPreview;
 use crate::model::deep_links_ext::DeepLinksExt;
+use chrono::{DateTime, Utc};
 use serde::Serialize;
+use std::collections::HashMap;
 use stremio_core::deep_links::AddonsDeepLinks;
 use stremio_core::models::installed_addons_with_filters::{
     InstalledAddonsRequest, InstalledAddonsWithFilters, Selected,
 };
+use url::Url;
 use wasm_bindgen::JsValue;
 
 mod model {
@@ -14,6 +19,15 @@ mod model {
         #[serde(flatten)]
         pub addon: &'a stremio_core::types::addon::DescriptorPreview,
         pub installed: bool,
+        /// When the addon was last updated to a new version, `None` if it wasn't since installed
+        pub updated_at: Option<&'a DateTime<Utc>>,
+        /// What the last update changed in the catalogs, resources and types of the addon
+        pub changelog: Option<&'a Vec<ChangelogEntry>>,
+        /// A newer version waiting for the user to confirm its installation
+        pub available_update: Option<&'a AddonUpdate>,
+        /// The url the addon is currently reached through, one of its mirrors when the transport url is unavailable
+        pub active_transport_url: &'a Url,
+        pub mirrors: Option<&'a MirrorsPreview>,
     }
     #[derive(Serialize)]
     #[serde(rename_all = "camelCase")]
@@ -44,7 +58,12 @@ mod model {
     }
 }
 
-pub fn serialize_installed_addons(installed_addons: &InstalledAddonsWithFilters) -> JsValue {
+pub fn serialize_installed_addons(
+    installed_addons: &InstalledAddonsWithFilters,
+    updates: &[AddonUpdate],
+    available_updates: &[AddonUpdate],
+    mirrors: &HashMap<Url, MirrorsPreview>,
+) -> JsValue {
     JsValue::from_serde(&model::InstalledAddonsWithFilters {
         selected: &installed_addons.selected,
         selectable: model::Selectable {
@@ -69,9 +88,25 @@ pub fn serialize_installed_addons(installed_addons: &InstalledAddonsWithFilters)
         catalog: installed_addons
             .catalog
             .iter()
-            .map(|addon| model::DescriptorPreview {
-                addon,
-                installed: true,
+            .map(|addon| {
+                let update = updates
+                    .iter()
+                    .find(|update| update.transport_url == addon.transport_url)
+                    .filter(|update| update.version == addon.manifest.version);
+                let mirrors = mirrors.get(&addon.transport_url);
+                model::DescriptorPreview {
+                    addon,
+                    installed: true,
+                    updated_at: update.map(|update| &update.updated_at),
+                    changelog: update.map(|update| &update.changelog),
+                    available_update: available_updates
+                        .iter()
+                        .find(|update| update.transport_url == addon.transport_url),
+                    active_transport_url: mirrors
+                        .and_then(|mirrors| mirrors.active.as_ref())
+                        .unwrap_or(&addon.transport_url),
+                    mirrors,
+                }
             })
             .collect(),
     })
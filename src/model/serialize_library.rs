@@ -1,10 +1,21 @@
+use crate::library_pending;
+use crate::library_tags;
+use crate::meta_overrides;
 use crate::model::deep_links_ext::DeepLinksExt;
+use crate::model::library_sort::{self, WebSort};
+use crate::model::spatial_navigation::{self, NavigationHint};
+use crate::user_ratings::{self, UserRating};
+use itertools::Itertools;
 use serde::Serialize;
+use std::borrow::Cow;
+use stremio_core::constants::CATALOG_PAGE_SIZE;
 use stremio_core::deep_links::{LibraryDeepLinks, LibraryItemDeepLinks};
-use stremio_core::models::library_with_filters::{LibraryWithFilters, Selected, Sort};
-use stremio_core::types::profile::Settings;
+use stremio_core::models::ctx::Ctx;
+use stremio_core::models::library_with_filters::{
+    LibraryFilter, LibraryWithFilters, Selected, Sort,
+};
 use stremio_core::types::resource::PosterShape;
-use stremio_core::types::streams::{StreamsBucket, StreamsItemKey};
+use stremio_core::types::streams::StreamsItemKey;
 use url::Url;
 use wasm_bindgen::JsValue;
 
@@ -15,22 +26,40 @@ mod model {
     pub struct LibraryItem<'a> {
         #[serde(rename = "_id")]
         pub id: &'a String,
-        pub name: &'a String,
+        /// The name and the poster set by the user, otherwise the ones of the item
+        pub name: Cow<'a, str>,
         pub r#type: &'a String,
-        pub poster: &'a Option<Url>,
+        pub poster: Cow<'a, Option<Url>>,
         pub poster_shape: &'a PosterShape,
         pub progress: f64,
+        /// Added to the library, the API did not confirm it yet
+        pub pending: bool,
+        /// Set by the user
+        pub tags: Vec<String>,
+        /// The rating and the note of the user
+        #[serde(skip_serializing_if = "Option::is_none")]
+        pub user_rating: Option<UserRating>,
         pub deep_links: LibraryItemDeepLinks,
+        #[serde(skip_serializing_if = "Option::is_none")]
+        pub navigation: Option<NavigationHint>,
     }
     #[derive(Serialize)]
     #[serde(rename_all = "camelCase")]
     pub struct SelectableType<'a> {
         pub r#type: &'a Option<String>,
         pub selected: &'a bool,
+        /// Number of library items of this type
+        pub count: usize,
         pub deep_links: LibraryDeepLinks,
     }
     #[derive(Serialize)]
     #[serde(rename_all = "camelCase")]
+    pub struct WatchedFacet {
+        pub watched: usize,
+        pub not_watched: usize,
+    }
+    #[derive(Serialize)]
+    #[serde(rename_all = "camelCase")]
     pub struct SelectableSort<'a> {
         pub sort: &'a Sort,
         pub selected: &'a bool,
@@ -38,6 +67,22 @@ mod model {
     }
     #[derive(Serialize)]
     #[serde(rename_all = "camelCase")]
+    pub struct SelectableWebSort {
+        pub sort: WebSort,
+        pub selected: bool,
+        pub deep_links: LibraryDeepLinks,
+    }
+    #[derive(Serialize)]
+    #[serde(rename_all = "camelCase")]
+    pub struct SelectableTag {
+        pub tag: String,
+        pub selected: bool,
+        /// Number of library items with this tag
+        pub count: usize,
+        pub deep_links: LibraryDeepLinks,
+    }
+    #[derive(Serialize)]
+    #[serde(rename_all = "camelCase")]
     pub struct SelectablePage {
         pub deep_links: LibraryDeepLinks,
     }
@@ -46,6 +91,12 @@ mod model {
     pub struct Selectable<'a> {
         pub types: Vec<SelectableType<'a>>,
         pub sorts: Vec<SelectableSort<'a>>,
+        /// Counts of the items of the selected type by their watched state
+        pub watched: WatchedFacet,
+        /// Additional sorts which are applied by the web model
+        pub web_sorts: Vec<SelectableWebSort>,
+        /// The tags set by the user, the catalog is filtered by the selected one
+        pub tags: Vec<SelectableTag>,
         pub prev_page: Option<SelectablePage>,
         pub next_page: Option<SelectablePage>,
     }
@@ -57,12 +108,64 @@ mod model {
     }
 }
 
-pub fn serialize_library<F>(
+pub fn serialize_library<F: LibraryFilter>(
     library: &LibraryWithFilters<F>,
-    streams_bucket: &StreamsBucket,
-    settings: &Settings,
+    ctx: &Ctx,
     root: String,
 ) -> JsValue {
+    let settings = &ctx.profile.settings;
+    let streams_bucket = &ctx.streams;
+    let library_items = ctx
+        .library
+        .items
+        .values()
+        .filter(|library_item| F::predicate(library_item, &ctx.notifications))
+        .collect::<Vec<_>>();
+    let selected_type = library
+        .selected
+        .as_ref()
+        .and_then(|selected| selected.request.r#type.as_ref());
+    let (watched, not_watched) = library_items
+        .iter()
+        .filter(|library_item| {
+            selected_type.map_or(true, |selected_type| library_item.r#type == *selected_type)
+        })
+        .fold((0, 0), |(watched, not_watched), library_item| {
+            if library_item.state.times_watched > 0 {
+                (watched + 1, not_watched)
+            } else {
+                (watched, not_watched + 1)
+            }
+        });
+    let web_sort = library_sort::selected_sort(&root);
+    let selected_tag = library_tags::selected_tag(&root);
+    // a tag filters the whole library rather than the page, its items are sorted by the web sort
+    // and the most recently added first by default.
+    // A web sort sorts the whole library too, the page selected in core is taken from it.
+    let catalog = match (web_sort, &selected_tag) {
+        (None, None) => library.catalog.iter().collect::<Vec<_>>(),
+        (web_sort, selected_tag) => {
+            let web_sort = web_sort.unwrap_or(WebSort::DateAdded);
+            let catalog = library_items
+                .iter()
+                .filter(|library_item| {
+                    selected_type
+                        .map_or(true, |selected_type| library_item.r#type == *selected_type)
+                        && selected_tag.as_ref().map_or(true, |selected_tag| {
+                            library_tags::tags(&library_item.id).contains(selected_tag)
+                        })
+                })
+                .copied()
+                .sorted_by(|a, b| web_sort.compare(a, b, &settings.interface_language));
+            match (&selected_tag, &library.selected) {
+                (None, Some(selected)) => catalog
+                    .skip((selected.request.page.0.get() - 1) * CATALOG_PAGE_SIZE)
+                    .take(CATALOG_PAGE_SIZE)
+                    .collect(),
+                _ => catalog.collect(),
+            }
+        }
+    };
     JsValue::from_serde(&model::LibraryWithFilters {
         selected: &library.selected,
         selectable: model::Selectable {
@@ -73,6 +176,13 @@ pub fn serialize_library<F>(
                 .map(|selectable_type| model::SelectableType {
                     r#type: &selectable_type.r#type,
                     selected: &selectable_type.selected,
+                    count: match &selectable_type.r#type {
+                        Some(r#type) => library_items
+                            .iter()
+                            .filter(|library_item| library_item.r#type == *r#type)
+                            .count(),
+                        None => library_items.len(),
+                    },
                     deep_links: LibraryDeepLinks::from((&root, &selectable_type.request))
                         .into_web_deep_links(),
                 })
@@ -83,28 +193,84 @@ pub fn serialize_library<F>(
                 .iter()
                 .map(|selectable_sort| model::SelectableSort {
                     sort: &selectable_sort.sort,
-                    selected: &selectable_sort.selected,
+                    selected: if web_sort.is_some() {
+                        &false
+                    } else {
+                        &selectable_sort.selected
+                    },
                     deep_links: LibraryDeepLinks::from((&root, &selectable_sort.request))
                         .into_web_deep_links(),
                 })
                 .collect(),
-            prev_page: library.selectable.prev_page.as_ref().map(|prev_page| {
-                model::SelectablePage {
+            watched: model::WatchedFacet {
+                watched,
+                not_watched,
+            },
+            web_sorts: library
+                .selected
+                .as_ref()
+                .map(|selected| {
+                    let deep_links = LibraryDeepLinks::from((&root, &selected.request));
+                    WebSort::ALL
+                        .iter()
+                        .map(|sort| model::SelectableWebSort {
+                            sort: *sort,
+                            selected: web_sort == Some(*sort),
+                            deep_links: LibraryDeepLinks {
+                                library: library_sort::with_web_sort(&deep_links.library, *sort),
+                            }
+                            .into_web_deep_links(),
+                        })
+                        .collect()
+                })
+                .unwrap_or_default(),
+            tags: library
+                .selected
+                .as_ref()
+                .map(|selected| {
+                    let deep_links = LibraryDeepLinks::from((&root, &selected.request));
+                    let ids = library_items.iter().map(|library_item| &library_item.id);
+                    library_tags::tag_counts(ids)
+                        .into_iter()
+                        .map(|tag_count| model::SelectableTag {
+                            selected: selected_tag.as_ref() == Some(&tag_count.tag),
+                            deep_links: LibraryDeepLinks {
+                                library: library_tags::with_tag(
+                                    &deep_links.library,
+                                    &tag_count.tag,
+                                ),
+                            }
+                            .into_web_deep_links(),
+                            tag: tag_count.tag,
+                            count: tag_count.count,
+                        })
+                        .collect()
+                })
+                .unwrap_or_default(),
+            // the items of a tag are not paged
+            prev_page: library
+                .selectable
+                .prev_page
+                .as_ref()
+                .filter(|_| selected_tag.is_none())
+                .map(|prev_page| model::SelectablePage {
                     deep_links: LibraryDeepLinks::from((&root, &prev_page.request))
                         .into_web_deep_links(),
-                }
-            }),
-            next_page: library.selectable.next_page.as_ref().map(|next_page| {
-                model::SelectablePage {
+                }),
+            next_page: library
+                .selectable
+                .next_page
+                .as_ref()
+                .filter(|_| selected_tag.is_none())
+                .map(|next_page| model::SelectablePage {
                     deep_links: LibraryDeepLinks::from((&root, &next_page.request))
                         .into_web_deep_links(),
-                }
-            }),
+                }),
         },
-        catalog: library
-            .catalog
-            .iter()
-            .map(|library_item| {
+        catalog: catalog
+            .into_iter()
+            .enumerate()
+            .map(|(index, library_item)| {
                 // Try to get the stream from the StreamBucket
                 // given that we have a video_id in the LibraryItemState!
                 let streams_item = library_item.state.video_id.as_ref().and_then(|video_id| {
@@ -116,9 +282,9 @@ pub fn serialize_library<F>(
 
                 model::LibraryItem {
                     id: &library_item.id,
-                    name: &library_item.name,
+                    name: meta_overrides::name(&library_item.id, &library_item.name),
                     r#type: &library_item.r#type,
-                    poster: &library_item.poster,
+                    poster: meta_overrides::poster(&library_item.id, &library_item.poster),
                     poster_shape: if library_item.poster_shape == PosterShape::Landscape {
                         &PosterShape::Square
                     } else {
@@ -131,12 +297,21 @@ pub fn serialize_library<F>(
                     } else {
                         0.0
                     },
+                    pending: library_pending::is_pending(&library_item.id),
+                    tags: library_tags::tags(&library_item.id),
+                    user_rating: user_ratings::user_rating(&library_item.id),
                     deep_links: LibraryItemDeepLinks::from((
                         library_item,
                         streams_item,
                         settings,
                     ))
                     .into_web_deep_links(),
+                    navigation: spatial_navigation::grid_hint(
+                        "library",
+                        index,
+                        None,
+                        &library_item.id,
+                    ),
                 }
             })
             .collect(),
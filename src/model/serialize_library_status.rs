@@ -0,0 +1,52 @@
+use std::collections::HashMap;
+
+use serde::Serialize;
+use wasm_bindgen::JsValue;
+
+use stremio_core::models::ctx::Ctx;
+
+use crate::library_pending;
+
+mod model {
+    use super::*;
+    #[derive(Serialize)]
+    #[serde(rename_all = "camelCase")]
+    pub struct LibraryStatus {
+        pub in_library: bool,
+        /// Changed in the library, the API did not confirm it yet
+        pub pending: bool,
+        pub watched: bool,
+        /// Watch progress percentage of the last watched video
+        pub progress: f64,
+        /// Count of the new videos of the item
+        pub notifications: usize,
+    }
+}
+
+/// Badges of the meta items by their ids, in one call for the lists which are not from the library
+pub fn serialize_library_status(ids: &[String], ctx: &Ctx) -> JsValue {
+    let statuses = ids
+        .iter()
+        .map(|id| {
+            let library_item = ctx
+                .library
+                .items
+                .get(id)
+                .filter(|library_item| !library_item.removed);
+            let status = model::LibraryStatus {
+                in_library: library_pending::in_library(id, library_item.is_some()),
+                pending: library_pending::is_pending(id),
+                watched: library_item
+                    .map_or(false, |library_item| library_item.state.times_watched > 0),
+                progress: library_item.map_or(0.0, |library_item| library_item.progress()),
+                notifications: ctx
+                    .notifications
+                    .items
+                    .get(id)
+                    .map_or(0, |notifications| notifications.len()),
+            };
+            (id, status)
+        })
+        .collect::<HashMap<_, _>>();
+    JsValue::from_serde(&statuses).unwrap()
+}
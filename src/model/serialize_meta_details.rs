@@ -1,8 +1,31 @@
-use crate::{env::WebEnv, model::deep_links_ext::DeepLinksExt};
+use crate::{
+    debrid,
+    env::WebEnv,
+    ipfs, library_pending,
+    meta_overrides::{self, MetaOverride},
+    model::{
+        deep_links_ext::DeepLinksExt,
+        lite_mode::{self, Slice, Truncated},
+        loadable_states::{self, LoadState},
+        meta_localization,
+        stream_trust::StreamTrust,
+    },
+    palettes::{self, Palette},
+    request_tracing, response_limits, retry,
+    rewatch::{self, Rewatch},
+    season_packs::{self, PackFile},
+    stream_history::{self, PlayedStream},
+    stream_timeouts,
+    trailers::{self, YouTubeTrailer},
+    user_ratings::{self, UserRating},
+    web_playback,
+    web_settings::{self, TypeAddons},
+};
 
 use either::Either;
 use itertools::Itertools;
 use serde::Serialize;
+use std::borrow::Cow;
 use std::iter;
 use url::Url;
 use wasm_bindgen::JsValue;
@@ -16,8 +39,13 @@ use stremio_core::{
         meta_details::{MetaDetails, Selected as MetaDetailsSelected},
         streaming_server::StreamingServer,
     },
-    runtime::Env,
-    types::library::LibraryItem,
+    runtime::{Env, EnvError},
+    types::{
+        addon::{Descriptor, ManifestBehaviorHints, ResourcePath, ResourceRequest},
+        library::LibraryItem,
+        resource::{MetaItem, Stream, Video},
+        streams::StreamsItemKey,
+    },
 };
 
 mod model {
@@ -28,6 +56,7 @@ mod model {
         pub id: &'a String,
         pub name: &'a String,
         pub logo: &'a Option<Url>,
+        pub behavior_hints: &'a ManifestBehaviorHints,
     }
     #[derive(Serialize)]
     #[serde(rename_all = "camelCase")]
@@ -43,6 +72,32 @@ mod model {
         // Watch progress percentage
         pub progress: Option<f64>,
         pub deep_links: StreamDeepLinks,
+        /// The url the web player loads, see `web_playback::web_url`
+        pub web_url: Option<Url>,
+        pub trust: StreamTrust,
+        /// Whether the torrent can be streamed right away through the debrid service,
+        /// `None` until the cache of the service is checked
+        #[serde(skip_serializing_if = "Option::is_none")]
+        pub debrid_cached: Option<bool>,
+        /// The stream played last time this meta item was watched
+        pub last_used: bool,
+        /// Files of the torrent, e.g. the episodes of a season pack, once the stream is expanded
+        #[serde(skip_serializing_if = "Option::is_none")]
+        pub pack_streams: Option<Loadable<Vec<PackStream>, String>>,
+        /// Embeddable urls of a YouTube trailer
+        #[serde(skip_serializing_if = "Option::is_none")]
+        pub youtube: Option<YouTubeTrailer<'a>>,
+    }
+    #[derive(Serialize)]
+    #[serde(rename_all = "camelCase")]
+    pub struct PackStream {
+        /// The stream of the torrent playing the file
+        #[serde(flatten)]
+        pub stream: stremio_core::types::resource::Stream,
+        pub file: PackFile,
+        /// The file is the episode of the selected video
+        pub is_selected_episode: bool,
+        pub deep_links: StreamDeepLinks,
     }
     #[derive(Serialize)]
     #[serde(rename_all = "camelCase")]
@@ -54,23 +109,78 @@ mod model {
         // Watch progress percentage
         pub progress: Option<f64>,
         pub scheduled: bool,
+        /// The last episode of its season, known only once the season has ended
+        pub is_season_finale: bool,
+        /// Episodes out of the regular seasons, i.e. of season 0
+        pub is_special: bool,
+        /// Image to show when the video has no thumbnail of its own
+        pub thumbnail_fallback: Option<&'a Url>,
         pub deep_links: VideoDeepLinks,
     }
     #[derive(Serialize)]
     #[serde(rename_all = "camelCase")]
+    pub struct SeasonProgress {
+        pub season: u32,
+        /// One character per episode in the order of the episodes, `1` when it was watched
+        pub watched: String,
+        pub watched_count: usize,
+    }
+    #[derive(Serialize)]
+    #[serde(rename_all = "camelCase")]
+    pub struct SeriesProgress {
+        pub seasons: Vec<SeasonProgress>,
+        /// Percentage of the released episodes which were watched, the specials left out
+        pub completion: f64,
+    }
+    #[derive(Serialize)]
+    #[serde(rename_all = "camelCase")]
     pub struct MetaItem<'a> {
+        /// With the name and the description in the interface language when an addon provides them
         #[serde(flatten)]
-        pub meta_item: &'a stremio_core::types::resource::MetaItem,
+        pub meta_item: Cow<'a, stremio_core::types::resource::MetaItemPreview>,
+        /// The name as provided by the addon of the item, only present when the name is localized
+        #[serde(skip_serializing_if = "Option::is_none")]
+        pub original_name: Option<&'a String>,
         pub videos: Vec<Video<'a>>,
+        #[serde(skip_serializing_if = "Option::is_none")]
+        pub videos_truncated: Option<Truncated>,
         pub trailer_streams: Vec<Stream<'a>>,
         pub in_library: bool,
+        /// The library change of the item is not yet confirmed by the API
+        pub in_library_pending: bool,
+        /// The API rejected the library change of the item, `in_library` is reverted
+        pub in_library_error: Option<String>,
         pub watched: bool,
         pub deep_links: MetaItemDeepLinks,
+        /// Colors of the background, or of the poster without one, for theming the page
+        pub palette: Option<Palette>,
+        /// The poster and the name set by the user, already applied to the item
+        #[serde(skip_serializing_if = "Option::is_none")]
+        pub meta_override: Option<MetaOverride>,
+        /// The rating and the note of the user
+        #[serde(skip_serializing_if = "Option::is_none")]
+        pub user_rating: Option<UserRating>,
+        /// The episodes watched by season, only for the series in the library
+        #[serde(skip_serializing_if = "Option::is_none")]
+        pub series_progress: Option<SeriesProgress>,
     }
     #[derive(Serialize)]
     #[serde(rename_all = "camelCase")]
     pub struct ResourceLoadable<'a, T> {
         pub content: Loadable<T, &'a ResourceError>,
+        /// Whether the content is loading again, it was cancelled or it timed out
+        #[serde(skip_serializing_if = "Option::is_none")]
+        pub load_state: Option<LoadState>,
+        /// Requests made to load the content, more than one when it was retried
+        pub attempts: u32,
+        /// Id of the failed request in the logs, for the users to report the error
+        #[serde(skip_serializing_if = "Option::is_none")]
+        pub request_id: Option<String>,
+        /// Only the first items of the oversized response of the addon are shown
+        pub truncated_by_limit: bool,
+        /// Only the first items of the content are serialized in the lite mode
+        #[serde(skip_serializing_if = "Option::is_none")]
+        pub truncated: Option<Truncated>,
         pub addon: DescriptorPreview<'a>,
     }
     #[derive(Serialize)]
@@ -82,18 +192,86 @@ mod model {
     }
     #[derive(Serialize)]
     #[serde(rename_all = "camelCase")]
+    pub enum StreamsDiagnosisReason {
+        /// None of the installed addons declares streams for this type and id
+        NoSupportingAddons,
+        /// Every addon timed out or could not be reached
+        AddonsUnreachable,
+        AddonsFailed,
+        /// At least one addon responded, but without any streams
+        NoResults,
+    }
+    #[derive(Serialize)]
+    #[serde(rename_all = "camelCase")]
+    pub enum StreamsOutcome {
+        Empty,
+        Unreachable,
+        Failed,
+    }
+    #[derive(Serialize)]
+    #[serde(rename_all = "camelCase")]
+    pub struct DiagnosedAddon<'a> {
+        pub addon: DescriptorPreview<'a>,
+        pub outcome: StreamsOutcome,
+        pub error: &'a ResourceError,
+    }
+    #[derive(Serialize)]
+    #[serde(rename_all = "camelCase")]
+    pub struct StreamsDiagnosis<'a> {
+        pub reason: StreamsDiagnosisReason,
+        pub r#type: &'a String,
+        pub id: &'a String,
+        pub addons: Vec<DiagnosedAddon<'a>>,
+    }
+    #[derive(Serialize)]
+    #[serde(rename_all = "camelCase")]
+    pub struct AddonPreferences<'a> {
+        pub r#type: &'a String,
+        /// The preferred meta addon, when it's installed
+        pub meta: Option<DescriptorPreview<'a>>,
+        /// The preferred stream addons which are installed, in the order their streams are listed
+        pub streams: Vec<DescriptorPreview<'a>>,
+        /// The meta item is the one of the preferred meta addon
+        pub meta_from_preferred: bool,
+    }
+    #[derive(Serialize)]
+    #[serde(rename_all = "camelCase")]
+    pub struct PlayTarget<'a> {
+        /// The meta item itself for the ones without videos, e.g. the movies
+        pub video: Cow<'a, stremio_core::types::resource::Video>,
+        /// The video was left in the middle, otherwise it's the first unwatched one
+        pub in_progress: bool,
+        pub progress: Option<f64>,
+        pub deep_links: VideoDeepLinks,
+        /// Deep links of the stream the video was played with, when it's known
+        pub stream_deep_links: Option<StreamDeepLinks>,
+    }
+    #[derive(Serialize)]
+    #[serde(rename_all = "camelCase")]
     pub struct MetaDetails<'a> {
         pub selected: &'a Option<MetaDetailsSelected>,
         pub meta_item: Option<ResourceLoadable<'a, MetaItem<'a>>>,
         pub library_item: &'a Option<LibraryItem>,
         pub streams: Vec<ResourceLoadable<'a, Vec<Stream<'a>>>>,
+        /// Why there are no streams, once every addon has responded without any
+        pub streams_diagnosis: Option<StreamsDiagnosis<'a>>,
         pub meta_extensions: Vec<MetaExtension<'a>>,
         pub title: Option<String>,
+        /// Deep links of the last used stream, when it's listed and its auto-selection is enabled
+        pub auto_selected_stream: Option<StreamDeepLinks>,
+        /// What the Play button plays, only for the items in the library
+        pub play_target: Option<PlayTarget<'a>>,
+        /// The series is watched again from the start
+        pub rewatch: Option<Rewatch>,
+        /// The addons preferred for the type of the item, already applied to the meta item
+        /// and to the order of the streams
+        pub addon_preferences: Option<AddonPreferences<'a>>,
     }
 }
 
 /// For MetaDetails:
 ///
+/// 0. If the preferred meta addon of the type has the item we show it, unless it failed
 /// 1. If at least 1 item is ready we show the first ready item's data
 /// 2. If all loaded resources have returned an error we show the first item's error
 /// 3. We show a loading state
@@ -102,30 +280,31 @@ pub fn serialize_meta_details(
     ctx: &Ctx,
     streaming_server: &StreamingServer,
 ) -> JsValue {
-    let meta_item = meta_details
-        .meta_items
-        .iter()
-        .find(|meta_item| matches!(&meta_item.content, Some(Loadable::Ready(_))))
-        .or_else(|| {
-            if meta_details
-                .meta_items
-                .iter()
-                .all(|meta_item| matches!(&meta_item.content, Some(Loadable::Err(_))))
-            {
-                meta_details.meta_items.first()
-            } else {
-                meta_details
-                    .meta_items
-                    .iter()
-                    .find(|meta_item| matches!(&meta_item.content, Some(Loadable::Loading)))
-            }
-        });
+    let web_settings = web_settings::web_settings();
+    let rewatch = meta_details
+        .library_item
+        .as_ref()
+        .and_then(|library_item| rewatch::rewatch(&library_item.id));
+    let type_addons = meta_details
+        .selected
+        .as_ref()
+        .and_then(|selected| web_settings.addons.type_addons(&selected.meta_path.r#type));
+    let preferred_meta_item = preferred_meta_item(meta_details, type_addons);
+    let meta_item = preferred_meta_item.or_else(|| first_meta_item(meta_details));
 
     let streams = if meta_details.meta_streams.is_empty() {
         meta_details.streams.iter()
     } else {
         meta_details.meta_streams.iter()
     };
+    let last_used = meta_details
+        .selected
+        .as_ref()
+        .and_then(|selected| stream_history::last_used(&selected.meta_path.id));
+    let auto_selected_stream = last_used
+        .as_ref()
+        .filter(|_| web_settings.streams.auto_select_last_used)
+        .and_then(|last_used| auto_selected_stream(last_used, streams.as_slice(), meta_item, ctx));
     JsValue::from_serde(&model::MetaDetails {
         selected: &meta_details.selected,
         meta_item: meta_item
@@ -142,40 +321,28 @@ pub fn serialize_meta_details(
                         request,
                         content: Some(Loadable::Ready(meta_item)),
                     } => Loadable::Ready(model::MetaItem {
-                        meta_item,
+                        meta_item: match meta_overrides::meta_item(meta_localization::localize(
+                            meta_item,
+                            &meta_details.meta_items,
+                            &ctx.profile.settings.interface_language,
+                        )) {
+                            Cow::Borrowed(meta_item) => Cow::Borrowed(&meta_item.preview),
+                            Cow::Owned(meta_item) => Cow::Owned(meta_item.preview),
+                        },
+                        original_name: meta_localization::original_name(
+                            meta_item,
+                            &meta_details.meta_items,
+                            &ctx.profile.settings.interface_language,
+                        ),
                         videos: meta_item
                             .videos
                             .iter()
-                            .map(|video| model::Video {
-                                video,
-                                upcomming: meta_item.preview.behavior_hints.has_scheduled_videos
-                                    && meta_item
-                                        .preview
-                                        .released
-                                        .map(|released| released > WebEnv::now())
-                                        .unwrap_or(true),
-                                watched: meta_details
-                                    .watched
-                                    .as_ref()
-                                    .map(|watched| watched.get_video(&video.id))
-                                    .unwrap_or_default(),
-                                progress: ctx
-                                    .library
-                                    .items
-                                    .get(&meta_item.preview.id)
-                                    .filter(|library_item| {
-                                        Some(video.id.to_owned()) == library_item.state.video_id
-                                    })
-                                    .map(|library_item| library_item.progress()),
-                                scheduled: meta_item.preview.behavior_hints.has_scheduled_videos,
-                                deep_links: VideoDeepLinks::from((
-                                    video,
-                                    request,
-                                    &ctx.profile.settings,
-                                ))
-                                .into_web_deep_links(),
+                            .take(lite_mode::max_items())
+                            .map(|video| {
+                                serialize_video(video, meta_item, request, meta_details, ctx)
                             })
                             .collect::<Vec<_>>(),
+                        videos_truncated: lite_mode::truncated(meta_item.videos.len()),
                         trailer_streams: meta_item
                             .preview
                             .trailer_streams
@@ -184,18 +351,34 @@ pub fn serialize_meta_details(
                                 stream,
                                 progress: None,
                                 deep_links: StreamDeepLinks::from((
-                                    stream,
+                                    ipfs::resolve_stream(stream).as_ref(),
                                     &ctx.profile.settings,
                                 ))
                                 .into_web_deep_links(),
+                                web_url: web_playback::web_url(stream),
+                                trust: StreamTrust::new(stream, addon),
+                                debrid_cached: None,
+                                last_used: false,
+                                pack_streams: None,
+                                youtube: trailers::youtube_trailer(
+                                    stream,
+                                    &meta_item.preview,
+                                    &request.base,
+                                    &ctx.profile.settings,
+                                    &web_settings.trailers,
+                                ),
                             })
                             .collect::<Vec<_>>(),
-                        in_library: ctx
-                            .library
-                            .items
-                            .get(&meta_item.preview.id)
-                            .map(|library_item| !library_item.removed)
-                            .unwrap_or_default(),
+                        in_library: library_pending::in_library(
+                            &meta_item.preview.id,
+                            ctx.library
+                                .items
+                                .get(&meta_item.preview.id)
+                                .map(|library_item| !library_item.removed)
+                                .unwrap_or_default(),
+                        ),
+                        in_library_pending: library_pending::is_pending(&meta_item.preview.id),
+                        in_library_error: library_pending::error(&meta_item.preview.id),
                         watched: ctx
                             .library
                             .items
@@ -204,6 +387,12 @@ pub fn serialize_meta_details(
                             .unwrap_or_default(),
                         deep_links: MetaItemDeepLinks::from((meta_item, request))
                             .into_web_deep_links(),
+                        palette: palettes::palette(palettes::theme_image(&meta_item.preview)),
+                        meta_override: meta_overrides::meta_override(&meta_item.preview.id),
+                        user_rating: user_ratings::user_rating(&meta_item.preview.id),
+                        series_progress: meta_details.watched.as_ref().and_then(|watched| {
+                            series_progress(meta_item, |video| watched.get_video(&video.id))
+                        }),
                     }),
                     ResourceLoadable {
                         content: Some(Loadable::Loading),
@@ -215,17 +404,35 @@ pub fn serialize_meta_details(
                         ..
                     } => Loadable::Err(error),
                 },
+                load_state: loadable_states::load_state(
+                    &meta_item.request,
+                    meta_item.content.as_ref(),
+                ),
+                attempts: retry::attempts(&meta_item.request),
+                request_id: request_tracing::failed_request_id(
+                    &meta_item.request,
+                    meta_item.content.as_ref(),
+                ),
+                truncated_by_limit: response_limits::is_truncated(&meta_item.request),
+                truncated: None,
                 addon: model::DescriptorPreview {
                     transport_url: &addon.transport_url,
                     manifest: model::ManifestPreview {
                         id: &addon.manifest.id,
                         name: &addon.manifest.name,
                         logo: &addon.manifest.logo,
+                        behavior_hints: &addon.manifest.behavior_hints,
                     },
                 },
             }),
         library_item: &meta_details.library_item,
+        streams_diagnosis: streams_diagnosis(meta_details, streams.as_slice(), ctx),
         streams: streams
+            .sorted_by_key(|streams| {
+                type_addons.map_or(0, |type_addons| {
+                    type_addons.streams_position(&streams.request.base)
+                })
+            })
             .filter_map(|streams| {
                 ctx.profile
                     .addons
@@ -233,64 +440,66 @@ pub fn serialize_meta_details(
                     .find(|addon| addon.transport_url == streams.request.base)
                     .map(|addon| (streams, addon))
             })
-            .map(|(streams, addon)| model::ResourceLoadable {
-                content: match streams {
-                    ResourceLoadable {
-                        request,
-                        content: Some(Loadable::Ready(streams)),
-                    } => Loadable::Ready(
-                        streams
-                            .iter()
-                            .map(|stream| model::Stream {
-                                stream,
-                                progress: meta_details.library_item.as_ref().and_then(
-                                    |library_item| {
-                                        ctx.streams
-                                            .items
-                                            .values()
-                                            .find(|item| item.stream == *stream)
-                                            .map(|_| library_item.progress())
-                                    },
-                                ),
-                                deep_links: meta_item
-                                    .map_or_else(
-                                        || {
-                                            StreamDeepLinks::from((
-                                                stream,
-                                                &ctx.profile.settings,
-                                            ))
-                                        },
-                                        |meta_item| {
-                                            StreamDeepLinks::from((
-                                                stream,
-                                                request,
-                                                &meta_item.request,
-                                                &ctx.profile.settings,
-                                            ))
-                                        },
-                                    )
-                                    .into_web_deep_links(),
-                            })
+            .map(|(streams, addon)| {
+                // the streams of an addon which timed out are shown once the user asks for them
+                let timed_out = stream_timeouts::is_timed_out(&streams.request);
+                model::ResourceLoadable {
+                    content: match streams {
+                        _ if timed_out => Loadable::Loading,
+                        ResourceLoadable {
+                            request,
+                            content: Some(Loadable::Ready(streams)),
+                        } => Loadable::Ready(
+                            serialize_streams(
+                                streams,
+                                request,
+                                addon,
+                                meta_details,
+                                meta_item,
+                                last_used.as_ref(),
+                                ctx,
+                            )
+                            .into_iter()
+                            .take(lite_mode::max_items())
                             .collect::<Vec<_>>(),
+                        ),
+                        ResourceLoadable {
+                            content: Some(Loadable::Loading),
+                            ..
+                        }
+                        | ResourceLoadable { content: None, .. } => Loadable::Loading,
+                        ResourceLoadable {
+                            content: Some(Loadable::Err(error)),
+                            ..
+                        } => Loadable::Err(error),
+                    },
+                    load_state: if timed_out {
+                        Some(LoadState::TimedOut)
+                    } else {
+                        loadable_states::load_state(&streams.request, streams.content.as_ref())
+                    },
+                    attempts: retry::attempts(&streams.request),
+                    request_id: request_tracing::failed_request_id(
+                        &streams.request,
+                        streams.content.as_ref(),
                     ),
-                    ResourceLoadable {
-                        content: Some(Loadable::Loading),
-                        ..
-                    }
-                    | ResourceLoadable { content: None, .. } => Loadable::Loading,
-                    ResourceLoadable {
-                        content: Some(Loadable::Err(error)),
-                        ..
-                    } => Loadable::Err(error),
-                },
-                addon: model::DescriptorPreview {
-                    transport_url: &addon.transport_url,
-                    manifest: model::ManifestPreview {
-                        id: &addon.manifest.id,
-                        name: &addon.manifest.name,
-                        logo: &addon.manifest.logo,
+                    truncated_by_limit: response_limits::is_truncated(&streams.request),
+                    truncated: match &streams.content {
+                        Some(Loadable::Ready(streams)) if !timed_out => {
+                            lite_mode::truncated(streams.len())
+                        }
+                        _ => None,
                     },
-                },
+                    addon: model::DescriptorPreview {
+                        transport_url: &addon.transport_url,
+                        manifest: model::ManifestPreview {
+                            id: &addon.manifest.id,
+                            name: &addon.manifest.name,
+                            logo: &addon.manifest.logo,
+                            behavior_hints: &addon.manifest.behavior_hints,
+                        },
+                    },
+                }
             })
             .collect::<Vec<_>>(),
         meta_extensions: meta_details
@@ -327,6 +536,7 @@ pub fn serialize_meta_details(
                         id: &addon.manifest.id,
                         name: &addon.manifest.name,
                         logo: &addon.manifest.logo,
+                        behavior_hints: &addon.manifest.behavior_hints,
                     },
                 },
             })
@@ -341,6 +551,11 @@ pub fn serialize_meta_details(
                 _ => None,
             })
             .map(|meta_item| {
+                let meta_item = meta_localization::localize(
+                    meta_item,
+                    &meta_details.meta_items,
+                    &ctx.profile.settings.interface_language,
+                );
                 meta_details
                     .selected
                     .as_ref()
@@ -373,6 +588,580 @@ pub fn serialize_meta_details(
                     })
                     .unwrap_or_else(|| meta_item.preview.name.to_owned())
             }),
+        auto_selected_stream,
+        play_target: play_target(meta_details, meta_item, rewatch.as_ref(), ctx),
+        rewatch,
+        addon_preferences: meta_details.selected.as_ref().zip(type_addons).map(
+            |(selected, type_addons)| model::AddonPreferences {
+                r#type: &selected.meta_path.r#type,
+                meta: type_addons
+                    .meta
+                    .as_ref()
+                    .and_then(|transport_url| installed_addon(transport_url, ctx))
+                    .map(descriptor_preview),
+                streams: type_addons
+                    .streams
+                    .iter()
+                    .filter_map(|transport_url| installed_addon(transport_url, ctx))
+                    .map(descriptor_preview)
+                    .collect(),
+                meta_from_preferred: preferred_meta_item.is_some(),
+            },
+        ),
     })
     .unwrap()
 }
+
+/// Videos of the meta item of MetaDetails past the ones serialized with the state in the lite mode,
+/// `null` until the meta item is loaded
+pub fn serialize_meta_details_videos(
+    meta_details: &MetaDetails,
+    ctx: &Ctx,
+    offset: usize,
+    limit: usize,
+) -> JsValue {
+    let web_settings = web_settings::web_settings();
+    let type_addons = meta_details
+        .selected
+        .as_ref()
+        .and_then(|selected| web_settings.addons.type_addons(&selected.meta_path.r#type));
+    match preferred_meta_item(meta_details, type_addons).or_else(|| first_meta_item(meta_details)) {
+        Some(ResourceLoadable {
+            request,
+            content: Some(Loadable::Ready(meta_item)),
+        }) => JsValue::from_serde(&Slice {
+            items: meta_item
+                .videos
+                .iter()
+                .skip(offset)
+                .take(limit)
+                .map(|video| serialize_video(video, meta_item, request, meta_details, ctx))
+                .collect::<Vec<_>>(),
+            total: meta_item.videos.len(),
+        })
+        .unwrap(),
+        _ => JsValue::NULL,
+    }
+}
+
+/// Streams of an addon of MetaDetails past the ones serialized with the state in the lite mode,
+/// `null` until they are loaded
+pub fn serialize_meta_details_streams(
+    meta_details: &MetaDetails,
+    ctx: &Ctx,
+    transport_url: &Url,
+    offset: usize,
+    limit: usize,
+) -> JsValue {
+    let web_settings = web_settings::web_settings();
+    let type_addons = meta_details
+        .selected
+        .as_ref()
+        .and_then(|selected| web_settings.addons.type_addons(&selected.meta_path.r#type));
+    let meta_item =
+        preferred_meta_item(meta_details, type_addons).or_else(|| first_meta_item(meta_details));
+    let streams = if meta_details.meta_streams.is_empty() {
+        &meta_details.streams
+    } else {
+        &meta_details.meta_streams
+    }
+    .iter()
+    .find(|streams| streams.request.base == *transport_url);
+    let last_used = meta_details
+        .selected
+        .as_ref()
+        .and_then(|selected| stream_history::last_used(&selected.meta_path.id));
+    match streams.zip(installed_addon(transport_url, ctx)) {
+        Some((
+            ResourceLoadable {
+                request,
+                content: Some(Loadable::Ready(streams)),
+            },
+            addon,
+        )) => JsValue::from_serde(&Slice {
+            items: serialize_streams(
+                streams,
+                request,
+                addon,
+                meta_details,
+                meta_item,
+                last_used.as_ref(),
+                ctx,
+            )
+            .into_iter()
+            .skip(offset)
+            .take(limit)
+            .collect::<Vec<_>>(),
+            total: streams.len(),
+        })
+        .unwrap(),
+        _ => JsValue::NULL,
+    }
+}
+
+/// The meta item of the preferred meta addon of the type, unless it failed
+fn preferred_meta_item<'a>(
+    meta_details: &'a MetaDetails,
+    type_addons: Option<&TypeAddons>,
+) -> Option<&'a ResourceLoadable<MetaItem>> {
+    type_addons
+        .and_then(|type_addons| type_addons.meta.as_ref())
+        .and_then(|transport_url| {
+            meta_details
+                .meta_items
+                .iter()
+                .find(|meta_item| meta_item.request.base == *transport_url)
+        })
+        .filter(|meta_item| !matches!(&meta_item.content, Some(Loadable::Err(_))))
+}
+
+fn first_meta_item(meta_details: &MetaDetails) -> Option<&ResourceLoadable<MetaItem>> {
+    meta_details
+        .meta_items
+        .iter()
+        .find(|meta_item| matches!(&meta_item.content, Some(Loadable::Ready(_))))
+        .or_else(|| {
+            if meta_details
+                .meta_items
+                .iter()
+                .all(|meta_item| matches!(&meta_item.content, Some(Loadable::Err(_))))
+            {
+                meta_details.meta_items.first()
+            } else {
+                meta_details
+                    .meta_items
+                    .iter()
+                    .find(|meta_item| matches!(&meta_item.content, Some(Loadable::Loading)))
+            }
+        })
+}
+
+fn serialize_video<'a>(
+    video: &'a Video,
+    meta_item: &'a MetaItem,
+    request: &ResourceRequest,
+    meta_details: &MetaDetails,
+    ctx: &Ctx,
+) -> model::Video<'a> {
+    model::Video {
+        video,
+        upcomming: meta_item.preview.behavior_hints.has_scheduled_videos
+            && meta_item
+                .preview
+                .released
+                .map(|released| released > WebEnv::now())
+                .unwrap_or(true),
+        watched: meta_details
+            .watched
+            .as_ref()
+            .map(|watched| watched.get_video(&video.id))
+            .unwrap_or_default(),
+        progress: ctx
+            .library
+            .items
+            .get(&meta_item.preview.id)
+            .filter(|library_item| Some(video.id.to_owned()) == library_item.state.video_id)
+            .map(|library_item| library_item.progress()),
+        scheduled: meta_item.preview.behavior_hints.has_scheduled_videos,
+        is_season_finale: is_season_finale(video, meta_item),
+        is_special: video
+            .series_info
+            .as_ref()
+            .map_or(false, |series_info| series_info.season == 0),
+        thumbnail_fallback: meta_item
+            .preview
+            .background
+            .as_ref()
+            .or(meta_item.preview.poster.as_ref())
+            .filter(|_| video.thumbnail.is_none()),
+        deep_links: VideoDeepLinks::from((video, request, &ctx.profile.settings))
+            .into_web_deep_links(),
+    }
+}
+
+/// The streams of an addon, the trusted sources first, otherwise in the order of the addon
+fn serialize_streams<'a>(
+    streams: &'a [Stream],
+    request: &ResourceRequest,
+    addon: &Descriptor,
+    meta_details: &MetaDetails,
+    meta_item: Option<&ResourceLoadable<MetaItem>>,
+    last_used: Option<&PlayedStream>,
+    ctx: &Ctx,
+) -> Vec<model::Stream<'a>> {
+    let selected_episode = selected_episode(meta_details, meta_item);
+    streams
+        .iter()
+        .map(|stream| model::Stream {
+            stream,
+            progress: meta_details.library_item.as_ref().and_then(|library_item| {
+                ctx.streams
+                    .items
+                    .values()
+                    .find(|item| item.stream == *stream)
+                    .map(|_| library_item.progress())
+            }),
+            deep_links: stream_deep_links(stream, request, meta_item, ctx),
+            web_url: web_playback::web_url(&debrid::resolve_stream(stream)),
+            trust: StreamTrust::new(stream, addon),
+            debrid_cached: debrid::is_cached(stream),
+            last_used: last_used.map_or(false, |last_used| {
+                last_used.is_stream(&request.base, stream)
+            }),
+            pack_streams: pack_streams(stream, request, meta_item, selected_episode, ctx),
+            youtube: None,
+        })
+        .sorted_by_key(|stream| stream.trust.level)
+        .collect()
+}
+
+fn installed_addon<'a>(transport_url: &Url, ctx: &'a Ctx) -> Option<&'a Descriptor> {
+    ctx.profile
+        .addons
+        .iter()
+        .find(|addon| addon.transport_url == *transport_url)
+}
+
+/// Episodes of a season which is still airing are not finales, even if they are the last one known
+fn is_season_finale(video: &Video, meta_item: &MetaItem) -> bool {
+    let series_info = match &video.series_info {
+        Some(series_info) if series_info.season > 0 => series_info,
+        _ => return false,
+    };
+    let mut episodes = meta_item
+        .videos
+        .iter()
+        .filter_map(|video| video.series_info.as_ref());
+    let is_last_episode = episodes
+        .clone()
+        .all(|other| other.season != series_info.season || other.episode <= series_info.episode);
+    let has_later_season = episodes.any(|other| other.season > series_info.season);
+    is_last_episode && (has_later_season || !meta_item.preview.behavior_hints.has_scheduled_videos)
+}
+
+/// The video left in the middle, otherwise the first released one which was not watched,
+/// the specials are left out. `None` once all of them were watched.
+/// While rewatching, the episodes played before the rewatch started don't count.
+fn play_target<'a>(
+    meta_details: &'a MetaDetails,
+    meta_item: Option<&'a ResourceLoadable<MetaItem>>,
+    rewatch: Option<&Rewatch>,
+    ctx: &Ctx,
+) -> Option<model::PlayTarget<'a>> {
+    let library_item = meta_details
+        .library_item
+        .as_ref()
+        .filter(|library_item| !library_item.removed)?;
+    let request = &meta_item?.request;
+    let ready_meta_item = meta_item?.content.as_ref()?.ready()?;
+    let movie_video = ready_meta_item
+        .videos
+        .is_empty()
+        .then(|| movie_video(ready_meta_item))
+        .flatten();
+    let videos = match &movie_video {
+        Some(movie_video) => std::slice::from_ref(movie_video),
+        None => ready_meta_item.videos.as_slice(),
+    };
+    let last_video = match rewatch {
+        Some(rewatch) => rewatch.last_video(library_item),
+        None => library_item.state.video_id.as_ref(),
+    };
+    let in_progress = last_video
+        .filter(|_| library_item.state.time_offset > 0)
+        .and_then(|video_id| videos.iter().find(|video| video.id == *video_id))
+        .is_some();
+    let now = WebEnv::now();
+    let mut episodes = videos
+        .iter()
+        .filter(|video| {
+            video
+                .series_info
+                .as_ref()
+                .map_or(true, |series_info| series_info.season > 0)
+                && video.released.map_or(true, |released| released <= now)
+        })
+        .sorted_by_key(|video| {
+            video
+                .series_info
+                .as_ref()
+                .map(|series_info| (series_info.season, series_info.episode))
+        });
+    let video = match (in_progress, rewatch, last_video) {
+        (true, _, Some(last_video)) => videos.iter().find(|video| video.id == *last_video)?,
+        // the rewatch goes on with the episode after the one played last
+        (_, Some(_), Some(last_video)) => episodes
+            .skip_while(|video| video.id != *last_video)
+            .nth(1)?,
+        (_, Some(_), None) => episodes.next()?,
+        (_, None, _) => episodes.find(|video| {
+            !meta_details
+                .watched
+                .as_ref()
+                .map_or(false, |watched| watched.get_video(&video.id))
+        })?,
+    };
+    // the selected video is borrowed from the meta item, unless it's the one of a movie
+    let video_id = video.id.to_owned();
+    let video = match movie_video {
+        Some(movie_video) => Cow::Owned(movie_video),
+        None => Cow::Borrowed(
+            ready_meta_item
+                .videos
+                .iter()
+                .find(|video| video.id == video_id)?,
+        ),
+    };
+    let stream_deep_links = ctx
+        .streams
+        .items
+        .get(&StreamsItemKey {
+            meta_id: library_item.id.to_owned(),
+            video_id: video.id.to_owned(),
+        })
+        .map(|streams_item| {
+            let stream_request = ResourceRequest::new(
+                streams_item.stream_transport_url.to_owned(),
+                ResourcePath::without_extra("stream", &library_item.r#type, &video.id),
+            );
+            stream_deep_links(&streams_item.stream, &stream_request, meta_item, ctx)
+        });
+    Some(model::PlayTarget {
+        in_progress,
+        progress: in_progress.then(|| library_item.progress()),
+        deep_links: VideoDeepLinks::from((video.as_ref(), request, &ctx.profile.settings))
+            .into_web_deep_links(),
+        stream_deep_links,
+        video,
+    })
+}
+
+/// The video of an item without videos, played by the id of its default video or of the item
+fn movie_video(meta_item: &MetaItem) -> Option<Video> {
+    serde_json::from_value(serde_json::json!({
+        "id": meta_item
+            .preview
+            .behavior_hints
+            .default_video_id
+            .as_ref()
+            .unwrap_or(&meta_item.preview.id),
+        "title": meta_item.preview.name,
+        "released": meta_item.preview.released,
+    }))
+    .ok()
+}
+
+/// `None` for the items without episodes
+fn series_progress(
+    meta_item: &MetaItem,
+    is_watched: impl Fn(&Video) -> bool,
+) -> Option<model::SeriesProgress> {
+    if meta_item.preview.r#type != "series" {
+        return None;
+    }
+    let now = WebEnv::now();
+    let episodes = meta_item
+        .videos
+        .iter()
+        .filter_map(|video| {
+            video
+                .series_info
+                .as_ref()
+                .map(|series_info| (series_info, video))
+        })
+        .sorted_by_key(|(series_info, _)| (series_info.season, series_info.episode))
+        .collect::<Vec<_>>();
+    if episodes.is_empty() {
+        return None;
+    }
+    let seasons = episodes
+        .iter()
+        .group_by(|(series_info, _)| series_info.season)
+        .into_iter()
+        .map(|(season, season_episodes)| {
+            let watched = season_episodes
+                .map(|(_, video)| if is_watched(*video) { '1' } else { '0' })
+                .collect::<String>();
+            model::SeasonProgress {
+                season,
+                watched_count: watched.matches('1').count(),
+                watched,
+            }
+        })
+        .collect::<Vec<_>>();
+    let (released, watched) = episodes
+        .iter()
+        .filter(|(series_info, video)| {
+            series_info.season > 0 && video.released.map_or(true, |released| released <= now)
+        })
+        .fold((0, 0), |(released, watched), (_, video)| {
+            (released + 1, watched + is_watched(*video) as usize)
+        });
+    Some(model::SeriesProgress {
+        seasons,
+        completion: if released > 0 {
+            watched as f64 / released as f64 * 100.0
+        } else {
+            0.0
+        },
+    })
+}
+
+fn stream_deep_links(
+    stream: &Stream,
+    request: &ResourceRequest,
+    meta_item: Option<&ResourceLoadable<MetaItem>>,
+    ctx: &Ctx,
+) -> StreamDeepLinks {
+    meta_item
+        .map_or_else(
+            || {
+                StreamDeepLinks::from((
+                    debrid::resolve_stream(stream).as_ref(),
+                    &ctx.profile.settings,
+                ))
+            },
+            |meta_item| {
+                StreamDeepLinks::from((
+                    debrid::resolve_stream(stream).as_ref(),
+                    request,
+                    &meta_item.request,
+                    &ctx.profile.settings,
+                ))
+            },
+        )
+        .into_web_deep_links()
+}
+
+/// Season and episode of the video the streams are listed for
+fn selected_episode(
+    meta_details: &MetaDetails,
+    meta_item: Option<&ResourceLoadable<MetaItem>>,
+) -> Option<(u32, u32)> {
+    let stream_path = meta_details.selected.as_ref()?.stream_path.as_ref()?;
+    let meta_item = meta_item?.content.as_ref()?.ready()?;
+    meta_item
+        .videos
+        .iter()
+        .find(|video| video.id == stream_path.id)
+        .and_then(|video| video.series_info.as_ref())
+        .map(|series_info| (series_info.season, series_info.episode))
+}
+
+fn pack_streams(
+    stream: &Stream,
+    request: &ResourceRequest,
+    meta_item: Option<&ResourceLoadable<MetaItem>>,
+    selected_episode: Option<(u32, u32)>,
+    ctx: &Ctx,
+) -> Option<Loadable<Vec<model::PackStream>, String>> {
+    let pack_streams = match season_packs::files(stream)? {
+        Loadable::Ready(files) => Loadable::Ready(
+            files
+                .into_iter()
+                .map(|file| {
+                    let pack_stream = season_packs::file_stream(stream, &file);
+                    model::PackStream {
+                        deep_links: stream_deep_links(&pack_stream, request, meta_item, ctx),
+                        stream: pack_stream,
+                        is_selected_episode: selected_episode.is_some()
+                            && file.season.zip(file.episode) == selected_episode,
+                        file,
+                    }
+                })
+                .collect(),
+        ),
+        Loadable::Loading => Loadable::Loading,
+        Loadable::Err(error) => Loadable::Err(error),
+    };
+    Some(pack_streams)
+}
+
+fn auto_selected_stream(
+    last_used: &PlayedStream,
+    streams: &[ResourceLoadable<Vec<Stream>>],
+    meta_item: Option<&ResourceLoadable<MetaItem>>,
+    ctx: &Ctx,
+) -> Option<StreamDeepLinks> {
+    streams
+        .iter()
+        .filter(|streams| streams.request.base == last_used.transport_url)
+        .find_map(|streams| match &streams.content {
+            Some(Loadable::Ready(content)) => content
+                .iter()
+                .find(|stream| last_used.is_stream(&streams.request.base, stream))
+                .map(|stream| stream_deep_links(stream, &streams.request, meta_item, ctx)),
+            _ => None,
+        })
+}
+
+fn streams_diagnosis<'a>(
+    meta_details: &'a MetaDetails,
+    streams: &'a [ResourceLoadable<Vec<Stream>>],
+    ctx: &'a Ctx,
+) -> Option<model::StreamsDiagnosis<'a>> {
+    let stream_path = meta_details
+        .selected
+        .as_ref()
+        .and_then(|selected| selected.stream_path.as_ref())?;
+    let errors = streams
+        .iter()
+        .map(|streams| match &streams.content {
+            Some(Loadable::Err(error)) => Some((streams, error)),
+            _ => None,
+        })
+        .collect::<Option<Vec<_>>>()?;
+    let addons = errors
+        .into_iter()
+        .filter_map(|(streams, error)| {
+            ctx.profile
+                .addons
+                .iter()
+                .find(|addon| addon.transport_url == streams.request.base)
+                .map(|addon| model::DiagnosedAddon {
+                    addon: descriptor_preview(addon),
+                    outcome: match error {
+                        ResourceError::EmptyContent => model::StreamsOutcome::Empty,
+                        ResourceError::Env(EnvError::Fetch(_)) => {
+                            model::StreamsOutcome::Unreachable
+                        }
+                        _ => model::StreamsOutcome::Failed,
+                    },
+                    error,
+                })
+        })
+        .collect::<Vec<_>>();
+    let reason = if addons.is_empty() {
+        model::StreamsDiagnosisReason::NoSupportingAddons
+    } else if addons
+        .iter()
+        .any(|addon| matches!(addon.outcome, model::StreamsOutcome::Empty))
+    {
+        model::StreamsDiagnosisReason::NoResults
+    } else if addons
+        .iter()
+        .all(|addon| matches!(addon.outcome, model::StreamsOutcome::Unreachable))
+    {
+        model::StreamsDiagnosisReason::AddonsUnreachable
+    } else {
+        model::StreamsDiagnosisReason::AddonsFailed
+    };
+    Some(model::StreamsDiagnosis {
+        reason,
+        r#type: &stream_path.r#type,
+        id: &stream_path.id,
+        addons,
+    })
+}
+
+fn descriptor_preview(addon: &Descriptor) -> model::DescriptorPreview<'_> {
+    model::DescriptorPreview {
+        transport_url: &addon.transport_url,
+        manifest: model::ManifestPreview {
+            id: &addon.manifest.id,
+            name: &addon.manifest.name,
+            logo: &addon.manifest.logo,
+            behavior_hints: &addon.manifest.behavior_hints,
+        },
+    }
+}
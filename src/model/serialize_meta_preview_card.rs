@@ -0,0 +1,188 @@
+use std::borrow::Cow;
+
+use serde::Serialize;
+use wasm_bindgen::JsValue;
+
+use stremio_core::deep_links::{LibraryItemDeepLinks, MetaItemDeepLinks, StreamDeepLinks};
+use stremio_core::models::common::Loadable;
+use stremio_core::models::ctx::Ctx;
+use stremio_core::types::addon::{ResourcePath, ResourceRequest};
+use stremio_core::types::resource::MetaItemPreview;
+
+use crate::model::deep_links_ext::DeepLinksExt;
+use crate::model::{WebModel, WebModelField};
+use crate::{meta_overrides, prefetch, web_settings};
+
+/// Characters of the description shown on the card, it's cut at the last word which fits
+const MAX_DESCRIPTION_LENGTH: usize = 200;
+
+#[derive(Serialize)]
+#[serde(rename_all = "camelCase")]
+pub enum CardSource {
+    MetaDetails,
+    Prefetched,
+    Catalog,
+}
+
+mod model {
+    use super::*;
+    #[derive(Serialize)]
+    #[serde(rename_all = "camelCase")]
+    pub struct QuickActions {
+        /// Opens the details of the meta item
+        pub details: MetaItemDeepLinks,
+        /// Resumes the playback, `None` unless the item is in the library
+        pub resume: Option<LibraryItemDeepLinks>,
+        pub trailer: Option<StreamDeepLinks>,
+    }
+    #[derive(Serialize)]
+    #[serde(rename_all = "camelCase")]
+    pub struct MetaPreviewCard<'a> {
+        pub id: &'a String,
+        pub r#type: &'a String,
+        pub name: Cow<'a, String>,
+        pub description: Option<String>,
+        pub genres: Vec<&'a String>,
+        pub runtime: Option<&'a String>,
+        pub release_info: Option<&'a String>,
+        /// The IMDb rating, e.g. `7.8`
+        pub rating: Option<&'a String>,
+        pub in_library: bool,
+        pub source: CardSource,
+        pub actions: QuickActions,
+    }
+}
+
+/// Hover card of a meta item from what is already loaded, `null` when nothing is.
+/// The meta item is prefetched meanwhile and the Board is updated once it's loaded.
+pub fn serialize_meta_preview_card(id: &str, r#type: &str, model: &WebModel) -> JsValue {
+    let meta_details = model
+        .meta_details
+        .meta_items
+        .iter()
+        .find_map(|meta_item| match &meta_item.content {
+            Some(Loadable::Ready(content)) if content.preview.id == id => Some((
+                &meta_item.request,
+                &content.preview,
+                CardSource::MetaDetails,
+            )),
+            _ => None,
+        });
+    let prefetched = prefetch::meta_item(r#type, id);
+    let found = meta_details
+        .or_else(|| {
+            prefetched
+                .as_ref()
+                .map(|(request, meta_item)| (request, &meta_item.preview, CardSource::Prefetched))
+        })
+        .or_else(|| {
+            model
+                .board
+                .catalogs
+                .iter()
+                .flatten()
+                .chain(model.search.catalogs.iter().flatten())
+                .chain(model.discover.catalog.iter())
+                .find_map(|page| {
+                    page.content
+                        .as_ref()
+                        .and_then(|content| content.ready())
+                        .and_then(|meta_items| {
+                            meta_items.iter().find(|meta_item| meta_item.id == id)
+                        })
+                        .map(|meta_item| (&page.request, meta_item, CardSource::Catalog))
+                })
+        });
+    let preview_card = match found {
+        Some((request, meta_item, source)) => Some(card(request, meta_item, source, &model.ctx)),
+        None => {
+            load_meta_item(id, r#type, model);
+            None
+        }
+    };
+    JsValue::from_serde(&preview_card).unwrap()
+}
+
+fn card<'a>(
+    request: &ResourceRequest,
+    meta_item: &'a MetaItemPreview,
+    source: CardSource,
+    ctx: &Ctx,
+) -> model::MetaPreviewCard<'a> {
+    let settings = &ctx.profile.settings;
+    let library_item = ctx
+        .library
+        .items
+        .get(&meta_item.id)
+        .filter(|library_item| !library_item.removed);
+    let name = match meta_overrides::meta_item_preview(meta_item) {
+        Cow::Owned(meta_item) => Cow::Owned(meta_item.name),
+        Cow::Borrowed(meta_item) => Cow::Borrowed(&meta_item.name),
+    };
+    model::MetaPreviewCard {
+        id: &meta_item.id,
+        r#type: &meta_item.r#type,
+        name,
+        description: meta_item.description.as_deref().map(short_description),
+        genres: meta_item
+            .links
+            .iter()
+            .filter(|link| link.category == "Genres")
+            .map(|link| &link.name)
+            .collect(),
+        runtime: meta_item.runtime.as_ref(),
+        release_info: meta_item.release_info.as_ref(),
+        rating: meta_item
+            .links
+            .iter()
+            .find(|link| link.category == "imdb")
+            .map(|link| &link.name),
+        in_library: library_item.is_some(),
+        source,
+        actions: model::QuickActions {
+            details: MetaItemDeepLinks::from((meta_item, request)).into_web_deep_links(),
+            resume: library_item.map(|library_item| {
+                LibraryItemDeepLinks::from((library_item, None, settings)).into_web_deep_links()
+            }),
+            trailer: meta_item
+                .trailer_streams
+                .first()
+                .map(|stream| StreamDeepLinks::from((stream, settings)).into_web_deep_links()),
+        },
+    }
+}
+
+fn short_description(description: &str) -> String {
+    if description.chars().count() <= MAX_DESCRIPTION_LENGTH {
+        return description.to_owned();
+    }
+    let cut = description
+        .char_indices()
+        .nth(MAX_DESCRIPTION_LENGTH)
+        .map_or(description.len(), |(index, _)| index);
+    let short = description[..cut]
+        .rsplit_once(char::is_whitespace)
+        .map_or(&description[..cut], |(short, _)| short);
+    format!(
+        "{}…",
+        short.trim_end_matches(|c: char| c.is_ascii_punctuation())
+    )
+}
+
+/// Prefetches the meta item from the first addon which provides it, unless the data saver is on
+fn load_meta_item(id: &str, r#type: &str, model: &WebModel) {
+    if web_settings::web_settings().network.data_saver {
+        return;
+    }
+    let path = ResourcePath::without_extra("meta", r#type, id);
+    if let Some(addon) = model
+        .ctx
+        .profile
+        .addons
+        .iter()
+        .find(|addon| addon.manifest.is_resource_supported(&path))
+    {
+        let request = ResourceRequest::new(addon.transport_url.to_owned(), path);
+        prefetch::prefetch(&request, Some(WebModelField::Board));
+    }
+}
@@ -1,24 +1,43 @@
+use crate::debrid;
+use crate::device_profile::{self, DeviceProfile};
 use crate::env::WebEnv;
+use crate::ipfs;
 use crate::model::deep_links_ext::DeepLinksExt;
+use crate::model::lite_mode::{self, Slice, Truncated};
+use crate::player_source::{self, PlayerSource};
+use crate::still_watching::{self, StillWatchingPrompt};
+use crate::subtitles_sync::{self, SubtitlesOffset};
+use crate::subtitles_translation::{self, SubtitlesTranslation, TRANSLATED_SUBTITLES_ORIGIN};
+use crate::uploaded_subtitles::{self, UPLOADED_SUBTITLES_ORIGIN};
+use crate::watch_party::{self, WatchParty};
 use semver::Version;
 use serde::Serialize;
+use std::borrow::Cow;
 use stremio_core::deep_links::{StreamDeepLinks, VideoDeepLinks};
 use stremio_core::models::common::{Loadable, ResourceError, ResourceLoadable};
 use stremio_core::models::ctx::Ctx;
 use stremio_core::models::player::Player;
 use stremio_core::models::streaming_server::StreamingServer;
 use stremio_core::runtime::Env;
-use stremio_core::types::addon::{ResourcePath, ResourceRequest};
+use stremio_core::types::addon::{ManifestBehaviorHints, ResourcePath, ResourceRequest};
+use stremio_core::types::resource::{MetaItem, StreamSource, Subtitles, Video};
 use url::Url;
 use wasm_bindgen::JsValue;
 
+/// Meta types of the music and podcast addons
+const AUDIO_TYPES: [&str; 3] = ["music", "podcast", "radio"];
+const AUDIO_EXTENSIONS: [&str; 7] = ["mp3", "m4a", "aac", "ogg", "oga", "flac", "opus"];
+
 mod model {
     use super::*;
     #[derive(Serialize)]
     #[serde(rename_all = "camelCase")]
     pub struct Stream<'a> {
         #[serde(flatten)]
-        pub stream: &'a stremio_core::types::resource::Stream,
+        pub stream: Cow<'a, stremio_core::types::resource::Stream>,
+        /// Alternative gateway urls for streams served from IPFS
+        #[serde(skip_serializing_if = "Vec::is_empty")]
+        pub fallback_urls: Vec<Url>,
         pub deep_links: StreamDeepLinks,
     }
     #[derive(Serialize)]
@@ -31,6 +50,7 @@ mod model {
         pub logo: &'a Option<Url>,
         pub background: &'a Option<Url>,
         pub types: &'a Vec<String>,
+        pub behavior_hints: &'a ManifestBehaviorHints,
     }
     #[derive(Serialize)]
     #[serde(rename_all = "camelCase")]
@@ -53,16 +73,20 @@ mod model {
     #[serde(rename_all = "camelCase")]
     pub struct MetaItem<'a> {
         #[serde(flatten)]
-        pub meta_item: &'a stremio_core::types::resource::MetaItem,
+        pub meta_item: &'a stremio_core::types::resource::MetaItemPreview,
         pub videos: Vec<Video<'a>>,
+        #[serde(skip_serializing_if = "Option::is_none")]
+        pub videos_truncated: Option<Truncated>,
     }
     #[derive(Serialize)]
     #[serde(rename_all = "camelCase")]
     pub struct Subtitles<'a> {
         #[serde(flatten)]
-        pub subtitles: &'a stremio_core::types::resource::Subtitles,
+        pub subtitles: Cow<'a, stremio_core::types::resource::Subtitles>,
         pub id: String,
-        pub origin: &'a String,
+        /// Name of the addon, `Uploaded` for the tracks uploaded by the user
+        /// or `Translated` for the translations of the other tracks
+        pub origin: &'a str,
     }
     #[derive(Serialize)]
     #[serde(rename_all = "camelCase")]
@@ -82,13 +106,32 @@ mod model {
     #[serde(rename_all = "camelCase")]
     pub struct Selected<'a> {
         pub stream: Stream<'a>,
+        /// The url the player loads the url stream from, through the streaming server
+        /// when it's needed
+        pub source: Option<PlayerSource>,
         pub stream_request: &'a Option<ResourceRequest>,
         pub meta_request: &'a Option<ResourceRequest>,
         pub subtitles_path: &'a Option<ResourcePath>,
     }
+    #[derive(Clone, Copy, PartialEq, Eq, Serialize)]
+    #[serde(rename_all = "camelCase")]
+    pub enum PlayerMode {
+        Video,
+        Audio,
+    }
+    /// Metadata and handlers for the Media Session API, to control the playback in the background
+    #[derive(Serialize)]
+    #[serde(rename_all = "camelCase")]
+    pub struct MediaSession<'a> {
+        pub title: Option<&'a String>,
+        pub artist: Option<&'a String>,
+        pub artwork: Option<&'a Url>,
+        pub actions: Vec<&'static str>,
+    }
     #[derive(Serialize)]
     #[serde(rename_all = "camelCase")]
     pub struct Player<'a> {
+        pub mode: PlayerMode,
         pub selected: Option<Selected<'a>>,
         pub meta_item: Option<Loadable<model::MetaItem<'a>, &'a ResourceError>>,
         pub subtitles: Vec<model::Subtitles<'a>>,
@@ -97,23 +140,61 @@ mod model {
         pub library_item: Option<LibraryItem<'a>>,
         pub title: Option<String>,
         pub addon: Option<model::DescriptorPreview<'a>>,
+        /// Sent to the streaming server when the stream is created, explains its transcode decisions
+        pub device_profile: Option<DeviceProfile>,
+        /// Presence of the watch party members, explains why the playback of the party halted
+        #[serde(skip_serializing_if = "Option::is_none")]
+        pub watch_party: Option<WatchParty>,
+        /// Shown after a few episodes were autoplayed without the user touching the player
+        pub still_watching_prompt: Option<StillWatchingPrompt>,
+        /// Delay of the subtitles of the stream, `None` until it is set or estimated
+        pub subtitles_offset: Option<SubtitlesOffset>,
+        /// Translations of the tracks of the video, the ready ones are in the subtitles as well
+        pub subtitles_translations: Vec<SubtitlesTranslation>,
+    }
+    /// The player of audio-only streams, without the video-specific fields.
+    /// The progress is still tracked through the library item, same as for videos.
+    #[derive(Serialize)]
+    #[serde(rename_all = "camelCase")]
+    pub struct AudioPlayer<'a> {
+        pub mode: PlayerMode,
+        pub selected: Option<Selected<'a>>,
+        pub meta_item: Option<Loadable<model::MetaItem<'a>, &'a ResourceError>>,
+        pub next_video: Option<Video<'a>>,
+        pub library_item: Option<LibraryItem<'a>>,
+        pub title: Option<String>,
+        pub addon: Option<model::DescriptorPreview<'a>>,
+        pub media_session: MediaSession<'a>,
+        #[serde(skip_serializing_if = "Option::is_none")]
+        pub watch_party: Option<WatchParty>,
+        pub still_watching_prompt: Option<StillWatchingPrompt>,
     }
 }
 
 pub fn serialize_player(player: &Player, ctx: &Ctx, streaming_server: &StreamingServer) -> JsValue {
-    JsValue::from_serde(&model::Player {
-        selected: player.selected.as_ref().map(|selected| model::Selected {
-            stream: model::Stream {
-                stream: &selected.stream,
-                deep_links: StreamDeepLinks::from((
-                    &selected.stream,
-                    &ctx.profile.settings,
-                ))
-                .into_web_deep_links(),
-            },
-            stream_request: &selected.stream_request,
-            meta_request: &selected.meta_request,
-            subtitles_path: &selected.subtitles_path,
+    let mode = player_mode(player);
+    let subtitles_translations = player
+        .selected
+        .as_ref()
+        .and_then(|selected| selected.stream_request.as_ref())
+        .map(|stream_request| subtitles_translation::translations(&stream_request.path.id))
+        .unwrap_or_default();
+    let player_state = model::Player {
+        mode,
+        selected: player.selected.as_ref().map(|selected| {
+            let stream = debrid::resolve_stream(&selected.stream);
+            model::Selected {
+                source: player_source::source(&stream),
+                stream: model::Stream {
+                    fallback_urls: ipfs::fallback_urls(&stream),
+                    deep_links: StreamDeepLinks::from((stream.as_ref(), &ctx.profile.settings))
+                        .into_web_deep_links(),
+                    stream,
+                },
+                stream_request: &selected.stream_request,
+                meta_request: &selected.meta_request,
+                subtitles_path: &selected.subtitles_path,
+            }
         }),
         meta_item: player
             .meta_item
@@ -121,33 +202,16 @@ pub fn serialize_player(player: &Player, ctx: &Ctx, streaming_server: &Streaming
             .map(|ResourceLoadable { request, content }| match &content {
                 Some(Loadable::Loading) | None => Loadable::Loading,
                 Some(Loadable::Err(error)) => Loadable::Err(error),
-                Some(Loadable::Ready(meta_item)) => {
-                    Loadable::Ready(model::MetaItem {
-                        meta_item,
-                        videos: meta_item
-                            .videos
-                            .iter()
-                            .map(|video| model::Video {
-                                video,
-                                upcomming: meta_item.preview.behavior_hints.has_scheduled_videos
-                                    && meta_item
-                                        .preview
-                                        .released
-                                        .map(|released| released > WebEnv::now())
-                                        .unwrap_or(true),
-                                watched: false, // TODO use library
-                                progress: None, // TODO use library,
-                                scheduled: meta_item.preview.behavior_hints.has_scheduled_videos,
-                                deep_links: VideoDeepLinks::from((
-                                    video,
-                                    request,
-                                    &ctx.profile.settings,
-                                ))
-                                .into_web_deep_links(),
-                            })
-                            .collect(),
-                    })
-                }
+                Some(Loadable::Ready(meta_item)) => Loadable::Ready(model::MetaItem {
+                    meta_item: &meta_item.preview,
+                    videos: meta_item
+                        .videos
+                        .iter()
+                        .take(lite_mode::max_items())
+                        .map(|video| serialize_video(video, meta_item, request, ctx))
+                        .collect(),
+                    videos_truncated: lite_mode::truncated(meta_item.videos.len()),
+                }),
             }),
         subtitles: player
             .subtitles
@@ -171,11 +235,45 @@ pub fn serialize_player(player: &Player, ctx: &Ctx, streaming_server: &Streaming
                     .iter()
                     .enumerate()
                     .map(move |(position, subtitles)| model::Subtitles {
-                        subtitles,
+                        subtitles: Cow::Borrowed(subtitles),
                         id: format!("{}_{}", addon.transport_url, position),
                         origin: &addon.manifest.name,
                     })
             })
+            .chain(
+                player
+                    .selected
+                    .as_ref()
+                    .and_then(|selected| selected.stream_request.as_ref())
+                    .map(|stream_request| uploaded_subtitles::uploaded(&stream_request.path.id))
+                    .unwrap_or_default()
+                    .into_iter()
+                    .filter_map(|uploaded| {
+                        Some(model::Subtitles {
+                            subtitles: Cow::Owned(Subtitles {
+                                url: uploaded.url().ok()?,
+                                lang: uploaded.lang,
+                            }),
+                            id: uploaded.id,
+                            origin: UPLOADED_SUBTITLES_ORIGIN,
+                        })
+                    }),
+            )
+            .chain(
+                subtitles_translations
+                    .iter()
+                    .filter_map(|translation| match &translation.url {
+                        Loadable::Ready(url) => Some(model::Subtitles {
+                            subtitles: Cow::Owned(Subtitles {
+                                lang: translation.lang.to_owned(),
+                                url: url.to_owned(),
+                            }),
+                            id: translation.id.to_owned(),
+                            origin: TRANSLATED_SUBTITLES_ORIGIN,
+                        }),
+                        _ => None,
+                    }),
+            )
             .collect(),
         next_video: player
             .selected
@@ -291,8 +389,133 @@ pub fn serialize_player(player: &Player, ctx: &Ctx, streaming_server: &Streaming
                     logo: &addon.manifest.logo,
                     background: &addon.manifest.background,
                     types: &addon.manifest.types,
+                    behavior_hints: &addon.manifest.behavior_hints,
                 },
             }),
-    })
-    .unwrap()
+        device_profile: device_profile::device_profile(),
+        watch_party: watch_party::watch_party(),
+        still_watching_prompt: still_watching::still_watching_prompt(),
+        subtitles_offset: player
+            .selected
+            .as_ref()
+            .and_then(|selected| subtitles_sync::offset(&selected.stream)),
+        subtitles_translations: subtitles_translations.to_owned(),
+    };
+    match mode {
+        model::PlayerMode::Video => JsValue::from_serde(&player_state).unwrap(),
+        model::PlayerMode::Audio => {
+            let meta_item = match &player_state.meta_item {
+                Some(Loadable::Ready(meta_item)) => Some(meta_item.meta_item),
+                _ => None,
+            };
+            let media_session = model::MediaSession {
+                title: player_state
+                    .title
+                    .as_ref()
+                    .or_else(|| meta_item.map(|meta_item| &meta_item.preview.name)),
+                artist: meta_item.map(|meta_item| &meta_item.preview.name),
+                artwork: meta_item.and_then(|meta_item| meta_item.preview.poster.as_ref()),
+                actions: media_session_actions(player_state.next_video.is_some()),
+            };
+            JsValue::from_serde(&model::AudioPlayer {
+                mode,
+                selected: player_state.selected,
+                meta_item: player_state.meta_item,
+                next_video: player_state.next_video,
+                library_item: player_state.library_item,
+                title: player_state.title,
+                addon: player_state.addon,
+                media_session,
+                watch_party: player_state.watch_party,
+                still_watching_prompt: player_state.still_watching_prompt,
+            })
+            .unwrap()
+        }
+    }
+}
+
+/// Streams of the music and podcast addons and streams of audio files are played without video
+/// Videos of the meta item of the Player past the ones serialized with the state in the lite mode,
+/// `null` until the meta item is loaded
+pub fn serialize_player_videos(player: &Player, ctx: &Ctx, offset: usize, limit: usize) -> JsValue {
+    match &player.meta_item {
+        Some(ResourceLoadable {
+            request,
+            content: Some(Loadable::Ready(meta_item)),
+        }) => JsValue::from_serde(&Slice {
+            items: meta_item
+                .videos
+                .iter()
+                .skip(offset)
+                .take(limit)
+                .map(|video| serialize_video(video, meta_item, request, ctx))
+                .collect::<Vec<_>>(),
+            total: meta_item.videos.len(),
+        })
+        .unwrap(),
+        _ => JsValue::NULL,
+    }
+}
+
+fn serialize_video<'a>(
+    video: &'a Video,
+    meta_item: &MetaItem,
+    request: &ResourceRequest,
+    ctx: &Ctx,
+) -> model::Video<'a> {
+    model::Video {
+        video,
+        upcomming: meta_item.preview.behavior_hints.has_scheduled_videos
+            && meta_item
+                .preview
+                .released
+                .map(|released| released > WebEnv::now())
+                .unwrap_or(true),
+        watched: false, // TODO use library
+        progress: None, // TODO use library,
+        scheduled: meta_item.preview.behavior_hints.has_scheduled_videos,
+        deep_links: VideoDeepLinks::from((video, request, &ctx.profile.settings))
+            .into_web_deep_links(),
+    }
+}
+
+fn player_mode(player: &Player) -> model::PlayerMode {
+    let selected = match &player.selected {
+        Some(selected) => selected,
+        None => return model::PlayerMode::Video,
+    };
+    let is_audio_type = selected
+        .meta_request
+        .as_ref()
+        .or(selected.stream_request.as_ref())
+        .map(|request| AUDIO_TYPES.contains(&request.path.r#type.as_str()))
+        .unwrap_or_default();
+    let is_audio_file = match &selected.stream.source {
+        StreamSource::Url { url } => url
+            .path()
+            .rsplit_once('.')
+            .map(|(_, extension)| AUDIO_EXTENSIONS.contains(&extension.to_lowercase().as_str()))
+            .unwrap_or_default(),
+        _ => false,
+    };
+    if is_audio_type || is_audio_file {
+        model::PlayerMode::Audio
+    } else {
+        model::PlayerMode::Video
+    }
+}
+
+fn media_session_actions(has_next_video: bool) -> Vec<&'static str> {
+    let mut actions = vec![
+        "play",
+        "pause",
+        "stop",
+        "seekbackward",
+        "seekforward",
+        "seekto",
+    ];
+    if has_next_video {
+        actions.push("nexttrack");
+    }
+    actions
 }
@@ -0,0 +1,171 @@
+use serde::{Deserialize, Serialize};
+use url::Url;
+use wasm_bindgen::JsValue;
+
+use stremio_core::deep_links::{MetaItemDeepLinks, StreamDeepLinks, VideoDeepLinks};
+use stremio_core::models::common::{Loadable, ResourceLoadable};
+use stremio_core::models::ctx::Ctx;
+use stremio_core::models::meta_details::MetaDetails;
+use stremio_core::models::player::Player;
+use stremio_core::types::addon::ResourceRequest;
+use stremio_core::types::resource::{MetaItem, Video};
+
+/// Base of the shared urls, the web deep links are appended to it
+const SHARE_BASE_URL: &str = "https://web.stremio.com/";
+
+#[derive(Deserialize, Debug)]
+#[serde(rename_all = "camelCase")]
+pub enum ShareSource {
+    MetaDetails,
+    Player,
+}
+
+#[derive(Deserialize, Debug)]
+#[serde(rename_all = "camelCase")]
+pub struct ShareArgs {
+    pub source: ShareSource,
+    /// Seconds to start the playback at, the watch progress is used for the player by default
+    #[serde(default)]
+    pub time_offset: Option<u64>,
+}
+
+mod model {
+    use super::*;
+    /// In the shape of the Web Share API data, with the poster for the platforms which preview it
+    #[derive(Serialize)]
+    #[serde(rename_all = "camelCase")]
+    pub struct SharePayload<'a> {
+        pub url: String,
+        pub title: String,
+        pub text: Option<&'a String>,
+        pub poster: Option<&'a Url>,
+        pub time_offset: Option<u64>,
+    }
+}
+
+/// `null` when there is nothing loaded to share
+pub fn serialize_share_payload(
+    meta_details: &MetaDetails,
+    player: &Player,
+    ctx: &Ctx,
+    args: &ShareArgs,
+) -> JsValue {
+    let payload = match args.source {
+        ShareSource::MetaDetails => meta_details
+            .meta_items
+            .iter()
+            .find_map(ready_meta_item)
+            .map(|(request, meta_item)| {
+                let video = meta_details
+                    .selected
+                    .as_ref()
+                    .and_then(|selected| selected.stream_path.as_ref())
+                    .and_then(|stream_path| {
+                        meta_item
+                            .videos
+                            .iter()
+                            .find(|video| video.id == stream_path.id)
+                    });
+                meta_item_payload(request, meta_item, video, ctx, args.time_offset)
+            }),
+        ShareSource::Player => player.selected.as_ref().and_then(|selected| {
+            let time_offset = args.time_offset.or_else(|| {
+                player
+                    .library_item
+                    .as_ref()
+                    .map(|library_item| library_item.state.time_offset / 1000)
+                    .filter(|time_offset| *time_offset > 0)
+            });
+            match player.meta_item.as_ref().and_then(ready_meta_item) {
+                Some((request, meta_item)) => {
+                    let video = selected.stream_request.as_ref().and_then(|stream_request| {
+                        meta_item
+                            .videos
+                            .iter()
+                            .find(|video| video.id == stream_request.path.id)
+                    });
+                    Some(meta_item_payload(
+                        request,
+                        meta_item,
+                        video,
+                        ctx,
+                        time_offset,
+                    ))
+                }
+                // streams played without a meta item, e.g. opened from a link
+                None => selected
+                    .stream
+                    .name
+                    .as_ref()
+                    .map(|name| model::SharePayload {
+                        url: share_url(
+                            &StreamDeepLinks::from((&selected.stream, &ctx.profile.settings))
+                                .player,
+                            time_offset,
+                        ),
+                        title: name.to_owned(),
+                        text: selected.stream.description.as_ref(),
+                        poster: None,
+                        time_offset,
+                    }),
+            }
+        }),
+    };
+    JsValue::from_serde(&payload).unwrap()
+}
+
+fn ready_meta_item(
+    meta_item: &ResourceLoadable<MetaItem>,
+) -> Option<(&ResourceRequest, &MetaItem)> {
+    match &meta_item.content {
+        Some(Loadable::Ready(content)) => Some((&meta_item.request, content)),
+        _ => None,
+    }
+}
+
+fn meta_item_payload<'a>(
+    request: &ResourceRequest,
+    meta_item: &'a MetaItem,
+    video: Option<&'a Video>,
+    ctx: &Ctx,
+    time_offset: Option<u64>,
+) -> model::SharePayload<'a> {
+    let deep_link = match video {
+        Some(video) => {
+            Some(VideoDeepLinks::from((video, request, &ctx.profile.settings)).meta_details_streams)
+        }
+        None => {
+            let deep_links = MetaItemDeepLinks::from((meta_item, request));
+            deep_links
+                .meta_details_videos
+                .or(deep_links.meta_details_streams)
+        }
+    };
+    let title = match video {
+        Some(video) if !video.title.is_empty() => {
+            format!("{} - {}", meta_item.preview.name, video.title)
+        }
+        _ => meta_item.preview.name.to_owned(),
+    };
+    model::SharePayload {
+        url: deep_link
+            .map(|deep_link| share_url(&deep_link, time_offset))
+            .unwrap_or_else(|| SHARE_BASE_URL.to_owned()),
+        title,
+        text: meta_item.preview.description.as_ref(),
+        poster: meta_item.preview.poster.as_ref(),
+        time_offset,
+    }
+}
+
+/// The web url of the deep link, in the same format as the web deep links of the models
+fn share_url(deep_link: &str, time_offset: Option<u64>) -> String {
+    let url = format!("{SHARE_BASE_URL}{}", deep_link.replace("stremio://", "#"));
+    match time_offset {
+        Some(time_offset) => {
+            let separator = if url.contains('?') { '&' } else { '?' };
+            format!("{url}{separator}t={time_offset}")
+        }
+        None => url,
+    }
+}
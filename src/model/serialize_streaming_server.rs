@@ -1,4 +1,7 @@
 use crate::model::deep_links_ext::DeepLinksExt;
+use crate::model::loadable_states;
+use crate::streaming_server_cache::{CacheClearing, CacheSize, StreamingServerCache};
+use crate::streaming_server_jobs::{StreamingServerJobs, TorrentJob};
 use serde::Serialize;
 use stremio_core::deep_links::MetaItemDeepLinks;
 use stremio_core::models::common::Loadable;
@@ -21,10 +24,26 @@ mod model {
         pub playback_devices: &'a Loadable<Vec<PlaybackDevice>, EnvError>,
         pub torrent: Option<(&'a String, TorrentLoadable<'a>)>,
         pub statistics: Option<&'a Loadable<Statistics, EnvError>>,
+        #[serde(serialize_with = "loadable_states::not_asked")]
+        pub jobs: Option<Loadable<Vec<Job<'a>>, &'a String>>,
+        #[serde(serialize_with = "loadable_states::not_asked")]
+        pub cache_size: Option<&'a Loadable<CacheSize, String>>,
+        pub cache_clearing: Option<&'a CacheClearing>,
+    }
+    #[derive(Serialize)]
+    #[serde(rename_all = "camelCase")]
+    pub struct Job<'a> {
+        #[serde(flatten)]
+        pub job: &'a TorrentJob,
+        pub removing: bool,
     }
 }
 
-pub fn serialize_streaming_server(streaming_server: &StreamingServer) -> JsValue {
+pub fn serialize_streaming_server(
+    streaming_server: &StreamingServer,
+    jobs: &StreamingServerJobs,
+    cache: &StreamingServerCache,
+) -> JsValue {
     JsValue::from_serde(&model::StreamingServer {
         selected: &streaming_server.selected,
         settings: &streaming_server.settings,
@@ -45,6 +64,21 @@ pub fn serialize_streaming_server(streaming_server: &StreamingServer) -> JsValue
                 (info_hash, loadable)
             }),
         statistics: streaming_server.statistics.as_ref(),
+        jobs: jobs.jobs.as_ref().map(|loadable| match loadable {
+            Loadable::Ready(torrent_jobs) => Loadable::Ready(
+                torrent_jobs
+                    .iter()
+                    .map(|job| model::Job {
+                        job,
+                        removing: jobs.removing.contains(&job.info_hash),
+                    })
+                    .collect(),
+            ),
+            Loadable::Loading => Loadable::Loading,
+            Loadable::Err(error) => Loadable::Err(error),
+        }),
+        cache_size: cache.size.as_ref(),
+        cache_clearing: cache.clearing.as_ref(),
     })
     .unwrap()
 }
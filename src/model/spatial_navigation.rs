@@ -0,0 +1,60 @@
+use std::sync::RwLock;
+
+use lazy_static::lazy_static;
+use serde::{Deserialize, Serialize};
+
+lazy_static! {
+    /// Set by the TV builds, the hints are not serialized otherwise
+    static ref OPTIONS: RwLock<Option<SpatialNavigationOptions>> = Default::default();
+}
+
+#[derive(Clone, Copy, PartialEq, Eq, Deserialize, Debug)]
+#[serde(rename_all = "camelCase")]
+pub struct SpatialNavigationOptions {
+    /// Columns of the grids, e.g. of discover and of the library,
+    /// unless the catalog declares its items per row
+    pub grid_columns: usize,
+}
+
+/// Position of an item for the D-pad navigation, the focus key identifies it between updates
+#[derive(Clone, PartialEq, Eq, Serialize, Debug)]
+#[serde(rename_all = "camelCase")]
+pub struct NavigationHint {
+    pub row: usize,
+    pub column: usize,
+    pub focus_key: String,
+}
+
+pub fn set_options(options: Option<SpatialNavigationOptions>) {
+    *OPTIONS.write().expect("spatial navigation write failed") = options;
+}
+
+fn options() -> Option<SpatialNavigationOptions> {
+    *OPTIONS.read().expect("spatial navigation read failed")
+}
+
+/// Hint of an item of a row, e.g. of a board catalog
+pub fn row_hint(section: &str, row: usize, column: usize, id: &str) -> Option<NavigationHint> {
+    options().map(|_| NavigationHint {
+        row,
+        column,
+        focus_key: format!("{section}/{row}/{id}"),
+    })
+}
+
+/// Hint of an item of a grid, laid out in rows of the given or the configured number of columns
+pub fn grid_hint(
+    section: &str,
+    index: usize,
+    columns: Option<usize>,
+    id: &str,
+) -> Option<NavigationHint> {
+    options().map(|options| {
+        let columns = columns.unwrap_or(options.grid_columns).max(1);
+        NavigationHint {
+            row: index / columns,
+            column: index % columns,
+            focus_key: format!("{section}/{id}"),
+        }
+    })
+}
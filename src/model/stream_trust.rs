@@ -0,0 +1,119 @@
+use serde::Serialize;
+use url::{Host, Url};
+
+use stremio_core::types::addon::Descriptor;
+use stremio_core::types::resource::{Stream, StreamSource};
+
+use crate::addon_signatures;
+
+/// Hosts of the debrid services by their display name, the subdomains of a host match it too
+const DEBRID_HOSTS: [(&str, &str); 8] = [
+    ("real-debrid.com", "Real-Debrid"),
+    ("alldebrid.com", "AllDebrid"),
+    ("premiumize.me", "Premiumize"),
+    ("debrid-link.com", "Debrid-Link"),
+    ("debrid-link.fr", "Debrid-Link"),
+    ("torbox.app", "TorBox"),
+    ("offcloud.com", "Offcloud"),
+    ("put.io", "Put.io"),
+];
+/// Marker the torrent addons put before the number of seeders in the stream title
+const SEEDERS_MARKER: char = '👤';
+
+/// The order of the levels is the order the streams of an addon are sorted in
+#[derive(Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Serialize, Debug)]
+#[serde(rename_all = "camelCase")]
+pub enum StreamTrustLevel {
+    /// From an official addon or served by a known debrid service over https
+    Trusted,
+    Neutral,
+    /// Served over plain http from a remote host, or a torrent without any seeders
+    Risky,
+}
+
+#[derive(Clone, PartialEq, Eq, Serialize, Debug)]
+#[serde(rename_all = "camelCase")]
+pub struct StreamTrust {
+    pub level: StreamTrustLevel,
+    /// The addon is flagged official or its manifest has a valid official signature
+    pub official_addon: bool,
+    pub https: bool,
+    /// Name of the debrid service serving the stream
+    pub debrid: Option<&'static str>,
+    /// Seeders reported by the addon, only for torrents
+    pub seeders: Option<u32>,
+}
+
+impl StreamTrust {
+    pub fn new(stream: &Stream, addon: &Descriptor) -> Self {
+        let official_addon = addon.flags.official
+            || addon_signatures::verification(&addon.transport_url)
+                .and_then(|verification| verification.ready().cloned())
+                .map_or(false, |verification| verification.is_verified());
+        let url = match &stream.source {
+            StreamSource::Url { url } => Some(url),
+            _ => None,
+        };
+        let https = match &stream.source {
+            StreamSource::YouTube { .. } => true,
+            _ => url.map_or(false, |url| url.scheme() == "https"),
+        };
+        let debrid = url.and_then(debrid_service).filter(|_| https);
+        let seeders = match &stream.source {
+            StreamSource::Torrent { .. } => seeders(stream),
+            _ => None,
+        };
+        let insecure = url.map_or(false, |url| url.scheme() == "http" && !is_local(url));
+        let level = if official_addon || debrid.is_some() {
+            StreamTrustLevel::Trusted
+        } else if insecure || seeders == Some(0) {
+            StreamTrustLevel::Risky
+        } else {
+            StreamTrustLevel::Neutral
+        };
+        StreamTrust {
+            level,
+            official_addon,
+            https,
+            debrid,
+            seeders,
+        }
+    }
+}
+
+fn debrid_service(url: &Url) -> Option<&'static str> {
+    let host = url.host_str()?;
+    DEBRID_HOSTS
+        .iter()
+        .find(|(debrid_host, _)| {
+            host == *debrid_host
+                || host
+                    .strip_suffix(debrid_host)
+                    .map_or(false, |subdomain| subdomain.ends_with('.'))
+        })
+        .map(|(_, name)| *name)
+}
+
+/// The streaming server and the other local hosts are not reachable by anyone else
+fn is_local(url: &Url) -> bool {
+    match url.host() {
+        Some(Host::Domain(domain)) => domain == "localhost",
+        Some(Host::Ipv4(ip)) => ip.is_loopback() || ip.is_private(),
+        Some(Host::Ipv6(ip)) => ip.is_loopback(),
+        None => false,
+    }
+}
+
+/// The number after the seeders marker in the name or the description, e.g. `👤 42`
+fn seeders(stream: &Stream) -> Option<u32> {
+    [&stream.name, &stream.description]
+        .into_iter()
+        .flatten()
+        .find_map(|text| {
+            let (_, rest) = text.split_once(SEEDERS_MARKER)?;
+            rest.trim_start()
+                .split(|character: char| !character.is_ascii_digit())
+                .next()
+                .and_then(|seeders| seeders.parse().ok())
+        })
+}
@@ -0,0 +1,247 @@
+use std::{
+    collections::{HashMap, HashSet},
+    sync::RwLock,
+};
+
+use chrono::{DateTime, Duration, Utc};
+use lazy_static::lazy_static;
+use serde::{Deserialize, Serialize};
+use url::Url;
+
+use stremio_core::types::{
+    library::LibraryBucket, notifications::NotificationsBucket, resource::MetaItem,
+};
+
+pub const NEW_EPISODES_STORAGE_KEY: &str = "new_episodes";
+/// Most recent episodes shown in the row
+const MAX_NEW_EPISODES: usize = 20;
+/// Days an episode is shown in the row for since it was detected
+const MAX_AGE_DAYS: i64 = 7;
+const ROW_TITLE: &str = "New episodes for you";
+
+lazy_static! {
+    /// Videos of the library series seen so far, the ones which appear later are new
+    static ref KNOWN_VIDEOS: RwLock<HashMap<String, HashSet<String>>> = Default::default();
+    static ref NEW_EPISODES: RwLock<Vec<NewEpisode>> = Default::default();
+}
+
+#[derive(Clone, Serialize, Deserialize, Debug)]
+#[serde(rename_all = "camelCase")]
+pub struct NewEpisode {
+    pub meta_id: String,
+    pub video_id: String,
+    pub r#type: String,
+    /// Name of the series
+    pub name: String,
+    pub poster: Option<Url>,
+    /// Known when the episode was detected from the meta of the series
+    pub title: Option<String>,
+    pub season: Option<u32>,
+    pub episode: Option<u32>,
+    pub released: Option<DateTime<Utc>>,
+    pub detected_at: DateTime<Utc>,
+    /// Opens the streams of the episode
+    pub deep_link: String,
+}
+
+/// The videos seen so far and the new episodes, persisted so the episodes released
+/// while the app was closed are detected as well
+#[derive(Default, Serialize, Deserialize, Debug)]
+#[serde(rename_all = "camelCase")]
+pub struct NewEpisodesState {
+    pub known_videos: HashMap<String, HashSet<String>>,
+    pub episodes: Vec<NewEpisode>,
+}
+
+#[derive(Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct NewEpisodesRow {
+    pub title: &'static str,
+    pub items: Vec<NewEpisode>,
+}
+
+pub fn set_state(state: NewEpisodesState) {
+    *KNOWN_VIDEOS.write().expect("known videos write failed") = state.known_videos;
+    *NEW_EPISODES.write().expect("new episodes write failed") = state.episodes;
+}
+
+pub fn state() -> NewEpisodesState {
+    NewEpisodesState {
+        known_videos: KNOWN_VIDEOS
+            .read()
+            .expect("known videos read failed")
+            .to_owned(),
+        episodes: NEW_EPISODES
+            .read()
+            .expect("new episodes read failed")
+            .to_owned(),
+    }
+}
+
+/// Compares the videos of a library series with the ones seen before, see `detect_videos`.
+/// Returns whether new episodes were found.
+pub fn detect_meta_videos(
+    meta_item: &MetaItem,
+    library: &LibraryBucket,
+    now: DateTime<Utc>,
+) -> bool {
+    let in_library = library
+        .items
+        .get(&meta_item.preview.id)
+        .map_or(false, |library_item| !library_item.removed);
+    in_library && detect_videos(meta_item, now)
+}
+
+/// Compares the released videos of a series with the ones seen before.
+/// The first time a series is seen its released videos are remembered only,
+/// the scheduled ones are new once they are released.
+/// Returns whether new episodes were found.
+pub fn detect_videos(meta_item: &MetaItem, now: DateTime<Utc>) -> bool {
+    let released_videos = meta_item
+        .videos
+        .iter()
+        .filter(|video| video.released.map_or(true, |released| released <= now));
+    let mut known_videos = KNOWN_VIDEOS.write().expect("known videos write failed");
+    let known = match known_videos.get_mut(&meta_item.preview.id) {
+        Some(known) => known,
+        None => {
+            known_videos.insert(
+                meta_item.preview.id.to_owned(),
+                released_videos.map(|video| video.id.to_owned()).collect(),
+            );
+            return false;
+        }
+    };
+    let new_episodes = released_videos
+        .filter(|video| known.insert(video.id.to_owned()))
+        .map(|video| NewEpisode {
+            meta_id: meta_item.preview.id.to_owned(),
+            video_id: video.id.to_owned(),
+            r#type: meta_item.preview.r#type.to_owned(),
+            name: meta_item.preview.name.to_owned(),
+            poster: meta_item.preview.poster.to_owned(),
+            title: Some(video.title.to_owned()).filter(|title| !title.is_empty()),
+            season: video
+                .series_info
+                .as_ref()
+                .map(|series_info| series_info.season),
+            episode: video
+                .series_info
+                .as_ref()
+                .map(|series_info| series_info.episode),
+            released: video.released,
+            detected_at: now,
+            deep_link: deep_link(&meta_item.preview.r#type, &meta_item.preview.id, &video.id),
+        })
+        .collect::<Vec<_>>();
+    add_new_episodes(new_episodes)
+}
+
+/// Adds the videos the notifications were pulled for, those are new episodes of library series.
+/// Returns whether new episodes were found.
+pub fn detect_notifications(
+    notifications: &NotificationsBucket,
+    library: &LibraryBucket,
+    now: DateTime<Utc>,
+) -> bool {
+    let mut known_videos = KNOWN_VIDEOS.write().expect("known videos write failed");
+    let new_episodes = notifications
+        .items
+        .iter()
+        .filter_map(|(meta_id, items)| {
+            library
+                .items
+                .get(meta_id)
+                .filter(|library_item| !library_item.removed)
+                .map(|library_item| (library_item, items))
+        })
+        .flat_map(|(library_item, items)| {
+            let known = known_videos.entry(library_item.id.to_owned()).or_default();
+            items
+                .values()
+                .filter(|item| known.insert(item.video_id.to_owned()))
+                .map(|item| NewEpisode {
+                    meta_id: library_item.id.to_owned(),
+                    video_id: item.video_id.to_owned(),
+                    r#type: library_item.r#type.to_owned(),
+                    name: library_item.name.to_owned(),
+                    poster: library_item.poster.to_owned(),
+                    title: None,
+                    season: None,
+                    episode: None,
+                    released: Some(item.video_released),
+                    detected_at: now,
+                    deep_link: deep_link(&library_item.r#type, &library_item.id, &item.video_id),
+                })
+                .collect::<Vec<_>>()
+        })
+        .collect::<Vec<_>>();
+    drop(known_videos);
+    add_new_episodes(new_episodes)
+}
+
+/// The row of the Board, `None` without new episodes.
+/// The episodes of the series which were removed from the library are left out.
+pub fn new_episodes_row(library: &LibraryBucket, now: DateTime<Utc>) -> Option<NewEpisodesRow> {
+    let items = NEW_EPISODES
+        .read()
+        .expect("new episodes read failed")
+        .iter()
+        .filter(|new_episode| now - new_episode.detected_at < Duration::days(MAX_AGE_DAYS))
+        .filter(|new_episode| {
+            library
+                .items
+                .get(&new_episode.meta_id)
+                .map_or(false, |library_item| !library_item.removed)
+        })
+        .cloned()
+        .collect::<Vec<_>>();
+    (!items.is_empty()).then(|| NewEpisodesRow {
+        title: ROW_TITLE,
+        items,
+    })
+}
+
+/// Drops the episodes which are no longer shown in the row, returns whether any was dropped
+pub fn expire(now: DateTime<Utc>) -> bool {
+    let mut episodes = NEW_EPISODES.write().expect("new episodes write failed");
+    let len = episodes.len();
+    episodes.retain(|new_episode| now - new_episode.detected_at < Duration::days(MAX_AGE_DAYS));
+    episodes.len() != len
+}
+
+pub fn clear() {
+    KNOWN_VIDEOS
+        .write()
+        .expect("known videos write failed")
+        .clear();
+    NEW_EPISODES
+        .write()
+        .expect("new episodes write failed")
+        .clear();
+}
+
+fn add_new_episodes(new_episodes: Vec<NewEpisode>) -> bool {
+    if new_episodes.is_empty() {
+        return false;
+    }
+    let mut episodes = NEW_EPISODES.write().expect("new episodes write failed");
+    // the latest episodes first
+    for new_episode in new_episodes {
+        episodes.retain(|episode| {
+            episode.meta_id != new_episode.meta_id || episode.video_id != new_episode.video_id
+        });
+        episodes.insert(0, new_episode);
+    }
+    episodes.truncate(MAX_NEW_EPISODES);
+    true
+}
+
+fn deep_link(r#type: &str, meta_id: &str, video_id: &str) -> String {
+    format!(
+        "#/detail/{}/{}/{}",
+        String::from(js_sys::encode_uri_component(r#type)),
+        String::from(js_sys::encode_uri_component(meta_id)),
+        String::from(js_sys::encode_uri_component(video_id)),
+    )
+}
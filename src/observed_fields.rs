@@ -0,0 +1,57 @@
+use std::sync::RwLock;
+
+use lazy_static::lazy_static;
+
+use crate::model::WebModelField;
+
+lazy_static! {
+    /// `None` means that every field is observed, which is the default.
+    static ref OBSERVED_FIELDS: RwLock<Option<Vec<WebModelField>>> = Default::default();
+    /// Fields which have changed while not being observed.
+    static ref STALE_FIELDS: RwLock<Vec<WebModelField>> = Default::default();
+}
+
+/// Sets the fields observed by the UI and returns the fields which became observed
+/// and have changed in the meantime, so the UI can be notified for them.
+pub fn set_observed_fields(fields: Option<Vec<WebModelField>>) -> Vec<WebModelField> {
+    let mut stale_fields = STALE_FIELDS.write().expect("stale fields write failed");
+    let (observed, stale) = stale_fields.drain(..).partition::<Vec<_>, _>(|field| {
+        fields
+            .as_ref()
+            .map(|fields| fields.contains(field))
+            .unwrap_or(true)
+    });
+    *stale_fields = stale;
+    *OBSERVED_FIELDS
+        .write()
+        .expect("observed fields write failed") = fields;
+    observed
+}
+
+pub fn is_observed(field: &WebModelField) -> bool {
+    OBSERVED_FIELDS
+        .read()
+        .expect("observed fields read failed")
+        .as_ref()
+        .map(|fields| fields.contains(field))
+        .unwrap_or(true)
+}
+
+/// Keeps only the observed fields, the rest are remembered as stale
+pub fn filter_observed(fields: &[WebModelField]) -> Vec<WebModelField> {
+    let mut stale_fields = STALE_FIELDS.write().expect("stale fields write failed");
+    fields
+        .iter()
+        .filter(|field| {
+            if is_observed(field) {
+                true
+            } else {
+                if !stale_fields.contains(field) {
+                    stale_fields.push((*field).to_owned());
+                }
+                false
+            }
+        })
+        .cloned()
+        .collect()
+}
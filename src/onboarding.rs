@@ -0,0 +1,198 @@
+use std::sync::RwLock;
+
+use lazy_static::lazy_static;
+use serde::{Deserialize, Serialize};
+use url::Url;
+
+use stremio_core::models::common::Loadable;
+
+pub const ONBOARDING_COMPLETED_STORAGE_KEY: &str = "onboarding_completed";
+
+lazy_static! {
+    static ref ONBOARDING: RwLock<Onboarding> = Default::default();
+}
+
+/// Choices offered during the onboarding, part of the remote config.
+#[derive(Clone, Default, Deserialize, Debug)]
+#[serde(rename_all = "camelCase", default)]
+pub struct OnboardingConfig {
+    pub languages: Vec<String>,
+    pub genres: Vec<String>,
+    pub addons: Vec<RecommendedAddon>,
+}
+
+#[derive(Clone, Deserialize, Debug)]
+#[serde(rename_all = "camelCase")]
+pub struct RecommendedAddon {
+    pub transport_url: Url,
+    pub name: String,
+    /// Languages the addon is recommended for, empty for all of them
+    #[serde(default)]
+    pub languages: Vec<String>,
+    /// Genres the addon is recommended for, empty for all of them
+    #[serde(default)]
+    pub genres: Vec<String>,
+}
+
+impl RecommendedAddon {
+    fn is_recommended(&self, language: Option<&String>, genres: &[String]) -> bool {
+        (self.languages.is_empty()
+            || language.map_or(true, |language| self.languages.contains(language)))
+            && (self.genres.is_empty() || self.genres.iter().any(|genre| genres.contains(genre)))
+    }
+}
+
+#[derive(Clone, Copy, Default, PartialEq, Eq, Serialize, Debug)]
+pub enum OnboardingStep {
+    #[default]
+    Language,
+    Genres,
+    Addons,
+}
+
+impl OnboardingStep {
+    pub const ALL: [OnboardingStep; 3] = [
+        OnboardingStep::Language,
+        OnboardingStep::Genres,
+        OnboardingStep::Addons,
+    ];
+    fn next(self) -> Self {
+        match self {
+            OnboardingStep::Language => OnboardingStep::Genres,
+            OnboardingStep::Genres | OnboardingStep::Addons => OnboardingStep::Addons,
+        }
+    }
+    fn previous(self) -> Self {
+        match self {
+            OnboardingStep::Language | OnboardingStep::Genres => OnboardingStep::Language,
+            OnboardingStep::Addons => OnboardingStep::Genres,
+        }
+    }
+}
+
+#[derive(Clone, Default, Debug)]
+pub struct Onboarding {
+    pub completed: bool,
+    pub step: OnboardingStep,
+    pub language: Option<String>,
+    pub genres: Vec<String>,
+    pub selected_addons: Vec<Url>,
+    /// Installing the selected addons and applying the settings at the end of the flow
+    pub finishing: Option<Loadable<(), String>>,
+}
+
+impl Onboarding {
+    pub fn recommended_addons<'a>(
+        &self,
+        config: &'a OnboardingConfig,
+    ) -> impl Iterator<Item = &'a RecommendedAddon> {
+        let language = self.language.to_owned();
+        let genres = self.genres.to_owned();
+        config
+            .addons
+            .iter()
+            .filter(move |addon| addon.is_recommended(language.as_ref(), &genres))
+    }
+}
+
+#[derive(Deserialize, Debug)]
+#[serde(tag = "action", content = "args")]
+pub enum OnboardingAction {
+    SelectLanguage(String),
+    ToggleGenre(String),
+    ToggleAddon(Url),
+    NextStep,
+    PreviousStep,
+    /// Completes the onboarding without installing anything
+    Skip,
+    Finish,
+}
+
+pub fn onboarding() -> Onboarding {
+    ONBOARDING
+        .read()
+        .expect("onboarding read failed")
+        .to_owned()
+}
+
+pub fn set_completed(completed: bool) {
+    ONBOARDING
+        .write()
+        .expect("onboarding write failed")
+        .completed = completed;
+}
+
+/// Applies the selection and navigation actions, `Skip` and `Finish` are handled by the caller.
+pub fn update(action: OnboardingAction, config: &OnboardingConfig) {
+    let mut onboarding = ONBOARDING.write().expect("onboarding write failed");
+    if onboarding.completed || matches!(onboarding.finishing, Some(Loadable::Loading)) {
+        return;
+    }
+    match action {
+        OnboardingAction::SelectLanguage(language) => onboarding.language = Some(language),
+        OnboardingAction::ToggleGenre(genre) => {
+            match onboarding
+                .genres
+                .iter()
+                .position(|selected| *selected == genre)
+            {
+                Some(position) => {
+                    onboarding.genres.remove(position);
+                }
+                None => onboarding.genres.push(genre),
+            }
+        }
+        OnboardingAction::ToggleAddon(transport_url) => {
+            match onboarding
+                .selected_addons
+                .iter()
+                .position(|selected| *selected == transport_url)
+            {
+                Some(position) => {
+                    onboarding.selected_addons.remove(position);
+                }
+                None => onboarding.selected_addons.push(transport_url),
+            }
+        }
+        OnboardingAction::NextStep => {
+            onboarding.step = onboarding.step.next();
+            if onboarding.step == OnboardingStep::Addons {
+                // every recommended addon is selected when entering the step
+                onboarding.selected_addons = onboarding
+                    .recommended_addons(config)
+                    .map(|addon| addon.transport_url.to_owned())
+                    .collect();
+            }
+        }
+        OnboardingAction::PreviousStep => onboarding.step = onboarding.step.previous(),
+        OnboardingAction::Skip | OnboardingAction::Finish => {}
+    }
+}
+
+/// Marks the onboarding as finishing and returns the chosen language and addons,
+/// `None` when it is already finishing or completed.
+pub fn start_finishing(config: &OnboardingConfig) -> Option<(Option<String>, Vec<Url>)> {
+    let mut onboarding = ONBOARDING.write().expect("onboarding write failed");
+    if onboarding.completed || matches!(onboarding.finishing, Some(Loadable::Loading)) {
+        return None;
+    }
+    onboarding.finishing = Some(Loadable::Loading);
+    let transport_urls = onboarding
+        .recommended_addons(config)
+        .map(|addon| &addon.transport_url)
+        .filter(|transport_url| onboarding.selected_addons.contains(transport_url))
+        .cloned()
+        .collect();
+    Some((onboarding.language.to_owned(), transport_urls))
+}
+
+pub fn set_finished(result: Result<(), String>) {
+    let mut onboarding = ONBOARDING.write().expect("onboarding write failed");
+    match result {
+        Ok(()) => {
+            onboarding.completed = true;
+            onboarding.finishing = Some(Loadable::Ready(()));
+        }
+        Err(error) => onboarding.finishing = Some(Loadable::Err(error)),
+    }
+}
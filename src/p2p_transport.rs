@@ -0,0 +1,112 @@
+use std::collections::HashMap;
+use std::sync::RwLock;
+
+use futures::{future, FutureExt};
+use lazy_static::lazy_static;
+use url::Url;
+
+use stremio_core::{
+    addon_transport::{AddonHTTPTransport, AddonTransport},
+    models::common::Loadable,
+    runtime::{EnvError, RuntimeEvent, TryEnvFuture},
+    types::addon::{Manifest, ResourcePath, ResourceResponse},
+};
+
+use crate::{env::WebEnv, model::WebModelField, stremio_core_web::emit_event};
+
+lazy_static! {
+    /// Resolution status of the manifest per p2p transport url
+    static ref RESOLUTIONS: RwLock<HashMap<Url, Loadable<Url, String>>> = Default::default();
+}
+
+/// Experimental transport for addons published as torrents (`torrent://{infoHash}/manifest.json`)
+/// or on IPFS (`ipfs://{cid}/manifest.json`), resolved through the streaming server.
+pub struct AddonP2PTransport {
+    transport_url: Url,
+}
+
+impl AddonP2PTransport {
+    pub fn new(transport_url: Url) -> Self {
+        Self { transport_url }
+    }
+    fn unresolved_error(&self) -> EnvError {
+        EnvError::Fetch(format!(
+            "Streaming server is required to resolve {}",
+            self.transport_url
+        ))
+    }
+}
+
+impl AddonTransport for AddonP2PTransport {
+    fn resource(&self, path: &ResourcePath) -> TryEnvFuture<ResourceResponse> {
+        match resolve_url(&self.transport_url) {
+            Some(resolved_url) => AddonHTTPTransport::<WebEnv>::new(resolved_url).resource(path),
+            None => future::err(self.unresolved_error()).boxed_local(),
+        }
+    }
+    fn manifest(&self) -> TryEnvFuture<Manifest> {
+        let transport_url = self.transport_url.to_owned();
+        match resolve_url(&transport_url) {
+            Some(resolved_url) => {
+                set_resolution(transport_url.to_owned(), Loadable::Loading);
+                AddonHTTPTransport::<WebEnv>::new(resolved_url.to_owned())
+                    .manifest()
+                    .inspect(move |result| {
+                        let resolution = match result {
+                            Ok(_) => Loadable::Ready(resolved_url),
+                            Err(error) => Loadable::Err(error.message()),
+                        };
+                        set_resolution(transport_url, resolution);
+                        emit_event(&RuntimeEvent::NewState(vec![WebModelField::AddonDetails]));
+                    })
+                    .boxed_local()
+            }
+            None => {
+                let error = self.unresolved_error();
+                set_resolution(transport_url, Loadable::Err(error.message()));
+                future::err(error).boxed_local()
+            }
+        }
+    }
+}
+
+pub fn is_p2p_transport_url(transport_url: &Url) -> bool {
+    matches!(transport_url.scheme(), "torrent" | "ipfs")
+}
+
+pub fn resolution(transport_url: &Url) -> Option<Loadable<Url, String>> {
+    RESOLUTIONS
+        .read()
+        .expect("p2p resolutions read failed")
+        .get(transport_url)
+        .cloned()
+}
+
+fn set_resolution(transport_url: Url, resolution: Loadable<Url, String>) {
+    RESOLUTIONS
+        .write()
+        .expect("p2p resolutions write failed")
+        .insert(transport_url, resolution);
+}
+
+/// Maps the p2p url to the http url served by the streaming server:
+/// - `torrent://{infoHash}/{path}` to `{server}/{infoHash}/{path}`
+/// - `ipfs://{cid}/{path}` to `{server}/ipfs/{cid}/{path}`
+fn resolve_url(transport_url: &Url) -> Option<Url> {
+    let streaming_server_url = WebEnv::streaming_server_url()?;
+    let host = transport_url.host_str()?;
+    let path = transport_url.path().trim_start_matches('/');
+    let resolved_path = match transport_url.scheme() {
+        "torrent" => format!("{host}/{path}"),
+        "ipfs" => format!("ipfs/{host}/{path}"),
+        _ => return None,
+    };
+    streaming_server_url.join(&resolved_path).ok()
+}
+
+pub fn clear() {
+    RESOLUTIONS
+        .write()
+        .expect("p2p resolutions write failed")
+        .clear();
+}
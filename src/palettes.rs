@@ -0,0 +1,218 @@
+use std::{
+    collections::{HashMap, HashSet, VecDeque},
+    sync::RwLock,
+};
+
+use boolinator::Boolinator;
+use itertools::Itertools;
+use lazy_static::lazy_static;
+use serde::Serialize;
+use url::Url;
+
+use stremio_core::{models::common::Loadable, types::resource::MetaItemPreview};
+
+/// Side of the square the image is scaled down to before its colors are counted
+pub const SAMPLE_SIZE: u32 = 32;
+/// Set by the fetch of an image which failed before a response, e.g. as its host
+/// does not allow cross-origin requests, the other images of the host are skipped
+pub const UNREADABLE_IMAGE_ERROR: &str = "Image could not be read";
+/// Palettes kept at most, the ones of the images seen first are dropped
+const MAX_PALETTES: usize = 500;
+/// Images fetched at once, the rest of them wait in a queue
+const MAX_CONCURRENT_EXTRACTIONS: usize = 4;
+/// Bits of each channel the colors are bucketed by
+const QUANTIZE_BITS: u32 = 4;
+
+lazy_static! {
+    static ref PALETTES: RwLock<Palettes> = Default::default();
+}
+
+#[derive(Default)]
+struct Palettes {
+    /// Palettes per image url
+    palettes: HashMap<Url, Loadable<Palette, String>>,
+    /// The order the images were first seen in
+    order: Vec<Url>,
+    /// Images waiting for a slot to be fetched
+    queue: VecDeque<Url>,
+    /// Images being fetched
+    extracting: usize,
+    /// Hosts whose images could not be read, see `UNREADABLE_IMAGE_ERROR`
+    unreadable_hosts: HashSet<String>,
+}
+
+impl Palettes {
+    /// Takes the queued images which fit in the free slots
+    fn next_extractions(&mut self) -> Vec<Url> {
+        let mut urls = vec![];
+        while self.extracting < MAX_CONCURRENT_EXTRACTIONS {
+            let url = match self.queue.pop_front() {
+                Some(url) => url,
+                None => break,
+            };
+            // the images dropped over the limit while they were queued are skipped
+            if !self.palettes.contains_key(&url) || self.is_unreadable(&url) {
+                self.palettes.remove(&url);
+                continue;
+            }
+            self.extracting += 1;
+            urls.push(url);
+        }
+        urls
+    }
+    fn is_unreadable(&self, url: &Url) -> bool {
+        url.host_str()
+            .map_or(false, |host| self.unreadable_hosts.contains(host))
+    }
+}
+
+/// Colors of a poster or a background, as `#rrggbb`
+#[derive(Clone, PartialEq, Eq, Serialize, Debug)]
+#[serde(rename_all = "camelCase")]
+pub struct Palette {
+    /// The most common color
+    pub dominant: String,
+    /// A saturated color for accents
+    pub vibrant: String,
+    /// A desaturated color for surfaces
+    pub muted: String,
+    /// A dark color for backgrounds under light text
+    pub dark_muted: String,
+    /// Whether the dominant color needs light text on top of it
+    pub is_dark: bool,
+}
+
+/// Queues the images which have no palette yet and returns the ones to fetch now
+pub fn start_extracting(urls: Vec<Url>) -> Vec<Url> {
+    let mut palettes = PALETTES.write().expect("palettes write failed");
+    let urls = urls
+        .into_iter()
+        .filter(|url| url.scheme() == "https" || url.scheme() == "http")
+        .filter(|url| !palettes.palettes.contains_key(url) && !palettes.is_unreadable(url))
+        .unique()
+        .collect::<Vec<_>>();
+    for url in urls {
+        palettes.palettes.insert(url.to_owned(), Loadable::Loading);
+        palettes.order.push(url.to_owned());
+        palettes.queue.push_back(url);
+    }
+    if palettes.order.len() > MAX_PALETTES {
+        let overflow = palettes.order.len() - MAX_PALETTES;
+        for url in palettes.order.drain(..overflow).collect::<Vec<_>>() {
+            palettes.palettes.remove(&url);
+        }
+    }
+    palettes.next_extractions()
+}
+
+/// Sets the palette of a fetched image and returns the queued images to fetch next
+pub fn set_palette(url: &Url, result: Result<Palette, String>) -> Vec<Url> {
+    let mut palettes = PALETTES.write().expect("palettes write failed");
+    palettes.extracting = palettes.extracting.saturating_sub(1);
+    if let Err(error) = &result {
+        if error.contains(UNREADABLE_IMAGE_ERROR) {
+            if let Some(host) = url.host_str() {
+                palettes.unreadable_hosts.insert(host.to_owned());
+            }
+        }
+    }
+    if let Some(palette) = palettes.palettes.get_mut(url) {
+        *palette = match result {
+            Ok(palette) => Loadable::Ready(palette),
+            Err(error) => Loadable::Err(error),
+        };
+    }
+    palettes.next_extractions()
+}
+
+/// The palette of the image, `None` until it's extracted
+pub fn palette(url: Option<&Url>) -> Option<Palette> {
+    let palettes = PALETTES.read().expect("palettes read failed");
+    url.and_then(|url| palettes.palettes.get(url))
+        .and_then(|palette| palette.ready())
+        .cloned()
+}
+
+/// The image the details page is themed by, the background and otherwise the poster
+pub fn theme_image(meta_item: &MetaItemPreview) -> Option<&Url> {
+    meta_item.background.as_ref().or(meta_item.poster.as_ref())
+}
+
+pub fn len() -> usize {
+    PALETTES
+        .read()
+        .expect("palettes read failed")
+        .palettes
+        .len()
+}
+
+pub fn clear() {
+    *PALETTES.write().expect("palettes write failed") = Default::default();
+}
+
+/// Builds the palette from the RGBA pixels of the scaled down image, the transparent ones are skipped.
+/// The colors are bucketed so that close shades count as the same color.
+pub fn extract(pixels: &[u8]) -> Option<Palette> {
+    let mut buckets = HashMap::<u32, (u32, [u32; 3])>::new();
+    for pixel in pixels.chunks_exact(4).filter(|pixel| pixel[3] >= 128) {
+        let key = pixel[..3].iter().fold(0, |key, channel| {
+            (key << QUANTIZE_BITS) | (u32::from(*channel) >> (8 - QUANTIZE_BITS))
+        });
+        let (count, sums) = buckets.entry(key).or_default();
+        *count += 1;
+        for (sum, channel) in sums.iter_mut().zip(&pixel[..3]) {
+            *sum += u32::from(*channel);
+        }
+    }
+    let colors = buckets
+        .into_values()
+        .map(|(count, sums)| (count, sums.map(|sum| (sum / count) as u8)))
+        .collect::<Vec<_>>();
+    let (_, dominant) = colors.iter().max_by_key(|(count, _)| *count)?;
+    let most_common = |score: &dyn Fn(f64, f64) -> Option<f64>| {
+        colors
+            .iter()
+            .filter_map(|(count, rgb)| {
+                let (saturation, lightness) = saturation_lightness(rgb);
+                score(saturation, lightness).map(|score| (score * f64::from(*count), rgb))
+            })
+            .max_by(|(a, _), (b, _)| a.total_cmp(b))
+            .map(|(_, rgb)| *rgb)
+    };
+    let vibrant = most_common(&|saturation, lightness| {
+        (saturation >= 0.35 && (0.3..=0.75).contains(&lightness)).as_some(saturation)
+    });
+    let muted = most_common(&|saturation, lightness| {
+        (saturation < 0.35 && (0.3..=0.75).contains(&lightness)).as_some(1.0 - saturation)
+    });
+    let dark_muted = most_common(&|_, lightness| (lightness < 0.3).as_some(1.0 - lightness));
+    Some(Palette {
+        dominant: hex(dominant),
+        vibrant: hex(&vibrant.unwrap_or(*dominant)),
+        muted: hex(&muted.unwrap_or(*dominant)),
+        dark_muted: hex(&dark_muted.unwrap_or_else(|| dominant.map(|channel| channel / 4))),
+        is_dark: luminance(dominant) < 0.5,
+    })
+}
+
+fn saturation_lightness(rgb: &[u8; 3]) -> (f64, f64) {
+    let channels = rgb.map(|channel| f64::from(channel) / 255.0);
+    let max = channels.iter().cloned().fold(0.0, f64::max);
+    let min = channels.iter().cloned().fold(1.0, f64::min);
+    let lightness = (max + min) / 2.0;
+    let saturation = if max == min {
+        0.0
+    } else {
+        (max - min) / (1.0 - (2.0 * lightness - 1.0).abs())
+    };
+    (saturation, lightness)
+}
+
+/// Relative luminance as perceived, from 0 for black to 1 for white
+fn luminance(rgb: &[u8; 3]) -> f64 {
+    (0.299 * f64::from(rgb[0]) + 0.587 * f64::from(rgb[1]) + 0.114 * f64::from(rgb[2])) / 255.0
+}
+
+fn hex(rgb: &[u8; 3]) -> String {
+    format!("#{:02x}{:02x}{:02x}", rgb[0], rgb[1], rgb[2])
+}
@@ -0,0 +1,181 @@
+use std::sync::RwLock;
+
+use http::Request;
+use lazy_static::lazy_static;
+use serde::Serialize;
+use serde_json::Value;
+use url::Url;
+
+use stremio_core::{
+    runtime::{Env, RuntimeEvent},
+    types::resource::Stream,
+};
+
+use crate::{env::WebEnv, model::WebModelField, stremio_core_web::emit_event, web_playback};
+
+const STREAMING_SERVER_NOT_AVAILABLE: &str = "Streaming server is not available";
+
+lazy_static! {
+    /// The source of the not web ready stream selected in the player, by the url of the stream
+    static ref SOURCE: RwLock<Option<(Url, PlayerSource)>> = Default::default();
+}
+
+/// How the player reaches the stream
+#[derive(Clone, Copy, PartialEq, Eq, Serialize, Debug)]
+pub enum SourcePath {
+    /// The streaming server is asked whether it can transcode the stream
+    Resolving,
+    /// The url of the stream as it is
+    Direct,
+    /// Through the proxy of the streaming server, which sets the request headers
+    Proxied,
+    /// The HLS playlist of the stream transcoded by the streaming server
+    Transcoded,
+}
+
+#[derive(Clone, PartialEq, Serialize, Debug)]
+#[serde(rename_all = "camelCase")]
+pub struct PlayerSource {
+    pub path: SourcePath,
+    /// `None` while resolving
+    pub url: Option<Url>,
+    /// Why the preferred paths were not taken, the most preferred first
+    pub fallback_reasons: Vec<String>,
+}
+
+impl PlayerSource {
+    fn new(path: SourcePath, url: Option<Url>, fallback_reasons: Vec<String>) -> Self {
+        Self {
+            path,
+            url,
+            fallback_reasons,
+        }
+    }
+}
+
+/// The source of the url stream, a not web ready one is `Resolving` until `resolve` is done
+pub fn source(stream: &Stream) -> Option<PlayerSource> {
+    let url = web_playback::stream_url(stream)?;
+    if !stream.behavior_hints.not_web_ready {
+        return Some(web_ready_source(stream, url));
+    }
+    let source = SOURCE
+        .read()
+        .expect("player source read failed")
+        .as_ref()
+        .filter(|(source_url, _)| source_url == url)
+        .map(|(_, source)| source.to_owned());
+    Some(source.unwrap_or_else(|| PlayerSource::new(SourcePath::Resolving, None, vec![])))
+}
+
+/// Asks the streaming server whether it can transcode the not web ready stream, otherwise
+/// the stream is played through the proxy of the streaming server or, at last, as it is.
+/// Nothing is done for the stream which was resolved already.
+pub fn resolve(stream: &Stream) {
+    let url = match web_playback::stream_url(stream) {
+        Some(url) if stream.behavior_hints.not_web_ready => url.to_owned(),
+        _ => return,
+    };
+    {
+        let mut source = SOURCE.write().expect("player source write failed");
+        if source
+            .as_ref()
+            .map_or(false, |(source_url, _)| *source_url == url)
+        {
+            return;
+        }
+        *source = Some((
+            url.to_owned(),
+            PlayerSource::new(SourcePath::Resolving, None, vec![]),
+        ));
+    }
+    let streaming_server_url = match WebEnv::streaming_server_url() {
+        Some(streaming_server_url) => streaming_server_url,
+        None => {
+            let reason = STREAMING_SERVER_NOT_AVAILABLE.to_owned();
+            set_source(
+                &url,
+                PlayerSource::new(SourcePath::Direct, Some(url.to_owned()), vec![reason]),
+            );
+            return;
+        }
+    };
+    let proxied_url = web_playback::request_headers(stream).and_then(|request_headers| {
+        web_playback::proxy_url(&streaming_server_url, &url, request_headers)
+    });
+    let media_url = proxied_url.to_owned().unwrap_or_else(|| url.to_owned());
+    let transcoded_url = web_playback::transcoded_url(&streaming_server_url, &url, &media_url);
+    let probe_url = streaming_server_url
+        .join("hlsv2/probe")
+        .ok()
+        .map(|mut probe_url| {
+            probe_url
+                .query_pairs_mut()
+                .append_pair("mediaURL", media_url.as_str());
+            probe_url
+        });
+    WebEnv::exec_concurrent(async move {
+        let result = match probe_url.zip(transcoded_url) {
+            Some((probe_url, transcoded_url)) => {
+                let request = Request::get(probe_url.as_str())
+                    .body(())
+                    .expect("request builder failed");
+                WebEnv::fetch::<_, Value>(request)
+                    .await
+                    .map(|_| transcoded_url)
+                    .map_err(|error| error.message())
+            }
+            None => Err("Invalid url of the stream".to_owned()),
+        };
+        let source = match result {
+            Ok(transcoded_url) => {
+                PlayerSource::new(SourcePath::Transcoded, Some(transcoded_url), vec![])
+            }
+            Err(error) => {
+                let reason = format!("Transcoding failed: {error}");
+                match proxied_url {
+                    Some(proxied_url) => {
+                        PlayerSource::new(SourcePath::Proxied, Some(proxied_url), vec![reason])
+                    }
+                    None => {
+                        PlayerSource::new(SourcePath::Direct, Some(url.to_owned()), vec![reason])
+                    }
+                }
+            }
+        };
+        set_source(&url, source);
+    });
+}
+
+pub fn clear() {
+    *SOURCE.write().expect("player source write failed") = None;
+}
+
+/// The web ready streams are played as they are, unless they need request headers
+fn web_ready_source(stream: &Stream, url: &Url) -> PlayerSource {
+    let request_headers = match web_playback::request_headers(stream) {
+        Some(request_headers) => request_headers,
+        None => return PlayerSource::new(SourcePath::Direct, Some(url.to_owned()), vec![]),
+    };
+    match WebEnv::streaming_server_url().and_then(|streaming_server_url| {
+        web_playback::proxy_url(&streaming_server_url, url, request_headers)
+    }) {
+        Some(proxied_url) => PlayerSource::new(SourcePath::Proxied, Some(proxied_url), vec![]),
+        None => PlayerSource::new(
+            SourcePath::Direct,
+            Some(url.to_owned()),
+            vec![STREAMING_SERVER_NOT_AVAILABLE.to_owned()],
+        ),
+    }
+}
+
+/// The source is dropped when another stream was selected meanwhile
+fn set_source(url: &Url, source: PlayerSource) {
+    let mut current = SOURCE.write().expect("player source write failed");
+    match current.as_mut() {
+        Some((source_url, current_source)) if source_url == url => *current_source = source,
+        _ => return,
+    };
+    drop(current);
+    emit_event(&RuntimeEvent::NewState(vec![WebModelField::Player]));
+}
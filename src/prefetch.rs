@@ -0,0 +1,172 @@
+use std::sync::RwLock;
+
+use chrono::{DateTime, Duration, Utc};
+use futures::{future, FutureExt};
+use lazy_static::lazy_static;
+use url::Url;
+
+use stremio_core::{
+    addon_transport::AddonTransport,
+    models::common::Loadable,
+    runtime::{Env, EnvError, RuntimeEvent, TryEnvFuture},
+    types::{
+        addon::{Manifest, ResourcePath, ResourceRequest, ResourceResponse},
+        resource::MetaItem,
+    },
+};
+
+use crate::{env::WebEnv, model::WebModelField, stremio_core_web::emit_event};
+
+/// Maximum number of prefetched responses kept in memory
+const MAX_PREFETCHED: usize = 20;
+/// Minutes a prefetched response is served for, it's loaded again afterwards
+const PREFETCHED_TTL: i64 = 10;
+
+lazy_static! {
+    static ref PREFETCHED: RwLock<Vec<Prefetched>> = Default::default();
+}
+
+struct Prefetched {
+    request: ResourceRequest,
+    content: Loadable<ResourceResponse, EnvError>,
+    started_at: DateTime<Utc>,
+}
+
+impl Prefetched {
+    fn is_fresh(&self, now: DateTime<Utc>) -> bool {
+        now < self.started_at + Duration::minutes(PREFETCHED_TTL)
+    }
+}
+
+/// Serves the prefetched responses, every other request goes through the wrapped transport.
+pub struct PrefetchTransport {
+    transport_url: Url,
+    transport: Box<dyn AddonTransport>,
+}
+
+impl PrefetchTransport {
+    pub fn new(transport_url: Url, transport: Box<dyn AddonTransport>) -> Self {
+        Self {
+            transport_url,
+            transport,
+        }
+    }
+}
+
+impl AddonTransport for PrefetchTransport {
+    fn resource(&self, path: &ResourcePath) -> TryEnvFuture<ResourceResponse> {
+        let request = ResourceRequest::new(self.transport_url.to_owned(), path.to_owned());
+        match prefetched(&request) {
+            Some(response) => future::ok(response).boxed_local(),
+            None => self.transport.resource(path),
+        }
+    }
+    fn manifest(&self) -> TryEnvFuture<Manifest> {
+        self.transport.manifest()
+    }
+}
+
+/// Starts loading the request in the background, unless it's already prefetched or loading.
+/// The field is updated once it's loaded.
+pub fn prefetch(request: &ResourceRequest, field: Option<WebModelField>) {
+    {
+        let now = WebEnv::now();
+        let mut prefetched = PREFETCHED.write().expect("prefetched write failed");
+        prefetched.retain(|prefetched| prefetched.is_fresh(now));
+        if prefetched
+            .iter()
+            .any(|prefetched| prefetched.request == *request)
+        {
+            return;
+        }
+        if prefetched.len() >= MAX_PREFETCHED {
+            prefetched.remove(0);
+        }
+        prefetched.push(Prefetched {
+            request: request.to_owned(),
+            content: Loadable::Loading,
+            started_at: now,
+        });
+    }
+    let request = request.to_owned();
+    WebEnv::exec_concurrent(
+        WebEnv::addon_transport(&request.base)
+            .resource(&request.path)
+            .map(move |result| {
+                let mut prefetched = PREFETCHED.write().expect("prefetched write failed");
+                match result {
+                    Ok(response) => {
+                        if let Some(prefetched) = prefetched
+                            .iter_mut()
+                            .find(|prefetched| prefetched.request == request)
+                        {
+                            prefetched.content = Loadable::Ready(response);
+                        }
+                    }
+                    // failed requests are not kept so they can be retried by core
+                    Err(_) => prefetched.retain(|prefetched| prefetched.request != request),
+                };
+                drop(prefetched);
+                if let Some(field) = field {
+                    emit_event(&RuntimeEvent::NewState(vec![field]));
+                }
+            }),
+    );
+}
+
+pub fn is_prefetched(request: &ResourceRequest) -> bool {
+    prefetched(request).is_some()
+}
+
+pub fn is_loading(request: &ResourceRequest) -> bool {
+    let now = WebEnv::now();
+    PREFETCHED
+        .read()
+        .expect("prefetched read failed")
+        .iter()
+        .any(|prefetched| {
+            prefetched.request == *request
+                && matches!(prefetched.content, Loadable::Loading)
+                && prefetched.is_fresh(now)
+        })
+}
+
+/// The prefetched meta item of any addon, along with its request
+pub fn meta_item(r#type: &str, id: &str) -> Option<(ResourceRequest, MetaItem)> {
+    let now = WebEnv::now();
+    PREFETCHED
+        .read()
+        .expect("prefetched read failed")
+        .iter()
+        .filter(|prefetched| prefetched.is_fresh(now))
+        .find_map(|prefetched| match &prefetched.content {
+            Loadable::Ready(ResourceResponse::Meta { meta })
+                if prefetched.request.path.resource == "meta"
+                    && prefetched.request.path.r#type == r#type
+                    && prefetched.request.path.id == id =>
+            {
+                Some((prefetched.request.to_owned(), meta.to_owned()))
+            }
+            _ => None,
+        })
+}
+
+fn prefetched(request: &ResourceRequest) -> Option<ResourceResponse> {
+    let now = WebEnv::now();
+    PREFETCHED
+        .read()
+        .expect("prefetched read failed")
+        .iter()
+        .filter(|prefetched| prefetched.is_fresh(now))
+        .find(|prefetched| prefetched.request == *request)
+        .and_then(|prefetched| prefetched.content.ready())
+        .cloned()
+}
+
+pub fn len() -> usize {
+    PREFETCHED.read().expect("prefetched read failed").len()
+}
+
+pub fn clear() {
+    PREFETCHED.write().expect("prefetched write failed").clear();
+}
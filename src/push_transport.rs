@@ -0,0 +1,190 @@
+use std::cell::RefCell;
+use std::collections::HashMap;
+use std::sync::RwLock;
+
+use futures::{FutureExt, TryFutureExt};
+use lazy_static::lazy_static;
+use serde::Deserialize;
+use tracing::error;
+use url::Url;
+use wasm_bindgen::closure::Closure;
+use wasm_bindgen::JsCast;
+
+use stremio_core::{
+    addon_transport::{AddonHTTPTransport, AddonTransport},
+    runtime::{RuntimeEvent, TryEnvFuture},
+    types::{
+        addon::{Manifest, ResourcePath, ResourceRequest, ResourceResponse},
+        resource::MetaItemPreview,
+    },
+};
+
+use crate::{env::WebEnv, model::WebModelField, stremio_core_web::emit_event};
+
+lazy_static! {
+    /// Latest catalog content per request, the initial response with the pushed updates applied.
+    static ref PUSHED_CATALOGS: RwLock<Vec<(ResourceRequest, Vec<MetaItemPreview>)>> =
+        Default::default();
+}
+
+thread_local! {
+    static SOCKETS: RefCell<HashMap<Url, web_sys::WebSocket>> = RefCell::new(HashMap::new());
+}
+
+/// An update pushed by the addon for a single catalog
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct PushMessage {
+    /// New or updated items, matched by their id
+    #[serde(default)]
+    metas: Vec<MetaItemPreview>,
+    /// Ids of the items which should be removed
+    #[serde(default)]
+    removed: Vec<String>,
+}
+
+/// Transport for addons installed with a `ws://` or `wss://` transport url.
+///
+/// Resources are requested over http(s), catalogs are then kept up to date
+/// through a persistent connection to `push/{type}/{id}.json` relative to the transport url.
+pub struct AddonPushTransport {
+    transport_url: Url,
+    http_transport: AddonHTTPTransport<WebEnv>,
+}
+
+impl AddonPushTransport {
+    pub fn new(transport_url: Url) -> Self {
+        let mut http_url = transport_url.to_owned();
+        let scheme = if transport_url.scheme() == "wss" {
+            "https"
+        } else {
+            "http"
+        };
+        http_url
+            .set_scheme(scheme)
+            .expect("transport url scheme change failed");
+        Self {
+            transport_url,
+            http_transport: AddonHTTPTransport::new(http_url),
+        }
+    }
+}
+
+impl AddonTransport for AddonPushTransport {
+    fn resource(&self, path: &ResourcePath) -> TryEnvFuture<ResourceResponse> {
+        let request = ResourceRequest::new(self.transport_url.to_owned(), path.to_owned());
+        let push_url = self
+            .transport_url
+            .join(&format!("push/{}/{}.json", path.r#type, path.id))
+            .ok();
+        self.http_transport
+            .resource(path)
+            .inspect_ok(move |response| {
+                if let ResourceResponse::Metas { metas } = response {
+                    set_pushed_catalog(request.to_owned(), metas.to_owned());
+                    if let Some(push_url) = push_url {
+                        subscribe(request, push_url);
+                    }
+                }
+            })
+            .boxed_local()
+    }
+    fn manifest(&self) -> TryEnvFuture<Manifest> {
+        self.http_transport.manifest()
+    }
+}
+
+/// Catalogs which have been updated through a push transport
+pub fn pushed_catalogs() -> Vec<(ResourceRequest, Vec<MetaItemPreview>)> {
+    PUSHED_CATALOGS
+        .read()
+        .expect("pushed catalogs read failed")
+        .to_owned()
+}
+
+/// Closes the push connections and drops the pushed catalogs
+pub fn close_all() {
+    SOCKETS.with(|sockets| {
+        for (_, socket) in sockets.borrow_mut().drain() {
+            socket.set_onmessage(None);
+            if let Err(error) = socket.close() {
+                error!("Failed to close push connection: {error:?}");
+            }
+        }
+    });
+    PUSHED_CATALOGS
+        .write()
+        .expect("pushed catalogs write failed")
+        .clear();
+}
+
+fn set_pushed_catalog(request: ResourceRequest, metas: Vec<MetaItemPreview>) {
+    let mut pushed_catalogs = PUSHED_CATALOGS
+        .write()
+        .expect("pushed catalogs write failed");
+    match pushed_catalogs
+        .iter_mut()
+        .find(|(pushed_request, _)| *pushed_request == request)
+    {
+        Some((_, pushed_metas)) => *pushed_metas = metas,
+        None => pushed_catalogs.push((request, metas)),
+    }
+}
+
+fn apply_push_message(request: &ResourceRequest, message: PushMessage) {
+    let mut pushed_catalogs = PUSHED_CATALOGS
+        .write()
+        .expect("pushed catalogs write failed");
+    if let Some((_, metas)) = pushed_catalogs
+        .iter_mut()
+        .find(|(pushed_request, _)| pushed_request == request)
+    {
+        metas.retain(|meta_item| !message.removed.contains(&meta_item.id));
+        for pushed_meta_item in message.metas {
+            match metas
+                .iter_mut()
+                .find(|meta_item| meta_item.id == pushed_meta_item.id)
+            {
+                Some(meta_item) => *meta_item = pushed_meta_item,
+                None => metas.push(pushed_meta_item),
+            }
+        }
+    }
+}
+
+fn subscribe(request: ResourceRequest, push_url: Url) {
+    SOCKETS.with(|sockets| {
+        let mut sockets = sockets.borrow_mut();
+        if sockets.contains_key(&push_url) {
+            return;
+        }
+        let socket = match web_sys::WebSocket::new(push_url.as_str()) {
+            Ok(socket) => socket,
+            Err(error) => {
+                error!("Failed to open push connection to {push_url}: {error:?}");
+                return;
+            }
+        };
+        let on_message = Closure::wrap(Box::new(move |event: web_sys::MessageEvent| {
+            let message = event
+                .data()
+                .as_string()
+                .map(|data| serde_json::from_str::<PushMessage>(&data));
+            match message {
+                Some(Ok(message)) => {
+                    apply_push_message(&request, message);
+                    emit_event(&RuntimeEvent::NewState(vec![
+                        WebModelField::Board,
+                        WebModelField::Discover,
+                        WebModelField::Search,
+                    ]));
+                }
+                Some(Err(error)) => error!("Invalid push message: {error}"),
+                None => {}
+            }
+        }) as Box<dyn FnMut(web_sys::MessageEvent)>);
+        socket.set_onmessage(Some(on_message.as_ref().unchecked_ref()));
+        on_message.forget();
+        sockets.insert(push_url, socket);
+    });
+}
@@ -0,0 +1,87 @@
+use std::{cell::RefCell, collections::VecDeque, fmt::Write};
+
+use chrono::{DateTime, Utc};
+use serde::Serialize;
+use tracing::{
+    field::{Field, Visit},
+    Event, Level, Subscriber,
+};
+use tracing_subscriber::{layer::Context, Layer};
+
+use stremio_core::runtime::Env;
+
+use crate::env::WebEnv;
+
+/// Events kept for the bug reports, the oldest are dropped first
+const MAX_LOGS: usize = 200;
+
+thread_local! {
+    static LOGS: RefCell<VecDeque<LogEntry>> = RefCell::new(VecDeque::new());
+}
+
+#[derive(Clone, Serialize, Debug)]
+#[serde(rename_all = "camelCase")]
+pub struct LogEntry {
+    pub time: DateTime<Utc>,
+    pub level: String,
+    pub target: String,
+    /// The message along with the fields of the event, e.g. `Request failed, id="3f9a"`
+    pub message: String,
+}
+
+/// Keeps the latest events of the levels which are logged to the console
+pub struct RecentLogsLayer {
+    max_level: Level,
+}
+
+impl RecentLogsLayer {
+    pub fn new(max_level: Level) -> Self {
+        RecentLogsLayer { max_level }
+    }
+}
+
+impl<S: Subscriber> Layer<S> for RecentLogsLayer {
+    fn on_event(&self, event: &Event<'_>, _ctx: Context<'_, S>) {
+        // the more verbose levels are the greater ones
+        if *event.metadata().level() > self.max_level {
+            return;
+        }
+        let mut visitor = MessageVisitor::default();
+        event.record(&mut visitor);
+        let entry = LogEntry {
+            time: WebEnv::now(),
+            level: event.metadata().level().to_string(),
+            target: event.metadata().target().to_owned(),
+            message: visitor.message,
+        };
+        LOGS.with(|logs| {
+            let mut logs = logs.borrow_mut();
+            if logs.len() >= MAX_LOGS {
+                logs.pop_front();
+            }
+            logs.push_back(entry);
+        });
+    }
+}
+
+#[derive(Default)]
+struct MessageVisitor {
+    message: String,
+}
+
+impl Visit for MessageVisitor {
+    fn record_debug(&mut self, field: &Field, value: &dyn std::fmt::Debug) {
+        if !self.message.is_empty() {
+            self.message.push_str(", ");
+        }
+        let _ = match field.name() {
+            "message" => write!(self.message, "{value:?}"),
+            name => write!(self.message, "{name}={value:?}"),
+        };
+    }
+}
+
+/// The oldest first
+pub fn recent_logs() -> Vec<LogEntry> {
+    LOGS.with(|logs| logs.borrow().iter().cloned().collect())
+}
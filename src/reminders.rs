@@ -0,0 +1,144 @@
+use std::sync::RwLock;
+
+use chrono::{DateTime, Duration, Utc};
+use lazy_static::lazy_static;
+use serde::{Deserialize, Serialize};
+use url::Url;
+
+use stremio_core::types::{addon::ResourceRequest, resource::MetaItemPreview};
+
+pub const REMINDERS_STORAGE_KEY: &str = "event_reminders";
+/// Meta types of the events which can be reminded of
+pub const EVENT_TYPES: [&str; 2] = ["event", "sports"];
+/// How many minutes before the start of the event the reminder is due
+const REMINDER_LEAD: i64 = 15;
+/// How many hours after the start of the event the reminder is kept
+const REMINDER_RETENTION: i64 = 24;
+
+lazy_static! {
+    static ref REMINDERS: RwLock<Vec<Reminder>> = Default::default();
+}
+
+/// A reminder of an event, kept on the device only
+#[derive(Clone, PartialEq, Serialize, Deserialize, Debug)]
+#[serde(rename_all = "camelCase")]
+pub struct Reminder {
+    pub meta_item: MetaItemPreview,
+    /// The request the meta item was loaded with, used for the deep links
+    pub meta_request: ResourceRequest,
+    pub start: DateTime<Utc>,
+    pub remind_at: DateTime<Utc>,
+    /// Whether the reminder has been surfaced since it became due
+    pub notified: bool,
+    pub dismissed: bool,
+}
+
+impl Reminder {
+    /// `None` when the meta item is not an event or it has already started
+    pub fn new(
+        meta_item: MetaItemPreview,
+        meta_request: ResourceRequest,
+        now: DateTime<Utc>,
+    ) -> Option<Self> {
+        if !EVENT_TYPES.contains(&meta_item.r#type.as_str()) {
+            return None;
+        }
+        let start = meta_item.released.filter(|released| *released > now)?;
+        Some(Self {
+            meta_item,
+            meta_request,
+            start,
+            remind_at: start - Duration::minutes(REMINDER_LEAD),
+            notified: false,
+            dismissed: false,
+        })
+    }
+    pub fn is_due(&self, now: DateTime<Utc>) -> bool {
+        !self.dismissed && self.remind_at <= now
+    }
+    /// Due and surfaced by the background task, until it's dismissed
+    pub fn is_notified(&self) -> bool {
+        !self.dismissed && self.notified
+    }
+    pub fn push_payload(&self) -> PushPayload<'_> {
+        PushPayload {
+            title: &self.meta_item.name,
+            body: format!("Starts at {}", self.start.to_rfc3339()),
+            icon: self.meta_item.poster.as_ref(),
+            tag: &self.meta_item.id,
+            timestamp: self.start.timestamp_millis(),
+        }
+    }
+}
+
+/// Shown by the service worker, in the shape of the Web Push notification options
+#[derive(Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct PushPayload<'a> {
+    pub title: &'a String,
+    pub body: String,
+    pub icon: Option<&'a Url>,
+    pub tag: &'a String,
+    pub timestamp: i64,
+}
+
+#[derive(Deserialize, Debug)]
+#[serde(tag = "action", content = "args")]
+pub enum ReminderAction {
+    /// Reminds of the event loaded in the meta details
+    RemindMe(String),
+    Cancel(String),
+    Dismiss(String),
+}
+
+pub fn reminders() -> Vec<Reminder> {
+    REMINDERS.read().expect("reminders read failed").to_owned()
+}
+
+pub fn set_reminders(reminders: Vec<Reminder>) {
+    *REMINDERS.write().expect("reminders write failed") = reminders;
+}
+
+/// Adds the reminder and returns the updated reminders to be persisted
+pub fn add(reminder: Reminder) -> Vec<Reminder> {
+    let mut reminders = REMINDERS.write().expect("reminders write failed");
+    reminders.retain(|existing| existing.meta_item.id != reminder.meta_item.id);
+    reminders.push(reminder);
+    reminders.to_owned()
+}
+
+/// Removes the reminder and returns the updated reminders to be persisted
+pub fn remove(id: &str) -> Vec<Reminder> {
+    let mut reminders = REMINDERS.write().expect("reminders write failed");
+    reminders.retain(|reminder| reminder.meta_item.id != id);
+    reminders.to_owned()
+}
+
+/// Dismisses the due reminder and returns the updated reminders to be persisted
+pub fn dismiss(id: &str) -> Vec<Reminder> {
+    let mut reminders = REMINDERS.write().expect("reminders write failed");
+    if let Some(reminder) = reminders
+        .iter_mut()
+        .find(|reminder| reminder.meta_item.id == id)
+    {
+        reminder.dismissed = true;
+    }
+    reminders.to_owned()
+}
+
+/// Marks the reminders which became due as notified and drops the ones of past events,
+/// returns the updated reminders to be persisted if any of them changed.
+pub fn update_due(now: DateTime<Utc>) -> Option<Vec<Reminder>> {
+    let mut reminders = REMINDERS.write().expect("reminders write failed");
+    let count = reminders.len();
+    reminders.retain(|reminder| reminder.start + Duration::hours(REMINDER_RETENTION) > now);
+    let mut changed = reminders.len() != count;
+    for reminder in reminders
+        .iter_mut()
+        .filter(|reminder| reminder.is_due(now) && !reminder.notified)
+    {
+        reminder.notified = true;
+        changed = true;
+    }
+    changed.then(|| reminders.to_owned())
+}
@@ -0,0 +1,116 @@
+use std::collections::{HashMap, HashSet};
+use std::sync::RwLock;
+
+use chrono::{DateTime, Utc};
+use lazy_static::lazy_static;
+use serde::{Deserialize, Serialize};
+use url::Url;
+
+use crate::features::Experiment;
+use crate::model::billboard::BillboardConfig;
+use crate::onboarding::OnboardingConfig;
+
+pub const DISMISSED_ANNOUNCEMENTS_STORAGE_KEY: &str = "dismissed_announcements";
+
+lazy_static! {
+    static ref REMOTE_CONFIG: RwLock<RemoteConfig> = Default::default();
+    static ref DISMISSED_ANNOUNCEMENTS: RwLock<HashSet<String>> = Default::default();
+}
+
+/// Configuration fetched from the remote config endpoint on startup.
+#[derive(Clone, Default, Deserialize, Debug)]
+#[serde(rename_all = "camelCase")]
+pub struct RemoteConfig {
+    #[serde(default)]
+    pub announcements: Vec<Announcement>,
+    /// Overrides for the locally defined feature flags
+    #[serde(default)]
+    pub features: HashMap<String, bool>,
+    #[serde(default)]
+    pub experiments: Vec<Experiment>,
+    #[serde(default)]
+    pub onboarding: OnboardingConfig,
+    #[serde(default)]
+    pub billboard: BillboardConfig,
+}
+
+#[derive(Clone, Deserialize, Serialize, Debug)]
+#[serde(rename_all = "camelCase")]
+pub struct Announcement {
+    pub id: String,
+    /// e.g. `maintenance`, `promo`, `info`
+    pub kind: String,
+    pub title: String,
+    #[serde(default)]
+    pub message: Option<String>,
+    #[serde(default)]
+    pub url: Option<Url>,
+    #[serde(default = "default_dismissible")]
+    pub dismissible: bool,
+    #[serde(default)]
+    pub starts_at: Option<DateTime<Utc>>,
+    #[serde(default)]
+    pub ends_at: Option<DateTime<Utc>>,
+}
+
+impl Announcement {
+    pub fn is_active(&self, now: DateTime<Utc>) -> bool {
+        self.starts_at.map_or(true, |starts_at| starts_at <= now)
+            && self.ends_at.map_or(true, |ends_at| ends_at > now)
+    }
+}
+
+fn default_dismissible() -> bool {
+    true
+}
+
+pub fn set_remote_config(remote_config: RemoteConfig) {
+    *REMOTE_CONFIG.write().expect("remote config write failed") = remote_config;
+}
+
+pub fn set_dismissed_announcements(ids: HashSet<String>) {
+    *DISMISSED_ANNOUNCEMENTS
+        .write()
+        .expect("dismissed announcements write failed") = ids;
+}
+
+/// Marks the announcement as dismissed and returns the updated set of ids to be persisted.
+pub fn dismiss_announcement(id: String) -> HashSet<String> {
+    let mut dismissed = DISMISSED_ANNOUNCEMENTS
+        .write()
+        .expect("dismissed announcements write failed");
+    dismissed.insert(id);
+    dismissed.to_owned()
+}
+
+pub fn onboarding_config() -> OnboardingConfig {
+    REMOTE_CONFIG
+        .read()
+        .expect("remote config read failed")
+        .onboarding
+        .to_owned()
+}
+
+pub fn billboard_config() -> BillboardConfig {
+    REMOTE_CONFIG
+        .read()
+        .expect("remote config read failed")
+        .billboard
+        .to_owned()
+}
+
+/// Announcements which are currently active and were not dismissed by the user.
+pub fn active_announcements(now: DateTime<Utc>) -> Vec<Announcement> {
+    let dismissed = DISMISSED_ANNOUNCEMENTS
+        .read()
+        .expect("dismissed announcements read failed");
+    REMOTE_CONFIG
+        .read()
+        .expect("remote config read failed")
+        .announcements
+        .iter()
+        .filter(|announcement| announcement.is_active(now))
+        .filter(|announcement| !announcement.dismissible || !dismissed.contains(&announcement.id))
+        .cloned()
+        .collect()
+}
@@ -0,0 +1,96 @@
+use std::cell::{Cell, RefCell};
+
+use stremio_core::{
+    constants::ADDON_MANIFEST_PATH, models::common::Loadable, runtime::EnvError,
+    types::addon::ResourceRequest,
+};
+use tracing::{error, trace};
+use url::Url;
+
+use crate::env::WebEnv;
+
+/// Number of the failed requests the ids are kept for
+const MAX_FAILED_REQUESTS: usize = 100;
+const REDACTED: &str = "[redacted]";
+
+thread_local! {
+    /// Distinguishes the requests of the different sessions in the logs
+    static SESSION_ID: RefCell<Option<String>> = RefCell::new(None);
+    static NEXT_REQUEST: Cell<u64> = Cell::new(1);
+    /// The urls of the failed requests along with the id of their last attempt
+    static FAILED_REQUESTS: RefCell<Vec<(String, String)>> = RefCell::new(vec![]);
+}
+
+/// Assigns an id to a request about to be sent, e.g. `3f9a1c2e-42`
+pub fn start(method: &str, url: &str) -> String {
+    let session_id = SESSION_ID.with(|session_id| {
+        session_id
+            .borrow_mut()
+            .get_or_insert_with(|| hex::encode(WebEnv::random_buffer(4)))
+            .to_owned()
+    });
+    let request = NEXT_REQUEST.with(|next_request| next_request.replace(next_request.get() + 1));
+    let request_id = format!("{session_id}-{request}");
+    trace!(
+        request_id = request_id.as_str(),
+        method,
+        url = redact_url(url).as_str(),
+        "Request started"
+    );
+    request_id
+}
+
+/// Logs the failed request, its id is serialized along with the error of the resource
+/// so the users can report it
+pub fn fail(request_id: &str, url: &str, error: EnvError) -> EnvError {
+    error!(
+        request_id,
+        url = redact_url(url).as_str(),
+        "Request failed: {}",
+        error.message()
+    );
+    FAILED_REQUESTS.with(|failed_requests| {
+        let mut failed_requests = failed_requests.borrow_mut();
+        failed_requests.retain(|(failed_url, _)| failed_url != url);
+        if failed_requests.len() >= MAX_FAILED_REQUESTS {
+            failed_requests.remove(0);
+        }
+        failed_requests.push((url.to_owned(), request_id.to_owned()));
+    });
+    error
+}
+
+/// Id of the last request of the resource, when the resource failed to load
+pub fn failed_request_id<T, E>(
+    request: &ResourceRequest,
+    content: Option<&Loadable<T, E>>,
+) -> Option<String> {
+    if !matches!(content, Some(Loadable::Err(_))) {
+        return None;
+    }
+    // the url requested by the http transport of the addon
+    let url = request
+        .base
+        .as_str()
+        .replace(ADDON_MANIFEST_PATH, &request.path.to_url_path());
+    FAILED_REQUESTS.with(|failed_requests| {
+        failed_requests
+            .borrow()
+            .iter()
+            .find(|(failed_url, _)| *failed_url == url)
+            .map(|(_, request_id)| request_id.to_owned())
+    })
+}
+
+/// The origin of the url, its path and its query are left out of the logs
+/// as they carry the configuration of the addons, e.g. the keys of the debrid services
+pub fn redact_url(url: &str) -> String {
+    match Url::parse(url) {
+        Ok(url) if url.has_host() => format!("{}/{REDACTED}", url.origin().ascii_serialization()),
+        _ => REDACTED.to_owned(),
+    }
+}
+
+pub fn clear() {
+    FAILED_REQUESTS.with(|failed_requests| failed_requests.borrow_mut().clear());
+}
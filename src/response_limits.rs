@@ -0,0 +1,218 @@
+use std::{fmt, sync::RwLock};
+
+use lazy_static::lazy_static;
+use serde::de::{DeserializeSeed, Deserializer, IgnoredAny, MapAccess, SeqAccess, Visitor};
+use serde_json::{Map, Value};
+use wasm_bindgen::{JsCast, JsValue};
+
+use stremio_core::{runtime::EnvError, types::addon::ResourceRequest};
+
+use crate::{addon_console, fetch_limiter, web_settings};
+
+/// Lists of the addon responses which are truncated, the other oversized responses fail
+const TRUNCATED_KEYS: [&str; 3] = ["metas", "streams", "subtitles"];
+/// Number of responses the truncation is kept for
+const MAX_TRACKED_RESPONSES: usize = 100;
+/// Times the size limit the oversized responses are read up to, to be truncated.
+/// The larger ones fail without being downloaded.
+const MAX_READ_FACTOR: usize = 4;
+
+lazy_static! {
+    /// Urls of the last responses which were truncated
+    static ref TRUNCATED: RwLock<Vec<String>> = Default::default();
+}
+
+/// Bytes an addon response may take, `None` when the response is not limited.
+/// The responses of the API and of the streaming server are never limited.
+pub fn max_size(url: &str) -> Option<usize> {
+    let settings = web_settings::web_settings().network;
+    (settings.max_response_size > 0 && fetch_limiter::is_limited(url))
+        .then(|| settings.max_response_size as usize * 1024)
+}
+
+/// Bytes of a response with the limit which are read, for its list to be truncated
+pub fn max_read_size(max_size: usize) -> usize {
+    max_size.saturating_mul(MAX_READ_FACTOR)
+}
+
+pub fn too_large(max_size: usize) -> EnvError {
+    EnvError::Fetch(format!(
+        "The response exceeds the limit of {} KB",
+        max_size / 1024
+    ))
+}
+
+/// Parses the response, an oversized one keeps only the first items of its list
+pub fn parse(url: &str, text: String, max_size: usize) -> Result<JsValue, EnvError> {
+    if text.len() <= max_size {
+        set_truncated(url, false);
+        return js_sys::JSON::parse(&text).map_err(|error| {
+            EnvError::Fetch(
+                error
+                    .dyn_into::<js_sys::Error>()
+                    .map(|error| String::from(error.message()))
+                    .unwrap_or_else(|_| "Invalid JSON".to_owned()),
+            )
+        });
+    }
+    let max_items = web_settings::web_settings().network.max_truncated_items as usize;
+    let (response, total) =
+        truncate(&text, max_items).map_err(|error| EnvError::Fetch(error.to_string()))?;
+    let total = total.ok_or_else(|| too_large(max_size))?;
+    set_truncated(url, total > max_items);
+    Ok(JsValue::from_serde(&response).unwrap())
+}
+
+/// Whether only the first items of the last response to the request were kept
+pub fn is_truncated(request: &ResourceRequest) -> bool {
+    addon_console::validate(request, &[]).map_or(false, |url| {
+        TRUNCATED
+            .read()
+            .expect("response limits read failed")
+            .iter()
+            .any(|truncated_url| truncated_url == url.as_str())
+    })
+}
+
+pub fn set_truncated(url: &str, is_truncated: bool) {
+    let mut truncated = TRUNCATED.write().expect("response limits write failed");
+    truncated.retain(|truncated_url| truncated_url != url);
+    if is_truncated {
+        if truncated.len() >= MAX_TRACKED_RESPONSES {
+            truncated.remove(0);
+        }
+        truncated.push(url.to_owned());
+    }
+}
+
+/// The response with only the first items of its list, along with the number of all of them.
+/// `None` for a response without a list.
+fn truncate(
+    text: &str,
+    max_items: usize,
+) -> serde_json::Result<(Map<String, Value>, Option<usize>)> {
+    let mut deserializer = serde_json::Deserializer::from_str(text);
+    let response = TruncatedResponse { max_items }.deserialize(&mut deserializer)?;
+    deserializer.end()?;
+    Ok(response)
+}
+
+/// Deserializes the response object, only the first items of the list are kept in memory.
+/// Resolves to the response along with the number of items of the list, `None` without a list.
+struct TruncatedResponse {
+    max_items: usize,
+}
+
+impl<'de> DeserializeSeed<'de> for TruncatedResponse {
+    type Value = (Map<String, Value>, Option<usize>);
+    fn deserialize<D: Deserializer<'de>>(self, deserializer: D) -> Result<Self::Value, D::Error> {
+        deserializer.deserialize_map(self)
+    }
+}
+
+impl<'de> Visitor<'de> for TruncatedResponse {
+    type Value = (Map<String, Value>, Option<usize>);
+    fn expecting(&self, formatter: &mut fmt::Formatter) -> fmt::Result {
+        formatter.write_str("an addon response")
+    }
+    fn visit_map<A: MapAccess<'de>>(self, mut map: A) -> Result<Self::Value, A::Error> {
+        let mut response = Map::new();
+        let mut total = None;
+        while let Some(key) = map.next_key::<String>()? {
+            if total.is_none() && TRUNCATED_KEYS.contains(&key.as_str()) {
+                let (items, items_total) = map.next_value_seed(FirstItems {
+                    max_items: self.max_items,
+                })?;
+                response.insert(key, Value::Array(items));
+                total = Some(items_total);
+            } else {
+                response.insert(key, map.next_value()?);
+            }
+        }
+        Ok((response, total))
+    }
+}
+
+/// The first items of the list along with the number of all of them, the rest are skipped
+struct FirstItems {
+    max_items: usize,
+}
+
+impl<'de> DeserializeSeed<'de> for FirstItems {
+    type Value = (Vec<Value>, usize);
+    fn deserialize<D: Deserializer<'de>>(self, deserializer: D) -> Result<Self::Value, D::Error> {
+        deserializer.deserialize_seq(self)
+    }
+}
+
+impl<'de> Visitor<'de> for FirstItems {
+    type Value = (Vec<Value>, usize);
+    fn expecting(&self, formatter: &mut fmt::Formatter) -> fmt::Result {
+        formatter.write_str("a list of items")
+    }
+    fn visit_seq<A: SeqAccess<'de>>(self, mut seq: A) -> Result<Self::Value, A::Error> {
+        let mut items = vec![];
+        while items.len() < self.max_items {
+            match seq.next_element::<Value>()? {
+                Some(item) => items.push(item),
+                None => {
+                    let total = items.len();
+                    return Ok((items, total));
+                }
+            }
+        }
+        let mut total = items.len();
+        while seq.next_element::<IgnoredAny>()?.is_some() {
+            total += 1;
+        }
+        Ok((items, total))
+    }
+}
+
+pub fn clear() {
+    TRUNCATED
+        .write()
+        .expect("response limits write failed")
+        .clear();
+}
+
+#[cfg(test)]
+mod tests {
+    use serde_json::json;
+
+    use super::*;
+
+    #[test]
+    fn parse_truncates_the_list() {
+        let text = json!({
+            "metas": [{ "id": "1" }, { "id": "2" }, { "id": "3" }],
+            "cacheMaxAge": 3600,
+        })
+        .to_string();
+        let (response, total) = truncate(&text, 2).unwrap();
+        assert_eq!(total, Some(3));
+        assert_eq!(
+            Value::Object(response),
+            json!({
+                "metas": [{ "id": "1" }, { "id": "2" }],
+                "cacheMaxAge": 3600,
+            })
+        );
+    }
+
+    #[test]
+    fn parse_keeps_a_short_list() {
+        let text = json!({ "streams": [{ "url": "https://example.com/1.mp4" }] }).to_string();
+        let (response, total) = truncate(&text, 2).unwrap();
+        assert_eq!(total, Some(1));
+        assert_eq!(response["streams"].as_array().unwrap().len(), 1);
+    }
+
+    #[test]
+    fn parse_without_a_list() {
+        let text = json!({ "meta": { "id": "1", "videos": [] } }).to_string();
+        assert_eq!(truncate(&text, 2).unwrap().1, None);
+        assert!(truncate(r#"{ "metas": [{ "id": "1" }"#, 2).is_err());
+        assert!(truncate(r#"{ "metas": [] } []"#, 2).is_err());
+    }
+}
@@ -0,0 +1,154 @@
+use std::{rc::Rc, sync::RwLock};
+
+use futures::{channel::oneshot, Future, FutureExt};
+use lazy_static::lazy_static;
+use url::Url;
+
+use stremio_core::{
+    addon_transport::AddonTransport,
+    runtime::{EnvError, RuntimeEvent, TryEnvFuture},
+    types::addon::{Manifest, ResourcePath, ResourceRequest, ResourceResponse},
+};
+
+use crate::{
+    env::WebEnv,
+    model::WebModelField,
+    stremio_core_web::emit_event,
+    web_settings::{self, RetrySettings},
+};
+
+/// Number of requests the attempts are kept for
+const MAX_TRACKED_REQUESTS: usize = 100;
+const HTTP_STATUS_ERROR_PREFIX: &str = "Unexpected HTTP status code ";
+/// Messages of the failed fetches in the different browsers
+const NETWORK_ERRORS: [&str; 3] = [
+    "Failed to fetch",
+    "NetworkError when attempting to fetch resource.",
+    "Load failed",
+];
+
+lazy_static! {
+    static ref ATTEMPTS: RwLock<Vec<(ResourceRequest, u32)>> = Default::default();
+}
+
+/// Retries the requests which failed because of the network or of the addon being unavailable,
+/// waiting twice as long before every next attempt.
+pub struct RetryTransport {
+    transport_url: Url,
+    transport: Rc<dyn AddonTransport>,
+}
+
+impl RetryTransport {
+    pub fn new(transport_url: Url, transport: Box<dyn AddonTransport>) -> Self {
+        Self {
+            transport_url,
+            transport: Rc::from(transport),
+        }
+    }
+}
+
+impl AddonTransport for RetryTransport {
+    fn resource(&self, path: &ResourcePath) -> TryEnvFuture<ResourceResponse> {
+        let request = ResourceRequest::new(self.transport_url.to_owned(), path.to_owned());
+        let settings = web_settings::web_settings().retry;
+        let max_retries = max_retries(&settings, &path.resource);
+        let transport = self.transport.to_owned();
+        async move {
+            let mut attempt = 1;
+            loop {
+                set_attempts(&request, attempt);
+                match transport.resource(&request.path).await {
+                    Err(error) if attempt <= max_retries && is_transient(&error) => {
+                        delay(backoff(&settings, attempt)).await;
+                        attempt += 1;
+                        emit_event(&RuntimeEvent::NewState(fields(&request.path.resource)));
+                    }
+                    result => return result,
+                }
+            }
+        }
+        .boxed_local()
+    }
+    fn manifest(&self) -> TryEnvFuture<Manifest> {
+        self.transport.manifest()
+    }
+}
+
+/// Attempts made to load the request so far, `0` when it was not requested
+pub fn attempts(request: &ResourceRequest) -> u32 {
+    ATTEMPTS
+        .read()
+        .expect("attempts read failed")
+        .iter()
+        .find(|(attempts_request, _)| attempts_request == request)
+        .map(|(_, attempts)| *attempts)
+        .unwrap_or_default()
+}
+
+fn set_attempts(request: &ResourceRequest, attempts: u32) {
+    let mut tracked = ATTEMPTS.write().expect("attempts write failed");
+    tracked.retain(|(attempts_request, _)| attempts_request != request);
+    if tracked.len() >= MAX_TRACKED_REQUESTS {
+        tracked.remove(0);
+    }
+    tracked.push((request.to_owned(), attempts));
+}
+
+/// The requests for the other resources, e.g. of the addon catalogs, are not retried
+fn max_retries(settings: &RetrySettings, resource: &str) -> u32 {
+    match resource {
+        "catalog" => settings.catalogs,
+        "meta" | "stream" => settings.meta_details,
+        "subtitles" => settings.player,
+        _ => 0,
+    }
+}
+
+/// The models which show the attempts of the resource
+fn fields(resource: &str) -> Vec<WebModelField> {
+    match resource {
+        "catalog" => vec![
+            WebModelField::Board,
+            WebModelField::Discover,
+            WebModelField::Search,
+        ],
+        "meta" | "stream" => vec![WebModelField::MetaDetails],
+        "subtitles" => vec![WebModelField::Player],
+        _ => vec![],
+    }
+}
+
+/// The errors of the client, e.g. `404` or an invalid response, fail the same way when retried
+pub fn is_transient(error: &EnvError) -> bool {
+    match error {
+        EnvError::Fetch(message) => match message.strip_prefix(HTTP_STATUS_ERROR_PREFIX) {
+            Some(status) => status.starts_with('5') || status.starts_with("429"),
+            None => NETWORK_ERRORS
+                .iter()
+                .any(|network_error| message.starts_with(network_error)),
+        },
+        _ => false,
+    }
+}
+
+fn backoff(settings: &RetrySettings, attempt: u32) -> u32 {
+    settings
+        .initial_delay
+        .saturating_mul(2u32.saturating_pow(attempt - 1))
+        .min(settings.max_delay)
+}
+
+fn delay(timeout: u32) -> impl Future<Output = ()> {
+    let (sender, receiver) = oneshot::channel();
+    WebEnv::set_timeout(
+        move || {
+            let _ = sender.send(());
+        },
+        timeout as i32,
+    );
+    receiver.map(|_| ())
+}
+
+pub fn clear() {
+    ATTEMPTS.write().expect("attempts write failed").clear();
+}
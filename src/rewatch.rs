@@ -0,0 +1,214 @@
+use std::sync::RwLock;
+
+use chrono::{DateTime, Utc};
+use lazy_static::lazy_static;
+use serde::{Deserialize, Serialize};
+
+use stremio_core::{
+    runtime::msg::ActionCtx,
+    types::library::{LibraryBucket, LibraryItem},
+};
+
+pub const REWATCHES_STORAGE_KEY: &str = "rewatches";
+
+lazy_static! {
+    /// Series the user watches again from the start, their watch state is reset in the library
+    /// and the one from before is kept in the rewatch
+    static ref REWATCHES: RwLock<Vec<Rewatch>> = Default::default();
+}
+
+#[derive(Clone, PartialEq, Serialize, Deserialize, Debug)]
+#[serde(rename_all = "camelCase")]
+pub struct Rewatch {
+    pub id: String,
+    pub started_at: DateTime<Utc>,
+    pub history: WatchHistory,
+}
+
+/// The watch state of the item when the rewatch started, restored when it's stopped
+#[derive(Clone, PartialEq, Serialize, Deserialize, Debug)]
+#[serde(rename_all = "camelCase")]
+pub struct WatchHistory {
+    pub times_watched: u32,
+    pub last_watched: Option<DateTime<Utc>>,
+    /// The watched episodes as encoded in the library item
+    pub watched: Option<String>,
+}
+
+#[derive(Deserialize, Debug)]
+#[serde(tag = "action", content = "args")]
+pub enum RewatchAction {
+    Start(String),
+    Stop(String),
+}
+
+impl Rewatch {
+    /// The video played last since the rewatch started, `None` until one is played
+    pub fn last_video<'a>(&self, library_item: &'a LibraryItem) -> Option<&'a String> {
+        library_item.state.video_id.as_ref().filter(|_| {
+            library_item
+                .state
+                .last_watched
+                .map_or(false, |last_watched| last_watched > self.started_at)
+        })
+    }
+    /// When the item was watched last, the start of the rewatch counts as watching it
+    pub fn last_activity(&self, library_item: &LibraryItem) -> DateTime<Utc> {
+        library_item
+            .state
+            .last_watched
+            .map_or(self.started_at, |last_watched| {
+                last_watched.max(self.started_at)
+            })
+    }
+}
+
+pub fn set_rewatches(rewatches: Vec<Rewatch>) {
+    *REWATCHES.write().expect("rewatches write failed") = rewatches;
+}
+
+/// Applies the action and returns the updated rewatches to be persisted, along with the action
+/// which resets the watch state of the item or restores the one from before.
+/// `None` when nothing changed.
+pub fn update_rewatches(
+    action: RewatchAction,
+    library: &LibraryBucket,
+    now: DateTime<Utc>,
+) -> Option<(Vec<Rewatch>, Option<ActionCtx>)> {
+    let mut rewatches = REWATCHES.write().expect("rewatches write failed");
+    let action = match action {
+        RewatchAction::Start(id) => {
+            if rewatches.iter().any(|rewatch| rewatch.id == id) {
+                return None;
+            }
+            let library_item = library
+                .items
+                .get(&id)
+                .filter(|library_item| !library_item.removed)?;
+            rewatches.push(Rewatch {
+                id,
+                started_at: now,
+                history: WatchHistory {
+                    times_watched: library_item.state.times_watched,
+                    last_watched: library_item.state.last_watched,
+                    watched: library_item
+                        .state
+                        .watched
+                        .as_ref()
+                        .map(|watched| watched.to_string()),
+                },
+            });
+            is_watched(library_item).then(|| ActionCtx::LibraryItemMarkAsWatched {
+                id: library_item.id.to_owned(),
+                is_watched: false,
+            })
+        }
+        RewatchAction::Stop(id) => {
+            let position = rewatches.iter().position(|rewatch| rewatch.id == id)?;
+            let rewatch = rewatches.remove(position);
+            // the episodes watched meanwhile are kept, the item is watched again as it was before
+            library
+                .items
+                .get(&id)
+                .filter(|library_item| {
+                    !library_item.removed
+                        && !is_watched(library_item)
+                        && rewatch.history.times_watched > 0
+                })
+                .map(|_| ActionCtx::LibraryItemMarkAsWatched {
+                    id,
+                    is_watched: true,
+                })
+        }
+    };
+    Some((rewatches.to_owned(), action))
+}
+
+pub fn rewatches() -> Vec<Rewatch> {
+    REWATCHES.read().expect("rewatches read failed").to_owned()
+}
+
+pub fn rewatch(id: &str) -> Option<Rewatch> {
+    REWATCHES
+        .read()
+        .expect("rewatches read failed")
+        .iter()
+        .find(|rewatch| rewatch.id == id)
+        .cloned()
+}
+
+fn is_watched(library_item: &LibraryItem) -> bool {
+    library_item.state.times_watched > 0 || library_item.state.flagged_watched > 0
+}
+
+#[cfg(test)]
+mod tests {
+    use chrono::TimeZone;
+    use serde_json::json;
+
+    use super::*;
+
+    fn library(times_watched: u32) -> LibraryBucket {
+        let library_item = serde_json::from_value::<LibraryItem>(json!({
+            "_id": "tt0903747",
+            "name": "Breaking Bad",
+            "type": "series",
+            "poster": null,
+            "posterShape": "poster",
+            "removed": false,
+            "temp": false,
+            "_ctime": "2024-01-01T00:00:00Z",
+            "_mtime": "2024-01-01T00:00:00Z",
+            "state": {
+                "lastWatched": null,
+                "timeWatched": 0,
+                "timeOffset": 0,
+                "overallTimeWatched": 0,
+                "timesWatched": times_watched,
+                "flaggedWatched": 0,
+                "duration": 0,
+                "video_id": null,
+                "watched": null,
+                "noNotif": false,
+            },
+            "behaviorHints": {
+                "defaultVideoId": null,
+                "featuredVideoId": null,
+                "hasScheduledVideos": false,
+            },
+        }))
+        .unwrap();
+        LibraryBucket::new(None, vec![library_item])
+    }
+
+    fn is_marked_as_watched(action: Option<ActionCtx>, watched: bool) -> bool {
+        matches!(
+            action,
+            Some(ActionCtx::LibraryItemMarkAsWatched { is_watched, .. }) if is_watched == watched
+        )
+    }
+
+    #[test]
+    fn reset_and_restore_the_watch_state() {
+        set_rewatches(vec![]);
+        let now = Utc.with_ymd_and_hms(2024, 2, 1, 0, 0, 0).unwrap();
+        let id = "tt0903747".to_owned();
+        let (rewatches, action) =
+            update_rewatches(RewatchAction::Start(id.to_owned()), &library(2), now).unwrap();
+        assert_eq!(rewatches[0].history.times_watched, 2);
+        assert!(is_marked_as_watched(action, false));
+        assert!(update_rewatches(RewatchAction::Start(id.to_owned()), &library(0), now).is_none());
+        let (rewatches, action) =
+            update_rewatches(RewatchAction::Stop(id.to_owned()), &library(0), now).unwrap();
+        assert!(rewatches.is_empty());
+        assert!(is_marked_as_watched(action, true));
+        assert!(update_rewatches(RewatchAction::Stop(id.to_owned()), &library(0), now).is_none());
+        // nothing to reset
+        let (_, action) =
+            update_rewatches(RewatchAction::Start(id.to_owned()), &library(0), now).unwrap();
+        assert!(action.is_none());
+        // watched again meanwhile
+        let (_, action) = update_rewatches(RewatchAction::Stop(id), &library(1), now).unwrap();
+        assert!(action.is_none());
+    }
+}
@@ -0,0 +1,240 @@
+use std::sync::RwLock;
+
+use futures::{future, FutureExt, TryFutureExt};
+use http::Request;
+use lazy_static::lazy_static;
+use serde::Serialize;
+use serde_json::Value;
+use url::Url;
+
+use stremio_core::{
+    addon_transport::AddonTransport,
+    constants::ADDON_MANIFEST_PATH,
+    runtime::{Env, EnvError, TryEnvFuture},
+    types::addon::{Manifest, ResourcePath, ResourceRequest, ResourceResponse},
+};
+
+use crate::env::WebEnv;
+
+/// Maximum number of responses for which the warnings are kept
+const MAX_VALIDATED: usize = 50;
+const POSTER_SHAPES: [&str; 3] = ["square", "poster", "landscape"];
+const STREAM_SOURCES: [&str; 5] = ["url", "ytId", "infoHash", "externalUrl", "playerFrameUrl"];
+
+lazy_static! {
+    static ref WARNINGS: RwLock<Vec<(ResourceRequest, Vec<SchemaWarning>)>> = Default::default();
+}
+
+/// A schema violation found in an addon response, `path` is a JSON pointer to the offending value
+#[derive(Clone, Serialize, Debug)]
+#[serde(rename_all = "camelCase")]
+pub struct SchemaWarning {
+    pub path: String,
+    pub message: String,
+}
+
+/// Fetches the resources as plain JSON and validates them before handing them to core,
+/// so the violations are collected even when they don't fail the load.
+pub struct ValidatingTransport {
+    transport_url: Url,
+    transport: Box<dyn AddonTransport>,
+}
+
+impl ValidatingTransport {
+    pub fn new(transport_url: Url, transport: Box<dyn AddonTransport>) -> Self {
+        Self {
+            transport_url,
+            transport,
+        }
+    }
+}
+
+impl AddonTransport for ValidatingTransport {
+    fn resource(&self, path: &ResourcePath) -> TryEnvFuture<ResourceResponse> {
+        // legacy addons are not validated
+        if !self.transport_url.path().ends_with(ADDON_MANIFEST_PATH) {
+            return self.transport.resource(path);
+        }
+        let url = self
+            .transport_url
+            .as_str()
+            .replace(ADDON_MANIFEST_PATH, &path.to_url_path());
+        let request = Request::get(url).body(()).expect("request builder failed");
+        let resource_request = ResourceRequest::new(self.transport_url.to_owned(), path.to_owned());
+        WebEnv::fetch::<_, Value>(request)
+            .and_then(move |response| {
+                set_warnings(
+                    resource_request.to_owned(),
+                    validate(&resource_request.path.resource, &response),
+                );
+                future::ready(serde_json::from_value(response).map_err(EnvError::from))
+            })
+            .boxed_local()
+    }
+    fn manifest(&self) -> TryEnvFuture<Manifest> {
+        self.transport.manifest()
+    }
+}
+
+pub fn warnings(request: &ResourceRequest) -> Vec<SchemaWarning> {
+    WARNINGS
+        .read()
+        .expect("schema warnings read failed")
+        .iter()
+        .find(|(validated_request, _)| validated_request == request)
+        .map(|(_, warnings)| warnings.to_owned())
+        .unwrap_or_default()
+}
+
+pub fn len() -> usize {
+    WARNINGS.read().expect("schema warnings read failed").len()
+}
+
+pub fn clear() {
+    WARNINGS
+        .write()
+        .expect("schema warnings write failed")
+        .clear();
+}
+
+fn set_warnings(request: ResourceRequest, warnings: Vec<SchemaWarning>) {
+    let mut validated = WARNINGS.write().expect("schema warnings write failed");
+    validated.retain(|(validated_request, _)| *validated_request != request);
+    if validated.len() >= MAX_VALIDATED {
+        validated.remove(0);
+    }
+    validated.push((request, warnings));
+}
+
+/// Validates the response of the given resource, unknown resources are not validated
+pub fn validate(resource: &str, response: &Value) -> Vec<SchemaWarning> {
+    let mut warnings = vec![];
+    match resource {
+        "catalog" => validate_array(&mut warnings, response, "", "metas", validate_meta_preview),
+        "meta" => match response.get("meta") {
+            Some(meta) => {
+                validate_meta_preview(&mut warnings, meta, "/meta");
+                if meta.get("videos").is_some() {
+                    validate_array(&mut warnings, meta, "/meta", "videos", validate_video);
+                }
+            }
+            None => warn(&mut warnings, "/meta", "is required"),
+        },
+        "stream" => validate_array(&mut warnings, response, "", "streams", validate_stream),
+        "subtitles" => validate_array(&mut warnings, response, "", "subtitles", validate_subtitles),
+        _ => {}
+    };
+    warnings
+}
+
+fn validate_array(
+    warnings: &mut Vec<SchemaWarning>,
+    value: &Value,
+    path: &str,
+    field: &str,
+    validate_item: fn(&mut Vec<SchemaWarning>, &Value, &str),
+) {
+    let path = format!("{path}/{field}");
+    match value.get(field) {
+        Some(Value::Array(items)) => {
+            for (index, item) in items.iter().enumerate() {
+                validate_item(warnings, item, &format!("{path}/{index}"));
+            }
+        }
+        Some(_) => warn(warnings, &path, "must be an array"),
+        None => warn(warnings, &path, "is required"),
+    }
+}
+
+fn validate_meta_preview(warnings: &mut Vec<SchemaWarning>, meta: &Value, path: &str) {
+    for field in ["id", "type", "name"] {
+        required_string(warnings, meta, path, field);
+    }
+    for field in ["poster", "background", "logo", "description", "releaseInfo"] {
+        optional_string(warnings, meta, path, field);
+    }
+    if let Some(genres) = meta.get("genres") {
+        let is_valid = genres
+            .as_array()
+            .map_or(false, |genres| genres.iter().all(Value::is_string));
+        if !is_valid {
+            warn(
+                warnings,
+                &format!("{path}/genres"),
+                "must be an array of strings",
+            );
+        }
+    }
+    if let Some(poster_shape) = meta.get("posterShape") {
+        let is_valid = poster_shape
+            .as_str()
+            .map_or(false, |poster_shape| POSTER_SHAPES.contains(&poster_shape));
+        if !is_valid {
+            warn(
+                warnings,
+                &format!("{path}/posterShape"),
+                &format!("must be one of {}", POSTER_SHAPES.join(", ")),
+            );
+        }
+    }
+}
+
+fn validate_video(warnings: &mut Vec<SchemaWarning>, video: &Value, path: &str) {
+    required_string(warnings, video, path, "id");
+    for field in ["title", "name", "released", "thumbnail"] {
+        optional_string(warnings, video, path, field);
+    }
+}
+
+fn validate_stream(warnings: &mut Vec<SchemaWarning>, stream: &Value, path: &str) {
+    if !STREAM_SOURCES
+        .iter()
+        .any(|source| stream.get(source).is_some())
+    {
+        warn(
+            warnings,
+            path,
+            &format!("must have one of {}", STREAM_SOURCES.join(", ")),
+        );
+    }
+    for field in ["name", "title", "description"] {
+        optional_string(warnings, stream, path, field);
+    }
+    if let Some(file_idx) = stream.get("fileIdx") {
+        if !file_idx.is_u64() {
+            warn(
+                warnings,
+                &format!("{path}/fileIdx"),
+                "must be a positive integer",
+            );
+        }
+    }
+}
+
+fn validate_subtitles(warnings: &mut Vec<SchemaWarning>, subtitles: &Value, path: &str) {
+    for field in ["id", "url", "lang"] {
+        required_string(warnings, subtitles, path, field);
+    }
+}
+
+fn required_string(warnings: &mut Vec<SchemaWarning>, value: &Value, path: &str, field: &str) {
+    match value.get(field) {
+        Some(Value::String(_)) => {}
+        Some(_) => warn(warnings, &format!("{path}/{field}"), "must be a string"),
+        None => warn(warnings, &format!("{path}/{field}"), "is required"),
+    }
+}
+
+fn optional_string(warnings: &mut Vec<SchemaWarning>, value: &Value, path: &str, field: &str) {
+    match value.get(field) {
+        Some(Value::String(_)) | Some(Value::Null) | None => {}
+        Some(_) => warn(warnings, &format!("{path}/{field}"), "must be a string"),
+    }
+}
+
+fn warn(warnings: &mut Vec<SchemaWarning>, path: &str, message: &str) {
+    warnings.push(SchemaWarning {
+        path: path.to_owned(),
+        message: message.to_owned(),
+    });
+}
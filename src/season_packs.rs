@@ -0,0 +1,237 @@
+use std::{collections::HashMap, sync::RwLock};
+
+use futures::{channel::oneshot, future, FutureExt, TryFutureExt};
+use http::Request;
+use lazy_static::lazy_static;
+use regex::Regex;
+use serde::{Deserialize, Serialize};
+
+use stremio_core::{
+    models::common::Loadable,
+    runtime::{Env, EnvError, TryEnvFuture},
+    types::resource::{Stream, StreamSource},
+};
+
+use crate::env::WebEnv;
+
+const VIDEO_EXTENSIONS: [&str; 8] = ["mkv", "mp4", "avi", "m4v", "webm", "mov", "ts", "wmv"];
+/// Milliseconds to wait for the metadata of the torrent, e.g. when it has no peers
+const EXPAND_TIMEOUT: i32 = 30 * 1000;
+/// Peers the streaming server looks for, the same as for the playback
+const MIN_PEERS: u32 = 40;
+const MAX_PEERS: u32 = 200;
+
+lazy_static! {
+    /// Files of the expanded torrents, by info hash
+    static ref PACKS: RwLock<HashMap<String, Loadable<Vec<PackFile>, String>>> =
+        Default::default();
+    /// `S01E02`, `s1.e2` or `1x02`
+    static ref EPISODE_REGEX: Regex =
+        Regex::new(r"(?i)(?:s(\d{1,2})[ ._-]?e(\d{1,3})|\b(\d{1,2})x(\d{2,3})\b)").unwrap();
+}
+
+/// A video file of a torrent, as listed by the streaming server
+#[derive(Clone, Serialize, Debug)]
+#[serde(rename_all = "camelCase")]
+pub struct PackFile {
+    pub file_idx: u16,
+    pub name: String,
+    pub size: u64,
+    /// Parsed from the name of the file, `None` when it doesn't look like an episode
+    pub season: Option<u32>,
+    pub episode: Option<u32>,
+}
+
+#[derive(Default, Deserialize)]
+#[serde(default)]
+struct TorrentFile {
+    name: String,
+    length: u64,
+}
+
+#[derive(Default, Deserialize)]
+#[serde(default)]
+struct TorrentStats {
+    files: Vec<TorrentFile>,
+}
+
+#[derive(Serialize)]
+#[serde(rename_all = "camelCase")]
+struct CreateTorrent {
+    torrent: TorrentInfoHash,
+    peer_search: PeerSearch,
+}
+
+#[derive(Serialize)]
+#[serde(rename_all = "camelCase")]
+struct PeerSearch {
+    /// The DHT along with the trackers of the stream
+    sources: Vec<String>,
+    min: u32,
+    max: u32,
+}
+
+#[derive(Serialize)]
+#[serde(rename_all = "camelCase")]
+struct TorrentInfoHash {
+    info_hash: String,
+}
+
+/// Info hash of the torrent streams, the ones which can be expanded
+pub fn info_hash(stream: &Stream) -> Option<String> {
+    match &stream.source {
+        StreamSource::Torrent { info_hash, .. } => Some(hex::encode(info_hash)),
+        _ => None,
+    }
+}
+
+/// Files of the torrent of the stream, `None` until it's expanded
+pub fn files(stream: &Stream) -> Option<Loadable<Vec<PackFile>, String>> {
+    let info_hash = info_hash(stream)?;
+    PACKS
+        .read()
+        .expect("season packs read failed")
+        .get(&info_hash)
+        .cloned()
+}
+
+/// `false` when the torrent is expanded already or it's being expanded,
+/// failed expansions are tried again.
+pub fn start_expanding(info_hash: &str) -> bool {
+    let mut packs = PACKS.write().expect("season packs write failed");
+    if packs.get(info_hash).map_or(false, |files| !files.is_err()) {
+        return false;
+    }
+    packs.insert(info_hash.to_owned(), Loadable::Loading);
+    true
+}
+
+pub fn set_files(info_hash: &str, result: Result<Vec<PackFile>, String>) {
+    PACKS.write().expect("season packs write failed").insert(
+        info_hash.to_owned(),
+        match result {
+            Ok(files) => Loadable::Ready(files),
+            Err(error) => Loadable::Err(error),
+        },
+    );
+}
+
+/// Adds the torrent of the stream to the streaming server, which responds with its files
+/// once it has the metadata. The torrent is removed from the server afterwards,
+/// unless `keep_engine` as it's played.
+pub fn fetch_files(stream: &Stream, keep_engine: bool) -> TryEnvFuture<Vec<PackFile>> {
+    let (info_hash, announce) = match &stream.source {
+        StreamSource::Torrent {
+            info_hash,
+            announce,
+            ..
+        } => (hex::encode(info_hash), announce.to_owned()),
+        _ => {
+            return future::err(EnvError::Fetch("Stream is not a torrent".to_owned())).boxed_local()
+        }
+    };
+    let (create_url, remove_url) = match WebEnv::streaming_server_url().and_then(|url| {
+        Some((
+            url.join(&format!("{info_hash}/create")).ok()?,
+            url.join(&format!("{info_hash}/remove")).ok()?,
+        ))
+    }) {
+        Some(urls) => urls,
+        None => {
+            return future::err(EnvError::Fetch(
+                "Streaming server is not available".to_owned(),
+            ))
+            .boxed_local()
+        }
+    };
+    let request = Request::post(create_url.as_str())
+        .body(CreateTorrent {
+            torrent: TorrentInfoHash {
+                info_hash: info_hash.to_owned(),
+            },
+            peer_search: PeerSearch {
+                sources: [vec![format!("dht:{info_hash}")], announce].concat(),
+                min: MIN_PEERS,
+                max: MAX_PEERS,
+            },
+        })
+        .expect("request builder failed");
+    let (sender, receiver) = oneshot::channel();
+    WebEnv::set_timeout(
+        move || {
+            let _ = sender.send(());
+        },
+        EXPAND_TIMEOUT,
+    );
+    let timeout = receiver.map(|_| {
+        Err(EnvError::Fetch(
+            "Timed out waiting for the files of the torrent".to_owned(),
+        ))
+    });
+    future::select(
+        WebEnv::fetch::<_, TorrentStats>(request),
+        timeout.boxed_local(),
+    )
+    .map(|result| result.factor_first().0)
+    .then(move |result| {
+        if keep_engine {
+            return future::ready(result).boxed_local();
+        }
+        // the files are listed, the engine would keep downloading the torrent otherwise
+        let request = Request::get(remove_url.as_str())
+            .body(())
+            .expect("request builder failed");
+        WebEnv::fetch::<_, serde_json::Value>(request)
+            .map(move |_| result)
+            .boxed_local()
+    })
+    .map_ok(|stats| {
+        stats
+            .files
+            .into_iter()
+            .enumerate()
+            .filter(|(_, file)| is_video(&file.name))
+            .filter_map(|(index, file)| {
+                let (season, episode) = episode(&file.name).unzip();
+                Some(PackFile {
+                    file_idx: u16::try_from(index).ok()?,
+                    name: file.name,
+                    size: file.length,
+                    season,
+                    episode,
+                })
+            })
+            .collect()
+    })
+    .boxed_local()
+}
+
+/// The stream of the torrent playing the given file
+pub fn file_stream(stream: &Stream, file: &PackFile) -> Stream {
+    let mut stream = stream.to_owned();
+    if let StreamSource::Torrent { file_idx, .. } = &mut stream.source {
+        *file_idx = Some(file.file_idx);
+    }
+    stream.behavior_hints.filename = Some(file.name.to_owned());
+    stream
+}
+
+pub fn clear() {
+    PACKS.write().expect("season packs write failed").clear();
+}
+
+fn is_video(name: &str) -> bool {
+    name.rsplit_once('.').map_or(false, |(_, extension)| {
+        VIDEO_EXTENSIONS.contains(&extension.to_lowercase().as_str())
+    })
+}
+
+fn episode(name: &str) -> Option<(u32, u32)> {
+    let captures = EPISODE_REGEX.captures(name)?;
+    let season = captures.get(1).or_else(|| captures.get(3))?;
+    let episode = captures.get(2).or_else(|| captures.get(4))?;
+    Some((
+        season.as_str().parse().ok()?,
+        episode.as_str().parse().ok()?,
+    ))
+}
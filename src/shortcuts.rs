@@ -0,0 +1,152 @@
+use std::sync::RwLock;
+
+use lazy_static::lazy_static;
+use serde::{Deserialize, Serialize};
+use url::Url;
+
+use stremio_core::{
+    deep_links::{DiscoverDeepLinks, LibraryItemDeepLinks},
+    models::{continue_watching_preview::ContinueWatchingPreview, ctx::Ctx},
+    types::{addon::ResourceRequest, streams::StreamsItemKey},
+};
+
+use crate::{meta_overrides, model::deep_links_ext::DeepLinksExt};
+
+pub const PINNED_CATALOGS_STORAGE_KEY: &str = "pinned_catalogs";
+/// Items of Continue Watching added after the pinned catalogs
+const CONTINUE_WATCHING_SHORTCUTS: usize = 3;
+/// Browsers show a few shortcuts only, the rest are dropped
+const MAX_SHORTCUTS: usize = 10;
+
+lazy_static! {
+    static ref PINNED_CATALOGS: RwLock<Vec<PinnedCatalog>> = Default::default();
+    /// The shortcuts last emitted to the UI
+    static ref SHORTCUTS: RwLock<Vec<Shortcut>> = Default::default();
+}
+
+#[derive(Clone, PartialEq, Serialize, Deserialize, Debug)]
+#[serde(rename_all = "camelCase")]
+pub struct PinnedCatalog {
+    pub request: ResourceRequest,
+    pub name: String,
+}
+
+#[derive(Deserialize, Debug)]
+#[serde(tag = "action", content = "args")]
+pub enum PinnedCatalogsAction {
+    Pin(PinnedCatalog),
+    Unpin(ResourceRequest),
+}
+
+/// An entry of the `shortcuts` of the web app manifest
+#[derive(Clone, PartialEq, Serialize, Debug)]
+#[serde(rename_all = "camelCase")]
+pub struct Shortcut {
+    pub name: String,
+    /// Relative to the root of the app
+    pub url: String,
+    pub icons: Vec<ShortcutIcon>,
+}
+
+#[derive(Clone, PartialEq, Serialize, Debug)]
+#[serde(rename_all = "camelCase")]
+pub struct ShortcutIcon {
+    pub src: Url,
+}
+
+pub fn set_pinned_catalogs(pinned_catalogs: Vec<PinnedCatalog>) {
+    *PINNED_CATALOGS
+        .write()
+        .expect("pinned catalogs write failed") = pinned_catalogs;
+}
+
+/// Applies the action and returns the updated pinned catalogs to be persisted
+pub fn update_pinned_catalogs(action: PinnedCatalogsAction) -> Vec<PinnedCatalog> {
+    let mut pinned_catalogs = PINNED_CATALOGS
+        .write()
+        .expect("pinned catalogs write failed");
+    match action {
+        PinnedCatalogsAction::Pin(pinned_catalog) => {
+            pinned_catalogs.retain(|pinned| pinned.request != pinned_catalog.request);
+            pinned_catalogs.push(pinned_catalog);
+        }
+        PinnedCatalogsAction::Unpin(request) => {
+            pinned_catalogs.retain(|pinned| pinned.request != request)
+        }
+    };
+    pinned_catalogs.to_owned()
+}
+
+/// The pinned catalogs followed by the top items of Continue Watching
+pub fn shortcuts(continue_watching_preview: &ContinueWatchingPreview, ctx: &Ctx) -> Vec<Shortcut> {
+    let pinned_catalogs = PINNED_CATALOGS.read().expect("pinned catalogs read failed");
+    let catalog_shortcuts = pinned_catalogs.iter().map(|pinned| Shortcut {
+        name: pinned.name.to_owned(),
+        url: app_url(
+            &DiscoverDeepLinks::from(&pinned.request)
+                .into_web_deep_links()
+                .discover,
+        ),
+        icons: ctx
+            .profile
+            .addons
+            .iter()
+            .find(|addon| addon.transport_url == pinned.request.base)
+            .and_then(|addon| addon.manifest.logo.to_owned())
+            .map(|src| ShortcutIcon { src })
+            .into_iter()
+            .collect(),
+    });
+    let continue_watching_shortcuts = continue_watching_preview
+        .items
+        .iter()
+        .take(CONTINUE_WATCHING_SHORTCUTS)
+        .filter_map(|item| {
+            let library_item = &item.library_item;
+            let streams_item = library_item.state.video_id.to_owned().and_then(|video_id| {
+                ctx.streams.items.get(&StreamsItemKey {
+                    meta_id: library_item.id.to_owned(),
+                    video_id,
+                })
+            });
+            let deep_links =
+                LibraryItemDeepLinks::from((library_item, streams_item, &ctx.profile.settings))
+                    .into_web_deep_links();
+            // resumes the playback when the stream is known
+            let deep_link = deep_links
+                .player
+                .or(deep_links.meta_details_streams)
+                .or(deep_links.meta_details_videos)?;
+            Some(Shortcut {
+                name: meta_overrides::name(&library_item.id, &library_item.name).into_owned(),
+                url: app_url(&deep_link),
+                icons: meta_overrides::poster(&library_item.id, &library_item.poster)
+                    .into_owned()
+                    .map(|src| ShortcutIcon { src })
+                    .into_iter()
+                    .collect(),
+            })
+        });
+    catalog_shortcuts
+        .chain(continue_watching_shortcuts)
+        .take(MAX_SHORTCUTS)
+        .collect()
+}
+
+/// Returns the shortcuts when they differ from the ones last emitted
+pub fn changed_shortcuts(shortcuts: Vec<Shortcut>) -> Option<Vec<Shortcut>> {
+    let mut last_shortcuts = SHORTCUTS.write().expect("shortcuts write failed");
+    if *last_shortcuts == shortcuts {
+        return None;
+    }
+    *last_shortcuts = shortcuts.to_owned();
+    Some(shortcuts)
+}
+
+pub fn clear() {
+    SHORTCUTS.write().expect("shortcuts write failed").clear();
+}
+
+fn app_url(deep_link: &str) -> String {
+    format!("/{deep_link}")
+}
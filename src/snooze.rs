@@ -0,0 +1,142 @@
+use std::sync::RwLock;
+
+use chrono::{DateTime, Duration, Utc};
+use lazy_static::lazy_static;
+use serde::{Deserialize, Serialize};
+
+use stremio_core::types::notifications::NotificationsBucket;
+
+pub const SNOOZED_ITEMS_STORAGE_KEY: &str = "snoozed_items";
+
+lazy_static! {
+    /// Continue Watching items hidden by the user for a while, kept apart from their library items
+    /// so that snoozing never changes the library
+    static ref SNOOZES: RwLock<Vec<Snooze>> = Default::default();
+}
+
+#[derive(Clone, PartialEq, Serialize, Deserialize, Debug)]
+#[serde(rename_all = "camelCase")]
+pub struct Snooze {
+    pub id: String,
+    pub snoozed_at: DateTime<Utc>,
+    /// `None` when the item is snoozed until a new episode only
+    pub until: Option<DateTime<Utc>>,
+    /// The item shows up again once an episode released after the snooze is known
+    pub until_new_episode: bool,
+}
+
+#[derive(Deserialize, Debug)]
+#[serde(tag = "action", content = "args")]
+pub enum SnoozeAction {
+    #[serde(rename_all = "camelCase")]
+    Snooze {
+        id: String,
+        days: Option<u32>,
+        until_new_episode: bool,
+    },
+    Unsnooze(String),
+}
+
+impl Snooze {
+    pub fn is_active(&self, notifications: &NotificationsBucket, now: DateTime<Utc>) -> bool {
+        let has_new_episode = self.until_new_episode
+            && notifications.items.get(&self.id).map_or(false, |items| {
+                items
+                    .values()
+                    .any(|item| item.video_released > self.snoozed_at)
+            });
+        !has_new_episode && !self.is_expired(now)
+    }
+    fn is_expired(&self, now: DateTime<Utc>) -> bool {
+        self.until.map_or(false, |until| now >= until)
+    }
+}
+
+pub fn set_snoozes(snoozes: Vec<Snooze>) {
+    *SNOOZES.write().expect("snoozes write failed") = snoozes;
+}
+
+/// Applies the action and returns the updated snoozes to be persisted
+pub fn update_snoozes(action: SnoozeAction, now: DateTime<Utc>) -> Vec<Snooze> {
+    let mut snoozes = SNOOZES.write().expect("snoozes write failed");
+    match action {
+        SnoozeAction::Snooze {
+            id,
+            days,
+            until_new_episode,
+        } => {
+            snoozes.retain(|snooze| snooze.id != id);
+            snoozes.push(Snooze {
+                id,
+                snoozed_at: now,
+                until: days.map(|days| now + Duration::days(days.into())),
+                until_new_episode,
+            });
+        }
+        SnoozeAction::Unsnooze(id) => snoozes.retain(|snooze| snooze.id != id),
+    };
+    snoozes.to_owned()
+}
+
+/// The snoozes which still hide their items
+pub fn active_snoozes(notifications: &NotificationsBucket, now: DateTime<Utc>) -> Vec<Snooze> {
+    SNOOZES
+        .read()
+        .expect("snoozes read failed")
+        .iter()
+        .filter(|snooze| snooze.is_active(notifications, now))
+        .cloned()
+        .collect()
+}
+
+/// Drops the snoozes which ran out, returns the rest to be persisted when any were dropped
+pub fn expire(now: DateTime<Utc>) -> Option<Vec<Snooze>> {
+    let mut snoozes = SNOOZES.write().expect("snoozes write failed");
+    let len = snoozes.len();
+    snoozes.retain(|snooze| !snooze.is_expired(now));
+    (snoozes.len() != len).then(|| snoozes.to_owned())
+}
+
+pub fn clear() {
+    SNOOZES.write().expect("snoozes write failed").clear();
+}
+
+#[cfg(test)]
+mod tests {
+    use chrono::TimeZone;
+
+    use super::*;
+
+    #[test]
+    fn snoozed_item_comes_back_after_expiry() {
+        let now = Utc.with_ymd_and_hms(2024, 3, 1, 12, 0, 0).unwrap();
+        clear();
+        let snoozes = update_snoozes(
+            SnoozeAction::Snooze {
+                id: "tt0903747".to_owned(),
+                days: Some(3),
+                until_new_episode: false,
+            },
+            now,
+        );
+        assert_eq!(snoozes.len(), 1);
+        assert!(!snoozes[0].is_expired(now + Duration::days(2)));
+        assert_eq!(expire(now + Duration::days(2)), None);
+        assert!(snoozes[0].is_expired(now + Duration::days(3)));
+        assert_eq!(expire(now + Duration::days(3)), Some(vec![]));
+        // snoozing until a new episode only never runs out
+        update_snoozes(
+            SnoozeAction::Snooze {
+                id: "tt0903747".to_owned(),
+                days: None,
+                until_new_episode: true,
+            },
+            now,
+        );
+        assert_eq!(expire(now + Duration::days(365)), None);
+        assert_eq!(
+            update_snoozes(SnoozeAction::Unsnooze("tt0903747".to_owned()), now),
+            vec![]
+        );
+    }
+}
@@ -0,0 +1,113 @@
+use std::cell::RefCell;
+
+use wasm_bindgen::JsValue;
+
+use crate::model::WebModelField;
+
+struct Entry {
+    field: WebModelField,
+    /// Incremented every time the field's state changes
+    revision: u64,
+    /// The serialized state and the revision it was serialized at
+    state: Option<(u64, JsValue)>,
+}
+
+thread_local! {
+    static ENTRIES: RefCell<Vec<Entry>> = RefCell::new(vec![]);
+}
+
+/// Invalidates the serialized state of the given fields.
+///
+/// Most of the serializers depend on the `ctx` and the `streaming_server` as well,
+/// so a change in any of them invalidates every field.
+pub fn bump_revisions(fields: &[WebModelField]) {
+    let invalidate_all = fields
+        .iter()
+        .any(|field| *field == WebModelField::Ctx || *field == WebModelField::StreamingServer);
+    ENTRIES.with(|entries| {
+        let mut entries = entries.borrow_mut();
+        for entry in entries.iter_mut() {
+            if invalidate_all || fields.contains(&entry.field) {
+                entry.revision += 1;
+            }
+        }
+        for field in fields {
+            if !entries.iter().any(|entry| entry.field == *field) {
+                entries.push(Entry {
+                    field: field.to_owned(),
+                    revision: 1,
+                    state: None,
+                });
+            }
+        }
+    });
+}
+
+pub fn revision(field: &WebModelField) -> u64 {
+    ENTRIES.with(|entries| {
+        entries
+            .borrow()
+            .iter()
+            .find(|entry| entry.field == *field)
+            .map(|entry| entry.revision)
+            .unwrap_or_default()
+    })
+}
+
+/// Returns the previously serialized state if the field has not changed since,
+/// otherwise serializes it again and caches the result.
+pub fn get_or_serialize(field: &WebModelField, serialize: impl FnOnce() -> JsValue) -> JsValue {
+    let revision = revision(field);
+    let cached = ENTRIES.with(|entries| {
+        entries
+            .borrow()
+            .iter()
+            .find(|entry| entry.field == *field)
+            .and_then(|entry| entry.state.as_ref())
+            .filter(|(state_revision, _)| *state_revision == revision)
+            .map(|(_, state)| state.to_owned())
+    });
+    match cached {
+        Some(state) => state,
+        None => {
+            let state = serialize();
+            ENTRIES.with(|entries| {
+                let mut entries = entries.borrow_mut();
+                match entries.iter_mut().find(|entry| entry.field == *field) {
+                    Some(entry) => entry.state = Some((revision, state.to_owned())),
+                    None => entries.push(Entry {
+                        field: field.to_owned(),
+                        revision,
+                        state: Some((revision, state.to_owned())),
+                    }),
+                }
+            });
+            state
+        }
+    }
+}
+
+/// Length of the JSON of every serialized state, measured on demand
+pub fn sizes() -> Vec<(WebModelField, u32)> {
+    ENTRIES.with(|entries| {
+        entries
+            .borrow()
+            .iter()
+            .filter_map(|entry| {
+                let (_, state) = entry.state.as_ref()?;
+                let json = js_sys::JSON::stringify(state).ok()?;
+                Some((entry.field.to_owned(), json.length()))
+            })
+            .collect()
+    })
+}
+
+/// Drops all the serialized states, revisions are kept.
+pub fn clear() {
+    ENTRIES.with(|entries| {
+        entries
+            .borrow_mut()
+            .iter_mut()
+            .for_each(|entry| entry.state = None)
+    });
+}
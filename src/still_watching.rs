@@ -0,0 +1,108 @@
+use std::sync::RwLock;
+
+use boolinator::Boolinator;
+use lazy_static::lazy_static;
+use serde::{Deserialize, Serialize};
+
+use stremio_core::runtime::msg::{Action, ActionLoad, ActionPlayer, Event};
+
+use crate::web_settings;
+
+lazy_static! {
+    static ref STREAK: RwLock<Streak> = Default::default();
+}
+
+#[derive(Default)]
+struct Streak {
+    /// Episodes played one after another without the user touching the player
+    autoplays: u32,
+    /// The next episode is being loaded by the binge watching, not by the user
+    is_autoplay_pending: bool,
+    is_prompting: bool,
+    /// The load of the next video and its location hash, held back until the prompt is answered
+    held_load: Option<(Action, String)>,
+}
+
+#[derive(Deserialize, Debug)]
+#[serde(tag = "action")]
+pub enum StillWatchingAction {
+    /// The user is still there, the next video is loaded and the streak starts over
+    Continue,
+    /// Nobody is watching, the player is unloaded
+    Stop,
+}
+
+#[derive(Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct StillWatchingPrompt {
+    pub autoplays: u32,
+}
+
+/// Counts the episodes started by the binge watching, returns whether the prompt was shown
+pub fn on_event(event: &Event) -> bool {
+    let is_playing_next_video = match event {
+        Event::PlayerEnded {
+            is_playing_next_video,
+            ..
+        } => *is_playing_next_video,
+        _ => return false,
+    };
+    let mut streak = STREAK.write().expect("still watching write failed");
+    if !is_playing_next_video {
+        *streak = Streak::default();
+        return false;
+    }
+    streak.autoplays += 1;
+    streak.is_autoplay_pending = true;
+    let max_autoplays = web_settings::web_settings().player.still_watching_after;
+    let was_prompting = streak.is_prompting;
+    streak.is_prompting = max_autoplays > 0 && streak.autoplays >= max_autoplays;
+    streak.is_prompting != was_prompting
+}
+
+/// The streak starts over when the user seeks or picks a video. The pauses are not counted,
+/// as the player reports them for the end of a video and for the start of the next one too.
+/// Returns whether the action is held back, the load of the next video waits while the prompt
+/// is shown, as it's only answered by a `StillWatchingAction`.
+pub fn on_action(action: &Action, location_hash: &str) -> bool {
+    let mut streak = STREAK.write().expect("still watching write failed");
+    if streak.is_prompting {
+        if let Action::Load(ActionLoad::Player(_)) = action {
+            streak.held_load = Some((action.to_owned(), location_hash.to_owned()));
+            return true;
+        }
+        return false;
+    }
+    match action {
+        Action::Load(ActionLoad::Player(_)) if streak.is_autoplay_pending => {
+            streak.is_autoplay_pending = false;
+        }
+        Action::Load(ActionLoad::Player(_)) | Action::Player(ActionPlayer::Seek { .. }) => {
+            *streak = Streak::default()
+        }
+        _ => {}
+    }
+    false
+}
+
+/// The load of the next video which waited for the answer of the prompt
+pub fn take_held_load() -> Option<(Action, String)> {
+    STREAK
+        .write()
+        .expect("still watching write failed")
+        .held_load
+        .take()
+}
+
+/// Serialized in the player after the configured number of autoplays without an interaction
+pub fn still_watching_prompt() -> Option<StillWatchingPrompt> {
+    let streak = STREAK.read().expect("still watching read failed");
+    streak.is_prompting.as_some(StillWatchingPrompt {
+        autoplays: streak.autoplays,
+    })
+}
+
+/// Starts the streak over and hides the prompt, e.g. once the user answered it
+pub fn clear() {
+    *STREAK.write().expect("still watching write failed") = Streak::default();
+}
@@ -0,0 +1,146 @@
+use std::sync::RwLock;
+
+use chrono::{DateTime, Duration, Utc};
+use lazy_static::lazy_static;
+use serde::{Deserialize, Serialize};
+use url::Url;
+
+use stremio_core::models::player::Player;
+use stremio_core::types::resource::{Stream, StreamSource};
+
+pub const STREAM_HISTORY_STORAGE_KEY: &str = "stream_history";
+/// Meta items the last used stream is remembered for, the least recently played are dropped
+const MAX_PLAYED_STREAMS: usize = 500;
+/// Days the last used stream is remembered for since it was played
+const MAX_AGE_DAYS: i64 = 180;
+
+lazy_static! {
+    /// Most recently played first, at most one per meta item
+    static ref PLAYED_STREAMS: RwLock<Vec<PlayedStream>> = Default::default();
+    /// The stream in the player and its time offset when it was selected
+    static ref OBSERVED: RwLock<Option<(StreamKey, u64)>> = Default::default();
+}
+
+/// Identifies a stream between the responses of an addon, which may change the other fields
+#[derive(Clone, PartialEq, Eq, Serialize, Deserialize, Debug)]
+#[serde(tag = "type", content = "content")]
+pub enum StreamKey {
+    #[serde(rename_all = "camelCase")]
+    Torrent {
+        info_hash: String,
+        file_idx: Option<u16>,
+    },
+    Url(Url),
+    YouTube(String),
+}
+
+impl StreamKey {
+    /// `None` for the streams which are opened outside of the player
+    pub fn from_stream(stream: &Stream) -> Option<Self> {
+        match &stream.source {
+            StreamSource::Torrent {
+                info_hash,
+                file_idx,
+                ..
+            } => Some(StreamKey::Torrent {
+                info_hash: hex::encode(info_hash),
+                file_idx: file_idx.to_owned(),
+            }),
+            StreamSource::Url { url } => Some(StreamKey::Url(url.to_owned())),
+            StreamSource::YouTube { yt_id } => Some(StreamKey::YouTube(yt_id.to_owned())),
+            _ => None,
+        }
+    }
+}
+
+/// The last stream of a meta item which played successfully
+#[derive(Clone, PartialEq, Serialize, Deserialize, Debug)]
+#[serde(rename_all = "camelCase")]
+pub struct PlayedStream {
+    pub meta_id: String,
+    pub transport_url: Url,
+    pub stream: StreamKey,
+    pub played_at: DateTime<Utc>,
+}
+
+impl PlayedStream {
+    pub fn is_stream(&self, transport_url: &Url, stream: &Stream) -> bool {
+        &self.transport_url == transport_url
+            && StreamKey::from_stream(stream).as_ref() == Some(&self.stream)
+    }
+}
+
+pub fn set_played_streams(played_streams: Vec<PlayedStream>) {
+    *PLAYED_STREAMS.write().expect("played streams write failed") = played_streams;
+}
+
+pub fn last_used(meta_id: &str) -> Option<PlayedStream> {
+    PLAYED_STREAMS
+        .read()
+        .expect("played streams read failed")
+        .iter()
+        .find(|played_stream| played_stream.meta_id == meta_id)
+        .cloned()
+}
+
+/// The stream in the player once it played, `None` while it did not
+pub fn played_stream(player: &Player, now: DateTime<Utc>) -> Option<PlayedStream> {
+    let selected = player.selected.as_ref()?;
+    let stream = StreamKey::from_stream(&selected.stream)?;
+    let time_offset = player.library_item.as_ref()?.state.time_offset;
+    if !has_progressed(&stream, time_offset) {
+        return None;
+    }
+    Some(PlayedStream {
+        meta_id: selected.meta_request.as_ref()?.path.id.to_owned(),
+        transport_url: selected.stream_request.as_ref()?.base.to_owned(),
+        stream,
+        played_at: now,
+    })
+}
+
+/// Whether the stream played since it was selected, i.e. its time offset moved.
+/// The first offset seen for a stream is the one it was resumed from.
+fn has_progressed(stream: &StreamKey, time_offset: u64) -> bool {
+    let mut observed = OBSERVED.write().expect("observed stream write failed");
+    match observed.as_ref() {
+        Some((observed_stream, initial_offset)) if observed_stream == stream => {
+            *initial_offset != time_offset
+        }
+        _ => {
+            *observed = Some((stream.to_owned(), time_offset));
+            false
+        }
+    }
+}
+
+/// Drops the streams played too long ago, returns the streams to be persisted if any was dropped
+pub fn expire(now: DateTime<Utc>) -> Option<Vec<PlayedStream>> {
+    let mut played_streams = PLAYED_STREAMS.write().expect("played streams write failed");
+    let len = played_streams.len();
+    played_streams
+        .retain(|played_stream| now - played_stream.played_at < Duration::days(MAX_AGE_DAYS));
+    (played_streams.len() != len).then(|| played_streams.to_owned())
+}
+
+/// Records the stream as the last used of its meta item.
+/// Returns the streams to be persisted, `None` when it was the last used already.
+pub fn record(played_stream: PlayedStream) -> Option<Vec<PlayedStream>> {
+    let mut played_streams = PLAYED_STREAMS.write().expect("played streams write failed");
+    if played_streams.iter().any(|recorded| {
+        recorded.meta_id == played_stream.meta_id
+            && recorded.transport_url == played_stream.transport_url
+            && recorded.stream == played_stream.stream
+    }) {
+        return None;
+    }
+    played_streams.retain(|recorded| recorded.meta_id != played_stream.meta_id);
+    played_streams.insert(0, played_stream);
+    played_streams.truncate(MAX_PLAYED_STREAMS);
+    Some(played_streams.to_owned())
+}
+
+/// Drops the observed stream, the played streams are loaded again from the storage
+pub fn clear() {
+    *OBSERVED.write().expect("observed stream write failed") = None;
+}
@@ -0,0 +1,152 @@
+use std::{cell::Cell, sync::RwLock};
+
+use futures::FutureExt;
+use lazy_static::lazy_static;
+use url::Url;
+
+use stremio_core::{
+    addon_transport::AddonTransport,
+    runtime::{RuntimeEvent, TryEnvFuture},
+    types::addon::{Manifest, ResourcePath, ResourceRequest, ResourceResponse},
+};
+
+use crate::{env::WebEnv, model::WebModelField, stremio_core_web::emit_event, web_settings};
+
+lazy_static! {
+    static ref PENDING: RwLock<Vec<PendingStreams>> = Default::default();
+}
+
+thread_local! {
+    static NEXT_ID: Cell<u64> = Cell::new(1);
+}
+
+/// A streams request still loading
+struct PendingStreams {
+    id: u64,
+    request: ResourceRequest,
+    /// It's loading for longer than the timeout of the addon
+    timed_out: bool,
+    /// The user chose to wait for it
+    loaded_anyway: bool,
+    /// It was loaded past the timeout, its content is hidden until the user asks for it
+    loaded_late: bool,
+}
+
+/// Shows the streams requests which take longer than the timeout of the addon as timed out,
+/// so a slow addon doesn't hold back the streams of the others. The request is not aborted,
+/// its content is hidden until the user asks for it, instead of showing up late.
+pub struct StreamTimeoutTransport {
+    transport_url: Url,
+    transport: Box<dyn AddonTransport>,
+}
+
+impl StreamTimeoutTransport {
+    pub fn new(transport_url: Url, transport: Box<dyn AddonTransport>) -> Self {
+        Self {
+            transport_url,
+            transport,
+        }
+    }
+}
+
+impl AddonTransport for StreamTimeoutTransport {
+    fn resource(&self, path: &ResourcePath) -> TryEnvFuture<ResourceResponse> {
+        let timeout = web_settings::web_settings()
+            .streams
+            .timeout(&self.transport_url);
+        if path.resource != "stream" || timeout == 0 {
+            return self.transport.resource(path);
+        }
+        let id = start(ResourceRequest::new(
+            self.transport_url.to_owned(),
+            path.to_owned(),
+        ));
+        WebEnv::set_timeout(
+            move || {
+                if set_timed_out(id) {
+                    emit_event(&RuntimeEvent::NewState(vec![WebModelField::MetaDetails]));
+                }
+            },
+            timeout as i32 * 1000,
+        );
+        self.transport
+            .resource(path)
+            .inspect(move |_| finish(id))
+            .boxed_local()
+    }
+    fn manifest(&self) -> TryEnvFuture<Manifest> {
+        self.transport.manifest()
+    }
+}
+
+/// Whether the streams are loading past the timeout or loaded since then,
+/// unless the user chose to wait for them
+pub fn is_timed_out(request: &ResourceRequest) -> bool {
+    PENDING
+        .read()
+        .expect("pending streams read failed")
+        .iter()
+        .any(|pending| pending.request == *request && pending.timed_out && !pending.loaded_anyway)
+}
+
+/// Shows the timed out streams of the addon, the ones still loading are shown once loaded.
+/// Returns whether any of them was timed out.
+pub fn load_anyway(transport_url: &Url) -> bool {
+    let mut pending = PENDING.write().expect("pending streams write failed");
+    let mut is_timed_out = false;
+    for pending in pending
+        .iter_mut()
+        .filter(|pending| pending.request.base == *transport_url && pending.timed_out)
+    {
+        is_timed_out |= !pending.loaded_anyway;
+        pending.loaded_anyway = true;
+    }
+    pending.retain(|pending| !(pending.loaded_anyway && pending.loaded_late));
+    is_timed_out
+}
+
+pub fn clear() {
+    PENDING
+        .write()
+        .expect("pending streams write failed")
+        .clear();
+}
+
+fn start(request: ResourceRequest) -> u64 {
+    let id = NEXT_ID.with(|next_id| next_id.replace(next_id.get() + 1));
+    let mut pending = PENDING.write().expect("pending streams write failed");
+    // a request made again replaces the previous one
+    pending.retain(|pending| pending.request != request);
+    pending.push(PendingStreams {
+        id,
+        request,
+        timed_out: false,
+        loaded_anyway: false,
+        loaded_late: false,
+    });
+    id
+}
+
+/// The timed out requests are kept until the user asks for their content
+fn finish(id: u64) {
+    let mut pending = PENDING.write().expect("pending streams write failed");
+    if let Some(pending) = pending
+        .iter_mut()
+        .find(|pending| pending.id == id && pending.timed_out && !pending.loaded_anyway)
+    {
+        pending.loaded_late = true;
+        return;
+    }
+    pending.retain(|pending| pending.id != id);
+}
+
+/// Returns whether the request was still loading
+fn set_timed_out(id: u64) -> bool {
+    PENDING
+        .write()
+        .expect("pending streams write failed")
+        .iter_mut()
+        .find(|pending| pending.id == id && !pending.loaded_late)
+        .map(|pending| pending.timed_out = true)
+        .is_some()
+}
@@ -0,0 +1,255 @@
+use std::{
+    cell::RefCell,
+    mem,
+    rc::Rc,
+    sync::{Arc, RwLock},
+};
+
+use futures::FutureExt;
+use lazy_static::lazy_static;
+use url::Url;
+
+use stremio_core::{
+    addon_transport::AddonTransport,
+    constants::ADDON_MANIFEST_PATH,
+    runtime::{EnvError, RuntimeEvent, TryEnvFuture},
+    types::{
+        addon::{Manifest, ResourcePath, ResourceRequest, ResourceResponse},
+        resource::MetaItemPreview,
+    },
+};
+
+use crate::{env::WebEnv, model::WebModelField, response_limits, stremio_core_web::emit_event};
+
+/// Items parsed between the updates of the partial content,
+/// smaller catalogs are shown once they are fully loaded
+const CHUNK_SIZE: usize = 100;
+
+/// The items of a catalog parsed so far, shared with the serializers instead of copied
+pub type PartialCatalog = (ResourceRequest, Arc<Vec<MetaItemPreview>>);
+
+lazy_static! {
+    /// Items of the catalogs parsed so far, while the rest of the response is still downloading
+    static ref PARTIAL_CATALOGS: RwLock<Vec<PartialCatalog>> = Default::default();
+}
+
+/// Parses the catalogs progressively, every other request goes through the wrapped transport.
+/// The body is not kept, the response is made of the items parsed from it.
+pub struct StreamingCatalogTransport {
+    transport_url: Url,
+    transport: Box<dyn AddonTransport>,
+}
+
+impl StreamingCatalogTransport {
+    pub fn new(transport_url: Url, transport: Box<dyn AddonTransport>) -> Self {
+        Self {
+            transport_url,
+            transport,
+        }
+    }
+}
+
+impl AddonTransport for StreamingCatalogTransport {
+    fn resource(&self, path: &ResourcePath) -> TryEnvFuture<ResourceResponse> {
+        // the legacy addons are not served as plain JSON
+        if path.resource != "catalog" || !self.transport_url.path().ends_with(ADDON_MANIFEST_PATH) {
+            return self.transport.resource(path);
+        }
+        let url = match Url::parse(
+            &self
+                .transport_url
+                .as_str()
+                .replace(ADDON_MANIFEST_PATH, &path.to_url_path()),
+        ) {
+            Ok(url) => url,
+            Err(_) => return self.transport.resource(path),
+        };
+        let request = ResourceRequest::new(self.transport_url.to_owned(), path.to_owned());
+        let scanner = Rc::new(RefCell::new(MetasScanner::default()));
+        WebEnv::fetch_chunks(&url, {
+            let request = request.to_owned();
+            let scanner = scanner.to_owned();
+            move |chunk| {
+                let mut scanner = scanner.borrow_mut();
+                scanner.feed(chunk);
+                if scanner.items.len() >= CHUNK_SIZE {
+                    append_partial(&request, mem::take(&mut scanner.items));
+                    emit_partial_catalogs();
+                }
+            }
+        })
+        .map(move |result| {
+            let partial = remove_partial(&request);
+            if partial.is_some() {
+                emit_partial_catalogs();
+            }
+            result.and_then(|is_complete| {
+                // a catalog over the size limit keeps the items before the cut
+                let items = if is_complete {
+                    scanner.borrow_mut().finish()?
+                } else {
+                    scanner.borrow_mut().truncated()?
+                };
+                response_limits::set_truncated(url.as_str(), !is_complete);
+                let mut metas = partial
+                    .map(|partial| {
+                        Arc::try_unwrap(partial).unwrap_or_else(|partial| (*partial).to_owned())
+                    })
+                    .unwrap_or_default();
+                metas.extend(items);
+                Ok(ResourceResponse::Metas { metas })
+            })
+        })
+        .boxed_local()
+    }
+    fn manifest(&self) -> TryEnvFuture<Manifest> {
+        self.transport.manifest()
+    }
+}
+
+/// Items of the catalogs which are still loading, the ones with no items parsed yet are omitted
+pub fn partial_catalogs() -> Vec<PartialCatalog> {
+    PARTIAL_CATALOGS
+        .read()
+        .expect("partial catalogs read failed")
+        .to_owned()
+}
+
+/// Whether the content of the catalog is the items parsed so far, the rest are still loading
+pub fn is_partial(request: &ResourceRequest) -> bool {
+    PARTIAL_CATALOGS
+        .read()
+        .expect("partial catalogs read failed")
+        .iter()
+        .any(|(partial_request, _)| partial_request == request)
+}
+
+fn append_partial(request: &ResourceRequest, meta_items: Vec<MetaItemPreview>) {
+    let mut partial_catalogs = PARTIAL_CATALOGS
+        .write()
+        .expect("partial catalogs write failed");
+    match partial_catalogs
+        .iter_mut()
+        .find(|(partial_request, _)| partial_request == request)
+    {
+        // copied only when a serialization still holds the previous items
+        Some((_, partial)) => Arc::make_mut(partial).extend(meta_items),
+        None => partial_catalogs.push((request.to_owned(), Arc::new(meta_items))),
+    }
+}
+
+fn remove_partial(request: &ResourceRequest) -> Option<Arc<Vec<MetaItemPreview>>> {
+    let mut partial_catalogs = PARTIAL_CATALOGS
+        .write()
+        .expect("partial catalogs write failed");
+    let position = partial_catalogs
+        .iter()
+        .position(|(partial_request, _)| partial_request == request)?;
+    Some(partial_catalogs.remove(position).1)
+}
+
+fn emit_partial_catalogs() {
+    emit_event(&RuntimeEvent::NewState(vec![
+        WebModelField::Board,
+        WebModelField::Discover,
+        WebModelField::Search,
+    ]));
+}
+
+/// Finds the items of the `metas` array of a catalog response as its bytes arrive.
+/// The items which don't parse are skipped.
+#[derive(Default)]
+struct MetasScanner {
+    depth: usize,
+    in_string: bool,
+    escaped: bool,
+    /// The last string of the root object, the key of the array which follows it
+    key: Option<Vec<u8>>,
+    last_key: Vec<u8>,
+    /// Within the `metas` array
+    in_metas: bool,
+    /// The `metas` array was found in the response
+    has_metas: bool,
+    item: Vec<u8>,
+    /// Items parsed since the last partial content
+    items: Vec<MetaItemPreview>,
+}
+
+impl MetasScanner {
+    fn feed(&mut self, bytes: &[u8]) {
+        for byte in bytes {
+            if !self.item.is_empty() {
+                self.item.push(*byte);
+            }
+            if self.in_string {
+                if self.escaped {
+                    self.escaped = false;
+                } else if *byte == b'\\' {
+                    self.escaped = true;
+                } else if *byte == b'"' {
+                    self.in_string = false;
+                    if let Some(key) = self.key.take() {
+                        self.last_key = key;
+                    }
+                } else if let Some(key) = self.key.as_mut() {
+                    key.push(*byte);
+                }
+                continue;
+            }
+            match byte {
+                b'"' => {
+                    self.in_string = true;
+                    if self.depth == 1 {
+                        self.key = Some(vec![]);
+                    }
+                }
+                b'{' | b'[' => {
+                    if *byte == b'[' && self.depth == 1 && self.last_key == b"metas" {
+                        self.in_metas = true;
+                        self.has_metas = true;
+                    } else if *byte == b'{' && self.in_metas && self.depth == 2 {
+                        self.item = vec![*byte];
+                    }
+                    self.depth += 1;
+                }
+                b'}' | b']' => {
+                    self.depth = self.depth.saturating_sub(1);
+                    if self.in_metas && self.depth == 2 && !self.item.is_empty() {
+                        if let Ok(meta_item) = serde_json::from_slice(&self.item) {
+                            self.items.push(meta_item);
+                        }
+                        self.item.clear();
+                    } else if self.in_metas && self.depth == 1 {
+                        self.in_metas = false;
+                    }
+                }
+                _ => {}
+            }
+        }
+    }
+    /// The items not yet in the partial content, once the whole response was scanned
+    fn finish(&mut self) -> Result<Vec<MetaItemPreview>, EnvError> {
+        if !self.has_metas || self.depth != 0 || self.in_string {
+            return Err(EnvError::Serde(
+                "The catalog response is not an object with metas".to_owned(),
+            ));
+        }
+        Ok(mem::take(&mut self.items))
+    }
+    /// The items not yet in the partial content, once the response was cut off
+    fn truncated(&mut self) -> Result<Vec<MetaItemPreview>, EnvError> {
+        if !self.has_metas {
+            return Err(EnvError::Fetch(
+                "The catalog response exceeds the size limit".to_owned(),
+            ));
+        }
+        Ok(mem::take(&mut self.items))
+    }
+}
+
+pub fn clear() {
+    PARTIAL_CATALOGS
+        .write()
+        .expect("partial catalogs write failed")
+        .clear();
+}
@@ -0,0 +1,112 @@
+use std::sync::RwLock;
+
+use lazy_static::lazy_static;
+use serde::{Deserialize, Serialize};
+
+use stremio_core::models::common::Loadable;
+
+use crate::streaming_server_jobs::TorrentJob;
+
+lazy_static! {
+    static ref CACHE: RwLock<StreamingServerCache> = Default::default();
+}
+
+/// Data of a torrent kept in the cache of the streaming server
+#[derive(Clone, Serialize, Debug)]
+#[serde(rename_all = "camelCase")]
+pub struct CacheItem {
+    pub info_hash: String,
+    pub name: Option<String>,
+    pub size: u64,
+}
+
+#[derive(Clone, Serialize, Debug)]
+#[serde(rename_all = "camelCase")]
+pub struct CacheSize {
+    pub total_size: u64,
+    /// Empty when the server does not report the torrents it keeps
+    pub items: Vec<CacheItem>,
+}
+
+impl CacheSize {
+    pub fn from_jobs(jobs: &[TorrentJob]) -> Self {
+        let items = jobs
+            .iter()
+            .map(|job| CacheItem {
+                info_hash: job.info_hash.to_owned(),
+                name: job.name.to_owned(),
+                size: job.downloaded,
+            })
+            .collect::<Vec<_>>();
+        Self {
+            total_size: items.iter().map(|item| item.size).sum(),
+            items,
+        }
+    }
+}
+
+#[derive(Clone, Serialize, Debug)]
+#[serde(tag = "type", content = "content")]
+pub enum CacheClearing {
+    #[serde(rename_all = "camelCase")]
+    InProgress {
+        cleared: usize,
+        total: usize,
+    },
+    #[serde(rename_all = "camelCase")]
+    Done {
+        /// Number of the removed torrents. The server doesn't report the size they took,
+        /// the size of the cache left is queried again instead.
+        cleared: usize,
+    },
+    Failed(String),
+}
+
+#[derive(Clone, Default, Debug)]
+pub struct StreamingServerCache {
+    /// `None` until the size is queried by the UI
+    pub size: Option<Loadable<CacheSize, String>>,
+    pub clearing: Option<CacheClearing>,
+}
+
+#[derive(Deserialize, Debug)]
+#[serde(tag = "action", content = "args")]
+pub enum StreamingServerCacheAction {
+    QuerySize,
+    Clear,
+}
+
+pub fn cache() -> StreamingServerCache {
+    CACHE
+        .read()
+        .expect("streaming server cache read failed")
+        .to_owned()
+}
+
+pub fn is_clearing() -> bool {
+    matches!(
+        CACHE
+            .read()
+            .expect("streaming server cache read failed")
+            .clearing,
+        Some(CacheClearing::InProgress { .. })
+    )
+}
+
+pub fn set_size(size: Loadable<CacheSize, String>) {
+    CACHE
+        .write()
+        .expect("streaming server cache write failed")
+        .size = Some(size);
+}
+
+pub fn set_clearing(clearing: CacheClearing) {
+    CACHE
+        .write()
+        .expect("streaming server cache write failed")
+        .clearing = Some(clearing);
+}
+
+pub fn clear() {
+    *CACHE.write().expect("streaming server cache write failed") = Default::default();
+}
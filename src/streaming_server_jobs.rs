@@ -0,0 +1,136 @@
+use std::collections::HashMap;
+use std::sync::RwLock;
+
+use futures::{future, FutureExt, TryFutureExt};
+use http::Request;
+use lazy_static::lazy_static;
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+
+use stremio_core::{
+    models::common::Loadable,
+    runtime::{Env, EnvError, TryEnvFuture},
+};
+
+use crate::env::WebEnv;
+
+lazy_static! {
+    static ref JOBS: RwLock<StreamingServerJobs> = Default::default();
+}
+
+/// A torrent currently active on the streaming server, as reported by its `stats.json`
+#[derive(Clone, Default, Serialize, Deserialize, Debug)]
+#[serde(rename_all = "camelCase", default)]
+pub struct TorrentJob {
+    pub info_hash: String,
+    /// Not known until the metadata of the torrent is fetched
+    pub name: Option<String>,
+    pub peers: u64,
+    pub swarm_size: u64,
+    pub downloaded: u64,
+    pub uploaded: u64,
+    pub download_speed: f64,
+    pub upload_speed: f64,
+    pub stream_progress: Option<f64>,
+}
+
+#[derive(Clone, Default, Debug)]
+pub struct StreamingServerJobs {
+    /// `None` until the jobs are requested by the UI
+    pub jobs: Option<Loadable<Vec<TorrentJob>, String>>,
+    /// Info hashes of the jobs which are being removed
+    pub removing: Vec<String>,
+}
+
+#[derive(Deserialize, Debug)]
+#[serde(tag = "action", content = "args")]
+pub enum StreamingServerJobsAction {
+    /// Polled by the UI while the jobs are shown
+    Refresh,
+    Remove(String),
+    RemoveAll,
+}
+
+pub fn jobs() -> StreamingServerJobs {
+    JOBS.read()
+        .expect("streaming server jobs read failed")
+        .to_owned()
+}
+
+pub fn set_jobs(result: Result<Vec<TorrentJob>, String>) {
+    let mut jobs = JOBS.write().expect("streaming server jobs write failed");
+    jobs.jobs = Some(match result {
+        Ok(torrent_jobs) => Loadable::Ready(torrent_jobs),
+        Err(error) => Loadable::Err(error),
+    });
+}
+
+/// Marks the jobs as being removed, `None` for all of them
+pub fn start_removing(info_hash: Option<&str>) {
+    let mut jobs = JOBS.write().expect("streaming server jobs write failed");
+    let info_hashes = match info_hash {
+        Some(info_hash) => vec![info_hash.to_owned()],
+        None => match &jobs.jobs {
+            Some(Loadable::Ready(torrent_jobs)) => torrent_jobs
+                .iter()
+                .map(|job| job.info_hash.to_owned())
+                .collect(),
+            _ => vec![],
+        },
+    };
+    jobs.removing.extend(info_hashes);
+}
+
+pub fn finish_removing(info_hash: Option<&str>) {
+    let mut jobs = JOBS.write().expect("streaming server jobs write failed");
+    match info_hash {
+        Some(info_hash) => jobs.removing.retain(|removing| removing != info_hash),
+        None => jobs.removing.clear(),
+    };
+}
+
+pub fn fetch_jobs() -> TryEnvFuture<Vec<TorrentJob>> {
+    let url = match WebEnv::streaming_server_url() {
+        Some(url) => url,
+        None => return future::err(not_available_error()).boxed_local(),
+    };
+    let request = Request::get(url.join("stats.json").expect("url builder failed").as_str())
+        .body(())
+        .expect("request builder failed");
+    WebEnv::fetch::<_, HashMap<String, Option<TorrentJob>>>(request)
+        .map_ok(|stats| {
+            let mut jobs = stats
+                .into_iter()
+                .filter_map(|(info_hash, job)| job.map(|job| TorrentJob { info_hash, ..job }))
+                .collect::<Vec<_>>();
+            jobs.sort_by(|a, b| a.info_hash.cmp(&b.info_hash));
+            jobs
+        })
+        .boxed_local()
+}
+
+/// Stops the torrent and removes it from the server, `None` removes all of them
+pub fn remove_job(info_hash: Option<&str>) -> TryEnvFuture<()> {
+    let url = match WebEnv::streaming_server_url() {
+        Some(url) => url,
+        None => return future::err(not_available_error()).boxed_local(),
+    };
+    let path = match info_hash {
+        Some(info_hash) => format!("{info_hash}/remove"),
+        None => "removeAll".to_owned(),
+    };
+    let request = Request::get(url.join(&path).expect("url builder failed").as_str())
+        .body(())
+        .expect("request builder failed");
+    WebEnv::fetch::<_, Value>(request)
+        .map_ok(|_| ())
+        .boxed_local()
+}
+
+fn not_available_error() -> EnvError {
+    EnvError::Fetch("Streaming server is not available".to_owned())
+}
+
+pub fn clear() {
+    *JOBS.write().expect("streaming server jobs write failed") = Default::default();
+}
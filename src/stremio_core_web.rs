@@ -1,33 +1,206 @@
-use std::sync::RwLock;
+use std::{
+    cell::RefCell,
+    collections::{HashMap, HashSet},
+    sync::RwLock,
+};
 
+use chrono::Duration;
 use enclose::enclose;
-use futures::{future, FutureExt, StreamExt};
+use futures::{future, FutureExt, StreamExt, TryFutureExt};
+use itertools::Itertools;
 use lazy_static::lazy_static;
-use tracing::{info, Level};
-use tracing_wasm::WASMLayerConfigBuilder;
+use serde::Deserialize;
+use tracing::{error, info, Level};
+use tracing_subscriber::layer::SubscriberExt;
+use tracing_wasm::{WASMLayer, WASMLayerConfigBuilder};
+use url::Url;
 use wasm_bindgen::prelude::wasm_bindgen;
 use wasm_bindgen::JsValue;
 
 use stremio_core::{
+    addon_transport::AddonTransport,
     constants::{
-        LIBRARY_RECENT_STORAGE_KEY, LIBRARY_STORAGE_KEY, NOTIFICATIONS_STORAGE_KEY,
-        PROFILE_STORAGE_KEY, STREAMS_STORAGE_KEY,
+        LIBRARY_RECENT_STORAGE_KEY, LIBRARY_STORAGE_KEY, META_RESOURCE_NAME,
+        NOTIFICATIONS_STORAGE_KEY, PROFILE_STORAGE_KEY, STREAMS_STORAGE_KEY,
+    },
+    models::{catalog_with_filters::Selected as CatalogWithFiltersSelected, common::Loadable},
+    runtime::{
+        msg::{Action, ActionCtx, ActionLoad},
+        Env, EnvError, Runtime, RuntimeAction, RuntimeEvent, TryEnvFuture,
     },
-    models::common::Loadable,
-    runtime::{msg::Action, Env, EnvError, Runtime, RuntimeAction, RuntimeEvent},
     types::{
-        library::LibraryBucket, notifications::NotificationsBucket, profile::Profile,
-        resource::Stream, streams::StreamsBucket,
+        addon::{Descriptor, ResourcePath, ResourceRequest, ResourceResponse},
+        library::{LibraryBucket, LibraryBucketRef, LibraryItem},
+        notifications::NotificationsBucket,
+        profile::{Profile, Settings},
+        resource::{MetaItemPreview, Stream},
+        streams::StreamsBucket,
     },
 };
 
-use crate::{env::WebEnv, event::WebEvent, model::WebModel};
+use crate::{
+    account::{
+        self, EmailFlowAction, EmailFlowError, EmailFlowErrorCode, EmailFlowKind,
+        ProfileDisplayAction, SavedProfileDisplay, SessionsAction, PROFILE_DISPLAY_STORAGE_KEY,
+    },
+    action_validation,
+    addon_console::{self, ReplayResult},
+    addon_diagnostics, addon_preview, addon_signatures,
+    addon_updates::{self, AddonUpdate, AddonUpdatesAction, ADDON_UPDATES_STORAGE_KEY},
+    background::{self, BackgroundTask},
+    blocklist::{self, BlockedItem, BlockedItemsAction, BLOCKED_ITEMS_STORAGE_KEY},
+    board_refresh, catalog_cache, catalog_hints,
+    debrid::{self, DebridTorrent},
+    device_profile::{self, DeviceProfile, DEVICE_PROFILE_STORAGE_KEY},
+    diagnostic_snapshot,
+    env::{StorageBackend, WebEnv},
+    epg,
+    event::{WebEvent, WebRuntimeEvent, WebStateEvent},
+    event_sequence, features, federated_search, fetch_limiter,
+    lan_sync::{self, LanSync, LanSyncAction, LAN_SYNC_STORAGE_KEY},
+    library_pending,
+    library_tags::{self, LibraryTagsAction, LIBRARY_TAGS_STORAGE_KEY},
+    library_transfer::{self, ExportFormat, ImportEntry, ImportSource, SkipReason},
+    load_cancellation,
+    memory::{self, TrimLevel},
+    meta_overrides::{self, MetaOverride, MetaOverridesAction, META_OVERRIDES_STORAGE_KEY},
+    meta_prefetch,
+    mirrors::{self, AddonMirrors, MirrorsAction, ADDON_MIRRORS_STORAGE_KEY},
+    model::{
+        deep_links_ext::{self, addon_install_link, ProtocolLink},
+        grid_density::{self, Viewport},
+        library_sort::{self, SortKeys, WebSort, LIBRARY_SORT_KEYS_STORAGE_KEY},
+        lite_mode, loadable_states, placeholders, range_extras, schema_version,
+        serialize_addon_capabilities, serialize_global_search, serialize_library_status,
+        serialize_meta_preview_card, serialize_share_payload,
+        spatial_navigation::{self, SpatialNavigationOptions},
+        ShareArgs, WebModel, WebModelField, BOARD_ROW_SIZE,
+    },
+    new_episodes::{self, NEW_EPISODES_STORAGE_KEY},
+    observed_fields,
+    onboarding::{self, OnboardingAction, ONBOARDING_COMPLETED_STORAGE_KEY},
+    p2p_transport, palettes, player_source, prefetch, push_transport,
+    recent_logs::RecentLogsLayer,
+    reminders::{self, Reminder, ReminderAction, REMINDERS_STORAGE_KEY},
+    remote_config::{self, DISMISSED_ANNOUNCEMENTS_STORAGE_KEY},
+    request_tracing, response_limits, retry,
+    rewatch::{self, Rewatch, RewatchAction, REWATCHES_STORAGE_KEY},
+    schema_validation, season_packs,
+    shortcuts::{self, PinnedCatalog, PinnedCatalogsAction, PINNED_CATALOGS_STORAGE_KEY},
+    snooze::{self, Snooze, SnoozeAction, SNOOZED_ITEMS_STORAGE_KEY},
+    state_cache,
+    still_watching::{self, StillWatchingAction},
+    stream_history::{self, PlayedStream, STREAM_HISTORY_STORAGE_KEY},
+    stream_timeouts, streaming_catalogs,
+    streaming_server_cache::{self, CacheClearing, CacheSize, StreamingServerCacheAction},
+    streaming_server_jobs::{self, StreamingServerJobsAction},
+    subtitles_sync::{self, SubtitlesOffset, SubtitlesSyncAction, SUBTITLES_OFFSETS_STORAGE_KEY},
+    subtitles_translation::{self, TranslationRequest},
+    tab_sync::{self, SideAction},
+    undo,
+    uploaded_subtitles::{self, UploadedSubtitles, UPLOADED_SUBTITLES_STORAGE_KEY},
+    user_ratings::{self, UserRating, UserRatingsAction, USER_RATINGS_STORAGE_KEY},
+    watch_party::{self, Presence},
+    web_settings::{self, WebSettings, WEB_SETTINGS_STORAGE_KEY},
+};
 
 lazy_static! {
     static ref RUNTIME: RwLock<Option<Loadable<Runtime<WebEnv, WebModel>, EnvError>>> =
         Default::default();
 }
 
+/// Options provided by the shell, shared by the ESM and the no-modules builds
+#[derive(Default, Deserialize)]
+#[serde(rename_all = "camelCase", default)]
+struct InitOptions {
+    /// Used instead of the streaming server url from the profile settings
+    streaming_server_url: Option<Url>,
+    storage: StorageBackend,
+    /// Replaces the persisted device profile
+    device_profile: Option<DeviceProfile>,
+    /// Add the routes of the older web versions next to the deep links
+    legacy_deep_links: bool,
+    /// Add the D-pad navigation hints to the items of the board, discover and the library
+    spatial_navigation: Option<SpatialNavigationOptions>,
+    /// Strip the heavy fields of the state and cap its long arrays, for memory constrained devices
+    lite: bool,
+    /// The schema version of the states the UI supports, the current one when it's not set
+    schema_version: Option<u32>,
+}
+
+thread_local! {
+    static EMIT_TO_UI: RefCell<Option<js_sys::Function>> = RefCell::new(None);
+    /// Kept for reloading the runtime when the tab takes over the leadership
+    static INIT_OPTIONS: RefCell<JsValue> = RefCell::new(JsValue::NULL);
+}
+
+/// Emits an event to the UI, used for both the runtime events
+/// and the state changes which happen outside of the runtime.
+///
+/// State changes of fields which are not observed by the UI are not emitted,
+/// they are emitted once the UI starts observing them again.
+pub fn emit_event(event: &RuntimeEvent<WebEnv, WebModel>) {
+    if let RuntimeEvent::NewState(fields) = event {
+        let fields = &with_dependent_fields(fields);
+        state_cache::bump_revisions(fields);
+        if tab_sync::is_leader()
+            && fields
+                .iter()
+                .any(|field| tab_sync::SHARED_FIELDS.contains(field))
+        {
+            broadcast_states(fields);
+        }
+        let fields = observed_fields::filter_observed(fields);
+        if !fields.is_empty() {
+            emit_to_ui(&RuntimeEvent::NewState(fields));
+        }
+    } else {
+        emit_to_ui(event);
+    }
+}
+
+/// Adds the fields which are serialized from the state of the changed fields,
+/// e.g. the billboard of the Board falls back to Continue Watching.
+fn with_dependent_fields(fields: &[WebModelField]) -> Vec<WebModelField> {
+    let mut fields = fields.to_vec();
+    if fields.contains(&WebModelField::ContinueWatchingPreview)
+        && !fields.contains(&WebModelField::Board)
+    {
+        fields.push(WebModelField::Board);
+    }
+    fields
+}
+
+/// Emits an event of the state kept outside of the model, along with the core events
+pub fn emit_web_event(event: &WebStateEvent) {
+    EMIT_TO_UI.with(|emit_to_ui| {
+        if let Some(emit_to_ui) = emit_to_ui.borrow().as_ref() {
+            emit_to_ui
+                .call1(
+                    &JsValue::NULL,
+                    &JsValue::from_serde(&event_sequence::sequenced(
+                        &WebRuntimeEvent::WebStateEvent(event),
+                    ))
+                    .unwrap(),
+                )
+                .expect("emit event failed");
+        }
+    });
+}
+
+fn emit_to_ui(event: &RuntimeEvent<WebEnv, WebModel>) {
+    EMIT_TO_UI.with(|emit_to_ui| {
+        if let Some(emit_to_ui) = emit_to_ui.borrow().as_ref() {
+            emit_to_ui
+                .call1(
+                    &JsValue::NULL,
+                    &JsValue::from_serde(&event_sequence::sequenced(event)).unwrap(),
+                )
+                .expect("emit event failed");
+        }
+    });
+}
+
 #[wasm_bindgen(start)]
 pub fn start() {
     // print pretty errors in wasm https://github.com/rustwasm/console_error_panic_hook
@@ -42,22 +215,50 @@ pub fn start() {
     let config = WASMLayerConfigBuilder::default()
         .set_max_level(max_level)
         .build();
-    // setup wasm tracing Subscriber on web console
-    tracing_wasm::set_as_global_default_with_config(config);
+    // setup wasm tracing Subscriber on web console,
+    // the recent events are kept as well for the diagnostic snapshots
+    tracing::subscriber::set_global_default(
+        tracing_subscriber::registry()
+            .with(WASMLayer::new(config))
+            .with(RecentLogsLayer::new(max_level)),
+    )
+    .expect("default global");
 
     info!(?max_level, "Logging level");
 }
 
+/// Initializes the runtime and resolves with the results of the env self-check
 #[wasm_bindgen]
-pub async fn initialize_runtime(emit_to_ui: js_sys::Function) -> Result<(), JsValue> {
+pub async fn initialize_runtime(
+    emit_to_ui: js_sys::Function,
+    options: JsValue,
+) -> Result<JsValue, JsValue> {
     if RUNTIME.read().expect("runtime read failed").is_some() {
         panic!("runtime initialization has already started");
     };
+    tab_sync::start();
+    INIT_OPTIONS.with(|init_options| *init_options.borrow_mut() = options.to_owned());
+    let options = options
+        .into_serde::<Option<InitOptions>>()
+        .expect("initialize runtime failed")
+        .unwrap_or_default();
+    WebEnv::set_storage_backend(options.storage);
+    deep_links_ext::set_legacy_routes_enabled(options.legacy_deep_links);
+    spatial_navigation::set_options(options.spatial_navigation);
+    lite_mode::set_lite_mode_enabled(options.lite);
+    if let Err(error) = schema_version::negotiate(options.schema_version) {
+        return Err(JsValue::from_serde(&error).unwrap());
+    }
 
     *RUNTIME.write().expect("runtime write failed") = Some(Loadable::Loading);
+    EMIT_TO_UI.with(|emit| *emit.borrow_mut() = Some(emit_to_ui));
     let env_init_result = WebEnv::init().await;
     match env_init_result {
         Ok(_) => {
+            if let Some(device_profile) = options.device_profile {
+                persist_device_profile(&device_profile);
+                device_profile::set_device_profile(Some(device_profile));
+            }
             let storage_result = future::try_join5(
                 WebEnv::get_storage::<Profile>(PROFILE_STORAGE_KEY),
                 WebEnv::get_storage::<LibraryBucket>(LIBRARY_RECENT_STORAGE_KEY),
@@ -74,7 +275,10 @@ pub async fn initialize_runtime(emit_to_ui: js_sys::Function) -> Result<(), JsVa
                     streams_bucket,
                     notifications_bucket,
                 )) => {
-                    let profile = profile.unwrap_or_default();
+                    let mut profile = profile.unwrap_or_default();
+                    if let Some(streaming_server_url) = options.streaming_server_url {
+                        profile.settings.streaming_server_url = streaming_server_url;
+                    }
                     let mut library = LibraryBucket::new(profile.uid(), vec![]);
                     if let Some(recent_bucket) = recent_bucket {
                         library.merge_bucket(recent_bucket);
@@ -113,14 +317,43 @@ pub async fn initialize_runtime(emit_to_ui: js_sys::Function) -> Result<(), JsVa
                                 }),
                             ));
                         };
-                        emit_to_ui
-                            .call1(&JsValue::NULL, &JsValue::from_serde(&event).unwrap())
-                            .expect("emit event failed");
+                        if let RuntimeEvent::NewState(fields) = &event {
+                            on_new_state(fields);
+                        };
+                        emit_event(&event);
+                        if let RuntimeEvent::CoreEvent(event) = &event {
+                            if library_pending::reconcile(event) {
+                                emit_event(&RuntimeEvent::NewState(vec![
+                                    WebModelField::ContinueWatchingPreview,
+                                    WebModelField::Library,
+                                    WebModelField::MetaDetails,
+                                ]));
+                            }
+                            if still_watching::on_event(event) {
+                                emit_event(&RuntimeEvent::NewState(vec![WebModelField::Player]));
+                            }
+                        };
                         future::ready(())
                     }));
                     *RUNTIME.write().expect("runtime write failed") =
                         Some(Loadable::Ready(runtime));
-                    Ok(())
+                    run_background_tasks(background::start(WebEnv::now()));
+                    WebEnv::exec_concurrent(WebEnv::fetch_remote_config().map(
+                        |result| match result {
+                            Ok(config) => {
+                                features::set_overrides(&config.features);
+                                features::set_experiments(config.experiments.to_owned());
+                                remote_config::set_remote_config(config);
+                                emit_event(&RuntimeEvent::NewState(vec![
+                                    WebModelField::Ctx,
+                                    WebModelField::Board,
+                                ]));
+                            }
+                            Err(error) => error!("Failed to fetch remote config: {error:?}"),
+                        },
+                    ));
+                    let self_check = WebEnv::self_check().await;
+                    Ok(JsValue::from_serde(&self_check).unwrap())
                 }
                 Err(error) => {
                     *RUNTIME.write().expect("runtime write failed") =
@@ -136,6 +369,969 @@ pub async fn initialize_runtime(emit_to_ui: js_sys::Function) -> Result<(), JsVa
     }
 }
 
+/// Persists the buckets of the current runtime, cancels the pending env futures
+/// and drops the runtime along with its listeners.
+#[wasm_bindgen]
+pub async fn destroy_runtime() {
+    if let Some(Loadable::Loading) = RUNTIME.read().expect("runtime read failed").as_ref() {
+        panic!("runtime initialization is in progress");
+    }
+    // the storage is owned by the leader tab
+    if tab_sync::is_leader() {
+        if let Some(flush) = flush_ctx_storage() {
+            if let Err(error) = flush.await {
+                error!("Failed to flush storage: {error:?}");
+            }
+        }
+    }
+    tab_sync::stop();
+    drop_runtime();
+}
+
+/// Writes the buckets of the ctx the way core persists them, the library is split
+/// into the recent and the other bucket core loads it from.
+/// The writes are not dropped on followers, e.g. while handing the leadership over.
+pub fn flush_ctx_storage() -> Option<TryEnvFuture<()>> {
+    let runtime = RUNTIME.read().expect("runtime read failed");
+    let runtime = match runtime.as_ref() {
+        Some(Loadable::Ready(runtime)) => runtime,
+        _ => return None,
+    };
+    let model = runtime.model().expect("model read failed");
+    let library = &model.ctx.library;
+    let (recent_items, other_items) = library.split_items_by_recent();
+    let writes = [
+        (
+            PROFILE_STORAGE_KEY,
+            serde_json::to_string(&model.ctx.profile),
+        ),
+        (
+            LIBRARY_RECENT_STORAGE_KEY,
+            serde_json::to_string(&LibraryBucketRef::new(&library.uid, &recent_items)),
+        ),
+        (
+            LIBRARY_STORAGE_KEY,
+            serde_json::to_string(&LibraryBucketRef::new(&library.uid, &other_items)),
+        ),
+        (
+            STREAMS_STORAGE_KEY,
+            serde_json::to_string(&model.ctx.streams),
+        ),
+        (
+            NOTIFICATIONS_STORAGE_KEY,
+            serde_json::to_string(&model.ctx.notifications),
+        ),
+    ];
+    Some(
+        future::try_join_all(writes.into_iter().map(|(key, value)| match value {
+            Ok(value) => WebEnv::write_storage(key.to_owned(), Some(value)),
+            Err(error) => future::err(EnvError::from(error)).boxed_local(),
+        }))
+        .map_ok(|_| ())
+        .boxed_local(),
+    )
+}
+
+fn drop_runtime() {
+    *RUNTIME.write().expect("runtime write failed") = None;
+    WebEnv::teardown();
+    push_transport::close_all();
+    fetch_limiter::clear();
+    load_cancellation::clear();
+    prefetch::clear();
+    meta_prefetch::clear();
+    stream_timeouts::clear();
+    player_source::clear();
+    catalog_cache::clear();
+    federated_search::clear();
+    state_cache::clear();
+    still_watching::clear();
+    season_packs::clear();
+    subtitles_translation::clear();
+    loadable_states::clear();
+    library_transfer::clear();
+    user_ratings::clear();
+    snooze::clear();
+    meta_overrides::clear();
+    blocklist::clear();
+    new_episodes::clear();
+    palettes::clear();
+    shortcuts::clear();
+    undo::clear();
+    account::clear();
+    addon_diagnostics::clear();
+    addon_updates::clear();
+    addon_preview::clear();
+    addon_signatures::clear();
+    board_refresh::clear();
+    catalog_hints::clear();
+    debrid::clear();
+    epg::clear();
+    lan_sync::clear();
+    library_pending::clear();
+    library_sort::clear();
+    library_tags::clear();
+    mirrors::clear();
+    p2p_transport::clear();
+    request_tracing::clear();
+    response_limits::clear();
+    retry::clear();
+    schema_validation::clear();
+    stream_history::clear();
+    streaming_catalogs::clear();
+    streaming_server_cache::clear();
+    streaming_server_jobs::clear();
+    watch_party::set_presence(None);
+    EMIT_TO_UI.with(|emit| *emit.borrow_mut() = None);
+}
+
+/// Rebuilds the runtime without reloading the page, e.g. after switching the storage backend
+#[wasm_bindgen]
+pub async fn reinitialize_runtime(
+    emit_to_ui: js_sys::Function,
+    options: JsValue,
+) -> Result<JsValue, JsValue> {
+    destroy_runtime().await;
+    initialize_runtime(emit_to_ui, options).await
+}
+
+/// Rebuilds the runtime from the storage persisted by the previous leader tab,
+/// the stale buckets of the tab are dropped without flushing them.
+/// It's not executed by the env, as the teardown cancels the env futures.
+pub fn reload_runtime() {
+    let emit_to_ui = EMIT_TO_UI.with(|emit_to_ui| emit_to_ui.borrow().to_owned());
+    let options = INIT_OPTIONS.with(|init_options| init_options.borrow().to_owned());
+    if let Some(emit_to_ui) = emit_to_ui {
+        wasm_bindgen_futures::spawn_local(async move {
+            drop_runtime();
+            if let Err(error) = initialize_runtime(emit_to_ui, options).await {
+                error!("Failed to reload runtime: {error:?}");
+                return;
+            }
+            // the player load which the leadership was taken over for
+            if let Some(pending) = tab_sync::take_pending_dispatch() {
+                dispatch(
+                    pending.action,
+                    pending.field,
+                    JsValue::from_str(&pending.location_hash),
+                    JsValue::from_str(&pending.action_id),
+                );
+            }
+        });
+    }
+}
+
+/// Sends the state of every shared field to the follower tabs
+pub fn broadcast_shared_states() {
+    broadcast_states(&tab_sync::SHARED_FIELDS);
+}
+
+fn broadcast_states(fields: &[WebModelField]) {
+    let runtime = RUNTIME.read().expect("runtime read failed");
+    let runtime = match runtime.as_ref() {
+        Some(Loadable::Ready(runtime)) => runtime,
+        _ => return,
+    };
+    let model = runtime.model().expect("model read failed");
+    tab_sync::broadcast_states(fields, |field| {
+        state_cache::get_or_serialize(field, || serialize_state(&model, field))
+    });
+}
+
+/// Dispatches the actions of the background tasks, ctx tasks are left to the leader tab
+pub fn run_background_tasks(tasks: Vec<BackgroundTask>) {
+    if tasks.is_empty() {
+        return;
+    }
+    if tasks.contains(&BackgroundTask::CheckReminders) {
+        if let Some(reminders) = reminders::update_due(WebEnv::now()) {
+            persist_reminders(&reminders);
+            emit_event(&RuntimeEvent::NewState(vec![WebModelField::Ctx]));
+        }
+    }
+    if tasks.contains(&BackgroundTask::ApplyRetention) {
+        if let Some(played_streams) = stream_history::expire(WebEnv::now()) {
+            persist_stream_history(&played_streams);
+        }
+        if new_episodes::expire(WebEnv::now()) {
+            persist_new_episodes();
+            emit_event(&RuntimeEvent::NewState(vec![WebModelField::Board]));
+        }
+        if let Some(snoozes) = snooze::expire(WebEnv::now()) {
+            persist_snoozes(&snoozes);
+            emit_event(&RuntimeEvent::NewState(vec![
+                WebModelField::ContinueWatchingPreview,
+            ]));
+        }
+    }
+    let runtime = RUNTIME.read().expect("runtime read failed");
+    let runtime = match runtime.as_ref() {
+        Some(Loadable::Ready(runtime)) => runtime,
+        _ => return,
+    };
+    let refresh_board = tasks.contains(&BackgroundTask::RefreshBoard)
+        && observed_fields::is_observed(&WebModelField::Board);
+    let (board_catalogs, library_series, addons, meta_requests) = {
+        let model = runtime.model().expect("model read failed");
+        (
+            // the loaded items are hashed only when they are refreshed
+            if refresh_board {
+                model
+                    .board
+                    .catalogs
+                    .iter()
+                    .filter_map(|catalog| catalog.first())
+                    .filter_map(|catalog| match &catalog.content {
+                        Some(Loadable::Ready(meta_items)) => {
+                            Some((catalog.request.to_owned(), board_refresh::hash(meta_items)))
+                        }
+                        _ => None,
+                    })
+                    .collect::<Vec<_>>()
+            } else {
+                vec![]
+            },
+            model
+                .ctx
+                .library
+                .items
+                .values()
+                .filter(|library_item| !library_item.removed && library_item.r#type == "series")
+                .map(|library_item| library_item.id.to_owned())
+                .collect::<HashSet<_>>(),
+            model.ctx.profile.addons.to_owned(),
+            meta_prefetch::requests(&model.continue_watching_preview, &model.ctx.profile.addons),
+        )
+    };
+    for task in tasks {
+        match task {
+            BackgroundTask::PullNotifications if !tab_sync::is_follower() => {
+                runtime.dispatch(RuntimeAction {
+                    field: None,
+                    action: Action::Ctx(ActionCtx::PullNotifications),
+                })
+            }
+            BackgroundTask::RefreshBoard if refresh_board => board_refresh::refresh(
+                board_catalogs.to_owned(),
+                enclose!((library_series, addons) move |meta_items: &[MetaItemPreview]| {
+                    detect_catalog_episodes(meta_items, &library_series, &addons)
+                }),
+            ),
+            BackgroundTask::CheckAddonUpdates if !tab_sync::is_follower() => {
+                check_addon_updates(addons.to_owned())
+            }
+            BackgroundTask::SyncLan if lan_sync::is_enabled() => sync_lan(),
+            BackgroundTask::PrefetchMetaDetails => {
+                meta_prefetch::prefetch(meta_requests.to_owned(), WebEnv::now())
+            }
+            _ => {}
+        }
+    }
+}
+
+/// Merges the snapshot of the streaming server into the profile and the library, then pushes
+/// the merge back for the other devices. The changes of the other devices are applied
+/// to the runtime by the actions of the ctx.
+fn sync_lan() {
+    // the ctx is owned by the leader tab
+    if tab_sync::is_follower() {
+        return;
+    }
+    let local = {
+        let runtime = RUNTIME.read().expect("runtime read failed");
+        let runtime = match runtime.as_ref() {
+            Some(Loadable::Ready(runtime)) => runtime,
+            _ => return,
+        };
+        let model = runtime.model().expect("model read failed");
+        if model.ctx.profile.auth.is_some() {
+            None
+        } else {
+            Some(lan_sync::local_snapshot(
+                &model.ctx.profile.addons,
+                &model.ctx.profile.settings,
+                model.ctx.library.items.values().cloned().collect(),
+                WebEnv::now(),
+            ))
+        }
+    };
+    if !lan_sync::start_sync() {
+        return;
+    }
+    let local = match local {
+        Some(local) => local,
+        None => {
+            lan_sync::set_sync(Err(
+                "Logged in devices are synced through the account".to_owned()
+            ));
+            emit_event(&RuntimeEvent::NewState(vec![WebModelField::Ctx]));
+            return;
+        }
+    };
+    emit_event(&RuntimeEvent::NewState(vec![WebModelField::Ctx]));
+    WebEnv::exec_concurrent(lan_sync::sync(local).map(|result| {
+        let merge = match result {
+            Ok(merge) => merge,
+            Err(error) => {
+                lan_sync::set_sync(Err(error.message()));
+                emit_event(&RuntimeEvent::NewState(vec![WebModelField::Ctx]));
+                return;
+            }
+        };
+        apply_lan_merge(&merge);
+        if let Some(lan_sync) = lan_sync::set_sync(Ok((merge.outcome, merge.profile_hash))) {
+            persist_lan_sync(&lan_sync);
+        }
+        emit_event(&RuntimeEvent::NewState(vec![WebModelField::Ctx]));
+    }));
+}
+
+/// Dispatches the changes of the other devices. Only whether the items are in the library
+/// and watched is applied, there is no action for the playback progress.
+fn apply_lan_merge(merge: &lan_sync::Merge) {
+    let mut actions = merge
+        .uninstall_addons
+        .iter()
+        .map(|addon| ActionCtx::UninstallAddon(addon.to_owned()))
+        .chain(
+            merge
+                .install_addons
+                .iter()
+                .map(|addon| ActionCtx::InstallAddon(addon.to_owned())),
+        )
+        .chain(
+            merge
+                .settings
+                .iter()
+                .map(|settings| ActionCtx::UpdateSettings(settings.to_owned())),
+        )
+        .collect::<Vec<_>>();
+    {
+        let runtime = RUNTIME.read().expect("runtime read failed");
+        let runtime = match runtime.as_ref() {
+            Some(Loadable::Ready(runtime)) => runtime,
+            _ => return,
+        };
+        let model = runtime.model().expect("model read failed");
+        for remote_item in merge.library.iter() {
+            let local_item = model.ctx.library.items.get(&remote_item.id);
+            match (
+                local_item.map_or(false, lan_sync::is_in_library),
+                lan_sync::is_in_library(remote_item),
+            ) {
+                (true, false) => {
+                    actions.push(ActionCtx::RemoveFromLibrary(remote_item.id.to_owned()))
+                }
+                (false, true) => match library_item_preview(remote_item) {
+                    Ok(meta_item) => actions.push(ActionCtx::AddToLibrary(meta_item)),
+                    Err(error) => error!(
+                        "Failed to pull the library item {}: {error}",
+                        remote_item.id
+                    ),
+                },
+                _ => {}
+            };
+            let is_watched = lan_sync::is_watched(remote_item);
+            if local_item.map_or(false, lan_sync::is_watched) != is_watched {
+                actions.push(ActionCtx::LibraryItemMarkAsWatched {
+                    id: remote_item.id.to_owned(),
+                    is_watched,
+                });
+            }
+        }
+    }
+    for action in actions {
+        dispatch_ctx(action);
+    }
+}
+
+/// Refetches the manifests of the addons and offers the ones with a newer version,
+/// an update is installed only once the user confirms it.
+fn check_addon_updates(addons: Vec<Descriptor>) {
+    for addon in addons {
+        WebEnv::exec_concurrent(
+            WebEnv::addon_transport(&addon.transport_url)
+                .manifest()
+                .map(move |result| {
+                    let manifest = match result {
+                        Ok(manifest) => manifest,
+                        Err(error) => {
+                            error!("Failed to check for addon update: {error:?}");
+                            return;
+                        }
+                    };
+                    let update = match addon_updates::detect_update(
+                        &addon.transport_url,
+                        &addon.manifest,
+                        &manifest,
+                        WebEnv::now(),
+                    ) {
+                        Some(update) => update,
+                        None => return,
+                    };
+                    {
+                        let runtime = RUNTIME.read().expect("runtime read failed");
+                        let runtime = match runtime.as_ref() {
+                            Some(Loadable::Ready(runtime)) => runtime,
+                            _ => return,
+                        };
+                        let model = runtime.model().expect("model read failed");
+                        // the addon could have been uninstalled or upgraded in the meantime
+                        if !model.ctx.profile.addons.contains(&addon) {
+                            return;
+                        }
+                    }
+                    if !addon_updates::set_available(update.to_owned(), manifest.to_owned()) {
+                        return;
+                    }
+                    emit_web_event(&WebStateEvent::AddonUpdateAvailable {
+                        transport_url: update.transport_url,
+                        name: manifest.name,
+                        version: update.version,
+                        changelog: update.changelog,
+                    });
+                    emit_event(&RuntimeEvent::NewState(vec![
+                        WebModelField::InstalledAddons,
+                    ]));
+                }),
+        );
+    }
+}
+
+/// Keeps the state outside of the model in sync after the model has been updated
+fn on_new_state(fields: &[WebModelField]) {
+    let runtime = RUNTIME.read().expect("runtime read failed");
+    let runtime = match runtime.as_ref() {
+        Some(Loadable::Ready(runtime)) => runtime,
+        _ => return,
+    };
+    let model = runtime.model().expect("model read failed");
+    loadable_states::update(&model);
+    if fields.contains(&WebModelField::StreamingServer) {
+        WebEnv::set_streaming_server_url(model.streaming_server.base_url.ready().cloned());
+    }
+    if fields.contains(&WebModelField::Player) {
+        if let Some(selected) = model.player.selected.as_ref() {
+            player_source::resolve(&debrid::resolve_stream(&selected.stream));
+        }
+    }
+    if fields.contains(&WebModelField::ContinueWatchingPreview) {
+        if let Some(shortcuts) = shortcuts::changed_shortcuts(shortcuts::shortcuts(
+            &model.continue_watching_preview,
+            &model.ctx,
+        )) {
+            emit_web_event(&WebStateEvent::ShortcutsChanged(shortcuts));
+        }
+    }
+    if fields.contains(&WebModelField::Discover)
+        && web_settings::web_settings().catalogs.prefetch_next_page
+        && !web_settings::web_settings().network.data_saver
+    {
+        let last_page_ready = model
+            .discover
+            .catalog
+            .last()
+            .map(|page| matches!(page.content, Some(Loadable::Ready(_))))
+            .unwrap_or_default();
+        if let Some(next_page) = model.discover.selectable.next_page.as_ref() {
+            if last_page_ready {
+                prefetch::prefetch(&next_page.request, Some(WebModelField::Discover));
+            }
+        }
+    }
+    if fields.iter().any(|field| {
+        [
+            WebModelField::Ctx,
+            WebModelField::Board,
+            WebModelField::Discover,
+        ]
+        .contains(field)
+    }) {
+        let transport_urls = model
+            .ctx
+            .profile
+            .addons
+            .iter()
+            .map(|addon| &addon.transport_url)
+            // catalogs of addons which are not installed can be browsed in Discover
+            .chain(
+                model
+                    .discover
+                    .catalog
+                    .first()
+                    .map(|page| &page.request.base),
+            )
+            .unique()
+            .cloned()
+            .collect();
+        load_catalog_hints(catalog_hints::start_loading(transport_urls, WebEnv::now()));
+    }
+    if fields.iter().any(|field| {
+        [
+            WebModelField::Board,
+            WebModelField::Discover,
+            WebModelField::Search,
+            WebModelField::MetaDetails,
+        ]
+        .contains(field)
+    }) {
+        let catalogs = model
+            .board
+            .catalogs
+            .iter()
+            .chain(model.discover.catalog.iter())
+            .chain(model.search.catalogs.iter())
+            .filter_map(|catalog| catalog.content.as_ref().and_then(|content| content.ready()))
+            .flatten();
+        let meta_items = model
+            .meta_details
+            .meta_items
+            .iter()
+            .filter_map(|meta_item| {
+                meta_item
+                    .content
+                    .as_ref()
+                    .and_then(|content| content.ready())
+            })
+            .map(|meta_item| &meta_item.preview);
+        if let Some(sort_keys) =
+            library_sort::record(catalogs.chain(meta_items), &model.ctx.library)
+        {
+            persist_library_sort_keys(&sort_keys);
+        }
+    }
+    if fields.contains(&WebModelField::Ctx) {
+        load_debrid_account();
+        if new_episodes::detect_notifications(
+            &model.ctx.notifications,
+            &model.ctx.library,
+            WebEnv::now(),
+        ) {
+            persist_new_episodes();
+            emit_event(&RuntimeEvent::NewState(vec![WebModelField::Board]));
+        }
+    }
+    if fields.contains(&WebModelField::MetaDetails) {
+        let new_episodes_detected = model
+            .meta_details
+            .meta_items
+            .iter()
+            .find_map(|meta_item| {
+                meta_item
+                    .content
+                    .as_ref()
+                    .and_then(|content| content.ready())
+            })
+            .map_or(false, |meta_item| {
+                new_episodes::detect_meta_videos(meta_item, &model.ctx.library, WebEnv::now())
+            });
+        if new_episodes_detected {
+            persist_new_episodes();
+            emit_event(&RuntimeEvent::NewState(vec![WebModelField::Board]));
+        }
+        if let Some(info_hashes) = debrid::start_checking_cache(
+            model
+                .meta_details
+                .streams
+                .iter()
+                .filter_map(|streams| streams.content.as_ref().and_then(|content| content.ready()))
+                .flatten(),
+        ) {
+            check_debrid_cache(info_hashes);
+        }
+    }
+    if fields.iter().any(|field| {
+        [
+            WebModelField::Board,
+            WebModelField::Discover,
+            WebModelField::MetaDetails,
+        ]
+        .contains(field)
+    }) {
+        let board_posters = model
+            .board
+            .catalogs
+            .iter()
+            .filter_map(|catalog| catalog.first())
+            .flat_map(|catalog| {
+                catalog
+                    .content
+                    .as_ref()
+                    .and_then(|content| content.ready())
+                    .into_iter()
+                    .flat_map(|meta_items| meta_items.iter().take(BOARD_ROW_SIZE))
+                    .filter_map(|meta_item| meta_item.poster.as_ref())
+            });
+        let discover_posters = model
+            .discover
+            .catalog
+            .iter()
+            .filter_map(|page| page.content.as_ref().and_then(|content| content.ready()))
+            .flatten()
+            .filter_map(|meta_item| meta_item.poster.as_ref());
+        let meta_details_images = model
+            .meta_details
+            .meta_items
+            .iter()
+            .filter_map(|meta_item| {
+                meta_item
+                    .content
+                    .as_ref()
+                    .and_then(|content| content.ready())
+            })
+            .filter_map(|meta_item| palettes::theme_image(&meta_item.preview));
+        extract_palettes(palettes::start_extracting(
+            board_posters
+                .chain(discover_posters)
+                .chain(meta_details_images)
+                .cloned()
+                .chain(meta_overrides::posters())
+                .collect(),
+        ));
+    }
+    if fields.contains(&WebModelField::Player) {
+        if let Some(played_streams) = stream_history::played_stream(&model.player, WebEnv::now())
+            .and_then(stream_history::record)
+        {
+            persist_stream_history(&played_streams);
+        }
+        if let Some(torrent) = model
+            .player
+            .selected
+            .as_ref()
+            .and_then(|selected| debrid::start_resolving(&selected.stream))
+        {
+            resolve_debrid_torrent(torrent);
+        }
+    }
+    if fields.contains(&WebModelField::AddonDetails) {
+        if let Some(selected) = model.addon_details.selected.as_ref() {
+            if addon_signatures::start_verifying(&selected.transport_url) {
+                verify_addon_signature(selected.transport_url.to_owned());
+            }
+        }
+        // the catalogs of the installed addons are already on the board
+        if let (None, Some(Loadable::Ready(addon))) = (
+            model.addon_details.local_addon.as_ref(),
+            model
+                .addon_details
+                .remote_addon
+                .as_ref()
+                .map(|remote_addon| &remote_addon.content),
+        ) {
+            load_addon_previews(addon_preview::start_loading(
+                addon_preview::preview_requests(addon),
+            ));
+        }
+    }
+    if fields.contains(&WebModelField::Search) {
+        search_official_addons(&model);
+    }
+    if fields.contains(&WebModelField::Discover) {
+        if let Some(first_page) = model
+            .discover
+            .catalog
+            .first()
+            .filter(|first_page| epg::is_channel_catalog(&first_page.request))
+        {
+            let requests = model
+                .discover
+                .catalog
+                .iter()
+                .filter_map(|page| page.content.as_ref().and_then(|content| content.ready()))
+                .flatten()
+                .take(epg::MAX_CHANNELS)
+                .map(|channel| epg::guide_request(&first_page.request, channel))
+                .collect();
+            load_guides(epg::start_loading(requests, WebEnv::now()));
+            advance_guide_clock();
+        }
+    }
+}
+
+fn verify_addon_signature(transport_url: Url) {
+    WebEnv::exec_concurrent(
+        catalog_hints::fetch_manifest(&transport_url).map(move |result| {
+            addon_signatures::set_verification(
+                &transport_url,
+                result.map_err(|error| error.message()),
+            );
+            emit_event(&RuntimeEvent::NewState(vec![WebModelField::AddonDetails]));
+        }),
+    );
+}
+
+fn load_debrid_account() {
+    if debrid::start_loading_account() {
+        let generation = debrid::generation();
+        WebEnv::exec_concurrent(
+            debrid::fetch_account(&web_settings::web_settings().debrid).map(move |result| {
+                debrid::set_account(result, generation);
+                emit_event(&RuntimeEvent::NewState(vec![WebModelField::Ctx]));
+            }),
+        );
+    }
+}
+
+/// Resolves the torrent to a direct link, which is played in place of the torrent
+fn resolve_debrid_torrent(torrent: DebridTorrent) {
+    let generation = debrid::generation();
+    WebEnv::exec_concurrent(
+        debrid::resolve(&web_settings::web_settings().debrid, torrent.to_owned()).map(
+            move |result| {
+                debrid::set_resolution(&torrent, result, generation);
+                emit_event(&RuntimeEvent::NewState(vec![
+                    WebModelField::Ctx,
+                    WebModelField::MetaDetails,
+                    WebModelField::Player,
+                ]));
+            },
+        ),
+    );
+}
+
+/// Checks which of the torrents of the streams can be streamed right away through the service
+fn check_debrid_cache(info_hashes: Vec<String>) {
+    let generation = debrid::generation();
+    WebEnv::exec_concurrent(
+        debrid::check_cache(&web_settings::web_settings().debrid, info_hashes.to_owned()).map(
+            move |result| {
+                debrid::set_cache(&info_hashes, result, generation);
+                emit_event(&RuntimeEvent::NewState(vec![WebModelField::MetaDetails]));
+            },
+        ),
+    );
+}
+
+/// Loads the manifests of the addons for the display hints of their catalogs
+fn load_catalog_hints(transport_urls: Vec<Url>) {
+    for transport_url in transport_urls {
+        WebEnv::exec_concurrent(
+            catalog_hints::fetch_manifest(&transport_url).map(move |result| {
+                catalog_hints::set_hints(
+                    &transport_url,
+                    result.map_err(|error| error.message()),
+                    WebEnv::now(),
+                );
+                emit_event(&RuntimeEvent::NewState(vec![
+                    WebModelField::Board,
+                    WebModelField::Discover,
+                ]));
+            }),
+        );
+    }
+}
+
+/// The fields are updated as each palette is extracted,
+/// the queued images are fetched as the ones before them are done
+fn extract_palettes(urls: Vec<Url>) {
+    for url in urls {
+        WebEnv::exec_concurrent(WebEnv::fetch_image_pixels(&url, palettes::SAMPLE_SIZE).map(
+            move |result| {
+                let result = result.map_err(|error| error.message()).and_then(|pixels| {
+                    palettes::extract(&pixels).ok_or_else(|| "Image is transparent".to_owned())
+                });
+                let is_ready = result.is_ok();
+                let next_urls = palettes::set_palette(&url, result);
+                if is_ready {
+                    emit_event(&RuntimeEvent::NewState(vec![
+                        WebModelField::Board,
+                        WebModelField::Discover,
+                        WebModelField::MetaDetails,
+                    ]));
+                }
+                extract_palettes(next_urls);
+            },
+        ));
+    }
+}
+
+/// Loads the first page of the catalogs of an addon which is not installed
+fn load_addon_previews(requests: Vec<ResourceRequest>) {
+    for request in requests {
+        WebEnv::exec_concurrent(
+            WebEnv::addon_transport(&request.base)
+                .resource(&request.path)
+                .map(move |result| {
+                    let preview = match result {
+                        Ok(ResourceResponse::Metas { metas }) => Loadable::Ready(metas),
+                        Ok(_) => Loadable::Err("Unexpected addon response".to_owned()),
+                        Err(error) => Loadable::Err(error.message()),
+                    };
+                    addon_preview::set_preview(&request, preview);
+                    emit_event(&RuntimeEvent::NewState(vec![WebModelField::AddonDetails]));
+                }),
+        );
+    }
+}
+
+/// Loads the official addons collection and the manifests of its addons which are not installed,
+/// then searches them. Called again as each of them is loaded.
+fn search_official_addons(model: &WebModel) {
+    let requests = if web_settings::web_settings()
+        .catalogs
+        .search_installable_addons
+    {
+        let addons = &model.ctx.profile.addons;
+        load_official_collection(federated_search::start_loading_collection(
+            addons,
+            WebEnv::now(),
+        ));
+        load_official_manifests(federated_search::start_loading_manifests(
+            addons,
+            WebEnv::now(),
+        ));
+        federated_search::requests(&model.search, addons)
+    } else {
+        vec![]
+    };
+    load_federated_search(federated_search::start_loading(requests));
+}
+
+fn search_official_addons_again() {
+    if let Some(Loadable::Ready(runtime)) = RUNTIME.read().expect("runtime read failed").as_ref() {
+        search_official_addons(&runtime.model().expect("model read failed"));
+    }
+}
+
+fn load_official_collection(requests: Vec<ResourceRequest>) {
+    for request in requests {
+        WebEnv::exec_concurrent(
+            WebEnv::addon_transport(&request.base)
+                .resource(&request.path)
+                .map(move |result| {
+                    let result = match result {
+                        Ok(ResourceResponse::Addons { addons }) => Ok(addons),
+                        Ok(_) => Err("Unexpected addon response".to_owned()),
+                        Err(error) => Err(error.message()),
+                    };
+                    federated_search::set_collection(&request, result, WebEnv::now());
+                    search_official_addons_again();
+                }),
+        );
+    }
+}
+
+fn load_official_manifests(transport_urls: Vec<Url>) {
+    for transport_url in transport_urls {
+        WebEnv::exec_concurrent(WebEnv::addon_transport(&transport_url).manifest().map(
+            move |result| {
+                federated_search::set_manifest(
+                    &transport_url,
+                    result.map_err(|error| error.message()),
+                    WebEnv::now(),
+                );
+                search_official_addons_again();
+            },
+        ));
+    }
+}
+
+/// Searches the official addons which are not installed
+fn load_federated_search(requests: Vec<ResourceRequest>) {
+    for request in requests {
+        WebEnv::exec_concurrent(
+            WebEnv::addon_transport(&request.base)
+                .resource(&request.path)
+                .map(move |result| {
+                    let result = match result {
+                        Ok(ResourceResponse::Metas { metas }) => Loadable::Ready(metas),
+                        Ok(_) => Loadable::Err("Unexpected addon response".to_owned()),
+                        Err(error) => Loadable::Err(error.message()),
+                    };
+                    federated_search::set_result(&request, result);
+                    emit_event(&RuntimeEvent::NewState(vec![WebModelField::Search]));
+                }),
+        );
+    }
+}
+
+/// Loads the meta items of the library series which are in a refreshed catalog of the Board,
+/// their new episodes are detected the same as when their details are opened
+fn detect_catalog_episodes(
+    meta_items: &[MetaItemPreview],
+    library_series: &HashSet<String>,
+    addons: &[Descriptor],
+) {
+    for meta_item in meta_items
+        .iter()
+        .filter(|meta_item| library_series.contains(&meta_item.id))
+    {
+        let path =
+            ResourcePath::without_extra(META_RESOURCE_NAME, &meta_item.r#type, &meta_item.id);
+        let addon = match addons
+            .iter()
+            .find(|addon| addon.manifest.is_resource_supported(&path))
+        {
+            Some(addon) => addon,
+            None => continue,
+        };
+        WebEnv::exec_concurrent(
+            WebEnv::addon_transport(&addon.transport_url)
+                .resource(&path)
+                .map(|result| match result {
+                    Ok(ResourceResponse::Meta { meta }) => {
+                        if new_episodes::detect_videos(&meta, WebEnv::now()) {
+                            persist_new_episodes();
+                            emit_event(&RuntimeEvent::NewState(vec![WebModelField::Board]));
+                        }
+                    }
+                    Ok(_) => {}
+                    Err(error) => error!("Failed to load the meta of a library series: {error:?}"),
+                }),
+        );
+    }
+}
+
+/// Loads the meta items of the channels, their videos are the programs of the guide
+fn load_guides(requests: Vec<ResourceRequest>) {
+    for request in requests {
+        WebEnv::exec_concurrent(
+            WebEnv::addon_transport(&request.base)
+                .resource(&request.path)
+                .map(move |result| {
+                    let result = match result {
+                        Ok(ResourceResponse::Meta { meta }) => Ok(meta),
+                        Ok(_) => Err("Unexpected addon response".to_owned()),
+                        Err(error) => Err(error.message()),
+                    };
+                    epg::set_guide(&request, result, WebEnv::now());
+                    advance_guide_clock();
+                    emit_event(&RuntimeEvent::NewState(vec![WebModelField::Discover]));
+                }),
+        );
+    }
+}
+
+/// Moves the programs on air forward, the next advance is scheduled while Discover shows channels
+fn advance_guide_clock() {
+    if let Some(next) = epg::advance_clock(WebEnv::now()) {
+        let timeout = i32::try_from((next - WebEnv::now()).num_milliseconds().max(0))
+            .unwrap_or(epg::CLOCK_INTERVAL * 1000);
+        WebEnv::set_timeout(
+            || {
+                let shows_channels = match RUNTIME.read().expect("runtime read failed").as_ref() {
+                    Some(Loadable::Ready(runtime)) => runtime
+                        .model()
+                        .expect("model read failed")
+                        .discover
+                        .catalog
+                        .first()
+                        .map_or(false, |first_page| {
+                            epg::is_channel_catalog(&first_page.request)
+                        }),
+                    _ => false,
+                };
+                if shows_channels {
+                    advance_guide_clock();
+                    emit_event(&RuntimeEvent::NewState(vec![WebModelField::Discover]));
+                }
+            },
+            timeout,
+        );
+    }
+}
+
 #[wasm_bindgen]
 #[cfg(debug_assertions)]
 pub fn get_debug_state() -> JsValue {
@@ -149,9 +1345,27 @@ pub fn get_debug_state() -> JsValue {
     JsValue::from_serde(&*model).unwrap()
 }
 
+/// A JSON document of the states, the recent logs and the environment for the bug reports,
+/// with the credentials and the emails scrubbed
 #[wasm_bindgen]
-pub fn get_state(field: JsValue) -> JsValue {
-    let field = field.into_serde().expect("get state failed");
+pub fn create_diagnostic_snapshot() -> String {
+    let runtime = RUNTIME.read().expect("runtime read failed");
+    let states = match runtime.as_ref() {
+        Some(Loadable::Ready(runtime)) => {
+            let model = runtime.model().expect("model read failed");
+            diagnostic_snapshot::SNAPSHOT_FIELDS
+                .iter()
+                .map(|field| (field.to_owned(), serialize_state(&model, field)))
+                .collect()
+        }
+        _ => vec![],
+    };
+    diagnostic_snapshot::create(states, WebEnv::now())
+}
+
+/// Installed addons cross-tabulated against the resources and types they provide
+#[wasm_bindgen]
+pub fn get_addon_capabilities() -> JsValue {
     let runtime = RUNTIME.read().expect("runtime read failed");
     let runtime = runtime
         .as_ref()
@@ -159,37 +1373,75 @@ pub fn get_state(field: JsValue) -> JsValue {
         .as_ref()
         .expect("runtime is not ready");
     let model = runtime.model().expect("model read failed");
-    model.get_state(&field)
+    serialize_addon_capabilities(&model.ctx.profile)
 }
 
+/// Sends the resource request to the addon as it is, for the addon console.
+/// The raw response is returned along with its schema warnings,
+/// a malformed request is returned as invalid instead of failing.
 #[wasm_bindgen]
-pub fn dispatch(action: JsValue, field: JsValue, location_hash: JsValue) {
-    let action = action.into_serde::<Action>().expect("dispatch failed");
-    let field = field.into_serde().expect("dispatch failed");
+pub async fn replay_resource_request(request: JsValue) -> JsValue {
+    let request = match request.into_serde::<ResourceRequest>() {
+        Ok(request) => request,
+        Err(error) => {
+            return JsValue::from_serde(&ReplayResult::InvalidRequest(error.to_string())).unwrap()
+        }
+    };
+    let addons = {
+        let runtime = RUNTIME.read().expect("runtime read failed");
+        match runtime.as_ref() {
+            Some(Loadable::Ready(runtime)) => {
+                let model = runtime.model().expect("model read failed");
+                model.ctx.profile.addons.to_owned()
+            }
+            _ => {
+                return JsValue::from_serde(&ReplayResult::InvalidRequest(
+                    "The runtime is not ready".to_owned(),
+                ))
+                .unwrap()
+            }
+        }
+    };
+    let result = addon_console::replay(request, &addons).await;
+    JsValue::from_serde(&result).unwrap()
+}
+
+/// Tests the network of the addon by fetching its manifest, a sample catalog and sample streams,
+/// the report is shown in the addon details once it's done
+#[wasm_bindgen]
+pub fn test_addon(transport_url: String) {
+    let transport_url = Url::parse(&transport_url).expect("test addon failed");
+    if !addon_diagnostics::start(&transport_url) {
+        return;
+    }
+    emit_event(&RuntimeEvent::NewState(vec![WebModelField::AddonDetails]));
+    WebEnv::exec_concurrent(async move {
+        let result = addon_diagnostics::run(transport_url.to_owned()).await;
+        addon_diagnostics::set_report(&transport_url, result);
+        emit_event(&RuntimeEvent::NewState(vec![WebModelField::AddonDetails]));
+    });
+}
+
+/// Share payload of the loaded meta item or of the playing stream, `null` when nothing is loaded
+#[wasm_bindgen]
+pub fn get_share_payload(args: JsValue) -> JsValue {
+    let args = args
+        .into_serde::<ShareArgs>()
+        .expect("get share payload failed");
     let runtime = RUNTIME.read().expect("runtime read failed");
     let runtime = runtime
         .as_ref()
         .expect("runtime is not ready")
         .as_ref()
         .expect("runtime is not ready");
-    {
-        let model = runtime.model().expect("model read failed");
-        let path = location_hash
-            .as_string()
-            .and_then(|location_hash| location_hash.split('#').last().map(|path| path.to_owned()))
-            .unwrap_or_default();
-        WebEnv::emit_to_analytics(
-            &WebEvent::CoreAction(Box::new(action.to_owned())),
-            &model,
-            &path,
-        );
-    }
-    runtime.dispatch(RuntimeAction { action, field });
+    let model = runtime.model().expect("model read failed");
+    serialize_share_payload(&model.meta_details, &model.player, &model.ctx, &args)
 }
 
+/// Library items, installed addons, their catalogs and settings matching the query,
+/// grouped for the quick switcher
 #[wasm_bindgen]
-pub fn analytics(event: JsValue, location_hash: JsValue) {
-    let event = event.into_serde().expect("analytics failed");
+pub fn global_search(query: String) -> JsValue {
     let runtime = RUNTIME.read().expect("runtime read failed");
     let runtime = runtime
         .as_ref()
@@ -197,18 +1449,1768 @@ pub fn analytics(event: JsValue, location_hash: JsValue) {
         .as_ref()
         .expect("runtime is not ready");
     let model = runtime.model().expect("model read failed");
-    let path = location_hash
-        .as_string()
-        .and_then(|location_hash| location_hash.split('#').last().map(|path| path.to_owned()))
-        .unwrap_or_default();
-    WebEnv::emit_to_analytics(&WebEvent::UIEvent(event), &model, &path);
+    serialize_global_search(&query, &model.ctx)
 }
 
+/// Library membership, watch state and notification count of each of the meta ids,
+/// e.g. for the badges of the addon preview rows
 #[wasm_bindgen]
-pub fn decode_stream(stream: JsValue) -> JsValue {
-    let stream = stream.as_string().map(Stream::decode);
-    match stream {
-        Some(Ok(stream)) => JsValue::from_serde(&stream).unwrap(),
-        _ => JsValue::NULL,
+pub fn get_library_status(ids: JsValue) -> JsValue {
+    let ids = ids
+        .into_serde::<Vec<String>>()
+        .expect("get library status failed");
+    let runtime = RUNTIME.read().expect("runtime read failed");
+    let runtime = runtime
+        .as_ref()
+        .expect("runtime is not ready")
+        .as_ref()
+        .expect("runtime is not ready");
+    let model = runtime.model().expect("model read failed");
+    serialize_library_status(&ids, &model.ctx)
+}
+
+/// Hover card of a meta item on the Board, served from what is already loaded.
+/// `null` until the meta item is loaded, the Board is updated then.
+#[wasm_bindgen]
+pub fn get_meta_preview_card(id: String, meta_type: String) -> JsValue {
+    let runtime = RUNTIME.read().expect("runtime read failed");
+    let runtime = runtime
+        .as_ref()
+        .expect("runtime is not ready")
+        .as_ref()
+        .expect("runtime is not ready");
+    let model = runtime.model().expect("model read failed");
+    serialize_meta_preview_card(&id, &meta_type, &model)
+}
+
+/// Selects a range of a numeric extra of Discover, e.g. the years or the minimum rating,
+/// translated to the value the addon expects. Both bounds `null` unselect the extra.
+/// Returns whether the selection changed.
+#[wasm_bindgen]
+pub fn select_discover_range(name: String, from: Option<f64>, to: Option<f64>) -> bool {
+    let runtime = RUNTIME.read().expect("runtime read failed");
+    let runtime = runtime
+        .as_ref()
+        .expect("runtime is not ready")
+        .as_ref()
+        .expect("runtime is not ready");
+    let request = {
+        let model = runtime.model().expect("model read failed");
+        let selected = match model.discover.selected.as_ref() {
+            Some(selected) => selected,
+            None => return false,
+        };
+        let selectable_extra = match model
+            .discover
+            .selectable
+            .extra
+            .iter()
+            .find(|selectable_extra| selectable_extra.name == name)
+        {
+            Some(selectable_extra) => selectable_extra,
+            None => return false,
+        };
+        let options = selectable_extra
+            .options
+            .iter()
+            .filter_map(|option| option.value.as_ref())
+            .collect::<Vec<_>>();
+        let value = range_extras::range_value(&name, &options, from, to);
+        if value.is_none() && (from.is_some() || to.is_some()) {
+            return false;
+        }
+        let request = range_extras::with_extra_value(&selected.request, &name, value);
+        if request == selected.request {
+            return false;
+        }
+        request
+    };
+    runtime.dispatch(RuntimeAction {
+        field: Some(WebModelField::Discover),
+        action: Action::Load(ActionLoad::CatalogWithFilters(Some(
+            CatalogWithFiltersSelected { request },
+        ))),
+    });
+    true
+}
+
+/// Preview of what opening the `stremio://` link does, `null` when it is not a valid one
+#[wasm_bindgen]
+pub fn parse_protocol_link(link: String) -> JsValue {
+    let runtime = RUNTIME.read().expect("runtime read failed");
+    let runtime = runtime
+        .as_ref()
+        .expect("runtime is not ready")
+        .as_ref()
+        .expect("runtime is not ready");
+    let model = runtime.model().expect("model read failed");
+    JsValue::from_serde(&ProtocolLink::parse(&link, &model.ctx.profile)).unwrap()
+}
+
+#[wasm_bindgen]
+pub fn get_addon_install_link(transport_url: String) -> JsValue {
+    let install_link = Url::parse(&transport_url)
+        .ok()
+        .and_then(|transport_url| addon_install_link(&transport_url));
+    JsValue::from_serde(&install_link).unwrap()
+}
+
+#[wasm_bindgen]
+pub fn get_state(field: JsValue) -> JsValue {
+    let field = field.into_serde().expect("get state failed");
+    if tab_sync::is_follower() {
+        if let Some(state) = tab_sync::remote_state(&field) {
+            return state;
+        }
+    }
+    let runtime = RUNTIME.read().expect("runtime read failed");
+    let runtime = runtime
+        .as_ref()
+        .expect("runtime is not ready")
+        .as_ref()
+        .expect("runtime is not ready");
+    let model = runtime.model().expect("model read failed");
+    let state = state_cache::get_or_serialize(&field, || serialize_state(&model, &field));
+    let exposures = features::take_pending_exposures();
+    if !exposures.is_empty() {
+        emit_exposures(exposures);
+    }
+    state
+}
+
+/// The state of the field as the UI receives it
+fn serialize_state(model: &WebModel, field: &WebModelField) -> JsValue {
+    let state = deep_links_ext::with_legacy_routes(model.get_state(field));
+    schema_version::with_schema_version(state)
+}
+
+/// The schema version of the states, along with the oldest one they can be adapted to
+/// and the one negotiated with the UI
+#[wasm_bindgen]
+pub fn get_schema_version() -> JsValue {
+    JsValue::from_serde(&schema_version::schema_version()).unwrap()
+}
+
+/// Items of an array of the state which was capped by the lite mode
+#[wasm_bindgen]
+pub fn get_state_slice(slice: JsValue, offset: usize, limit: usize) -> JsValue {
+    let slice = slice.into_serde().expect("get state slice failed");
+    let runtime = RUNTIME.read().expect("runtime read failed");
+    let runtime = runtime
+        .as_ref()
+        .expect("runtime is not ready")
+        .as_ref()
+        .expect("runtime is not ready");
+    let model = runtime.model().expect("model read failed");
+    deep_links_ext::with_legacy_routes(model.get_state_slice(&slice, offset, limit))
+}
+
+fn emit_exposures(exposures: Vec<(String, String)>) {
+    WebEnv::exec_concurrent(WebEnv::get_location_hash().map(move |location_hash| {
+        let runtime = RUNTIME.read().expect("runtime read failed");
+        let runtime = runtime
+            .as_ref()
+            .expect("runtime is not ready")
+            .as_ref()
+            .expect("runtime is not ready");
+        let model = runtime.model().expect("model read failed");
+        let path = location_hash
+            .split('#')
+            .last()
+            .map(|path| path.to_owned())
+            .unwrap_or_default();
+        for (experiment_id, variant) in exposures {
+            WebEnv::emit_to_analytics(
+                &WebEvent::ExperimentExposure {
+                    experiment_id,
+                    variant,
+                },
+                &model,
+                &path,
+            );
+        }
+    }));
+}
+
+/// Called by the shell on `visibilitychange`, background work is deferred while the page is hidden
+/// and the work which became due meanwhile is caught up on once it is visible again.
+#[wasm_bindgen]
+pub fn set_visibility(visible: bool) {
+    run_background_tasks(background::set_visible(visible, WebEnv::now()));
+}
+
+/// The background tasks along with their last and next runs
+#[wasm_bindgen]
+pub fn get_background_schedule() -> JsValue {
+    background::serialize_schedule()
+}
+
+/// Size of the wasm memory and of the caches kept on top of the runtime
+#[wasm_bindgen]
+pub fn get_memory_usage() -> JsValue {
+    JsValue::from_serde(&memory::usage()).unwrap()
+}
+
+/// Addon requests in flight and waiting for a slot, overall and per host
+#[wasm_bindgen]
+pub fn get_request_queue() -> JsValue {
+    JsValue::from_serde(&fetch_limiter::request_queue()).unwrap()
+}
+
+/// Drops caches when the webview is low on memory, the states are serialized again on demand
+#[wasm_bindgen]
+pub fn trim_memory(level: JsValue) {
+    let level = level.into_serde::<TrimLevel>().expect("trim memory failed");
+    memory::trim(level);
+}
+
+/// Declares the model fields currently observed by the UI, `null` observes all of them.
+#[wasm_bindgen]
+pub fn observe_fields(fields: JsValue) {
+    let fields = fields
+        .into_serde::<Option<Vec<WebModelField>>>()
+        .expect("observe fields failed");
+    let stale_fields = observed_fields::set_observed_fields(fields);
+    if !stale_fields.is_empty() {
+        emit_to_ui(&RuntimeEvent::NewState(stale_fields));
+    }
+}
+
+/// Drops the action when it's invalid for the current state,
+/// an `ActionRejected` event is emitted instead.
+/// The emitted events carry the `action_id`, one is generated when it's not given.
+#[wasm_bindgen]
+pub fn dispatch(action: JsValue, field: JsValue, location_hash: JsValue, action_id: JsValue) {
+    let action_id = action_id
+        .as_string()
+        .unwrap_or_else(|| hex::encode(WebEnv::random_buffer(8)));
+    event_sequence::dispatched(action_id.to_owned(), || {
+        dispatch_action(action, field, location_hash, action_id)
+    });
+}
+
+fn dispatch_action(action: JsValue, field: JsValue, location_hash: JsValue, action_id: String) {
+    let raw_action = action;
+    let action = raw_action.into_serde::<Action>().expect("dispatch failed");
+    // the ctx is owned by the leader tab
+    if matches!(action, Action::Ctx(_)) && tab_sync::is_follower() {
+        tab_sync::forward_dispatch(
+            &raw_action,
+            location_hash.as_string().unwrap_or_default(),
+            Some(action_id),
+        );
+        return;
+    }
+    // the player updates the library items of the ctx, so the tab which plays takes over
+    // the leadership and the player is loaded once it owns the ctx
+    if matches!(action, Action::Load(ActionLoad::Player(_))) && tab_sync::is_follower() {
+        tab_sync::take_over(
+            raw_action,
+            field,
+            location_hash.as_string().unwrap_or_default(),
+            action_id,
+        );
+        return;
+    }
+    if background::is_deferred_action(&action) {
+        return;
+    }
+    if still_watching::on_action(&action, &location_hash.as_string().unwrap_or_default()) {
+        return;
+    }
+    let field = field.into_serde().expect("dispatch failed");
+    if field == Some(WebModelField::Board) && matches!(action, Action::Load(_)) {
+        board_refresh::clear();
+    }
+    let runtime = RUNTIME.read().expect("runtime read failed");
+    let runtime = runtime
+        .as_ref()
+        .expect("runtime is not ready")
+        .as_ref()
+        .expect("runtime is not ready");
+    {
+        let model = runtime.model().expect("model read failed");
+        if let Err(rejection) = action_validation::validate(&action, field.as_ref(), &model) {
+            emit_web_event(&WebStateEvent::ActionRejected(rejection));
+            return;
+        }
+        let path = location_hash
+            .as_string()
+            .and_then(|location_hash| location_hash.split('#').last().map(|path| path.to_owned()))
+            .unwrap_or_default();
+        WebEnv::emit_to_analytics(
+            &WebEvent::CoreAction(Box::new(action.to_owned())),
+            &model,
+            &path,
+        );
+        // without a user the changes are never pushed to the API
+        if model.ctx.profile.auth.is_some() {
+            library_pending::start(&action);
+        }
+        let timeout = web_settings::web_settings().undo.timeout;
+        if timeout > 0 {
+            let expires_at = WebEnv::now() + Duration::seconds(timeout as i64);
+            if undo::record(&action, &model.ctx, expires_at).is_some() {
+                WebEnv::set_timeout(
+                    || {
+                        if undo::expire(WebEnv::now()) {
+                            emit_event(&RuntimeEvent::NewState(vec![WebModelField::Ctx]));
+                        }
+                    },
+                    timeout as i32 * 1000,
+                );
+            }
+        }
+    }
+    // the web sort and the tag are selected by the location the library is loaded for
+    if let (Action::Load(ActionLoad::LibraryWithFilters(_)), Some(field)) = (&action, &field) {
+        let root = match field {
+            WebModelField::ContinueWatching => Some("continuewatching"),
+            WebModelField::Library => Some("library"),
+            _ => None,
+        };
+        if let Some(root) = root {
+            let location_hash = location_hash.as_string().unwrap_or_default();
+            library_sort::set_selected_sort(
+                root.to_owned(),
+                library_sort::parse_web_sort(&location_hash),
+            );
+            library_tags::set_selected_tag(
+                root.to_owned(),
+                library_tags::parse_tag(&location_hash),
+            );
+        }
+    }
+    // the loads in flight of the previous selection are cancelled, instead of arriving late
+    let loading_field = match action {
+        Action::Load(_) | Action::Unload => field.to_owned(),
+        _ => None,
+    };
+    let previous_load = loading_field.as_ref().map(|loading_field| {
+        let model = runtime.model().expect("model read failed");
+        (
+            load_cancellation::selected(&model, loading_field),
+            load_cancellation::in_flight(loading_field),
+        )
+    });
+    if let Some(loading_field) = loading_field.as_ref() {
+        // the same selection loads nothing, so the cancelled loads are started from scratch
+        if matches!(action, Action::Load(_)) && load_cancellation::take_cancelled(loading_field) {
+            runtime.dispatch(RuntimeAction {
+                action: Action::Unload,
+                field: Some(loading_field.to_owned()),
+            });
+        }
+    }
+    // a Discover catalog opened mid-way starts at a full row of the grid
+    let action = match (action, &field) {
+        (
+            Action::Load(ActionLoad::CatalogWithFilters(Some(selected))),
+            Some(WebModelField::Discover),
+        ) => {
+            let request =
+                grid_density::grid_layout(&placeholders::poster_shape(&selected.request), None)
+                    .and_then(|layout| grid_density::aligned_request(&selected.request, &layout))
+                    .unwrap_or(selected.request);
+            Action::Load(ActionLoad::CatalogWithFilters(Some(
+                CatalogWithFiltersSelected { request },
+            )))
+        }
+        (action, _) => action,
+    };
+    load_cancellation::with_loading_field(loading_field.to_owned(), || {
+        runtime.dispatch(RuntimeAction { action, field })
+    });
+    if let (Some(loading_field), Some((previous_selected, in_flight))) =
+        (loading_field, previous_load)
+    {
+        let model = runtime.model().expect("model read failed");
+        if load_cancellation::selected(&model, &loading_field) != previous_selected {
+            drop(model);
+            load_cancellation::cancel(&in_flight);
+        }
+    }
+}
+
+/// Aborts the loads of the field which are still in flight, e.g. when its page is left.
+/// They are serialized as `{ "type": "Cancelled" }` and loaded again by the next load of the field.
+#[wasm_bindgen]
+pub fn cancel_loads(field: JsValue) {
+    let field = field
+        .into_serde::<WebModelField>()
+        .expect("cancel loads failed");
+    if load_cancellation::cancel(&load_cancellation::in_flight(&field)) {
+        load_cancellation::set_cancelled(&field);
+    }
+}
+
+/// Shows the streams of the addon which timed out, the ones still loading once they are loaded
+#[wasm_bindgen]
+pub fn load_timed_out_streams(transport_url: String) {
+    let transport_url = Url::parse(&transport_url).expect("load timed out streams failed");
+    if stream_timeouts::load_anyway(&transport_url) {
+        emit_event(&RuntimeEvent::NewState(vec![WebModelField::MetaDetails]));
+    }
+}
+
+/// Reverts an addon install or uninstall or a library removal while it can still be undone.
+/// Returns whether the action was reverted.
+#[wasm_bindgen]
+pub fn undo(action_id: String) -> bool {
+    let action = match undo::take_inverse(&action_id, WebEnv::now()) {
+        Some(action) => action,
+        None => return false,
+    };
+    let runtime = RUNTIME.read().expect("runtime read failed");
+    let runtime = runtime
+        .as_ref()
+        .expect("runtime is not ready")
+        .as_ref()
+        .expect("runtime is not ready");
+    {
+        let model = runtime.model().expect("model read failed");
+        if model.ctx.profile.auth.is_some() {
+            library_pending::start(&action);
+        }
+    }
+    runtime.dispatch(RuntimeAction {
+        action,
+        field: None,
+    });
+    true
+}
+
+#[wasm_bindgen]
+pub fn analytics(event: JsValue, location_hash: JsValue) {
+    let event = event.into_serde().expect("analytics failed");
+    let runtime = RUNTIME.read().expect("runtime read failed");
+    let runtime = runtime
+        .as_ref()
+        .expect("runtime is not ready")
+        .as_ref()
+        .expect("runtime is not ready");
+    let model = runtime.model().expect("model read failed");
+    let path = location_hash
+        .as_string()
+        .and_then(|location_hash| location_hash.split('#').last().map(|path| path.to_owned()))
+        .unwrap_or_default();
+    WebEnv::emit_to_analytics(&WebEvent::UIEvent(event), &model, &path);
+}
+
+#[wasm_bindgen]
+pub fn decode_stream(stream: JsValue) -> JsValue {
+    let stream = stream.as_string().map(Stream::decode);
+    match stream {
+        Some(Ok(stream)) => JsValue::from_serde(&stream).unwrap(),
+        _ => JsValue::NULL,
+    }
+}
+
+/// Lists the files of the torrent of a stream, e.g. the episodes of a season pack, under the stream
+#[wasm_bindgen]
+pub fn expand_season_pack(info_hash: String) {
+    let (stream, is_playing) = {
+        let runtime = RUNTIME.read().expect("runtime read failed");
+        let runtime = match runtime.as_ref() {
+            Some(Loadable::Ready(runtime)) => runtime,
+            _ => return,
+        };
+        let model = runtime.model().expect("model read failed");
+        let stream = model
+            .meta_details
+            .streams
+            .iter()
+            .filter_map(|streams| streams.content.as_ref()?.ready())
+            .flatten()
+            .find(|stream| season_packs::info_hash(stream).as_ref() == Some(&info_hash))
+            .cloned();
+        let is_playing = model.player.selected.as_ref().map_or(false, |selected| {
+            season_packs::info_hash(&selected.stream).as_ref() == Some(&info_hash)
+        });
+        match stream {
+            Some(stream) => (stream, is_playing),
+            None => return,
+        }
+    };
+    if !season_packs::start_expanding(&info_hash) {
+        return;
+    }
+    emit_event(&RuntimeEvent::NewState(vec![WebModelField::MetaDetails]));
+    WebEnv::exec_concurrent(
+        season_packs::fetch_files(&stream, is_playing).map(move |result| {
+            season_packs::set_files(&info_hash, result.map_err(|error| error.message()));
+            emit_event(&RuntimeEvent::NewState(vec![WebModelField::MetaDetails]));
+        }),
+    );
+}
+
+#[wasm_bindgen]
+pub fn dismiss_announcement(id: String) {
+    let dismissed = remote_config::dismiss_announcement(id);
+    WebEnv::exec_concurrent(
+        WebEnv::set_storage(DISMISSED_ANNOUNCEMENTS_STORAGE_KEY, Some(&dismissed)).map(|result| {
+            if let Err(error) = result {
+                error!("Failed to persist dismissed announcements: {error:?}");
+            }
+        }),
+    );
+    emit_event(&RuntimeEvent::NewState(vec![WebModelField::Board]));
+}
+
+/// Called by the watch party connection of the shell whenever the presence of the members changes,
+/// `null` once the party is left.
+#[wasm_bindgen]
+pub fn set_watch_party_presence(presence: JsValue) {
+    let presence = presence
+        .into_serde::<Option<Presence>>()
+        .expect("set watch party presence failed");
+    if watch_party::set_presence(presence) {
+        emit_event(&RuntimeEvent::NewState(vec![WebModelField::Player]));
+    }
+}
+
+/// Describes the viewport of the Discover grid, `null` stops the grid density hints
+#[wasm_bindgen]
+pub fn set_viewport(viewport: JsValue) {
+    let viewport = viewport
+        .into_serde::<Option<Viewport>>()
+        .expect("set viewport failed");
+    if grid_density::set_viewport(viewport) {
+        emit_event(&RuntimeEvent::NewState(vec![WebModelField::Discover]));
+    }
+}
+
+/// Selects one of the web sorts for the given library root (`library` or `continuewatching`),
+/// `null` goes back to the sort selected in core.
+#[wasm_bindgen]
+pub fn set_library_sort(root: String, sort: JsValue) {
+    let sort = sort
+        .into_serde::<Option<WebSort>>()
+        .expect("set library sort failed");
+    let field = match root.as_str() {
+        "continuewatching" => WebModelField::ContinueWatching,
+        _ => WebModelField::Library,
+    };
+    library_sort::set_selected_sort(root, sort);
+    emit_event(&RuntimeEvent::NewState(vec![field]));
+}
+
+/// Adds or removes a tag of a library item, or filters the library of a root by a tag
+#[wasm_bindgen]
+pub fn library_tags(action: JsValue) {
+    let action = action
+        .into_serde::<LibraryTagsAction>()
+        .expect("library tags failed");
+    if let Some(library_tags) = library_tags::update_library_tags(action) {
+        persist_library_tags(&library_tags);
+    }
+    emit_event(&RuntimeEvent::NewState(vec![
+        WebModelField::Library,
+        WebModelField::ContinueWatching,
+    ]));
+}
+
+/// Rates an item from 1 to 10 or notes it
+#[wasm_bindgen]
+pub fn user_ratings(action: JsValue) {
+    tab_sync::broadcast_side_action(SideAction::UserRatings, &action);
+    apply_user_ratings(action);
+}
+
+fn apply_user_ratings(action: JsValue) {
+    let action = action
+        .into_serde::<UserRatingsAction>()
+        .expect("user ratings failed");
+    let user_ratings = match user_ratings::update_user_ratings(action) {
+        Some(user_ratings) => user_ratings,
+        None => return,
+    };
+    persist_user_ratings(&user_ratings);
+    emit_event(&RuntimeEvent::NewState(vec![
+        WebModelField::Library,
+        WebModelField::ContinueWatching,
+        WebModelField::MetaDetails,
+    ]));
+}
+
+/// All of the tags of the library items, along with the number of items tagged by them
+#[wasm_bindgen]
+pub fn get_library_tags() -> JsValue {
+    let runtime = RUNTIME.read().expect("runtime read failed");
+    let runtime = runtime
+        .as_ref()
+        .expect("runtime is not ready")
+        .as_ref()
+        .expect("runtime is not ready");
+    let model = runtime.model().expect("model read failed");
+    let ids = model
+        .ctx
+        .library
+        .items
+        .values()
+        .filter(|library_item| !library_item.removed)
+        .map(|library_item| &library_item.id);
+    JsValue::from_serde(&library_tags::tag_counts(ids)).unwrap()
+}
+
+/// The library with the watch state of its items, as the contents of a CSV or a JSON file
+#[wasm_bindgen]
+pub fn export_library(format: JsValue) -> JsValue {
+    let format = format
+        .into_serde::<ExportFormat>()
+        .expect("export library failed");
+    let runtime = RUNTIME.read().expect("runtime read failed");
+    let runtime = runtime
+        .as_ref()
+        .expect("runtime is not ready")
+        .as_ref()
+        .expect("runtime is not ready");
+    let model = runtime.model().expect("model read failed");
+    JsValue::from_str(&library_transfer::export(&model.ctx.library, format))
+}
+
+/// Adds the movies and the series of a file exported from another service to the library.
+/// The progress is reported as part of the data export state.
+#[wasm_bindgen]
+pub fn import_library(source: JsValue, content: String) {
+    let source = source
+        .into_serde::<ImportSource>()
+        .expect("import library failed");
+    let addons = {
+        let runtime = RUNTIME.read().expect("runtime read failed");
+        let runtime = runtime
+            .as_ref()
+            .expect("runtime is not ready")
+            .as_ref()
+            .expect("runtime is not ready");
+        let model = runtime.model().expect("model read failed");
+        model.ctx.profile.addons.to_owned()
+    };
+    match library_transfer::parse(source, &content) {
+        Ok((entries, skipped)) => {
+            let import_id = library_transfer::start_import(source, entries.len(), skipped);
+            WebEnv::exec_concurrent(async move {
+                // one entry at a time, so that the meta addons are not flooded with requests
+                for entry in entries {
+                    if !import_library_entry(import_id, entry, &addons).await {
+                        return;
+                    }
+                    emit_event(&RuntimeEvent::NewState(vec![WebModelField::DataExport]));
+                }
+            });
+        }
+        Err(error) => library_transfer::fail_import(source, error),
+    };
+    emit_event(&RuntimeEvent::NewState(vec![WebModelField::DataExport]));
+}
+
+/// Returns whether the import goes on, it stops once the runtime is destroyed
+/// or another import is started. The watched entries are marked as watched,
+/// including the ones which were in the library already.
+async fn import_library_entry(import_id: u64, entry: ImportEntry, addons: &[Descriptor]) -> bool {
+    let in_library = match entry.imdb_id.as_ref().map(|imdb_id| is_in_library(imdb_id)) {
+        Some(Some(in_library)) => in_library,
+        Some(None) => return false,
+        None => false,
+    };
+    let (id, reason) = if in_library {
+        (entry.imdb_id.to_owned(), Some(SkipReason::AlreadyInLibrary))
+    } else {
+        match library_transfer::resolve(&entry, addons).await {
+            Some(meta_item) => match is_in_library(&meta_item.id) {
+                Some(true) => (Some(meta_item.id), Some(SkipReason::AlreadyInLibrary)),
+                Some(false) => {
+                    let id = meta_item.id.to_owned();
+                    dispatch_ctx(ActionCtx::AddToLibrary(meta_item));
+                    (Some(id), None)
+                }
+                None => return false,
+            },
+            None => (None, None),
+        }
+    };
+    if let (Some(id), true) = (&id, entry.watched) {
+        dispatch_ctx(ActionCtx::LibraryItemMarkAsWatched {
+            id: id.to_owned(),
+            is_watched: true,
+        });
+    }
+    library_transfer::set_resolved(import_id, entry, id, reason)
+}
+
+/// `None` when the runtime is not ready
+fn is_in_library(id: &str) -> Option<bool> {
+    let runtime = RUNTIME.read().expect("runtime read failed");
+    let runtime = match runtime.as_ref() {
+        Some(Loadable::Ready(runtime)) => runtime,
+        _ => return None,
+    };
+    let model = runtime.model().expect("model read failed");
+    Some(
+        model
+            .ctx
+            .library
+            .items
+            .get(id)
+            .map_or(false, |library_item| !library_item.removed),
+    )
+}
+
+/// The meta item a library item is added to the library with
+fn library_item_preview(library_item: &LibraryItem) -> serde_json::Result<MetaItemPreview> {
+    serde_json::from_value(serde_json::json!({
+        "id": library_item.id,
+        "type": library_item.r#type,
+        "name": library_item.name,
+        "poster": library_item.poster,
+        "posterShape": library_item.poster_shape,
+        "behaviorHints": library_item.behavior_hints,
+    }))
+}
+
+fn dispatch_ctx(action: ActionCtx) {
+    let action = Action::Ctx(action);
+    // the ctx is owned by the leader tab
+    if tab_sync::is_follower() {
+        // the events of the action carry the id of the dispatch which caused it
+        tab_sync::forward_dispatch(
+            &JsValue::from_serde(&action).unwrap(),
+            String::new(),
+            event_sequence::action_id(),
+        );
+        return;
+    }
+    if let Some(Loadable::Ready(runtime)) = RUNTIME.read().expect("runtime read failed").as_ref() {
+        runtime.dispatch(RuntimeAction {
+            field: None,
+            action,
+        });
+    }
+}
+
+#[wasm_bindgen]
+pub fn onboarding(action: JsValue) {
+    let action = action
+        .into_serde::<OnboardingAction>()
+        .expect("onboarding failed");
+    let config = remote_config::onboarding_config();
+    match action {
+        OnboardingAction::Skip => complete_onboarding(),
+        OnboardingAction::Finish => finish_onboarding(&config),
+        action => onboarding::update(action, &config),
+    };
+    emit_event(&RuntimeEvent::NewState(vec![WebModelField::Ctx]));
+}
+
+/// Fetches the manifests of the selected addons first,
+/// so either all of them are installed along with the settings or none of them.
+fn finish_onboarding(config: &onboarding::OnboardingConfig) {
+    let (language, transport_urls) = match onboarding::start_finishing(config) {
+        Some(choices) => choices,
+        None => return,
+    };
+    WebEnv::exec_concurrent(
+        future::try_join_all(transport_urls.into_iter().map(|transport_url| {
+            WebEnv::addon_transport(&transport_url)
+                .manifest()
+                .map_ok(|manifest| Descriptor {
+                    manifest,
+                    transport_url,
+                    flags: Default::default(),
+                })
+        }))
+        .map(move |result| {
+            match result {
+                Ok(descriptors) => {
+                    let (descriptors, settings) = {
+                        let runtime = RUNTIME.read().expect("runtime read failed");
+                        let runtime = runtime
+                            .as_ref()
+                            .expect("runtime is not ready")
+                            .as_ref()
+                            .expect("runtime is not ready");
+                        let model = runtime.model().expect("model read failed");
+                        let descriptors =
+                            descriptors
+                                .into_iter()
+                                .filter(|descriptor| {
+                                    !model.ctx.profile.addons.iter().any(|addon| {
+                                        addon.transport_url == descriptor.transport_url
+                                    })
+                                })
+                                .collect::<Vec<_>>();
+                        let settings = language.map(|interface_language| Settings {
+                            interface_language,
+                            ..model.ctx.profile.settings.to_owned()
+                        });
+                        (descriptors, settings)
+                    };
+                    for descriptor in descriptors {
+                        dispatch_ctx(ActionCtx::InstallAddon(descriptor));
+                    }
+                    if let Some(settings) = settings {
+                        dispatch_ctx(ActionCtx::UpdateSettings(settings));
+                    }
+                    onboarding::set_finished(Ok(()));
+                    persist_onboarding_completed();
+                }
+                Err(error) => onboarding::set_finished(Err(error.message())),
+            };
+            emit_event(&RuntimeEvent::NewState(vec![WebModelField::Ctx]));
+        }),
+    );
+}
+
+fn complete_onboarding() {
+    onboarding::set_completed(true);
+    persist_onboarding_completed();
+}
+
+/// Answers the "Are you still watching?" prompt of the player
+#[wasm_bindgen]
+pub fn still_watching(action: JsValue) {
+    let action = action
+        .into_serde::<StillWatchingAction>()
+        .expect("still watching failed");
+    let held_load = still_watching::take_held_load();
+    still_watching::clear();
+    match action {
+        StillWatchingAction::Continue => {
+            emit_event(&RuntimeEvent::NewState(vec![WebModelField::Player]));
+            if let Some((action, location_hash)) = held_load {
+                dispatch(
+                    JsValue::from_serde(&action).unwrap(),
+                    JsValue::from_serde(&WebModelField::Player).unwrap(),
+                    JsValue::from_str(&location_hash),
+                    JsValue::NULL,
+                );
+            }
+        }
+        StillWatchingAction::Stop => {
+            let runtime = RUNTIME.read().expect("runtime read failed");
+            let runtime = runtime
+                .as_ref()
+                .expect("runtime is not ready")
+                .as_ref()
+                .expect("runtime is not ready");
+            runtime.dispatch(RuntimeAction {
+                field: Some(WebModelField::Player),
+                action: Action::Unload,
+            });
+        }
+    }
+}
+
+/// Adds a subtitles file (SRT, WebVTT or ASS) picked by the user to the tracks of the playing video,
+/// returns the track or throws when the file can't be converted
+#[wasm_bindgen]
+pub fn upload_subtitles(
+    file_name: String,
+    lang: String,
+    bytes: Vec<u8>,
+) -> Result<JsValue, JsValue> {
+    let video_id = {
+        let runtime = RUNTIME.read().expect("runtime read failed");
+        let runtime = runtime
+            .as_ref()
+            .expect("runtime is not ready")
+            .as_ref()
+            .expect("runtime is not ready");
+        let model = runtime.model().expect("model read failed");
+        model
+            .player
+            .selected
+            .as_ref()
+            .and_then(|selected| selected.stream_request.as_ref())
+            .map(|stream_request| stream_request.path.id.to_owned())
+    };
+    let video_id = video_id.ok_or_else(|| JsValue::from_str("No video is playing"))?;
+    let (subtitles, uploaded) =
+        uploaded_subtitles::upload(&video_id, &file_name, &lang, &bytes, WebEnv::now())
+            .map_err(|error| JsValue::from_str(&error))?;
+    persist_uploaded_subtitles(&uploaded);
+    emit_event(&RuntimeEvent::NewState(vec![WebModelField::Player]));
+    let url = subtitles.url().map_err(|error| JsValue::from_str(&error))?;
+    Ok(JsValue::from_serde(&serde_json::json!({
+        "id": subtitles.id,
+        "lang": subtitles.lang,
+        "label": subtitles.label,
+        "url": url,
+    }))
+    .unwrap())
+}
+
+#[wasm_bindgen]
+pub fn remove_uploaded_subtitles(id: String) {
+    persist_uploaded_subtitles(&uploaded_subtitles::remove(&id));
+    emit_event(&RuntimeEvent::NewState(vec![WebModelField::Player]));
+}
+
+/// Sets, adjusts or estimates the delay of the subtitles of the stream in the player,
+/// returns the applied offset or `null` when it could not be estimated
+#[wasm_bindgen]
+pub fn subtitles_sync(action: JsValue) -> JsValue {
+    let action = action
+        .into_serde::<SubtitlesSyncAction>()
+        .expect("subtitles sync failed");
+    let stream = {
+        let runtime = RUNTIME.read().expect("runtime read failed");
+        let runtime = runtime
+            .as_ref()
+            .expect("runtime is not ready")
+            .as_ref()
+            .expect("runtime is not ready");
+        let model = runtime.model().expect("model read failed");
+        model
+            .player
+            .selected
+            .as_ref()
+            .map(|selected| selected.stream.to_owned())
+    };
+    match stream.and_then(|stream| subtitles_sync::update_offset(&stream, action, WebEnv::now())) {
+        Some((offset, offsets)) => {
+            persist_subtitles_offsets(&offsets);
+            emit_event(&RuntimeEvent::NewState(vec![WebModelField::Player]));
+            JsValue::from_serde(&offset).unwrap()
+        }
+        None => JsValue::NULL,
+    }
+}
+
+/// Registers the translator of the app, called with the texts of the cues, the target and
+/// the source language, resolving to the translated texts.
+/// `null` falls back to the translation endpoint of the settings.
+#[wasm_bindgen]
+pub fn register_subtitles_translator(translator: Option<js_sys::Function>) {
+    subtitles_translation::set_translator(translator);
+}
+
+/// Translates the cues of a loaded track of the player, the translated track is added
+/// to the subtitles of the player once it's ready
+#[wasm_bindgen]
+pub fn translate_subtitles(request: JsValue) {
+    let request = request
+        .into_serde::<TranslationRequest>()
+        .expect("translate subtitles failed");
+    let video_id = {
+        let runtime = RUNTIME.read().expect("runtime read failed");
+        let runtime = runtime
+            .as_ref()
+            .expect("runtime is not ready")
+            .as_ref()
+            .expect("runtime is not ready");
+        let model = runtime.model().expect("model read failed");
+        model
+            .player
+            .selected
+            .as_ref()
+            .and_then(|selected| selected.stream_request.as_ref())
+            .map(|stream_request| stream_request.path.id.to_owned())
+    };
+    let id = match video_id
+        .and_then(|video_id| subtitles_translation::start_translating(&video_id, &request))
+    {
+        Some(id) => id,
+        None => return,
+    };
+    emit_event(&RuntimeEvent::NewState(vec![WebModelField::Player]));
+    WebEnv::exec_concurrent(
+        subtitles_translation::translate(request).map(move |result| {
+            subtitles_translation::set_translation(&id, result);
+            emit_event(&RuntimeEvent::NewState(vec![WebModelField::Player]));
+        }),
+    );
+}
+
+/// Enables or disables the sync of the profile and the library with the other devices
+/// of the local network through the streaming server, or syncs right away.
+/// Throws when the server can't be reached by the other devices.
+#[wasm_bindgen]
+pub fn lan_sync(action: JsValue) -> Result<(), JsValue> {
+    let action = action
+        .into_serde::<LanSyncAction>()
+        .expect("lan sync failed");
+    match action {
+        LanSyncAction::Enable { server_url } => {
+            let lan_sync = lan_sync::set_enabled(true, Some(server_url))
+                .map_err(|error| JsValue::from_str(&error))?;
+            persist_lan_sync(&lan_sync);
+            sync_lan();
+        }
+        LanSyncAction::Disable => {
+            if let Ok(lan_sync) = lan_sync::set_enabled(false, None) {
+                persist_lan_sync(&lan_sync);
+            }
+        }
+        LanSyncAction::Sync => sync_lan(),
+        LanSyncAction::ResolveConflict { keep } => {
+            lan_sync::set_resolution(keep);
+            sync_lan();
+        }
+    };
+    emit_event(&RuntimeEvent::NewState(vec![WebModelField::Ctx]));
+    Ok(())
+}
+
+/// Hides a Continue Watching item for a number of days or until a new episode, or shows it again
+#[wasm_bindgen]
+pub fn snooze(action: JsValue) {
+    tab_sync::broadcast_side_action(SideAction::Snooze, &action);
+    apply_snooze(action);
+}
+
+fn apply_snooze(action: JsValue) {
+    let action = action.into_serde::<SnoozeAction>().expect("snooze failed");
+    persist_snoozes(&snooze::update_snoozes(action, WebEnv::now()));
+    emit_event(&RuntimeEvent::NewState(vec![
+        WebModelField::ContinueWatchingPreview,
+    ]));
+}
+
+/// Applies the action on the side state which another tab was asked for,
+/// so the state of every tab is the same and the leader persists it
+pub fn apply_side_action(side_action: SideAction, action: JsValue) {
+    match side_action {
+        SideAction::Snooze => apply_snooze(action),
+        SideAction::MetaOverrides => apply_meta_overrides(action),
+        SideAction::BlockedItems => apply_blocked_items(action),
+        SideAction::UserRatings => apply_user_ratings(action),
+    }
+}
+
+/// Starts watching a series again from the first episode, resetting its watch state and keeping
+/// aside the one from before, or stops it and restores the watch state
+#[wasm_bindgen]
+pub fn rewatch(action: JsValue) {
+    let action = action
+        .into_serde::<RewatchAction>()
+        .expect("rewatch failed");
+    let rewatches = {
+        let runtime = RUNTIME.read().expect("runtime read failed");
+        let runtime = match runtime.as_ref() {
+            Some(Loadable::Ready(runtime)) => runtime,
+            _ => return,
+        };
+        let model = runtime.model().expect("model read failed");
+        rewatch::update_rewatches(action, &model.ctx.library, WebEnv::now())
+    };
+    if let Some((rewatches, watch_state_action)) = rewatches {
+        if let Some(watch_state_action) = watch_state_action {
+            dispatch_ctx(watch_state_action);
+        }
+        persist_rewatches(&rewatches);
+        emit_event(&RuntimeEvent::NewState(vec![
+            WebModelField::ContinueWatchingPreview,
+            WebModelField::MetaDetails,
+        ]));
+    }
+}
+
+/// Blocks an item the user is not interested in, hiding it from all of the catalogs, or unblocks it
+#[wasm_bindgen]
+pub fn blocked_items(action: JsValue) {
+    tab_sync::broadcast_side_action(SideAction::BlockedItems, &action);
+    apply_blocked_items(action);
+}
+
+fn apply_blocked_items(action: JsValue) {
+    let action = action
+        .into_serde::<BlockedItemsAction>()
+        .expect("blocked items failed");
+    persist_blocked_items(&blocklist::update_blocked_items(action));
+    emit_event(&RuntimeEvent::NewState(vec![
+        WebModelField::Ctx,
+        WebModelField::Board,
+        WebModelField::Discover,
+        WebModelField::Search,
+    ]));
+}
+
+/// Adds or removes a mirror of an installed addon, tried when its transport url is unavailable
+#[wasm_bindgen]
+pub fn addon_mirrors(action: JsValue) {
+    let action = action
+        .into_serde::<MirrorsAction>()
+        .expect("addon mirrors failed");
+    persist_addon_mirrors(&mirrors::update_mirrors(action));
+    emit_event(&RuntimeEvent::NewState(vec![
+        WebModelField::InstalledAddons,
+    ]));
+}
+
+/// Installs or dismisses the available update of an installed addon
+#[wasm_bindgen]
+pub fn addon_updates(action: JsValue) {
+    let action = action
+        .into_serde::<AddonUpdatesAction>()
+        .expect("addon updates failed");
+    match action {
+        AddonUpdatesAction::Install(transport_url) => {
+            let available = match addon_updates::take_available(&transport_url) {
+                Some(available) => available,
+                None => return,
+            };
+            let (addon, installed) = {
+                let runtime = RUNTIME.read().expect("runtime read failed");
+                let runtime = match runtime.as_ref() {
+                    Some(Loadable::Ready(runtime)) => runtime,
+                    _ => return,
+                };
+                let model = runtime.model().expect("model read failed");
+                let addon = model.ctx.profile.addons.iter().find(|addon| {
+                    addon.transport_url == transport_url
+                        && addon.manifest.version == available.update.previous_version
+                });
+                let installed = model
+                    .ctx
+                    .profile
+                    .addons
+                    .iter()
+                    .map(|addon| addon.transport_url.to_owned())
+                    .collect::<Vec<_>>();
+                (addon.cloned(), installed)
+            };
+            // the addon could have been uninstalled or upgraded in the meantime
+            let addon = match addon {
+                Some(addon) => addon,
+                None => return,
+            };
+            let update = AddonUpdate {
+                updated_at: WebEnv::now(),
+                ..available.update
+            };
+            persist_addon_updates(&addon_updates::record(update, &installed));
+            dispatch_ctx(ActionCtx::InstallAddon(Descriptor {
+                manifest: available.manifest,
+                transport_url: addon.transport_url,
+                flags: addon.flags,
+            }));
+        }
+        AddonUpdatesAction::Dismiss(transport_url) => {
+            addon_updates::dismiss_available(&transport_url)
+        }
+    };
+    emit_event(&RuntimeEvent::NewState(vec![
+        WebModelField::InstalledAddons,
+    ]));
+}
+
+/// Sets or clears the custom poster or name of an item, shown instead of the ones of the addon
+#[wasm_bindgen]
+pub fn meta_overrides(action: JsValue) {
+    tab_sync::broadcast_side_action(SideAction::MetaOverrides, &action);
+    apply_meta_overrides(action);
+}
+
+fn apply_meta_overrides(action: JsValue) {
+    let action = action
+        .into_serde::<MetaOverridesAction>()
+        .expect("meta overrides failed");
+    persist_meta_overrides(&meta_overrides::update_meta_overrides(action));
+    extract_palettes(palettes::start_extracting(meta_overrides::posters()));
+    emit_shortcuts();
+    emit_event(&RuntimeEvent::NewState(vec![
+        WebModelField::ContinueWatchingPreview,
+        WebModelField::Board,
+        WebModelField::Discover,
+        WebModelField::Search,
+        WebModelField::Library,
+        WebModelField::ContinueWatching,
+        WebModelField::MetaDetails,
+    ]));
+}
+
+/// Pins or unpins a catalog to the shortcuts of the installed app
+#[wasm_bindgen]
+pub fn pinned_catalogs(action: JsValue) {
+    let action = action
+        .into_serde::<PinnedCatalogsAction>()
+        .expect("pinned catalogs failed");
+    persist_pinned_catalogs(&shortcuts::update_pinned_catalogs(action));
+    emit_shortcuts();
+}
+
+/// Entries for the `shortcuts` of the web app manifest, updates are emitted as `ShortcutsChanged`
+#[wasm_bindgen]
+pub fn get_shortcuts() -> JsValue {
+    let runtime = RUNTIME.read().expect("runtime read failed");
+    let runtime = runtime
+        .as_ref()
+        .expect("runtime is not ready")
+        .as_ref()
+        .expect("runtime is not ready");
+    let model = runtime.model().expect("model read failed");
+    JsValue::from_serde(&shortcuts::shortcuts(
+        &model.continue_watching_preview,
+        &model.ctx,
+    ))
+    .unwrap()
+}
+
+fn emit_shortcuts() {
+    let shortcuts = match RUNTIME.read().expect("runtime read failed").as_ref() {
+        Some(Loadable::Ready(runtime)) => {
+            let model = runtime.model().expect("model read failed");
+            shortcuts::shortcuts(&model.continue_watching_preview, &model.ctx)
+        }
+        _ => return,
+    };
+    if let Some(shortcuts) = shortcuts::changed_shortcuts(shortcuts) {
+        emit_web_event(&WebStateEvent::ShortcutsChanged(shortcuts));
+    }
+}
+
+/// Reminds of the events loaded in the meta details, the due reminders are part of the ctx notifications
+#[wasm_bindgen]
+pub fn event_reminders(action: JsValue) {
+    let action = action
+        .into_serde::<ReminderAction>()
+        .expect("event reminders failed");
+    let reminders = match action {
+        ReminderAction::RemindMe(id) => {
+            let reminder = {
+                let runtime = RUNTIME.read().expect("runtime read failed");
+                let runtime = runtime
+                    .as_ref()
+                    .expect("runtime is not ready")
+                    .as_ref()
+                    .expect("runtime is not ready");
+                let model = runtime.model().expect("model read failed");
+                model.meta_details.meta_items.iter().find_map(|meta_item| {
+                    match &meta_item.content {
+                        Some(Loadable::Ready(meta_item_content))
+                            if meta_item_content.preview.id == id =>
+                        {
+                            Reminder::new(
+                                meta_item_content.preview.to_owned(),
+                                meta_item.request.to_owned(),
+                                WebEnv::now(),
+                            )
+                        }
+                        _ => None,
+                    }
+                })
+            };
+            match reminder {
+                Some(reminder) => reminders::add(reminder),
+                None => return,
+            }
+        }
+        ReminderAction::Cancel(id) => reminders::remove(&id),
+        ReminderAction::Dismiss(id) => reminders::dismiss(&id),
+    };
+    persist_reminders(&reminders);
+    emit_event(&RuntimeEvent::NewState(vec![WebModelField::Ctx]));
+}
+
+/// Updates the avatar or the display name of the user, the update is shown until the API rejects it
+#[wasm_bindgen]
+pub fn profile_display(action: JsValue) {
+    let action = action
+        .into_serde::<ProfileDisplayAction>()
+        .expect("profile display failed");
+    let update = {
+        let runtime = RUNTIME.read().expect("runtime read failed");
+        let runtime = runtime
+            .as_ref()
+            .expect("runtime is not ready")
+            .as_ref()
+            .expect("runtime is not ready");
+        let model = runtime.model().expect("model read failed");
+        model.ctx.profile.auth.as_ref().and_then(|auth| {
+            account::start_update(action, &auth.user)
+                .map(|display| (auth.key.to_owned(), auth.user.id.to_owned(), display))
+        })
+    };
+    emit_event(&RuntimeEvent::NewState(vec![WebModelField::Ctx]));
+    let (auth_key, user_id, display) = match update {
+        Some(update) => update,
+        None => return,
+    };
+    WebEnv::exec_concurrent(
+        account::save_display(&auth_key, &display).map(move |result| {
+            let saved_display =
+                account::finish_update(user_id, display, result.map_err(|error| error.message()));
+            if let Some(saved_display) = saved_display {
+                persist_profile_display(&saved_display);
+            }
+            emit_event(&RuntimeEvent::NewState(vec![WebModelField::Ctx]));
+        }),
+    );
+}
+
+/// Loads the devices the user is logged in on or logs one of them out
+#[wasm_bindgen]
+pub fn account_sessions(action: JsValue) {
+    let action = action
+        .into_serde::<SessionsAction>()
+        .expect("account sessions failed");
+    let auth = {
+        let runtime = RUNTIME.read().expect("runtime read failed");
+        let runtime = runtime
+            .as_ref()
+            .expect("runtime is not ready")
+            .as_ref()
+            .expect("runtime is not ready");
+        let model = runtime.model().expect("model read failed");
+        model.ctx.profile.auth.to_owned()
+    };
+    let auth = match auth {
+        Some(auth) => auth,
+        None => return,
+    };
+    match action {
+        SessionsAction::Load => {
+            let user_id = auth.user.id;
+            account::start_loading_sessions(user_id.to_owned());
+            WebEnv::exec_concurrent(account::fetch_sessions(&auth.key).map(move |result| {
+                let sessions = match result {
+                    Ok(sessions) => Loadable::Ready(sessions),
+                    Err(error) => Loadable::Err(error.message()),
+                };
+                account::set_sessions(user_id, sessions);
+                emit_event(&RuntimeEvent::NewState(vec![WebModelField::Ctx]));
+            }));
+        }
+        SessionsAction::Revoke(session_id) => {
+            if !account::start_revoking(&session_id) {
+                return;
+            }
+            WebEnv::exec_concurrent(account::revoke_session(&auth.key, &session_id).map(
+                move |result| {
+                    if let Err(error) = &result {
+                        error!("Failed to revoke session: {error:?}");
+                    }
+                    account::finish_revoking(&session_id, result.is_ok());
+                    emit_event(&RuntimeEvent::NewState(vec![WebModelField::Ctx]));
+                },
+            ));
+        }
+    };
+    emit_event(&RuntimeEvent::NewState(vec![WebModelField::Ctx]));
+}
+
+/// Resends the email verification or starts the password reset
+#[wasm_bindgen]
+pub fn email_flow(action: JsValue) {
+    let action = action
+        .into_serde::<EmailFlowAction>()
+        .expect("email flow failed");
+    match action {
+        EmailFlowAction::ResendVerification => {
+            let auth = {
+                let runtime = RUNTIME.read().expect("runtime read failed");
+                let runtime = runtime
+                    .as_ref()
+                    .expect("runtime is not ready")
+                    .as_ref()
+                    .expect("runtime is not ready");
+                let model = runtime.model().expect("model read failed");
+                model.ctx.profile.auth.to_owned()
+            };
+            match auth {
+                Some(auth) => send_email(
+                    EmailFlowKind::EmailVerification,
+                    auth.user.email,
+                    account::send_verification_email(&auth.key),
+                ),
+                None => account::set_email_flow_error(
+                    EmailFlowKind::EmailVerification,
+                    EmailFlowError::new(
+                        EmailFlowErrorCode::NotLoggedIn,
+                        "Log in to verify your email",
+                    ),
+                ),
+            }
+        }
+        EmailFlowAction::ResetPassword(email) => {
+            let email = email.trim().to_owned();
+            let send = account::send_password_reset(&email);
+            send_email(EmailFlowKind::PasswordReset, email, send);
+        }
+        EmailFlowAction::Clear => account::clear_email_flows(),
+    };
+    emit_event(&RuntimeEvent::NewState(vec![WebModelField::Ctx]));
+}
+
+/// Sends the email unless the flow rejects it, the future is dropped in that case
+fn send_email(kind: EmailFlowKind, email: String, send: TryEnvFuture<()>) {
+    if !account::start_sending(kind, &email, WebEnv::now()) {
+        return;
+    }
+    WebEnv::exec_concurrent(send.map(move |result| {
+        account::finish_sending(
+            kind,
+            email,
+            result.map_err(|error| error.message()),
+            WebEnv::now(),
+        );
+        emit_event(&RuntimeEvent::NewState(vec![WebModelField::Ctx]));
+    }));
+}
+
+fn persist_profile_display(saved_display: &SavedProfileDisplay) {
+    WebEnv::exec_concurrent(
+        WebEnv::set_storage(PROFILE_DISPLAY_STORAGE_KEY, Some(saved_display)).map(|result| {
+            if let Err(error) = result {
+                error!("Failed to persist profile display: {error:?}");
+            }
+        }),
+    );
+}
+
+fn persist_addon_updates(updates: &[AddonUpdate]) {
+    WebEnv::exec_concurrent(
+        WebEnv::set_storage(ADDON_UPDATES_STORAGE_KEY, Some(&updates)).map(|result| {
+            if let Err(error) = result {
+                error!("Failed to persist addon updates: {error:?}");
+            }
+        }),
+    );
+}
+
+fn persist_stream_history(played_streams: &[PlayedStream]) {
+    WebEnv::exec_concurrent(
+        WebEnv::set_storage(STREAM_HISTORY_STORAGE_KEY, Some(&played_streams)).map(|result| {
+            if let Err(error) = result {
+                error!("Failed to persist stream history: {error:?}");
+            }
+        }),
+    );
+}
+
+fn persist_uploaded_subtitles(uploaded: &[UploadedSubtitles]) {
+    WebEnv::exec_concurrent(
+        WebEnv::set_storage(UPLOADED_SUBTITLES_STORAGE_KEY, Some(&uploaded)).map(|result| {
+            if let Err(error) = result {
+                error!("Failed to persist uploaded subtitles: {error:?}");
+            }
+        }),
+    );
+}
+
+fn persist_lan_sync(lan_sync: &LanSync) {
+    WebEnv::exec_concurrent(
+        WebEnv::set_storage(LAN_SYNC_STORAGE_KEY, Some(lan_sync)).map(|result| {
+            if let Err(error) = result {
+                error!("Failed to persist lan sync: {error:?}");
+            }
+        }),
+    );
+}
+
+fn persist_subtitles_offsets(offsets: &[SubtitlesOffset]) {
+    WebEnv::exec_concurrent(
+        WebEnv::set_storage(SUBTITLES_OFFSETS_STORAGE_KEY, Some(&offsets)).map(|result| {
+            if let Err(error) = result {
+                error!("Failed to persist subtitles offsets: {error:?}");
+            }
+        }),
+    );
+}
+
+fn persist_new_episodes() {
+    WebEnv::exec_concurrent(
+        WebEnv::set_storage(NEW_EPISODES_STORAGE_KEY, Some(&new_episodes::state())).map(|result| {
+            if let Err(error) = result {
+                error!("Failed to persist new episodes: {error:?}");
+            }
+        }),
+    );
+}
+
+fn persist_meta_overrides(meta_overrides: &HashMap<String, MetaOverride>) {
+    WebEnv::exec_concurrent(
+        WebEnv::set_storage(META_OVERRIDES_STORAGE_KEY, Some(meta_overrides)).map(|result| {
+            if let Err(error) = result {
+                error!("Failed to persist meta overrides: {error:?}");
+            }
+        }),
+    );
+}
+
+fn persist_user_ratings(user_ratings: &HashMap<String, UserRating>) {
+    WebEnv::exec_concurrent(
+        WebEnv::set_storage(USER_RATINGS_STORAGE_KEY, Some(user_ratings)).map(|result| {
+            if let Err(error) = result {
+                error!("Failed to persist user ratings: {error:?}");
+            }
+        }),
+    );
+}
+
+fn persist_blocked_items(blocked_items: &[BlockedItem]) {
+    WebEnv::exec_concurrent(
+        WebEnv::set_storage(BLOCKED_ITEMS_STORAGE_KEY, Some(&blocked_items)).map(|result| {
+            if let Err(error) = result {
+                error!("Failed to persist blocked items: {error:?}");
+            }
+        }),
+    );
+}
+
+fn persist_snoozes(snoozes: &[Snooze]) {
+    WebEnv::exec_concurrent(
+        WebEnv::set_storage(SNOOZED_ITEMS_STORAGE_KEY, Some(&snoozes)).map(|result| {
+            if let Err(error) = result {
+                error!("Failed to persist snoozed items: {error:?}");
+            }
+        }),
+    );
+}
+
+fn persist_rewatches(rewatches: &[Rewatch]) {
+    WebEnv::exec_concurrent(
+        WebEnv::set_storage(REWATCHES_STORAGE_KEY, Some(&rewatches)).map(|result| {
+            if let Err(error) = result {
+                error!("Failed to persist rewatches: {error:?}");
+            }
+        }),
+    );
+}
+
+fn persist_library_sort_keys(sort_keys: &HashMap<String, SortKeys>) {
+    WebEnv::exec_concurrent(
+        WebEnv::set_storage(LIBRARY_SORT_KEYS_STORAGE_KEY, Some(sort_keys)).map(|result| {
+            if let Err(error) = result {
+                error!("Failed to persist library sort keys: {error:?}");
+            }
+        }),
+    );
+}
+
+fn persist_library_tags(library_tags: &HashMap<String, Vec<String>>) {
+    WebEnv::exec_concurrent(
+        WebEnv::set_storage(LIBRARY_TAGS_STORAGE_KEY, Some(library_tags)).map(|result| {
+            if let Err(error) = result {
+                error!("Failed to persist library tags: {error:?}");
+            }
+        }),
+    );
+}
+
+/// Also called by the mirror transport once the mirrors declared in a manifest change
+pub fn persist_addon_mirrors(mirrors: &HashMap<Url, AddonMirrors>) {
+    WebEnv::exec_concurrent(
+        WebEnv::set_storage(ADDON_MIRRORS_STORAGE_KEY, Some(mirrors)).map(|result| {
+            if let Err(error) = result {
+                error!("Failed to persist addon mirrors: {error:?}");
+            }
+        }),
+    );
+}
+
+fn persist_pinned_catalogs(pinned_catalogs: &[PinnedCatalog]) {
+    WebEnv::exec_concurrent(
+        WebEnv::set_storage(PINNED_CATALOGS_STORAGE_KEY, Some(&pinned_catalogs)).map(|result| {
+            if let Err(error) = result {
+                error!("Failed to persist pinned catalogs: {error:?}");
+            }
+        }),
+    );
+}
+
+fn persist_reminders(reminders: &[Reminder]) {
+    WebEnv::exec_concurrent(
+        WebEnv::set_storage(REMINDERS_STORAGE_KEY, Some(&reminders)).map(|result| {
+            if let Err(error) = result {
+                error!("Failed to persist event reminders: {error:?}");
+            }
+        }),
+    );
+}
+
+fn persist_device_profile(device_profile: &DeviceProfile) {
+    WebEnv::exec_concurrent(
+        WebEnv::set_storage(DEVICE_PROFILE_STORAGE_KEY, Some(device_profile)).map(|result| {
+            if let Err(error) = result {
+                error!("Failed to persist device profile: {error:?}");
+            }
+        }),
+    );
+}
+
+fn persist_onboarding_completed() {
+    WebEnv::exec_concurrent(
+        WebEnv::set_storage(ONBOARDING_COMPLETED_STORAGE_KEY, Some(&true)).map(|result| {
+            if let Err(error) = result {
+                error!("Failed to persist onboarding completion: {error:?}");
+            }
+        }),
+    );
+}
+
+/// Lists and removes the torrents active on the streaming server,
+/// the jobs are serialized as part of the streaming server state.
+#[wasm_bindgen]
+pub fn streaming_server_jobs(action: JsValue) {
+    let action = action
+        .into_serde::<StreamingServerJobsAction>()
+        .expect("streaming server jobs failed");
+    match action {
+        StreamingServerJobsAction::Refresh => {
+            if background::is_visible() {
+                refresh_streaming_server_jobs();
+            }
+        }
+        StreamingServerJobsAction::Remove(info_hash) => {
+            remove_streaming_server_jobs(Some(info_hash))
+        }
+        StreamingServerJobsAction::RemoveAll => remove_streaming_server_jobs(None),
+    };
+}
+
+fn refresh_streaming_server_jobs() {
+    WebEnv::exec_concurrent(streaming_server_jobs::fetch_jobs().map(|result| {
+        streaming_server_jobs::set_jobs(result.map_err(|error| error.message()));
+        emit_event(&RuntimeEvent::NewState(vec![
+            WebModelField::StreamingServer,
+        ]));
+    }));
+}
+
+fn remove_streaming_server_jobs(info_hash: Option<String>) {
+    streaming_server_jobs::start_removing(info_hash.as_deref());
+    emit_event(&RuntimeEvent::NewState(vec![
+        WebModelField::StreamingServer,
+    ]));
+    WebEnv::exec_concurrent(streaming_server_jobs::remove_job(info_hash.as_deref()).map(
+        move |result| {
+            if let Err(error) = result {
+                error!("Failed to remove streaming server job: {error:?}");
+            }
+            streaming_server_jobs::finish_removing(info_hash.as_deref());
+            refresh_streaming_server_jobs();
+        },
+    ));
+}
+
+/// Queries and clears the cache of the streaming server,
+/// the size and the clearing progress are serialized as part of the streaming server state.
+#[wasm_bindgen]
+pub fn streaming_server_cache(action: JsValue) {
+    let action = action
+        .into_serde::<StreamingServerCacheAction>()
+        .expect("streaming server cache failed");
+    match action {
+        StreamingServerCacheAction::QuerySize => query_streaming_server_cache_size(),
+        StreamingServerCacheAction::Clear => clear_streaming_server_cache(),
+    };
+}
+
+fn query_streaming_server_cache_size() {
+    streaming_server_cache::set_size(Loadable::Loading);
+    emit_event(&RuntimeEvent::NewState(vec![
+        WebModelField::StreamingServer,
+    ]));
+    WebEnv::exec_concurrent(streaming_server_jobs::fetch_jobs().map(|result| {
+        streaming_server_cache::set_size(match result {
+            Ok(jobs) => Loadable::Ready(CacheSize::from_jobs(&jobs)),
+            Err(error) => Loadable::Err(error.message()),
+        });
+        emit_event(&RuntimeEvent::NewState(vec![
+            WebModelField::StreamingServer,
+        ]));
+    }));
+}
+
+/// Removes the cached torrents one by one, so the progress can be reported.
+/// The torrent of the stream which is played is kept.
+fn clear_streaming_server_cache() {
+    if streaming_server_cache::is_clearing() {
+        return;
+    }
+    let playing_info_hash = {
+        let runtime = RUNTIME.read().expect("runtime read failed");
+        let runtime = match runtime.as_ref() {
+            Some(Loadable::Ready(runtime)) => runtime,
+            _ => return,
+        };
+        let model = runtime.model().expect("model read failed");
+        model
+            .player
+            .selected
+            .as_ref()
+            .and_then(|selected| season_packs::info_hash(&selected.stream))
+    };
+    streaming_server_cache::set_clearing(CacheClearing::InProgress {
+        cleared: 0,
+        total: 0,
+    });
+    emit_event(&RuntimeEvent::NewState(vec![
+        WebModelField::StreamingServer,
+    ]));
+    WebEnv::exec_concurrent(async move {
+        let result = async {
+            let jobs = streaming_server_jobs::fetch_jobs()
+                .await?
+                .into_iter()
+                .filter(|job| Some(&job.info_hash) != playing_info_hash.as_ref())
+                .collect::<Vec<_>>();
+            let total = jobs.len();
+            for (index, job) in jobs.iter().enumerate() {
+                streaming_server_cache::set_clearing(CacheClearing::InProgress {
+                    cleared: index,
+                    total,
+                });
+                emit_event(&RuntimeEvent::NewState(vec![
+                    WebModelField::StreamingServer,
+                ]));
+                streaming_server_jobs::remove_job(Some(&job.info_hash)).await?;
+            }
+            Ok::<_, EnvError>(total)
+        }
+        .await;
+        streaming_server_cache::set_clearing(match result {
+            Ok(cleared) => CacheClearing::Done { cleared },
+            Err(error) => CacheClearing::Failed(error.message()),
+        });
+        emit_event(&RuntimeEvent::NewState(vec![
+            WebModelField::StreamingServer,
+        ]));
+        query_streaming_server_cache_size();
+        refresh_streaming_server_jobs();
+    });
+}
+
+/// Registers an in-memory addon under a `mock://` transport url, it can then be installed as any other addon.
+#[cfg(feature = "mock-addon")]
+#[wasm_bindgen]
+pub fn register_mock_addon(transport_url: String, mock_addon: JsValue) {
+    let transport_url = url::Url::parse(&transport_url).expect("register mock addon failed");
+    let mock_addon = mock_addon.into_serde().expect("register mock addon failed");
+    crate::mock_addon::register_mock_addon(transport_url, mock_addon);
+}
+
+#[cfg(feature = "mock-addon")]
+#[wasm_bindgen]
+pub fn unregister_mock_addon(transport_url: String) {
+    let transport_url = url::Url::parse(&transport_url).expect("unregister mock addon failed");
+    crate::mock_addon::unregister_mock_addon(&transport_url);
+}
+
+#[wasm_bindgen]
+pub fn update_web_settings(settings: JsValue) {
+    let settings = settings
+        .into_serde::<WebSettings>()
+        .expect("update web settings failed");
+    WebEnv::exec_concurrent(
+        WebEnv::set_storage(WEB_SETTINGS_STORAGE_KEY, Some(&settings)).map(|result| {
+            if let Err(error) = result {
+                error!("Failed to persist web settings: {error:?}");
+            }
+        }),
+    );
+    let previous_settings = web_settings::web_settings();
+    let debrid_changed = previous_settings.debrid != settings.debrid;
+    let mut fields = vec![WebModelField::Ctx];
+    if previous_settings.continue_watching != settings.continue_watching {
+        fields.push(WebModelField::ContinueWatchingPreview);
+    }
+    web_settings::set_web_settings(settings);
+    if debrid_changed {
+        debrid::reset();
+        load_debrid_account();
     }
+    emit_event(&RuntimeEvent::NewState(fields));
 }
@@ -0,0 +1,204 @@
+use std::{collections::HashMap, sync::RwLock};
+
+use chrono::{DateTime, Utc};
+use lazy_static::lazy_static;
+use serde::{Deserialize, Serialize};
+
+use stremio_core::types::resource::Stream;
+
+use crate::stream_history::StreamKey;
+
+pub const SUBTITLES_OFFSETS_STORAGE_KEY: &str = "subtitles_offsets";
+/// Streams the offset is remembered for, the least recently synchronized are dropped
+const MAX_OFFSETS: usize = 200;
+/// Largest delay looked for between the subtitles and the audio, in milliseconds
+const MAX_OFFSET: i64 = 60_000;
+/// Width of the buckets the candidate offsets are voted into, in milliseconds
+const BUCKET_SIZE: i64 = 100;
+/// A cue matches a reference time when they are at most this far apart at the offset
+const MATCH_TOLERANCE: i64 = 300;
+/// Fewer matching cues are not enough to tell the offset apart from a coincidence
+const MIN_MATCHES: usize = 5;
+
+lazy_static! {
+    /// Most recently synchronized last
+    static ref OFFSETS: RwLock<Vec<SubtitlesOffset>> = Default::default();
+}
+
+#[derive(Clone, PartialEq, Serialize, Deserialize, Debug)]
+#[serde(rename_all = "camelCase")]
+pub struct SubtitlesOffset {
+    pub stream: StreamKey,
+    /// Milliseconds the subtitles are shown later, negative for earlier
+    pub offset: i64,
+    /// Share of the cues which matched the reference at the offset, `None` when set by the user
+    pub confidence: Option<f64>,
+    pub updated_at: DateTime<Utc>,
+}
+
+/// The times are in milliseconds since the start of the video
+#[derive(Deserialize, Debug)]
+#[serde(tag = "action", content = "args")]
+pub enum SubtitlesSyncAction {
+    SetOffset(i64),
+    /// Moves the subtitles by the given milliseconds, e.g. by the nudge buttons of the player
+    AdjustOffset(i64),
+    Reset,
+    /// Matches the starts of the cues of the selected track to the times speech was detected in the audio
+    EstimateFromAudio {
+        speech: Vec<u64>,
+        cues: Vec<u64>,
+    },
+    /// Matches the starts of the cues of the selected track to the ones of a track known to be in sync
+    EstimateFromReference {
+        reference: Vec<u64>,
+        cues: Vec<u64>,
+    },
+}
+
+pub fn set_offsets(offsets: Vec<SubtitlesOffset>) {
+    *OFFSETS.write().expect("subtitles offsets write failed") = offsets;
+}
+
+/// The offset of the subtitles of the stream, `None` for the streams which are opened outside of the player
+pub fn offset(stream: &Stream) -> Option<SubtitlesOffset> {
+    let stream = StreamKey::from_stream(stream)?;
+    OFFSETS
+        .read()
+        .expect("subtitles offsets read failed")
+        .iter()
+        .find(|offset| offset.stream == stream)
+        .cloned()
+}
+
+/// Applies the action to the offset of the stream, returns the applied offset along with all of
+/// the offsets to be persisted. `None` when the offset could not be estimated.
+pub fn update_offset(
+    stream: &Stream,
+    action: SubtitlesSyncAction,
+    now: DateTime<Utc>,
+) -> Option<(SubtitlesOffset, Vec<SubtitlesOffset>)> {
+    let stream = StreamKey::from_stream(stream)?;
+    let current = OFFSETS
+        .read()
+        .expect("subtitles offsets read failed")
+        .iter()
+        .find(|offset| offset.stream == stream)
+        .map_or(0, |offset| offset.offset);
+    let (offset, confidence) = match action {
+        SubtitlesSyncAction::SetOffset(offset) => (offset, None),
+        SubtitlesSyncAction::AdjustOffset(delta) => (current + delta, None),
+        SubtitlesSyncAction::Reset => (0, None),
+        SubtitlesSyncAction::EstimateFromAudio { speech, cues } => {
+            let (offset, confidence) = estimate(&speech, &cues)?;
+            (offset, Some(confidence))
+        }
+        SubtitlesSyncAction::EstimateFromReference { reference, cues } => {
+            let (offset, confidence) = estimate(&reference, &cues)?;
+            (offset, Some(confidence))
+        }
+    };
+    let subtitles_offset = SubtitlesOffset {
+        stream,
+        offset,
+        confidence,
+        updated_at: now,
+    };
+    let mut offsets = OFFSETS.write().expect("subtitles offsets write failed");
+    offsets.retain(|offset| offset.stream != subtitles_offset.stream);
+    if subtitles_offset.offset != 0 {
+        if offsets.len() >= MAX_OFFSETS {
+            offsets.remove(0);
+        }
+        offsets.push(subtitles_offset.to_owned());
+    }
+    Some((subtitles_offset, offsets.to_owned()))
+}
+
+/// The offset most of the cues agree on once shifted onto the reference times,
+/// along with the share of the cues which match at it.
+///
+/// Every pair of a cue and a reference time close enough votes for the offset between them,
+/// the offset is then refined to the median of the votes of the winning bucket.
+fn estimate(reference: &[u64], cues: &[u64]) -> Option<(i64, f64)> {
+    let mut reference = reference
+        .iter()
+        .map(|time| *time as i64)
+        .collect::<Vec<_>>();
+    reference.sort_unstable();
+    let cues = cues.iter().map(|time| *time as i64).collect::<Vec<_>>();
+    let mut votes = HashMap::<i64, Vec<i64>>::new();
+    for cue in &cues {
+        let start = reference.partition_point(|time| *time < cue - MAX_OFFSET);
+        let end = reference.partition_point(|time| *time <= cue + MAX_OFFSET);
+        for time in &reference[start..end] {
+            let offset = time - cue;
+            votes
+                .entry(offset.div_euclid(BUCKET_SIZE))
+                .or_default()
+                .push(offset);
+        }
+    }
+    let (_, mut offsets) = votes
+        .into_iter()
+        .max_by_key(|(bucket, offsets)| (offsets.len(), -bucket.abs()))?;
+    offsets.sort_unstable();
+    let offset = offsets[offsets.len() / 2];
+    let matches = cues
+        .iter()
+        .filter(|cue| {
+            let shifted = *cue + offset;
+            let index = reference.partition_point(|time| *time < shifted);
+            [index.checked_sub(1), Some(index)]
+                .into_iter()
+                .flatten()
+                .filter_map(|index| reference.get(index))
+                .any(|time| (time - shifted).abs() <= MATCH_TOLERANCE)
+        })
+        .count();
+    if matches < MIN_MATCHES {
+        return None;
+    }
+    Some((offset, matches as f64 / cues.len() as f64))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const SPEECH: [u64; 8] = [
+        10_000, 13_500, 19_200, 26_100, 30_700, 38_400, 45_900, 51_300,
+    ];
+
+    fn shifted(times: &[u64], shift: i64) -> Vec<u64> {
+        times
+            .iter()
+            .map(|time| (*time as i64 + shift) as u64)
+            .collect()
+    }
+
+    #[test]
+    fn early_and_late_cues() {
+        assert_eq!(
+            estimate(&SPEECH, &shifted(&SPEECH, -2_300)),
+            Some((2_300, 1.0))
+        );
+        assert_eq!(
+            estimate(&SPEECH, &shifted(&SPEECH, 1_500)),
+            Some((-1_500, 1.0))
+        );
+    }
+
+    #[test]
+    fn unmatched_cues() {
+        let mut cues = shifted(&SPEECH, -2_300);
+        cues.extend([100_000, 150_000]);
+        assert_eq!(estimate(&SPEECH, &cues), Some((2_300, 0.8)));
+    }
+
+    #[test]
+    fn too_few_matches() {
+        assert_eq!(estimate(&SPEECH[..3], &shifted(&SPEECH[..3], -2_300)), None);
+        assert_eq!(estimate(&[], &SPEECH), None);
+    }
+}
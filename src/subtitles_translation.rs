@@ -0,0 +1,278 @@
+use std::{
+    cell::RefCell,
+    collections::{hash_map::DefaultHasher, HashMap, VecDeque},
+    hash::{Hash, Hasher},
+    sync::RwLock,
+};
+
+use futures::{future::LocalBoxFuture, FutureExt, TryFutureExt};
+use http::Request;
+use lazy_static::lazy_static;
+use serde::{Deserialize, Serialize};
+use url::Url;
+use wasm_bindgen::{JsCast, JsValue};
+use wasm_bindgen_futures::JsFuture;
+
+use stremio_core::{
+    models::common::Loadable,
+    runtime::{Env, EnvError},
+};
+
+use crate::{env::WebEnv, uploaded_subtitles, web_settings};
+
+/// Shown as the origin of the translated tracks, in place of the name of an addon
+pub const TRANSLATED_SUBTITLES_ORIGIN: &str = "Translated";
+/// Translated cues kept in memory, the oldest are dropped first
+const MAX_CACHED_CUES: usize = 5000;
+
+thread_local! {
+    /// Registered by the app, called with the texts, the target and the source language,
+    /// resolves to the translated texts in the same order
+    static TRANSLATOR: RefCell<Option<js_sys::Function>> = RefCell::new(None);
+}
+
+lazy_static! {
+    /// Translated texts by the hash of the cue and the target language
+    static ref CACHE: RwLock<(HashMap<u64, String>, VecDeque<u64>)> = Default::default();
+    static ref TRANSLATIONS: RwLock<Vec<SubtitlesTranslation>> = Default::default();
+}
+
+/// Times are in milliseconds since the start of the video
+#[derive(Clone, Deserialize, Debug)]
+#[serde(rename_all = "camelCase")]
+pub struct Cue {
+    pub start: u64,
+    pub end: u64,
+    pub text: String,
+}
+
+/// The cues of a loaded track to be translated, parsed by the player
+#[derive(Deserialize, Debug)]
+#[serde(rename_all = "camelCase")]
+pub struct TranslationRequest {
+    /// Id of the track in the subtitles of the player
+    pub id: String,
+    pub lang: Option<String>,
+    pub target_lang: String,
+    pub cues: Vec<Cue>,
+}
+
+#[derive(Clone, Serialize, Debug)]
+#[serde(rename_all = "camelCase")]
+pub struct SubtitlesTranslation {
+    /// Id of the translated track in the subtitles of the player
+    pub id: String,
+    pub video_id: String,
+    pub source_id: String,
+    pub lang: String,
+    /// `data:` url of the translated track
+    pub url: Loadable<Url, String>,
+}
+
+#[derive(Serialize)]
+#[serde(rename_all = "camelCase")]
+struct EndpointRequest<'a> {
+    texts: &'a [String],
+    source: Option<&'a str>,
+    target: &'a str,
+}
+
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct EndpointResponse {
+    translations: Vec<String>,
+}
+
+pub fn set_translator(translator: Option<js_sys::Function>) {
+    TRANSLATOR.with(|current| *current.borrow_mut() = translator);
+}
+
+/// Marks the translation as loading, returns its id. `None` when the track is translated
+/// already or it's being translated, failed translations are tried again.
+/// Only the translations of the video are kept, the tracks of the others are large `data:` urls.
+pub fn start_translating(video_id: &str, request: &TranslationRequest) -> Option<String> {
+    let id = format!("translated_{}_{}", request.id, request.target_lang);
+    let mut translations = TRANSLATIONS
+        .write()
+        .expect("subtitles translations write failed");
+    if translations
+        .iter()
+        .any(|translation| translation.id == id && !translation.url.is_err())
+    {
+        return None;
+    }
+    translations.retain(|translation| translation.id != id && translation.video_id == video_id);
+    translations.push(SubtitlesTranslation {
+        id: id.to_owned(),
+        video_id: video_id.to_owned(),
+        source_id: request.id.to_owned(),
+        lang: request.target_lang.to_owned(),
+        url: Loadable::Loading,
+    });
+    Some(id)
+}
+
+pub fn set_translation(id: &str, result: Result<Url, String>) {
+    if let Some(translation) = TRANSLATIONS
+        .write()
+        .expect("subtitles translations write failed")
+        .iter_mut()
+        .find(|translation| translation.id == id)
+    {
+        translation.url = match result {
+            Ok(url) => Loadable::Ready(url),
+            Err(error) => Loadable::Err(error),
+        };
+    }
+}
+
+/// The translations of the tracks of the video, along with the ones still loading or failed
+pub fn translations(video_id: &str) -> Vec<SubtitlesTranslation> {
+    TRANSLATIONS
+        .read()
+        .expect("subtitles translations read failed")
+        .iter()
+        .filter(|translation| translation.video_id == video_id)
+        .cloned()
+        .collect()
+}
+
+/// Translates the cues which are not cached yet and builds the translated track
+pub fn translate(request: TranslationRequest) -> LocalBoxFuture<'static, Result<Url, String>> {
+    let keys = request
+        .cues
+        .iter()
+        .map(|cue| cue_key(&cue.text, &request.target_lang))
+        .collect::<Vec<_>>();
+    let missing = {
+        let cache = CACHE
+            .read()
+            .expect("subtitles translation cache read failed");
+        request
+            .cues
+            .iter()
+            .zip(&keys)
+            .filter(|(_, key)| !cache.0.contains_key(key))
+            .map(|(cue, key)| (cue.text.to_owned(), *key))
+            .collect::<Vec<_>>()
+    };
+    async move {
+        if !missing.is_empty() {
+            let (texts, missing_keys): (Vec<_>, Vec<_>) = missing.into_iter().unzip();
+            let translated = translate_texts(
+                texts,
+                request.lang.to_owned(),
+                request.target_lang.to_owned(),
+            )
+            .await?;
+            if translated.len() != missing_keys.len() {
+                return Err("The translator returned a different number of texts".to_owned());
+            }
+            cache(missing_keys.into_iter().zip(translated));
+        }
+        let cache = CACHE
+            .read()
+            .expect("subtitles translation cache read failed");
+        let cues = request
+            .cues
+            .iter()
+            .zip(&keys)
+            .map(|(cue, key)| {
+                let text = cache.0.get(key).unwrap_or(&cue.text);
+                format!(
+                    "{} --> {}\n{}",
+                    vtt_timestamp(cue.start),
+                    vtt_timestamp(cue.end),
+                    text
+                )
+            })
+            .collect::<Vec<_>>();
+        uploaded_subtitles::vtt_url(&format!("WEBVTT\n\n{}\n", cues.join("\n\n")))
+    }
+    .boxed_local()
+}
+
+pub fn clear() {
+    TRANSLATIONS
+        .write()
+        .expect("subtitles translations write failed")
+        .clear();
+}
+
+/// Through the translator registered by the app, otherwise through the configured endpoint
+fn translate_texts(
+    texts: Vec<String>,
+    source: Option<String>,
+    target: String,
+) -> LocalBoxFuture<'static, Result<Vec<String>, String>> {
+    let translator = TRANSLATOR.with(|translator| translator.borrow().to_owned());
+    if let Some(translator) = translator {
+        let texts = JsValue::from_serde(&texts).unwrap();
+        let source = source.map_or(JsValue::NULL, |source| JsValue::from_str(&source));
+        return async move {
+            let promise = translator
+                .call3(&JsValue::NULL, &texts, &JsValue::from_str(&target), &source)
+                .and_then(|promise| promise.dyn_into::<js_sys::Promise>())
+                .map_err(|error| format!("{error:?}"))?;
+            JsFuture::from(promise)
+                .await
+                .map_err(|error| format!("{error:?}"))?
+                .into_serde::<Vec<String>>()
+                .map_err(|error| error.to_string())
+        }
+        .boxed_local();
+    }
+    let endpoint = match web_settings::web_settings().player.translation_endpoint {
+        Some(endpoint) => endpoint,
+        None => {
+            return futures::future::err("No subtitles translator is available".to_owned())
+                .boxed_local()
+        }
+    };
+    let request = Request::post(endpoint.as_str())
+        .body(EndpointRequest {
+            texts: &texts,
+            source: source.as_deref(),
+            target: &target,
+        })
+        .expect("request builder failed");
+    WebEnv::fetch::<_, EndpointResponse>(request)
+        .map_ok(|response| response.translations)
+        .map_err(|error: EnvError| error.message())
+        .boxed_local()
+}
+
+fn cache(translations: impl Iterator<Item = (u64, String)>) {
+    let mut cache = CACHE
+        .write()
+        .expect("subtitles translation cache write failed");
+    let (texts, order) = &mut *cache;
+    for (key, text) in translations {
+        if texts.insert(key, text).is_none() {
+            order.push_back(key);
+        }
+        if order.len() > MAX_CACHED_CUES {
+            if let Some(oldest) = order.pop_front() {
+                texts.remove(&oldest);
+            }
+        }
+    }
+}
+
+fn cue_key(text: &str, target_lang: &str) -> u64 {
+    let mut hasher = DefaultHasher::new();
+    text.hash(&mut hasher);
+    target_lang.hash(&mut hasher);
+    hasher.finish()
+}
+
+/// Milliseconds to `HH:MM:SS.MMM`
+fn vtt_timestamp(time: u64) -> String {
+    format!(
+        "{:02}:{:02}:{:02}.{:03}",
+        time / 3_600_000,
+        time / 60_000 % 60,
+        time / 1000 % 60,
+        time % 1000
+    )
+}
@@ -0,0 +1,503 @@
+use std::cell::RefCell;
+
+use futures::FutureExt;
+use serde::{Deserialize, Serialize};
+use tracing::{error, info};
+use wasm_bindgen::closure::Closure;
+use wasm_bindgen::{JsCast, JsValue};
+
+use stremio_core::runtime::Env;
+
+use crate::{env::WebEnv, model::WebModelField};
+
+const CHANNEL_NAME: &str = "stremio_core_web";
+const HEARTBEAT_INTERVAL: i32 = 1000;
+/// A follower takes over when the leader has not sent a heartbeat for this long,
+/// a candidate becomes the leader when no other tab answers in this time.
+const ELECTION_TIMEOUT: f64 = 3000.0;
+
+/// Fields which only depend on the ctx and are the same for every tab.
+/// The rest of the models are page models and each tab keeps its own.
+pub const SHARED_FIELDS: [WebModelField; 2] =
+    [WebModelField::Ctx, WebModelField::ContinueWatchingPreview];
+
+#[derive(Clone, PartialEq, Debug)]
+enum Role {
+    /// Waiting for an answer of an existing leader, storage writes are buffered meanwhile
+    Candidate { since: f64 },
+    /// Owns the storage and the shared state
+    Leader { since: f64 },
+    /// Storage writes are dropped, ctx actions are forwarded to the leader
+    Follower { last_heartbeat: f64 },
+}
+
+/// Actions on the state kept aside of the model which every tab applies to its own copy,
+/// only the leader persists it
+#[derive(Clone, Copy, Serialize, Deserialize, Debug)]
+pub enum SideAction {
+    Snooze,
+    MetaOverrides,
+    BlockedItems,
+    UserRatings,
+}
+
+/// The player load of a follower, dispatched once the follower became the leader
+pub struct PendingDispatch {
+    /// When the take over was asked for
+    since: f64,
+    pub action: JsValue,
+    pub field: JsValue,
+    pub location_hash: String,
+    pub action_id: String,
+}
+
+#[derive(Serialize, Deserialize, Debug)]
+#[serde(tag = "type", rename_all = "camelCase")]
+enum TabMessage {
+    #[serde(rename_all = "camelCase")]
+    Heartbeat { tab_id: String, since: f64 },
+    /// Sent by new followers, answered with the state of every shared field
+    StateRequest,
+    /// The serialized state is attached as `state`
+    State { field: WebModelField },
+    /// The action is attached as `action`
+    #[serde(rename_all = "camelCase")]
+    Dispatch {
+        location_hash: String,
+        /// `None` for the actions no dispatch caused, the leader generates an id for them
+        action_id: Option<String>,
+    },
+    /// The action is attached as `action`
+    #[serde(rename_all = "camelCase")]
+    SideAction { side_action: SideAction },
+    /// Sent by a follower which loads the player, as the player updates the ctx
+    #[serde(rename_all = "camelCase")]
+    TakeOver { tab_id: String },
+    /// Sent by the leader to the tab which took over, once the ctx is persisted
+    #[serde(rename_all = "camelCase")]
+    Released { tab_id: String },
+}
+
+struct TabSync {
+    tab_id: String,
+    role: Role,
+    channel: Option<web_sys::BroadcastChannel>,
+    has_followers: bool,
+    remote_states: Vec<(WebModelField, JsValue)>,
+    pending_writes: Vec<(String, Option<String>)>,
+    pending_dispatch: Option<PendingDispatch>,
+    interval_id: Option<i32>,
+}
+
+thread_local! {
+    static TAB_SYNC: RefCell<Option<TabSync>> = RefCell::new(None);
+}
+
+/// Opens the channel shared by the tabs and starts the leader election,
+/// every tab is the leader when the `BroadcastChannel` is not supported.
+pub fn start() {
+    let is_started = TAB_SYNC.with(|tab_sync| tab_sync.borrow().is_some());
+    if is_started {
+        return;
+    }
+    let now = js_sys::Date::now();
+    let tab_id = hex::encode(WebEnv::random_buffer(10));
+    let channel = match web_sys::BroadcastChannel::new(CHANNEL_NAME) {
+        Ok(channel) => {
+            let on_message =
+                Closure::wrap(
+                    Box::new(|event: web_sys::MessageEvent| on_message(event.data()))
+                        as Box<dyn FnMut(web_sys::MessageEvent)>,
+                );
+            channel.set_onmessage(Some(on_message.as_ref().unchecked_ref()));
+            on_message.forget();
+            Some(channel)
+        }
+        Err(error) => {
+            info!("Tabs are not synchronized, BroadcastChannel is not available: {error:?}");
+            None
+        }
+    };
+    let role = match channel {
+        Some(_) => Role::Candidate { since: now },
+        None => Role::Leader { since: now },
+    };
+    let is_candidate = matches!(role, Role::Candidate { .. });
+    TAB_SYNC.with(|tab_sync| {
+        *tab_sync.borrow_mut() = Some(TabSync {
+            tab_id,
+            role,
+            channel,
+            has_followers: false,
+            remote_states: vec![],
+            pending_writes: vec![],
+            pending_dispatch: None,
+            interval_id: None,
+        })
+    });
+    if is_candidate {
+        post(&TabMessage::StateRequest, &[]);
+        let interval_id = WebEnv::set_interval(tick, HEARTBEAT_INTERVAL);
+        TAB_SYNC.with(|tab_sync| {
+            if let Some(tab_sync) = tab_sync.borrow_mut().as_mut() {
+                tab_sync.interval_id = Some(interval_id);
+            }
+        });
+    }
+}
+
+/// Stops the heartbeats and closes the channel, the followers elect a new leader
+pub fn stop() {
+    if let Some(tab_sync) = TAB_SYNC.with(|tab_sync| tab_sync.borrow_mut().take()) {
+        if let Some(interval_id) = tab_sync.interval_id {
+            WebEnv::clear_interval(interval_id);
+        }
+        if let Some(channel) = tab_sync.channel {
+            channel.set_onmessage(None);
+            channel.close();
+        }
+    }
+}
+
+/// Storage writes are buffered while electing and dropped on followers,
+/// so only the leader ever writes to the storage.
+pub fn is_leader() -> bool {
+    TAB_SYNC.with(|tab_sync| {
+        tab_sync.borrow().as_ref().map_or(true, |tab_sync| {
+            matches!(tab_sync.role, Role::Leader { .. })
+        })
+    })
+}
+
+pub fn is_follower() -> bool {
+    TAB_SYNC.with(|tab_sync| {
+        tab_sync.borrow().as_ref().map_or(false, |tab_sync| {
+            matches!(tab_sync.role, Role::Follower { .. })
+        })
+    })
+}
+
+/// Buffers the storage write while electing, the write is dropped when following another tab
+pub fn buffer_write(key: &str, value: Option<String>) {
+    TAB_SYNC.with(|tab_sync| {
+        if let Some(TabSync {
+            role: Role::Candidate { .. },
+            pending_writes,
+            ..
+        }) = tab_sync.borrow_mut().as_mut()
+        {
+            pending_writes.retain(|(pending_key, _)| pending_key != key);
+            pending_writes.push((key.to_owned(), value));
+        }
+    })
+}
+
+/// The state of a shared field as serialized by the leader
+pub fn remote_state(field: &WebModelField) -> Option<JsValue> {
+    TAB_SYNC.with(|tab_sync| {
+        tab_sync.borrow().as_ref().and_then(|tab_sync| {
+            tab_sync
+                .remote_states
+                .iter()
+                .find(|(remote_field, _)| remote_field == field)
+                .map(|(_, state)| state.to_owned())
+        })
+    })
+}
+
+/// Sends the state of the changed shared fields to the followers
+pub fn broadcast_states(fields: &[WebModelField], get_state: impl Fn(&WebModelField) -> JsValue) {
+    let has_followers = TAB_SYNC.with(|tab_sync| {
+        tab_sync.borrow().as_ref().map_or(false, |tab_sync| {
+            tab_sync.has_followers && matches!(tab_sync.role, Role::Leader { .. })
+        })
+    });
+    if !has_followers {
+        return;
+    }
+    let invalidate_all = fields.contains(&WebModelField::Ctx);
+    for field in SHARED_FIELDS
+        .iter()
+        .filter(|field| invalidate_all || fields.contains(field))
+    {
+        post(
+            &TabMessage::State {
+                field: field.to_owned(),
+            },
+            &[("state", &get_state(field))],
+        );
+    }
+}
+
+/// Forwards the ctx action to the leader
+pub fn forward_dispatch(action: &JsValue, location_hash: String, action_id: Option<String>) {
+    post(
+        &TabMessage::Dispatch {
+            location_hash,
+            action_id,
+        },
+        &[("action", action)],
+    );
+}
+
+/// Sends the action on the side state to the other tabs, each of them applies it
+pub fn broadcast_side_action(side_action: SideAction, action: &JsValue) {
+    post(
+        &TabMessage::SideAction { side_action },
+        &[("action", action)],
+    );
+}
+
+/// Asks the leader to hand the leadership over, the player is loaded once this tab is the leader
+pub fn take_over(action: JsValue, field: JsValue, location_hash: String, action_id: String) {
+    let tab_id = TAB_SYNC.with(|tab_sync| {
+        tab_sync.borrow_mut().as_mut().map(|tab_sync| {
+            tab_sync.pending_dispatch = Some(PendingDispatch {
+                since: js_sys::Date::now(),
+                action,
+                field,
+                location_hash,
+                action_id,
+            });
+            tab_sync.tab_id.to_owned()
+        })
+    });
+    if let Some(tab_id) = tab_id {
+        post(&TabMessage::TakeOver { tab_id }, &[]);
+    }
+}
+
+pub fn take_pending_dispatch() -> Option<PendingDispatch> {
+    TAB_SYNC.with(|tab_sync| {
+        tab_sync
+            .borrow_mut()
+            .as_mut()
+            .and_then(|tab_sync| tab_sync.pending_dispatch.take())
+    })
+}
+
+fn tick() {
+    let now = js_sys::Date::now();
+    let (tab_id, role, take_over_since) = match TAB_SYNC.with(|tab_sync| {
+        tab_sync.borrow().as_ref().map(|tab_sync| {
+            (
+                tab_sync.tab_id.to_owned(),
+                tab_sync.role.to_owned(),
+                tab_sync
+                    .pending_dispatch
+                    .as_ref()
+                    .map(|pending_dispatch| pending_dispatch.since),
+            )
+        })
+    }) {
+        Some(state) => state,
+        None => return,
+    };
+    match role {
+        Role::Leader { since } => post(&TabMessage::Heartbeat { tab_id, since }, &[]),
+        Role::Candidate { since } if now - since > ELECTION_TIMEOUT => become_leader(false),
+        Role::Follower { last_heartbeat, .. } if now - last_heartbeat > ELECTION_TIMEOUT => {
+            become_leader(true)
+        }
+        // the leader did not release the leadership, it's taken over as after a failover
+        Role::Follower { .. }
+            if take_over_since.map_or(false, |since| now - since > ELECTION_TIMEOUT) =>
+        {
+            become_leader(true)
+        }
+        _ => {}
+    }
+}
+
+/// A follower reloads the runtime, as its ctx is behind the one persisted by the previous leader.
+/// The player load the leadership was taken over for is dispatched once the runtime is reloaded.
+fn become_leader(reload: bool) {
+    let now = js_sys::Date::now();
+    let (tab_id, pending_writes) = match TAB_SYNC.with(|tab_sync| {
+        tab_sync.borrow_mut().as_mut().map(|tab_sync| {
+            tab_sync.role = Role::Leader { since: now };
+            tab_sync.remote_states.clear();
+            (
+                tab_sync.tab_id.to_owned(),
+                tab_sync.pending_writes.drain(..).collect::<Vec<_>>(),
+            )
+        })
+    }) {
+        Some(state) => state,
+        None => return,
+    };
+    info!("Tab {tab_id} became the leader");
+    post(&TabMessage::Heartbeat { tab_id, since: now }, &[]);
+    for (key, value) in pending_writes {
+        WebEnv::exec_concurrent(async move {
+            if let Err(error) = WebEnv::write_storage(key, value).await {
+                error!("Failed to write buffered storage: {error:?}");
+            }
+        });
+    }
+    if reload {
+        crate::stremio_core_web::reload_runtime();
+    } else {
+        crate::stremio_core_web::emit_event(&stremio_core::runtime::RuntimeEvent::NewState(
+            SHARED_FIELDS.to_vec(),
+        ));
+    }
+}
+
+fn become_follower(leader_id: String) {
+    let now = js_sys::Date::now();
+    TAB_SYNC.with(|tab_sync| {
+        if let Some(tab_sync) = tab_sync.borrow_mut().as_mut() {
+            tab_sync.role = Role::Follower {
+                last_heartbeat: now,
+            };
+            tab_sync.pending_writes.clear();
+        }
+    });
+    info!("Following the leader tab {leader_id}");
+    post(&TabMessage::StateRequest, &[]);
+}
+
+/// Follows the tab which took over once the ctx is persisted, the storage writes of the tab
+/// are dropped from now on so the flushed ctx is the last one written by it
+fn release(tab_id: String) {
+    let now = js_sys::Date::now();
+    TAB_SYNC.with(|tab_sync| {
+        if let Some(tab_sync) = tab_sync.borrow_mut().as_mut() {
+            tab_sync.role = Role::Follower {
+                last_heartbeat: now,
+            };
+            tab_sync.has_followers = false;
+        }
+    });
+    info!("Handing the leadership over to the tab {tab_id}");
+    match crate::stremio_core_web::flush_ctx_storage() {
+        Some(flush) => WebEnv::exec_concurrent(flush.map(move |result| {
+            if let Err(error) = result {
+                error!("Failed to flush storage: {error:?}");
+            }
+            post(&TabMessage::Released { tab_id }, &[]);
+        })),
+        None => post(&TabMessage::Released { tab_id }, &[]),
+    }
+}
+
+fn on_message(data: JsValue) {
+    let message = match data.into_serde::<TabMessage>() {
+        Ok(message) => message,
+        Err(error) => {
+            error!("Invalid tab message: {error}");
+            return;
+        }
+    };
+    let (own_tab_id, role) = match TAB_SYNC.with(|tab_sync| {
+        tab_sync
+            .borrow()
+            .as_ref()
+            .map(|tab_sync| (tab_sync.tab_id.to_owned(), tab_sync.role.to_owned()))
+    }) {
+        Some(state) => state,
+        None => return,
+    };
+    match (message, role) {
+        (TabMessage::Heartbeat { tab_id, since }, Role::Leader { since: own_since }) => {
+            // two leaders after a failover, the older one stays
+            if (since, &tab_id) < (own_since, &own_tab_id) {
+                become_follower(tab_id);
+            }
+        }
+        (TabMessage::Heartbeat { tab_id, .. }, Role::Candidate { .. }) => become_follower(tab_id),
+        (TabMessage::TakeOver { tab_id }, Role::Leader { .. }) => release(tab_id),
+        (TabMessage::Released { tab_id }, Role::Follower { .. }) if tab_id == own_tab_id => {
+            become_leader(true)
+        }
+        (TabMessage::Heartbeat { .. }, Role::Follower { .. }) => {
+            let now = js_sys::Date::now();
+            let has_states = TAB_SYNC.with(|tab_sync| {
+                tab_sync.borrow_mut().as_mut().map_or(true, |tab_sync| {
+                    tab_sync.role = Role::Follower {
+                        last_heartbeat: now,
+                    };
+                    !tab_sync.remote_states.is_empty()
+                })
+            });
+            // e.g. the previous leader, once the leadership was handed over
+            if !has_states {
+                post(&TabMessage::StateRequest, &[]);
+            }
+        }
+        (TabMessage::StateRequest, Role::Leader { since }) => {
+            TAB_SYNC.with(|tab_sync| {
+                if let Some(tab_sync) = tab_sync.borrow_mut().as_mut() {
+                    tab_sync.has_followers = true;
+                }
+            });
+            post(
+                &TabMessage::Heartbeat {
+                    tab_id: own_tab_id,
+                    since,
+                },
+                &[],
+            );
+            crate::stremio_core_web::broadcast_shared_states();
+        }
+        (TabMessage::State { field }, Role::Follower { .. }) => {
+            let state =
+                js_sys::Reflect::get(&data, &JsValue::from_str("state")).unwrap_or(JsValue::NULL);
+            TAB_SYNC.with(|tab_sync| {
+                if let Some(tab_sync) = tab_sync.borrow_mut().as_mut() {
+                    tab_sync
+                        .remote_states
+                        .retain(|(remote_field, _)| *remote_field != field);
+                    tab_sync.remote_states.push((field.to_owned(), state));
+                }
+            });
+            crate::stremio_core_web::emit_event(&stremio_core::runtime::RuntimeEvent::NewState(
+                vec![field],
+            ));
+        }
+        (
+            TabMessage::Dispatch {
+                location_hash,
+                action_id,
+            },
+            Role::Leader { .. },
+        ) => {
+            let action =
+                js_sys::Reflect::get(&data, &JsValue::from_str("action")).unwrap_or(JsValue::NULL);
+            crate::stremio_core_web::dispatch(
+                action,
+                JsValue::NULL,
+                JsValue::from_str(&location_hash),
+                action_id.map_or(JsValue::UNDEFINED, |action_id| {
+                    JsValue::from_str(&action_id)
+                }),
+            );
+        }
+        (TabMessage::SideAction { side_action }, _) => {
+            let action =
+                js_sys::Reflect::get(&data, &JsValue::from_str("action")).unwrap_or(JsValue::NULL);
+            crate::stremio_core_web::apply_side_action(side_action, action);
+        }
+        _ => {}
+    }
+}
+
+fn post(message: &TabMessage, attachments: &[(&str, &JsValue)]) {
+    TAB_SYNC.with(|tab_sync| {
+        if let Some(channel) = tab_sync
+            .borrow()
+            .as_ref()
+            .and_then(|tab_sync| tab_sync.channel.as_ref())
+        {
+            let data = JsValue::from_serde(message).unwrap();
+            for (key, value) in attachments {
+                js_sys::Reflect::set(&data, &JsValue::from_str(key), value)
+                    .expect("tab message attachment failed");
+            }
+            if let Err(error) = channel.post_message(&data) {
+                error!("Failed to post tab message: {error:?}");
+            }
+        }
+    });
+}
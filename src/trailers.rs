@@ -0,0 +1,78 @@
+use serde::Serialize;
+use url::Url;
+
+use stremio_core::{
+    deep_links::StreamDeepLinks,
+    types::{
+        addon::{ResourcePath, ResourceRequest},
+        profile::Settings,
+        resource::{MetaItemPreview, Stream, StreamSource},
+    },
+};
+
+use crate::{model::deep_links_ext::DeepLinksExt, web_settings::TrailersSettings};
+
+const YOUTUBE_URL: &str = "https://www.youtube.com";
+const YOUTUBE_NOCOOKIE_URL: &str = "https://www.youtube-nocookie.com";
+const YOUTUBE_THUMBNAILS_URL: &str = "https://img.youtube.com/vi";
+
+/// Ready to use urls of a YouTube trailer
+#[derive(Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct YouTubeTrailer<'a> {
+    pub yt_id: &'a String,
+    /// On the privacy-enhanced domain when it's enabled in the settings
+    pub embed_url: Url,
+    pub watch_url: Url,
+    pub thumbnail: Url,
+    pub thumbnail_hd: Url,
+    /// Opens the trailer in the internal player, which loads it through the resolver addon.
+    /// `None` unless a resolver addon is configured.
+    pub player: Option<String>,
+}
+
+/// `None` for the trailers which are not on YouTube
+pub fn youtube_trailer<'a>(
+    stream: &'a Stream,
+    meta_item: &MetaItemPreview,
+    meta_transport_url: &Url,
+    settings: &Settings,
+    trailers_settings: &TrailersSettings,
+) -> Option<YouTubeTrailer<'a>> {
+    let yt_id = match &stream.source {
+        StreamSource::YouTube { yt_id } => yt_id,
+        _ => return None,
+    };
+    let embed_domain = if trailers_settings.privacy_enhanced {
+        YOUTUBE_NOCOOKIE_URL
+    } else {
+        YOUTUBE_URL
+    };
+    let player = trailers_settings
+        .resolver_addon
+        .as_ref()
+        .map(|resolver_addon| {
+            let stream_request = ResourceRequest::new(
+                resolver_addon.to_owned(),
+                ResourcePath::without_extra("stream", &meta_item.r#type, &format!("yt_id:{yt_id}")),
+            );
+            let meta_request = ResourceRequest::new(
+                meta_transport_url.to_owned(),
+                ResourcePath::without_extra("meta", &meta_item.r#type, &meta_item.id),
+            );
+            StreamDeepLinks::from((stream, &stream_request, &meta_request, settings))
+                .into_web_deep_links()
+                .player
+        });
+    Some(YouTubeTrailer {
+        yt_id,
+        embed_url: Url::parse(&format!("{embed_domain}/embed/{yt_id}")).ok()?,
+        watch_url: Url::parse_with_params(&format!("{YOUTUBE_URL}/watch"), &[("v", yt_id)]).ok()?,
+        thumbnail: Url::parse(&format!("{YOUTUBE_THUMBNAILS_URL}/{yt_id}/hqdefault.jpg")).ok()?,
+        thumbnail_hd: Url::parse(&format!(
+            "{YOUTUBE_THUMBNAILS_URL}/{yt_id}/maxresdefault.jpg"
+        ))
+        .ok()?,
+        player,
+    })
+}
@@ -0,0 +1,158 @@
+use std::sync::RwLock;
+
+use chrono::{DateTime, Utc};
+use lazy_static::lazy_static;
+use serde::Serialize;
+use serde_json::json;
+
+use stremio_core::{
+    models::ctx::Ctx,
+    runtime::msg::{Action, ActionCtx},
+    types::{addon::Descriptor, library::LibraryItem, resource::MetaItemPreview},
+};
+
+use crate::env::WebEnv;
+
+lazy_static! {
+    static ref UNDOABLE: RwLock<Vec<UndoableAction>> = Default::default();
+}
+
+#[derive(Clone, Copy, PartialEq, Eq, Serialize, Debug)]
+pub enum UndoType {
+    AddonInstall,
+    AddonUninstall,
+    LibraryRemoval,
+}
+
+/// What is needed to revert an action
+#[derive(Clone, Debug)]
+enum Snapshot {
+    AddonInstalled(Descriptor),
+    AddonUninstalled(Descriptor),
+    LibraryItemRemoved(LibraryItem),
+}
+
+/// A destructive action which can be undone until it expires
+#[derive(Clone, Debug)]
+pub struct UndoableAction {
+    pub id: String,
+    pub expires_at: DateTime<Utc>,
+    snapshot: Snapshot,
+}
+
+impl UndoableAction {
+    pub fn r#type(&self) -> UndoType {
+        match self.snapshot {
+            Snapshot::AddonInstalled(_) => UndoType::AddonInstall,
+            Snapshot::AddonUninstalled(_) => UndoType::AddonUninstall,
+            Snapshot::LibraryItemRemoved(_) => UndoType::LibraryRemoval,
+        }
+    }
+    /// Name of the addon or of the library item, for the undo message
+    pub fn name(&self) -> &String {
+        match &self.snapshot {
+            Snapshot::AddonInstalled(addon) | Snapshot::AddonUninstalled(addon) => {
+                &addon.manifest.name
+            }
+            Snapshot::LibraryItemRemoved(library_item) => &library_item.name,
+        }
+    }
+    /// The action reverting this one
+    fn inverse(&self) -> Action {
+        match &self.snapshot {
+            Snapshot::AddonInstalled(addon) => {
+                Action::Ctx(ActionCtx::UninstallAddon(addon.to_owned()))
+            }
+            Snapshot::AddonUninstalled(addon) => {
+                Action::Ctx(ActionCtx::InstallAddon(addon.to_owned()))
+            }
+            // the removed item is still in the library, adding it again keeps its state
+            Snapshot::LibraryItemRemoved(library_item) => {
+                let meta_item = serde_json::from_value::<MetaItemPreview>(json!({
+                    "id": library_item.id,
+                    "type": library_item.r#type,
+                    "name": library_item.name,
+                    "poster": library_item.poster,
+                    "posterShape": library_item.poster_shape,
+                }))
+                .expect("meta item preview of a library item");
+                Action::Ctx(ActionCtx::AddToLibrary(meta_item))
+            }
+        }
+    }
+}
+
+/// Keeps what is needed to undo the action, before it's dispatched.
+/// Returns the undoable action, `None` when the action can't be undone or changes nothing.
+pub fn record(action: &Action, ctx: &Ctx, expires_at: DateTime<Utc>) -> Option<UndoableAction> {
+    let snapshot = match action {
+        Action::Ctx(ActionCtx::InstallAddon(addon))
+            if !ctx
+                .profile
+                .addons
+                .iter()
+                .any(|installed| installed.transport_url == addon.transport_url) =>
+        {
+            Snapshot::AddonInstalled(addon.to_owned())
+        }
+        Action::Ctx(ActionCtx::UninstallAddon(addon)) => ctx
+            .profile
+            .addons
+            .iter()
+            .find(|installed| {
+                installed.transport_url == addon.transport_url && !installed.flags.protected
+            })
+            .map(|installed| Snapshot::AddonUninstalled(installed.to_owned()))?,
+        Action::Ctx(ActionCtx::RemoveFromLibrary(id)) => ctx
+            .library
+            .items
+            .get(id)
+            .filter(|library_item| !library_item.removed)
+            .map(|library_item| Snapshot::LibraryItemRemoved(library_item.to_owned()))?,
+        _ => return None,
+    };
+    let undoable_action = UndoableAction {
+        id: hex::encode(WebEnv::random_buffer(8)),
+        expires_at,
+        snapshot,
+    };
+    UNDOABLE
+        .write()
+        .expect("undoable actions write failed")
+        .push(undoable_action.to_owned());
+    Some(undoable_action)
+}
+
+/// The actions which can still be undone, they are dropped by `expire` once their timeout passes
+pub fn undoable() -> Vec<UndoableAction> {
+    UNDOABLE
+        .read()
+        .expect("undoable actions read failed")
+        .to_owned()
+}
+
+/// Drops the expired actions, returns whether any of them was dropped
+pub fn expire(now: DateTime<Utc>) -> bool {
+    let mut undoable = UNDOABLE.write().expect("undoable actions write failed");
+    let len = undoable.len();
+    undoable.retain(|undoable_action| undoable_action.expires_at > now);
+    undoable.len() != len
+}
+
+/// Takes the action out of the buffer and returns the action reverting it,
+/// `None` when it's unknown or it expired.
+pub fn take_inverse(id: &str, now: DateTime<Utc>) -> Option<Action> {
+    let mut undoable = UNDOABLE.write().expect("undoable actions write failed");
+    let position = undoable
+        .iter()
+        .position(|undoable_action| undoable_action.id == id)?;
+    let undoable_action = undoable.remove(position);
+    (undoable_action.expires_at > now).then(|| undoable_action.inverse())
+}
+
+pub fn clear() {
+    UNDOABLE
+        .write()
+        .expect("undoable actions write failed")
+        .clear();
+}
@@ -0,0 +1,346 @@
+use std::{
+    collections::hash_map::DefaultHasher,
+    hash::{Hash, Hasher},
+    sync::RwLock,
+};
+
+use chrono::{DateTime, Utc};
+use encoding_rs::{Encoding, WINDOWS_1250, WINDOWS_1251, WINDOWS_1252};
+use lazy_static::lazy_static;
+use regex::Regex;
+use serde::{Deserialize, Serialize};
+use url::Url;
+
+pub const UPLOADED_SUBTITLES_STORAGE_KEY: &str = "uploaded_subtitles";
+/// Shown as the origin of the uploaded tracks, in place of the name of an addon
+pub const UPLOADED_SUBTITLES_ORIGIN: &str = "Uploaded";
+/// Maximum number of uploaded tracks kept, the oldest are dropped first
+const MAX_UPLOADED: usize = 20;
+/// Bytes of the tracks kept in the storage, which is shared with the rest of the app
+const MAX_UPLOADED_SIZE: usize = 2 * 1024 * 1024;
+/// Letters common in the languages of the encodings of the older subtitles, the letters which
+/// are the same in all of them are left out. Cyrillic is told apart by its words.
+const CENTRAL_EUROPEAN_LETTERS: &str = "ąćęłńśźżčěřůňťľőűăşţ";
+const WESTERN_EUROPEAN_LETTERS: &str = "àèêòùñãõåæøœ";
+
+lazy_static! {
+    static ref UPLOADED: RwLock<Vec<UploadedSubtitles>> = Default::default();
+    /// Override blocks of the ASS dialogues, e.g. `{\i1}` or `{\pos(10,20)}`
+    static ref ASS_TAGS_REGEX: Regex = Regex::new(r"\{[^}]*\}").unwrap();
+}
+
+/// A subtitles file added by the user to a video, converted to WebVTT
+#[derive(Clone, PartialEq, Serialize, Deserialize, Debug)]
+#[serde(rename_all = "camelCase")]
+pub struct UploadedSubtitles {
+    /// Derived from the video and the content, the same file uploaded again replaces the track
+    pub id: String,
+    pub video_id: String,
+    pub lang: String,
+    /// Name of the uploaded file
+    pub label: String,
+    /// The converted track, played from a `data:` url.
+    /// Empty for the tracks stored as urls before, which are dropped.
+    #[serde(default)]
+    pub vtt: String,
+    pub uploaded_at: DateTime<Utc>,
+}
+
+impl UploadedSubtitles {
+    /// Built when the track is played rather than kept, as the encoded url is a few times
+    /// the size of the track
+    pub fn url(&self) -> Result<Url, String> {
+        vtt_url(&self.vtt)
+    }
+}
+
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+enum SubtitlesFormat {
+    Srt,
+    Vtt,
+    Ass,
+}
+
+pub fn set_uploaded(uploaded: Vec<UploadedSubtitles>) {
+    *UPLOADED.write().expect("uploaded subtitles write failed") = uploaded
+        .into_iter()
+        .filter(|uploaded| !uploaded.vtt.is_empty())
+        .collect();
+}
+
+/// Converts the file to WebVTT and adds it to the tracks of the video,
+/// returns the track along with all of the tracks to be persisted.
+/// The oldest tracks are dropped to keep the stored ones within `MAX_UPLOADED_SIZE`.
+pub fn upload(
+    video_id: &str,
+    file_name: &str,
+    lang: &str,
+    bytes: &[u8],
+    now: DateTime<Utc>,
+) -> Result<(UploadedSubtitles, Vec<UploadedSubtitles>), String> {
+    let text = decode(bytes);
+    let vtt = match format(file_name, &text) {
+        SubtitlesFormat::Srt => srt_to_vtt(&text),
+        SubtitlesFormat::Vtt => vtt(&text),
+        SubtitlesFormat::Ass => ass_to_vtt(&text),
+    }?;
+    let mut hasher = DefaultHasher::new();
+    video_id.hash(&mut hasher);
+    vtt.hash(&mut hasher);
+    if vtt.len() > MAX_UPLOADED_SIZE {
+        return Err("The file is too large".to_owned());
+    }
+    let subtitles = UploadedSubtitles {
+        id: format!("uploaded_{:016x}", hasher.finish()),
+        video_id: video_id.to_owned(),
+        lang: lang.to_owned(),
+        label: file_name.to_owned(),
+        vtt,
+        uploaded_at: now,
+    };
+    let mut uploaded = UPLOADED.write().expect("uploaded subtitles write failed");
+    uploaded.retain(|uploaded| uploaded.id != subtitles.id);
+    uploaded.push(subtitles.to_owned());
+    let mut size = uploaded
+        .iter()
+        .map(|uploaded| uploaded.vtt.len())
+        .sum::<usize>();
+    while uploaded.len() > MAX_UPLOADED || size > MAX_UPLOADED_SIZE {
+        size -= uploaded.remove(0).vtt.len();
+    }
+    Ok((subtitles, uploaded.to_owned()))
+}
+
+/// `data:` url of a WebVTT track, so it plays without being served from anywhere
+pub fn vtt_url(vtt: &str) -> Result<Url, String> {
+    Url::parse(&format!(
+        "data:text/vtt;charset=utf-8,{}",
+        String::from(js_sys::encode_uri_component(vtt))
+    ))
+    .map_err(|error| error.to_string())
+}
+
+/// Returns the remaining tracks to be persisted
+pub fn remove(id: &str) -> Vec<UploadedSubtitles> {
+    let mut uploaded = UPLOADED.write().expect("uploaded subtitles write failed");
+    uploaded.retain(|uploaded| uploaded.id != id);
+    uploaded.to_owned()
+}
+
+/// The tracks uploaded for the video, the most recent last
+pub fn uploaded(video_id: &str) -> Vec<UploadedSubtitles> {
+    UPLOADED
+        .read()
+        .expect("uploaded subtitles read failed")
+        .iter()
+        .filter(|uploaded| uploaded.video_id == video_id)
+        .cloned()
+        .collect()
+}
+
+/// UTF-8 and UTF-16 with a byte order mark are recognized, anything which is not valid UTF-8
+/// is taken as the Windows code page of the older subtitles it reads best in
+fn decode(bytes: &[u8]) -> String {
+    let text = match bytes {
+        [0xEF, 0xBB, 0xBF, rest @ ..] => String::from_utf8_lossy(rest).into_owned(),
+        [0xFF, 0xFE, rest @ ..] => decode_utf16(rest, u16::from_le_bytes),
+        [0xFE, 0xFF, rest @ ..] => decode_utf16(rest, u16::from_be_bytes),
+        _ => match std::str::from_utf8(bytes) {
+            Ok(text) => text.to_owned(),
+            Err(_) => decode_legacy(bytes),
+        },
+    };
+    text.replace("\r\n", "\n").replace('\r', "\n")
+}
+
+/// Cyrillic (Windows-1251), Central European (Windows-1250) or Western (Windows-1252),
+/// the last one when the text doesn't tell
+fn decode_legacy(bytes: &[u8]) -> String {
+    [WINDOWS_1251, WINDOWS_1250, WINDOWS_1252]
+        .into_iter()
+        .map(|encoding| {
+            let text = encoding.decode_without_bom_handling(bytes).0.into_owned();
+            (legacy_score(encoding, &text), text)
+        })
+        .max_by_key(|(score, _)| *score)
+        .map(|(_, text)| text)
+        .unwrap_or_default()
+}
+
+/// The number of the letters of the text which are common in the languages of the encoding
+fn legacy_score(encoding: &'static Encoding, text: &str) -> usize {
+    let letters = if encoding == WINDOWS_1250 {
+        CENTRAL_EUROPEAN_LETTERS
+    } else if encoding == WINDOWS_1252 {
+        WESTERN_EUROPEAN_LETTERS
+    } else {
+        // the words of a misread Latin text mix Cyrillic letters with the Latin ones
+        return text
+            .split(|char: char| !char.is_alphabetic())
+            .filter(|word| !word.chars().any(|char| char.is_ascii_alphabetic()))
+            .flat_map(str::chars)
+            .filter(|char| ('\u{0400}'..='\u{04FF}').contains(char))
+            .count();
+    };
+    text.chars()
+        .flat_map(char::to_lowercase)
+        .filter(|char| letters.contains(*char))
+        .count()
+}
+
+fn decode_utf16(bytes: &[u8], from_bytes: fn([u8; 2]) -> u16) -> String {
+    char::decode_utf16(
+        bytes
+            .chunks_exact(2)
+            .map(|chunk| from_bytes([chunk[0], chunk[1]])),
+    )
+    .map(|result| result.unwrap_or(char::REPLACEMENT_CHARACTER))
+    .collect()
+}
+
+/// By the extension of the file, otherwise by its content
+fn format(file_name: &str, text: &str) -> SubtitlesFormat {
+    let extension = file_name
+        .rsplit_once('.')
+        .map(|(_, extension)| extension.to_lowercase());
+    match extension.as_deref() {
+        Some("srt") => SubtitlesFormat::Srt,
+        Some("vtt") => SubtitlesFormat::Vtt,
+        Some("ass") | Some("ssa") => SubtitlesFormat::Ass,
+        _ if text.trim_start().starts_with("WEBVTT") => SubtitlesFormat::Vtt,
+        _ if text.contains("[Events]") => SubtitlesFormat::Ass,
+        _ => SubtitlesFormat::Srt,
+    }
+}
+
+fn vtt(text: &str) -> Result<String, String> {
+    let text = text.trim_start();
+    if !text.starts_with("WEBVTT") {
+        return Err("The file is not a valid WebVTT file".to_owned());
+    }
+    Ok(text.to_owned())
+}
+
+/// The cues are the same, only the decimal separator of the timestamps differs
+fn srt_to_vtt(text: &str) -> Result<String, String> {
+    if !text.contains("-->") {
+        return Err("No subtitles found in the file".to_owned());
+    }
+    let cues = text
+        .trim()
+        .lines()
+        .map(|line| {
+            if line.contains("-->") {
+                line.replace(',', ".")
+            } else {
+                line.to_owned()
+            }
+        })
+        .collect::<Vec<_>>()
+        .join("\n");
+    Ok(format!("WEBVTT\n\n{cues}\n"))
+}
+
+/// Only the timing and the text of the dialogues are kept, the styling is dropped
+fn ass_to_vtt(text: &str) -> Result<String, String> {
+    let mut is_events = false;
+    let mut fields = Vec::new();
+    let mut cues = Vec::new();
+    for line in text.lines().map(str::trim) {
+        if line.starts_with('[') {
+            is_events = line.eq_ignore_ascii_case("[Events]");
+            continue;
+        }
+        if !is_events {
+            continue;
+        }
+        if let Some(format) = line.strip_prefix("Format:") {
+            fields = format.split(',').map(|field| field.trim()).collect();
+        } else if let Some(dialogue) = line.strip_prefix("Dialogue:") {
+            let values = dialogue.splitn(fields.len(), ',').collect::<Vec<_>>();
+            let value = |name: &str| {
+                fields
+                    .iter()
+                    .position(|field| field.eq_ignore_ascii_case(name))
+                    .and_then(|position| values.get(position))
+                    .map(|value| value.trim())
+            };
+            let (start, end, text) = match (
+                value("Start").and_then(ass_timestamp),
+                value("End").and_then(ass_timestamp),
+                value("Text"),
+            ) {
+                (Some(start), Some(end), Some(text)) => (start, end, text),
+                _ => continue,
+            };
+            let text = ASS_TAGS_REGEX
+                .replace_all(text, "")
+                .replace("\\N", "\n")
+                .replace("\\n", "\n")
+                .replace("\\h", " ");
+            if !text.trim().is_empty() {
+                cues.push(format!("{start} --> {end}\n{}", text.trim()));
+            }
+        }
+    }
+    if cues.is_empty() {
+        return Err("No subtitles found in the file".to_owned());
+    }
+    Ok(format!("WEBVTT\n\n{}\n", cues.join("\n\n")))
+}
+
+/// `H:MM:SS.CC` to `HH:MM:SS.MMM`
+fn ass_timestamp(timestamp: &str) -> Option<String> {
+    let mut parts = timestamp.split(':');
+    let hours = parts.next()?.parse::<u32>().ok()?;
+    let minutes = parts.next()?.parse::<u32>().ok()?;
+    let (seconds, centiseconds) = parts.next()?.split_once('.')?;
+    let seconds = seconds.parse::<u32>().ok()?;
+    let centiseconds = centiseconds.parse::<u32>().ok()?;
+    Some(format!(
+        "{hours:02}:{minutes:02}:{seconds:02}.{:03}",
+        centiseconds * 10
+    ))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn decode_cyrillic() {
+        let text = "1\n00:00:01,000 --> 00:00:02,000\nПривет, как дела?\n";
+        assert_eq!(decode(&WINDOWS_1251.encode(text).0), text);
+    }
+
+    #[test]
+    fn decode_central_european() {
+        let text = "1\n00:00:01,000 --> 00:00:02,000\nZażółć gęślą jaźń, příliš žluťoučký kůň\n";
+        assert_eq!(decode(&WINDOWS_1250.encode(text).0), text);
+    }
+
+    #[test]
+    fn decode_western_european() {
+        let text = "1\n00:00:01,000 --> 00:00:02,000\nOù est la fenêtre? À côté, señor.\n";
+        assert_eq!(decode(&WINDOWS_1252.encode(text).0), text);
+    }
+
+    #[test]
+    fn decode_line_endings() {
+        assert_eq!(decode(b"\xEF\xBB\xBFa\r\nb\rc"), "a\nb\nc");
+    }
+
+    #[test]
+    fn srt() {
+        let srt = "1\n00:00:01,500 --> 00:00:03,250\nHello, world\n\n2\n00:00:04,000 --> 00:00:05,000\nBye\n";
+        assert_eq!(
+            srt_to_vtt(srt).unwrap(),
+            "WEBVTT\n\n1\n00:00:01.500 --> 00:00:03.250\nHello, world\n\n2\n00:00:04.000 --> 00:00:05.000\nBye\n"
+        );
+    }
+
+    #[test]
+    fn srt_without_cues() {
+        assert!(srt_to_vtt("not subtitles").is_err());
+    }
+}
@@ -0,0 +1,194 @@
+use std::{collections::HashMap, sync::RwLock};
+
+use lazy_static::lazy_static;
+use serde::{Deserialize, Serialize};
+
+pub const USER_RATINGS_STORAGE_KEY: &str = "user_ratings";
+pub const MIN_RATING: u8 = 1;
+pub const MAX_RATING: u8 = 10;
+const MAX_NOTE_LENGTH: usize = 2000;
+
+lazy_static! {
+    /// Ratings and notes of the user, by item id
+    static ref USER_RATINGS: RwLock<HashMap<String, UserRating>> = Default::default();
+}
+
+/// The opinion of the user of an item, removed once both the rating and the note are `None`
+#[derive(Clone, PartialEq, Serialize, Deserialize, Debug)]
+#[serde(rename_all = "camelCase")]
+pub struct UserRating {
+    /// From `MIN_RATING` to `MAX_RATING`
+    pub rating: Option<u8>,
+    pub note: Option<String>,
+}
+
+impl UserRating {
+    pub fn is_empty(&self) -> bool {
+        self.rating.is_none() && self.note.is_none()
+    }
+}
+
+#[derive(Deserialize, Debug)]
+#[serde(tag = "action", content = "args")]
+pub enum UserRatingsAction {
+    /// `None` removes the rating
+    Rate { id: String, rating: Option<u8> },
+    /// `None` or a blank note removes the note
+    SetNote { id: String, note: Option<String> },
+}
+
+pub fn set_user_ratings(user_ratings: HashMap<String, UserRating>) {
+    *USER_RATINGS.write().expect("user ratings write failed") = user_ratings;
+}
+
+/// Applies the action and returns the updated ratings to be persisted,
+/// `None` when the rating is out of range or nothing changed
+pub fn update_user_ratings(action: UserRatingsAction) -> Option<HashMap<String, UserRating>> {
+    let mut user_ratings = USER_RATINGS.write().expect("user ratings write failed");
+    let (id, user_rating) = updated_user_rating(&user_ratings, action)?;
+    match user_rating {
+        Some(user_rating) => user_ratings.insert(id, user_rating),
+        None => user_ratings.remove(&id),
+    };
+    Some(user_ratings.to_owned())
+}
+
+/// `None` for the items the user did not rate nor note
+pub fn user_rating(id: &str) -> Option<UserRating> {
+    USER_RATINGS
+        .read()
+        .expect("user ratings read failed")
+        .get(id)
+        .cloned()
+}
+
+pub fn clear() {
+    USER_RATINGS
+        .write()
+        .expect("user ratings write failed")
+        .clear();
+}
+
+/// The id of the item along with its new rating, `None` when it's removed
+fn updated_user_rating(
+    user_ratings: &HashMap<String, UserRating>,
+    action: UserRatingsAction,
+) -> Option<(String, Option<UserRating>)> {
+    let (id, user_rating) = match action {
+        UserRatingsAction::Rate { id, rating } => {
+            if rating.map_or(false, |rating| !(MIN_RATING..=MAX_RATING).contains(&rating)) {
+                return None;
+            }
+            let note = user_ratings
+                .get(&id)
+                .and_then(|user_rating| user_rating.note.to_owned());
+            (id, UserRating { rating, note })
+        }
+        UserRatingsAction::SetNote { id, note } => {
+            let note = note
+                .map(|note| {
+                    note.trim()
+                        .chars()
+                        .take(MAX_NOTE_LENGTH)
+                        .collect::<String>()
+                })
+                .filter(|note| !note.is_empty());
+            let rating = user_ratings
+                .get(&id)
+                .and_then(|user_rating| user_rating.rating);
+            (id, UserRating { rating, note })
+        }
+    };
+    let user_rating = (!user_rating.is_empty()).then_some(user_rating);
+    if user_ratings.get(&id) == user_rating.as_ref() {
+        return None;
+    }
+    Some((id, user_rating))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn user_ratings() -> HashMap<String, UserRating> {
+        HashMap::from([(
+            "tt1".to_owned(),
+            UserRating {
+                rating: Some(8),
+                note: Some("note".to_owned()),
+            },
+        )])
+    }
+
+    #[test]
+    fn rate_keeps_the_note() {
+        let action = UserRatingsAction::Rate {
+            id: "tt1".to_owned(),
+            rating: Some(9),
+        };
+        assert_eq!(
+            updated_user_rating(&user_ratings(), action),
+            Some((
+                "tt1".to_owned(),
+                Some(UserRating {
+                    rating: Some(9),
+                    note: Some("note".to_owned()),
+                })
+            ))
+        );
+    }
+
+    #[test]
+    fn rate_out_of_range() {
+        for rating in [0, MAX_RATING + 1] {
+            let action = UserRatingsAction::Rate {
+                id: "tt1".to_owned(),
+                rating: Some(rating),
+            };
+            assert_eq!(updated_user_rating(&user_ratings(), action), None);
+        }
+    }
+
+    #[test]
+    fn set_note_trims_and_caps() {
+        let action = UserRatingsAction::SetNote {
+            id: "tt2".to_owned(),
+            note: Some(format!("  {}  ", "a".repeat(MAX_NOTE_LENGTH + 1))),
+        };
+        let (_, user_rating) = updated_user_rating(&user_ratings(), action).unwrap();
+        assert_eq!(user_rating.unwrap().note.unwrap().len(), MAX_NOTE_LENGTH);
+    }
+
+    #[test]
+    fn unchanged() {
+        let action = UserRatingsAction::Rate {
+            id: "tt1".to_owned(),
+            rating: Some(8),
+        };
+        assert_eq!(updated_user_rating(&user_ratings(), action), None);
+        let action = UserRatingsAction::SetNote {
+            id: "tt2".to_owned(),
+            note: Some("   ".to_owned()),
+        };
+        assert_eq!(updated_user_rating(&user_ratings(), action), None);
+    }
+
+    #[test]
+    fn removed_once_empty() {
+        let user_ratings = HashMap::from([(
+            "tt1".to_owned(),
+            UserRating {
+                rating: None,
+                note: Some("note".to_owned()),
+            },
+        )]);
+        let action = UserRatingsAction::SetNote {
+            id: "tt1".to_owned(),
+            note: None,
+        };
+        assert_eq!(
+            updated_user_rating(&user_ratings, action),
+            Some(("tt1".to_owned(), None))
+        );
+    }
+}
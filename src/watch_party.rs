@@ -0,0 +1,93 @@
+use std::sync::RwLock;
+
+use lazy_static::lazy_static;
+use serde::{Deserialize, Serialize};
+
+lazy_static! {
+    /// Reported by the watch party connection of the shell, `None` outside of a party
+    static ref PRESENCE: RwLock<Option<Presence>> = Default::default();
+}
+
+#[derive(Clone, Copy, PartialEq, Eq, Serialize, Deserialize, Debug)]
+pub enum PlaybackState {
+    Playing,
+    Paused,
+    Buffering,
+}
+
+#[derive(Clone, PartialEq, Serialize, Deserialize, Debug)]
+#[serde(rename_all = "camelCase")]
+pub struct Member {
+    pub id: String,
+    pub name: String,
+    pub state: PlaybackState,
+    /// The member of this device
+    #[serde(default)]
+    pub is_self: bool,
+}
+
+#[derive(Clone, PartialEq, Serialize, Deserialize, Debug)]
+#[serde(rename_all = "camelCase")]
+pub struct Presence {
+    pub party_id: String,
+    pub members: Vec<Member>,
+    /// The member whose pause paused the playback of everyone
+    #[serde(default)]
+    pub paused_by: Option<String>,
+}
+
+/// Why the playback of the party is halted, for the overlay of the player
+#[derive(Serialize)]
+#[serde(tag = "type", content = "content")]
+pub enum HaltReason {
+    PausedBy(Member),
+    /// The playback waits for the members who are still buffering
+    Buffering(Vec<Member>),
+}
+
+#[derive(Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct WatchParty {
+    pub party_id: String,
+    pub members: Vec<Member>,
+    pub halt_reason: Option<HaltReason>,
+}
+
+/// Returns whether the presence changed
+pub fn set_presence(presence: Option<Presence>) -> bool {
+    let mut current = PRESENCE.write().expect("watch party presence write failed");
+    if *current == presence {
+        return false;
+    }
+    *current = presence;
+    true
+}
+
+/// The presence of the party for the player, `None` outside of a party
+pub fn watch_party() -> Option<WatchParty> {
+    let presence = PRESENCE.read().expect("watch party presence read failed");
+    let presence = presence.as_ref()?;
+    let paused_by = presence.paused_by.as_ref().and_then(|paused_by| {
+        presence
+            .members
+            .iter()
+            .find(|member| member.id == *paused_by)
+    });
+    let buffering = presence
+        .members
+        .iter()
+        .filter(|member| member.state == PlaybackState::Buffering)
+        .cloned()
+        .collect::<Vec<_>>();
+    // a pause halts the playback even when others are still buffering
+    let halt_reason = match paused_by {
+        Some(member) => Some(HaltReason::PausedBy(member.to_owned())),
+        None if !buffering.is_empty() => Some(HaltReason::Buffering(buffering)),
+        None => None,
+    };
+    Some(WatchParty {
+        party_id: presence.party_id.to_owned(),
+        members: presence.members.to_owned(),
+        halt_reason,
+    })
+}
@@ -0,0 +1,83 @@
+use std::{
+    collections::{hash_map::DefaultHasher, HashMap},
+    hash::{Hash, Hasher},
+};
+
+use itertools::Itertools;
+use url::{form_urlencoded, Url};
+
+use stremio_core::types::resource::{Stream, StreamSource};
+
+use crate::env::WebEnv;
+
+/// The url the web player loads the url stream from. The streams which are not web ready are
+/// transcoded by the streaming server, the ones with request headers go through its proxy.
+/// `None` for the other sources, or when the streaming server is needed but not available.
+pub fn web_url(stream: &Stream) -> Option<Url> {
+    let url = stream_url(stream)?;
+    let request_headers = request_headers(stream);
+    if !stream.behavior_hints.not_web_ready && request_headers.is_none() {
+        return Some(url.to_owned());
+    }
+    let streaming_server_url = WebEnv::streaming_server_url()?;
+    let media_url = match request_headers {
+        Some(request_headers) => proxy_url(&streaming_server_url, url, request_headers)?,
+        None => url.to_owned(),
+    };
+    if !stream.behavior_hints.not_web_ready {
+        return Some(media_url);
+    }
+    transcoded_url(&streaming_server_url, url, &media_url)
+}
+
+pub fn stream_url(stream: &Stream) -> Option<&Url> {
+    match &stream.source {
+        StreamSource::Url { url } => Some(url),
+        _ => None,
+    }
+}
+
+/// The headers the url has to be requested with, which the browser can't set
+pub fn request_headers(stream: &Stream) -> Option<&HashMap<String, String>> {
+    stream
+        .behavior_hints
+        .proxy_headers
+        .as_ref()
+        .map(|proxy_headers| &proxy_headers.request)
+        .filter(|request_headers| !request_headers.is_empty())
+}
+
+/// `/proxy/d=<origin>&h=<name>:<value>/<path>`, the streaming server requests the url
+/// with the headers
+pub fn proxy_url(
+    streaming_server_url: &Url,
+    url: &Url,
+    request_headers: &HashMap<String, String>,
+) -> Option<Url> {
+    let mut query = form_urlencoded::Serializer::new(String::new());
+    query.append_pair("d", &url.origin().ascii_serialization());
+    for (name, value) in request_headers.iter().sorted() {
+        query.append_pair("h", &format!("{name}:{value}"));
+    }
+    let path = match url.query() {
+        Some(url_query) => format!("{}?{}", url.path(), url_query),
+        None => url.path().to_owned(),
+    };
+    streaming_server_url
+        .join(&format!("proxy/{}{}", query.finish(), path))
+        .ok()
+}
+
+/// The HLS playlist the streaming server transcodes the media url to,
+/// the same url of the stream is transcoded by the same job
+pub fn transcoded_url(streaming_server_url: &Url, url: &Url, media_url: &Url) -> Option<Url> {
+    let mut hasher = DefaultHasher::new();
+    url.as_str().hash(&mut hasher);
+    let mut hls_url = streaming_server_url
+        .join(&format!("hlsv2/{:016x}/master.m3u8", hasher.finish()))
+        .ok()?;
+    hls_url
+        .query_pairs_mut()
+        .append_pair("mediaURL", media_url.as_str());
+    Some(hls_url)
+}
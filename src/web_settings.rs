@@ -0,0 +1,363 @@
+use std::{collections::HashMap, sync::RwLock};
+
+use chrono::NaiveTime;
+use lazy_static::lazy_static;
+use serde::{Deserialize, Serialize};
+use url::Url;
+
+use stremio_core::types::{library::LibraryItem, streams::StreamsItem};
+
+use crate::epg;
+
+pub const WEB_SETTINGS_STORAGE_KEY: &str = "web_settings";
+
+lazy_static! {
+    static ref WEB_SETTINGS: RwLock<WebSettings> = Default::default();
+}
+
+/// Settings which are specific to the web app and are not part of the core's profile settings,
+/// persisted in the local storage only.
+#[derive(Clone, Default, PartialEq, Serialize, Deserialize, Debug)]
+#[serde(rename_all = "camelCase", default)]
+pub struct WebSettings {
+    pub ipfs: IpfsSettings,
+    pub catalogs: CatalogsSettings,
+    pub developer: DeveloperSettings,
+    pub notifications: NotificationsSettings,
+    pub debrid: DebridSettings,
+    pub streams: StreamsSettings,
+    pub continue_watching: ContinueWatchingSettings,
+    pub undo: UndoSettings,
+    pub retry: RetrySettings,
+    pub network: NetworkSettings,
+    pub player: PlayerSettings,
+    pub addons: AddonsSettings,
+    pub trailers: TrailersSettings,
+}
+
+#[derive(Clone, Default, PartialEq, Serialize, Deserialize, Debug)]
+#[serde(rename_all = "camelCase", default)]
+pub struct TrailersSettings {
+    /// The trailers are embedded from youtube-nocookie.com
+    pub privacy_enhanced: bool,
+    /// Addon which resolves the YouTube trailers to playable streams, the trailers open in the
+    /// internal player when it's set
+    pub resolver_addon: Option<Url>,
+}
+
+#[derive(Clone, Default, PartialEq, Serialize, Deserialize, Debug)]
+#[serde(rename_all = "camelCase", default)]
+pub struct AddonsSettings {
+    /// Addons preferred for the content of a type, by the type, e.g. `series`
+    pub by_type: HashMap<String, TypeAddons>,
+}
+
+impl AddonsSettings {
+    pub fn type_addons(&self, r#type: &str) -> Option<&TypeAddons> {
+        self.by_type.get(r#type)
+    }
+}
+
+#[derive(Clone, Default, PartialEq, Serialize, Deserialize, Debug)]
+#[serde(rename_all = "camelCase", default)]
+pub struct TypeAddons {
+    /// The meta item of this addon is shown whenever it has one
+    pub meta: Option<Url>,
+    /// The streams of these addons are listed first, in this order
+    pub streams: Vec<Url>,
+}
+
+impl TypeAddons {
+    /// The addons which are not preferred come after the preferred ones, in their own order
+    pub fn streams_position(&self, transport_url: &Url) -> usize {
+        self.streams
+            .iter()
+            .position(|preferred| preferred == transport_url)
+            .unwrap_or(self.streams.len())
+    }
+}
+
+#[derive(Clone, PartialEq, Serialize, Deserialize, Debug)]
+#[serde(rename_all = "camelCase", default)]
+pub struct PlayerSettings {
+    /// Episodes autoplayed in a row before asking whether the user is still watching, 0 never asks
+    pub still_watching_after: u32,
+    /// Translates the subtitles when the app registers no translator of its own
+    pub translation_endpoint: Option<Url>,
+}
+
+impl Default for PlayerSettings {
+    fn default() -> Self {
+        Self {
+            still_watching_after: 3,
+            translation_endpoint: None,
+        }
+    }
+}
+
+#[derive(Clone, PartialEq, Serialize, Deserialize, Debug)]
+#[serde(rename_all = "camelCase", default)]
+pub struct NetworkSettings {
+    /// Addon requests in flight at once, the others wait in a queue
+    pub max_concurrent_requests: u32,
+    /// Requests in flight at once to the same host
+    pub max_concurrent_requests_per_host: u32,
+    /// Kilobytes of an addon response, `0` for no limit.
+    /// The larger catalogs, streams and subtitles are truncated up to 4 times the limit,
+    /// the other responses fail.
+    pub max_response_size: u32,
+    /// Items kept of the list of a response over the size limit
+    pub max_truncated_items: u32,
+    /// Nothing is loaded in the background ahead of time, the requests of the UI still are
+    pub data_saver: bool,
+}
+
+impl Default for NetworkSettings {
+    fn default() -> Self {
+        Self {
+            max_concurrent_requests: 6,
+            max_concurrent_requests_per_host: 2,
+            max_response_size: 8192,
+            max_truncated_items: 1000,
+            data_saver: false,
+        }
+    }
+}
+
+#[derive(Clone, PartialEq, Serialize, Deserialize, Debug)]
+#[serde(rename_all = "camelCase", default)]
+pub struct RetrySettings {
+    /// Retries of the failed catalog requests of the Board, Discover and Search
+    pub catalogs: u32,
+    /// Retries of the failed meta and streams requests
+    pub meta_details: u32,
+    /// Retries of the failed subtitles requests
+    pub player: u32,
+    /// Milliseconds before the first retry, doubled before every next one
+    pub initial_delay: u32,
+    pub max_delay: u32,
+}
+
+impl Default for RetrySettings {
+    fn default() -> Self {
+        Self {
+            catalogs: 2,
+            meta_details: 2,
+            player: 1,
+            initial_delay: 1000,
+            max_delay: 8000,
+        }
+    }
+}
+
+#[derive(Clone, PartialEq, Serialize, Deserialize, Debug)]
+#[serde(rename_all = "camelCase", default)]
+pub struct UndoSettings {
+    /// Seconds the addon installs and uninstalls and the library removals can be undone for,
+    /// `0` disables the undo
+    pub timeout: u32,
+}
+
+impl Default for UndoSettings {
+    fn default() -> Self {
+        Self { timeout: 10 }
+    }
+}
+
+#[derive(Clone, Default, PartialEq, Serialize, Deserialize, Debug)]
+#[serde(rename_all = "camelCase", default)]
+pub struct ContinueWatchingSettings {
+    /// Hide the items of the channel types, e.g. `tv`
+    pub exclude_channels: bool,
+    pub excluded_types: Vec<String>,
+    /// Hide the items watched through the streams of these addons
+    pub excluded_addons: Vec<Url>,
+    /// Hide the items opened from the catalogs of an addon of a type
+    pub excluded_catalogs: Vec<ExcludedCatalog>,
+}
+
+/// The catalogs of an addon of a type, matched by the addon which served the meta of the item
+#[derive(Clone, PartialEq, Serialize, Deserialize, Debug)]
+#[serde(rename_all = "camelCase")]
+pub struct ExcludedCatalog {
+    pub transport_url: Url,
+    pub r#type: String,
+}
+
+impl ContinueWatchingSettings {
+    pub fn excludes(&self, library_item: &LibraryItem, streams_item: Option<&StreamsItem>) -> bool {
+        (self.exclude_channels && epg::CHANNEL_TYPES.contains(&library_item.r#type.as_str()))
+            || self.excluded_types.contains(&library_item.r#type)
+            || streams_item.map_or(false, |streams_item| {
+                self.excluded_addons
+                    .contains(&streams_item.stream_transport_url)
+                    || self.excluded_catalogs.iter().any(|catalog| {
+                        catalog.transport_url == streams_item.meta_transport_url
+                            && catalog.r#type == library_item.r#type
+                    })
+            })
+    }
+}
+
+#[derive(Clone, PartialEq, Serialize, Deserialize, Debug)]
+#[serde(rename_all = "camelCase", default)]
+pub struct StreamsSettings {
+    /// Pick the stream which played last time when the meta item is opened again
+    pub auto_select_last_used: bool,
+    /// Seconds before the streams still loading are shown as timed out, `0` for no timeout
+    pub timeout: u32,
+    /// The timeouts of the addons which differ, by the transport url
+    pub addon_timeouts: HashMap<Url, u32>,
+}
+
+impl StreamsSettings {
+    pub fn timeout(&self, transport_url: &Url) -> u32 {
+        self.addon_timeouts
+            .get(transport_url)
+            .copied()
+            .unwrap_or(self.timeout)
+    }
+}
+
+impl Default for StreamsSettings {
+    fn default() -> Self {
+        Self {
+            auto_select_last_used: false,
+            timeout: 10,
+            addon_timeouts: HashMap::new(),
+        }
+    }
+}
+
+#[derive(Clone, Default, PartialEq, Serialize, Deserialize, Debug)]
+#[serde(rename_all = "camelCase", default)]
+pub struct DebridSettings {
+    /// Service the torrent streams are resolved through, `None` disables the resolving
+    pub service: Option<DebridService>,
+    pub api_token: String,
+}
+
+impl DebridSettings {
+    pub fn is_enabled(&self) -> bool {
+        self.service.is_some() && !self.api_token.is_empty()
+    }
+}
+
+#[derive(Clone, Copy, PartialEq, Eq, Serialize, Deserialize, Debug)]
+#[serde(rename_all = "camelCase")]
+pub enum DebridService {
+    RealDebrid,
+    AllDebrid,
+    Premiumize,
+}
+
+#[derive(Clone, PartialEq, Serialize, Deserialize, Debug)]
+#[serde(rename_all = "camelCase", default)]
+pub struct NotificationsSettings {
+    /// Minutes between the pulls of the new episode notifications
+    pub pull_interval: u32,
+    /// The notifications are not pulled during the quiet hours, only once they are over
+    pub quiet_hours: Option<QuietHours>,
+}
+
+impl NotificationsSettings {
+    pub const MIN_PULL_INTERVAL: u32 = 5;
+}
+
+impl Default for NotificationsSettings {
+    fn default() -> Self {
+        Self {
+            pull_interval: 30,
+            quiet_hours: None,
+        }
+    }
+}
+
+/// A range of the local time of the day, it spans midnight when the start is after the end
+#[derive(Clone, PartialEq, Serialize, Deserialize, Debug)]
+#[serde(rename_all = "camelCase")]
+pub struct QuietHours {
+    pub start: NaiveTime,
+    pub end: NaiveTime,
+}
+
+impl QuietHours {
+    pub fn contains(&self, time: NaiveTime) -> bool {
+        if self.start <= self.end {
+            self.start <= time && time < self.end
+        } else {
+            self.start <= time || time < self.end
+        }
+    }
+}
+
+#[derive(Clone, Default, PartialEq, Serialize, Deserialize, Debug)]
+#[serde(rename_all = "camelCase", default)]
+pub struct DeveloperSettings {
+    /// Collect schema violations of the addon responses, shown as `warnings` alongside the catalogs
+    pub validate_addon_responses: bool,
+}
+
+#[derive(Clone, PartialEq, Serialize, Deserialize, Debug)]
+#[serde(rename_all = "camelCase", default)]
+pub struct CatalogsSettings {
+    /// Load the next page of Discover in the background once the current one is ready
+    pub prefetch_next_page: bool,
+    /// Minutes between the background refreshes of the Board while it's shown
+    pub board_refresh_interval: u32,
+    /// Top Continue Watching items whose meta details are loaded while idle, `0` for none
+    pub prefetch_continue_watching: u32,
+    /// Search also the official addons which are not installed, their results are listed apart
+    pub search_installable_addons: bool,
+}
+
+impl CatalogsSettings {
+    pub const MIN_BOARD_REFRESH_INTERVAL: u32 = 5;
+}
+
+impl Default for CatalogsSettings {
+    fn default() -> Self {
+        Self {
+            prefetch_next_page: true,
+            board_refresh_interval: 60,
+            prefetch_continue_watching: 3,
+            search_installable_addons: false,
+        }
+    }
+}
+
+#[derive(Clone, PartialEq, Serialize, Deserialize, Debug)]
+#[serde(rename_all = "camelCase", default)]
+pub struct IpfsSettings {
+    /// Gateways in order of preference, e.g. `https://ipfs.io/ipfs/`
+    pub gateways: Vec<Url>,
+    /// Prefer resolving through the streaming server when it's available
+    pub use_streaming_server: bool,
+}
+
+impl Default for IpfsSettings {
+    fn default() -> Self {
+        Self {
+            gateways: [
+                "https://ipfs.io/ipfs/",
+                "https://dweb.link/ipfs/",
+                "https://cloudflare-ipfs.com/ipfs/",
+            ]
+            .iter()
+            .map(|gateway| Url::parse(gateway).expect("Invalid IPFS gateway"))
+            .collect(),
+            use_streaming_server: false,
+        }
+    }
+}
+
+pub fn web_settings() -> WebSettings {
+    WEB_SETTINGS
+        .read()
+        .expect("web settings read failed")
+        .to_owned()
+}
+
+pub fn set_web_settings(web_settings: WebSettings) {
+    *WEB_SETTINGS.write().expect("web settings write failed") = web_settings;
+}
@@ -0,0 +1,93 @@
+const Bridge = require('./bridge');
+
+const bridge = new Bridge(self, self);
+
+// Shared by the ESM and the no-modules workers, `bindings` are the exports of the wasm-bindgen glue
+const initialize = async (bindings, initializeApi, { appVersion, shellVersion, remoteConfigUrl, streamingServerUrl, storage, deviceProfile }) => {
+    self.app_version = appVersion;
+    self.shell_version = shellVersion;
+    self.remote_config_url = remoteConfigUrl;
+    self.get_location_hash = async () => bridge.call(['location', 'hash'], []);
+    self.local_storage_get_item = async (key) => bridge.call(['localStorage', 'getItem'], [key]);
+    self.local_storage_set_item = async (key, value) => bridge.call(['localStorage', 'setItem'], [key, value]);
+    self.local_storage_remove_item = async (key) => bridge.call(['localStorage', 'removeItem'], [key]);
+    const { initialize_runtime, destroy_runtime, reinitialize_runtime, get_state, get_state_slice, get_schema_version, get_debug_state, create_diagnostic_snapshot, get_addon_capabilities, get_share_payload, global_search, get_meta_preview_card, get_library_status, select_discover_range, replay_resource_request, test_addon, parse_protocol_link, get_addon_install_link, dispatch, cancel_loads, load_timed_out_streams, analytics, decode_stream, expand_season_pack, dismiss_announcement, set_watch_party_presence, observe_fields, set_viewport, set_library_sort, library_tags, get_library_tags, user_ratings, update_web_settings, onboarding, set_visibility, get_background_schedule, get_memory_usage, get_request_queue, trim_memory, streaming_server_jobs, streaming_server_cache, export_library, import_library, still_watching, upload_subtitles, remove_uploaded_subtitles, subtitles_sync, register_subtitles_translator, translate_subtitles, lan_sync, snooze, rewatch, blocked_items, meta_overrides, addon_updates, addon_mirrors, pinned_catalogs, get_shortcuts, event_reminders, profile_display, account_sessions, email_flow, register_mock_addon, unregister_mock_addon, undo } = bindings;
+    self.getState = get_state;
+    self.getStateSlice = get_state_slice;
+    self.getSchemaVersion = get_schema_version;
+    self.getDebugState = get_debug_state;
+    // attached to the bug reports, scrubbed of the credentials and the emails
+    self.createDiagnosticSnapshot = create_diagnostic_snapshot;
+    self.getAddonCapabilities = get_addon_capabilities;
+    self.getSharePayload = get_share_payload;
+    self.globalSearch = global_search;
+    // for the hover cards of the Board, without loading the meta details
+    self.getMetaPreviewCard = get_meta_preview_card;
+    self.getLibraryStatus = get_library_status;
+    self.selectDiscoverRange = select_discover_range;
+    self.replayResourceRequest = replay_resource_request;
+    // for the "Test" panel of the addon details
+    self.testAddon = test_addon;
+    // for the `stremio://` links the app is registered as a protocol handler of
+    self.parseProtocolLink = parse_protocol_link;
+    self.getAddonInstallLink = get_addon_install_link;
+    self.dispatch = dispatch;
+    self.cancelLoads = cancel_loads;
+    self.loadTimedOutStreams = load_timed_out_streams;
+    self.undo = undo;
+    self.analytics = analytics;
+    self.decodeStream = decode_stream;
+    self.expandSeasonPack = expand_season_pack;
+    self.dismissAnnouncement = dismiss_announcement;
+    self.setWatchPartyPresence = set_watch_party_presence;
+    self.observeFields = observe_fields;
+    self.setViewport = set_viewport;
+    self.setLibrarySort = set_library_sort;
+    self.libraryTags = library_tags;
+    self.getLibraryTags = get_library_tags;
+    self.userRatings = user_ratings;
+    self.updateWebSettings = update_web_settings;
+    self.onboarding = onboarding;
+    // to be called from the main thread on `visibilitychange`, workers can't observe the document
+    self.setVisibility = set_visibility;
+    self.getBackgroundSchedule = get_background_schedule;
+    self.getMemoryUsage = get_memory_usage;
+    self.getRequestQueue = get_request_queue;
+    self.trimMemory = trim_memory;
+    self.streamingServerJobs = streaming_server_jobs;
+    self.streamingServerCache = streaming_server_cache;
+    self.exportLibrary = export_library;
+    self.importLibrary = import_library;
+    self.stillWatching = still_watching;
+    self.uploadSubtitles = upload_subtitles;
+    self.removeUploadedSubtitles = remove_uploaded_subtitles;
+    self.subtitlesSync = subtitles_sync;
+    // the translator runs on the main thread, `path` is where it's found there, `null` unregisters it
+    self.registerSubtitlesTranslator = (path) => register_subtitles_translator(path ? async (texts, target, source) => bridge.call(path, [texts, target, source]) : null);
+    self.translateSubtitles = translate_subtitles;
+    self.lanSync = lan_sync;
+    self.snooze = snooze;
+    self.rewatch = rewatch;
+    self.blockedItems = blocked_items;
+    self.metaOverrides = meta_overrides;
+    self.addonUpdates = addon_updates;
+    self.addonMirrors = addon_mirrors;
+    self.pinnedCatalogs = pinned_catalogs;
+    self.getShortcuts = get_shortcuts;
+    self.eventReminders = event_reminders;
+    self.profileDisplay = profile_display;
+    self.accountSessions = account_sessions;
+    self.emailFlow = email_flow;
+    // only available in builds with the `mock-addon` feature
+    self.registerMockAddon = register_mock_addon;
+    self.unregisterMockAddon = unregister_mock_addon;
+    const emitToUI = (event) => bridge.call(['onCoreEvent'], [event]);
+    self.destroy = destroy_runtime;
+    // resolves with the results of the env self-check, same as `init`
+    self.reinitialize = (options) => reinitialize_runtime(emitToUI, { streamingServerUrl, storage, deviceProfile, ...options });
+    await initializeApi();
+    // resolves with the results of the env self-check
+    return initialize_runtime(emitToUI, { streamingServerUrl, storage, deviceProfile });
+};
+
+module.exports = initialize;
@@ -0,0 +1,7 @@
+const initialize = require('./worker_common');
+
+// For browsers without support for modules in workers, e.g. older TV browsers
+self.init = async (options) => {
+    const bindings = require('./stremio_core_web_no_modules.js');
+    return initialize(bindings, () => bindings(require('./stremio_core_web_no_modules_bg.wasm')), options);
+};